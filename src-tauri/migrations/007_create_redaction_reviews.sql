@@ -0,0 +1,15 @@
+-- Redaction approval workflow
+-- A processed document may only leave the system once a reviewer approves its redactions
+
+CREATE TABLE IF NOT EXISTS redaction_reviews (
+    document_id TEXT PRIMARY KEY,
+    state TEXT NOT NULL DEFAULT 'draft', -- 'draft', 'pending_review', 'approved', 'rejected'
+    submitted_by TEXT,
+    reviewed_by TEXT,
+    rejection_reason TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_redaction_reviews_state
+    ON redaction_reviews(state, updated_at DESC);
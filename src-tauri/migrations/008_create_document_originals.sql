@@ -0,0 +1,27 @@
+-- Encrypted originals of redacted documents
+-- Only written under legal hold or explicit opt-in; normal processing keeps redacted content only
+
+CREATE TABLE IF NOT EXISTS document_originals (
+    document_id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    ciphertext BLOB NOT NULL, -- AES-256-GCM, key derived from the OS keychain master key
+    nonce BLOB NOT NULL,
+    legal_hold INTEGER NOT NULL DEFAULT 0,
+    retention_until DATETIME,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_document_originals_user
+    ON document_originals(user_id);
+
+CREATE INDEX IF NOT EXISTS idx_document_originals_retention
+    ON document_originals(legal_hold, retention_until);
+
+-- Active retention policy; a single row so a legal hold survives restarts
+CREATE TABLE IF NOT EXISTS original_retention_policy (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    retain_originals INTEGER NOT NULL DEFAULT 0,
+    legal_hold INTEGER NOT NULL DEFAULT 0,
+    retention_days INTEGER NOT NULL DEFAULT 90,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
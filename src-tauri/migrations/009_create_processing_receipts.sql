@@ -0,0 +1,21 @@
+-- Chain-of-custody receipts for document operations
+-- Records hashes and counts only; no document text or detected PII values
+
+CREATE TABLE IF NOT EXISTS processing_receipts (
+    receipt_id TEXT PRIMARY KEY,
+    document_id TEXT NOT NULL,
+    operation TEXT NOT NULL, -- 'upload_document', 'process_document'
+    filename TEXT NOT NULL,
+    content_sha256 TEXT NOT NULL, -- hash of the extracted text before redaction
+    redacted_sha256 TEXT NOT NULL, -- hash of the stored, redacted text
+    extracted_chars INTEGER NOT NULL,
+    entities_redacted INTEGER NOT NULL,
+    entities_by_type TEXT NOT NULL, -- JSON object: entity type -> count
+    entities_by_tier TEXT NOT NULL, -- JSON object: sensitivity tier -> count
+    chunks_indexed INTEGER NOT NULL,
+    operator TEXT NOT NULL,
+    created_at DATETIME NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_processing_receipts_document
+    ON processing_receipts(document_id, created_at DESC);
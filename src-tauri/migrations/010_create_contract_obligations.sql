@@ -0,0 +1,12 @@
+-- Obligations extracted from analysed contracts, one row per document
+-- Extracted from the original text, so party names are stored; erased with the user's data
+
+CREATE TABLE IF NOT EXISTS contract_obligations (
+    document_id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    obligations TEXT NOT NULL, -- JSON array of obligation records
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_contract_obligations_user
+    ON contract_obligations(user_id);
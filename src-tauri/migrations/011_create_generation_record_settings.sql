@@ -0,0 +1,8 @@
+-- Whether replies are stored with the parameters that generated them
+-- A single row; recording stays off until the user opts in
+
+CREATE TABLE IF NOT EXISTS generation_record_settings (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    enabled BOOLEAN NOT NULL,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
@@ -2,8 +2,12 @@
 ///
 /// Implements confidence estimation for AI-generated responses
 /// to help users assess reliability of outputs.
+use super::RiskLevel;
 use serde::{Deserialize, Serialize};
 
+/// Component value below which it is reported as a weakness
+const WEAK_COMPONENT_THRESHOLD: f32 = 0.5;
+
 /// Confidence score for an AI response
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConfidenceScore {
@@ -265,6 +269,199 @@ impl ConfidenceFactors {
     }
 }
 
+/// Factor in an explained confidence score
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakdownFactor {
+    /// How well retrieved sources back the answer
+    RetrievalSupport,
+    /// Agreement between repeated or ensemble generations
+    SelfConsistency,
+    /// Risk level of the interaction; higher risk lowers confidence
+    RiskLevel,
+    /// Response length relative to a complete answer
+    Completeness,
+}
+
+impl BreakdownFactor {
+    pub fn label(&self) -> &'static str {
+        match self {
+            BreakdownFactor::RetrievalSupport => "retrieval support",
+            BreakdownFactor::SelfConsistency => "model self-consistency",
+            BreakdownFactor::RiskLevel => "risk level",
+            BreakdownFactor::Completeness => "completeness",
+        }
+    }
+}
+
+/// Relative weight of each breakdown factor; user-configurable
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfidenceWeights {
+    pub retrieval_support: f32,
+    pub self_consistency: f32,
+    pub risk_level: f32,
+    pub completeness: f32,
+}
+
+impl Default for ConfidenceWeights {
+    fn default() -> Self {
+        Self {
+            retrieval_support: 3.0, // Unsupported legal answers are the main risk
+            self_consistency: 1.5,
+            risk_level: 1.0,
+            completeness: 0.5,
+        }
+    }
+}
+
+/// Observations about a response that feed the explained score
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfidenceSignals {
+    /// Share of the answer backed by retrieved sources (0.0 - 1.0)
+    pub retrieval_support: f32,
+    /// Agreement between generations (0.0 - 1.0)
+    pub self_consistency: f32,
+    pub risk_level: RiskLevel,
+    pub token_count: usize,
+}
+
+/// One factor's share of an explained confidence score
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfidenceComponent {
+    pub factor: BreakdownFactor,
+    /// Factor value (0.0 - 1.0)
+    pub value: f32,
+    /// Normalized weight; weights of all components sum to 1.0
+    pub weight: f32,
+    /// `value * weight`; contributions of all components sum to the overall score
+    pub contribution: f32,
+    pub weak: bool,
+    pub reason: String,
+}
+
+/// Confidence score with the factors that produced it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfidenceBreakdown {
+    /// Overall confidence (0.0 - 1.0), same meaning as `ConfidenceScore::overall`
+    pub overall: f32,
+    pub level: ConfidenceLevel,
+    pub components: Vec<ConfidenceComponent>,
+    /// Weak factor costing the most confidence, if any
+    pub primary_concern: Option<BreakdownFactor>,
+    pub explanation: String,
+    /// Factors of the older `ConfidenceScore`, so its JSON shape is kept
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub factors: Option<ConfidenceFactors>,
+}
+
+impl ConfidenceBreakdown {
+    pub fn new(signals: &ConfidenceSignals, weights: &ConfidenceWeights) -> Self {
+        let risk_value = match signals.risk_level {
+            RiskLevel::Minimal => 1.0,
+            RiskLevel::Limited => 0.8,
+            RiskLevel::High => 0.5,
+            RiskLevel::Unacceptable => 0.0,
+        };
+        let completeness = if signals.token_count > 100 {
+            0.8
+        } else if signals.token_count > 50 {
+            0.6
+        } else {
+            0.4
+        };
+
+        let raw = [
+            (
+                BreakdownFactor::RetrievalSupport,
+                signals.retrieval_support.clamp(0.0, 1.0),
+                weights.retrieval_support,
+                format!(
+                    "{:.0}% of the answer is backed by retrieved sources",
+                    signals.retrieval_support.clamp(0.0, 1.0) * 100.0
+                ),
+            ),
+            (
+                BreakdownFactor::SelfConsistency,
+                signals.self_consistency.clamp(0.0, 1.0),
+                weights.self_consistency,
+                format!(
+                    "Generations agree at {:.0}%",
+                    signals.self_consistency.clamp(0.0, 1.0) * 100.0
+                ),
+            ),
+            (
+                BreakdownFactor::RiskLevel,
+                risk_value,
+                weights.risk_level,
+                signals.risk_level.warning_message().to_string(),
+            ),
+            (
+                BreakdownFactor::Completeness,
+                completeness,
+                weights.completeness,
+                format!("Response is {} tokens long", signals.token_count),
+            ),
+        ];
+
+        let total_weight: f32 = raw.iter().map(|(_, _, weight, _)| weight.max(0.0)).sum();
+        let components: Vec<ConfidenceComponent> = raw
+            .into_iter()
+            .map(|(factor, value, weight, reason)| {
+                let weight = if total_weight > 0.0 {
+                    weight.max(0.0) / total_weight
+                } else {
+                    0.0
+                };
+                ConfidenceComponent {
+                    factor,
+                    value,
+                    weight,
+                    contribution: value * weight,
+                    weak: value < WEAK_COMPONENT_THRESHOLD,
+                    reason,
+                }
+            })
+            .collect();
+
+        let overall = components.iter().map(|c| c.contribution).sum::<f32>();
+        let level = ConfidenceLevel::from_score(overall);
+        let primary_concern = components
+            .iter()
+            .filter(|c| c.weak)
+            .max_by(|a, b| {
+                let lost = |c: &ConfidenceComponent| (1.0 - c.value) * c.weight;
+                lost(a).total_cmp(&lost(b))
+            })
+            .map(|c| c.factor);
+
+        let mut explanation = format!("Confidence level: {}. ", level.indicator());
+        if let Some(concern) = primary_concern {
+            explanation.push_str(&format!("Main concern: {}. ", concern.label()));
+        }
+        explanation.push_str(level.recommendation());
+
+        Self {
+            overall,
+            level,
+            components,
+            primary_concern,
+            explanation,
+            factors: None,
+        }
+    }
+
+    /// Attach the legacy factor values for callers that read them
+    pub fn with_factors(mut self, factors: ConfidenceFactors) -> Self {
+        self.factors = Some(factors);
+        self
+    }
+
+    /// Scalar score, for callers that only need the number
+    pub fn score(&self) -> f32 {
+        self.overall
+    }
+}
+
 /// Weights for confidence factors
 struct FactorWeights {
     completeness: f32,
@@ -337,6 +534,53 @@ mod confidence_tests {
         assert!(factors.source_reliability > 0.4);
     }
 
+    #[test]
+    fn test_breakdown_explains_low_retrieval_support() {
+        let weights = ConfidenceWeights::default();
+        let supported = ConfidenceBreakdown::new(
+            &ConfidenceSignals {
+                retrieval_support: 0.9,
+                self_consistency: 0.85,
+                risk_level: RiskLevel::Limited,
+                token_count: 150,
+            },
+            &weights,
+        );
+        let unsupported = ConfidenceBreakdown::new(
+            &ConfidenceSignals {
+                retrieval_support: 0.05,
+                self_consistency: 0.85,
+                risk_level: RiskLevel::Limited,
+                token_count: 150,
+            },
+            &weights,
+        );
+
+        for breakdown in [&supported, &unsupported] {
+            let sum: f32 = breakdown.components.iter().map(|c| c.contribution).sum();
+            assert!((sum - breakdown.score()).abs() < 1e-6);
+            let weight_sum: f32 = breakdown.components.iter().map(|c| c.weight).sum();
+            assert!((weight_sum - 1.0).abs() < 1e-6);
+        }
+
+        assert!(supported.score() > 0.75);
+        assert_eq!(supported.primary_concern, None);
+
+        assert!(unsupported.score() < 0.5);
+        assert!(matches!(
+            unsupported.level,
+            ConfidenceLevel::Low | ConfidenceLevel::VeryLow
+        ));
+        assert_eq!(
+            unsupported.primary_concern,
+            Some(BreakdownFactor::RetrievalSupport)
+        );
+        let support = &unsupported.components[0];
+        assert_eq!(support.factor, BreakdownFactor::RetrievalSupport);
+        assert!(support.weak);
+        assert!(unsupported.explanation.contains("retrieval support"));
+    }
+
     #[test]
     fn test_weighted_confidence_calculation() {
         let high_factual = ConfidenceFactors {
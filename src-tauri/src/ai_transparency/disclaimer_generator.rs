@@ -70,6 +70,14 @@ impl DisclaimerGenerator {
         // Add safety warnings from model card
         warnings.extend(model_card.safety_warnings.clone());
 
+        // License terms that limit how the model may be used
+        if model_card.license_terms.commercial_use_prohibited {
+            warnings.push("⚠️ The model's license prohibits commercial use".to_string());
+        }
+        if model_card.license_terms.research_only {
+            warnings.push("⚠️ The model's license limits use to research".to_string());
+        }
+
         // Standard AI warnings
         warnings.push("⚠️ Known Limitations:".to_string());
 
@@ -251,6 +259,7 @@ mod tests {
             ethical_considerations: vec![],
             safety_warnings: vec!["Use with caution".to_string()],
             performance_metrics: vec![],
+            license_terms: Default::default(),
         };
 
         let disclaimer = DisclaimerGenerator::generate(&model_card);
@@ -0,0 +1,131 @@
+use super::model_card_parser::ModelLicense;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// What the firm uses models for, as declared in its settings
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeclaredUseCase {
+    /// Client work and other commercial practice
+    #[default]
+    Commercial,
+    /// Pro bono, academic or internal non-commercial work
+    NonCommercial,
+    Research,
+}
+
+/// How model licenses are checked against the declared use case
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LicensePolicy {
+    pub use_case: DeclaredUseCase,
+    /// Refuse to load a model whose license conflicts, instead of only warning
+    #[serde(default)]
+    pub block_on_conflict: bool,
+}
+
+impl LicensePolicy {
+    /// Load the policy from its config file, or the default when there is none
+    pub fn load_from_file(config_path: &Path) -> Result<Self, String> {
+        if !config_path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(config_path)
+            .map_err(|e| format!("Failed to read license policy: {}", e))?;
+        serde_json::from_str(&content).map_err(|e| format!("Failed to parse license policy: {}", e))
+    }
+
+    pub fn save_to_file(&self, config_path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize license policy: {}", e))?;
+        fs::write(config_path, json).map_err(|e| format!("Failed to write license policy: {}", e))
+    }
+
+    /// Why `license` does not permit the declared use case; None when it does
+    pub fn conflict(&self, model_id: &str, license: &ModelLicense) -> Option<String> {
+        let name = license.license.as_deref().unwrap_or("its license");
+        match self.use_case {
+            DeclaredUseCase::Commercial if license.commercial_use_prohibited => Some(format!(
+                "{} is licensed under {}, which prohibits commercial use",
+                model_id, name
+            )),
+            DeclaredUseCase::Commercial | DeclaredUseCase::NonCommercial
+                if license.research_only =>
+            {
+                Some(format!(
+                    "{} is licensed under {}, which limits use to research",
+                    model_id, name
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether `model_name` may be loaded: a warning when its license conflicts
+    /// with the declared use case, an error when the policy blocks it. A model
+    /// with no known model ID or license fails closed under a blocking policy,
+    /// since nothing shows its license permits the use.
+    pub fn check(
+        &self,
+        model_name: &str,
+        model_id: Option<&str>,
+        license: Option<&ModelLicense>,
+    ) -> Result<Option<String>, String> {
+        let conflict = match (model_id, license) {
+            (Some(model_id), Some(license)) => self.conflict(model_id, license),
+            (Some(model_id), None) => Some(format!(
+                "The license of {} is not known; fetch its model card to check it",
+                model_id
+            )),
+            (None, _) => Some(format!(
+                "{} is not mapped to a model card, so its license is not known",
+                model_name
+            )),
+        };
+        match conflict {
+            Some(conflict) if self.block_on_conflict => Err(format!(
+                "{}; loading it is blocked by the license policy",
+                conflict
+            )),
+            conflict => Ok(conflict),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown_license_fails_closed_when_blocking() {
+        let non_commercial = ModelLicense {
+            license: Some("cc-by-nc-4.0".to_string()),
+            commercial_use_prohibited: true,
+            ..Default::default()
+        };
+        let mut policy = LicensePolicy::default();
+
+        // Warned about but loaded while the policy only warns
+        let warning = policy
+            .check("model.gguf", Some("org/model"), Some(&non_commercial))
+            .unwrap();
+        assert!(warning.unwrap().contains("prohibits commercial use"));
+        let warning = policy.check("model.gguf", Some("org/model"), None).unwrap();
+        assert!(warning.unwrap().contains("not known"));
+
+        policy.block_on_conflict = true;
+        assert!(policy
+            .check("model.gguf", Some("org/model"), Some(&non_commercial))
+            .is_err());
+        assert!(policy.check("model.gguf", Some("org/model"), None).is_err());
+        assert!(policy.check("model.gguf", None, None).is_err());
+        assert_eq!(
+            policy.check(
+                "model.gguf",
+                Some("org/model"),
+                Some(&ModelLicense::default())
+            ),
+            Ok(None)
+        );
+    }
+}
@@ -8,14 +8,16 @@ pub mod notices;
 // Model card fetching and transparency
 pub mod disclaimer_generator;
 pub mod generic_disclaimer;
+pub mod license_policy;
 pub mod model_card_fetcher;
 pub mod model_card_parser;
 pub mod model_registry;
 
 pub use disclaimer_generator::{DisclaimerGenerator, ModelDisclaimer};
 pub use generic_disclaimer::{GenericDisclaimer, GenericDisclaimerGenerator};
-pub use model_card_fetcher::ModelCardFetcher;
-pub use model_card_parser::ModelCardParser;
+pub use license_policy::{DeclaredUseCase, LicensePolicy};
+pub use model_card_fetcher::{ModelCardFetchConfig, ModelCardFetcher};
+pub use model_card_parser::{ModelCardParser, ModelLicense};
 pub use model_registry::ModelRegistry;
 
 use chrono::{DateTime, Utc};
@@ -185,6 +187,10 @@ pub struct TransparencyPreferences {
 
     /// Date of last disclaimer acknowledgment
     pub last_acknowledgment: Option<DateTime<Utc>>,
+
+    /// Weights of the factors in explained confidence scores
+    #[serde(default)]
+    pub confidence_weights: confidence::ConfidenceWeights,
 }
 
 impl Default for TransparencyPreferences {
@@ -196,6 +202,7 @@ impl Default for TransparencyPreferences {
             min_confidence_warning: 0.7,
             onboarding_completed: false,
             last_acknowledgment: None,
+            confidence_weights: confidence::ConfidenceWeights::default(),
         }
     }
 }
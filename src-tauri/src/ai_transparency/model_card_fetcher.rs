@@ -1,9 +1,33 @@
+use crate::model_updates::is_offline_mode;
 use reqwest;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
 use std::time::{Duration, SystemTime};
 
+/// Bounds on model card fetching, so a slow or huge card cannot stall model loading
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelCardFetchConfig {
+    /// Limit for fetching metadata and README together
+    pub timeout_secs: u64,
+    /// Largest metadata or README response accepted
+    pub max_response_bytes: u64,
+    /// How long a fetched card is served from cache
+    pub cache_ttl_secs: u64,
+    pub hub_url: String,
+}
+
+impl Default for ModelCardFetchConfig {
+    fn default() -> Self {
+        Self {
+            timeout_secs: 10,
+            max_response_bytes: 1024 * 1024,
+            cache_ttl_secs: 7 * 24 * 60 * 60, // 7 days
+            hub_url: "https://huggingface.co".to_string(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModelMetadata {
     pub model_id: String,
@@ -22,33 +46,55 @@ pub struct CachedModelCard {
     pub cached_at: SystemTime,
 }
 
+impl CachedModelCard {
+    /// License from the Hub metadata: its `license` field or a `license:` tag
+    pub fn metadata_license(&self) -> Option<String> {
+        self.metadata.license.clone().or_else(|| {
+            self.metadata
+                .tags
+                .iter()
+                .find_map(|tag| tag.strip_prefix("license:").map(str::to_string))
+        })
+    }
+}
+
 pub struct ModelCardFetcher {
     #[allow(dead_code)]
     cache_dir: PathBuf,
     http_client: reqwest::Client,
     cache_ttl: Duration,
+    config: ModelCardFetchConfig,
 }
 
 impl ModelCardFetcher {
     pub fn new(cache_dir: PathBuf) -> Self {
+        Self::with_config(cache_dir, ModelCardFetchConfig::default())
+    }
+
+    pub fn with_config(cache_dir: PathBuf, config: ModelCardFetchConfig) -> Self {
         // Ensure cache directory exists
         if let Err(e) = fs::create_dir_all(&cache_dir) {
             eprintln!("Warning: Failed to create cache directory: {}", e);
         }
 
-        let http_client = reqwest::Client::builder()
-            .timeout(Duration::from_secs(10))
-            .build()
-            .expect("Failed to create HTTP client");
+        // The configured timeout bounds the whole fetch in `fetch_model_card`
+        let http_client = reqwest::Client::new();
 
         Self {
             cache_dir,
             http_client,
-            cache_ttl: Duration::from_secs(7 * 24 * 60 * 60), // 7 days
+            cache_ttl: Duration::from_secs(config.cache_ttl_secs),
+            config,
         }
     }
 
-    /// Fetch model card from HuggingFace Hub API
+    pub fn config(&self) -> &ModelCardFetchConfig {
+        &self.config
+    }
+
+    /// Fetch model card from HuggingFace Hub API. Fails once the configured
+    /// timeout or response size is exceeded, and only reads the cache in
+    /// offline mode.
     pub async fn fetch_model_card(&self, model_id: &str) -> Result<CachedModelCard, String> {
         // Check cache first
         if let Ok(cached) = self.get_cached_model_card(model_id) {
@@ -59,8 +105,23 @@ impl ModelCardFetcher {
             }
         }
 
+        if is_offline_mode() {
+            return self
+                .get_cached_model_card(model_id)
+                .map_err(|_| format!("No cached model card for {} in offline mode", model_id));
+        }
+
         // Fetch from API
-        match self.fetch_from_api(model_id).await {
+        let timeout = Duration::from_secs(self.config.timeout_secs);
+        let fetched = tokio::time::timeout(timeout, self.fetch_from_api(model_id))
+            .await
+            .unwrap_or_else(|_| {
+                Err(format!(
+                    "Model card fetch timed out after {} s",
+                    self.config.timeout_secs
+                ))
+            });
+        match fetched {
             Ok(card) => {
                 // Save to cache
                 let _ = self.cache_model_card(model_id, &card);
@@ -79,29 +140,24 @@ impl ModelCardFetcher {
 
     /// Fetch model metadata and README from HuggingFace API
     async fn fetch_from_api(&self, model_id: &str) -> Result<CachedModelCard, String> {
+        let hub_url = self.config.hub_url.trim_end_matches('/');
+
         // Fetch metadata
-        let metadata_url = format!("https://huggingface.co/api/models/{}", model_id);
-        let metadata: ModelMetadata = self
-            .http_client
-            .get(&metadata_url)
-            .send()
-            .await
-            .map_err(|e| format!("Failed to fetch metadata: {}", e))?
-            .json()
+        let metadata_url = format!("{}/api/models/{}", hub_url, model_id);
+        let metadata = self
+            .get_capped(&metadata_url)
             .await
+            .map_err(|e| format!("Failed to fetch metadata: {}", e))?;
+        let metadata: ModelMetadata = serde_json::from_slice(&metadata)
             .map_err(|e| format!("Failed to parse metadata: {}", e))?;
 
         // Fetch README
-        let readme_url = format!("https://huggingface.co/{}/raw/main/README.md", model_id);
-        let readme_content = self
-            .http_client
-            .get(&readme_url)
-            .send()
-            .await
-            .map_err(|e| format!("Failed to fetch README: {}", e))?
-            .text()
+        let readme_url = format!("{}/{}/raw/main/README.md", hub_url, model_id);
+        let readme = self
+            .get_capped(&readme_url)
             .await
-            .map_err(|e| format!("Failed to read README: {}", e))?;
+            .map_err(|e| format!("Failed to fetch README: {}", e))?;
+        let readme_content = String::from_utf8_lossy(&readme).into_owned();
 
         Ok(CachedModelCard {
             metadata,
@@ -110,6 +166,32 @@ impl ModelCardFetcher {
         })
     }
 
+    /// Response body of `url`, refused once it exceeds `max_response_bytes`
+    async fn get_capped(&self, url: &str) -> Result<Vec<u8>, String> {
+        let max_bytes = self.config.max_response_bytes;
+        let too_large = || format!("response exceeds {} bytes", max_bytes);
+
+        let mut response = self
+            .http_client
+            .get(url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| e.to_string())?;
+        if response.content_length().is_some_and(|len| len > max_bytes) {
+            return Err(too_large());
+        }
+
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+            if (body.len() + chunk.len()) as u64 > max_bytes {
+                return Err(too_large());
+            }
+            body.extend_from_slice(&chunk);
+        }
+        Ok(body)
+    }
+
     /// Get cached model card
     fn get_cached_model_card(&self, model_id: &str) -> Result<CachedModelCard, String> {
         let cache_path = self.get_cache_path(model_id);
@@ -191,6 +273,58 @@ mod tests {
             .contains("meta-llama_Llama-2-7b-chat-hf.json"));
     }
 
+    /// Local stand-in for the Hub: answers every request with `response`,
+    /// or holds connections open without answering when it is None
+    async fn fake_hub(response: Option<&'static [u8]>) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut held = Vec::new();
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = [0u8; 1024];
+                let _ = socket.read(&mut request).await;
+                match response {
+                    Some(response) => {
+                        let _ = socket.write_all(response).await;
+                    }
+                    None => held.push(socket),
+                }
+            }
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn test_slow_or_oversized_card_fails_within_bounds() {
+        let temp_dir = tempdir().unwrap();
+        let config = ModelCardFetchConfig {
+            timeout_secs: 1,
+            max_response_bytes: 1024,
+            hub_url: fake_hub(None).await,
+            ..ModelCardFetchConfig::default()
+        };
+        let fetcher = ModelCardFetcher::with_config(temp_dir.path().to_path_buf(), config.clone());
+
+        let started = std::time::Instant::now();
+        let error = fetcher.fetch_model_card("test/slow").await.unwrap_err();
+        assert!(error.contains("timed out"), "{}", error);
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        let oversized = ModelCardFetchConfig {
+            hub_url: fake_hub(Some(
+                b"HTTP/1.1 200 OK\r\nContent-Length: 10000000\r\n\r\n{\"model_id\":",
+            ))
+            .await,
+            ..config
+        };
+        let fetcher = ModelCardFetcher::with_config(temp_dir.path().to_path_buf(), oversized);
+        let error = fetcher.fetch_model_card("test/huge").await.unwrap_err();
+        assert!(error.contains("exceeds 1024 bytes"), "{}", error);
+        assert!(!fetcher.is_cached("test/huge"));
+    }
+
     #[test]
     fn test_cache_operations() {
         let temp_dir = tempdir().unwrap();
@@ -14,6 +14,26 @@ pub struct ModelCard {
     pub ethical_considerations: Vec<String>,
     pub safety_warnings: Vec<String>,
     pub performance_metrics: Vec<String>,
+    #[serde(default)]
+    pub license_terms: ModelLicense,
+}
+
+/// License of a model and the restrictions it places on use
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModelLicense {
+    /// License identifier or text as given by the card, e.g. "cc-by-nc-4.0"
+    pub license: Option<String>,
+    pub commercial_use_prohibited: bool,
+    pub research_only: bool,
+    /// Restrictions quoted from the card: license terms and out-of-scope uses
+    pub restrictions: Vec<String>,
+}
+
+impl ModelLicense {
+    /// Whether the license or card limits how the model may be used
+    pub fn is_restrictive(&self) -> bool {
+        self.commercial_use_prohibited || self.research_only
+    }
 }
 
 pub struct ModelCardParser;
@@ -22,6 +42,7 @@ impl ModelCardParser {
     /// Parse model card from markdown content
     pub fn parse(model_id: String, markdown: &str) -> ModelCard {
         let sections = Self::extract_sections(markdown);
+        let license = Self::extract_license(&sections, markdown);
 
         ModelCard {
             model_id: model_id.clone(),
@@ -39,7 +60,8 @@ impl ModelCardParser {
                 &sections,
                 &["training data", "dataset", "data"],
             ),
-            license: Self::extract_license(&sections, markdown),
+            license_terms: Self::extract_license_terms(&sections, markdown, license.clone()),
+            license,
             paper_url: Self::extract_paper_url(markdown),
             ethical_considerations: Self::extract_list_items(
                 &sections,
@@ -50,6 +72,14 @@ impl ModelCardParser {
         }
     }
 
+    /// License terms of a card whose license is known from elsewhere, such as
+    /// the Hub metadata, when the card itself does not state it
+    pub fn parse_license(markdown: &str, license: Option<String>) -> ModelLicense {
+        let sections = Self::extract_sections(markdown);
+        let license = Self::extract_license(&sections, markdown).or(license);
+        Self::extract_license_terms(&sections, markdown, license)
+    }
+
     /// Extract sections from markdown
     fn extract_sections(markdown: &str) -> Vec<(String, String)> {
         let mut sections = Vec::new();
@@ -186,6 +216,56 @@ impl ModelCardParser {
         None
     }
 
+    /// Classify the license and collect the use restrictions stated in the card
+    fn extract_license_terms(
+        sections: &[(String, String)],
+        markdown: &str,
+        license: Option<String>,
+    ) -> ModelLicense {
+        let non_commercial_re = Regex::new(
+            r"(?i)non[- ]?commercial|not (be used )?for commercial|no commercial use|commercial use (is )?(not permitted|prohibited|forbidden)",
+        )
+        .unwrap();
+        let research_only_re =
+            Regex::new(r"(?i)research (purposes |use )?only|only for research|solely for research")
+                .unwrap();
+
+        // SPDX-style identifiers mark non-commercial licenses with an "nc" part
+        let nc_identifier = license.as_deref().is_some_and(|license| {
+            license
+                .to_lowercase()
+                .split(['-', '_', ' '])
+                .any(|part| part == "nc")
+        });
+
+        let mut restrictions = Self::extract_list_items(
+            sections,
+            &["out-of-scope", "out of scope", "restriction", "prohibited"],
+        );
+        let mut commercial_use_prohibited = nc_identifier;
+        let mut research_only = false;
+        for line in markdown.lines() {
+            let non_commercial = non_commercial_re.is_match(line);
+            let research = research_only_re.is_match(line);
+            if !non_commercial && !research {
+                continue;
+            }
+            commercial_use_prohibited |= non_commercial;
+            research_only |= research;
+            let statement = line.trim().trim_start_matches(['-', '*', '•', '>']).trim();
+            if !restrictions.iter().any(|r| r == statement) {
+                restrictions.push(statement.to_string());
+            }
+        }
+
+        ModelLicense {
+            license,
+            commercial_use_prohibited,
+            research_only,
+            restrictions,
+        }
+    }
+
     /// Extract paper URL
     fn extract_paper_url(markdown: &str) -> Option<String> {
         let paper_re = Regex::new(r"(?i)paper:\s*(https?://[^\s\)]+)").unwrap();
@@ -311,6 +391,50 @@ License: Llama 2 Community License
         assert_eq!(card.safety_warnings.len(), 1);
     }
 
+    #[test]
+    fn test_restrictive_license_surfaced() {
+        let markdown = r#"---
+license: cc-by-nc-4.0
+---
+
+# Legal Summarizer 7B
+
+A fine-tuned model for summarizing court opinions and contracts.
+
+## Out-of-Scope Use
+
+- Providing legal advice without review by a qualified lawyer
+
+## License
+
+This model is released for research purposes only. Commercial use is prohibited.
+"#;
+
+        let card = ModelCardParser::parse("acme/legal-summarizer-7b".to_string(), markdown);
+        let terms = &card.license_terms;
+        let license = card.license.clone().unwrap();
+        assert!(license.contains("research purposes only"));
+        assert!(terms.commercial_use_prohibited);
+        assert!(terms.research_only);
+        assert!(terms.is_restrictive());
+        assert!(terms
+            .restrictions
+            .contains(&"Providing legal advice without review by a qualified lawyer".to_string()));
+        assert!(terms.restrictions.contains(
+            &"This model is released for research purposes only. Commercial use is prohibited."
+                .to_string()
+        ));
+
+        // An "nc" license identifier alone marks commercial use as prohibited
+        let terms = ModelCardParser::parse_license("# Model", Some("cc-by-nc-sa-4.0".into()));
+        assert_eq!(terms.license.as_deref(), Some("cc-by-nc-sa-4.0"));
+        assert!(terms.commercial_use_prohibited);
+        assert!(!terms.research_only);
+
+        let terms = ModelCardParser::parse_license("license: apache-2.0", None);
+        assert!(!terms.is_restrictive());
+    }
+
     #[ignore]
     #[test]
     fn test_extract_sections() {
@@ -1,9 +1,14 @@
 use crate::constants::*;
+use crate::grammar::Grammar;
 use anyhow::{anyhow, Result};
 use candle_core::{Device, Tensor};
 use candle_transformers::models::quantized_llama as llama;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tokenizers::Tokenizer;
 use tokio::sync::RwLock;
@@ -23,7 +28,11 @@ pub struct GGUFInferenceConfig {
     pub top_k: i32,          // Top-k sampling
     pub top_p: f32,          // Top-p (nucleus) sampling
     pub repeat_penalty: f32, // Repetition penalty
-    pub seed: u32,           // Random seed for reproducibility
+    pub seed: u64,           // Random seed for reproducibility
+    #[serde(default)]
+    pub logit_bias: HashMap<u32, f32>, // Added to a token's logit before sampling
+    #[serde(default)]
+    pub banned_tokens: Vec<u32>, // Never sampled
 }
 
 impl Default for GGUFInferenceConfig {
@@ -41,6 +50,8 @@ impl Default for GGUFInferenceConfig {
             top_p: DEFAULT_TOP_P,
             repeat_penalty: DEFAULT_REPEAT_PENALTY,
             seed: 42,
+            logit_bias: HashMap::new(),
+            banned_tokens: Vec::new(),
         }
     }
 }
@@ -59,6 +70,203 @@ pub enum StopReason {
     MaxTokens,
     StopSequence,
     EndOfText,
+    Cancelled,
+}
+
+/// Cooperative cancellation, checked before each generated token
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// End-of-text token id (typically 2 for LLaMA)
+const EOS_TOKEN: u32 = 2;
+
+/// One decoding step at a time; the loaded model in production
+trait TokenSource {
+    /// Next token given the prompt and everything generated so far
+    fn next_token(&mut self, context: &[u32]) -> Result<u32>;
+    fn decode(&self, token: u32) -> Result<String>;
+
+    /// Next token among those `allowed` accepts, given each token's id and text
+    fn next_token_masked(
+        &mut self,
+        _context: &[u32],
+        _allowed: &mut dyn FnMut(u32, &str) -> bool,
+    ) -> Result<u32> {
+        Err(anyhow!(
+            "Constrained generation failed: grammar not supported by this inference backend"
+        ))
+    }
+}
+
+struct ModelTokenSource<'a> {
+    engine: &'a GGUFInferenceEngine,
+    model: &'a mut llama::ModelWeights,
+    tokenizer: &'a Tokenizer,
+    config: &'a GGUFInferenceConfig,
+    /// Seeded from `config.seed`, so a generation can be repeated exactly
+    rng: StdRng,
+}
+
+impl ModelTokenSource<'_> {
+    fn logits(&mut self, context: &[u32]) -> Result<Tensor> {
+        Ok(self.model.forward(
+            &Tensor::new(context, &self.engine.device)?,
+            context.len() - 1,
+        )?)
+    }
+}
+
+impl TokenSource for ModelTokenSource<'_> {
+    fn next_token(&mut self, context: &[u32]) -> Result<u32> {
+        let logits = self.logits(context)?;
+        // Sample next token with temperature, top-k, top-p
+        self.engine
+            .sample_token(&logits, self.config, &mut self.rng, None)
+    }
+
+    fn decode(&self, token: u32) -> Result<String> {
+        self.tokenizer
+            .decode(&[token], false)
+            .map_err(|e| anyhow!("Failed to decode token: {}", e))
+    }
+
+    fn next_token_masked(
+        &mut self,
+        context: &[u32],
+        allowed: &mut dyn FnMut(u32, &str) -> bool,
+    ) -> Result<u32> {
+        let logits = self.logits(context)?;
+        let tokenizer = self.tokenizer;
+        let mut allowed = |token: u32| {
+            tokenizer
+                .decode(&[token], false)
+                .is_ok_and(|text| allowed(token, &text))
+        };
+        self.engine
+            .sample_token(&logits, self.config, &mut self.rng, Some(&mut allowed))
+    }
+}
+
+/// Generation loop shared by blocking and streaming generation. Stops at end of
+/// text, a stop sequence, `max_tokens`, cancellation, or when `on_token`
+/// returns false; the text generated up to that point is always returned.
+/// With a grammar, only tokens that keep the text inside it are sampled, and
+/// generation ends once the grammar allows nothing more.
+fn run_generation(
+    source: &mut dyn TokenSource,
+    prompt_tokens: Vec<u32>,
+    max_tokens: usize,
+    stop_sequences: &[String],
+    grammar: Option<&Grammar>,
+    cancel: &CancellationToken,
+    on_token: &mut dyn FnMut(&str) -> bool,
+) -> Result<GenerationResult> {
+    let start_time = std::time::Instant::now();
+    let mut generated_text = String::new();
+    let mut tokens_generated = 0;
+    let mut stop_reason = StopReason::MaxTokens;
+    let mut all_tokens = prompt_tokens;
+    let mut matcher = grammar.map(Grammar::matcher);
+
+    for _ in 0..max_tokens {
+        if cancel.is_cancelled() {
+            stop_reason = StopReason::Cancelled;
+            break;
+        }
+
+        let next_token = match &matcher {
+            Some(matcher) => source.next_token_masked(&all_tokens, &mut |token, text| {
+                if token == EOS_TOKEN {
+                    matcher.is_complete()
+                } else {
+                    !text.is_empty() && matcher.allows(text)
+                }
+            })?,
+            None => source.next_token(&all_tokens)?,
+        };
+
+        if next_token == EOS_TOKEN {
+            stop_reason = StopReason::EndOfText;
+            break;
+        }
+
+        all_tokens.push(next_token);
+        let piece = source.decode(next_token)?;
+        generated_text.push_str(&piece);
+        tokens_generated += 1;
+        if let Some(matcher) = matcher.as_mut() {
+            matcher.accept(&piece);
+        }
+
+        // Stream token to callback
+        if !on_token(&piece) {
+            break;
+        }
+
+        if let Some((_matched_seq, pos)) = find_stop_sequence(&generated_text, stop_sequences) {
+            stop_reason = StopReason::StopSequence;
+            generated_text.truncate(pos);
+            break;
+        }
+
+        if matcher.as_ref().is_some_and(|m| m.is_finished()) {
+            stop_reason = StopReason::EndOfText;
+            break;
+        }
+    }
+
+    let elapsed = start_time.elapsed();
+    let tokens_per_second = if elapsed.as_secs_f32() > 0.0 {
+        tokens_generated as f32 / elapsed.as_secs_f32()
+    } else {
+        0.0
+    };
+
+    Ok(GenerationResult {
+        text: generated_text,
+        tokens_generated,
+        time_ms: elapsed.as_millis(),
+        tokens_per_second,
+        stop_reason,
+    })
+}
+
+/// Find stop sequence in generated text
+fn find_stop_sequence(text: &str, stop_sequences: &[String]) -> Option<(String, usize)> {
+    let mut best_match: Option<(String, usize)> = None;
+
+    for seq in stop_sequences {
+        if text.ends_with(seq) {
+            if let Some(pos) = text.rfind(seq) {
+                if pos + seq.len() == text.len() {
+                    match &best_match {
+                        None => best_match = Some((seq.clone(), pos)),
+                        Some((prev_seq, _)) => {
+                            if seq.len() > prev_seq.len() {
+                                best_match = Some((seq.clone(), pos));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    best_match
 }
 
 pub struct GGUFInferenceEngine {
@@ -100,6 +308,15 @@ impl GGUFInferenceEngine {
 
         tracing::info!("Loading GGUF model from: {:?}", path);
 
+        let compatibility = crate::gguf_compat::validate_gguf_compatibility(path)?;
+        if !compatibility.compatible {
+            return Err(anyhow!(
+                "Model {:?} is not compatible with the inference engine: {}",
+                path,
+                compatibility.reasons.join("; ")
+            ));
+        }
+
         // Load quantized model (GGUF format)
         use candle_core::quantized::gguf_file;
 
@@ -152,13 +369,18 @@ impl GGUFInferenceEngine {
         Ok(())
     }
 
-    /// Generate text from a prompt
+    /// Generate text from a prompt, constrained to a GBNF grammar if given,
+    /// sampling with the settings and seed of `config`
     pub async fn generate(
         &self,
         prompt: &str,
         max_tokens: usize,
         stop_sequences: Vec<String>,
+        grammar: Option<&str>,
+        config: &GGUFInferenceConfig,
+        cancel: &CancellationToken,
     ) -> Result<GenerationResult> {
+        let grammar = grammar.map(Grammar::parse).transpose()?;
         let mut model_lock = self.model.write().await;
         let model = model_lock
             .as_mut()
@@ -169,8 +391,6 @@ impl GGUFInferenceEngine {
             .as_ref()
             .ok_or_else(|| anyhow!("No tokenizer loaded"))?;
 
-        let config = self.config.read().await.clone();
-
         // Tokenize prompt
         let encoding = tokenizer
             .encode(prompt, true)
@@ -211,66 +431,31 @@ impl GGUFInferenceEngine {
         }
 
         // Tokens are ready for inference (no explicit tensor conversion needed for quantized models)
-
-        // Generation loop
-        let start_time = std::time::Instant::now();
-        let mut generated_text = String::new();
-        let mut tokens_generated = 0;
-        let mut stop_reason = StopReason::MaxTokens;
-        let mut all_tokens = tokens.clone();
-
-        for _ in 0..max_tokens {
-            // Forward pass
-            let logits = model.forward(&Tensor::new(&all_tokens[..], &self.device)?, all_tokens.len() - 1)?;
-
-            // Sample next token with temperature, top-k, top-p
-            let next_token = self.sample_token(&logits, &config)?;
-
-            // Check for end of text (EOS token is typically 2 for LLaMA)
-            if next_token == 2 {
-                stop_reason = StopReason::EndOfText;
-                break;
-            }
-
-            all_tokens.push(next_token);
-
-            // Decode token to text
-            let piece = tokenizer
-                .decode(&[next_token], false)
-                .map_err(|e| anyhow!("Failed to decode token: {}", e))?;
-
-            generated_text.push_str(&piece);
-            tokens_generated += 1;
-
-            // Check for stop sequences
-            if let Some((_matched_seq, pos)) = self.find_stop_sequence(&generated_text, &stop_sequences) {
-                stop_reason = StopReason::StopSequence;
-                generated_text.truncate(pos);
-                break;
-            }
-        }
-
-        let elapsed = start_time.elapsed();
-        let tokens_per_second = if elapsed.as_secs_f32() > 0.0 {
-            tokens_generated as f32 / elapsed.as_secs_f32()
-        } else {
-            0.0
+        let mut source = ModelTokenSource {
+            engine: self,
+            model,
+            tokenizer,
+            config,
+            rng: StdRng::seed_from_u64(config.seed),
         };
+        let result = run_generation(
+            &mut source,
+            tokens,
+            max_tokens,
+            &stop_sequences,
+            grammar.as_ref(),
+            cancel,
+            &mut |_| true,
+        )?;
 
         tracing::info!(
             "Generated {} tokens in {:.2}s ({:.2} tok/s)",
-            tokens_generated,
-            elapsed.as_secs_f32(),
-            tokens_per_second
+            result.tokens_generated,
+            result.time_ms as f32 / 1000.0,
+            result.tokens_per_second
         );
 
-        Ok(GenerationResult {
-            text: generated_text,
-            tokens_generated,
-            time_ms: elapsed.as_millis(),
-            tokens_per_second,
-            stop_reason,
-        })
+        Ok(result)
     }
 
     /// Generate text with streaming support
@@ -280,11 +465,15 @@ impl GGUFInferenceEngine {
         prompt: &str,
         max_tokens: usize,
         stop_sequences: Vec<String>,
+        grammar: Option<&str>,
+        config: &GGUFInferenceConfig,
+        cancel: &CancellationToken,
         mut on_token: F,
     ) -> Result<GenerationResult>
     where
         F: FnMut(&str) -> bool,
     {
+        let grammar = grammar.map(Grammar::parse).transpose()?;
         let mut model_lock = self.model.write().await;
         let model = model_lock
             .as_mut()
@@ -295,8 +484,6 @@ impl GGUFInferenceEngine {
             .as_ref()
             .ok_or_else(|| anyhow!("No tokenizer loaded"))?;
 
-        let config = self.config.read().await.clone();
-
         let encoding = tokenizer
             .encode(prompt, true)
             .map_err(|e| anyhow!("Tokenization failed: {}", e))?;
@@ -311,58 +498,22 @@ impl GGUFInferenceEngine {
             tokens.truncate(max_prompt_tokens);
         }
 
-        let start_time = std::time::Instant::now();
-        let mut generated_text = String::new();
-        let mut tokens_generated = 0;
-        let mut stop_reason = StopReason::MaxTokens;
-        let mut all_tokens = tokens.clone();
-        let mut user_stopped = false;
-
-        for _ in 0..max_tokens {
-            let logits = model.forward(&Tensor::new(&all_tokens[..], &self.device)?, all_tokens.len() - 1)?;
-            let next_token = self.sample_token(&logits, &config)?;
-
-            if next_token == 2 {
-                stop_reason = StopReason::EndOfText;
-                break;
-            }
-
-            all_tokens.push(next_token);
-
-            let piece = tokenizer
-                .decode(&[next_token], false)
-                .map_err(|e| anyhow!("Failed to decode token: {}", e))?;
-
-            generated_text.push_str(&piece);
-            tokens_generated += 1;
-
-            // Stream token to callback
-            if !on_token(&piece) {
-                user_stopped = true;
-                break;
-            }
-
-            if let Some((_matched_seq, pos)) = self.find_stop_sequence(&generated_text, &stop_sequences) {
-                stop_reason = StopReason::StopSequence;
-                generated_text.truncate(pos);
-                break;
-            }
-        }
-
-        let elapsed = start_time.elapsed();
-        let tokens_per_second = if elapsed.as_secs_f32() > 0.0 {
-            tokens_generated as f32 / elapsed.as_secs_f32()
-        } else {
-            0.0
+        let mut source = ModelTokenSource {
+            engine: self,
+            model,
+            tokenizer,
+            config,
+            rng: StdRng::seed_from_u64(config.seed),
         };
-
-        Ok(GenerationResult {
-            text: generated_text,
-            tokens_generated,
-            time_ms: elapsed.as_millis(),
-            tokens_per_second,
-            stop_reason: if user_stopped { StopReason::MaxTokens } else { stop_reason },
-        })
+        run_generation(
+            &mut source,
+            tokens,
+            max_tokens,
+            &stop_sequences,
+            grammar.as_ref(),
+            cancel,
+            &mut on_token,
+        )
     }
 
     /// Update generation configuration
@@ -374,14 +525,32 @@ impl GGUFInferenceEngine {
     }
 
     /// Get current configuration
-    #[allow(dead_code)] // Part of public API for config inspection
     pub async fn get_config(&self) -> GGUFInferenceConfig {
         self.config.read().await.clone()
     }
 
-    /// Sample next token with temperature, top-k, top-p
-    fn sample_token(&self, logits: &Tensor, config: &GGUFInferenceConfig) -> Result<u32> {
-        let logits = logits.to_vec1::<f32>()?;
+    /// Sample next token with logit bias, temperature, top-k, top-p, among
+    /// the tokens `allowed` accepts if given; banned tokens are never sampled
+    fn sample_token(
+        &self,
+        logits: &Tensor,
+        config: &GGUFInferenceConfig,
+        rng: &mut StdRng,
+        allowed: Option<&mut dyn FnMut(u32) -> bool>,
+    ) -> Result<u32> {
+        let mut logits = logits.to_vec1::<f32>()?;
+
+        // Apply logit bias and bans; ids outside the vocabulary are ignored
+        for (&token, &bias) in &config.logit_bias {
+            if let Some(logit) = logits.get_mut(token as usize) {
+                *logit += bias;
+            }
+        }
+        for &token in &config.banned_tokens {
+            if let Some(logit) = logits.get_mut(token as usize) {
+                *logit = f32::NEG_INFINITY;
+            }
+        }
 
         // Apply temperature
         let logits: Vec<f32> = if config.temperature > 0.0 {
@@ -392,8 +561,24 @@ impl GGUFInferenceEngine {
 
         // Apply top-k filtering
         let mut logits_with_idx: Vec<(usize, f32)> = logits.iter().enumerate().map(|(i, &v)| (i, v)).collect();
+        logits_with_idx.retain(|&(_, v)| v > f32::NEG_INFINITY);
         logits_with_idx.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
 
+        // Mask disallowed tokens, checking from the most likely down only
+        // until top-k are found, since checking is costly for a grammar
+        if let Some(allowed) = allowed {
+            let limit = if config.top_k > 0 {
+                config.top_k as usize
+            } else {
+                usize::MAX
+            };
+            logits_with_idx = logits_with_idx
+                .into_iter()
+                .filter(|&(i, _)| allowed(i as u32))
+                .take(limit)
+                .collect();
+        }
+
         if config.top_k > 0 {
             logits_with_idx.truncate(config.top_k as usize);
         }
@@ -416,7 +601,6 @@ impl GGUFInferenceEngine {
 
         // Sample from remaining tokens
         use rand::Rng;
-        let mut rng = rand::thread_rng();
 
         if logits_with_idx.is_empty() {
             return Err(anyhow!("No valid tokens to sample from"));
@@ -436,30 +620,6 @@ impl GGUFInferenceEngine {
         Ok(logits_with_idx[0].0 as u32)
     }
 
-    /// Find stop sequence in generated text
-    fn find_stop_sequence(&self, text: &str, stop_sequences: &[String]) -> Option<(String, usize)> {
-        let mut best_match: Option<(String, usize)> = None;
-
-        for seq in stop_sequences {
-            if text.ends_with(seq) {
-                if let Some(pos) = text.rfind(seq) {
-                    if pos + seq.len() == text.len() {
-                        match &best_match {
-                            None => best_match = Some((seq.clone(), pos)),
-                            Some((prev_seq, _)) => {
-                                if seq.len() > prev_seq.len() {
-                                    best_match = Some((seq.clone(), pos));
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
-
-        best_match
-    }
-
     /// Create a fallback tokenizer when tokenizer.json is not available
     fn create_fallback_tokenizer(&self) -> Result<Tokenizer> {
         // PRODUCTION: Fail loudly instead of using broken tokenizer
@@ -516,7 +676,209 @@ mod tests {
         let engine = GGUFInferenceEngine::new().unwrap();
         assert!(!engine.is_model_loaded().await);
 
-        let result = engine.generate("Hello", 10, vec![]).await;
+        let result = engine
+            .generate(
+                "Hello",
+                10,
+                vec![],
+                None,
+                &GGUFInferenceConfig::default(),
+                &CancellationToken::new(),
+            )
+            .await;
         assert!(result.is_err());
     }
+
+    /// Yields one word per step, slowly enough to cancel part way through
+    struct SlowWords;
+
+    impl TokenSource for SlowWords {
+        fn next_token(&mut self, _context: &[u32]) -> Result<u32> {
+            std::thread::sleep(std::time::Duration::from_millis(2));
+            Ok(100)
+        }
+
+        fn decode(&self, _token: u32) -> Result<String> {
+            Ok("word ".to_string())
+        }
+    }
+
+    #[test]
+    fn test_cancel_stops_generation_mid_stream() {
+        let cancel = CancellationToken::new();
+        let (streamed_tx, streamed_rx) = std::sync::mpsc::channel();
+        let generation = {
+            let cancel = cancel.clone();
+            std::thread::spawn(move || {
+                run_generation(
+                    &mut SlowWords,
+                    vec![1],
+                    10_000,
+                    &[],
+                    None,
+                    &cancel,
+                    &mut |_| {
+                        let _ = streamed_tx.send(());
+                        true
+                    },
+                )
+            })
+        };
+
+        for _ in 0..3 {
+            streamed_rx.recv().unwrap();
+        }
+        cancel.cancel();
+        let result = generation.join().unwrap().unwrap();
+
+        assert!(matches!(result.stop_reason, StopReason::Cancelled));
+        assert!(
+            (3..10).contains(&result.tokens_generated),
+            "stopped after {} tokens",
+            result.tokens_generated
+        );
+        assert_eq!(result.text, "word ".repeat(result.tokens_generated));
+    }
+
+    /// Small vocabulary that prefers chatty, off-schema tokens when unconstrained
+    struct ChattyVocab;
+
+    const CHATTY_VOCAB: &[&str] = &[
+        "Sure", "! ", "extreme", " ", "{", "\"risk\"", ":", "\"", "medium", "}",
+    ];
+
+    impl TokenSource for ChattyVocab {
+        fn next_token(&mut self, _context: &[u32]) -> Result<u32> {
+            Ok(10)
+        }
+
+        fn decode(&self, token: u32) -> Result<String> {
+            Ok(CHATTY_VOCAB[token as usize - 10].to_string())
+        }
+
+        fn next_token_masked(
+            &mut self,
+            _context: &[u32],
+            allowed: &mut dyn FnMut(u32, &str) -> bool,
+        ) -> Result<u32> {
+            (10..10 + CHATTY_VOCAB.len() as u32)
+                .chain([EOS_TOKEN])
+                .find(|&token| allowed(token, &self.decode(token).unwrap_or_default()))
+                .ok_or_else(|| anyhow!("No valid tokens to sample from"))
+        }
+    }
+
+    #[test]
+    fn test_grammar_constrains_output_to_schema() {
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": {"risk": {"enum": ["low", "medium", "high"]}}
+        });
+        let gbnf = crate::grammar::json_schema_to_grammar(&schema).unwrap();
+        let grammar = Grammar::parse(&gbnf).unwrap();
+        let cancel = CancellationToken::new();
+
+        let free = run_generation(
+            &mut ChattyVocab,
+            vec![1],
+            5,
+            &[],
+            None,
+            &cancel,
+            &mut |_| true,
+        )
+        .unwrap();
+        assert!(serde_json::from_str::<serde_json::Value>(&free.text).is_err());
+
+        let result = run_generation(
+            &mut ChattyVocab,
+            vec![1],
+            100,
+            &[],
+            Some(&grammar),
+            &cancel,
+            &mut |_| true,
+        )
+        .unwrap();
+        assert!(matches!(result.stop_reason, StopReason::EndOfText));
+        let output: serde_json::Value = serde_json::from_str(&result.text).unwrap();
+        let risk = output["risk"].as_str().unwrap();
+        assert!(["low", "medium", "high"].contains(&risk), "{}", result.text);
+
+        let err = run_generation(
+            &mut SlowWords,
+            vec![1],
+            5,
+            &[],
+            Some(&grammar),
+            &cancel,
+            &mut |_| true,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("grammar not supported"));
+    }
+
+    #[test]
+    fn test_logit_bias_and_banned_tokens() {
+        let engine = GGUFInferenceEngine::new().unwrap();
+        let logits = Tensor::new(&[10.0f32, 0.0, 0.0, 0.0], &Device::Cpu).unwrap();
+        let mut config = GGUFInferenceConfig {
+            top_k: 0,
+            top_p: 1.0,
+            ..Default::default()
+        };
+        let mut rng = StdRng::seed_from_u64(config.seed);
+
+        config.banned_tokens = vec![0];
+        for _ in 0..50 {
+            assert_ne!(
+                engine
+                    .sample_token(&logits, &config, &mut rng, None)
+                    .unwrap(),
+                0
+            );
+        }
+
+        config.banned_tokens = vec![99];
+        config.logit_bias = HashMap::from([(0, -100.0), (3, 100.0)]);
+        for _ in 0..50 {
+            assert_eq!(
+                engine
+                    .sample_token(&logits, &config, &mut rng, None)
+                    .unwrap(),
+                3
+            );
+        }
+
+        config.banned_tokens = vec![0, 1, 2, 3];
+        assert!(engine
+            .sample_token(&logits, &config, &mut rng, None)
+            .is_err());
+    }
+
+    #[test]
+    fn test_same_seed_samples_same_tokens() {
+        let engine = GGUFInferenceEngine::new().unwrap();
+        let logits = Tensor::new(&[1.0f32, 1.0, 1.0, 1.0, 1.0, 1.0], &Device::Cpu).unwrap();
+        let config = GGUFInferenceConfig {
+            top_k: 0,
+            top_p: 1.0,
+            seed: 1234,
+            ..Default::default()
+        };
+        let sample = |seed: u64| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            (0..32)
+                .map(|_| {
+                    engine
+                        .sample_token(&logits, &config, &mut rng, None)
+                        .unwrap()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let first = sample(config.seed);
+        assert_eq!(first, sample(config.seed));
+        assert_ne!(first, sample(config.seed + 1));
+    }
 }
@@ -0,0 +1,182 @@
+/// Instruction formats of the supported model families
+///
+/// Chat-tuned models only follow instructions wrapped the way they were
+/// trained, e.g. `[INST] ... [/INST]` for Llama 2 and Mistral or
+/// `<|user|>` turns for Zephyr-style models such as TinyLlama. A prompt
+/// formatted for one family ends with the opening of the assistant's turn, so
+/// generation continues as the assistant's reply. The BOS token is left out
+/// because the tokenizer adds it when encoding the prompt.
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChatTemplate {
+    /// `[INST] <<SYS>> ... <</SYS>> ... [/INST]`
+    Llama2,
+    /// `[INST] ... [/INST]`; the system prompt leads the first instruction
+    Mistral,
+    /// `Instruct: ...\nOutput:`
+    Phi,
+    /// `<|system|>`, `<|user|>` and `<|assistant|>` turns ended by `</s>`
+    Zephyr,
+    /// `<|im_start|>role ... <|im_end|>`
+    ChatMl,
+    /// No wrapping: system prompt and messages separated by blank lines
+    #[default]
+    Raw,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChatRole {
+    User,
+    Assistant,
+}
+
+/// One message of a conversation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatTurn {
+    pub role: ChatRole,
+    pub content: String,
+}
+
+impl ChatTurn {
+    pub fn user(content: impl Into<String>) -> Self {
+        Self {
+            role: ChatRole::User,
+            content: content.into(),
+        }
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self {
+            role: ChatRole::Assistant,
+            content: content.into(),
+        }
+    }
+}
+
+impl ChatTemplate {
+    /// Prompt for the next assistant reply to `messages`
+    pub fn format_prompt(&self, system: Option<&str>, messages: &[ChatTurn]) -> String {
+        let system = system.map(str::trim).filter(|s| !s.is_empty());
+        let mut prompt = String::new();
+
+        match self {
+            ChatTemplate::Llama2 | ChatTemplate::Mistral => {
+                let mut first = true;
+                for turn in messages {
+                    match turn.role {
+                        ChatRole::User => {
+                            if !first {
+                                prompt.push_str("<s>");
+                            }
+                            prompt.push_str("[INST] ");
+                            if let (true, Some(system)) = (first, system) {
+                                if *self == ChatTemplate::Llama2 {
+                                    prompt.push_str(&format!("<<SYS>>\n{}\n<</SYS>>\n\n", system));
+                                } else {
+                                    prompt.push_str(&format!("{}\n\n", system));
+                                }
+                            }
+                            prompt.push_str(&format!("{} [/INST]", turn.content.trim()));
+                            first = false;
+                        }
+                        ChatRole::Assistant => {
+                            prompt.push_str(&format!(" {} </s>", turn.content.trim()));
+                        }
+                    }
+                }
+            }
+            ChatTemplate::Phi => {
+                if let Some(system) = system {
+                    prompt.push_str(&format!("{}\n", system));
+                }
+                for turn in messages {
+                    match turn.role {
+                        ChatRole::User => {
+                            prompt.push_str(&format!("Instruct: {}\n", turn.content.trim()))
+                        }
+                        ChatRole::Assistant => {
+                            prompt.push_str(&format!("Output: {}\n", turn.content.trim()))
+                        }
+                    }
+                }
+                prompt.push_str("Output:");
+            }
+            ChatTemplate::Zephyr => {
+                if let Some(system) = system {
+                    prompt.push_str(&format!("<|system|>\n{}</s>\n", system));
+                }
+                for turn in messages {
+                    let tag = match turn.role {
+                        ChatRole::User => "<|user|>",
+                        ChatRole::Assistant => "<|assistant|>",
+                    };
+                    prompt.push_str(&format!("{}\n{}</s>\n", tag, turn.content.trim()));
+                }
+                prompt.push_str("<|assistant|>\n");
+            }
+            ChatTemplate::ChatMl => {
+                if let Some(system) = system {
+                    prompt.push_str(&format!("<|im_start|>system\n{}<|im_end|>\n", system));
+                }
+                for turn in messages {
+                    let role = match turn.role {
+                        ChatRole::User => "user",
+                        ChatRole::Assistant => "assistant",
+                    };
+                    prompt.push_str(&format!(
+                        "<|im_start|>{}\n{}<|im_end|>\n",
+                        role,
+                        turn.content.trim()
+                    ));
+                }
+                prompt.push_str("<|im_start|>assistant\n");
+            }
+            ChatTemplate::Raw => {
+                let parts: Vec<&str> = system
+                    .into_iter()
+                    .chain(messages.iter().map(|turn| turn.content.as_str()))
+                    .collect();
+                prompt = parts.join("\n\n");
+            }
+        }
+        prompt
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_llama2_and_mistral_prompts() {
+        let conversation = [
+            ChatTurn::user("What is the notice period?"),
+            ChatTurn::assistant("30 days."),
+            ChatTurn::user("Can it be shortened?"),
+        ];
+        let system = Some("You are a legal assistant.");
+
+        assert_eq!(
+            ChatTemplate::Llama2.format_prompt(system, &conversation),
+            "[INST] <<SYS>>\nYou are a legal assistant.\n<</SYS>>\n\n\
+             What is the notice period? [/INST] 30 days. </s>\
+             <s>[INST] Can it be shortened? [/INST]"
+        );
+        assert_eq!(
+            ChatTemplate::Mistral.format_prompt(system, &conversation),
+            "[INST] You are a legal assistant.\n\nWhat is the notice period? [/INST] \
+             30 days. </s><s>[INST] Can it be shortened? [/INST]"
+        );
+        assert_eq!(
+            ChatTemplate::Mistral.format_prompt(None, &[ChatTurn::user("Summarise the lease.")]),
+            "[INST] Summarise the lease. [/INST]"
+        );
+        assert_eq!(
+            ChatTemplate::Raw.format_prompt(None, &[ChatTurn::user("Summarise the lease.")]),
+            "Summarise the lease."
+        );
+    }
+}
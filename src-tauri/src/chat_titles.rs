@@ -0,0 +1,314 @@
+/// Automatic titles for persisted chat sessions
+///
+/// After the first exchange the loaded model is asked for a short title; with
+/// no model loaded (or a failed generation) a heuristic built from the user's
+/// first message is used instead. Both messages are redacted before they reach
+/// the model, so a title never carries PII into the session list.
+use crate::chat_template::{ChatRole, ChatTurn};
+use crate::completion::{CompletionModel, CompletionOptions};
+use crate::pii_detector::PIIDetector;
+use anyhow::{anyhow, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Title a session carries until one is generated
+pub const DEFAULT_CHAT_TITLE: &str = "New Chat";
+
+/// Characters of each message included in the title prompt
+const TITLE_PROMPT_CHARS: usize = 1000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatTitleConfig {
+    /// Title sessions automatically after their first exchange
+    pub enabled: bool,
+    pub max_words: usize,
+}
+
+impl Default for ChatTitleConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_words: 6,
+        }
+    }
+}
+
+/// Tokens a title completion may run to
+const TITLE_MAX_TOKENS: usize = 24;
+
+/// Tidy a model completion into a title: first line, no quotes or label,
+/// at most `max_words` words
+fn clean_title(raw: &str, max_words: usize) -> String {
+    let line = raw
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty())
+        .unwrap_or("");
+    let line = line
+        .strip_prefix("Title:")
+        .or_else(|| line.strip_prefix("title:"))
+        .unwrap_or(line);
+    line.split_whitespace()
+        .take(max_words)
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim_matches(|c: char| c == '"' || c == '\'' || c == '*' || c == '.' || c == ':')
+        .trim()
+        .to_string()
+}
+
+/// Title from the opening words of the (redacted) user message
+pub fn heuristic_title(user_message: &str, max_words: usize) -> String {
+    let words: Vec<&str> = user_message
+        .split_whitespace()
+        // Redaction placeholders such as [EMAIL] say nothing about the topic
+        .filter(|w| !(w.starts_with('[') && w.ends_with(']')))
+        .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric()))
+        .filter(|w| !w.is_empty())
+        .take(max_words)
+        .collect();
+
+    let mut chars = words.join(" ").chars().collect::<Vec<_>>();
+    match chars.first_mut() {
+        Some(first) => {
+            *first = first.to_ascii_uppercase();
+            chars.into_iter().collect()
+        }
+        None => DEFAULT_CHAT_TITLE.to_string(),
+    }
+}
+
+fn excerpt(text: &str) -> String {
+    text.chars().take(TITLE_PROMPT_CHARS).collect()
+}
+
+/// First user message and the assistant reply that followed it
+fn first_exchange<'a>(session_id: &str, turns: &'a [ChatTurn]) -> Result<(&'a str, &'a str)> {
+    let user_index = turns
+        .iter()
+        .position(|turn| turn.role == ChatRole::User)
+        .ok_or_else(|| anyhow!("Chat session {} has no user message", session_id))?;
+    let reply = turns[user_index..]
+        .iter()
+        .find(|turn| turn.role == ChatRole::Assistant)
+        .map(|turn| turn.content.as_str())
+        .unwrap_or_default();
+    Ok((&turns[user_index].content, reply))
+}
+
+/// Generates and stores titles in the `chat_sessions` table
+pub struct ChatTitleManager {
+    db_path: PathBuf,
+    config: ChatTitleConfig,
+}
+
+impl ChatTitleManager {
+    pub fn new(db_path: PathBuf) -> Self {
+        Self {
+            db_path,
+            config: ChatTitleConfig::default(),
+        }
+    }
+
+    pub fn get_config(&self) -> ChatTitleConfig {
+        self.config.clone()
+    }
+
+    pub fn set_config(&mut self, config: ChatTitleConfig) -> Result<()> {
+        if config.max_words == 0 {
+            return Err(anyhow!("Titles need at least one word"));
+        }
+        self.config = config;
+        Ok(())
+    }
+
+    /// Title for one exchange; PII is redacted before the model sees it
+    pub async fn generate_title(
+        &self,
+        user_message: &str,
+        assistant_reply: &str,
+        model: Option<&dyn CompletionModel>,
+        detector: &PIIDetector,
+    ) -> Result<String> {
+        let user_message = detector.redact_pii(&excerpt(user_message), None).await?;
+        let assistant_reply = detector.redact_pii(&excerpt(assistant_reply), None).await?;
+
+        if let Some(model) = model {
+            let prompt = format!(
+                "Write a title of at most {} words for this conversation. \
+                Reply with the title only.\n\nUser: {}\nAssistant: {}\n\nTitle:",
+                self.config.max_words, user_message, assistant_reply
+            );
+            let options = CompletionOptions {
+                max_tokens: Some(TITLE_MAX_TOKENS),
+                ..Default::default()
+            };
+            match model.complete(&prompt, &options).await {
+                Ok(raw) => {
+                    let title = clean_title(&raw, self.config.max_words);
+                    if !title.is_empty() {
+                        return Ok(title);
+                    }
+                }
+                Err(e) => tracing::warn!(error = %e, "Title generation failed, using heuristic"),
+            }
+        }
+
+        Ok(heuristic_title(&user_message, self.config.max_words))
+    }
+
+    /// Title a session after its first exchange, unless disabled or the
+    /// session already has a title. Returns the stored title, if any.
+    pub async fn title_after_exchange(
+        &self,
+        session_id: &str,
+        user_message: &str,
+        assistant_reply: &str,
+        model: Option<&dyn CompletionModel>,
+        detector: &PIIDetector,
+    ) -> Result<Option<String>> {
+        if !self.config.enabled {
+            return Ok(None);
+        }
+        let current = self.current_title(session_id)?;
+        if !current.trim().is_empty() && current != DEFAULT_CHAT_TITLE {
+            return Ok(None);
+        }
+
+        let title = self
+            .generate_title(user_message, assistant_reply, model, detector)
+            .await?;
+        self.store_title(session_id, &title)?;
+        Ok(Some(title))
+    }
+
+    /// Retitle a session from its first exchange, replacing any title. `turns`
+    /// are the session's decrypted messages, oldest first.
+    pub async fn regenerate_title(
+        &self,
+        session_id: &str,
+        turns: &[ChatTurn],
+        model: Option<&dyn CompletionModel>,
+        detector: &PIIDetector,
+    ) -> Result<String> {
+        let (user_message, assistant_reply) = first_exchange(session_id, turns)?;
+        let title = self
+            .generate_title(user_message, assistant_reply, model, detector)
+            .await?;
+        self.store_title(session_id, &title)?;
+        Ok(title)
+    }
+
+    fn current_title(&self, session_id: &str) -> Result<String> {
+        let conn = Connection::open(&self.db_path)?;
+        conn.query_row(
+            "SELECT title FROM chat_sessions WHERE id = ?1",
+            params![session_id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .ok_or_else(|| anyhow!("Chat session not found: {}", session_id))
+    }
+
+    fn store_title(&self, session_id: &str, title: &str) -> Result<()> {
+        let conn = Connection::open(&self.db_path)?;
+        let updated = conn.execute(
+            "UPDATE chat_sessions SET title = ?1, updated_at = CURRENT_TIMESTAMP WHERE id = ?2",
+            params![title, session_id],
+        )?;
+        if updated == 0 {
+            return Err(anyhow!("Chat session not found: {}", session_id));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::completion::mock::MockCompletion;
+    use crate::pii_detector::PIIExclusionsConfig;
+
+    fn setup_db(db_path: &PathBuf) {
+        let conn = Connection::open(db_path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE chat_sessions (
+                id TEXT PRIMARY KEY,
+                title TEXT NOT NULL,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                model_used TEXT NOT NULL,
+                tags TEXT DEFAULT '[]'
+            );
+            INSERT INTO chat_sessions (id, title, model_used) VALUES ('chat1', 'New Chat', 'llama');
+            INSERT INTO chat_sessions (id, title, model_used) VALUES ('chat2', 'New Chat', 'llama');",
+        )
+        .unwrap();
+    }
+
+    fn stored_title(db_path: &PathBuf, session_id: &str) -> String {
+        Connection::open(db_path)
+            .unwrap()
+            .query_row(
+                "SELECT title FROM chat_sessions WHERE id = ?1",
+                params![session_id],
+                |row| row.get(0),
+            )
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_title_generated_after_first_exchange_and_by_heuristic() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("chats.db");
+        setup_db(&db_path);
+        let manager = ChatTitleManager::new(db_path.clone());
+        let detector = PIIDetector::with_exclusions(PIIExclusionsConfig::default());
+        let model = MockCompletion::replies(vec![
+            "\"Notice Period for Lease Termination\"\nThis conversation covers...",
+        ]);
+
+        let title = manager
+            .title_after_exchange(
+                "chat1",
+                "How much notice do I give? I'm John at john@example.com",
+                "Usually 30 days under your lease.",
+                Some(&model),
+                &detector,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            title.as_deref(),
+            Some("Notice Period for Lease Termination")
+        );
+        assert_eq!(
+            stored_title(&db_path, "chat1"),
+            "Notice Period for Lease Termination"
+        );
+        assert!(!model.prompts()[0].contains("john@example.com"));
+
+        // An existing title is left alone after later exchanges
+        let again = manager
+            .title_after_exchange("chat1", "And after?", "Same.", Some(&model), &detector)
+            .await
+            .unwrap();
+        assert_eq!(again, None);
+
+        // No model loaded: the heuristic titles the first exchange
+        let turns = [
+            ChatTurn::assistant("Hello, how can I help?"),
+            ChatTurn::user("Can my landlord keep the deposit? Email me at tenant@example.com"),
+            ChatTurn::assistant("Only for damage beyond normal wear."),
+        ];
+        let fallback = manager
+            .regenerate_title("chat2", &turns, None, &detector)
+            .await
+            .unwrap();
+        assert!(!fallback.is_empty());
+        assert_ne!(fallback, DEFAULT_CHAT_TITLE);
+        assert!(!fallback.contains("tenant@example.com"));
+        assert_eq!(stored_title(&db_path, "chat2"), fallback);
+    }
+}
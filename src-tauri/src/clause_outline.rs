@@ -0,0 +1,354 @@
+/// Clause hierarchy extraction for legal documents
+///
+/// Parses nested clause numbering (`1`, `1.1`, `1.1.a`, `(a)`, `(i)`,
+/// `Article IV`, ...) into a tree of sections with byte ranges into the
+/// original text. The tree drives the document-navigation sidebar and can
+/// scope RAG search to a single section via [`DocumentOutline::section_text`].
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+lazy_static! {
+    static ref ARTICLE_PATTERN: Regex = Regex::new(
+        r"(?i)^(article|section|clause|schedule|part)\s+(\d{1,3}|[ivxlc]{1,7})(?:[.:]?\s+(.*)|[.:]?)$"
+    )
+    .expect("Article heading regex is invalid");
+    static ref DOTTED_PATTERN: Regex =
+        Regex::new(r"^(\d{1,3}(?:\.\d{1,3})+)(?:\.([a-z])|\(([a-z])\))?\.?\s+(.*)$")
+            .expect("Dotted clause regex is invalid");
+    static ref SIMPLE_PATTERN: Regex =
+        Regex::new(r"^(\d{1,3})[.)]\s+(.*)$").expect("Simple clause regex is invalid");
+    static ref PAREN_PATTERN: Regex =
+        Regex::new(r"^\(([a-z]{1,2}|[ivxlc]{1,6}|\d{1,3})\)\s+(.*)$")
+            .expect("Parenthesised clause regex is invalid");
+    static ref LETTER_PATTERN: Regex =
+        Regex::new(r"^([a-z])\)\s+(.*)$").expect("Letter clause regex is invalid");
+}
+
+/// Maximum heading length before it is cut at the first sentence
+const MAX_HEADING_CHARS: usize = 100;
+
+/// A numbered section and its nested sub-sections
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutlineSection {
+    /// Number as written in the document, e.g. "1.1", "(a)", "Article IV"
+    pub number: String,
+    pub heading: Option<String>,
+    /// Depth in the tree, 0 for top-level sections
+    pub level: usize,
+    /// Byte offset of the section's first line
+    pub start: usize,
+    /// Byte offset where the section (including its children) ends
+    pub end: usize,
+    pub children: Vec<OutlineSection>,
+}
+
+/// Tree of numbered sections extracted from a document
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct DocumentOutline {
+    pub sections: Vec<OutlineSection>,
+    pub total_sections: usize,
+}
+
+impl DocumentOutline {
+    /// Find a section anywhere in the tree by its number
+    pub fn find(&self, number: &str) -> Option<&OutlineSection> {
+        fn walk<'a>(sections: &'a [OutlineSection], number: &str) -> Option<&'a OutlineSection> {
+            for section in sections {
+                if section.number.eq_ignore_ascii_case(number) {
+                    return Some(section);
+                }
+                if let Some(found) = walk(&section.children, number) {
+                    return Some(found);
+                }
+            }
+            None
+        }
+        walk(&self.sections, number)
+    }
+
+    /// Text of a section (including its sub-sections), for scoping search
+    pub fn section_text<'a>(&self, content: &'a str, number: &str) -> Option<&'a str> {
+        self.find(number)
+            .and_then(|section| content.get(section.start..section.end))
+    }
+}
+
+/// Numbering style used to decide whether a heading is a sibling or a child
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum NumberingStyle {
+    Article,
+    Dotted(usize),
+    ParenAlpha,
+    ParenRoman,
+    ParenNumeric,
+    LetterParen,
+}
+
+struct FlatSection {
+    number: String,
+    heading: Option<String>,
+    style: NumberingStyle,
+    start: usize,
+    end: usize,
+    parent: Option<usize>,
+}
+
+/// Extract the numbered clause hierarchy from document text
+pub fn extract_outline(content: &str) -> DocumentOutline {
+    let mut flat: Vec<FlatSection> = Vec::new();
+    // Indices into `flat` for the currently open path from root to leaf
+    let mut stack: Vec<usize> = Vec::new();
+
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+
+        let trimmed = line.trim();
+        let Some((number, style, rest)) = classify_line(trimmed, &stack, &flat) else {
+            continue;
+        };
+
+        // A style already on the open path means a sibling of that entry;
+        // an unseen style nests under the current leaf.
+        if let Some(pos) = stack.iter().rposition(|&idx| flat[idx].style == style) {
+            for &idx in &stack[pos..] {
+                flat[idx].end = line_start;
+            }
+            stack.truncate(pos);
+        } else if let NumberingStyle::Dotted(depth) = style {
+            // Shallower dotted numbering closes any deeper open clauses
+            while let Some(&top) = stack.last() {
+                match flat[top].style {
+                    NumberingStyle::Dotted(d) if d < depth => break,
+                    NumberingStyle::Article => break,
+                    _ => {
+                        flat[top].end = line_start;
+                        stack.pop();
+                    }
+                }
+            }
+        }
+
+        flat.push(FlatSection {
+            number,
+            heading: heading_from(rest),
+            style,
+            start: line_start,
+            end: content.len(),
+            parent: stack.last().copied(),
+        });
+        stack.push(flat.len() - 1);
+    }
+
+    let total_sections = flat.len();
+    DocumentOutline {
+        sections: build_tree(&flat, None, 0),
+        total_sections,
+    }
+}
+
+fn classify_line<'a>(
+    line: &'a str,
+    stack: &[usize],
+    flat: &[FlatSection],
+) -> Option<(String, NumberingStyle, &'a str)> {
+    if let Some(caps) = ARTICLE_PATTERN.captures(line) {
+        let number = format!("{} {}", &caps[1], &caps[2]);
+        return Some((
+            number,
+            NumberingStyle::Article,
+            caps.get(3).map_or("", |m| m.as_str()),
+        ));
+    }
+
+    if let Some(caps) = DOTTED_PATTERN.captures(line) {
+        let mut number = caps[1].to_string();
+        let mut depth = number.split('.').count();
+        if let Some(letter) = caps.get(2).or_else(|| caps.get(3)) {
+            number.push('.');
+            number.push_str(letter.as_str());
+            depth += 1;
+        }
+        let rest = caps.get(4).map_or("", |m| m.as_str());
+        return Some((number, NumberingStyle::Dotted(depth), rest));
+    }
+
+    if let Some(caps) = SIMPLE_PATTERN.captures(line) {
+        let rest = caps.get(2).map_or("", |m| m.as_str());
+        return Some((caps[1].to_string(), NumberingStyle::Dotted(1), rest));
+    }
+
+    if let Some(caps) = PAREN_PATTERN.captures(line) {
+        let label = &caps[1];
+        let style = if label.chars().all(|c| c.is_ascii_digit()) {
+            NumberingStyle::ParenNumeric
+        } else if is_roman(label) && !continues_alpha_sequence(label, stack, flat) {
+            NumberingStyle::ParenRoman
+        } else {
+            NumberingStyle::ParenAlpha
+        };
+        let rest = caps.get(2).map_or("", |m| m.as_str());
+        return Some((format!("({})", label), style, rest));
+    }
+
+    if let Some(caps) = LETTER_PATTERN.captures(line) {
+        let rest = caps.get(2).map_or("", |m| m.as_str());
+        return Some((format!("{})", &caps[1]), NumberingStyle::LetterParen, rest));
+    }
+
+    None
+}
+
+fn is_roman(label: &str) -> bool {
+    label
+        .chars()
+        .all(|c| matches!(c, 'i' | 'v' | 'x' | 'l' | 'c'))
+}
+
+/// `(i)` directly after `(h)` (or `(v)` after `(u)`, ...) is a letter, not a numeral
+fn continues_alpha_sequence(label: &str, stack: &[usize], flat: &[FlatSection]) -> bool {
+    let mut chars = label.chars();
+    let (Some(letter), None) = (chars.next(), chars.next()) else {
+        return false;
+    };
+    stack
+        .iter()
+        .rev()
+        .map(|&idx| &flat[idx])
+        .find(|s| s.style == NumberingStyle::ParenAlpha)
+        .and_then(|s| {
+            s.number
+                .trim_matches(|c| c == '(' || c == ')')
+                .chars()
+                .next()
+        })
+        .map(|prev| (prev as u8) + 1 == letter as u8)
+        .unwrap_or(false)
+}
+
+fn heading_from(rest: &str) -> Option<String> {
+    let rest = rest.trim();
+    if rest.is_empty() {
+        return None;
+    }
+
+    let heading = if rest.chars().count() > MAX_HEADING_CHARS {
+        match rest.find(". ") {
+            Some(pos) if pos <= MAX_HEADING_CHARS => &rest[..pos],
+            _ => {
+                let cut = rest
+                    .char_indices()
+                    .nth(MAX_HEADING_CHARS)
+                    .map(|(i, _)| i)
+                    .unwrap_or(rest.len());
+                &rest[..cut]
+            }
+        }
+    } else {
+        rest
+    };
+
+    Some(heading.trim_end_matches(['.', ':']).trim().to_string())
+}
+
+fn build_tree(flat: &[FlatSection], parent: Option<usize>, level: usize) -> Vec<OutlineSection> {
+    flat.iter()
+        .enumerate()
+        .filter(|(_, s)| s.parent == parent)
+        .map(|(idx, s)| OutlineSection {
+            number: s.number.clone(),
+            heading: s.heading.clone(),
+            level,
+            start: s.start,
+            end: s.end,
+            children: build_tree(flat, Some(idx), level + 1),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &str = "\
+SERVICES AGREEMENT
+
+ARTICLE I DEFINITIONS
+1. Interpretation
+1.1 In this Agreement the following terms apply.
+1.2 Headings are for convenience only.
+(a) references to a party include its successors;
+(b) references to a statute include amendments:
+(i) made before the date hereof; and
+(ii) made after the date hereof.
+(c) the singular includes the plural.
+2. Payment
+2.1 Fees
+2.1.a Fees are payable monthly.
+2.1.b Late fees accrue at 2%.
+ARTICLE II TERMINATION
+3. Termination for cause.
+";
+
+    #[test]
+    fn test_nested_numbering_structure() {
+        let outline = extract_outline(FIXTURE);
+        assert_eq!(outline.total_sections, 15);
+        assert_eq!(outline.sections.len(), 2);
+
+        let article_one = &outline.sections[0];
+        assert_eq!(article_one.number, "ARTICLE I");
+        assert_eq!(article_one.heading.as_deref(), Some("DEFINITIONS"));
+        let clause_numbers: Vec<&str> = article_one
+            .children
+            .iter()
+            .map(|s| s.number.as_str())
+            .collect();
+        assert_eq!(clause_numbers, vec!["1", "2"]);
+
+        let one = &article_one.children[0];
+        assert_eq!(one.heading.as_deref(), Some("Interpretation"));
+        assert_eq!(one.children.len(), 2);
+        let one_two = &one.children[1];
+        assert_eq!(one_two.number, "1.2");
+        assert_eq!(one_two.level, 2);
+
+        let paren: Vec<&str> = one_two.children.iter().map(|s| s.number.as_str()).collect();
+        assert_eq!(paren, vec!["(a)", "(b)", "(c)"]);
+        let romans: Vec<&str> = one_two.children[1]
+            .children
+            .iter()
+            .map(|s| s.number.as_str())
+            .collect();
+        assert_eq!(romans, vec!["(i)", "(ii)"]);
+
+        let two_one = &article_one.children[1].children[0];
+        assert_eq!(two_one.number, "2.1");
+        let lettered: Vec<&str> = two_one.children.iter().map(|s| s.number.as_str()).collect();
+        assert_eq!(lettered, vec!["2.1.a", "2.1.b"]);
+
+        let article_two = &outline.sections[1];
+        assert_eq!(article_two.children[0].number, "3");
+    }
+
+    #[test]
+    fn test_section_text_scopes_to_subtree() {
+        let outline = extract_outline(FIXTURE);
+        let text = outline.section_text(FIXTURE, "2.1").unwrap();
+        assert!(text.starts_with("2.1 Fees"));
+        assert!(text.contains("Late fees"));
+        assert!(!text.contains("ARTICLE II"));
+    }
+
+    #[test]
+    fn test_letter_i_after_h_is_alphabetic() {
+        let content = "1. Scope\n(g) seven\n(h) eight\n(i) nine\n";
+        let outline = extract_outline(content);
+        let children = &outline.sections[0].children;
+        assert_eq!(children.len(), 3);
+        assert_eq!(children[2].number, "(i)");
+        assert!(children[2].children.is_empty());
+    }
+}
@@ -41,8 +41,11 @@ pub use model_transparency::{
     // format_disclaimer_display, format_generic_disclaimer_display, get_high_risk_disclaimer, // Unused
     get_ai_act_disclaimer,
     get_general_disclaimer,
+    get_license_policy,
     get_model_info,
+    get_model_license,
     get_model_mappings,
     remove_model_mapping,
+    set_license_policy,
     ModelTransparencyState,
 };
@@ -1,18 +1,26 @@
 use crate::ai_transparency::{
-    DisclaimerGenerator, GenericDisclaimer, GenericDisclaimerGenerator, ModelCardFetcher,
-    ModelCardParser, ModelDisclaimer, ModelRegistry,
+    DisclaimerGenerator, GenericDisclaimer, GenericDisclaimerGenerator, LicensePolicy,
+    ModelCardFetchConfig, ModelCardFetcher, ModelCardParser, ModelDisclaimer, ModelLicense,
+    ModelRegistry,
 };
-use std::path::PathBuf;
-use std::sync::Mutex;
+use crate::llm_manager::LicenseCheck;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use tauri::State;
 use tokio::sync::Mutex as TokioMutex;
 
 pub struct ModelTransparencyState {
     fetcher: TokioMutex<ModelCardFetcher>,
-    registry: Mutex<ModelRegistry>,
-    #[allow(dead_code)]
+    registry: Arc<Mutex<ModelRegistry>>,
     cache_dir: PathBuf,
     config_path: PathBuf,
+    /// License terms parsed from fetched model cards, by HuggingFace model ID
+    licenses: Arc<Mutex<HashMap<String, ModelLicense>>>,
+    licenses_path: PathBuf,
+    license_policy: Arc<Mutex<LicensePolicy>>,
+    policy_path: PathBuf,
+    fetch_config_path: PathBuf,
 }
 
 impl ModelTransparencyState {
@@ -20,19 +28,222 @@ impl ModelTransparencyState {
         let cache_dir = app_data_dir.join("model_cards");
         let config_path = app_data_dir.join("model_mappings.json");
 
-        let fetcher = ModelCardFetcher::new(cache_dir.clone());
+        let fetch_config_path = app_data_dir.join("model_card_fetch.json");
+        let fetch_config = std::fs::read_to_string(&fetch_config_path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        let fetcher = ModelCardFetcher::with_config(cache_dir.clone(), fetch_config);
         let registry = ModelRegistry::load_from_file(config_path.clone())
             .unwrap_or_else(|_| ModelRegistry::new());
 
+        let licenses_path = app_data_dir.join("model_licenses.json");
+        let licenses = std::fs::read_to_string(&licenses_path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        let policy_path = app_data_dir.join("license_policy.json");
+        let license_policy = LicensePolicy::load_from_file(&policy_path).unwrap_or_default();
+
         Self {
             fetcher: TokioMutex::new(fetcher),
-            registry: Mutex::new(registry),
+            registry: Arc::new(Mutex::new(registry)),
             cache_dir,
             config_path,
+            licenses: Arc::new(Mutex::new(licenses)),
+            licenses_path,
+            license_policy: Arc::new(Mutex::new(license_policy)),
+            policy_path,
+            fetch_config_path,
+        }
+    }
+
+    /// HuggingFace model ID for a GGUF filename or path
+    fn resolve_model_id(&self, model_name: &str) -> Result<Option<String>, String> {
+        resolve_model_id(&self.registry, model_name)
+    }
+
+    /// Remember the license parsed from a model's card
+    fn store_license(&self, model_id: &str, license: ModelLicense) -> Result<(), String> {
+        let mut licenses = self.licenses.lock().map_err(|e| e.to_string())?;
+        if licenses.get(model_id) == Some(&license) {
+            return Ok(());
+        }
+        licenses.insert(model_id.to_string(), license);
+        let json = serde_json::to_string_pretty(&*licenses)
+            .map_err(|e| format!("Failed to serialize licenses: {}", e))?;
+        std::fs::write(&self.licenses_path, json)
+            .map_err(|e| format!("Failed to write licenses: {}", e))
+    }
+
+    /// Model information and disclaimer for a GGUF file; when the model card
+    /// cannot be fetched within the configured bounds the generic offline
+    /// disclaimer is used instead
+    pub async fn model_info(&self, filename: String) -> Result<ModelInfo, String> {
+        let display_name = {
+            let registry = self.registry.lock().map_err(|e| e.to_string())?;
+            registry.extract_model_name(&filename)
+        };
+
+        // Try to resolve model ID
+        let model_id = {
+            let registry = self.registry.lock().map_err(|e| e.to_string())?;
+            registry.resolve_model_id(&filename)
+        };
+
+        if let Some(ref model_id) = model_id {
+            // Try to fetch model card using tokio mutex (async-aware, doesn't hold lock across await)
+            let fetcher = self.fetcher.lock().await;
+            let fetcher_result = fetcher.fetch_model_card(model_id).await;
+            match fetcher_result {
+                Ok(cached_card) => {
+                    let mut model_card =
+                        ModelCardParser::parse(model_id.clone(), &cached_card.readme_content);
+                    if model_card.license.is_none() {
+                        model_card.license_terms = ModelCardParser::parse_license(
+                            &cached_card.readme_content,
+                            cached_card.metadata_license(),
+                        );
+                    }
+                    self.store_license(model_id, model_card.license_terms.clone())?;
+                    let disclaimer = DisclaimerGenerator::generate(&model_card);
+
+                    return Ok(ModelInfo {
+                        filename,
+                        display_name,
+                        model_id: Some(model_id.clone()),
+                        disclaimer: Some(disclaimer),
+                        generic_disclaimer: None,
+                    });
+                }
+                Err(_) => {
+                    // Fallback to offline disclaimer
+                    let generic_disclaimer =
+                        GenericDisclaimerGenerator::generate_offline_disclaimer(&display_name);
+
+                    return Ok(ModelInfo {
+                        filename,
+                        display_name: display_name.clone(),
+                        model_id: Some(model_id.clone()),
+                        disclaimer: None,
+                        generic_disclaimer: Some(generic_disclaimer),
+                    });
+                }
+            }
+        }
+
+        // No model ID found - use generic unknown model disclaimer
+        let generic_disclaimer = GenericDisclaimerGenerator::generate_unknown_model(&display_name);
+
+        Ok(ModelInfo {
+            filename,
+            display_name,
+            model_id: None,
+            disclaimer: None,
+            generic_disclaimer: Some(generic_disclaimer),
+        })
+    }
+
+    pub async fn fetch_config(&self) -> ModelCardFetchConfig {
+        self.fetcher.lock().await.config().clone()
+    }
+
+    pub async fn set_fetch_config(&self, config: ModelCardFetchConfig) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(&config)
+            .map_err(|e| format!("Failed to serialize fetch settings: {}", e))?;
+        std::fs::write(&self.fetch_config_path, content)
+            .map_err(|e| format!("Failed to write fetch settings: {}", e))?;
+        *self.fetcher.lock().await = ModelCardFetcher::with_config(self.cache_dir.clone(), config);
+        Ok(())
+    }
+
+    /// Forget stored licenses, custom model mappings and cached model cards
+    /// and return to the default license policy and fetch settings, deleting
+    /// their files
+    pub async fn reset_settings(&self) -> Result<(), String> {
+        {
+            let mut fetcher = self.fetcher.lock().await;
+            *fetcher = ModelCardFetcher::new(self.cache_dir.clone());
+            if self.cache_dir.exists() {
+                fetcher.clear_all_cache()?;
+            }
+        }
+        self.licenses.lock().map_err(|e| e.to_string())?.clear();
+        *self.license_policy.lock().map_err(|e| e.to_string())? = LicensePolicy::default();
+        *self.registry.lock().map_err(|e| e.to_string())? = ModelRegistry::new();
+        for path in [
+            &self.licenses_path,
+            &self.policy_path,
+            &self.config_path,
+            &self.fetch_config_path,
+        ] {
+            if path.exists() {
+                std::fs::remove_file(path)
+                    .map_err(|e| format!("Failed to delete {}: {}", path.display(), e))?;
+            }
         }
+        Ok(())
+    }
+
+    /// Check the stored license of the model at `model_path` against the
+    /// license policy. Returns a warning when the license conflicts with the
+    /// declared use case or is not known, or an error when the policy blocks
+    /// such models.
+    pub fn check_model_license(&self, model_path: &str) -> Result<Option<String>, String> {
+        check_model_license(
+            &self.registry,
+            &self.licenses,
+            &self.license_policy,
+            model_path,
+        )
+    }
+
+    /// `check_model_license` for the LLM manager to run on every download and
+    /// load, following later changes to licenses, mappings and the policy
+    pub fn license_check(&self) -> LicenseCheck {
+        let registry = self.registry.clone();
+        let licenses = self.licenses.clone();
+        let policy = self.license_policy.clone();
+        Arc::new(move |model_path: &str| {
+            check_model_license(&registry, &licenses, &policy, model_path)
+        })
     }
 }
 
+/// HuggingFace model ID for a GGUF filename or path, or the name itself when
+/// it already is one
+fn resolve_model_id(
+    registry: &Mutex<ModelRegistry>,
+    model_name: &str,
+) -> Result<Option<String>, String> {
+    let filename = Path::new(model_name)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(model_name);
+    let registry = registry.lock().map_err(|e| e.to_string())?;
+    Ok(registry
+        .resolve_model_id(filename)
+        .or_else(|| model_name.contains('/').then(|| model_name.to_string())))
+}
+
+fn check_model_license(
+    registry: &Mutex<ModelRegistry>,
+    licenses: &Mutex<HashMap<String, ModelLicense>>,
+    policy: &Mutex<LicensePolicy>,
+    model_path: &str,
+) -> Result<Option<String>, String> {
+    let model_id = resolve_model_id(registry, model_path)?;
+    let license = match &model_id {
+        Some(model_id) => {
+            let licenses = licenses.lock().map_err(|e| e.to_string())?;
+            licenses.get(model_id).cloned()
+        }
+        None => None,
+    };
+    let policy = policy.lock().map_err(|e| e.to_string())?;
+    policy.check(model_path, model_id.as_deref(), license.as_ref())
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct ModelInfo {
     pub filename: String,
@@ -48,61 +259,72 @@ pub async fn get_model_info(
     filename: String,
     state: State<'_, ModelTransparencyState>,
 ) -> Result<ModelInfo, String> {
-    let display_name = {
-        let registry = state.registry.lock().map_err(|e| e.to_string())?;
-        registry.extract_model_name(&filename)
-    };
+    state.model_info(filename).await
+}
+
+/// Get the timeout, size cap and cache TTL applied to model card fetches
+#[tauri::command]
+pub async fn get_model_card_fetch_config(
+    state: State<'_, ModelTransparencyState>,
+) -> Result<ModelCardFetchConfig, String> {
+    Ok(state.fetch_config().await)
+}
+
+/// Update the timeout, size cap and cache TTL applied to model card fetches
+#[tauri::command]
+pub async fn set_model_card_fetch_config(
+    config: ModelCardFetchConfig,
+    state: State<'_, ModelTransparencyState>,
+) -> Result<(), String> {
+    state.set_fetch_config(config).await
+}
 
-    // Try to resolve model ID
-    let model_id = {
-        let registry = state.registry.lock().map_err(|e| e.to_string())?;
-        registry.resolve_model_id(&filename)
+/// Get the license terms of a model, fetching its card when needed
+#[tauri::command]
+pub async fn get_model_license(
+    model_name: String,
+    state: State<'_, ModelTransparencyState>,
+) -> Result<Option<ModelLicense>, String> {
+    let Some(model_id) = state.resolve_model_id(&model_name)? else {
+        return Ok(None);
     };
 
-    if let Some(ref model_id) = model_id {
-        // Try to fetch model card using tokio mutex (async-aware, doesn't hold lock across await)
-        let fetcher = state.fetcher.lock().await;
-        let fetcher_result = fetcher.fetch_model_card(model_id).await;
-        match fetcher_result {
-            Ok(cached_card) => {
-                let model_card =
-                    ModelCardParser::parse(model_id.clone(), &cached_card.readme_content);
-                let disclaimer = DisclaimerGenerator::generate(&model_card);
-
-                return Ok(ModelInfo {
-                    filename,
-                    display_name,
-                    model_id: Some(model_id.clone()),
-                    disclaimer: Some(disclaimer),
-                    generic_disclaimer: None,
-                });
-            }
-            Err(_) => {
-                // Fallback to offline disclaimer
-                let generic_disclaimer =
-                    GenericDisclaimerGenerator::generate_offline_disclaimer(&display_name);
-
-                return Ok(ModelInfo {
-                    filename,
-                    display_name: display_name.clone(),
-                    model_id: Some(model_id.clone()),
-                    disclaimer: None,
-                    generic_disclaimer: Some(generic_disclaimer),
-                });
-            }
+    let fetcher = state.fetcher.lock().await;
+    match fetcher.fetch_model_card(&model_id).await {
+        Ok(cached_card) => {
+            let license = ModelCardParser::parse_license(
+                &cached_card.readme_content,
+                cached_card.metadata_license(),
+            );
+            state.store_license(&model_id, license.clone())?;
+            Ok(Some(license))
+        }
+        Err(e) => {
+            // Offline: fall back to the terms stored when the card was last seen
+            let licenses = state.licenses.lock().map_err(|e| e.to_string())?;
+            licenses.get(&model_id).cloned().map(Some).ok_or(e)
         }
     }
+}
 
-    // No model ID found - use generic unknown model disclaimer
-    let generic_disclaimer = GenericDisclaimerGenerator::generate_unknown_model(&display_name);
+/// Get the declared use case and whether conflicting licenses block loading
+#[tauri::command]
+pub async fn get_license_policy(
+    state: State<'_, ModelTransparencyState>,
+) -> Result<LicensePolicy, String> {
+    let policy = state.license_policy.lock().map_err(|e| e.to_string())?;
+    Ok(policy.clone())
+}
 
-    Ok(ModelInfo {
-        filename,
-        display_name,
-        model_id: None,
-        disclaimer: None,
-        generic_disclaimer: Some(generic_disclaimer),
-    })
+/// Update the license policy
+#[tauri::command]
+pub async fn set_license_policy(
+    policy: LicensePolicy,
+    state: State<'_, ModelTransparencyState>,
+) -> Result<(), String> {
+    policy.save_to_file(&state.policy_path)?;
+    *state.license_policy.lock().map_err(|e| e.to_string())? = policy;
+    Ok(())
 }
 
 /// Add custom model mapping
@@ -188,3 +410,63 @@ pub async fn format_generic_disclaimer_display(
 ) -> Result<String, String> {
     Ok(GenericDisclaimerGenerator::format_for_display(&disclaimer))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    /// Hub URL on which nothing is listening, so every fetch fails at once
+    fn unreachable_hub() -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        format!("http://{}", listener.local_addr().unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_failed_card_fetch_falls_back_to_generic_disclaimer() {
+        let temp_dir = tempdir().unwrap();
+        let state = ModelTransparencyState::new(temp_dir.path().to_path_buf());
+        let config = ModelCardFetchConfig {
+            timeout_secs: 1,
+            hub_url: unreachable_hub(),
+            ..ModelCardFetchConfig::default()
+        };
+        state.set_fetch_config(config).await.unwrap();
+
+        let info = state
+            .model_info("llama-2-7b-chat.Q4_K_M.gguf".to_string())
+            .await
+            .unwrap();
+        assert!(info.model_id.is_some());
+        assert!(info.disclaimer.is_none());
+        let generic = info.generic_disclaimer.expect("offline disclaimer");
+        let expected = GenericDisclaimerGenerator::generate_offline_disclaimer(&info.display_name);
+        assert_eq!(generic.title, expected.title);
+        assert_eq!(generic.message, expected.message);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_config_survives_restart_until_reset() {
+        let temp_dir = tempdir().unwrap();
+        let state = ModelTransparencyState::new(temp_dir.path().to_path_buf());
+        let config = ModelCardFetchConfig {
+            timeout_secs: 3,
+            max_response_bytes: 4096,
+            ..ModelCardFetchConfig::default()
+        };
+        state.set_fetch_config(config).await.unwrap();
+
+        let restarted = ModelTransparencyState::new(temp_dir.path().to_path_buf());
+        let loaded = restarted.fetch_config().await;
+        assert_eq!(loaded.timeout_secs, 3);
+        assert_eq!(loaded.max_response_bytes, 4096);
+
+        restarted.reset_settings().await.unwrap();
+        let reset = ModelTransparencyState::new(temp_dir.path().to_path_buf());
+        let defaults = ModelCardFetchConfig::default();
+        assert_eq!(
+            reset.fetch_config().await.timeout_secs,
+            defaults.timeout_secs
+        );
+    }
+}
@@ -1,5 +1,7 @@
+use crate::scheduler::compliance_snapshot::{SnapshotResult, SnapshotScheduleConfig};
 use crate::scheduler::retention_tasks::{CleanupPreview, RetentionCleanupTask};
 use crate::scheduler::{CleanupResult, ScheduleConfig, SchedulerHandle, SchedulerStatus};
+use chrono::{DateTime, Utc};
 use std::path::PathBuf;
 use std::sync::Arc;
 use tauri::State;
@@ -21,6 +23,55 @@ pub async fn trigger_retention_cleanup(
     }
 }
 
+/// Schedule a one-off retention cleanup at a chosen time (UTC)
+#[tauri::command]
+pub async fn schedule_retention_cleanup(
+    scheduler_handle: State<'_, Option<Arc<RwLock<SchedulerHandle>>>>,
+    at: DateTime<Utc>,
+) -> Result<String, String> {
+    if let Some(handle_arc) = scheduler_handle.inner() {
+        let handle = handle_arc.read().await;
+        handle
+            .run_at(at)
+            .map(|_| format!("Cleanup scheduled for {}", at.to_rfc3339()))
+            .map_err(|e| format!("Failed to schedule cleanup: {}", e))
+    } else {
+        Err("Scheduler not initialized".to_string())
+    }
+}
+
+/// Pause scheduled cleanups, keeping the configured schedule
+#[tauri::command]
+pub async fn pause_retention_scheduler(
+    scheduler_handle: State<'_, Option<Arc<RwLock<SchedulerHandle>>>>,
+) -> Result<String, String> {
+    if let Some(handle_arc) = scheduler_handle.inner() {
+        let handle = handle_arc.read().await;
+        handle
+            .pause()
+            .map(|_| "Scheduled cleanups paused".to_string())
+            .map_err(|e| format!("Failed to pause scheduler: {}", e))
+    } else {
+        Err("Scheduler not initialized".to_string())
+    }
+}
+
+/// Resume scheduled cleanups
+#[tauri::command]
+pub async fn resume_retention_scheduler(
+    scheduler_handle: State<'_, Option<Arc<RwLock<SchedulerHandle>>>>,
+) -> Result<String, String> {
+    if let Some(handle_arc) = scheduler_handle.inner() {
+        let handle = handle_arc.read().await;
+        handle
+            .resume()
+            .map(|_| "Scheduled cleanups resumed".to_string())
+            .map_err(|e| format!("Failed to resume scheduler: {}", e))
+    } else {
+        Err("Scheduler not initialized".to_string())
+    }
+}
+
 /// Get scheduler status
 #[tauri::command]
 pub async fn get_scheduler_status(
@@ -51,13 +102,14 @@ pub async fn update_scheduler_config(
     }
 }
 
-/// Preview what would be deleted in next cleanup
+/// Preview the documents, chat sessions and records the next cleanup would
+/// delete, without deleting anything
 #[tauri::command]
 pub async fn preview_retention_cleanup(
     db_path: State<'_, PathBuf>,
 ) -> Result<CleanupPreview, String> {
     let task = RetentionCleanupTask::new(db_path.inner().clone());
-    task.preview_cleanup()
+    task.execute_preview()
         .await
         .map_err(|e| format!("Failed to preview cleanup: {}", e))
 }
@@ -76,17 +128,21 @@ pub async fn get_last_cleanup_result(
     }
 }
 
-/// Enable or disable automatic cleanup
+/// Enable or disable automatic cleanup, every `interval_hours` or at the
+/// times of a cron expression
 #[tauri::command]
 pub async fn set_automatic_cleanup(
     scheduler_handle: State<'_, Option<Arc<RwLock<SchedulerHandle>>>>,
     enabled: bool,
     interval_hours: Option<u64>,
+    cron: Option<String>,
 ) -> Result<String, String> {
     if let Some(handle_arc) = scheduler_handle.inner() {
         let handle = handle_arc.read().await;
+        let cron = cron.filter(|expression| !expression.trim().is_empty());
         let config = ScheduleConfig {
             interval_hours: interval_hours.unwrap_or(24),
+            cron: cron.clone(),
             enabled,
             next_run: None,
         };
@@ -94,13 +150,15 @@ pub async fn set_automatic_cleanup(
         handle
             .update_config(config)
             .map(|_| {
-                if enabled {
+                if !enabled {
+                    "Automatic cleanup disabled".to_string()
+                } else if let Some(expression) = &cron {
+                    format!("Automatic cleanup enabled (cron '{}', UTC)", expression)
+                } else {
                     format!(
                         "Automatic cleanup enabled (every {} hours)",
                         interval_hours.unwrap_or(24)
                     )
-                } else {
-                    "Automatic cleanup disabled".to_string()
                 }
             })
             .map_err(|e| format!("Failed to set automatic cleanup: {}", e))
@@ -109,6 +167,76 @@ pub async fn set_automatic_cleanup(
     }
 }
 
+/// Enable or disable periodic compliance report snapshots
+#[tauri::command]
+pub async fn set_compliance_snapshot_schedule(
+    scheduler_handle: State<'_, Option<Arc<RwLock<SchedulerHandle>>>>,
+    enabled: bool,
+    interval_hours: Option<u64>,
+    keep_last: Option<usize>,
+    encrypt: Option<bool>,
+) -> Result<String, String> {
+    if let Some(handle_arc) = scheduler_handle.inner() {
+        let handle = handle_arc.read().await;
+        let defaults = SnapshotScheduleConfig::default();
+        let config = SnapshotScheduleConfig {
+            interval_hours: interval_hours.unwrap_or(defaults.interval_hours).max(1),
+            enabled,
+            keep_last: keep_last.unwrap_or(defaults.keep_last).max(1),
+            encrypt: encrypt.unwrap_or(defaults.encrypt),
+            user_id: defaults.user_id,
+        };
+        let interval = config.interval_hours;
+        let keep = config.keep_last;
+
+        handle
+            .update_snapshot_config(config)
+            .map(|_| {
+                if enabled {
+                    format!(
+                        "Compliance snapshots enabled (every {} hours, keeping last {})",
+                        interval, keep
+                    )
+                } else {
+                    "Compliance snapshots disabled".to_string()
+                }
+            })
+            .map_err(|e| format!("Failed to set compliance snapshot schedule: {}", e))
+    } else {
+        Err("Scheduler not initialized".to_string())
+    }
+}
+
+/// Trigger a compliance snapshot immediately
+#[tauri::command]
+pub async fn trigger_compliance_snapshot(
+    scheduler_handle: State<'_, Option<Arc<RwLock<SchedulerHandle>>>>,
+) -> Result<String, String> {
+    if let Some(handle_arc) = scheduler_handle.inner() {
+        let handle = handle_arc.read().await;
+        handle
+            .trigger_snapshot()
+            .map(|_| "Compliance snapshot triggered successfully".to_string())
+            .map_err(|e| format!("Failed to trigger compliance snapshot: {}", e))
+    } else {
+        Err("Scheduler not initialized".to_string())
+    }
+}
+
+/// Get last compliance snapshot result
+#[tauri::command]
+pub async fn get_last_snapshot_result(
+    scheduler_handle: State<'_, Option<Arc<RwLock<SchedulerHandle>>>>,
+) -> Result<Option<SnapshotResult>, String> {
+    if let Some(handle_arc) = scheduler_handle.inner() {
+        let handle = handle_arc.read().await;
+        let status = handle.get_status().await;
+        Ok(status.last_snapshot_result)
+    } else {
+        Err("Scheduler not initialized".to_string())
+    }
+}
+
 /// Apply default retention policies (stub for compilation)
 #[tauri::command]
 pub async fn apply_default_retention_policies() -> Result<String, String> {
@@ -2,7 +2,7 @@
 ///
 /// Exposes transparency functionality to the frontend application.
 use crate::ai_transparency::{
-    confidence::{ConfidenceFactors, ConfidenceScore},
+    confidence::{ConfidenceBreakdown, ConfidenceFactors, ConfidenceSignals},
     notices::NoticeTemplates,
     RiskLevel, TransparencyContext, TransparencyPreferences,
 };
@@ -48,6 +48,31 @@ pub struct ResponseMetadata {
     pub has_citations: bool,
     pub context_tokens: usize,
     pub coherence_score: Option<f32>,
+    /// Share of answer sentences backed by sources, e.g. from sentence attribution
+    #[serde(default)]
+    pub retrieval_support: Option<f32>,
+    /// Agreement between generations, e.g. from the second-opinion ensemble
+    #[serde(default)]
+    pub self_consistency: Option<f32>,
+    #[serde(default)]
+    pub risk_level: Option<RiskLevel>,
+}
+
+impl ResponseMetadata {
+    /// Fill unmeasured signals; retrieval support is estimated from citations
+    pub fn signals(&self) -> ConfidenceSignals {
+        let cited_support = match (self.context_tokens, self.has_citations) {
+            (0, _) => 0.0, // Nothing was retrieved
+            (_, true) => 0.6,
+            (_, false) => 0.3,
+        };
+        ConfidenceSignals {
+            retrieval_support: self.retrieval_support.unwrap_or(cited_support),
+            self_consistency: self.self_consistency.unwrap_or(0.6),
+            risk_level: self.risk_level.unwrap_or(RiskLevel::Limited),
+            token_count: self.token_count,
+        }
+    }
 }
 
 /// Get startup disclaimer notice
@@ -117,19 +142,20 @@ pub async fn get_transparency_notice(context: TransparencyContext) -> Result<Str
     Ok(context.get_notice())
 }
 
-/// Calculate confidence score for a response
+/// Calculate confidence score for a response, with the factors behind it
 #[tauri::command]
 pub async fn calculate_confidence_score(
+    state: tauri::State<'_, TransparencyState>,
     metadata: ResponseMetadata,
-) -> Result<ConfidenceScore, String> {
+) -> Result<ConfidenceBreakdown, String> {
+    let weights = state.preferences.read().await.confidence_weights.clone();
     let factors = ConfidenceFactors::from_response_metadata(
         metadata.token_count,
         metadata.has_citations,
         metadata.context_tokens,
         metadata.coherence_score,
     );
-
-    Ok(ConfidenceScore::new(factors))
+    Ok(ConfidenceBreakdown::new(&metadata.signals(), &weights).with_factors(factors))
 }
 
 /// Get user transparency preferences
@@ -208,6 +234,7 @@ pub async fn export_transparency_context(context: TransparencyContext) -> Result
 #[cfg(test)]
 mod command_tests {
     use super::*;
+    use tauri::Manager;
 
     #[tokio::test]
     async fn test_create_transparency_context() {
@@ -233,14 +260,26 @@ mod command_tests {
             has_citations: true,
             context_tokens: 800,
             coherence_score: Some(0.8),
+            retrieval_support: None,
+            self_consistency: None,
+            risk_level: None,
         };
 
-        let result = calculate_confidence_score(metadata).await;
-        assert!(result.is_ok());
-
-        let score = result.unwrap();
+        let app = tauri::test::mock_app();
+        app.manage(TransparencyState::new());
+        let score = calculate_confidence_score(app.state(), metadata)
+            .await
+            .unwrap();
         assert!(score.overall > 0.0);
         assert!(score.overall <= 1.0);
+        assert_eq!(score.score(), score.overall);
+        assert_eq!(score.components.len(), 4);
+
+        // The scalar and factor fields of the old response are still there
+        let json = serde_json::to_value(&score).unwrap();
+        assert!(json["overall"].is_number());
+        assert!(json["factors"]["coherence"].is_number());
+        assert!(json["explanation"].is_string());
     }
 
     #[tokio::test]
@@ -0,0 +1,96 @@
+/// One-shot text completion shared by the features that prompt the loaded model
+///
+/// Structured extraction, chat titles, query expansion and the agent loop each
+/// build their own prompt and need only a reply. They take a `CompletionModel`
+/// rather than the `LLMManager` itself, state the few generation settings they
+/// care about in `CompletionOptions`, and can be tested with one mock.
+use crate::llm_manager::LLMManager;
+use anyhow::Result;
+use async_trait::async_trait;
+
+/// Adjustments a caller makes to the user's generation config for one completion
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CompletionOptions {
+    /// Replaces the configured token limit
+    pub max_tokens: Option<usize>,
+    /// Upper bound on the configured temperature
+    pub max_temperature: Option<f32>,
+    /// Appended to the configured stop sequences
+    pub stop_sequences: Vec<String>,
+}
+
+/// Text completion of a single prompt
+#[async_trait]
+pub trait CompletionModel: Send + Sync {
+    async fn complete(&self, prompt: &str, options: &CompletionOptions) -> Result<String>;
+}
+
+#[async_trait]
+impl CompletionModel for LLMManager {
+    async fn complete(&self, prompt: &str, options: &CompletionOptions) -> Result<String> {
+        let mut config = self.get_generation_config().await;
+        if let Some(max_tokens) = options.max_tokens {
+            config.max_tokens = max_tokens;
+        }
+        if let Some(max_temperature) = options.max_temperature {
+            config.temperature = config.temperature.min(max_temperature);
+        }
+        for stop in &options.stop_sequences {
+            if !config.stop_sequences.contains(stop) {
+                config.stop_sequences.push(stop.clone());
+            }
+        }
+        Ok(self.generate(prompt, Some(config)).await?.text)
+    }
+}
+
+#[cfg(test)]
+pub mod mock {
+    use super::*;
+    use std::sync::Mutex;
+
+    type Respond = dyn Fn(&str, usize) -> Result<String> + Send + Sync;
+
+    /// Answers each prompt through a closure given the prompt and the number of
+    /// earlier calls, recording every prompt and its options
+    pub struct MockCompletion {
+        respond: Box<Respond>,
+        pub calls: Mutex<Vec<(String, CompletionOptions)>>,
+    }
+
+    impl MockCompletion {
+        pub fn new(
+            respond: impl Fn(&str, usize) -> Result<String> + Send + Sync + 'static,
+        ) -> Self {
+            Self {
+                respond: Box::new(respond),
+                calls: Mutex::new(Vec::new()),
+            }
+        }
+
+        /// Replies with `replies` in order, repeating the last one once they run out
+        pub fn replies(replies: Vec<&'static str>) -> Self {
+            Self::new(move |_, call| {
+                let reply = replies.get(call).or(replies.last()).copied();
+                Ok(reply.unwrap_or_default().to_string())
+            })
+        }
+
+        pub fn prompts(&self) -> Vec<String> {
+            let calls = self.calls.lock().unwrap();
+            calls.iter().map(|(prompt, _)| prompt.clone()).collect()
+        }
+    }
+
+    #[async_trait]
+    impl CompletionModel for MockCompletion {
+        async fn complete(&self, prompt: &str, options: &CompletionOptions) -> Result<String> {
+            let call = {
+                let mut calls = self.calls.lock().unwrap();
+                calls.push((prompt.to_string(), options.clone()));
+                calls.len() - 1
+            };
+            (self.respond)(prompt, call)
+        }
+    }
+}
@@ -1,9 +1,26 @@
 #![allow(dead_code)]
 use anyhow::Result;
 use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
 use rusqlite::{params, Connection};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use tokio::sync::broadcast;
+
+/// Events buffered per live subscriber; one that falls further behind skips
+/// the oldest instead of holding up logging
+pub const AUDIT_EVENT_BUFFER: usize = 256;
+
+lazy_static! {
+    /// Shared by every logger, so subscribers see entries from all subsystems
+    static ref AUDIT_EVENTS: broadcast::Sender<AuditEvent> =
+        broadcast::channel(AUDIT_EVENT_BUFFER).0;
+}
+
+/// Receive every audit entry written from now on
+pub fn subscribe_audit_events() -> broadcast::Receiver<AuditEvent> {
+    AUDIT_EVENTS.subscribe()
+}
 
 /// Audit action types for GDPR compliance
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,6 +35,10 @@ pub enum AuditAction {
     UserLogin,
     UserLogout,
     SettingChanged,
+    GenerationFailed,
+    RedactionSubmitted,
+    RedactionApproved,
+    RedactionRejected,
 }
 
 impl AuditAction {
@@ -32,6 +53,10 @@ impl AuditAction {
             AuditAction::UserLogin => "user_login",
             AuditAction::UserLogout => "user_logout",
             AuditAction::SettingChanged => "setting_changed",
+            AuditAction::GenerationFailed => "generation_failed",
+            AuditAction::RedactionSubmitted => "redaction_submitted",
+            AuditAction::RedactionApproved => "redaction_approved",
+            AuditAction::RedactionRejected => "redaction_rejected",
         }
     }
 }
@@ -75,6 +100,39 @@ pub struct AuditLogEntry {
     pub error_message: Option<String>,
 }
 
+/// Audit entry as streamed to live subscribers. Details and error messages
+/// can carry PII, so only the names of the detail fields are included.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEvent {
+    pub id: i64,
+    pub timestamp: DateTime<Utc>,
+    pub user_id: String,
+    pub action_type: AuditAction,
+    pub entity_type: EntityType,
+    pub entity_id: Option<String>,
+    pub success: bool,
+    pub detail_fields: Vec<String>,
+}
+
+/// Restricts a live audit subscription; unset fields match everything
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuditEventFilter {
+    pub action_type: Option<String>,
+    pub user_id: Option<String>,
+}
+
+impl AuditEventFilter {
+    pub fn matches(&self, event: &AuditEvent) -> bool {
+        self.action_type
+            .as_deref()
+            .is_none_or(|action| action == event.action_type.as_str())
+            && self
+                .user_id
+                .as_deref()
+                .is_none_or(|user| user == event.user_id)
+    }
+}
+
 /// Audit query filters
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuditQuery {
@@ -115,13 +173,7 @@ impl AuditLogger {
 
         // Run audit log migration
         let migration = include_str!("../../migrations/004_create_audit_log.sql");
-
-        for statement in migration.split(';') {
-            let trimmed = statement.trim();
-            if !trimmed.is_empty() && !trimmed.starts_with("--") {
-                conn.execute(trimmed, [])?;
-            }
-        }
+        super::run_migration(&conn, migration)?;
 
         Ok(())
     }
@@ -140,6 +192,11 @@ impl AuditLogger {
     ) -> Result<i64> {
         let conn = Connection::open(&self.db_path)?;
 
+        let detail_fields = details
+            .as_ref()
+            .and_then(|d| d.as_object())
+            .map(|fields| fields.keys().cloned().collect())
+            .unwrap_or_default();
         let details_json = details.map(|d| d.to_string());
 
         conn.execute(
@@ -155,8 +212,21 @@ impl AuditLogger {
                 error_message
             ],
         )?;
+        let id = conn.last_insert_rowid();
+
+        // No receivers is the normal case, not an error
+        let _ = AUDIT_EVENTS.send(AuditEvent {
+            id,
+            timestamp: Utc::now(),
+            user_id: user_id.to_string(),
+            action_type: action,
+            entity_type,
+            entity_id: entity_id.map(str::to_string),
+            success,
+            detail_fields,
+        });
 
-        Ok(conn.last_insert_rowid())
+        Ok(id)
     }
 
     /// Log successful action (convenience method)
@@ -458,4 +528,57 @@ mod tests {
         // Cleanup
         let _ = std::fs::remove_file(db_path);
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_subscriber_receives_logged_action_without_details() {
+        let db_path = get_test_db();
+        let logger = AuditLogger::new(db_path.clone());
+        logger.initialize().unwrap();
+        let mut events = subscribe_audit_events();
+
+        // Other tests log concurrently, so filter on a user unique to this one
+        let user_id = format!("tail_{}", uuid::Uuid::new_v4());
+        let filter = AuditEventFilter {
+            action_type: Some("data_exported".to_string()),
+            user_id: Some(user_id.clone()),
+        };
+        logger
+            .log_success(
+                &user_id,
+                AuditAction::DataAccessed,
+                EntityType::Document,
+                Some("doc_1"),
+                None,
+            )
+            .unwrap();
+        logger
+            .log_success(
+                &user_id,
+                AuditAction::DataExported,
+                EntityType::Document,
+                Some("doc_1"),
+                Some(serde_json::json!({"recipient": "jane@example.com"})),
+            )
+            .unwrap();
+
+        let event = tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            loop {
+                let event = events.recv().await.unwrap();
+                if filter.matches(&event) {
+                    break event;
+                }
+            }
+        })
+        .await
+        .expect("audit event was not emitted");
+
+        assert!(matches!(event.action_type, AuditAction::DataExported));
+        assert_eq!(event.entity_id.as_deref(), Some("doc_1"));
+        assert_eq!(event.detail_fields, vec!["recipient"]);
+        assert!(!serde_json::to_string(&event)
+            .unwrap()
+            .contains("jane@example.com"));
+
+        let _ = std::fs::remove_file(db_path);
+    }
+}
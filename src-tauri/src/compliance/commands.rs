@@ -165,6 +165,38 @@ pub async fn set_data_retention(
     Ok(true)
 }
 
+/// Set the retention period of a data category (document, chat_message or
+/// query_history)
+#[tauri::command]
+pub async fn set_category_retention_policy(
+    compliance: State<'_, ComplianceManager>,
+    entity_type: EntityType,
+    retention_days: i64,
+    auto_delete: bool,
+) -> Result<JsonValue, String> {
+    let retention_lock = compliance.retention();
+    let retention_mgr = retention_lock.write().await;
+    let policy = retention_mgr
+        .set_category_policy(&entity_type, retention_days, auto_delete)
+        .map_err(|e| e.to_string())?;
+
+    Ok(serde_json::to_value(policy).unwrap())
+}
+
+/// Get the retention policies set per data category
+#[tauri::command]
+pub async fn get_category_retention_policies(
+    compliance: State<'_, ComplianceManager>,
+) -> Result<JsonValue, String> {
+    let retention_lock = compliance.retention();
+    let retention_mgr = retention_lock.read().await;
+    let policies = retention_mgr
+        .get_category_policies()
+        .map_err(|e| e.to_string())?;
+
+    Ok(serde_json::to_value(policies).unwrap())
+}
+
 /// Get retention statistics
 #[tauri::command]
 pub async fn get_retention_stats(
@@ -308,62 +340,31 @@ pub async fn run_compliance_maintenance(
 }
 
 /// GDPR Article 16 - Right to Rectification
-/// Allows users to update their personal data
+/// Corrects one rectifiable field of the user's stored data; old and new values are audited
 #[tauri::command]
 pub async fn update_user_data(
     compliance: State<'_, ComplianceManager>,
     user_id: String,
     data_type: String,
     entity_id: String,
+    field: String,
     updated_content: String,
 ) -> Result<JsonValue, String> {
-    // Validate data type
-    let valid_types = vec!["chat", "document", "setting"];
-    if !valid_types.contains(&data_type.as_str()) {
-        return Err(format!(
-            "Invalid data type. Must be one of: {:?}",
-            valid_types
-        ));
-    }
-
-    // Validate content is not empty or malicious
-    if updated_content.trim().is_empty() {
-        return Err("Updated content cannot be empty".to_string());
-    }
-
-    if updated_content.len() > 1_000_000 {
-        return Err("Updated content exceeds maximum size of 1MB".to_string());
-    }
-
-    // Log the rectification action
-    let audit_lock = compliance.audit();
-    let audit = audit_lock.write().await;
-    let log_id = audit
-        .log_success(
-            &user_id,
-            AuditAction::DataModified,
-            match data_type.as_str() {
-                "chat" => EntityType::ChatMessage,
-                "document" => EntityType::Document,
-                "setting" => EntityType::UserSetting,
-                _ => EntityType::UserSetting,
-            },
-            Some(&entity_id),
-            Some(serde_json::json!({
-                "action": "data_rectification",
-                "data_type": data_type,
-                "reason": "User exercised GDPR Article 16 - Right to Rectification"
-            })),
-        )
-        .map_err(|e| e.to_string())?;
-    drop(audit);
+    let rectification = {
+        let rectification_lock = compliance.rectification();
+        let rectification_mgr = rectification_lock.write().await;
+        rectification_mgr
+            .rectify(&user_id, &data_type, &entity_id, &field, &updated_content)
+            .map_err(|e| e.to_string())?
+    };
 
     Ok(serde_json::json!({
         "success": true,
-        "message": "Data rectification logged successfully",
-        "audit_log_id": log_id,
+        "message": "Data rectified successfully",
+        "audit_log_id": rectification.audit_log_id,
         "data_type": data_type,
         "entity_id": entity_id,
+        "field": field,
         "timestamp": chrono::Utc::now().to_rfc3339(),
         "gdpr_article": "Article 16 - Right to Rectification"
     }))
@@ -5,22 +5,63 @@
 pub mod audit;
 pub mod commands;
 pub mod consent;
+pub mod originals;
+pub mod receipts;
+pub mod rectification;
 pub mod retention;
+pub mod review;
 
 pub use audit::{AuditAction, AuditLogger, AuditQuery, EntityType};
 pub use consent::{ConsentManager, ConsentType};
+pub use originals::{OriginalErasure, OriginalRetentionPolicy, OriginalsVault};
+pub use receipts::{ProcessingReceipt, ProcessingReceiptStore};
+pub use rectification::{Rectification, RectificationManager};
 pub use retention::RetentionManager;
+pub use review::{RedactionReview, RedactionReviewManager, ReviewState};
 
 use anyhow::Result;
+use async_trait::async_trait;
+use rusqlite::Connection;
 use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+/// Run each statement of a migration script. Statements may be preceded by
+/// comment lines; fragments holding only comments are skipped.
+pub fn run_migration(conn: &Connection, migration: &str) -> Result<()> {
+    for statement in migration.split(';') {
+        let has_sql = statement.lines().any(|line| {
+            let line = line.trim();
+            !line.is_empty() && !line.starts_with("--")
+        });
+        if has_sql {
+            conn.execute(statement.trim(), [])?;
+        }
+    }
+    Ok(())
+}
+
+/// User data held outside the compliance database that erasure must also reach
+#[async_trait]
+pub trait UserDataStore: Send + Sync {
+    /// Key under which the erasure result reports this store
+    fn name(&self) -> &str;
+
+    /// Delete everything held for `user_id`, returning the number of items removed
+    async fn erase_user_data(&self, user_id: &str) -> Result<usize>;
+}
+
 /// Unified compliance manager that coordinates all GDPR features
 pub struct ComplianceManager {
     consent_manager: Arc<RwLock<ConsentManager>>,
     retention_manager: Arc<RwLock<RetentionManager>>,
     audit_logger: Arc<RwLock<AuditLogger>>,
+    review_manager: Arc<RwLock<RedactionReviewManager>>,
+    originals_vault: Arc<RwLock<OriginalsVault>>,
+    rectification_manager: Arc<RwLock<RectificationManager>>,
+    receipt_store: Arc<RwLock<ProcessingReceiptStore>>,
+    /// Stores that `delete_user_data` cascades into
+    user_data_stores: Arc<RwLock<Vec<Arc<dyn UserDataStore>>>>,
 }
 
 impl ComplianceManager {
@@ -28,7 +69,14 @@ impl ComplianceManager {
         Self {
             consent_manager: Arc::new(RwLock::new(ConsentManager::new(db_path.clone()))),
             retention_manager: Arc::new(RwLock::new(RetentionManager::new(db_path.clone()))),
-            audit_logger: Arc::new(RwLock::new(AuditLogger::new(db_path))),
+            audit_logger: Arc::new(RwLock::new(AuditLogger::new(db_path.clone()))),
+            review_manager: Arc::new(RwLock::new(RedactionReviewManager::new(db_path.clone()))),
+            originals_vault: Arc::new(RwLock::new(OriginalsVault::new(db_path.clone()))),
+            rectification_manager: Arc::new(RwLock::new(RectificationManager::new(
+                db_path.clone(),
+            ))),
+            receipt_store: Arc::new(RwLock::new(ProcessingReceiptStore::new(db_path))),
+            user_data_stores: Arc::new(RwLock::new(Vec::new())),
         }
     }
 
@@ -49,6 +97,21 @@ impl ComplianceManager {
         audit.initialize()?;
         drop(audit);
 
+        // Initialize redaction approval workflow
+        let review = self.review_manager.write().await;
+        review.initialize()?;
+        drop(review);
+
+        // Initialize encrypted originals store (legal hold / opt-in)
+        let mut originals = self.originals_vault.write().await;
+        originals.initialize()?;
+        drop(originals);
+
+        // Initialize chain-of-custody processing receipts
+        let receipts = self.receipt_store.write().await;
+        receipts.initialize()?;
+        drop(receipts);
+
         // Log initialization
         let audit = self.audit_logger.write().await;
         audit.log_success(
@@ -77,6 +140,31 @@ impl ComplianceManager {
         self.audit_logger.clone()
     }
 
+    /// Get redaction review manager
+    pub fn review(&self) -> Arc<RwLock<RedactionReviewManager>> {
+        self.review_manager.clone()
+    }
+
+    /// Get encrypted originals store
+    pub fn originals(&self) -> Arc<RwLock<OriginalsVault>> {
+        self.originals_vault.clone()
+    }
+
+    /// Get rectification manager
+    pub fn rectification(&self) -> Arc<RwLock<RectificationManager>> {
+        self.rectification_manager.clone()
+    }
+
+    /// Get processing receipt store
+    pub fn receipts(&self) -> Arc<RwLock<ProcessingReceiptStore>> {
+        self.receipt_store.clone()
+    }
+
+    /// Include `store` in user data erasure
+    pub async fn register_user_data_store(&self, store: Arc<dyn UserDataStore>) {
+        self.user_data_stores.write().await.push(store);
+    }
+
     /// Check if operation is allowed based on consent
     #[allow(dead_code)]
     pub async fn check_operation_consent(&self, user_id: &str, operation: &str) -> Result<bool> {
@@ -102,6 +190,15 @@ impl ComplianceManager {
         results.insert("retention_cleanup".to_string(), cleanup_results);
         drop(retention);
 
+        // Expire retained originals that are not under legal hold
+        let originals = self.originals_vault.write().await;
+        let expired_originals = originals.delete_expired_originals()?;
+        results.insert(
+            "originals_expired".to_string(),
+            serde_json::json!(expired_originals),
+        );
+        drop(originals);
+
         // Clean old audit logs (keep 2 years)
         let audit = self.audit_logger.write().await;
         let deleted_logs = audit.delete_old_logs(730)?;
@@ -202,8 +299,27 @@ impl ComplianceManager {
         );
         drop(consent);
 
-        // Note: Actual data deletion (documents, chats, etc.) should be handled
-        // by the application layer with appropriate cascading
+        // Erase retained originals; legal holds override erasure
+        let originals = self.originals_vault.write().await;
+        let erasure = originals.erase_user_originals(user_id)?;
+        results.insert(
+            "originals_deleted".to_string(),
+            serde_json::json!(erasure.deleted),
+        );
+        results.insert(
+            "originals_held".to_string(),
+            serde_json::json!(erasure.held),
+        );
+        drop(originals);
+
+        // Cascade into registered stores (indexed documents, ...)
+        for store in self.user_data_stores.read().await.iter() {
+            let erased = store.erase_user_data(user_id).await?;
+            results.insert(
+                format!("{}_deleted", store.name()),
+                serde_json::json!(erased),
+            );
+        }
 
         // Log deletion
         let audit = self.audit_logger.write().await;
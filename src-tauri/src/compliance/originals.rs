@@ -0,0 +1,489 @@
+use anyhow::{anyhow, Result};
+use chrono::{Duration, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use zeroize::Zeroize;
+
+use super::audit::{AuditAction, AuditLogger, EntityType};
+use super::review::RedactionReviewManager;
+use crate::security::{ChatEncryptor, EncryptedMessage, KeyManager};
+
+/// Key derivation context for original-document encryption
+const ORIGINALS_KEY_CONTEXT: &str = "document-originals";
+
+/// When pre-redaction originals are kept
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OriginalRetentionPolicy {
+    /// Explicit opt-in to keep originals for re-scans and subject-access requests
+    pub retain_originals: bool,
+    /// Active legal hold: originals are kept and exempt from retention and erasure
+    pub legal_hold: bool,
+    /// Days a retained original is kept when not under legal hold
+    pub retention_days: i64,
+}
+
+impl Default for OriginalRetentionPolicy {
+    fn default() -> Self {
+        Self {
+            retain_originals: false,
+            legal_hold: false,
+            retention_days: 90,
+        }
+    }
+}
+
+impl OriginalRetentionPolicy {
+    pub fn should_retain(&self) -> bool {
+        self.retain_originals || self.legal_hold
+    }
+}
+
+/// Outcome of erasing a user's originals
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OriginalErasure {
+    pub deleted: usize,
+    /// Originals kept because they are under legal hold (GDPR Art. 17(3)(e))
+    pub held: usize,
+}
+
+/// Encrypted store of pre-redaction document content
+pub struct OriginalsVault {
+    db_path: PathBuf,
+    audit: AuditLogger,
+    encryptor: ChatEncryptor,
+    policy: OriginalRetentionPolicy,
+    /// Loaded from the OS keychain on first use unless supplied up front
+    key: Option<Vec<u8>>,
+}
+
+impl Drop for OriginalsVault {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.as_mut() {
+            key.zeroize();
+        }
+    }
+}
+
+impl OriginalsVault {
+    pub fn new(db_path: PathBuf) -> Self {
+        Self {
+            audit: AuditLogger::new(db_path.clone()),
+            db_path,
+            encryptor: ChatEncryptor::new(),
+            policy: OriginalRetentionPolicy::default(),
+            key: None,
+        }
+    }
+
+    /// Use a fixed 32-byte key instead of the keychain-derived one
+    pub fn with_key(mut self, key: Vec<u8>) -> Self {
+        self.key = Some(key);
+        self
+    }
+
+    /// Initialize document originals tables and load the stored policy
+    pub fn initialize(&mut self) -> Result<()> {
+        let conn = Connection::open(&self.db_path)?;
+
+        let migration = include_str!("../../migrations/008_create_document_originals.sql");
+        super::run_migration(&conn, migration)?;
+
+        if let Some(policy) = conn
+            .query_row(
+                "SELECT retain_originals, legal_hold, retention_days
+                 FROM original_retention_policy WHERE id = 1",
+                [],
+                |row| {
+                    Ok(OriginalRetentionPolicy {
+                        retain_originals: row.get(0)?,
+                        legal_hold: row.get(1)?,
+                        retention_days: row.get(2)?,
+                    })
+                },
+            )
+            .optional()?
+        {
+            self.policy = policy;
+        }
+
+        Ok(())
+    }
+
+    pub fn policy(&self) -> &OriginalRetentionPolicy {
+        &self.policy
+    }
+
+    /// Replace and persist the retention policy. Placing a legal hold also holds
+    /// originals already retained; releasing it makes them expirable again.
+    pub fn set_policy(&mut self, policy: OriginalRetentionPolicy) -> Result<()> {
+        let mut conn = Connection::open(&self.db_path)?;
+        let tx = conn.transaction()?;
+        if policy.legal_hold {
+            tx.execute(
+                "UPDATE document_originals SET legal_hold = 1, retention_until = NULL
+                 WHERE legal_hold = 0",
+                [],
+            )?;
+        } else if self.policy.legal_hold {
+            tx.execute(
+                "UPDATE document_originals SET legal_hold = 0, retention_until = ?1
+                 WHERE legal_hold = 1",
+                params![(Utc::now() + Duration::days(policy.retention_days)).to_rfc3339()],
+            )?;
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO original_retention_policy
+             (id, retain_originals, legal_hold, retention_days, updated_at)
+             VALUES (1, ?1, ?2, ?3, CURRENT_TIMESTAMP)",
+            params![
+                policy.retain_originals,
+                policy.legal_hold,
+                policy.retention_days
+            ],
+        )?;
+        tx.commit()?;
+
+        self.policy = policy;
+        Ok(())
+    }
+
+    /// Encrypt and keep the original if the policy asks for it; returns whether
+    /// it was kept. An original under legal hold is never replaced.
+    pub fn retain_if_enabled(
+        &mut self,
+        document_id: &str,
+        user_id: &str,
+        original: &str,
+    ) -> Result<bool> {
+        if !self.policy.should_retain() {
+            return Ok(false);
+        }
+
+        let held: bool = Connection::open(&self.db_path)?.query_row(
+            "SELECT EXISTS(SELECT 1 FROM document_originals
+             WHERE document_id = ?1 AND legal_hold = 1)",
+            params![document_id],
+            |row| row.get(0),
+        )?;
+        if held {
+            return Err(anyhow!(
+                "The original of document {} is under legal hold and cannot be replaced",
+                document_id
+            ));
+        }
+
+        let key = self.key()?;
+        let encrypted = self.encryptor.encrypt(original, &key, user_id)?;
+        let retention_until = if self.policy.legal_hold {
+            None
+        } else {
+            Some((Utc::now() + Duration::days(self.policy.retention_days)).to_rfc3339())
+        };
+
+        let conn = Connection::open(&self.db_path)?;
+        conn.execute(
+            "INSERT OR REPLACE INTO document_originals
+             (document_id, user_id, ciphertext, nonce, legal_hold, retention_until)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                document_id,
+                user_id,
+                encrypted.ciphertext,
+                encrypted.nonce,
+                self.policy.legal_hold,
+                retention_until
+            ],
+        )?;
+
+        if let Err(e) = self.audit.log_success(
+            user_id,
+            AuditAction::DataModified,
+            EntityType::Document,
+            Some(document_id),
+            Some(serde_json::json!({
+                "action": "original_retained",
+                "legal_hold": self.policy.legal_hold
+            })),
+        ) {
+            tracing::warn!("Failed to audit original retention: {}", e);
+        }
+
+        Ok(true)
+    }
+
+    /// Decrypt a retained original, e.g. for a re-scan or subject-access
+    /// request. Only its owner and the reviewer of its redactions may read it.
+    pub fn get_original(&mut self, document_id: &str, actor: &str) -> Result<Option<String>> {
+        let conn = Connection::open(&self.db_path)?;
+        let row = conn
+            .query_row(
+                "SELECT user_id, ciphertext, nonce FROM document_originals WHERE document_id = ?1",
+                params![document_id],
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, Vec<u8>>(1)?,
+                        row.get::<_, Vec<u8>>(2)?,
+                    ))
+                },
+            )
+            .optional()?;
+
+        let Some((user_id, ciphertext, nonce)) = row else {
+            return Ok(None);
+        };
+
+        if actor != user_id && !self.is_reviewer(document_id, actor) {
+            if let Err(e) = self.audit.log_failure(
+                actor,
+                AuditAction::DataAccessed,
+                EntityType::Document,
+                Some(document_id),
+                "Original access denied: not the owner or reviewer",
+            ) {
+                tracing::warn!("Failed to audit denied original access: {}", e);
+            }
+            return Err(anyhow!(
+                "{} may not read the original of document {}",
+                actor,
+                document_id
+            ));
+        }
+
+        let key = self.key()?;
+        let original = self.encryptor.decrypt(
+            &EncryptedMessage {
+                ciphertext,
+                nonce,
+                version: 1,
+                user_id,
+            },
+            &key,
+        )?;
+
+        if let Err(e) = self.audit.log_success(
+            actor,
+            AuditAction::DataAccessed,
+            EntityType::Document,
+            Some(document_id),
+            Some(serde_json::json!({"action": "original_accessed"})),
+        ) {
+            tracing::warn!("Failed to audit original access: {}", e);
+        }
+
+        Ok(Some(original))
+    }
+
+    /// Erase a user's originals, keeping any under legal hold
+    pub fn erase_user_originals(&self, user_id: &str) -> Result<OriginalErasure> {
+        let conn = Connection::open(&self.db_path)?;
+        let deleted = conn.execute(
+            "DELETE FROM document_originals WHERE user_id = ?1 AND legal_hold = 0",
+            params![user_id],
+        )?;
+        let held = conn.query_row(
+            "SELECT COUNT(*) FROM document_originals WHERE user_id = ?1",
+            params![user_id],
+            |row| row.get::<_, i64>(0),
+        )? as usize;
+
+        Ok(OriginalErasure { deleted, held })
+    }
+
+    /// Delete originals past their retention date; held originals have none
+    pub fn delete_expired_originals(&self) -> Result<usize> {
+        let conn = Connection::open(&self.db_path)?;
+        let count = conn.execute(
+            "DELETE FROM document_originals
+             WHERE legal_hold = 0 AND retention_until IS NOT NULL AND retention_until < ?1",
+            params![Utc::now().to_rfc3339()],
+        )?;
+        Ok(count)
+    }
+
+    /// Whether `actor` reviewed the document's redactions; any failure to tell denies
+    fn is_reviewer(&self, document_id: &str, actor: &str) -> bool {
+        RedactionReviewManager::new(self.db_path.clone())
+            .get_review(document_id)
+            .ok()
+            .flatten()
+            .and_then(|review| review.reviewed_by)
+            .is_some_and(|reviewer| reviewer == actor)
+    }
+
+    fn key(&mut self) -> Result<Vec<u8>> {
+        if self.key.is_none() {
+            let key = KeyManager::new()?
+                .derive_key(ORIGINALS_KEY_CONTEXT)
+                .map_err(|e| anyhow!("Failed to load originals encryption key: {}", e))?;
+            self.key = Some(key);
+        }
+        Ok(self.key.clone().expect("key loaded above"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compliance::AuditQuery;
+    use crate::pii_detector::PIIDetector;
+    use crate::rag_engine::{EmbeddingBackend, RAGEngine};
+    use async_trait::async_trait;
+    use std::sync::Arc;
+
+    struct ConstantBackend;
+
+    #[async_trait]
+    impl EmbeddingBackend for ConstantBackend {
+        async fn embed_batch(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+            Ok(texts.iter().map(|_| vec![1.0, 0.0]).collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retained_original_is_encrypted_and_recoverable() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("originals.db");
+        let mut vault = OriginalsVault::new(db_path.clone()).with_key(vec![7u8; 32]);
+        vault.initialize().unwrap();
+
+        let original = "Client SSN 123-45-6789 must not appear in the index.";
+        let redacted = PIIDetector::new()
+            .redact_pii_with_report(original, None)
+            .await
+            .unwrap()
+            .redacted_text;
+        assert!(!redacted.contains("123-45-6789"));
+
+        // Normal flow keeps nothing
+        assert!(!vault.retain_if_enabled("doc-1", "alice", original).unwrap());
+        assert!(vault.get_original("doc-1", "alice").unwrap().is_none());
+
+        vault
+            .set_policy(OriginalRetentionPolicy {
+                retain_originals: true,
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(vault.retain_if_enabled("doc-1", "alice", original).unwrap());
+
+        let rag = RAGEngine::with_index_path(temp_dir.path().join("index"));
+        rag.set_embedding_backend(Arc::new(ConstantBackend)).await;
+        rag.add_document(&redacted, serde_json::json!({"document_id": "doc-1"}))
+            .await
+            .unwrap();
+        let indexed = rag.search("client SSN", Some(5)).await.unwrap();
+        assert!(!indexed.is_empty());
+        assert!(indexed.iter().all(|r| !r.content.contains("123-45-6789")));
+
+        // Stored bytes are ciphertext, not the original
+        let stored: Vec<u8> = Connection::open(&db_path)
+            .unwrap()
+            .query_row(
+                "SELECT ciphertext FROM document_originals WHERE document_id = 'doc-1'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!(!String::from_utf8_lossy(&stored).contains("123-45-6789"));
+
+        assert_eq!(
+            vault.get_original("doc-1", "alice").unwrap().as_deref(),
+            Some(original)
+        );
+
+        let erased = vault.erase_user_originals("alice").unwrap();
+        assert_eq!(erased.deleted, 1);
+        assert!(vault.get_original("doc-1", "alice").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_legal_hold_persists_and_covers_existing_originals() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("originals.db");
+        let mut vault = OriginalsVault::new(db_path.clone()).with_key(vec![7u8; 32]);
+        vault.initialize().unwrap();
+
+        vault
+            .set_policy(OriginalRetentionPolicy {
+                retain_originals: true,
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(vault
+            .retain_if_enabled("doc-1", "alice", "original")
+            .unwrap());
+
+        // Backdate the retained original so it would expire now
+        Connection::open(&db_path)
+            .unwrap()
+            .execute(
+                "UPDATE document_originals SET retention_until = '2000-01-01T00:00:00+00:00'",
+                [],
+            )
+            .unwrap();
+
+        vault
+            .set_policy(OriginalRetentionPolicy {
+                retain_originals: true,
+                legal_hold: true,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(vault.delete_expired_originals().unwrap(), 0);
+        assert_eq!(vault.erase_user_originals("alice").unwrap().held, 1);
+
+        // A restarted vault still has the hold
+        let mut restarted = OriginalsVault::new(db_path).with_key(vec![7u8; 32]);
+        restarted.initialize().unwrap();
+        assert!(restarted.policy().legal_hold);
+        assert!(restarted.get_original("doc-1", "alice").unwrap().is_some());
+
+        // A held original is never overwritten by a later upload
+        assert!(restarted
+            .retain_if_enabled("doc-1", "bob", "replacement")
+            .is_err());
+        assert_eq!(
+            restarted.get_original("doc-1", "alice").unwrap().as_deref(),
+            Some("original")
+        );
+    }
+
+    #[test]
+    fn test_original_readable_only_by_owner_and_reviewer() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("originals.db");
+        let audit = AuditLogger::new(db_path.clone());
+        audit.initialize().unwrap();
+        let review = RedactionReviewManager::new(db_path.clone());
+        review.initialize().unwrap();
+        let mut vault = OriginalsVault::new(db_path).with_key(vec![7u8; 32]);
+        vault.initialize().unwrap();
+        vault
+            .set_policy(OriginalRetentionPolicy {
+                retain_originals: true,
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(vault
+            .retain_if_enabled("doc-1", "alice", "original")
+            .unwrap());
+
+        assert!(vault.get_original("doc-1", "mallory").is_err());
+        let denied = audit
+            .query_logs(&AuditQuery {
+                user_id: Some("mallory".to_string()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(denied.len(), 1);
+        assert!(!denied[0].success);
+
+        review.submit_for_review("doc-1", "alice").unwrap();
+        review.approve("doc-1", "reviewer").unwrap();
+        assert!(vault.get_original("doc-1", "reviewer").unwrap().is_some());
+        assert!(vault.get_original("doc-1", "alice").unwrap().is_some());
+        assert!(vault.get_original("doc-1", "mallory").is_err());
+    }
+}
@@ -0,0 +1,257 @@
+use anyhow::Result;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use super::audit::{AuditAction, AuditLogger, EntityType};
+use crate::pii_detector::PIIStatistics;
+
+/// Chain-of-custody record of what was done to one document
+///
+/// Holds hashes and counts only, so it can be archived outside the app
+/// without carrying the document text or any detected PII.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessingReceipt {
+    pub receipt_id: String,
+    pub document_id: String,
+    /// Command that processed the document, e.g. "upload_document"
+    pub operation: String,
+    pub filename: String,
+    /// SHA-256 of the extracted text before redaction
+    pub content_sha256: String,
+    /// SHA-256 of the redacted text that was stored and indexed
+    pub redacted_sha256: String,
+    pub extracted_chars: usize,
+    pub entities_redacted: usize,
+    pub entities_by_type: HashMap<String, usize>,
+    pub entities_by_tier: HashMap<String, usize>,
+    pub chunks_indexed: usize,
+    pub operator: String,
+    pub created_at: String,
+}
+
+impl ProcessingReceipt {
+    /// Build a receipt from the outputs of one extraction/redaction/indexing run
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        document_id: &str,
+        operation: &str,
+        filename: &str,
+        extracted_text: &str,
+        redacted_text: &str,
+        pii_stats: &PIIStatistics,
+        chunks_indexed: usize,
+        operator: &str,
+    ) -> Self {
+        Self {
+            receipt_id: uuid::Uuid::new_v4().to_string(),
+            document_id: document_id.to_string(),
+            operation: operation.to_string(),
+            filename: filename.to_string(),
+            content_sha256: sha256_hex(extracted_text),
+            redacted_sha256: sha256_hex(redacted_text),
+            extracted_chars: extracted_text.chars().count(),
+            entities_redacted: pii_stats.total_entities,
+            entities_by_type: pii_stats.by_type.clone(),
+            entities_by_tier: pii_stats.by_tier.clone(),
+            chunks_indexed,
+            operator: operator.to_string(),
+            created_at: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+}
+
+fn sha256_hex(text: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(text.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+/// Persists processing receipts for later retrieval and archiving
+pub struct ProcessingReceiptStore {
+    db_path: PathBuf,
+    audit: AuditLogger,
+}
+
+impl ProcessingReceiptStore {
+    pub fn new(db_path: PathBuf) -> Self {
+        Self {
+            audit: AuditLogger::new(db_path.clone()),
+            db_path,
+        }
+    }
+
+    /// Initialize processing receipts table
+    pub fn initialize(&self) -> Result<()> {
+        let conn = Connection::open(&self.db_path)?;
+
+        let migration = include_str!("../../migrations/009_create_processing_receipts.sql");
+        super::run_migration(&conn, migration)?;
+
+        Ok(())
+    }
+
+    /// Store a receipt and note its issue in the audit trail
+    pub fn record(&self, receipt: &ProcessingReceipt) -> Result<()> {
+        let conn = Connection::open(&self.db_path)?;
+        conn.execute(
+            "INSERT INTO processing_receipts (
+                receipt_id, document_id, operation, filename, content_sha256, redacted_sha256,
+                extracted_chars, entities_redacted, entities_by_type, entities_by_tier,
+                chunks_indexed, operator, created_at
+             ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+            params![
+                receipt.receipt_id,
+                receipt.document_id,
+                receipt.operation,
+                receipt.filename,
+                receipt.content_sha256,
+                receipt.redacted_sha256,
+                receipt.extracted_chars as i64,
+                receipt.entities_redacted as i64,
+                serde_json::to_string(&receipt.entities_by_type)?,
+                serde_json::to_string(&receipt.entities_by_tier)?,
+                receipt.chunks_indexed as i64,
+                receipt.operator,
+                receipt.created_at,
+            ],
+        )?;
+
+        if let Err(e) = self.audit.log_success(
+            &receipt.operator,
+            AuditAction::DataModified,
+            EntityType::Document,
+            Some(&receipt.document_id),
+            Some(serde_json::json!({
+                "action": "processing_receipt_issued",
+                "receipt_id": receipt.receipt_id,
+                "operation": receipt.operation,
+                "content_sha256": receipt.content_sha256,
+            })),
+        ) {
+            tracing::warn!("Failed to audit processing receipt: {}", e);
+        }
+
+        Ok(())
+    }
+
+    /// Most recent receipt for a document, if it has been processed
+    pub fn get_receipt(&self, document_id: &str) -> Result<Option<ProcessingReceipt>> {
+        let conn = Connection::open(&self.db_path)?;
+        let row = conn
+            .query_row(
+                "SELECT receipt_id, document_id, operation, filename, content_sha256,
+                        redacted_sha256, extracted_chars, entities_redacted, entities_by_type,
+                        entities_by_tier, chunks_indexed, operator, created_at
+                 FROM processing_receipts WHERE document_id = ?1
+                 ORDER BY created_at DESC LIMIT 1",
+                params![document_id],
+                |row| {
+                    Ok((
+                        ProcessingReceipt {
+                            receipt_id: row.get(0)?,
+                            document_id: row.get(1)?,
+                            operation: row.get(2)?,
+                            filename: row.get(3)?,
+                            content_sha256: row.get(4)?,
+                            redacted_sha256: row.get(5)?,
+                            extracted_chars: row.get::<_, i64>(6)? as usize,
+                            entities_redacted: row.get::<_, i64>(7)? as usize,
+                            entities_by_type: HashMap::new(),
+                            entities_by_tier: HashMap::new(),
+                            chunks_indexed: row.get::<_, i64>(10)? as usize,
+                            operator: row.get(11)?,
+                            created_at: row.get(12)?,
+                        },
+                        row.get::<_, String>(8)?,
+                        row.get::<_, String>(9)?,
+                    ))
+                },
+            )
+            .optional()?;
+
+        row.map(|(mut receipt, by_type, by_tier)| {
+            receipt.entities_by_type = serde_json::from_str(&by_type)?;
+            receipt.entities_by_tier = serde_json::from_str(&by_tier)?;
+            Ok(receipt)
+        })
+        .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pii_detector::PIIDetector;
+    use crate::rag_engine::{EmbeddingBackend, RAGEngine};
+    use async_trait::async_trait;
+    use std::sync::Arc;
+
+    struct LengthBackend;
+
+    #[async_trait]
+    impl EmbeddingBackend for LengthBackend {
+        async fn embed_batch(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+            Ok(texts.iter().map(|t| vec![t.len() as f32, 1.0]).collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_receipt_matches_processing() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("receipts.db");
+        AuditLogger::new(db_path.clone()).initialize().unwrap();
+        let store = ProcessingReceiptStore::new(db_path);
+        store.initialize().unwrap();
+
+        // 20 words at 8 words per chunk -> 3 chunks
+        let text = "SSN 123-45-6789, contact tenant@example.com or landlord@example.com about \
+                    rent due on the first of each month under this lease agreement today.";
+
+        let detector = PIIDetector::new();
+        let report = detector.redact_pii_with_report(text, None).await.unwrap();
+        let stats = detector.summarize_detections(&report.detections).await;
+
+        let rag = RAGEngine::with_index_path(temp_dir.path().join("rag"));
+        let mut config = rag.get_config().await;
+        config.chunk_size = 8;
+        config.chunk_overlap = 0;
+        rag.update_config(config).await.unwrap();
+        rag.set_embedding_backend(Arc::new(LengthBackend)).await;
+        let doc_id = rag
+            .add_document(&report.redacted_text, serde_json::json!({}))
+            .await
+            .unwrap();
+        let chunks = rag.document_chunk_count(&doc_id).await;
+
+        let receipt = ProcessingReceipt::new(
+            &doc_id,
+            "upload_document",
+            "lease.txt",
+            text,
+            &report.redacted_text,
+            &stats,
+            chunks,
+            "default_user",
+        );
+        store.record(&receipt).unwrap();
+        let stored = store.get_receipt(&doc_id).unwrap().unwrap();
+
+        assert_eq!(stored.receipt_id, receipt.receipt_id);
+        assert_eq!(stored.entities_redacted, report.detections.len());
+        assert_eq!(stored.entities_by_type.get("SSN"), Some(&1));
+        assert_eq!(stored.entities_by_type.get("EMAIL"), Some(&2));
+        assert_eq!(stored.chunks_indexed, 3);
+        assert_eq!(stored.content_sha256, sha256_hex(text));
+        assert_eq!(stored.content_sha256.len(), 64);
+        assert_ne!(stored.content_sha256, stored.redacted_sha256);
+
+        // No PII leaks into the receipt
+        let serialized = serde_json::to_string(&stored).unwrap();
+        assert!(!serialized.contains("123-45-6789"));
+        assert!(!serialized.contains("tenant@example.com"));
+        assert!(store.get_receipt("unknown").unwrap().is_none());
+    }
+}
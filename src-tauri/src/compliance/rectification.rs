@@ -0,0 +1,271 @@
+use anyhow::{anyhow, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use super::audit::{AuditAction, AuditLogger, EntityType};
+
+/// Fields a data subject may correct (GDPR Art. 16), per entity type.
+///
+/// Identifiers, timestamps, embeddings and retention columns are system-managed.
+/// Consent records are not listed: the consent text is what the user agreed to,
+/// and consent state changes go through grant/revoke so the trail stays intact.
+const RECTIFIABLE_FIELDS: &[(&str, &str, &[&str])] = &[
+    ("document", "documents", &["filename", "file_type"]),
+    ("chat_session", "chat_sessions", &["title", "tags"]),
+    ("chat_message", "chat_messages", &["content", "metadata"]),
+];
+
+/// Upper bound on a corrected value
+const MAX_VALUE_BYTES: usize = 1_000_000;
+
+/// Applied correction, as recorded in the audit trail
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rectification {
+    pub entity_type: String,
+    pub entity_id: String,
+    pub field: String,
+    pub old_value: Option<String>,
+    pub new_value: String,
+    pub audit_log_id: i64,
+}
+
+/// Applies field-level corrections to stored personal data
+pub struct RectificationManager {
+    db_path: PathBuf,
+    audit: AuditLogger,
+}
+
+impl RectificationManager {
+    pub fn new(db_path: PathBuf) -> Self {
+        Self {
+            audit: AuditLogger::new(db_path.clone()),
+            db_path,
+        }
+    }
+
+    /// Rectifiable fields for an entity type, or an error for unknown types
+    pub fn rectifiable_fields(entity_type: &str) -> Result<&'static [&'static str]> {
+        Ok(Self::lookup(entity_type)?.1)
+    }
+
+    fn lookup(entity_type: &str) -> Result<(&'static str, &'static [&'static str])> {
+        RECTIFIABLE_FIELDS
+            .iter()
+            .find(|(name, _, _)| *name == entity_type)
+            .map(|(_, table, fields)| (*table, *fields))
+            .ok_or_else(|| anyhow!("Unknown entity type: {}", entity_type))
+    }
+
+    /// Correct one field of a user's record and audit the old and new values
+    pub fn rectify(
+        &self,
+        user_id: &str,
+        entity_type: &str,
+        entity_id: &str,
+        field: &str,
+        new_value: &str,
+    ) -> Result<Rectification> {
+        let (table, fields) = Self::lookup(entity_type)?;
+        if !fields.contains(&field) {
+            return Err(anyhow!(
+                "Field '{}' of {} is not rectifiable; allowed: {:?}",
+                field,
+                entity_type,
+                fields
+            ));
+        }
+        if new_value.trim().is_empty() {
+            return Err(anyhow!("Corrected value cannot be empty"));
+        }
+        if new_value.len() > MAX_VALUE_BYTES {
+            return Err(anyhow!("Corrected value exceeds maximum size of 1MB"));
+        }
+
+        let conn = Connection::open(&self.db_path)?;
+
+        // Both the read and the write are scoped to records the requesting user owns
+        let owner_clause = Self::owner_clause(&conn, table)?;
+        let select = format!(
+            "SELECT {} FROM {} WHERE id = ?1 AND {}",
+            field, table, owner_clause
+        );
+        let old_value: Option<Option<String>> = conn
+            .query_row(&select, params![entity_id, user_id], |row| row.get(0))
+            .optional()?;
+        let Some(old_value) = old_value else {
+            return Err(anyhow!("No {} '{}' found for user", entity_type, entity_id));
+        };
+
+        let update = format!(
+            "UPDATE {} SET {} = ?3 WHERE id = ?1 AND {}",
+            table, field, owner_clause
+        );
+        conn.execute(&update, params![entity_id, user_id, new_value])?;
+
+        let audit_log_id = self.audit.log_success(
+            user_id,
+            AuditAction::DataModified,
+            match entity_type {
+                "document" => EntityType::Document,
+                _ => EntityType::ChatMessage,
+            },
+            Some(entity_id),
+            Some(serde_json::json!({
+                "action": "data_rectification",
+                "entity_type": entity_type,
+                "field": field,
+                "old_value": old_value,
+                "new_value": new_value,
+                "gdpr_article": "Article 16 - Right to Rectification"
+            })),
+        )?;
+
+        Ok(Rectification {
+            entity_type: entity_type.to_string(),
+            entity_id: entity_id.to_string(),
+            field: field.to_string(),
+            old_value,
+            new_value: new_value.to_string(),
+            audit_log_id,
+        })
+    }
+
+    /// Condition restricting `table` to rows owned by the user bound to `?2`.
+    /// Messages without their own owner column are owned through their session;
+    /// a record whose owner cannot be established is never rectified.
+    fn owner_clause(conn: &Connection, table: &str) -> Result<&'static str> {
+        if Self::has_user_column(conn, table)? {
+            return Ok("user_id = ?2");
+        }
+        if table == "chat_messages" && Self::has_user_column(conn, "chat_sessions")? {
+            return Ok("chat_id IN (SELECT id FROM chat_sessions WHERE user_id = ?2)");
+        }
+        Err(anyhow!(
+            "Cannot verify ownership of {} records; rectification refused",
+            table
+        ))
+    }
+
+    fn has_user_column(conn: &Connection, table: &str) -> Result<bool> {
+        let count: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM pragma_table_info(?1) WHERE name = 'user_id'",
+            params![table],
+            |row| row.get(0),
+        )?;
+        Ok(count > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compliance::audit::AuditQuery;
+
+    #[test]
+    fn test_rectify_document_metadata_audits_old_and_new_values() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("rectify.db");
+        let audit = AuditLogger::new(db_path.clone());
+        audit.initialize().unwrap();
+
+        let conn = Connection::open(&db_path).unwrap();
+        conn.execute(
+            "CREATE TABLE documents (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                filename TEXT NOT NULL,
+                content TEXT NOT NULL,
+                file_type TEXT NOT NULL,
+                user_id TEXT NOT NULL DEFAULT 'default_user'
+            )",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO documents (filename, content, file_type, user_id)
+             VALUES ('smtih_lease.pdf', 'Lease terms', 'pdf', 'alice')",
+            [],
+        )
+        .unwrap();
+
+        let manager = RectificationManager::new(db_path.clone());
+        let result = manager
+            .rectify("alice", "document", "1", "filename", "smith_lease.pdf")
+            .unwrap();
+        assert_eq!(result.old_value.as_deref(), Some("smtih_lease.pdf"));
+
+        let stored: String = conn
+            .query_row("SELECT filename FROM documents WHERE id = 1", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(stored, "smith_lease.pdf");
+
+        let entries = audit
+            .query_logs(&AuditQuery {
+                user_id: Some("alice".to_string()),
+                action_type: Some("data_modified".to_string()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(entries.len(), 1);
+        let details = entries[0].details.as_ref().unwrap();
+        assert_eq!(details["field"], "filename");
+        assert_eq!(details["old_value"], "smtih_lease.pdf");
+        assert_eq!(details["new_value"], "smith_lease.pdf");
+
+        // System-managed fields and other users' records are off limits
+        assert!(manager
+            .rectify("alice", "document", "1", "user_id", "mallory")
+            .is_err());
+        assert!(manager
+            .rectify("bob", "document", "1", "filename", "bob.pdf")
+            .is_err());
+        assert!(manager
+            .rectify("alice", "document", "1", "content", "Rewritten")
+            .is_err());
+    }
+
+    #[test]
+    fn test_chat_message_rectification_is_scoped_to_session_owner() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("rectify.db");
+        AuditLogger::new(db_path.clone()).initialize().unwrap();
+
+        let conn = Connection::open(&db_path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE chat_sessions (
+                id TEXT PRIMARY KEY,
+                title TEXT NOT NULL,
+                user_id TEXT NOT NULL DEFAULT 'default_user'
+            );
+            CREATE TABLE chat_messages (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                chat_id TEXT NOT NULL,
+                content TEXT NOT NULL,
+                metadata TEXT
+            );
+            INSERT INTO chat_sessions (id, title, user_id) VALUES ('s1', 'Lease', 'alice');
+            INSERT INTO chat_messages (chat_id, content) VALUES ('s1', 'My adress is 1 Main St');",
+        )
+        .unwrap();
+
+        let manager = RectificationManager::new(db_path);
+        assert!(manager
+            .rectify("bob", "chat_message", "1", "content", "Overwritten")
+            .is_err());
+        let result = manager
+            .rectify(
+                "alice",
+                "chat_message",
+                "1",
+                "content",
+                "My address is 1 Main St",
+            )
+            .unwrap();
+        assert_eq!(result.old_value.as_deref(), Some("My adress is 1 Main St"));
+
+        // Consent text records what was agreed to and cannot be rewritten
+        assert!(RectificationManager::rectifiable_fields("consent").is_err());
+    }
+}
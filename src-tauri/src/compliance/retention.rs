@@ -1,7 +1,9 @@
+use super::audit::EntityType;
 use anyhow::{anyhow, Result};
 use chrono::{DateTime, Duration as ChronoDuration, Utc};
-use rusqlite::{params, Connection};
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
 /// Data retention policy
@@ -35,6 +37,17 @@ impl RetentionPolicy {
     }
 }
 
+/// Identifying details of an expired row, for reviewing a cleanup before it runs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExpiredRecord {
+    pub id: String,
+    /// Filename of a document or title of a chat session
+    pub label: Option<String>,
+    /// Chat session a message belongs to
+    pub chat_id: Option<String>,
+    pub retention_until: Option<String>,
+}
+
 /// Retention statistics
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RetentionStats {
@@ -44,6 +57,38 @@ pub struct RetentionStats {
     pub pending_deletion: Vec<i64>,
 }
 
+/// Table and creation timestamp column backing a retention category
+fn category_table(entity_type: &str) -> Result<(&'static str, &'static str)> {
+    match entity_type {
+        "document" => Ok(("documents", "upload_date")),
+        "chat_session" => Ok(("chat_sessions", "created_at")),
+        "chat_message" => Ok(("chat_messages", "timestamp")),
+        "query_history" => Ok(("query_history", "created_at")),
+        _ => Err(anyhow!("Unknown entity type: {}", entity_type)),
+    }
+}
+
+/// Dependent tables, and their key column, whose rows are deleted together
+/// with an expired entity
+fn cascade_tables(entity_type: &str) -> &'static [(&'static str, &'static str)] {
+    match entity_type {
+        "document" => &[
+            ("document_chunks", "document_id"),
+            ("pii_detections", "document_id"),
+        ],
+        "chat_session" => &[("chat_messages", "chat_id")],
+        _ => &[],
+    }
+}
+
+fn has_column(conn: &Connection, table: &str, column: &str) -> Result<bool> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let columns = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(columns.iter().any(|name| name == column))
+}
+
 /// Data Retention Manager
 pub struct RetentionManager {
     db_path: PathBuf,
@@ -119,81 +164,267 @@ impl RetentionManager {
         Ok(count)
     }
 
+    fn ensure_policy_table(conn: &Connection) -> Result<()> {
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS retention_policies (
+                entity_type TEXT PRIMARY KEY,
+                retention_days INTEGER NOT NULL,
+                auto_delete INTEGER NOT NULL,
+                updated_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// Set the retention period of a data category
+    ///
+    /// With `auto_delete`, rows of the category that have no `retention_until`
+    /// of their own expire `retention_days` after they were created. Chat
+    /// sessions follow the chat message policy, measured from their last
+    /// message.
+    pub fn set_category_policy(
+        &self,
+        entity_type: &EntityType,
+        retention_days: i64,
+        auto_delete: bool,
+    ) -> Result<RetentionPolicy> {
+        let category = match entity_type {
+            EntityType::Document | EntityType::ChatMessage | EntityType::QueryHistory => {
+                entity_type.as_str()
+            }
+            _ => {
+                return Err(anyhow!(
+                    "No retention policy for {} data",
+                    entity_type.as_str()
+                ))
+            }
+        };
+        if retention_days < 0 {
+            return Err(anyhow!("Retention period cannot be negative"));
+        }
+
+        let conn = Connection::open(&self.db_path)?;
+        Self::ensure_policy_table(&conn)?;
+        conn.execute(
+            "INSERT OR REPLACE INTO retention_policies
+                (entity_type, retention_days, auto_delete, updated_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![
+                category,
+                retention_days,
+                auto_delete,
+                Utc::now().to_rfc3339()
+            ],
+        )?;
+
+        Ok(RetentionPolicy {
+            entity_type: category.to_string(),
+            retention_days,
+            auto_delete,
+        })
+    }
+
+    /// Retention policies set per data category
+    pub fn get_category_policies(&self) -> Result<Vec<RetentionPolicy>> {
+        let conn = Connection::open(&self.db_path)?;
+        Self::ensure_policy_table(&conn)?;
+
+        let mut stmt = conn.prepare(
+            "SELECT entity_type, retention_days, auto_delete
+             FROM retention_policies ORDER BY entity_type",
+        )?;
+        let policies = stmt
+            .query_map([], |row| {
+                Ok(RetentionPolicy {
+                    entity_type: row.get(0)?,
+                    retention_days: row.get(1)?,
+                    auto_delete: row.get(2)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(policies)
+    }
+
+    /// Policy governing an entity type, if one has been set
+    pub fn get_category_policy(&self, entity_type: &str) -> Result<Option<RetentionPolicy>> {
+        let conn = Connection::open(&self.db_path)?;
+        Self::category_policy(&conn, entity_type)
+    }
+
+    fn category_policy(conn: &Connection, entity_type: &str) -> Result<Option<RetentionPolicy>> {
+        category_table(entity_type)?;
+        let category = match entity_type {
+            "chat_session" => "chat_message",
+            other => other,
+        };
+
+        Self::ensure_policy_table(conn)?;
+        let policy = conn
+            .query_row(
+                "SELECT entity_type, retention_days, auto_delete
+                 FROM retention_policies WHERE entity_type = ?1",
+                params![category],
+                |row| {
+                    Ok(RetentionPolicy {
+                        entity_type: row.get(0)?,
+                        retention_days: row.get(1)?,
+                        auto_delete: row.get(2)?,
+                    })
+                },
+            )
+            .optional()?;
+
+        Ok(policy)
+    }
+
+    /// Condition matching the expired rows of an entity type and its
+    /// parameters: a `retention_until` in the past, or no `retention_until`
+    /// and created longer ago than an auto-delete category policy allows.
+    /// Tables without a creation timestamp only expire by `retention_until`.
+    /// A chat session ages from its last message, so an old session that is
+    /// still in use is kept together with its recent messages.
+    fn expiry_condition(conn: &Connection, entity_type: &str) -> Result<(String, Vec<String>)> {
+        let (table, created_column) = category_table(entity_type)?;
+        let now = Utc::now();
+        let mut condition = "(retention_until IS NOT NULL AND retention_until < ?1)".to_string();
+        let mut values = vec![now.to_rfc3339()];
+
+        if let Some(policy) = Self::category_policy(conn, entity_type)? {
+            if policy.auto_delete && has_column(conn, table, created_column)? {
+                let cutoff = now - ChronoDuration::days(policy.retention_days);
+                let mut age = format!("datetime({})", created_column);
+                if table == "chat_sessions"
+                    && has_column(conn, "chat_messages", "chat_id")?
+                    && has_column(conn, "chat_messages", "timestamp")?
+                {
+                    age = format!(
+                        "COALESCE((SELECT MAX(datetime(timestamp)) FROM chat_messages
+                            WHERE chat_messages.chat_id = chat_sessions.id), {})",
+                        age
+                    );
+                }
+                condition.push_str(&format!(
+                    " OR (retention_until IS NULL AND {} < datetime(?2))",
+                    age
+                ));
+                values.push(cutoff.format("%Y-%m-%d %H:%M:%S").to_string());
+            }
+        }
+
+        Ok((condition, values))
+    }
+
     /// Get entities pending deletion
     pub fn get_expired_entities(&self, entity_type: &str) -> Result<Vec<i64>> {
         let conn = Connection::open(&self.db_path)?;
-        let now = Utc::now().to_rfc3339();
+        let (table, _) = category_table(entity_type)?;
+        let (condition, values) = Self::expiry_condition(&conn, entity_type)?;
 
-        let table = match entity_type {
-            "document" => "documents",
-            "chat_session" => "chat_sessions",
-            "chat_message" => "chat_messages",
-            "query_history" => "query_history",
-            _ => return Err(anyhow!("Unknown entity type: {}", entity_type)),
+        let query = format!("SELECT id FROM {} WHERE {}", table, condition);
+
+        let mut stmt = conn.prepare(&query)?;
+        let ids: Vec<i64> = stmt
+            .query_map(params_from_iter(values.iter()), |row| row.get(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(ids)
+    }
+
+    /// Expired rows of an entity type with identifying metadata, selected with
+    /// the same condition as `delete_expired_entities`
+    pub fn get_expired_records(&self, entity_type: &str) -> Result<Vec<ExpiredRecord>> {
+        let conn = Connection::open(&self.db_path)?;
+        let (table, _) = category_table(entity_type)?;
+        let (condition, values) = Self::expiry_condition(&conn, entity_type)?;
+
+        let label_column = match table {
+            "documents" => Some("filename"),
+            "chat_sessions" => Some("title"),
+            _ => None,
+        };
+        let column = |name: Option<&str>| -> Result<String> {
+            Ok(match name {
+                Some(name) if has_column(&conn, table, name)? => format!("CAST({} AS TEXT)", name),
+                _ => "NULL".to_string(),
+            })
         };
+        let label = column(label_column)?;
+        let chat_id = column((table == "chat_messages").then_some("chat_id"))?;
 
         let query = format!(
-            "SELECT id FROM {} WHERE retention_until IS NOT NULL AND retention_until < ?1",
-            table
+            "SELECT CAST(id AS TEXT), {}, {}, retention_until FROM {} WHERE {} ORDER BY id",
+            label, chat_id, table, condition
         );
 
         let mut stmt = conn.prepare(&query)?;
-        let ids: Vec<i64> = stmt
-            .query_map(params![now], |row| row.get(0))?
+        let records = stmt
+            .query_map(params_from_iter(values.iter()), |row| {
+                Ok(ExpiredRecord {
+                    id: row.get(0)?,
+                    label: row.get(1)?,
+                    chat_id: row.get(2)?,
+                    retention_until: row.get(3)?,
+                })
+            })?
             .collect::<Result<Vec<_>, _>>()?;
 
-        Ok(ids)
+        Ok(records)
+    }
+
+    /// Rows per dependent table that `delete_expired_entities` removes along
+    /// with the expired entities of a type
+    pub fn get_cascaded_counts(&self, entity_type: &str) -> Result<BTreeMap<String, usize>> {
+        let conn = Connection::open(&self.db_path)?;
+        let (table, _) = category_table(entity_type)?;
+        let (condition, values) = Self::expiry_condition(&conn, entity_type)?;
+
+        let mut counts = BTreeMap::new();
+        for (dependent, key) in cascade_tables(entity_type) {
+            if !has_column(&conn, dependent, key)? {
+                continue;
+            }
+            let count: i64 = conn.query_row(
+                &format!(
+                    "SELECT COUNT(*) FROM {} WHERE {} IN (SELECT id FROM {} WHERE {})",
+                    dependent, key, table, condition
+                ),
+                params_from_iter(values.iter()),
+                |row| row.get(0),
+            )?;
+            counts.insert(dependent.to_string(), count as usize);
+        }
+
+        Ok(counts)
     }
 
     /// Delete expired entities (GDPR automated deletion)
     pub fn delete_expired_entities(&self, entity_type: &str) -> Result<usize> {
-        let expired_ids = self.get_expired_entities(entity_type)?;
+        let expired = self.get_expired_records(entity_type)?;
 
-        if expired_ids.is_empty() {
+        if expired.is_empty() {
             return Ok(0);
         }
 
         let conn = Connection::open(&self.db_path)?;
-        let now = Utc::now().to_rfc3339();
+        let (condition, values) = Self::expiry_condition(&conn, entity_type)?;
 
-        let table = match entity_type {
-            "document" => {
-                // Also delete related data
-                conn.execute(
-                    "DELETE FROM document_chunks WHERE document_id IN (
-                        SELECT id FROM documents WHERE retention_until < ?1
-                    )",
-                    params![now],
-                )?;
-                conn.execute(
-                    "DELETE FROM pii_detections WHERE document_id IN (
-                        SELECT id FROM documents WHERE retention_until < ?1
-                    )",
-                    params![now],
-                )?;
-                "documents"
-            }
-            "chat_session" => {
-                // Delete related messages
-                conn.execute(
-                    "DELETE FROM chat_messages WHERE chat_id IN (
-                        SELECT id FROM chat_sessions WHERE retention_until < ?1
-                    )",
-                    params![now],
-                )?;
-                "chat_sessions"
-            }
-            "chat_message" => "chat_messages",
-            "query_history" => "query_history",
-            _ => return Err(anyhow!("Unknown entity type: {}", entity_type)),
-        };
+        // Also delete related data
+        let (table, _) = category_table(entity_type)?;
+        for (dependent, key) in cascade_tables(entity_type) {
+            conn.execute(
+                &format!(
+                    "DELETE FROM {} WHERE {} IN (SELECT id FROM {} WHERE {})",
+                    dependent, key, table, condition
+                ),
+                params_from_iter(values.iter()),
+            )?;
+        }
 
-        let query = format!(
-            "DELETE FROM {} WHERE retention_until IS NOT NULL AND retention_until < ?1",
-            table
-        );
+        let query = format!("DELETE FROM {} WHERE {}", table, condition);
 
-        let count = conn.execute(&query, params![now])?;
+        let count = conn.execute(&query, params_from_iter(values.iter()))?;
 
         Ok(count)
     }
@@ -214,15 +445,8 @@ impl RetentionManager {
     /// Get retention statistics for specific entity type
     fn get_entity_retention_stats(&self, entity_type: &str) -> Result<RetentionStats> {
         let conn = Connection::open(&self.db_path)?;
-        let now = Utc::now().to_rfc3339();
-
-        let table = match entity_type {
-            "document" => "documents",
-            "chat_session" => "chat_sessions",
-            "chat_message" => "chat_messages",
-            "query_history" => "query_history",
-            _ => return Err(anyhow!("Unknown entity type: {}", entity_type)),
-        };
+        let (table, _) = category_table(entity_type)?;
+        let (condition, values) = Self::expiry_condition(&conn, entity_type)?;
 
         // Total count
         let total_count: i64 =
@@ -232,11 +456,8 @@ impl RetentionManager {
 
         // Expired count
         let expired_count: i64 = conn.query_row(
-            &format!(
-                "SELECT COUNT(*) FROM {} WHERE retention_until IS NOT NULL AND retention_until < ?1",
-                table
-            ),
-            params![now],
+            &format!("SELECT COUNT(*) FROM {} WHERE {}", table, condition),
+            params_from_iter(values.iter()),
             |row| row.get(0),
         )?;
 
@@ -251,7 +472,8 @@ impl RetentionManager {
         })
     }
 
-    /// Clear retention period (keep indefinitely)
+    /// Clear retention period (keep indefinitely unless the category policy
+    /// auto-deletes)
     #[allow(dead_code)]
     pub fn clear_retention(&self, entity_type: &str, entity_id: i64) -> Result<()> {
         let conn = Connection::open(&self.db_path)?;
@@ -395,4 +617,60 @@ mod tests {
         // Cleanup
         let _ = std::fs::remove_file(db_path);
     }
+
+    #[test]
+    fn test_chat_session_expires_from_last_message() {
+        let db_path = get_test_db();
+
+        {
+            let conn = Connection::open(&db_path).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE chat_sessions (
+                    id INTEGER PRIMARY KEY,
+                    title TEXT,
+                    created_at DATETIME,
+                    retention_until DATETIME
+                );
+                CREATE TABLE chat_messages (
+                    id INTEGER PRIMARY KEY,
+                    chat_id INTEGER,
+                    content TEXT,
+                    timestamp DATETIME,
+                    retention_until DATETIME
+                );
+                INSERT INTO chat_sessions (id, title, created_at)
+                    VALUES (1, 'active', datetime('now', '-200 days')),
+                           (2, 'stale', datetime('now', '-200 days')),
+                           (3, 'empty', datetime('now', '-200 days'));
+                INSERT INTO chat_messages (chat_id, content, timestamp)
+                    VALUES (1, 'old', datetime('now', '-150 days')),
+                           (1, 'yesterday', datetime('now', '-1 days')),
+                           (2, 'old', datetime('now', '-150 days'));",
+            )
+            .unwrap();
+        }
+
+        let manager = RetentionManager::new(db_path.clone());
+        manager
+            .set_category_policy(&EntityType::ChatMessage, 90, true)
+            .unwrap();
+
+        assert_eq!(
+            manager.get_expired_entities("chat_session").unwrap(),
+            vec![2, 3]
+        );
+        assert_eq!(manager.delete_expired_entities("chat_session").unwrap(), 2);
+
+        let conn = Connection::open(&db_path).unwrap();
+        let messages: Vec<String> = conn
+            .prepare("SELECT content FROM chat_messages ORDER BY id")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(messages, vec!["old", "yesterday"]);
+
+        let _ = std::fs::remove_file(db_path);
+    }
 }
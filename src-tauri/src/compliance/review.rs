@@ -0,0 +1,315 @@
+use anyhow::{anyhow, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use super::audit::{AuditAction, AuditLogger, EntityType};
+
+/// Review state of a processed document's redactions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewState {
+    Draft,
+    PendingReview,
+    Approved,
+    Rejected,
+}
+
+impl ReviewState {
+    pub fn as_str(&self) -> &str {
+        match self {
+            ReviewState::Draft => "draft",
+            ReviewState::PendingReview => "pending_review",
+            ReviewState::Approved => "approved",
+            ReviewState::Rejected => "rejected",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "draft" => Ok(ReviewState::Draft),
+            "pending_review" => Ok(ReviewState::PendingReview),
+            "approved" => Ok(ReviewState::Approved),
+            "rejected" => Ok(ReviewState::Rejected),
+            other => Err(anyhow!("Unknown review state: {}", other)),
+        }
+    }
+
+    /// Whether the workflow allows moving from this state to `next`
+    pub fn can_transition_to(&self, next: ReviewState) -> bool {
+        matches!(
+            (self, next),
+            (ReviewState::Draft, ReviewState::PendingReview)
+                | (ReviewState::Rejected, ReviewState::PendingReview)
+                | (ReviewState::PendingReview, ReviewState::Approved)
+                | (ReviewState::PendingReview, ReviewState::Rejected)
+        )
+    }
+}
+
+/// Current review record for a document
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedactionReview {
+    pub document_id: String,
+    pub state: ReviewState,
+    pub submitted_by: Option<String>,
+    pub reviewed_by: Option<String>,
+    pub rejection_reason: Option<String>,
+    pub updated_at: String,
+}
+
+/// Redaction approval workflow (draft → pending review → approved/rejected)
+pub struct RedactionReviewManager {
+    db_path: PathBuf,
+    audit: AuditLogger,
+}
+
+impl RedactionReviewManager {
+    pub fn new(db_path: PathBuf) -> Self {
+        Self {
+            audit: AuditLogger::new(db_path.clone()),
+            db_path,
+        }
+    }
+
+    /// Initialize redaction review table
+    pub fn initialize(&self) -> Result<()> {
+        let conn = Connection::open(&self.db_path)?;
+
+        let migration = include_str!("../../migrations/007_create_redaction_reviews.sql");
+        super::run_migration(&conn, migration)?;
+
+        Ok(())
+    }
+
+    /// Register a freshly processed document as a draft
+    pub fn register_document(&self, document_id: &str) -> Result<()> {
+        let conn = Connection::open(&self.db_path)?;
+        conn.execute(
+            "INSERT OR IGNORE INTO redaction_reviews (document_id, state) VALUES (?1, 'draft')",
+            params![document_id],
+        )?;
+        Ok(())
+    }
+
+    /// Get the review record for a document, if it has been registered
+    pub fn get_review(&self, document_id: &str) -> Result<Option<RedactionReview>> {
+        let conn = Connection::open(&self.db_path)?;
+        let row = conn
+            .query_row(
+                "SELECT document_id, state, submitted_by, reviewed_by, rejection_reason, updated_at
+                 FROM redaction_reviews WHERE document_id = ?1",
+                params![document_id],
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, Option<String>>(2)?,
+                        row.get::<_, Option<String>>(3)?,
+                        row.get::<_, Option<String>>(4)?,
+                        row.get::<_, String>(5)?,
+                    ))
+                },
+            )
+            .optional()?;
+
+        row.map(
+            |(document_id, state, submitted_by, reviewed_by, rejection_reason, updated_at)| {
+                Ok(RedactionReview {
+                    document_id,
+                    state: ReviewState::parse(&state)?,
+                    submitted_by,
+                    reviewed_by,
+                    rejection_reason,
+                    updated_at,
+                })
+            },
+        )
+        .transpose()
+    }
+
+    /// Submit a document's redactions for review
+    pub fn submit_for_review(&self, document_id: &str, actor: &str) -> Result<RedactionReview> {
+        self.register_document(document_id)?;
+        self.transition(document_id, actor, ReviewState::PendingReview, None)
+    }
+
+    /// Approve a document's redactions, allowing it to be exported
+    pub fn approve(&self, document_id: &str, actor: &str) -> Result<RedactionReview> {
+        self.transition(document_id, actor, ReviewState::Approved, None)
+    }
+
+    /// Reject a document's redactions; the reason is kept for the submitter
+    pub fn reject(&self, document_id: &str, actor: &str, reason: &str) -> Result<RedactionReview> {
+        if reason.trim().is_empty() {
+            return Err(anyhow!("A rejection reason is required"));
+        }
+        self.transition(document_id, actor, ReviewState::Rejected, Some(reason))
+    }
+
+    /// Fail unless the document's redactions have been approved
+    pub fn ensure_exportable(&self, document_id: &str) -> Result<()> {
+        match self.get_review(document_id)? {
+            Some(review) if review.state == ReviewState::Approved => Ok(()),
+            Some(review) => Err(anyhow!(
+                "Document {} cannot be exported: redactions are {}",
+                document_id,
+                review.state.as_str()
+            )),
+            None => Err(anyhow!(
+                "Document {} cannot be exported: redactions have not been reviewed",
+                document_id
+            )),
+        }
+    }
+
+    /// Fail unless every listed document's redactions have been approved,
+    /// naming all blocked documents in the error
+    pub fn ensure_all_exportable<I, S>(&self, document_ids: I) -> Result<()>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut blocked = Vec::new();
+        for document_id in document_ids {
+            let document_id = document_id.as_ref();
+            if self.ensure_exportable(document_id).is_err() {
+                blocked.push(document_id.to_string());
+            }
+        }
+        if blocked.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "Export refused: redactions of document(s) {} are not approved",
+                blocked.join(", ")
+            ))
+        }
+    }
+
+    fn transition(
+        &self,
+        document_id: &str,
+        actor: &str,
+        next: ReviewState,
+        reason: Option<&str>,
+    ) -> Result<RedactionReview> {
+        let current = self
+            .get_review(document_id)?
+            .ok_or_else(|| anyhow!("Document {} has no redaction review", document_id))?;
+
+        if !current.state.can_transition_to(next) {
+            return Err(anyhow!(
+                "Cannot move document {} from {} to {}",
+                document_id,
+                current.state.as_str(),
+                next.as_str()
+            ));
+        }
+
+        // Four-eyes principle: whoever submitted the redactions cannot review them
+        let reviewing = matches!(next, ReviewState::Approved | ReviewState::Rejected);
+        if reviewing && current.submitted_by.as_deref() == Some(actor) {
+            return Err(anyhow!(
+                "Document {} must be reviewed by someone other than its submitter {}",
+                document_id,
+                actor
+            ));
+        }
+
+        let conn = Connection::open(&self.db_path)?;
+        match next {
+            ReviewState::PendingReview => conn.execute(
+                "UPDATE redaction_reviews
+                 SET state = ?2, submitted_by = ?3, reviewed_by = NULL, rejection_reason = NULL,
+                     updated_at = CURRENT_TIMESTAMP
+                 WHERE document_id = ?1",
+                params![document_id, next.as_str(), actor],
+            )?,
+            _ => conn.execute(
+                "UPDATE redaction_reviews
+                 SET state = ?2, reviewed_by = ?3, rejection_reason = ?4,
+                     updated_at = CURRENT_TIMESTAMP
+                 WHERE document_id = ?1",
+                params![document_id, next.as_str(), actor, reason],
+            )?,
+        };
+
+        let action = match next {
+            ReviewState::Approved => AuditAction::RedactionApproved,
+            ReviewState::Rejected => AuditAction::RedactionRejected,
+            _ => AuditAction::RedactionSubmitted,
+        };
+        if let Err(e) = self.audit.log_success(
+            actor,
+            action,
+            EntityType::Document,
+            Some(document_id),
+            Some(serde_json::json!({
+                "from": current.state.as_str(),
+                "to": next.as_str(),
+                "reason": reason,
+            })),
+        ) {
+            tracing::warn!("Failed to audit redaction review transition: {}", e);
+        }
+
+        self.get_review(document_id)?
+            .ok_or_else(|| anyhow!("Document {} has no redaction review", document_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_export_blocked_until_approved() {
+        let mut db_path = env::temp_dir();
+        db_path.push(format!("test_review_{}.db", uuid::Uuid::new_v4()));
+
+        AuditLogger::new(db_path.clone()).initialize().unwrap();
+        let reviews = RedactionReviewManager::new(db_path.clone());
+        reviews.initialize().unwrap();
+
+        reviews.register_document("doc_1").unwrap();
+        assert!(reviews.ensure_exportable("doc_1").is_err());
+
+        // Approval is only possible from pending review
+        assert!(reviews.approve("doc_1", "reviewer").is_err());
+
+        let pending = reviews.submit_for_review("doc_1", "paralegal").unwrap();
+        assert_eq!(pending.state, ReviewState::PendingReview);
+        assert_eq!(pending.submitted_by.as_deref(), Some("paralegal"));
+        assert!(reviews.ensure_exportable("doc_1").is_err());
+
+        // The submitter cannot approve their own redactions
+        assert!(reviews.approve("doc_1", "paralegal").is_err());
+        assert!(reviews.reject("doc_1", "paralegal", "Looks wrong").is_err());
+
+        let approved = reviews.approve("doc_1", "reviewer").unwrap();
+        assert_eq!(approved.state, ReviewState::Approved);
+        assert_eq!(approved.reviewed_by.as_deref(), Some("reviewer"));
+        assert!(reviews.ensure_exportable("doc_1").is_ok());
+
+        // Rejected documents stay blocked and keep the reason
+        reviews.submit_for_review("doc_2", "paralegal").unwrap();
+        let rejected = reviews
+            .reject("doc_2", "reviewer", "Client name left unredacted")
+            .unwrap();
+        assert_eq!(
+            rejected.rejection_reason.as_deref(),
+            Some("Client name left unredacted")
+        );
+        assert!(reviews.ensure_exportable("doc_2").is_err());
+
+        assert!(reviews.ensure_all_exportable(["doc_1"]).is_ok());
+        let err = reviews
+            .ensure_all_exportable(["doc_1", "doc_2", "doc_3"])
+            .unwrap_err();
+        assert!(err.to_string().contains("doc_2, doc_3"));
+
+        let _ = std::fs::remove_file(db_path);
+    }
+}
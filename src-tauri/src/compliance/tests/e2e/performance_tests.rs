@@ -323,7 +323,7 @@ async fn test_pii_redaction_performance() {
     let text = mock_text_with_pii().repeat(50); // ~50x PII occurrences
 
     let start = Instant::now();
-    let result = detector.redact_pii(&text).await;
+    let result = detector.redact_pii(&text, None).await;
     let duration = start.elapsed();
 
     assert!(result.is_ok(), "Redaction should succeed");
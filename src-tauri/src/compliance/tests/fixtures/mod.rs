@@ -29,6 +29,7 @@ pub fn mock_user_full() -> UserDataExport {
         ],
         settings: mock_settings(),
         metadata: mock_export_metadata(),
+        consents: vec![],
     }
 }
 
@@ -45,6 +46,7 @@ pub fn mock_user_empty() -> UserDataExport {
             retention_policy: None,
         },
         metadata: mock_export_metadata(),
+        consents: vec![],
     }
 }
 
@@ -72,6 +74,7 @@ pub fn mock_user_large() -> UserDataExport {
         documents,
         settings: mock_settings(),
         metadata: mock_export_metadata(),
+        consents: vec![],
     }
 }
 
@@ -157,7 +157,7 @@ async fn test_redact_pii() {
     detector.initialize().await.unwrap();
 
     let text = "Email: test@example.com, SSN: 123-45-6789";
-    let redacted = detector.redact_pii(text).await.unwrap();
+    let redacted = detector.redact_pii(text, None).await.unwrap();
 
     assert!(!redacted.contains("test@example.com"), "Email should be redacted");
     assert!(!redacted.contains("123-45-6789"), "SSN should be redacted");
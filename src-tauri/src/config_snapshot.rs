@@ -0,0 +1,236 @@
+/// Effective configuration snapshot for support bundles
+///
+/// Collects the settings that shape PII detection, retrieval and generation
+/// into one JSON document a user can attach to a support request, and applies
+/// such a document to reproduce their setup. Only settings are captured: no
+/// keys, documents, exclusion terms or conversation content.
+use crate::llm_manager::{GenerationConfig, LLMManager};
+use crate::middleware::ConsentGuard;
+use crate::model_updates::is_offline_mode;
+use crate::pii_detector::{DetectionLayer, PIIDetectionConfig, PIIDetector};
+use crate::rag_engine::{RAGConfig, RAGEngine};
+use crate::scheduler::{ScheduleConfig, SchedulerHandle};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+/// Bumped when the snapshot layout changes incompatibly
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigSnapshot {
+    pub version: u32,
+    pub app_version: String,
+    pub created_at: String,
+    /// Includes the active detection layer
+    pub pii: PIIDetectionConfig,
+    /// Includes the embedding model
+    pub rag: RAGConfig,
+    pub generation: GenerationConfig,
+    /// Loaded LLM, if any; recorded for reference but not loaded on import
+    pub active_model: Option<String>,
+    /// Retention cleanup schedule; absent when the scheduler failed to start
+    pub scheduler: Option<ScheduleConfig>,
+    pub consent_strict_mode: bool,
+    /// Model downloads disabled through `HF_HUB_OFFLINE`; environment-controlled,
+    /// so reported but not applied on import
+    pub offline_mode: bool,
+}
+
+impl ConfigSnapshot {
+    /// Reject snapshots that would leave a component in an unusable state
+    pub fn validate(&self) -> Result<()> {
+        if self.version > SNAPSHOT_VERSION {
+            return Err(anyhow!(
+                "Snapshot version {} is newer than supported version {}",
+                self.version,
+                SNAPSHOT_VERSION
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.pii.confidence_threshold) {
+            return Err(anyhow!("PII confidence threshold must be between 0 and 1"));
+        }
+        if self.rag.chunk_size == 0 || self.rag.chunk_overlap >= self.rag.chunk_size {
+            return Err(anyhow!(
+                "RAG chunk overlap must be smaller than a non-zero chunk size"
+            ));
+        }
+        if self.rag.embedding_batch_size == 0 || self.rag.embedding_parallelism == 0 {
+            return Err(anyhow!(
+                "RAG embedding batch size and parallelism must be at least 1"
+            ));
+        }
+        if self.rag.embedding_model.trim().is_empty() {
+            return Err(anyhow!("RAG embedding model must not be empty"));
+        }
+        if self.generation.max_tokens == 0 {
+            return Err(anyhow!("Generation max_tokens must be at least 1"));
+        }
+        if self.generation.temperature < 0.0 {
+            return Err(anyhow!("Generation temperature must not be negative"));
+        }
+        if !(self.generation.top_p > 0.0 && self.generation.top_p <= 1.0) {
+            return Err(anyhow!("Generation top_p must be in (0, 1]"));
+        }
+        if let Some(scheduler) = &self.scheduler {
+            if scheduler.interval_hours == 0 {
+                return Err(anyhow!("Scheduler interval must be at least one hour"));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Gather the effective configuration of each component
+pub async fn capture_snapshot(
+    pii: &PIIDetector,
+    rag: &RAGEngine,
+    llm: &LLMManager,
+    scheduler: Option<&SchedulerHandle>,
+    consent_guard: &ConsentGuard,
+) -> ConfigSnapshot {
+    let scheduler = match scheduler {
+        Some(handle) => Some(ScheduleConfig {
+            // Next run is runtime state, not configuration
+            next_run: None,
+            ..handle.get_config().await
+        }),
+        None => None,
+    };
+
+    ConfigSnapshot {
+        version: SNAPSHOT_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at: chrono::Utc::now().to_rfc3339(),
+        pii: pii.get_config().await,
+        rag: rag.get_config().await,
+        generation: llm.get_generation_config().await,
+        active_model: llm.get_active_model().await,
+        scheduler,
+        consent_strict_mode: consent_guard.is_strict_mode(),
+        offline_mode: is_offline_mode(),
+    }
+}
+
+/// Validate and apply a snapshot; returns notes on settings that were not reproduced
+pub async fn apply_snapshot(
+    snapshot: &ConfigSnapshot,
+    pii: &PIIDetector,
+    rag: &RAGEngine,
+    llm: &LLMManager,
+    scheduler: Option<&SchedulerHandle>,
+    consent_guard: &ConsentGuard,
+) -> Result<Vec<String>> {
+    snapshot.validate()?;
+    let mut notes = Vec::new();
+
+    pii.update_config(snapshot.pii.clone()).await?;
+    if matches!(
+        snapshot.pii.detection_layer,
+        DetectionLayer::WithCandle | DetectionLayer::FullStack
+    ) && !pii.is_candle_available().await
+    {
+        notes.push("Candle NER model is not loaded; Layer 2 stays unavailable".to_string());
+    }
+
+    rag.update_config(snapshot.rag.clone()).await?;
+    llm.update_generation_config(snapshot.generation.clone())
+        .await?;
+    consent_guard.set_strict_mode(snapshot.consent_strict_mode);
+
+    match (&snapshot.scheduler, scheduler) {
+        (Some(config), Some(handle)) => handle.update_config(config.clone())?,
+        (Some(_), None) => notes.push("Scheduler is not running; schedule not applied".to_string()),
+        _ => {}
+    }
+
+    if let Some(model) = &snapshot.active_model {
+        if llm.get_active_model().await.as_deref() != Some(model.as_str()) {
+            notes.push(format!("Model '{}' must be loaded manually", model));
+        }
+    }
+    if snapshot.offline_mode != is_offline_mode() {
+        notes.push("Offline mode is set through HF_HUB_OFFLINE and was not changed".into());
+    }
+
+    Ok(notes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_snapshot_round_trip_reproduces_configuration() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("consent.db");
+
+        let pii = PIIDetector::new();
+        pii.set_detection_layer(DetectionLayer::RegexOnly)
+            .await
+            .unwrap();
+        pii.set_confidence_voting(true, None).await.unwrap();
+        let rag = RAGEngine::with_index_path(temp_dir.path().join("source"));
+        rag.update_config(RAGConfig {
+            chunk_size: 256,
+            chunk_overlap: 32,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        let llm = LLMManager::new().unwrap();
+        llm.update_generation_config(GenerationConfig {
+            temperature: 0.2,
+            max_tokens: 2048,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        let guard = ConsentGuard::new(db_path.clone(), false);
+
+        let exported = capture_snapshot(&pii, &rag, &llm, None, &guard).await;
+        let json = serde_json::to_string(&exported).unwrap();
+
+        let target_pii = PIIDetector::new();
+        let target_rag = RAGEngine::with_index_path(temp_dir.path().join("target"));
+        let target_llm = LLMManager::new().unwrap();
+        let target_guard = ConsentGuard::new(db_path, true);
+
+        let imported: ConfigSnapshot = serde_json::from_str(&json).unwrap();
+        let notes = apply_snapshot(
+            &imported,
+            &target_pii,
+            &target_rag,
+            &target_llm,
+            None,
+            &target_guard,
+        )
+        .await
+        .unwrap();
+        assert!(notes.is_empty(), "{:?}", notes);
+
+        let reapplied =
+            capture_snapshot(&target_pii, &target_rag, &target_llm, None, &target_guard).await;
+        let strip = |snapshot: &ConfigSnapshot| {
+            let mut value = serde_json::to_value(snapshot).unwrap();
+            value.as_object_mut().unwrap().remove("created_at");
+            value
+        };
+        assert_eq!(strip(&reapplied), strip(&exported));
+        assert_eq!(reapplied.rag.chunk_size, 256);
+        assert!(!target_guard.is_strict_mode());
+
+        // Invalid snapshots are rejected before anything is applied
+        let mut invalid = imported;
+        invalid.rag.chunk_overlap = invalid.rag.chunk_size;
+        assert!(apply_snapshot(
+            &invalid,
+            &target_pii,
+            &target_rag,
+            &target_llm,
+            None,
+            &target_guard
+        )
+        .await
+        .is_err());
+    }
+}
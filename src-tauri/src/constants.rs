@@ -58,6 +58,18 @@ pub const DEFAULT_MAX_TOKENS: usize = 2048;
 /// Safety margin for token overflow prevention (in tokens)
 pub const TOKEN_OVERFLOW_SAFETY_MARGIN: usize = 10;
 
+/// Idle time after which the loaded model is unloaded (in seconds)
+pub const DEFAULT_MODEL_IDLE_TIMEOUT_SECS: u64 = 900; // 15 minutes
+
+/// How often the idle-unload policy is checked (in seconds)
+pub const MODEL_IDLE_CHECK_INTERVAL_SECS: u64 = 30;
+
+/// Longest wait for each subsystem to stop during application shutdown (in seconds)
+pub const SHUTDOWN_STEP_TIMEOUT_SECS: u64 = 10;
+
+/// Corrective retries allowed when structured output fails schema validation
+pub const STRUCTURED_EXTRACTION_MAX_RETRIES: usize = 2;
+
 // ============================================================================
 // RAG Engine Configuration
 // ============================================================================
@@ -74,6 +86,9 @@ pub const RAG_MAX_CHUNK_SIZE: usize = 1000;
 /// Chunk overlap size for context preservation (in characters)
 pub const RAG_CHUNK_OVERLAP: usize = 200;
 
+/// Upper bound on paraphrases per query in query expansion; each adds a retrieval
+pub const RAG_MAX_QUERY_PARAPHRASES: usize = 8;
+
 // ============================================================================
 // Embedding Model Configuration
 // ============================================================================
@@ -103,6 +118,23 @@ pub const DB_POOL_SIZE: u32 = 5;
 /// Database query timeout (in seconds)
 pub const DB_QUERY_TIMEOUT_SECS: u64 = 30;
 
+/// Seconds a factory reset confirmation token stays valid
+pub const FACTORY_RESET_TOKEN_TTL_SECS: u64 = 300;
+
+/// Maximum rows returned by a read-only SQL query
+pub const SQL_MAX_ROWS: usize = 500;
+
+/// Time budget for a read-only SQL query, including waits on a busy database (in milliseconds)
+pub const SQL_QUERY_BUDGET_MS: u64 = 2000;
+
+/// SQLite VM instructions between time budget checks
+pub const SQL_PROGRESS_HANDLER_OPS: i32 = 1000;
+
+/// Tables a read-only SQL query may read; audit, consent, PII detection,
+/// original document and per-user chat tables stay out of reach
+pub const SQL_QUERYABLE_TABLES: &[&str] =
+    &["contract_obligations", "documents", "retention_policies"];
+
 // ============================================================================
 // PII Detection Configuration
 // ============================================================================
@@ -116,6 +148,18 @@ pub const PII_HIGH_CONFIDENCE: f32 = 0.85;
 /// Context window size for PII detection (in characters)
 pub const PII_CONTEXT_WINDOW: usize = 100;
 
+/// Characters held back while streaming output, so entities spanning
+/// several tokens are redacted before any part of them is emitted
+pub const PII_STREAM_HOLDBACK_CHARS: usize = 64;
+
+/// Fraction of the remaining gap to 1.0 closed by each additional layer that
+/// agrees on an entity when confidence voting is enabled
+pub const PII_VOTE_AGREEMENT_BOOST: f32 = 0.5;
+
+/// Detections per document after which detection stops and the result is
+/// flagged for manual review, bounding the cost of pathological input
+pub const PII_MAX_ENTITIES_PER_DOCUMENT: usize = 10_000;
+
 // ============================================================================
 // File Processing Limits
 // ============================================================================
@@ -126,9 +170,39 @@ pub const MAX_FILE_SIZE_MB: usize = 100;
 /// Maximum number of pages to extract from PDF
 pub const MAX_PDF_PAGES: usize = 1000;
 
+/// Letters and digits per page below which a PDF's text layer is treated as
+/// missing (a scanned document) and OCR is tried instead
+pub const PDF_OCR_MIN_CHARS_PER_PAGE: usize = 40;
+
+/// Size of the text chunks large documents are read and PII-scanned in (in bytes)
+pub const PII_STREAM_CHUNK_BYTES: usize = 64 * 1024;
+
+/// Tail of each chunk rescanned with the next one, so PII cut by a chunk
+/// boundary is still detected whole (in bytes)
+pub const PII_STREAM_OVERLAP_BYTES: usize = 1024;
+
+/// Original text returned with a document PII analysis, for preview (in bytes)
+pub const PII_ANALYSIS_PREVIEW_BYTES: usize = 64 * 1024;
+
 /// Maximum text extraction length (in characters)
 pub const MAX_TEXT_EXTRACTION_CHARS: usize = 10_000_000; // 10M chars
 
+/// Maximum number of file entries in an uploaded archive (including nested archives)
+pub const MAX_ARCHIVE_ENTRIES: usize = 1000;
+
+/// Maximum total uncompressed size of an uploaded archive (in MB)
+pub const MAX_ARCHIVE_TOTAL_MB: u64 = 200;
+
+/// Maximum uncompressed/compressed ratio for a single archive entry
+pub const MAX_ARCHIVE_COMPRESSION_RATIO: u64 = 100;
+
+/// Entries smaller than this are exempt from the compression-ratio check,
+/// since small repetitive text legitimately compresses very well
+pub const ARCHIVE_RATIO_CHECK_MIN_BYTES: u64 = 1024 * 1024;
+
+/// Maximum nesting depth of archives inside archives
+pub const MAX_ARCHIVE_DEPTH: usize = 2;
+
 // ============================================================================
 // Network and API Configuration
 // ============================================================================
@@ -142,6 +216,12 @@ pub const MAX_HTTP_RETRIES: usize = 3;
 /// Retry backoff delay (in milliseconds)
 pub const RETRY_BACKOFF_MS: u64 = 1000;
 
+/// How long HuggingFace model search results are reused (in seconds)
+pub const HF_SEARCH_CACHE_TTL_SECS: u64 = 300;
+
+/// Most results a HuggingFace model search may request
+pub const HF_SEARCH_MAX_RESULTS: usize = 50;
+
 // ============================================================================
 // Temporary File Management
 // ============================================================================
@@ -152,6 +232,22 @@ pub const TEMP_FILE_PREFIX: &str = "bear_ai";
 /// Temporary file cleanup age (in seconds)
 pub const TEMP_FILE_CLEANUP_AGE_SECS: u64 = 3600; // 1 hour
 
+// ============================================================================
+// Model Downloads
+// ============================================================================
+
+/// Extra free space required on the models volume beyond the model size (in MB)
+pub const DOWNLOAD_DISK_HEADROOM_MB: u64 = 1024;
+
+/// Minimum interval between progress updates for a model download (in ms)
+pub const DOWNLOAD_PROGRESS_INTERVAL_MS: u64 = 250;
+
+/// RAM needed per MB of model weights kept in system memory (KV cache, buffers)
+pub const MODEL_RAM_OVERHEAD_RATIO: f32 = 1.3;
+
+/// Fixed RAM needed by the inference runtime regardless of model size (in MB)
+pub const MODEL_RAM_BASE_MB: u64 = 512;
+
 // ============================================================================
 // GPU Layer Offloading
 // ============================================================================
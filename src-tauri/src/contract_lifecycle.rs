@@ -0,0 +1,156 @@
+/// Contract lifecycle extraction for calendaring
+///
+/// Pulls the effective date, the term length, whether the contract renews
+/// automatically and the termination notice period out of contract text and
+/// normalizes them (term in months, notice in days), so renewal and notice
+/// deadlines can be put on a calendar.
+use crate::obligations::parse_date;
+use chrono::NaiveDate;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+lazy_static! {
+    static ref EFFECTIVE_DATE_PATTERN: Regex = Regex::new(
+        r"(?i)\b(?:effective\s+(?:date\s+(?:is|of|shall\s+be)\s+|(?:as\s+of|from|on)\s+)|commenc(?:e|es|ing)\s+on\s+)(?:the\s+)?(\d{4}-\d{2}-\d{2}|(?:january|february|march|april|may|june|july|august|september|october|november|december)\s+\d{1,2},?\s+\d{4}|\d{1,2}\s+(?:january|february|march|april|may|june|july|august|september|october|november|december)\s+\d{4}|\d{1,2}/\d{1,2}/\d{4})\b"
+    )
+    .expect("Effective date regex is invalid");
+    static ref TERM_PATTERN: Regex = Regex::new(
+        r"(?i)\b(?:term|period)\s+of\s+(?:(\w+)\s+\()?(\w+)\)?\s+(month|year)s?\b"
+    )
+    .expect("Term regex is invalid");
+    static ref NOTICE_PATTERN: Regex = Regex::new(
+        r"(?i)\b(?:(\w+)\s+\()?(\w+)\)?\s+(?:calendar\s+|business\s+)?(day|week|month)s?['’]?\s+(?:prior\s+|advance\s+)?(?:written\s+)?notice\b|\bnotice\s+(?:period\s+)?of\s+(?:at\s+least\s+)?(?:(\w+)\s+\()?(\w+)\)?\s+(?:calendar\s+|business\s+)?(day|week|month)s?\b"
+    )
+    .expect("Notice period regex is invalid");
+    static ref AUTO_RENEWAL_PATTERN: Regex = Regex::new(
+        r"(?i)\b(?:auto(?:matically)?[-\s]?renew(?:s|ed|ing|al)?|renew(?:s|ed)?\s+automatically|evergreen)\b"
+    )
+    .expect("Auto-renewal regex is invalid");
+    static ref NO_RENEWAL_PATTERN: Regex = Regex::new(
+        r"(?i)\b(?:(?:shall|will|does)\s+not\s+(?:be\s+)?(?:automatically\s+)?renew|no\s+automatic\s+renewal|not\s+auto(?:matically)?[-\s]?renew)"
+    )
+    .expect("No-renewal regex is invalid");
+}
+
+/// Average month length used to express month-based notice in days
+const DAYS_PER_MONTH: u32 = 30;
+
+/// Spelled-out numbers commonly used for terms and notice periods
+const NUMBER_WORDS: &[(&str, u32)] = &[
+    ("one", 1),
+    ("two", 2),
+    ("three", 3),
+    ("four", 4),
+    ("five", 5),
+    ("six", 6),
+    ("seven", 7),
+    ("ten", 10),
+    ("twelve", 12),
+    ("fourteen", 14),
+    ("fifteen", 15),
+    ("eighteen", 18),
+    ("twenty", 20),
+    ("thirty", 30),
+    ("sixty", 60),
+    ("ninety", 90),
+];
+
+/// Lifecycle terms of a contract, normalized for calendaring
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ContractLifecycle {
+    pub effective_date: Option<NaiveDate>,
+    /// Initial term, with years converted to months
+    pub term_months: Option<u32>,
+    /// The contract renews unless notice is given
+    pub auto_renewal: bool,
+    /// Termination or non-renewal notice, with weeks and months converted to days
+    pub notice_period_days: Option<u32>,
+}
+
+/// Extract the effective date, term, renewal and notice period from contract text
+pub fn extract_lifecycle(content: &str) -> ContractLifecycle {
+    let effective_date = EFFECTIVE_DATE_PATTERN
+        .captures_iter(content)
+        .find_map(|caps| parse_date(&caps[1]));
+
+    let term_months = TERM_PATTERN.captures_iter(content).find_map(|caps| {
+        let count = parse_count(caps.get(1).map(|m| m.as_str()), &caps[2])?;
+        Some(match caps[3].to_lowercase().as_str() {
+            "year" => count * 12,
+            _ => count,
+        })
+    });
+
+    let notice_period_days = NOTICE_PATTERN.captures_iter(content).find_map(|caps| {
+        let (word, number, unit) = if caps.get(2).is_some() {
+            (caps.get(1), &caps[2], &caps[3])
+        } else {
+            (caps.get(4), &caps[5], &caps[6])
+        };
+        let count = parse_count(word.map(|m| m.as_str()), number)?;
+        Some(match unit.to_lowercase().as_str() {
+            "week" => count * 7,
+            "month" => count * DAYS_PER_MONTH,
+            _ => count,
+        })
+    });
+
+    let auto_renewal =
+        AUTO_RENEWAL_PATTERN.is_match(content) && !NO_RENEWAL_PATTERN.is_match(content);
+
+    ContractLifecycle {
+        effective_date,
+        term_months,
+        auto_renewal,
+        notice_period_days,
+    }
+}
+
+/// Read "12", "twelve" or "twelve (12)"; the digits win when both are given
+fn parse_count(word: Option<&str>, number: &str) -> Option<u32> {
+    number
+        .parse()
+        .ok()
+        .or_else(|| word_to_number(number))
+        .or_else(|| word.and_then(word_to_number))
+        .filter(|&n| n > 0)
+}
+
+fn word_to_number(word: &str) -> Option<u32> {
+    NUMBER_WORDS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(word))
+        .map(|&(_, n)| n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lifecycle_fields_extracted_and_normalized() {
+        let lifecycle = extract_lifecycle(
+            "This Agreement is effective as of January 1, 2024 for a term of 12 months, \
+             auto-renewing unless 30 days notice is given.",
+        );
+
+        assert_eq!(
+            lifecycle.effective_date,
+            NaiveDate::from_ymd_opt(2024, 1, 1)
+        );
+        assert_eq!(lifecycle.term_months, Some(12));
+        assert!(lifecycle.auto_renewal);
+        assert_eq!(lifecycle.notice_period_days, Some(30));
+
+        let fixed = extract_lifecycle(
+            "The initial term of two (2) years commences on 2025-03-01. This Agreement \
+             shall not automatically renew. Either party may terminate on three months' \
+             written notice.",
+        );
+        assert_eq!(fixed.effective_date, NaiveDate::from_ymd_opt(2025, 3, 1));
+        assert_eq!(fixed.term_months, Some(24));
+        assert!(!fixed.auto_renewal);
+        assert_eq!(fixed.notice_period_days, Some(90));
+    }
+}
@@ -10,6 +10,7 @@
 use anyhow::{Context, Result};
 use r2d2::PooledConnection;
 use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{params, Connection, OptionalExtension};
 use serde_json::Value as JsonValue;
 use std::sync::Arc;
 use tracing;
@@ -178,6 +179,124 @@ impl ChatEncryptionLayer {
         Ok((chat_id, role, decrypted_content, decrypted_metadata))
     }
 
+    /// Owner and decrypted metadata of a message, or None when the message does
+    /// not exist. Metadata that fails to decrypt is an error, so callers never
+    /// overwrite metadata they could not read.
+    pub fn message_metadata(
+        &self,
+        conn: &Connection,
+        message_id: i64,
+    ) -> Result<Option<(String, Option<String>)>> {
+        let row: Option<(String, bool, Option<String>)> = conn
+            .query_row(
+                "SELECT COALESCE(user_id, ''), COALESCE(encrypted, 0), metadata
+                 FROM chat_messages WHERE id = ?1",
+                [message_id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()?;
+        let Some((user_id, encrypted, metadata)) = row else {
+            return Ok(None);
+        };
+
+        let metadata = match metadata.filter(|meta| !meta.is_empty()) {
+            Some(meta) if encrypted => Some(
+                self.encryptor
+                    .decrypt_from_json(&meta, &self.user_key(&user_id)?)
+                    .with_context(|| {
+                        format!("Failed to decrypt metadata of message {}", message_id)
+                    })?,
+            ),
+            metadata => metadata,
+        };
+        Ok(Some((user_id, metadata)))
+    }
+
+    /// Replace a message's metadata, encrypted for its owner when the message
+    /// itself is encrypted
+    pub fn update_message_metadata(
+        &self,
+        conn: &Connection,
+        message_id: i64,
+        metadata: &str,
+    ) -> Result<()> {
+        let (user_id, encrypted): (String, bool) = conn
+            .query_row(
+                "SELECT COALESCE(user_id, ''), COALESCE(encrypted, 0)
+                 FROM chat_messages WHERE id = ?1",
+                [message_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?
+            .with_context(|| format!("Chat message not found: {}", message_id))?;
+
+        let stored = if encrypted {
+            let user_key = self.user_key(&user_id)?;
+            let encrypted = self.encryptor.encrypt(metadata, &user_key, &user_id)?;
+            serde_json::to_string(&encrypted).context("Failed to serialize encrypted metadata")?
+        } else {
+            metadata.to_string()
+        };
+        conn.execute(
+            "UPDATE chat_messages SET metadata = ?1 WHERE id = ?2",
+            params![stored, message_id],
+        )
+        .context("Failed to update message metadata")?;
+        Ok(())
+    }
+
+    fn user_key(&self, user_id: &str) -> Result<Vec<u8>> {
+        let master_key = self.key_manager.get_or_create_key()?;
+        UserKeyDerivation::new(master_key)?.derive_default_key(user_id)
+    }
+
+    /// Retrieve all messages for a chat session (decrypted), after checking
+    /// that the session belongs to `user_id`
+    pub fn retrieve_user_session_messages(
+        &self,
+        conn: &PooledConnection<SqliteConnectionManager>,
+        chat_id: &str,
+        user_id: &str,
+    ) -> Result<Vec<JsonValue>> {
+        Self::ensure_session_owner(conn, chat_id, user_id)?;
+        self.retrieve_chat_session_messages(conn, chat_id)
+    }
+
+    /// Fail unless every message of the session, and the session itself when
+    /// it records an owner, belongs to `user_id`. Messages without an owner
+    /// count as someone else's, since their owner cannot be established.
+    fn ensure_session_owner(
+        conn: &PooledConnection<SqliteConnectionManager>,
+        chat_id: &str,
+        user_id: &str,
+    ) -> Result<()> {
+        let foreign_messages: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM chat_messages
+             WHERE chat_id = ?1 AND COALESCE(user_id, '') != ?2",
+            [chat_id, user_id],
+            |row| row.get(0),
+        )?;
+
+        let session_has_owner: bool = conn.query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('chat_sessions') WHERE name = 'user_id'",
+            [],
+            |row| row.get(0),
+        )?;
+        let foreign_session = session_has_owner && {
+            let owners: i64 = conn.query_row(
+                "SELECT COUNT(*) FROM chat_sessions WHERE id = ?1 AND user_id != ?2",
+                [chat_id, user_id],
+                |row| row.get(0),
+            )?;
+            owners > 0
+        };
+
+        if foreign_messages > 0 || foreign_session {
+            anyhow::bail!("Chat session {} does not belong to this user", chat_id);
+        }
+        Ok(())
+    }
+
     /// Retrieve all messages for a chat session (decrypted)
     pub fn retrieve_chat_session_messages(
         &self,
@@ -410,4 +529,43 @@ mod tests {
         assert_eq!(stats["plaintext_messages"], 0);
         assert_eq!(stats["encryption_percentage"], 100.0);
     }
+
+    #[test]
+    fn test_session_of_another_user_is_not_retrieved() {
+        let pool = setup_test_db();
+        let conn = pool.get().unwrap();
+        conn.execute(
+            "CREATE TABLE chat_sessions (id TEXT PRIMARY KEY, user_id TEXT NOT NULL)",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO chat_sessions (id, user_id) VALUES ('chat1', 'alice'), ('chat2', 'bob')",
+            [],
+        )
+        .unwrap();
+
+        let key_manager = Arc::new(KeyManager::new().unwrap());
+        let encryption_layer = ChatEncryptionLayer::new(key_manager).unwrap();
+        encryption_layer
+            .store_encrypted_message(&conn, "chat1", "user", "Alice's lease", "alice", None)
+            .unwrap();
+
+        let messages = encryption_layer
+            .retrieve_user_session_messages(&conn, "chat1", "alice")
+            .unwrap();
+        assert_eq!(messages[0]["content"], "Alice's lease");
+        assert!(encryption_layer
+            .retrieve_user_session_messages(&conn, "chat1", "mallory")
+            .is_err());
+
+        // A session row owned by someone else is refused before it has messages
+        assert!(encryption_layer
+            .retrieve_user_session_messages(&conn, "chat2", "alice")
+            .is_err());
+        assert!(encryption_layer
+            .retrieve_user_session_messages(&conn, "new-chat", "alice")
+            .unwrap()
+            .is_empty());
+    }
 }
@@ -23,11 +23,14 @@ use chrono::Utc;
 use rusqlite::{params, Connection};
 use serde_json;
 use std::path::PathBuf;
+use std::sync::Arc;
 
+use super::field_encryption::{is_encrypted_field, FieldEncryptionConfig, FieldEncryptionLayer};
 use crate::export_engine::{
-    ChatExport, ComplianceInfo, DocumentExport, ExportMetadata, MessageExport, PIIDetection,
-    SettingsExport, UserDataExport,
+    ChatExport, ComplianceInfo, ConsentExport, DocumentExport, ExportMetadata, MessageExport,
+    PIIDetection, SettingsExport, UserDataExport,
 };
+use crate::security::KeyManager;
 
 /// Database Export Manager - fetches all data for single-user desktop app
 ///
@@ -56,6 +59,7 @@ impl ExportIntegration {
         let chats = self.fetch_chat_history()?;
         let documents = self.fetch_documents()?;
         let settings = self.fetch_user_settings()?;
+        let consents = self.fetch_consent_history()?;
 
         // Generate export metadata
         let metadata = self.generate_export_metadata(&chats, &documents)?;
@@ -68,6 +72,7 @@ impl ExportIntegration {
             documents,
             settings,
             metadata,
+            consents,
         })
     }
 
@@ -214,7 +219,24 @@ impl ExportIntegration {
             })?
             .collect::<Result<Vec<_>, _>>()?;
 
-        Ok(detections)
+        // Replacement text may be stored with field-level encryption
+        if !detections
+            .iter()
+            .any(|detection| is_encrypted_field(&detection.replacement_text))
+        {
+            return Ok(detections);
+        }
+        let layer = FieldEncryptionLayer::new(
+            Arc::new(KeyManager::new()?),
+            FieldEncryptionConfig::default(),
+        );
+        detections
+            .into_iter()
+            .map(|mut detection| {
+                detection.replacement_text = layer.decrypt_field(&detection.replacement_text)?;
+                Ok(detection)
+            })
+            .collect()
     }
 
     /// Fetch user settings and preferences
@@ -303,6 +325,31 @@ impl ExportIntegration {
         hex::encode(hasher.finalize())
     }
 
+    /// Fetch consent grants and withdrawals, newest version first per type
+    fn fetch_consent_history(&self) -> Result<Vec<ConsentExport>> {
+        let conn = self.get_connection()?;
+
+        let mut stmt = conn.prepare(
+            "SELECT consent_type, granted, version, granted_at, revoked_at
+             FROM user_consent
+             ORDER BY consent_type, version DESC",
+        )?;
+
+        let consents = stmt
+            .query_map([], |row| {
+                Ok(ConsentExport {
+                    consent_type: row.get(0)?,
+                    granted: row.get(1)?,
+                    version: row.get(2)?,
+                    granted_at: row.get(3)?,
+                    revoked_at: row.get(4)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(consents)
+    }
+
     /// Export consent data from compliance module
     /// Single-user app: fetches all consent records
     pub fn fetch_consent_data(&self) -> Result<serde_json::Value> {
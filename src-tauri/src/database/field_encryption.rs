@@ -0,0 +1,472 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2025 BEAR AI LLM
+//
+// Field-Level Encryption for PII Columns
+// GDPR Article 32 - Security of Processing
+//
+// Encrypts `documents.content` and `pii_detections.replacement_text` with the
+// same per-user AES-256-GCM keys used for chat messages. Encrypted values are
+// stored as a prefixed JSON `EncryptedMessage`, so plaintext rows written
+// before the layer was enabled stay readable and can be migrated in place.
+
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use zeroize::Zeroize;
+
+use crate::export_engine::PIIDetection;
+use crate::security::{ChatEncryptor, EncryptedMessage, KeyManager, UserKeyDerivation};
+
+/// Marks a column value as encrypted by this layer
+const ENCRYPTED_PREFIX: &str = "bear-enc:v1:";
+
+/// Opt-in switch for field-level encryption
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FieldEncryptionConfig {
+    /// Encrypt `content` and `replacement_text` on write. Reads decrypt
+    /// encrypted values whether or not this is set.
+    #[serde(default)]
+    pub enabled: bool,
+}
+
+impl FieldEncryptionConfig {
+    /// Setting saved at `path`; disabled when none was saved
+    pub fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Save the setting to `path` so it survives a restart
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, content).context("Failed to write field encryption setting")
+    }
+}
+
+/// Rows encrypted by `migrate_existing_rows`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldMigrationStats {
+    pub documents: usize,
+    pub pii_detections: usize,
+}
+
+/// Whether a stored column value was written by the encryption layer
+pub fn is_encrypted_field(value: &str) -> bool {
+    value.starts_with(ENCRYPTED_PREFIX)
+}
+
+fn has_column(conn: &Connection, table: &str, column: &str) -> Result<bool> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let columns = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(columns.iter().any(|name| name == column))
+}
+
+/// Transparent encryption of PII columns in `documents` and `pii_detections`
+pub struct FieldEncryptionLayer {
+    key_manager: Arc<KeyManager>,
+    encryptor: ChatEncryptor,
+    config: Mutex<FieldEncryptionConfig>,
+    // Argon2id derivation is slow, so derived keys are kept per user
+    user_keys: Mutex<HashMap<String, Vec<u8>>>,
+}
+
+impl FieldEncryptionLayer {
+    pub fn new(key_manager: Arc<KeyManager>, config: FieldEncryptionConfig) -> Self {
+        Self {
+            key_manager,
+            encryptor: ChatEncryptor::new(),
+            config: Mutex::new(config),
+            user_keys: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.lock().unwrap().enabled
+    }
+
+    /// Switch encryption on write on or off; stored values are unaffected
+    pub fn set_config(&self, config: FieldEncryptionConfig) {
+        *self.config.lock().unwrap() = config;
+    }
+
+    /// Create the `documents` and `pii_detections` tables if missing
+    pub fn ensure_tables(conn: &Connection) -> Result<()> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS documents (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                filename TEXT NOT NULL,
+                content TEXT NOT NULL,
+                file_type TEXT NOT NULL,
+                upload_date DATETIME DEFAULT CURRENT_TIMESTAMP,
+                chunk_count INTEGER DEFAULT 0,
+                user_id TEXT NOT NULL DEFAULT 'default_user',
+                retention_until DATETIME
+            );
+            CREATE TABLE IF NOT EXISTS pii_detections (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                document_id INTEGER NOT NULL,
+                pii_type TEXT NOT NULL,
+                replacement_text TEXT NOT NULL,
+                confidence REAL NOT NULL,
+                position_start INTEGER NOT NULL,
+                position_end INTEGER NOT NULL,
+                user_id TEXT NOT NULL DEFAULT 'default_user',
+                FOREIGN KEY (document_id) REFERENCES documents (id)
+            );",
+        )
+        .context("Failed to create document tables")
+    }
+
+    fn user_key(&self, user_id: &str) -> Result<Vec<u8>> {
+        let mut keys = self.user_keys.lock().unwrap();
+        if let Some(key) = keys.get(user_id) {
+            return Ok(key.clone());
+        }
+
+        let master_key = self.key_manager.get_or_create_key()?;
+        let key = UserKeyDerivation::new(master_key)?.derive_default_key(user_id)?;
+        keys.insert(user_id.to_string(), key.clone());
+        Ok(key)
+    }
+
+    fn seal(&self, value: &str, user_id: &str) -> Result<String> {
+        let mut key = self.user_key(user_id)?;
+        let sealed = self.encryptor.encrypt_to_json(value, &key, user_id);
+        key.zeroize();
+        Ok(format!("{}{}", ENCRYPTED_PREFIX, sealed?))
+    }
+
+    /// Value to store for a PII column; unchanged when the layer is disabled
+    pub fn encrypt_field(&self, value: &str, user_id: &str) -> Result<String> {
+        if !self.is_enabled() || is_encrypted_field(value) {
+            return Ok(value.to_string());
+        }
+        self.seal(value, user_id)
+    }
+
+    /// Plaintext of a stored PII column; legacy plaintext is returned as is
+    pub fn decrypt_field(&self, stored: &str) -> Result<String> {
+        let Some(json) = stored.strip_prefix(ENCRYPTED_PREFIX) else {
+            return Ok(stored.to_string());
+        };
+
+        let encrypted: EncryptedMessage =
+            serde_json::from_str(json).context("Failed to deserialize encrypted field")?;
+        let mut key = self.user_key(&encrypted.user_id)?;
+        let plaintext = self.encryptor.decrypt(&encrypted, &key);
+        key.zeroize();
+        plaintext.context("Failed to decrypt field")
+    }
+
+    /// Insert a document owned by `user_id`, encrypting its cleaned content
+    pub fn insert_document(
+        &self,
+        conn: &Connection,
+        filename: &str,
+        content: &str,
+        file_type: &str,
+        user_id: &str,
+    ) -> Result<i64> {
+        let content = self.encrypt_field(content, user_id)?;
+        conn.execute(
+            "INSERT INTO documents (filename, content, file_type, user_id)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![filename, content, file_type, user_id],
+        )
+        .context("Failed to insert document")?;
+
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Decrypted content of a document
+    pub fn get_document_content(&self, conn: &Connection, document_id: i64) -> Result<String> {
+        let stored: String = conn
+            .query_row(
+                "SELECT content FROM documents WHERE id = ?1",
+                [document_id],
+                |row| row.get(0),
+            )
+            .context("Failed to retrieve document")?;
+
+        self.decrypt_field(&stored)
+    }
+
+    /// Insert a PII detection, encrypting its replacement text
+    pub fn insert_pii_detection(
+        &self,
+        conn: &Connection,
+        document_id: i64,
+        detection: &PIIDetection,
+        user_id: &str,
+    ) -> Result<i64> {
+        let replacement_text = self.encrypt_field(&detection.replacement_text, user_id)?;
+        conn.execute(
+            "INSERT INTO pii_detections
+                (document_id, pii_type, replacement_text, confidence, position_start, position_end,
+                 user_id)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                document_id,
+                detection.pii_type,
+                replacement_text,
+                detection.confidence,
+                detection.position_start as i64,
+                detection.position_end as i64,
+                user_id,
+            ],
+        )
+        .context("Failed to insert PII detection")?;
+
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Decrypted PII detections for a document, in document order
+    pub fn get_pii_detections(
+        &self,
+        conn: &Connection,
+        document_id: i64,
+    ) -> Result<Vec<PIIDetection>> {
+        let mut stmt = conn.prepare(
+            "SELECT pii_type, replacement_text, confidence, position_start, position_end
+             FROM pii_detections
+             WHERE document_id = ?1
+             ORDER BY position_start ASC",
+        )?;
+
+        let rows = stmt
+            .query_map([document_id], |row| {
+                Ok(PIIDetection {
+                    pii_type: row.get(0)?,
+                    replacement_text: row.get(1)?,
+                    confidence: row.get(2)?,
+                    position_start: row.get::<_, i64>(3)? as usize,
+                    position_end: row.get::<_, i64>(4)? as usize,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        rows.into_iter()
+            .map(|mut detection| {
+                detection.replacement_text = self.decrypt_field(&detection.replacement_text)?;
+                Ok(detection)
+            })
+            .collect()
+    }
+
+    /// Encrypt plaintext rows written before the layer was enabled
+    ///
+    /// Each row is encrypted under the key of the user owning it, so erasing
+    /// one user's key still leaves other users' rows readable. Runs in one
+    /// transaction; rows that are already encrypted are skipped, so the
+    /// migration can be re-run safely.
+    pub fn migrate_existing_rows(&self, conn: &mut Connection) -> Result<FieldMigrationStats> {
+        if !self.is_enabled() {
+            anyhow::bail!("Field encryption is disabled; enable it before migrating");
+        }
+
+        let tx = conn.transaction()?;
+        let documents = self.migrate_column(&tx, "documents", "content")?;
+        let pii_detections = self.migrate_column(&tx, "pii_detections", "replacement_text")?;
+        tx.commit()
+            .context("Failed to commit field encryption migration")?;
+
+        tracing::info!(documents, pii_detections, "Encrypted existing PII columns");
+        Ok(FieldMigrationStats {
+            documents,
+            pii_detections,
+        })
+    }
+
+    fn migrate_column(&self, conn: &Connection, table: &str, column: &str) -> Result<usize> {
+        // Rows from before per-user columns belong to the default user
+        let owner = if has_column(conn, table, "user_id")? {
+            "COALESCE(user_id, 'default_user')"
+        } else {
+            "'default_user'"
+        };
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id, {column}, {owner} FROM {table}
+             WHERE {column} IS NOT NULL AND {column} NOT LIKE ?1"
+        ))?;
+        let rows: Vec<(i64, String, String)> = stmt
+            .query_map([format!("{}%", ENCRYPTED_PREFIX)], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for (id, value, user_id) in &rows {
+            conn.execute(
+                &format!("UPDATE {table} SET {column} = ?1 WHERE id = ?2"),
+                params![self.seal(value, user_id)?, id],
+            )
+            .with_context(|| format!("Failed to encrypt {}.{} for row {}", table, column, id))?;
+        }
+
+        Ok(rows.len())
+    }
+}
+
+impl Drop for FieldEncryptionLayer {
+    fn drop(&mut self) {
+        if let Ok(mut keys) = self.user_keys.lock() {
+            for key in keys.values_mut() {
+                key.zeroize();
+            }
+            keys.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup_test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        FieldEncryptionLayer::ensure_tables(&conn).unwrap();
+        conn
+    }
+
+    fn detection(replacement_text: &str) -> PIIDetection {
+        PIIDetection {
+            pii_type: "EMAIL".to_string(),
+            replacement_text: replacement_text.to_string(),
+            confidence: 0.95,
+            position_start: 10,
+            position_end: 30,
+        }
+    }
+
+    fn field_layer(enabled: bool) -> FieldEncryptionLayer {
+        let key_manager = Arc::new(KeyManager::new().unwrap());
+        FieldEncryptionLayer::new(key_manager, FieldEncryptionConfig { enabled })
+    }
+
+    #[test]
+    fn test_field_encryption_round_trip() {
+        let conn = setup_test_db();
+        let layer = field_layer(true);
+
+        let doc_id = layer
+            .insert_document(&conn, "nda.pdf", "Client [NAME] agrees", "pdf", "user1")
+            .unwrap();
+        layer
+            .insert_pii_detection(&conn, doc_id, &detection("[EMAIL]"), "user1")
+            .unwrap();
+
+        // Nothing readable is stored
+        let (content, replacement): (String, String) = conn
+            .query_row(
+                "SELECT d.content, p.replacement_text
+                 FROM documents d JOIN pii_detections p ON p.document_id = d.id",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert!(is_encrypted_field(&content) && !content.contains("agrees"));
+        assert!(is_encrypted_field(&replacement) && !replacement.contains("[EMAIL]"));
+
+        assert_eq!(
+            layer.get_document_content(&conn, doc_id).unwrap(),
+            "Client [NAME] agrees"
+        );
+        let detections = layer.get_pii_detections(&conn, doc_id).unwrap();
+        assert_eq!(detections.len(), 1);
+        assert_eq!(detections[0].replacement_text, "[EMAIL]");
+
+        // Disabled layers write plaintext but still read encrypted rows
+        let plain = field_layer(false);
+        let plain_id = plain
+            .insert_document(&conn, "memo.txt", "plain memo", "txt", "user1")
+            .unwrap();
+        assert_eq!(
+            plain.get_document_content(&conn, doc_id).unwrap(),
+            "Client [NAME] agrees"
+        );
+        assert_eq!(
+            plain.get_document_content(&conn, plain_id).unwrap(),
+            "plain memo"
+        );
+    }
+
+    #[test]
+    fn test_migration_encrypts_existing_rows() {
+        let mut conn = setup_test_db();
+        let plain = field_layer(false);
+        let doc_id = plain
+            .insert_document(&conn, "lease.docx", "Tenant [NAME]", "docx", "user1")
+            .unwrap();
+        plain
+            .insert_pii_detection(&conn, doc_id, &detection("[PHONE]"), "user1")
+            .unwrap();
+
+        let other_id = plain
+            .insert_document(&conn, "will.pdf", "Testator [NAME]", "pdf", "user2")
+            .unwrap();
+
+        assert!(plain.migrate_existing_rows(&mut conn).is_err());
+
+        let layer = field_layer(false);
+        layer.set_config(FieldEncryptionConfig { enabled: true });
+        let stats = layer.migrate_existing_rows(&mut conn).unwrap();
+        assert_eq!(
+            stats,
+            FieldMigrationStats {
+                documents: 2,
+                pii_detections: 1
+            }
+        );
+
+        // Each row is sealed for the user owning it
+        let owner = |id: i64| -> String {
+            let stored: String = conn
+                .query_row("SELECT content FROM documents WHERE id = ?1", [id], |row| {
+                    row.get(0)
+                })
+                .unwrap();
+            let encrypted: EncryptedMessage =
+                serde_json::from_str(stored.strip_prefix(ENCRYPTED_PREFIX).unwrap()).unwrap();
+            encrypted.user_id
+        };
+        assert_eq!(owner(doc_id), "user1");
+        assert_eq!(owner(other_id), "user2");
+
+        let stored: String = conn
+            .query_row("SELECT content FROM documents", [], |row| row.get(0))
+            .unwrap();
+        assert!(is_encrypted_field(&stored));
+        assert_eq!(
+            layer.get_document_content(&conn, doc_id).unwrap(),
+            "Tenant [NAME]"
+        );
+        assert_eq!(
+            layer.get_pii_detections(&conn, doc_id).unwrap()[0].replacement_text,
+            "[PHONE]"
+        );
+
+        // Already encrypted rows are left alone
+        assert_eq!(
+            layer.migrate_existing_rows(&mut conn).unwrap(),
+            FieldMigrationStats::default()
+        );
+    }
+
+    #[test]
+    fn test_field_encryption_setting_survives_restart() {
+        let path =
+            std::env::temp_dir().join(format!("field_encryption_{}.json", uuid::Uuid::new_v4()));
+        assert!(!FieldEncryptionConfig::load(&path).enabled);
+
+        FieldEncryptionConfig { enabled: true }.save(&path).unwrap();
+        assert!(FieldEncryptionConfig::load(&path).enabled);
+
+        let _ = std::fs::remove_file(path);
+    }
+}
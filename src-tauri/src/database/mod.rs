@@ -3,5 +3,6 @@
 
 pub mod chat_encryption_integration;
 pub mod export_integration;
+pub mod field_encryption;
 
 // ChatEncryptionLayer and ExportIntegration are internal to database module
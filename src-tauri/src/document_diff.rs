@@ -0,0 +1,192 @@
+/// Sentence-level comparison of two versions of a document
+///
+/// The textual pass aligns sentences with a longest-common-subsequence diff, so
+/// unchanged sentences anchor the comparison. Each run of removed and added
+/// sentences between anchors is then paired semantically: a removed and an
+/// added sentence that say nearly the same thing are reported as one
+/// modification rather than an unrelated removal and addition.
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Modified,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SentenceChange {
+    pub kind: ChangeKind,
+    pub previous: Option<String>,
+    pub current: Option<String>,
+    /// Semantic similarity of a modified sentence to the one it replaced
+    pub similarity: Option<f32>,
+}
+
+/// Sentences removed and added between two unchanged sentences
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hunk<'a> {
+    pub removed: Vec<&'a str>,
+    pub added: Vec<&'a str>,
+}
+
+/// Compare sentences ignoring differences in whitespace
+fn same_sentence(a: &str, b: &str) -> bool {
+    a.split_whitespace().eq(b.split_whitespace())
+}
+
+/// Runs of differing sentences, in document order
+pub fn diff_sentences<'a>(previous: &[&'a str], current: &[&'a str]) -> Vec<Hunk<'a>> {
+    let (n, m) = (previous.len(), current.len());
+    // lcs[i][j]: length of the common subsequence of previous[i..] and current[j..]
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if same_sentence(previous[i], current[j]) {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut hunks = Vec::new();
+    let mut hunk = Hunk {
+        removed: Vec::new(),
+        added: Vec::new(),
+    };
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && same_sentence(previous[i], current[j]) {
+            if !hunk.removed.is_empty() || !hunk.added.is_empty() {
+                hunks.push(std::mem::replace(
+                    &mut hunk,
+                    Hunk {
+                        removed: Vec::new(),
+                        added: Vec::new(),
+                    },
+                ));
+            }
+            i += 1;
+            j += 1;
+        } else if j < m && (i == n || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            hunk.added.push(current[j]);
+            j += 1;
+        } else {
+            hunk.removed.push(previous[i]);
+            i += 1;
+        }
+    }
+    if !hunk.removed.is_empty() || !hunk.added.is_empty() {
+        hunks.push(hunk);
+    }
+    hunks
+}
+
+/// Pair removed with added sentences whose similarity reaches `min_similarity`,
+/// most similar first; unpaired sentences are plain removals and additions
+pub fn classify_hunk(
+    hunk: &Hunk<'_>,
+    similarity: impl Fn(&str, &str) -> f32,
+    min_similarity: f32,
+) -> Vec<SentenceChange> {
+    let mut candidates = Vec::new();
+    for (r, removed) in hunk.removed.iter().enumerate() {
+        for (a, added) in hunk.added.iter().enumerate() {
+            let score = similarity(removed, added);
+            if score >= min_similarity {
+                candidates.push((score, r, a));
+            }
+        }
+    }
+    candidates.sort_by(|x, y| y.0.partial_cmp(&x.0).unwrap_or(std::cmp::Ordering::Equal));
+
+    let mut removed_pair = vec![None; hunk.removed.len()];
+    let mut added_paired = vec![false; hunk.added.len()];
+    for (score, r, a) in candidates {
+        if removed_pair[r].is_none() && !added_paired[a] {
+            removed_pair[r] = Some((a, score));
+            added_paired[a] = true;
+        }
+    }
+
+    let mut changes = Vec::new();
+    for (removed, pair) in hunk.removed.iter().zip(&removed_pair) {
+        changes.push(match pair {
+            Some((a, score)) => SentenceChange {
+                kind: ChangeKind::Modified,
+                previous: Some(removed.to_string()),
+                current: Some(hunk.added[*a].to_string()),
+                similarity: Some(*score),
+            },
+            None => SentenceChange {
+                kind: ChangeKind::Removed,
+                previous: Some(removed.to_string()),
+                current: None,
+                similarity: None,
+            },
+        });
+    }
+    for (added, paired) in hunk.added.iter().zip(added_paired) {
+        if !paired {
+            changes.push(SentenceChange {
+                kind: ChangeKind::Added,
+                previous: None,
+                current: Some(added.to_string()),
+                similarity: None,
+            });
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unchanged_sentences_anchor_hunks() {
+        let previous = [
+            "Rent is due monthly.",
+            "The term is one year.",
+            "Notice is 30 days.",
+        ];
+        let current = [
+            "Rent is due weekly.",
+            "The term is  one year.",
+            "Notice is 30 days.",
+            "Pets are not allowed.",
+        ];
+        let hunks = diff_sentences(&previous, &current);
+        assert_eq!(
+            hunks,
+            vec![
+                Hunk {
+                    removed: vec!["Rent is due monthly."],
+                    added: vec!["Rent is due weekly."],
+                },
+                Hunk {
+                    removed: vec![],
+                    added: vec!["Pets are not allowed."],
+                },
+            ]
+        );
+
+        let rent_similarity = |a: &str, b: &str| {
+            if a.starts_with("Rent") && b.starts_with("Rent") {
+                0.9
+            } else {
+                0.1
+            }
+        };
+        let changes = classify_hunk(&hunks[0], rent_similarity, 0.8);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].kind, ChangeKind::Modified);
+        assert_eq!(changes[0].current.as_deref(), Some("Rent is due weekly."));
+        assert_eq!(
+            classify_hunk(&hunks[1], rent_similarity, 0.8)[0].kind,
+            ChangeKind::Added
+        );
+    }
+}
@@ -0,0 +1,527 @@
+/// Redaction of documents in their original format
+///
+/// A DOCX is a ZIP of XML parts in which each paragraph's text is split across
+/// `<w:t>` elements (`<w:delText>` for tracked deletions), one per formatting
+/// run. Redaction rebuilds each paragraph's text, detects PII in it (so an
+/// entity split across runs is still found), and rewrites only the affected
+/// element contents: the run where an entity starts receives the `[TYPE]`
+/// placeholder and the rest of the entity is removed from the runs it continues
+/// into. Names outside the text are replaced outright: comment and revision
+/// authors, the document properties' author, editor, manager and company, and
+/// `mailto:` link targets. Run properties, styles and every other part are
+/// copied unchanged, so the result keeps the original's formatting.
+use crate::constants::MAX_ARCHIVE_TOTAL_MB;
+use crate::pii_detector::{PIIDetector, PIIEntity};
+use anyhow::{anyhow, Result};
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{Cursor, Read, Write};
+use zip::write::FileOptions;
+
+lazy_static! {
+    /// A text or deleted-text element: its name, attributes and contents;
+    /// `<w:tab/>`, `<w:tbl>` etc. do not match
+    static ref TEXT_ELEMENT: Regex =
+        Regex::new(r"<w:(t|delText)(\s[^>]*)?>([^<]*)</w:(?:t|delText)>")
+            .expect("CRITICAL: DOCX text element regex is invalid - this should never fail");
+    /// Parts holding user-visible text
+    static ref TEXT_PART: Regex = Regex::new(
+        r"^word/(document|header\d*|footer\d*|footnotes|endnotes|comments)\.xml$"
+    )
+    .expect("CRITICAL: DOCX part name regex is invalid - this should never fail");
+    /// Comment, revision and co-author names in `word/` parts
+    static ref AUTHOR_ATTRIBUTE: Regex =
+        Regex::new(r#"\b(w(?:15)?:(?:author|initials))="([^"]*)""#)
+            .expect("CRITICAL: DOCX author attribute regex is invalid - this should never fail");
+    /// Author and last editor in `docProps/core.xml`
+    static ref CORE_AUTHOR: Regex = Regex::new(
+        r"(<(dc:creator|cp:lastModifiedBy)(?:\s[^>]*)?>)([^<]*)(</(?:dc:creator|cp:lastModifiedBy)>)"
+    )
+    .expect("CRITICAL: DOCX core properties regex is invalid - this should never fail");
+    /// Manager and company in `docProps/app.xml`
+    static ref APP_AUTHOR: Regex =
+        Regex::new(r"(<(Manager|Company)>)([^<]*)(</(?:Manager|Company)>)")
+            .expect("CRITICAL: DOCX app properties regex is invalid - this should never fail");
+    /// E-mail link targets in relationship parts
+    static ref MAILTO_TARGET: Regex = Regex::new(r#"(Target=")mailto:([^"]*)(")"#)
+        .expect("CRITICAL: DOCX mailto target regex is invalid - this should never fail");
+}
+
+/// Placeholder for a name that is replaced without detection
+const PERSON_PLACEHOLDER: &str = "[PERSON]";
+
+/// Formats that can be redacted in place rather than exported as plain text
+pub fn supports_in_place_redaction(file_type: &str) -> bool {
+    file_type.eq_ignore_ascii_case("docx")
+}
+
+/// Redacted copy of a document in its original format
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedactedDocument {
+    pub content: Vec<u8>,
+    pub redacted_count: usize,
+    /// Detection stopped at the entity cap; the copy should be reviewed manually
+    pub truncated: bool,
+}
+
+/// Text of one `<w:t>` element, located in its part's paragraph text
+struct TextSegment {
+    start: usize,
+    text: String,
+}
+
+/// Paragraph text of an XML part, one line per paragraph, and the segment each
+/// `<w:t>` element contributes to it
+fn part_text(xml: &str) -> (String, Vec<TextSegment>) {
+    let mut text = String::new();
+    let mut segments = Vec::new();
+    let mut previous_end = 0;
+    for captures in TEXT_ELEMENT.captures_iter(xml) {
+        let element = captures.get(0).expect("group 0 always participates");
+        if !segments.is_empty() && xml[previous_end..element.start()].contains("</w:p>") {
+            text.push('\n');
+        }
+        let segment = unescape(&captures[3]);
+        segments.push(TextSegment {
+            start: text.len(),
+            text: segment.clone(),
+        });
+        text.push_str(&segment);
+        previous_end = element.end();
+    }
+    (text, segments)
+}
+
+/// Apply `entities` (spans of the part's paragraph text) to its segments,
+/// returning the new text of each segment
+fn redact_segments(segments: &[TextSegment], entities: &[PIIEntity]) -> Vec<String> {
+    let mut sorted: Vec<&PIIEntity> = entities.iter().collect();
+    sorted.sort_by_key(|e| e.start);
+    let mut spans = Vec::new();
+    let mut covered_to = 0;
+    for entity in sorted {
+        if entity.start >= covered_to && entity.start < entity.end {
+            spans.push(entity);
+            covered_to = entity.end;
+        }
+    }
+
+    segments
+        .iter()
+        .map(|segment| {
+            let end = segment.start + segment.text.len();
+            let mut redacted = String::with_capacity(segment.text.len());
+            let mut copied_to = segment.start;
+            for entity in spans
+                .iter()
+                .filter(|e| e.start < end && e.end > segment.start)
+            {
+                let from = entity.start.max(segment.start);
+                redacted.push_str(&segment.text[copied_to - segment.start..from - segment.start]);
+                if entity.start >= segment.start {
+                    redacted.push_str(&format!("[{}]", entity.entity_type));
+                }
+                copied_to = entity.end.min(end);
+            }
+            redacted.push_str(&segment.text[copied_to - segment.start..]);
+            redacted
+        })
+        .collect()
+}
+
+/// Replace the contents of each text element with `texts`, in order
+fn write_part(xml: &str, texts: &[String]) -> String {
+    let mut index = 0;
+    TEXT_ELEMENT
+        .replace_all(xml, |captures: &Captures| {
+            let text = &texts[index];
+            index += 1;
+            let name = &captures[1];
+            let attributes = captures.get(2).map_or("", |m| m.as_str());
+            // Placeholders can leave leading or trailing spaces that Word would drop
+            let preserve = if attributes.contains("xml:space") {
+                ""
+            } else {
+                " xml:space=\"preserve\""
+            };
+            format!(
+                "<w:{}{}{}>{}</w:{}>",
+                name,
+                preserve,
+                attributes,
+                escape(text),
+                name
+            )
+        })
+        .into_owned()
+}
+
+/// Parts that can name people outside the document text
+fn is_metadata_part(name: &str) -> bool {
+    (name.starts_with("word/") && name.ends_with(".xml"))
+        || name == "docProps/core.xml"
+        || name == "docProps/app.xml"
+        || name.ends_with(".rels")
+}
+
+/// Replace the names and addresses a metadata part holds, returning the new
+/// part and how many values were replaced
+fn redact_metadata(name: &str, xml: &str) -> (String, usize) {
+    let mut replaced = 0;
+    let mut count = |value: &str, placeholder: &str| {
+        if !value.is_empty() && value != placeholder {
+            replaced += 1;
+        }
+    };
+
+    let xml = if name.ends_with(".rels") {
+        MAILTO_TARGET
+            .replace_all(xml, |captures: &Captures| {
+                count(&captures[2], "[EMAIL]");
+                format!("{}mailto:[EMAIL]{}", &captures[1], &captures[3])
+            })
+            .into_owned()
+    } else if name == "docProps/core.xml" {
+        CORE_AUTHOR
+            .replace_all(xml, |captures: &Captures| {
+                count(&captures[3], PERSON_PLACEHOLDER);
+                format!("{}{}{}", &captures[1], PERSON_PLACEHOLDER, &captures[4])
+            })
+            .into_owned()
+    } else if name == "docProps/app.xml" {
+        APP_AUTHOR
+            .replace_all(xml, |captures: &Captures| {
+                let placeholder = match &captures[2] {
+                    "Company" => "[ORGANIZATION]",
+                    _ => PERSON_PLACEHOLDER,
+                };
+                count(&captures[3], placeholder);
+                format!("{}{}{}", &captures[1], placeholder, &captures[4])
+            })
+            .into_owned()
+    } else {
+        AUTHOR_ATTRIBUTE
+            .replace_all(xml, |captures: &Captures| {
+                count(&captures[2], PERSON_PLACEHOLDER);
+                format!("{}=\"{}\"", &captures[1], PERSON_PLACEHOLDER)
+            })
+            .into_owned()
+    };
+    (xml, replaced)
+}
+
+fn unescape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        result.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let Some(semi) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..semi];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(|dec| dec.parse()))
+                .and_then(|code| code.ok())
+                .and_then(char::from_u32),
+        };
+        match decoded {
+            Some(c) => {
+                result.push(c);
+                rest = &rest[semi + 1..];
+            }
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Redact PII in the text runs and metadata of a DOCX, returning the new file's bytes
+pub async fn redact_docx(content: &[u8], detector: &PIIDetector) -> Result<RedactedDocument> {
+    let open = || {
+        zip::ZipArchive::new(Cursor::new(content))
+            .map_err(|e| anyhow!("Not a valid DOCX file: {}", e))
+    };
+
+    // Read the text parts up front so no archive state is held across detection
+    let max_part_bytes = MAX_ARCHIVE_TOTAL_MB * 1024 * 1024;
+    let mut parts = Vec::new();
+    {
+        let mut archive = open()?;
+        for i in 0..archive.len() {
+            let file = archive.by_index(i)?;
+            if !TEXT_PART.is_match(file.name()) && !is_metadata_part(file.name()) {
+                continue;
+            }
+            let name = file.name().to_string();
+            // SECURITY: Bound decompressed size; the size in the header can lie
+            let mut xml = String::new();
+            file.take(max_part_bytes + 1).read_to_string(&mut xml)?;
+            if xml.len() as u64 > max_part_bytes {
+                return Err(anyhow!("DOCX part {} is too large to redact", name));
+            }
+            parts.push((name, xml));
+        }
+    }
+
+    let mut redacted_parts = HashMap::new();
+    let mut redacted_count = 0;
+    let mut truncated = false;
+    for (name, xml) in parts {
+        let mut part = xml.clone();
+        if TEXT_PART.is_match(&name) {
+            let (text, segments) = part_text(&part);
+            let detection = detector.detect_pii_bounded(&text).await?;
+            truncated |= detection.truncated;
+            if !detection.entities.is_empty() {
+                redacted_count += detection.entities.len();
+                part = write_part(&part, &redact_segments(&segments, &detection.entities));
+            }
+        }
+        if is_metadata_part(&name) {
+            let (redacted, replaced) = redact_metadata(&name, &part);
+            redacted_count += replaced;
+            part = redacted;
+        }
+        if part != xml {
+            redacted_parts.insert(name, part);
+        }
+    }
+
+    // Everything else is copied byte for byte, keeping its compression
+    let mut archive = open()?;
+    let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    for i in 0..archive.len() {
+        let file = archive.by_index(i)?;
+        match redacted_parts.remove(file.name()) {
+            Some(part) => {
+                writer.start_file(file.name(), options)?;
+                writer.write_all(part.as_bytes())?;
+            }
+            None => writer.raw_copy_file(file)?,
+        }
+    }
+
+    Ok(RedactedDocument {
+        content: writer.finish()?.into_inner(),
+        redacted_count,
+        truncated,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pii_detector::PIIExclusionsConfig;
+    use docx_rs::*;
+
+    fn run_texts(content: &[u8]) -> Vec<String> {
+        let docx = read_docx(content).unwrap();
+        let mut texts = Vec::new();
+        for child in &docx.document.children {
+            if let DocumentChild::Paragraph(paragraph) = child {
+                for child in &paragraph.children {
+                    if let ParagraphChild::Run(run) = child {
+                        for run_child in &run.children {
+                            if let RunChild::Text(text) = run_child {
+                                texts.push(text.text.clone());
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        texts
+    }
+
+    #[tokio::test]
+    async fn test_docx_redacted_in_place_across_runs() {
+        let mut fixture = Cursor::new(Vec::new());
+        Docx::new()
+            .add_paragraph(
+                Paragraph::new()
+                    .add_run(Run::new().add_text("Write to jane.doe@"))
+                    .add_run(Run::new().add_text("example.com").bold())
+                    .add_run(Run::new().add_text(" by Friday & no later.")),
+            )
+            .add_paragraph(Paragraph::new().add_run(Run::new().add_text("SSN 123-45-6789")))
+            .build()
+            .pack(&mut fixture)
+            .unwrap();
+        let fixture = fixture.into_inner();
+        assert!(run_texts(&fixture)
+            .concat()
+            .contains("jane.doe@example.com"));
+
+        let detector = PIIDetector::with_exclusions(PIIExclusionsConfig::default());
+        let redacted = redact_docx(&fixture, &detector).await.unwrap();
+
+        let all_text = run_texts(&redacted.content).concat();
+        assert!(!all_text.contains("jane.doe"));
+        assert!(!all_text.contains("example.com"));
+        assert!(!all_text.contains("123-45-6789"));
+        assert!(all_text.contains("[EMAIL] by Friday & no later."));
+        assert!(all_text.contains("[SSN]"));
+        assert!(redacted.redacted_count >= 2);
+
+        // The bold run survives, emptied of the entity's remainder
+        let mut archive = zip::ZipArchive::new(Cursor::new(&redacted.content)).unwrap();
+        let mut xml = String::new();
+        archive
+            .by_name("word/document.xml")
+            .unwrap()
+            .read_to_string(&mut xml)
+            .unwrap();
+        assert!(xml.contains("<w:b"));
+    }
+
+    /// DOCX archive holding `parts` as given
+    fn docx_with_parts(parts: &[(&str, &str)]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, xml) in parts {
+            writer.start_file(*name, FileOptions::default()).unwrap();
+            writer.write_all(xml.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn read_part(content: &[u8], name: &str) -> String {
+        let mut archive = zip::ZipArchive::new(Cursor::new(content)).unwrap();
+        let mut xml = String::new();
+        archive
+            .by_name(name)
+            .unwrap()
+            .read_to_string(&mut xml)
+            .unwrap();
+        xml
+    }
+
+    async fn redact(parts: &[(&str, &str)]) -> RedactedDocument {
+        let detector = PIIDetector::with_exclusions(PIIExclusionsConfig::default());
+        redact_docx(&docx_with_parts(parts), &detector)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_tracked_deletion_text_is_redacted() {
+        let redacted = redact(&[(
+            "word/document.xml",
+            "<w:document><w:body><w:p><w:del><w:r>\
+             <w:delText>SSN 123-45-6789</w:delText>\
+             </w:r></w:del></w:p></w:body></w:document>",
+        )])
+        .await;
+
+        let xml = read_part(&redacted.content, "word/document.xml");
+        assert!(!xml.contains("123-45-6789"), "{}", xml);
+        assert!(xml.contains("[SSN]</w:delText>"), "{}", xml);
+    }
+
+    #[tokio::test]
+    async fn test_document_property_authors_are_replaced() {
+        let redacted = redact(&[
+            (
+                "docProps/core.xml",
+                "<cp:coreProperties><dc:creator>Jane Doe</dc:creator>\
+                 <cp:lastModifiedBy>John Roe</cp:lastModifiedBy></cp:coreProperties>",
+            ),
+            (
+                "docProps/app.xml",
+                "<Properties><Manager>Mary Major</Manager>\
+                 <Company>Doe &amp; Roe LLP</Company></Properties>",
+            ),
+        ])
+        .await;
+
+        let core = read_part(&redacted.content, "docProps/core.xml");
+        assert!(
+            !core.contains("Jane Doe") && !core.contains("John Roe"),
+            "{}",
+            core
+        );
+        assert!(core.contains("<dc:creator>[PERSON]</dc:creator>"));
+        let app = read_part(&redacted.content, "docProps/app.xml");
+        assert!(
+            !app.contains("Mary Major") && !app.contains("Doe &amp; Roe"),
+            "{}",
+            app
+        );
+        assert_eq!(redacted.redacted_count, 4);
+    }
+
+    #[tokio::test]
+    async fn test_comment_and_revision_authors_are_replaced() {
+        let redacted = redact(&[
+            (
+                "word/document.xml",
+                "<w:document><w:body><w:p>\
+                 <w:ins w:id=\"1\" w:author=\"Jane Doe\"><w:r><w:t>Added</w:t></w:r></w:ins>\
+                 </w:p></w:body></w:document>",
+            ),
+            (
+                "word/comments.xml",
+                "<w:comments><w:comment w:id=\"0\" w:author=\"John Roe\" w:initials=\"JR\">\
+                 <w:p><w:r><w:t>Check clause 4</w:t></w:r></w:p></w:comment></w:comments>",
+            ),
+        ])
+        .await;
+
+        for part in ["word/document.xml", "word/comments.xml"] {
+            let xml = read_part(&redacted.content, part);
+            assert!(
+                !xml.contains("Jane Doe") && !xml.contains("John Roe"),
+                "{}",
+                xml
+            );
+            assert!(!xml.contains("\"JR\""), "{}", xml);
+        }
+        let comments = read_part(&redacted.content, "word/comments.xml");
+        assert!(comments.contains("w:author=\"[PERSON]\""));
+        assert!(comments.contains("Check clause 4"));
+    }
+
+    #[tokio::test]
+    async fn test_mailto_relationship_targets_are_replaced() {
+        let redacted = redact(&[(
+            "word/_rels/document.xml.rels",
+            "<Relationships><Relationship Id=\"rId5\" Type=\"hyperlink\" \
+             Target=\"mailto:jane.doe@example.com?subject=Lease\" TargetMode=\"External\"/>\
+             <Relationship Id=\"rId6\" Type=\"hyperlink\" Target=\"https://example.org\"/>\
+             </Relationships>",
+        )])
+        .await;
+
+        let rels = read_part(&redacted.content, "word/_rels/document.xml.rels");
+        assert!(!rels.contains("jane.doe"), "{}", rels);
+        assert!(rels.contains("Target=\"mailto:[EMAIL]\""));
+        assert!(rels.contains("Target=\"https://example.org\""));
+    }
+
+    #[test]
+    fn test_in_place_redaction_flags_unsupported_formats() {
+        assert!(supports_in_place_redaction("DOCX"));
+        assert!(!supports_in_place_redaction("pdf"));
+    }
+}
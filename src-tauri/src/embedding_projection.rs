@@ -0,0 +1,260 @@
+/// Dimension reduction and normalization of stored embeddings
+///
+/// Large indices spend most of their memory and disk on embedding vectors. A
+/// projection maps each vector from the embedding model's full dimension to a
+/// smaller one, either by keeping the leading components (for Matryoshka-trained
+/// models) or by projecting onto principal components fitted on the corpus.
+/// The fitted parameters are stored with the index so query vectors are
+/// projected the same way as the chunks they are compared with.
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+/// Subspace iterations used to fit PCA components
+const PCA_ITERATIONS: usize = 50;
+
+/// Most corpus vectors PCA is fitted on; larger corpora are sampled evenly
+pub const PCA_MAX_FIT_SAMPLES: usize = 4096;
+
+/// How embeddings are reduced before they are stored
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum DimensionReduction {
+    /// Store vectors at the model's full dimension
+    #[default]
+    None,
+    /// Keep the leading `dimensions` components
+    Truncate { dimensions: usize },
+    /// Project onto the corpus' top `dimensions` principal components
+    Pca { dimensions: usize },
+}
+
+impl DimensionReduction {
+    /// Dimension of reduced vectors; None when vectors are not reduced
+    pub fn target_dimensions(&self) -> Option<usize> {
+        match self {
+            DimensionReduction::None => None,
+            DimensionReduction::Truncate { dimensions }
+            | DimensionReduction::Pca { dimensions } => Some(*dimensions),
+        }
+    }
+}
+
+/// Fitted projection applied to every stored and query embedding
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EmbeddingProjection {
+    pub reduction: DimensionReduction,
+    /// Scale projected vectors to unit length
+    pub normalize: bool,
+    /// Dimension of vectors produced by the embedding model
+    pub source_dimension: usize,
+    /// PCA only: corpus mean subtracted before projecting
+    #[serde(default)]
+    pub mean: Vec<f32>,
+    /// PCA only: one principal component per output dimension, most variance first
+    #[serde(default)]
+    pub components: Vec<Vec<f32>>,
+}
+
+impl EmbeddingProjection {
+    /// Whether `samples` corpus vectors are enough to fit `reduction`. PCA needs
+    /// at least as many as it keeps dimensions; until then vectors are stored
+    /// unprojected.
+    pub fn can_fit(reduction: &DimensionReduction, samples: usize) -> bool {
+        match reduction {
+            DimensionReduction::Pca { dimensions } => samples >= (*dimensions).max(1),
+            _ => samples > 0,
+        }
+    }
+
+    /// Fit a projection on full-dimension `samples` from the corpus. PCA is
+    /// fitted on at most `PCA_MAX_FIT_SAMPLES` of them.
+    pub fn fit(
+        reduction: &DimensionReduction,
+        normalize: bool,
+        samples: &[Vec<f32>],
+    ) -> Result<Self> {
+        let source_dimension = samples
+            .first()
+            .map(Vec::len)
+            .ok_or_else(|| anyhow!("No embeddings to fit a projection on"))?;
+        if let Some(dimensions) = reduction.target_dimensions() {
+            if dimensions == 0 || dimensions >= source_dimension {
+                return Err(anyhow!(
+                    "Reduced dimension must be between 1 and {}, got {}",
+                    source_dimension.saturating_sub(1),
+                    dimensions
+                ));
+            }
+        }
+
+        let (mean, components) = match reduction {
+            DimensionReduction::Pca { dimensions } => {
+                fit_pca(&sample_evenly(samples, PCA_MAX_FIT_SAMPLES), *dimensions)?
+            }
+            _ => (Vec::new(), Vec::new()),
+        };
+
+        Ok(Self {
+            reduction: reduction.clone(),
+            normalize,
+            source_dimension,
+            mean,
+            components,
+        })
+    }
+
+    /// Dimension of projected vectors
+    pub fn output_dimension(&self) -> usize {
+        self.reduction
+            .target_dimensions()
+            .unwrap_or(self.source_dimension)
+    }
+
+    pub fn project(&self, vector: &[f32]) -> Result<Vec<f32>> {
+        if vector.len() != self.source_dimension {
+            return Err(anyhow!(
+                "Embedding has {} dimensions, projection expects {}",
+                vector.len(),
+                self.source_dimension
+            ));
+        }
+
+        let mut projected = match &self.reduction {
+            DimensionReduction::None => vector.to_vec(),
+            DimensionReduction::Truncate { dimensions } => vector[..*dimensions].to_vec(),
+            DimensionReduction::Pca { .. } => self
+                .components
+                .iter()
+                .map(|component| {
+                    component
+                        .iter()
+                        .zip(vector.iter().zip(&self.mean))
+                        .map(|(weight, (x, mean))| weight * (x - mean))
+                        .sum::<f32>()
+                })
+                .collect(),
+        };
+
+        if self.normalize {
+            let norm = projected.iter().map(|x| x * x).sum::<f32>().sqrt();
+            if norm > 0.0 {
+                projected.iter_mut().for_each(|x| *x /= norm);
+            }
+        }
+        Ok(projected)
+    }
+}
+
+/// At most `max` of `items`, spread evenly over the whole slice
+pub fn sample_evenly<T>(items: &[T], max: usize) -> Vec<&T> {
+    if items.len() <= max {
+        return items.iter().collect();
+    }
+    (0..max).map(|i| &items[i * items.len() / max]).collect()
+}
+
+/// Corpus mean and top principal components, by subspace iteration on the
+/// covariance matrix
+fn fit_pca(samples: &[&Vec<f32>], dimensions: usize) -> Result<(Vec<f32>, Vec<Vec<f32>>)> {
+    let n = samples.len();
+    if n < dimensions {
+        return Err(anyhow!(
+            "PCA to {} dimensions needs at least {} indexed chunks to fit, found {}",
+            dimensions,
+            dimensions,
+            n
+        ));
+    }
+    let d = samples[0].len();
+    if samples.iter().any(|s| s.len() != d) {
+        return Err(anyhow!("Embeddings of mixed dimensions cannot be fitted"));
+    }
+
+    let mut mean = vec![0.0f64; d];
+    for sample in samples {
+        for (m, x) in mean.iter_mut().zip(sample.iter()) {
+            *m += *x as f64;
+        }
+    }
+    mean.iter_mut().for_each(|m| *m /= n as f64);
+
+    // Covariance (unscaled; only the eigenvectors are needed), row-major d x d
+    let mut covariance = vec![0.0f64; d * d];
+    let mut centered = vec![0.0f64; d];
+    for sample in samples {
+        for (c, (x, m)) in centered.iter_mut().zip(sample.iter().zip(&mean)) {
+            *c = *x as f64 - m;
+        }
+        for (i, ci) in centered.iter().enumerate() {
+            if *ci == 0.0 {
+                continue;
+            }
+            let row = &mut covariance[i * d..(i + 1) * d];
+            for (r, cj) in row.iter_mut().zip(&centered) {
+                *r += ci * cj;
+            }
+        }
+    }
+
+    // Fixed starting basis so the same corpus always gives the same fit
+    let mut state: u64 = 0x853c_49e6_748f_ea9b;
+    let mut basis: Vec<Vec<f64>> = (0..dimensions)
+        .map(|_| {
+            (0..d)
+                .map(|_| {
+                    state = state
+                        .wrapping_mul(6_364_136_223_846_793_005)
+                        .wrapping_add(1_442_695_040_888_963_407);
+                    (state >> 11) as f64 / (1u64 << 53) as f64 - 0.5
+                })
+                .collect()
+        })
+        .collect();
+    orthonormalize(&mut basis);
+    for _ in 0..PCA_ITERATIONS {
+        basis = basis.iter().map(|v| mat_vec(&covariance, v, d)).collect();
+        orthonormalize(&mut basis);
+    }
+
+    let mut ranked: Vec<(f64, Vec<f64>)> = basis
+        .into_iter()
+        .map(|v| (dot(&v, &mat_vec(&covariance, &v, d)), v))
+        .collect();
+    ranked.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+
+    Ok((
+        mean.into_iter().map(|m| m as f32).collect(),
+        ranked
+            .into_iter()
+            .map(|(_, v)| v.into_iter().map(|x| x as f32).collect())
+            .collect(),
+    ))
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+fn mat_vec(matrix: &[f64], v: &[f64], d: usize) -> Vec<f64> {
+    matrix.chunks(d).map(|row| dot(row, v)).collect()
+}
+
+/// Modified Gram-Schmidt; vectors left without a direction become zero
+fn orthonormalize(vectors: &mut [Vec<f64>]) {
+    for i in 0..vectors.len() {
+        let (done, rest) = vectors.split_at_mut(i);
+        let v = &mut rest[0];
+        for u in done.iter() {
+            let p = dot(u, v);
+            for (x, y) in v.iter_mut().zip(u) {
+                *x -= p * y;
+            }
+        }
+        let norm = dot(v, v).sqrt();
+        if norm > 1e-12 {
+            v.iter_mut().for_each(|x| *x /= norm);
+        } else {
+            v.iter_mut().for_each(|x| *x = 0.0);
+        }
+    }
+}
@@ -0,0 +1,271 @@
+/// "Second opinion" ensemble for high-risk legal queries
+///
+/// When enabled, a `RiskLevel::High` query is answered by two configured
+/// models. The answers are compared (embedding cosine similarity when an
+/// embedder is available, lexical overlap otherwise) and low agreement is
+/// flagged for human review.
+use crate::ai_transparency::RiskLevel;
+use crate::llm_manager::LLMManager;
+use crate::rag_engine::RAGEngine;
+use crate::utils::cosine_similarity;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// Default agreement below which answers are flagged for review
+pub const DEFAULT_AGREEMENT_THRESHOLD: f32 = 0.75;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnsembleConfig {
+    pub enabled: bool,
+    pub primary_model: String,
+    pub secondary_model: String,
+    pub agreement_threshold: f32,
+}
+
+impl Default for EnsembleConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            primary_model: "mistral-7b-instruct".to_string(),
+            secondary_model: "llama2-7b-chat".to_string(),
+            agreement_threshold: DEFAULT_AGREEMENT_THRESHOLD,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnsembleAnswer {
+    pub model_name: String,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnsembleResponse {
+    /// Whether the second opinion was actually run
+    pub ensemble_used: bool,
+    pub answers: Vec<EnsembleAnswer>,
+    /// Similarity of the two answers (0.0 - 1.0), if both were generated
+    pub agreement: Option<f32>,
+    /// "embedding" or "lexical"
+    pub agreement_method: Option<String>,
+    /// Set when the two answers fall below the agreement threshold
+    pub low_agreement: bool,
+    pub requires_human_review: bool,
+}
+
+/// Generation and embedding backend for the ensemble
+#[async_trait]
+pub trait EnsembleBackend: Send + Sync {
+    async fn generate(&self, model_name: &str, prompt: &str) -> Result<String>;
+
+    /// Embed text for semantic comparison; backends without an embedder
+    /// fall back to lexical agreement.
+    async fn embed(&self, _text: &str) -> Result<Vec<f32>> {
+        Err(anyhow!("Embedding not available"))
+    }
+}
+
+/// Run the ensemble for a query, or a single model when not high-risk/disabled
+pub async fn run_ensemble(
+    backend: &dyn EnsembleBackend,
+    config: &EnsembleConfig,
+    risk_level: RiskLevel,
+    default_model: &str,
+    prompt: &str,
+) -> Result<EnsembleResponse> {
+    if !config.enabled || risk_level != RiskLevel::High {
+        let text = backend.generate(default_model, prompt).await?;
+        return Ok(EnsembleResponse {
+            ensemble_used: false,
+            answers: vec![EnsembleAnswer {
+                model_name: default_model.to_string(),
+                text,
+            }],
+            agreement: None,
+            agreement_method: None,
+            low_agreement: false,
+            requires_human_review: risk_level.requires_human_oversight(),
+        });
+    }
+
+    let primary = backend.generate(&config.primary_model, prompt).await?;
+    let secondary = backend.generate(&config.secondary_model, prompt).await?;
+
+    let (agreement, method) = match (
+        backend.embed(&primary).await,
+        backend.embed(&secondary).await,
+    ) {
+        (Ok(a), Ok(b)) => (cosine_similarity(&a, &b).clamp(0.0, 1.0), "embedding"),
+        _ => (lexical_similarity(&primary, &secondary), "lexical"),
+    };
+
+    let low_agreement = agreement < config.agreement_threshold;
+    if low_agreement {
+        tracing::warn!(
+            agreement,
+            threshold = config.agreement_threshold,
+            "Ensemble answers disagree - flagging for human review"
+        );
+    }
+
+    Ok(EnsembleResponse {
+        ensemble_used: true,
+        answers: vec![
+            EnsembleAnswer {
+                model_name: config.primary_model.clone(),
+                text: primary,
+            },
+            EnsembleAnswer {
+                model_name: config.secondary_model.clone(),
+                text: secondary,
+            },
+        ],
+        agreement: Some(agreement),
+        agreement_method: Some(method.to_string()),
+        low_agreement,
+        requires_human_review: low_agreement || risk_level.requires_human_oversight(),
+    })
+}
+
+/// Term-frequency cosine similarity, used when no embedder is available
+pub fn lexical_similarity(a: &str, b: &str) -> f32 {
+    fn term_counts(text: &str) -> HashMap<String, f32> {
+        let mut counts = HashMap::new();
+        for token in text
+            .to_lowercase()
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|t| t.len() > 2)
+        {
+            *counts.entry(token.to_string()).or_insert(0.0) += 1.0;
+        }
+        counts
+    }
+
+    let counts_a = term_counts(a);
+    let counts_b = term_counts(b);
+    if counts_a.is_empty() || counts_b.is_empty() {
+        return 0.0;
+    }
+
+    let vocab: HashSet<&String> = counts_a.keys().chain(counts_b.keys()).collect();
+    let vec_a: Vec<f32> = vocab
+        .iter()
+        .map(|t| *counts_a.get(*t).unwrap_or(&0.0))
+        .collect();
+    let vec_b: Vec<f32> = vocab
+        .iter()
+        .map(|t| *counts_b.get(*t).unwrap_or(&0.0))
+        .collect();
+    cosine_similarity(&vec_a, &vec_b).clamp(0.0, 1.0)
+}
+
+/// Production backend: local LLMs for generation, RAG embeddings for agreement
+pub struct LocalEnsembleBackend {
+    pub llm_manager: Arc<RwLock<LLMManager>>,
+    pub rag_engine: Arc<RwLock<RAGEngine>>,
+}
+
+#[async_trait]
+impl EnsembleBackend for LocalEnsembleBackend {
+    async fn generate(&self, model_name: &str, prompt: &str) -> Result<String> {
+        let llm = self.llm_manager.read().await;
+        llm.ensure_model_ready(model_name).await?;
+        Ok(llm.generate(prompt, None).await?.text)
+    }
+
+    async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        let rag = self.rag_engine.read().await;
+        rag.embed_text(text).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct MockModels {
+        answers: HashMap<String, String>,
+    }
+
+    #[async_trait]
+    impl EnsembleBackend for MockModels {
+        async fn generate(&self, model_name: &str, _prompt: &str) -> Result<String> {
+            self.answers
+                .get(model_name)
+                .cloned()
+                .ok_or_else(|| anyhow!("Unknown model {}", model_name))
+        }
+    }
+
+    fn mock_models() -> MockModels {
+        let mut answers = HashMap::new();
+        answers.insert(
+            "model-a".to_string(),
+            "The non-compete clause is enforceable because the restriction is limited to twelve months and one city."
+                .to_string(),
+        );
+        answers.insert(
+            "model-b".to_string(),
+            "Statutory minimum wage rules override any contractual payroll arrangement here."
+                .to_string(),
+        );
+        MockModels { answers }
+    }
+
+    fn enabled_config() -> EnsembleConfig {
+        EnsembleConfig {
+            enabled: true,
+            primary_model: "model-a".to_string(),
+            secondary_model: "model-b".to_string(),
+            agreement_threshold: DEFAULT_AGREEMENT_THRESHOLD,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_divergent_answers_flag_low_agreement() {
+        let response = run_ensemble(
+            &mock_models(),
+            &enabled_config(),
+            RiskLevel::High,
+            "model-a",
+            "Is this non-compete enforceable?",
+        )
+        .await
+        .unwrap();
+
+        assert!(response.ensemble_used);
+        assert_eq!(response.answers.len(), 2);
+        assert!(response.agreement.unwrap() < DEFAULT_AGREEMENT_THRESHOLD);
+        assert_eq!(response.agreement_method.as_deref(), Some("lexical"));
+        assert!(response.low_agreement);
+        assert!(response.requires_human_review);
+    }
+
+    #[tokio::test]
+    async fn test_ensemble_only_runs_on_high_risk() {
+        let response = run_ensemble(
+            &mock_models(),
+            &enabled_config(),
+            RiskLevel::Limited,
+            "model-a",
+            "Summarise this clause",
+        )
+        .await
+        .unwrap();
+
+        assert!(!response.ensemble_used);
+        assert!(!response.low_agreement);
+        assert_eq!(response.answers.len(), 1);
+        assert!(response.agreement.is_none());
+    }
+
+    #[test]
+    fn test_lexical_similarity_identical_text() {
+        let text = "The lease terminates on thirty days notice";
+        assert!((lexical_similarity(text, text) - 1.0).abs() < 0.001);
+    }
+}
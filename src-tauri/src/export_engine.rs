@@ -1,11 +1,14 @@
-use anyhow::{anyhow, Result};
+use crate::security::{ChatEncryptor, EncryptedMessage};
+use anyhow::{anyhow, Context, Result};
 use chrono::{DateTime, Utc};
 use docx_rs::*;
 use hex;
 use printpdf::*;
+use ring::rand::{SecureRandom, SystemRandom};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::path::Path;
+use zeroize::Zeroize;
 
 // Core export structures for GDPR Article 20 compliance
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -17,6 +20,9 @@ pub struct UserDataExport {
     pub documents: Vec<DocumentExport>,
     pub settings: SettingsExport,
     pub metadata: ExportMetadata,
+    /// Consent grants and withdrawals
+    #[serde(default)]
+    pub consents: Vec<ConsentExport>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -57,6 +63,15 @@ pub struct PIIDetection {
     pub position_end: usize,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsentExport {
+    pub consent_type: String,
+    pub granted: bool,
+    pub version: i32,
+    pub granted_at: Option<String>,
+    pub revoked_at: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SettingsExport {
     pub preferences: serde_json::Value,
@@ -78,8 +93,253 @@ pub struct ComplianceInfo {
     pub integrity_verified: bool,
 }
 
+/// Identifies a passphrase-encrypted export file
+const ENCRYPTED_EXPORT_FORMAT: &str = "bear-ai-encrypted-export";
+const MIN_PASSPHRASE_LEN: usize = 8;
+/// Upper bounds on the Argon2 costs read from an export file: a crafted file
+/// must not make decryption allocate gigabytes or spin for hours
+const MAX_KDF_M_COST: u32 = 262_144;
+const MAX_KDF_T_COST: u32 = 10;
+const MAX_KDF_P_COST: u32 = 16;
+
+/// Argon2id cost parameters, stored with each encrypted export so it stays
+/// decryptable if the defaults change
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KdfParams {
+    pub m_cost: u32,
+    pub t_cost: u32,
+    pub p_cost: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        // Same costs as chat key derivation: 64 MB, 3 iterations, 4 lanes
+        Self {
+            m_cost: 65536,
+            t_cost: 3,
+            p_cost: 4,
+        }
+    }
+}
+
+/// Envelope of a passphrase-encrypted data export: the AES-256-GCM encrypted
+/// export JSON plus what is needed to derive the key again
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptedExport {
+    pub format: String,
+    pub version: u32,
+    pub kdf: String,
+    pub kdf_params: KdfParams,
+    /// Hex-encoded random Argon2 salt
+    pub salt: String,
+    /// Hex-encoded GCM nonce
+    pub nonce: String,
+    /// Hex-encoded ciphertext with the authentication tag appended
+    pub ciphertext: String,
+    pub export_date: DateTime<Utc>,
+}
+
+impl KdfParams {
+    /// Reject costs above what any writer of this format uses
+    fn check_limits(&self) -> Result<()> {
+        if self.m_cost > MAX_KDF_M_COST
+            || self.t_cost > MAX_KDF_T_COST
+            || self.p_cost > MAX_KDF_P_COST
+        {
+            return Err(anyhow!(
+                "Key derivation costs of the export exceed the supported limits \
+                 (m_cost {}, t_cost {}, p_cost {})",
+                self.m_cost,
+                self.t_cost,
+                self.p_cost
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// 256-bit key from a passphrase with Argon2id
+fn derive_export_key(passphrase: &str, salt: &[u8], params: &KdfParams) -> Result<Vec<u8>> {
+    use argon2::{Algorithm, Argon2, ParamsBuilder, Version};
+
+    let params = ParamsBuilder::new()
+        .m_cost(params.m_cost)
+        .t_cost(params.t_cost)
+        .p_cost(params.p_cost)
+        .output_len(32)
+        .build()
+        .map_err(|e| anyhow!("Failed to build Argon2 parameters: {}", e))?;
+    let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params);
+
+    let mut key = vec![0u8; 32];
+    argon2
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| anyhow!("Key derivation failed: {}", e))?;
+    Ok(key)
+}
+
 pub struct ExportEngine;
 
+const PAGE_WIDTH_MM: f32 = 210.0;
+const PAGE_HEIGHT_MM: f32 = 297.0;
+const MARGIN_MM: f32 = 20.0;
+/// Space kept free above the bottom margin for the footer
+const FOOTER_MM: f32 = 10.0;
+const PT_TO_MM: f32 = 0.3528;
+
+/// Unicode fonts embedded in PDF exports, so names and text outside ASCII
+/// keep their characters
+const PDF_FONT: &[u8] = include_bytes!("../fonts/DejaVuSans.ttf");
+const PDF_FONT_BOLD: &[u8] = include_bytes!("../fonts/DejaVuSans-Bold.ttf");
+
+/// Text as laid out in PDF exports: tabs and carriage returns become spaces
+/// and other control characters are dropped
+fn pdf_safe(text: &str) -> String {
+    text.chars()
+        .filter_map(|c| match c {
+            '\t' | '\r' => Some(' '),
+            '\n' => Some(c),
+            c if c.is_control() => None,
+            c => Some(c),
+        })
+        .collect()
+}
+
+/// Greedy word wrap to at most `width` characters per line
+fn wrap_text(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+        let mut line_len = 0;
+        for word in paragraph.split_whitespace() {
+            let mut word: Vec<char> = word.chars().collect();
+            while word.len() > width {
+                if !line.is_empty() {
+                    lines.push(std::mem::take(&mut line));
+                    line_len = 0;
+                }
+                let rest = word.split_off(width);
+                lines.push(word.into_iter().collect());
+                word = rest;
+            }
+            if !line.is_empty() && line_len + 1 + word.len() > width {
+                lines.push(std::mem::take(&mut line));
+                line_len = 0;
+            }
+            if !line.is_empty() {
+                line.push(' ');
+                line_len += 1;
+            }
+            line_len += word.len();
+            line.extend(word);
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// Flowing A4 text layout that starts a new page when the current one is full
+struct PdfLayout {
+    doc: PdfDocumentReference,
+    font: IndirectFontRef,
+    font_bold: IndirectFontRef,
+    pages: Vec<(PdfPageIndex, PdfLayerIndex)>,
+    y: f32,
+}
+
+impl PdfLayout {
+    fn new(title: &str) -> Result<Self> {
+        let (doc, page, layer) =
+            PdfDocument::new(title, Mm(PAGE_WIDTH_MM), Mm(PAGE_HEIGHT_MM), "Layer 1");
+        let font = doc.add_external_font(PDF_FONT)?;
+        let font_bold = doc.add_external_font(PDF_FONT_BOLD)?;
+        Ok(Self {
+            doc,
+            font,
+            font_bold,
+            pages: vec![(page, layer)],
+            y: PAGE_HEIGHT_MM - MARGIN_MM,
+        })
+    }
+
+    fn new_page(&mut self) {
+        let (page, layer) = self
+            .doc
+            .add_page(Mm(PAGE_WIDTH_MM), Mm(PAGE_HEIGHT_MM), "Layer 1");
+        self.pages.push((page, layer));
+        self.y = PAGE_HEIGHT_MM - MARGIN_MM;
+    }
+
+    /// Write `text` wrapped to the page width
+    fn text(&mut self, text: &str, size: f32, bold: bool, indent_mm: f32) {
+        let line_height = size * PT_TO_MM * 1.4;
+        // DejaVu Sans averages about 0.6 of the font size per character
+        let usable_width = PAGE_WIDTH_MM - 2.0 * MARGIN_MM - indent_mm;
+        let width = (usable_width / (size * PT_TO_MM * 0.6)) as usize;
+
+        for line in wrap_text(&pdf_safe(text), width.max(10)) {
+            if self.y - line_height < MARGIN_MM + FOOTER_MM {
+                self.new_page();
+            }
+            self.y -= line_height;
+            let (page, layer) = self.pages[self.pages.len() - 1];
+            let font = if bold { &self.font_bold } else { &self.font };
+            self.doc.get_page(page).get_layer(layer).use_text(
+                line,
+                size,
+                Mm(MARGIN_MM + indent_mm),
+                Mm(self.y),
+                font,
+            );
+        }
+    }
+
+    fn heading(&mut self, text: &str) {
+        self.y -= 4.0;
+        self.text(text, 14.0, true, 0.0);
+        self.y -= 1.0;
+    }
+
+    /// Stamp `footer` lines and the page number on every page and serialize
+    fn finish(self, footer: &[String]) -> Result<Vec<u8>> {
+        let total = self.pages.len();
+        for (number, (page, layer)) in self.pages.iter().enumerate() {
+            let layer = self.doc.get_page(*page).get_layer(*layer);
+            let mut lines = footer.to_vec();
+            lines.push(format!("Page {} of {}", number + 1, total));
+            for (row, line) in lines.iter().rev().enumerate() {
+                layer.use_text(
+                    pdf_safe(line),
+                    8.0,
+                    Mm(MARGIN_MM),
+                    Mm(MARGIN_MM / 2.0 + row as f32 * 4.0),
+                    &self.font,
+                );
+            }
+        }
+
+        let mut writer = std::io::BufWriter::new(Vec::new());
+        self.doc.save(&mut writer)?;
+        writer
+            .into_inner()
+            .map_err(|e| anyhow!("Failed to write PDF: {}", e.error()))
+    }
+}
+
+/// One line per JSON value: objects as `key: value` pairs, strings unquoted
+fn json_summary(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Object(fields) => fields
+            .iter()
+            .filter(|(_, value)| !value.is_null())
+            .map(|(key, value)| format!("{}: {}", key, json_summary(value)))
+            .collect::<Vec<_>>()
+            .join("; "),
+        serde_json::Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
 impl Default for ExportEngine {
     fn default() -> Self {
         Self::new()
@@ -92,7 +352,6 @@ impl ExportEngine {
     }
 
     /// Generate SHA-256 hash for data integrity verification
-    #[allow(dead_code)]
     fn generate_hash(data: &str) -> String {
         let mut hasher = Sha256::new();
         hasher.update(data.as_bytes());
@@ -396,106 +655,209 @@ impl ExportEngine {
 
     /// Export to PDF format with professional quality
     pub fn export_to_pdf(&self, data: &UserDataExport, output_path: &Path) -> Result<()> {
-        let (doc, page1, layer1) =
-            PdfDocument::new("BEAR AI Data Export", Mm(210.0), Mm(297.0), "Layer 1");
-        let font = doc.add_builtin_font(BuiltinFont::Helvetica)?;
-        let font_bold = doc.add_builtin_font(BuiltinFont::HelveticaBold)?;
-
-        let current_layer = doc.get_page(page1).get_layer(layer1);
-
-        // Title
-        current_layer.use_text(
-            "BEAR AI - Data Export Report",
-            24.0,
-            Mm(50.0),
-            Mm(270.0),
-            &font_bold,
-        );
-        current_layer.use_text(
-            format!(
+        std::fs::write(output_path, self.to_pdf(data)?)?;
+        Ok(())
+    }
+
+    /// Render a data export as a paginated PDF: consent history, chats,
+    /// documents and their PII detections, with the export hash and date in
+    /// every page footer
+    pub fn to_pdf(&self, data: &UserDataExport) -> Result<Vec<u8>> {
+        let mut pdf = PdfLayout::new("BEAR AI Data Export")?;
+
+        pdf.text("BEAR AI - Data Export Report", 20.0, true, 0.0);
+        pdf.text(
+            &format!(
                 "Export Date: {}",
                 data.export_date.format("%Y-%m-%d %H:%M:%S UTC")
             ),
-            12.0,
-            Mm(50.0),
-            Mm(260.0),
-            &font,
-        );
-
-        // Compliance Statement
-        current_layer.use_text(
-            "GDPR Article 20 Compliance Statement",
-            14.0,
-            Mm(20.0),
-            Mm(240.0),
-            &font_bold,
-        );
-        current_layer.use_text(
-            "This export has been generated in accordance with GDPR Article 20.",
             10.0,
-            Mm(20.0),
-            Mm(230.0),
-            &font,
+            false,
+            0.0,
         );
-
-        // Export Metadata
-        current_layer.use_text("Export Metadata", 14.0, Mm(20.0), Mm(210.0), &font_bold);
-        current_layer.use_text(
-            format!("Version: {}", data.version),
-            10.0,
-            Mm(20.0),
-            Mm(200.0),
-            &font,
-        );
-        current_layer.use_text(
-            format!("User ID: {}", data.user_id),
+        pdf.text(&format!("User ID: {}", data.user_id), 10.0, false, 0.0);
+        pdf.text(
+            &format!(
+                "Version: {} (format {})",
+                data.version, data.metadata.format_version
+            ),
             10.0,
-            Mm(20.0),
-            Mm(195.0),
-            &font,
+            false,
+            0.0,
         );
-        current_layer.use_text(
-            format!("Hash: {}", &data.metadata.export_hash[..16]),
+
+        pdf.heading("GDPR Article 20 Compliance Statement");
+        pdf.text(
+            "This export has been generated in accordance with GDPR Article 20 and lists \
+             all personal data held by BEAR AI for the user above.",
             10.0,
-            Mm(20.0),
-            Mm(190.0),
-            &font,
+            false,
+            0.0,
         );
 
-        // Chat History Summary
-        let mut y_pos = 170.0;
-        current_layer.use_text(
-            "Chat History Summary",
-            14.0,
-            Mm(20.0),
-            Mm(y_pos),
-            &font_bold,
-        );
-        y_pos -= 10.0;
+        pdf.heading("Consent History");
+        if data.consents.is_empty() {
+            pdf.text("No consent records.", 10.0, false, 0.0);
+        }
+        for consent in &data.consents {
+            let mut line = format!(
+                "{} (version {}): {}",
+                consent.consent_type,
+                consent.version,
+                if consent.granted {
+                    "granted"
+                } else {
+                    "not granted"
+                }
+            );
+            if let Some(granted_at) = &consent.granted_at {
+                line.push_str(&format!(", granted {}", granted_at));
+            }
+            if let Some(revoked_at) = &consent.revoked_at {
+                line.push_str(&format!(", withdrawn {}", revoked_at));
+            }
+            pdf.text(&line, 10.0, false, 0.0);
+        }
 
+        pdf.heading(&format!(
+            "Chat History ({} conversations)",
+            data.chats.len()
+        ));
         for (idx, chat) in data.chats.iter().enumerate() {
-            if y_pos < 30.0 {
-                break; // Avoid running off page (proper pagination would be more complex)
-            }
-            current_layer.use_text(
-                format!(
-                    "{}. {} ({} messages)",
-                    idx + 1,
-                    chat.title,
+            pdf.y -= 2.0;
+            pdf.text(&format!("{}. {}", idx + 1, chat.title), 11.0, true, 0.0);
+            pdf.text(
+                &format!(
+                    "Created: {} | Model: {} | Messages: {}",
+                    chat.created_at.format("%Y-%m-%d %H:%M:%S"),
+                    chat.model_used,
                     chat.messages.len()
                 ),
-                10.0,
-                Mm(20.0),
-                Mm(y_pos),
-                &font,
+                9.0,
+                false,
+                0.0,
             );
-            y_pos -= 5.0;
+            for msg in &chat.messages {
+                let role_label = match msg.role.as_str() {
+                    "user" => "You",
+                    "assistant" => "BEAR AI",
+                    _ => &msg.role,
+                };
+                pdf.text(
+                    &format!(
+                        "{} ({})",
+                        role_label,
+                        msg.timestamp.format("%Y-%m-%d %H:%M:%S")
+                    ),
+                    9.0,
+                    true,
+                    4.0,
+                );
+                pdf.text(&msg.content, 9.0, false, 4.0);
+            }
         }
 
-        doc.save(&mut std::io::BufWriter::new(std::fs::File::create(
-            output_path,
-        )?))?;
-        Ok(())
+        pdf.heading(&format!("Processed Documents ({})", data.documents.len()));
+        for doc in &data.documents {
+            pdf.y -= 2.0;
+            pdf.text(&doc.filename, 11.0, true, 0.0);
+            pdf.text(
+                &format!(
+                    "Type: {} | Uploaded: {} | Chunks: {} | PII detections: {}",
+                    doc.file_type,
+                    doc.upload_date.format("%Y-%m-%d"),
+                    doc.chunk_count,
+                    doc.pii_detections.len()
+                ),
+                9.0,
+                false,
+                0.0,
+            );
+            for detection in &doc.pii_detections {
+                pdf.text(
+                    &format!(
+                        "{} at {}-{} replaced by {} ({:.0}% confidence)",
+                        detection.pii_type,
+                        detection.position_start,
+                        detection.position_end,
+                        detection.replacement_text,
+                        detection.confidence * 100.0
+                    ),
+                    9.0,
+                    false,
+                    4.0,
+                );
+            }
+        }
+
+        pdf.finish(&[
+            format!("Export hash (SHA-256): {}", data.metadata.export_hash),
+            format!(
+                "Exported {}",
+                data.export_date.format("%Y-%m-%d %H:%M:%S UTC")
+            ),
+        ])
+    }
+
+    /// Render a GDPR compliance report (as produced by
+    /// `ComplianceManager::generate_compliance_report`) as a paginated PDF;
+    /// the footer carries the SHA-256 of the report JSON and its date
+    pub fn report_to_pdf(&self, report: &serde_json::Value) -> Result<Vec<u8>> {
+        let field = |path: &str| report.pointer(path).cloned().unwrap_or_default();
+        let user_id = field("/user_id");
+        let report_date = field("/report_date");
+        let report_date = report_date.as_str().unwrap_or_default();
+
+        let mut pdf = PdfLayout::new("BEAR AI Compliance Report")?;
+        pdf.text("BEAR AI - GDPR Compliance Report", 20.0, true, 0.0);
+        pdf.text(&format!("Report Date: {}", report_date), 10.0, false, 0.0);
+        pdf.text(
+            &format!("User ID: {}", user_id.as_str().unwrap_or_default()),
+            10.0,
+            false,
+            0.0,
+        );
+
+        let sections = [
+            ("Current Consents", "/consents/current"),
+            ("Consent History", "/consents/audit_trail"),
+            ("Data Retention", "/data_retention"),
+            ("Recent Activity", "/audit_trail/recent_logs"),
+            ("Audit Statistics", "/audit_trail/statistics"),
+        ];
+        for (title, path) in sections {
+            pdf.heading(title);
+            match field(path) {
+                serde_json::Value::Array(items) if items.is_empty() => {
+                    pdf.text("None recorded.", 10.0, false, 0.0)
+                }
+                serde_json::Value::Array(items) => {
+                    for item in &items {
+                        pdf.text(&json_summary(item), 9.0, false, 0.0);
+                    }
+                }
+                serde_json::Value::Object(fields) => {
+                    for (key, value) in &fields {
+                        pdf.text(
+                            &format!("{}: {}", key, json_summary(value)),
+                            9.0,
+                            false,
+                            0.0,
+                        );
+                    }
+                }
+                serde_json::Value::Null => pdf.text("None recorded.", 10.0, false, 0.0),
+                other => pdf.text(&json_summary(&other), 9.0, false, 0.0),
+            }
+        }
+
+        pdf.finish(&[
+            format!(
+                "Report hash (SHA-256): {}",
+                Self::generate_hash(&report.to_string())
+            ),
+            format!("Generated {}", report_date),
+        ])
     }
 
     /// Export to plain text format (fallback)
@@ -606,6 +968,76 @@ impl ExportEngine {
         Ok(())
     }
 
+    /// Serialize a data export and encrypt it with a key derived from
+    /// `passphrase`; the returned bytes are an `EncryptedExport` JSON envelope
+    pub fn export_encrypted(&self, data: &UserDataExport, passphrase: &str) -> Result<Vec<u8>> {
+        if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+            return Err(anyhow!(
+                "Passphrase must be at least {} characters",
+                MIN_PASSPHRASE_LEN
+            ));
+        }
+
+        let mut data = data.clone();
+        data.metadata.compliance_info.encrypted = true;
+        let mut json = serde_json::to_string(&data).context("Failed to serialize export")?;
+
+        let mut salt = [0u8; 16];
+        SystemRandom::new()
+            .fill(&mut salt)
+            .map_err(|_| anyhow!("Failed to generate random salt"))?;
+        let kdf_params = KdfParams::default();
+        let mut key = derive_export_key(passphrase, &salt, &kdf_params)?;
+
+        let encrypted = ChatEncryptor::new().encrypt(&json, &key, &data.user_id);
+        key.zeroize();
+        json.zeroize();
+        let encrypted = encrypted?;
+
+        let envelope = EncryptedExport {
+            format: ENCRYPTED_EXPORT_FORMAT.to_string(),
+            version: encrypted.version,
+            kdf: "argon2id".to_string(),
+            kdf_params,
+            salt: hex::encode(salt),
+            nonce: hex::encode(&encrypted.nonce),
+            ciphertext: hex::encode(&encrypted.ciphertext),
+            export_date: data.export_date,
+        };
+        serde_json::to_vec_pretty(&envelope).context("Failed to serialize encrypted export")
+    }
+
+    /// Decrypt an export produced by `export_encrypted`
+    pub fn decrypt_export(&self, bytes: &[u8], passphrase: &str) -> Result<UserDataExport> {
+        let envelope: EncryptedExport =
+            serde_json::from_slice(bytes).context("Not an encrypted BEAR AI export")?;
+        if envelope.format != ENCRYPTED_EXPORT_FORMAT || envelope.kdf != "argon2id" {
+            return Err(anyhow!(
+                "Unsupported encrypted export format: {} ({})",
+                envelope.format,
+                envelope.kdf
+            ));
+        }
+        envelope.kdf_params.check_limits()?;
+
+        let salt = hex::decode(&envelope.salt).context("Invalid salt")?;
+        let encrypted = EncryptedMessage {
+            ciphertext: hex::decode(&envelope.ciphertext).context("Invalid ciphertext")?,
+            nonce: hex::decode(&envelope.nonce).context("Invalid nonce")?,
+            version: envelope.version,
+            user_id: String::new(),
+        };
+
+        let mut key = derive_export_key(passphrase, &salt, &envelope.kdf_params)?;
+        let decrypted = ChatEncryptor::new().decrypt(&encrypted, &key);
+        key.zeroize();
+        let mut json = decrypted.map_err(|_| anyhow!("Wrong passphrase or corrupted export"))?;
+
+        let data = serde_json::from_str(&json).context("Decrypted export is not valid");
+        json.zeroize();
+        data
+    }
+
     /// Main export function that generates all formats and creates encrypted archive
     pub fn export_user_data(
         &self,
@@ -648,3 +1080,157 @@ impl ExportEngine {
         Ok(exported_files)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_export(message_count: usize) -> UserDataExport {
+        let messages = (0..message_count)
+            .map(|i| MessageExport {
+                role: if i % 2 == 0 { "user" } else { "assistant" }.to_string(),
+                content: format!("Message {} about the lease termination clause.", i),
+                timestamp: Utc::now(),
+                metadata: None,
+            })
+            .collect();
+        UserDataExport {
+            export_date: Utc::now(),
+            version: "1.0.41".to_string(),
+            user_id: "dsar-user-42".to_string(),
+            chats: vec![ChatExport {
+                id: "chat-1".to_string(),
+                title: "Lease review".to_string(),
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+                messages,
+                model_used: "tinyllama".to_string(),
+                tags: Vec::new(),
+            }],
+            documents: vec![DocumentExport {
+                id: 1,
+                filename: "lease.pdf".to_string(),
+                file_type: "pdf".to_string(),
+                upload_date: Utc::now(),
+                chunk_count: 3,
+                pii_detections: vec![PIIDetection {
+                    pii_type: "EMAIL".to_string(),
+                    replacement_text: "[EMAIL]".to_string(),
+                    confidence: 0.95,
+                    position_start: 10,
+                    position_end: 30,
+                }],
+            }],
+            settings: SettingsExport {
+                preferences: serde_json::json!({}),
+                retention_policy: None,
+            },
+            metadata: ExportMetadata {
+                format_version: "1.0.0".to_string(),
+                application_version: "1.0.41".to_string(),
+                export_hash: ExportEngine::generate_hash("dsar-user-42"),
+                compliance_info: ComplianceInfo {
+                    gdpr_article_20: true,
+                    encrypted: false,
+                    integrity_verified: true,
+                },
+            },
+            consents: vec![ConsentExport {
+                consent_type: "chat_storage".to_string(),
+                granted: false,
+                version: 1,
+                granted_at: Some("2024-01-01T00:00:00Z".to_string()),
+                revoked_at: Some("2024-02-01T00:00:00Z".to_string()),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_pdf_export_paginates_and_extracts() {
+        let data = sample_export(80);
+
+        let pdf = ExportEngine::new().to_pdf(&data).unwrap();
+        assert!(pdf.starts_with(b"%PDF"));
+
+        let text = pdf_extract::extract_text_from_mem(&pdf).unwrap();
+        assert!(text.contains("dsar-user-42"));
+        assert!(text.contains(&data.metadata.export_hash));
+        assert!(text.contains("Page 2 of"));
+        assert!(text.contains("withdrawn 2024-02-01"));
+    }
+
+    #[test]
+    fn test_pdf_export_keeps_non_ascii_text() {
+        let mut data = sample_export(2);
+        data.chats[0].title = "Mietvertrag Müller – Kündigung".to_string();
+        data.chats[0].messages[0].content = format!("Δήλωση {}", "ü".repeat(200));
+
+        let pdf = ExportEngine::new().to_pdf(&data).unwrap();
+        let text = pdf_extract::extract_text_from_mem(&pdf).unwrap();
+        assert!(text.contains("Müller"));
+        assert!(text.contains("Kündigung"));
+        assert!(text.contains("Δήλωση"));
+        assert!(!text.contains("M?ller"));
+
+        let lines = wrap_text(&"ü".repeat(25), 10);
+        assert_eq!(
+            lines
+                .iter()
+                .map(|line| line.chars().count())
+                .collect::<Vec<_>>(),
+            vec![10, 10, 5]
+        );
+    }
+
+    #[test]
+    fn test_encrypted_export_round_trip() {
+        let engine = ExportEngine::new();
+        let data = sample_export(2);
+
+        let encrypted = engine
+            .export_encrypted(&data, "correct horse battery")
+            .unwrap();
+        let envelope: EncryptedExport = serde_json::from_slice(&encrypted).unwrap();
+        assert_eq!(envelope.format, ENCRYPTED_EXPORT_FORMAT);
+        assert!(!String::from_utf8_lossy(&encrypted).contains("lease termination"));
+
+        let decrypted = engine
+            .decrypt_export(&encrypted, "correct horse battery")
+            .unwrap();
+        assert_eq!(decrypted.user_id, data.user_id);
+        assert_eq!(decrypted.chats[0].messages.len(), 2);
+        assert!(decrypted.metadata.compliance_info.encrypted);
+
+        assert!(engine.export_encrypted(&data, "short").is_err());
+    }
+
+    #[test]
+    fn test_encrypted_export_wrong_passphrase() {
+        let engine = ExportEngine::new();
+        let encrypted = engine
+            .export_encrypted(&sample_export(1), "correct horse battery")
+            .unwrap();
+
+        let err = engine
+            .decrypt_export(&encrypted, "incorrect horse battery")
+            .unwrap_err();
+        assert!(err.to_string().contains("Wrong passphrase"));
+    }
+
+    #[test]
+    fn test_encrypted_export_rejects_excessive_kdf_costs() {
+        let engine = ExportEngine::new();
+        let encrypted = engine
+            .export_encrypted(&sample_export(1), "correct horse battery")
+            .unwrap();
+
+        let mut envelope: EncryptedExport = serde_json::from_slice(&encrypted).unwrap();
+        envelope.kdf_params.m_cost = u32::MAX;
+        let crafted = serde_json::to_vec(&envelope).unwrap();
+
+        let err = engine
+            .decrypt_export(&crafted, "correct horse battery")
+            .unwrap_err();
+        assert!(err.to_string().contains("exceed the supported limits"));
+    }
+}
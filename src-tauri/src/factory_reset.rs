@@ -0,0 +1,527 @@
+/// Factory reset of the whole application
+///
+/// Deletes downloaded models, clears the knowledge base, wipes every table of
+/// the application database and returns settings to their defaults. None of
+/// it can be undone, so a reset is only carried out with the one-time token
+/// issued by `FactoryReset::preview`, which also lists what will be deleted.
+/// Originals under legal hold survive the reset together with the retention
+/// policy placing the hold and the audit trail, to which the reset is added.
+use crate::compliance::{AuditAction, AuditLogger, EntityType};
+use crate::constants::FACTORY_RESET_TOKEN_TTL_SECS;
+use crate::llm_manager::LLMManager;
+use crate::pii_detector::PIIDetector;
+use crate::rag_engine::RAGEngine;
+use crate::rate_limiter::RateLimiter;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use rusqlite::Connection;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+/// Tables a reset keeps: reference data seeded by migrations, and the legal
+/// hold bookkeeping and audit trail that must outlive held originals
+const PRESERVED_TABLES: &[&str] = &["audit_log", "consent_versions", "original_retention_policy"];
+
+/// Application state outside the database that a factory reset clears
+#[async_trait]
+pub trait ResettableStore: Send + Sync {
+    /// Key under which the reset report lists this store
+    fn name(&self) -> &str;
+
+    /// Delete everything held and restore defaults, returning the number of items removed
+    async fn reset(&self) -> Result<usize>;
+}
+
+#[async_trait]
+impl ResettableStore for RwLock<RAGEngine> {
+    fn name(&self) -> &str {
+        "rag_chunks"
+    }
+
+    async fn reset(&self) -> Result<usize> {
+        self.read().await.reset().await
+    }
+}
+
+#[async_trait]
+impl ResettableStore for RwLock<LLMManager> {
+    fn name(&self) -> &str {
+        "models"
+    }
+
+    async fn reset(&self) -> Result<usize> {
+        let llm = self.read().await;
+        let removed = llm.delete_all_models().await?;
+        llm.clear_generation_overrides().await?;
+        Ok(removed)
+    }
+}
+
+#[async_trait]
+impl ResettableStore for RwLock<PIIDetector> {
+    fn name(&self) -> &str {
+        "pii_settings"
+    }
+
+    async fn reset(&self) -> Result<usize> {
+        self.read().await.reset_settings().await
+    }
+}
+
+#[async_trait]
+impl ResettableStore for RateLimiter {
+    fn name(&self) -> &str {
+        "rate_limits"
+    }
+
+    async fn reset(&self) -> Result<usize> {
+        Ok(RateLimiter::reset(self))
+    }
+}
+
+/// The one-time token a factory reset must be confirmed with
+#[derive(Default)]
+pub struct ResetConfirmation {
+    pending: Mutex<Option<(String, Instant)>>,
+}
+
+impl ResetConfirmation {
+    /// Issue a new token, replacing any earlier one
+    pub fn issue(&self) -> Result<String> {
+        let token = uuid::Uuid::new_v4().to_string();
+        let expires_at = Instant::now() + Duration::from_secs(FACTORY_RESET_TOKEN_TTL_SECS);
+        *self.lock()? = Some((token.clone(), expires_at));
+        Ok(token)
+    }
+
+    /// Consume the pending token if `token` is it and has not expired
+    pub fn redeem(&self, token: &str) -> Result<()> {
+        let mut pending = self.lock()?;
+        match pending.take() {
+            Some((expected, expires_at)) if expected == token => {
+                if Instant::now() > expires_at {
+                    return Err(anyhow!(
+                        "Factory reset confirmation expired; request a new one"
+                    ));
+                }
+                Ok(())
+            }
+            other => {
+                // A wrong guess does not use up the token that was issued
+                *pending = other;
+                Err(anyhow!(
+                    "Invalid factory reset confirmation token; request one first"
+                ))
+            }
+        }
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, Option<(String, Instant)>>> {
+        self.pending
+            .lock()
+            .map_err(|_| anyhow!("Factory reset confirmation lock poisoned"))
+    }
+}
+
+/// What a factory reset would delete, with the token that confirms it
+#[derive(Debug, Clone, Serialize)]
+pub struct FactoryResetPreview {
+    pub confirm_token: String,
+    pub expires_in_secs: u64,
+    /// Rows per database table that the reset deletes
+    pub table_rows: BTreeMap<String, usize>,
+    /// Originals under legal hold, which the reset keeps
+    pub legal_hold_originals: usize,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FactoryResetReport {
+    /// Rows deleted per database table
+    pub tables_cleared: BTreeMap<String, usize>,
+    pub legal_hold_originals_kept: usize,
+    /// Items removed per store, e.g. model directories and indexed chunks
+    pub stores_reset: BTreeMap<String, usize>,
+    /// Stores that could not be reset, with the reason
+    pub failures: BTreeMap<String, String>,
+    /// Id of the audit entry recording the reset
+    pub audit_entry_id: i64,
+}
+
+/// Resets the application database at `db_path` and the registered stores
+pub struct FactoryReset {
+    db_path: PathBuf,
+    stores: Vec<Arc<dyn ResettableStore>>,
+}
+
+impl FactoryReset {
+    pub fn new(db_path: PathBuf) -> Self {
+        Self {
+            db_path,
+            stores: Vec::new(),
+        }
+    }
+
+    /// Also reset `store`
+    pub fn with_store(mut self, store: Arc<dyn ResettableStore>) -> Self {
+        self.stores.push(store);
+        self
+    }
+
+    /// List what a reset would delete and issue the token confirming it
+    pub fn preview(&self, confirmation: &ResetConfirmation) -> Result<FactoryResetPreview> {
+        let conn = Connection::open(&self.db_path)?;
+        let mut table_rows = BTreeMap::new();
+        for table in user_tables(&conn)? {
+            let rows: i64 =
+                conn.query_row(&format!("SELECT COUNT(*) FROM \"{}\"", table), [], |row| {
+                    row.get(0)
+                })?;
+            table_rows.insert(table, rows as usize);
+        }
+        let legal_hold_originals = legal_hold_originals(&conn)?;
+
+        let mut warnings = Vec::new();
+        if legal_hold_originals > 0 {
+            warnings.push(format!(
+                "{} document originals are under legal hold; they are kept and the hold stays \
+                 in force",
+                legal_hold_originals
+            ));
+        }
+        warnings.push(
+            "The audit trail and original retention policy are kept; all other tables are \
+             emptied"
+                .to_string(),
+        );
+
+        Ok(FactoryResetPreview {
+            confirm_token: confirmation.issue()?,
+            expires_in_secs: FACTORY_RESET_TOKEN_TTL_SECS,
+            table_rows,
+            legal_hold_originals,
+            warnings,
+        })
+    }
+
+    /// Reset everything once `token` is confirmed by `confirmation`, then
+    /// record the reset in the audit log on behalf of `user_id`
+    pub async fn run(
+        &self,
+        confirmation: &ResetConfirmation,
+        token: &str,
+        user_id: &str,
+    ) -> Result<FactoryResetReport> {
+        confirmation.redeem(token)?;
+        tracing::warn!("Factory reset confirmed; deleting all application data");
+
+        let mut stores_reset = BTreeMap::new();
+        let mut failures = BTreeMap::new();
+        for store in &self.stores {
+            match store.reset().await {
+                Ok(removed) => {
+                    stores_reset.insert(store.name().to_string(), removed);
+                }
+                Err(e) => {
+                    tracing::error!(store = store.name(), error = %e, "Store reset failed");
+                    failures.insert(store.name().to_string(), e.to_string());
+                }
+            }
+        }
+
+        let (tables_cleared, legal_hold_originals_kept) = wipe_database(&self.db_path)?;
+        // The rows are already gone; a failed vacuum only leaves them in free pages
+        if let Err(e) = vacuum(&self.db_path) {
+            tracing::warn!(error = %e, "Vacuum after factory reset failed");
+            failures.insert("vacuum".to_string(), e.to_string());
+        }
+
+        let audit = AuditLogger::new(self.db_path.clone());
+        audit.initialize()?;
+        let audit_entry_id = audit.log(
+            user_id,
+            AuditAction::DataDeleted,
+            EntityType::UserSetting,
+            None,
+            Some(serde_json::json!({
+                "action": "factory_reset",
+                "tables_cleared": tables_cleared,
+                "stores_reset": stores_reset,
+                "legal_hold_originals_kept": legal_hold_originals_kept
+            })),
+            failures.is_empty(),
+            (!failures.is_empty()).then_some("Some stores could not be reset"),
+        )?;
+
+        Ok(FactoryResetReport {
+            tables_cleared,
+            legal_hold_originals_kept,
+            stores_reset,
+            failures,
+            audit_entry_id,
+        })
+    }
+}
+
+/// Tables holding user data: all but SQLite's own, internal `_`-prefixed
+/// ones and migration-seeded reference data
+fn user_tables(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT name FROM sqlite_master
+         WHERE type = 'table' AND name NOT LIKE 'sqlite\\_%' ESCAPE '\\'
+           AND name NOT LIKE '\\_%' ESCAPE '\\'
+         ORDER BY name",
+    )?;
+    let tables = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(tables
+        .into_iter()
+        .filter(|table| !PRESERVED_TABLES.contains(&table.as_str()))
+        .collect())
+}
+
+fn legal_hold_originals(conn: &Connection) -> Result<usize> {
+    if !user_tables(conn)?.iter().any(|t| t == "document_originals") {
+        return Ok(0);
+    }
+    let held: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM document_originals WHERE legal_hold = 1",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(held as usize)
+}
+
+/// Delete every user row in one transaction, keeping originals under legal hold
+fn wipe_database(db_path: &Path) -> Result<(BTreeMap<String, usize>, usize)> {
+    let mut conn = Connection::open(db_path)?;
+    let tx = conn.transaction()?;
+    let mut cleared = BTreeMap::new();
+    for table in user_tables(&tx)? {
+        let sql = if table == "document_originals" {
+            "DELETE FROM document_originals WHERE legal_hold = 0".to_string()
+        } else {
+            format!("DELETE FROM \"{}\"", table)
+        };
+        let deleted = tx.execute(&sql, [])?;
+        cleared.insert(table, deleted);
+    }
+    let held = legal_hold_originals(&tx)?;
+    tx.commit()?;
+    Ok((cleared, held))
+}
+
+/// Rebuild the database file so deleted data does not linger in free pages
+fn vacuum(db_path: &Path) -> Result<()> {
+    Connection::open(db_path)?.execute_batch("VACUUM")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rag_engine::EmbeddingBackend;
+
+    struct LengthBackend;
+
+    #[async_trait]
+    impl EmbeddingBackend for LengthBackend {
+        async fn embed_batch(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+            Ok(texts.iter().map(|t| vec![t.len() as f32, 1.0]).collect())
+        }
+    }
+
+    /// Model files in a directory of their own
+    struct ModelDir(PathBuf);
+
+    #[async_trait]
+    impl ResettableStore for ModelDir {
+        fn name(&self) -> &str {
+            "models"
+        }
+
+        async fn reset(&self) -> Result<usize> {
+            let removed = std::fs::read_dir(&self.0)?.count();
+            std::fs::remove_dir_all(&self.0)?;
+            Ok(removed)
+        }
+    }
+
+    fn rows(conn: &Connection, table: &str) -> i64 {
+        conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+            row.get(0)
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_factory_reset_empties_populated_state() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("bear_ai.db");
+        let audit = AuditLogger::new(db_path.clone());
+        audit.initialize().unwrap();
+        audit
+            .log_success(
+                "alice",
+                AuditAction::DataAccessed,
+                EntityType::Document,
+                Some("doc-1"),
+                None,
+            )
+            .unwrap();
+        let conn = Connection::open(&db_path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE chat_messages (id INTEGER PRIMARY KEY, content TEXT);
+             CREATE TABLE pii_detections (id INTEGER PRIMARY KEY, entity TEXT);
+             CREATE TABLE consent_versions (consent_type TEXT, version INTEGER);
+             CREATE TABLE document_originals (document_id TEXT, legal_hold INTEGER);
+             CREATE TABLE original_retention_policy (id INTEGER PRIMARY KEY, legal_hold INTEGER);
+             INSERT INTO chat_messages (content) VALUES ('hello'), ('world');
+             INSERT INTO pii_detections (entity) VALUES ('SSN');
+             INSERT INTO consent_versions VALUES ('chat_storage', 1);
+             INSERT INTO document_originals VALUES ('held', 1), ('expirable', 0);
+             INSERT INTO original_retention_policy VALUES (1, 1);",
+        )
+        .unwrap();
+
+        let rag = Arc::new(RwLock::new(RAGEngine::with_index_path(
+            temp_dir.path().join("rag_index"),
+        )));
+        {
+            let engine = rag.read().await;
+            engine.set_embedding_backend(Arc::new(LengthBackend)).await;
+            engine
+                .add_document("The tenant shall pay rent monthly.", serde_json::json!({}))
+                .await
+                .unwrap();
+            let mut config = engine.get_config().await;
+            config.chunk_size = 64;
+            engine.update_config(config).await.unwrap();
+        }
+        let models_dir = temp_dir.path().join("models");
+        std::fs::create_dir_all(models_dir.join("tinyllama")).unwrap();
+
+        let reset = FactoryReset::new(db_path.clone())
+            .with_store(rag.clone())
+            .with_store(Arc::new(ModelDir(models_dir.clone())));
+        let confirmation = ResetConfirmation::default();
+        let preview = reset.preview(&confirmation).unwrap();
+        assert_eq!(preview.table_rows["chat_messages"], 2);
+        for preserved in PRESERVED_TABLES {
+            assert!(!preview.table_rows.contains_key(*preserved));
+        }
+        assert_eq!(preview.legal_hold_originals, 1);
+        assert_eq!(preview.warnings.len(), 2);
+
+        // Nothing happens without the issued token
+        assert!(reset.run(&confirmation, "yes", "alice").await.is_err());
+        assert_eq!(rows(&conn, "chat_messages"), 2);
+
+        let report = reset
+            .run(&confirmation, &preview.confirm_token, "alice")
+            .await
+            .unwrap();
+        assert!(report.failures.is_empty());
+        assert_eq!(report.stores_reset["rag_chunks"], 1);
+        assert_eq!(report.stores_reset["models"], 1);
+        assert_eq!(report.legal_hold_originals_kept, 1);
+
+        assert_eq!(rows(&conn, "chat_messages"), 0);
+        assert_eq!(rows(&conn, "pii_detections"), 0);
+        assert_eq!(rows(&conn, "consent_versions"), 1);
+        assert_eq!(rows(&conn, "original_retention_policy"), 1);
+        let kept: String = conn
+            .query_row("SELECT document_id FROM document_originals", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(kept, "held");
+        assert!(!models_dir.exists());
+        let engine = rag.read().await;
+        assert_eq!(engine.get_index_statistics().await.unwrap().total_chunks, 0);
+        assert_eq!(engine.get_config().await.chunk_size, 512);
+        drop(engine);
+
+        // Earlier audit entries are kept and the reset is added after them
+        assert_eq!(rows(&conn, "audit_log"), 2);
+        let (id, action, details): (i64, String, String) = conn
+            .query_row(
+                "SELECT id, action_type, details FROM audit_log ORDER BY id DESC LIMIT 1",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(id, report.audit_entry_id);
+        assert_eq!(action, "data_deleted");
+        assert!(details.contains("factory_reset"));
+
+        // The token is spent
+        assert!(reset
+            .run(&confirmation, &preview.confirm_token, "alice")
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_factory_reset_restores_detector_and_rate_limits() {
+        use crate::pii_detector::{CustomPattern, PIIExclusionsConfig, SensitivityTier};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let patterns_path = temp_dir.path().join("custom_pii_patterns.json");
+        let detector = Arc::new(RwLock::new(
+            PIIDetector::with_exclusions(PIIExclusionsConfig::default())
+                .with_custom_patterns_file(patterns_path.clone()),
+        ));
+        {
+            let detector = detector.read().await;
+            detector
+                .add_custom_pattern(CustomPattern {
+                    name: "matter".to_string(),
+                    pattern: r"MAT-\d{4}".to_string(),
+                    label: "MATTER_NUMBER".to_string(),
+                    confidence: 0.9,
+                })
+                .await
+                .unwrap();
+            detector
+                .set_sensitivity_tier("EMAIL", SensitivityTier::Low)
+                .await;
+        }
+        let limiter = Arc::new(RateLimiter::default());
+        limiter
+            .configure("chat", 1, Duration::from_secs(60))
+            .unwrap();
+        limiter.check_rate_limit("alice", "chat").unwrap();
+
+        let reset = FactoryReset::new(temp_dir.path().join("bear_ai.db"))
+            .with_store(detector.clone())
+            .with_store(limiter.clone());
+        let confirmation = ResetConfirmation::default();
+        let token = reset.preview(&confirmation).unwrap().confirm_token;
+        let report = reset.run(&confirmation, &token, "alice").await.unwrap();
+        assert!(report.failures.is_empty());
+        assert_eq!(report.stores_reset["pii_settings"], 1);
+        assert_eq!(report.stores_reset["rate_limits"], 1);
+
+        let detector = detector.read().await;
+        assert!(detector.get_custom_patterns().await.is_empty());
+        assert!(!patterns_path.exists());
+        let default_tiers = crate::pii_detector::PIIDetectionConfig::default().sensitivity_tiers;
+        assert_eq!(
+            detector
+                .get_config()
+                .await
+                .sensitivity_tiers
+                .tier_for("EMAIL"),
+            default_tiers.tier_for("EMAIL")
+        );
+        assert!(!limiter.get_config().action_limits.contains_key("chat"));
+        assert_eq!(limiter.get_usage("alice").used, 0);
+        limiter.check_rate_limit("alice", "chat").unwrap();
+    }
+}
@@ -0,0 +1,243 @@
+/// Stream long generations (drafts, memos) straight to a file
+///
+/// Tokens pass through the streaming PII redactor and are appended to
+/// `<output>.partial` as they arrive. A completed generation is renamed to the
+/// requested path; a cancelled one keeps the `.partial` suffix so an
+/// incomplete draft is never mistaken for a finished document.
+use crate::llm_manager::{GenerationConfig, LLMManager};
+use crate::pii_detector::streaming::StreamingRedactor;
+use crate::pii_detector::PIIDetector;
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::RwLock;
+
+/// Suffix of files still being written, or left behind by a cancelled generation
+pub const PARTIAL_SUFFIX: &str = ".partial";
+
+/// Source of streamed tokens; returns the number of tokens generated
+#[async_trait]
+pub trait TokenStreamer: Send + Sync {
+    async fn stream_tokens(
+        &self,
+        prompt: &str,
+        config: Option<GenerationConfig>,
+        on_token: Box<dyn FnMut(&str) -> bool + Send>,
+    ) -> Result<usize>;
+}
+
+#[async_trait]
+impl TokenStreamer for LLMManager {
+    async fn stream_tokens(
+        &self,
+        prompt: &str,
+        config: Option<GenerationConfig>,
+        on_token: Box<dyn FnMut(&str) -> bool + Send>,
+    ) -> Result<usize> {
+        Ok(self
+            .generate_stream(prompt, config, on_token)
+            .await?
+            .tokens_generated)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileGenerationResult {
+    /// Final file, or the `.partial` file when `partial` is set
+    pub path: PathBuf,
+    /// Generation was cancelled before completion
+    pub partial: bool,
+    pub bytes_written: u64,
+    pub tokens_generated: usize,
+    pub time_ms: u128,
+}
+
+fn partial_path(output_path: &Path) -> PathBuf {
+    let mut name = output_path.as_os_str().to_os_string();
+    name.push(PARTIAL_SUFFIX);
+    PathBuf::from(name)
+}
+
+/// Generate to `output_path` (already validated), redacting PII on the way out.
+/// Setting `cancel` stops generation and leaves the flagged partial file.
+pub async fn generate_to_file(
+    streamer: &dyn TokenStreamer,
+    detector: Arc<RwLock<PIIDetector>>,
+    prompt: &str,
+    output_path: &Path,
+    config: Option<GenerationConfig>,
+    cancel: Arc<AtomicBool>,
+) -> Result<FileGenerationResult> {
+    let start = std::time::Instant::now();
+    let partial = partial_path(output_path);
+    let file = tokio::fs::File::create(&partial).await?;
+
+    // Generation callbacks are synchronous; redaction and disk writes run in a task
+    let (token_tx, mut token_rx) = tokio::sync::mpsc::unbounded_channel::<String>();
+    let writer = tokio::spawn(async move {
+        let detector = detector.read().await;
+        let mut redactor = StreamingRedactor::for_detector(&detector).await;
+        let mut file = tokio::io::BufWriter::new(file);
+        let mut bytes_written = 0u64;
+
+        while let Some(token) = token_rx.recv().await {
+            let chunk = redactor.push(&detector, &token).await?;
+            file.write_all(chunk.as_bytes()).await?;
+            bytes_written += chunk.len() as u64;
+        }
+
+        let tail = redactor.finish(&detector).await?;
+        file.write_all(tail.as_bytes()).await?;
+        bytes_written += tail.len() as u64;
+        file.flush().await?;
+        Ok::<u64, anyhow::Error>(bytes_written)
+    });
+
+    let stop = cancel.clone();
+    let generation = streamer
+        .stream_tokens(
+            prompt,
+            config,
+            Box::new(move |token| {
+                !stop.load(Ordering::SeqCst) && token_tx.send(token.to_string()).is_ok()
+            }),
+        )
+        .await;
+
+    let written = match writer.await {
+        Ok(written) => written,
+        Err(e) => Err(e.into()),
+    };
+    let (tokens_generated, bytes_written) = match (generation, written) {
+        (Ok(tokens), Ok(bytes)) => (tokens, bytes),
+        (Err(e), _) | (_, Err(e)) => {
+            let _ = tokio::fs::remove_file(&partial).await;
+            return Err(e);
+        }
+    };
+
+    let cancelled = cancel.load(Ordering::SeqCst);
+    let path = if cancelled {
+        tracing::warn!(path = %partial.display(), "Generation to file cancelled, partial output kept");
+        partial
+    } else {
+        tokio::fs::rename(&partial, output_path).await?;
+        output_path.to_path_buf()
+    };
+
+    Ok(FileGenerationResult {
+        path,
+        partial: cancelled,
+        bytes_written,
+        tokens_generated,
+        time_ms: start.elapsed().as_millis(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Streams a fixed list of tokens, optionally raising `cancel` part-way
+    struct MockStreamer {
+        tokens: Vec<&'static str>,
+        cancel_after: Option<(usize, Arc<AtomicBool>)>,
+    }
+
+    #[async_trait]
+    impl TokenStreamer for MockStreamer {
+        async fn stream_tokens(
+            &self,
+            _prompt: &str,
+            _config: Option<GenerationConfig>,
+            mut on_token: Box<dyn FnMut(&str) -> bool + Send>,
+        ) -> Result<usize> {
+            let mut generated = 0;
+            for (i, token) in self.tokens.iter().enumerate() {
+                if let Some((after, cancel)) = &self.cancel_after {
+                    if i == *after {
+                        cancel.store(true, Ordering::SeqCst);
+                    }
+                }
+                if !on_token(token) {
+                    break;
+                }
+                generated += 1;
+            }
+            Ok(generated)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_generate_to_file_writes_full_output() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let output = temp_dir.path().join("memo.txt");
+        let streamer = MockStreamer {
+            tokens: vec![
+                "MEMORANDUM",
+                "\n\n",
+                "The lease ",
+                "renews ",
+                "annually ",
+                "on 1 March.",
+            ],
+            cancel_after: None,
+        };
+
+        let result = generate_to_file(
+            &streamer,
+            Arc::new(RwLock::new(PIIDetector::new())),
+            "Draft a memo",
+            &output,
+            None,
+            Arc::new(AtomicBool::new(false)),
+        )
+        .await
+        .unwrap();
+
+        let written = std::fs::read_to_string(&output).unwrap();
+        assert_eq!(
+            written,
+            "MEMORANDUM\n\nThe lease renews annually on 1 March."
+        );
+        assert_eq!(result.path, output);
+        assert!(!result.partial);
+        assert_eq!(result.tokens_generated, 6);
+        assert_eq!(result.bytes_written, written.len() as u64);
+        assert!(!partial_path(&output).exists());
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_generation_leaves_flagged_partial_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let output = temp_dir.path().join("draft.txt");
+        let cancel = Arc::new(AtomicBool::new(false));
+        let streamer = MockStreamer {
+            tokens: vec!["First ", "clause. ", "Second ", "clause."],
+            cancel_after: Some((2, cancel.clone())),
+        };
+
+        let result = generate_to_file(
+            &streamer,
+            Arc::new(RwLock::new(PIIDetector::new())),
+            "Draft a contract",
+            &output,
+            None,
+            cancel,
+        )
+        .await
+        .unwrap();
+
+        assert!(result.partial);
+        assert_eq!(result.path, partial_path(&output));
+        assert!(!output.exists());
+        assert_eq!(
+            std::fs::read_to_string(&result.path).unwrap(),
+            "First clause. "
+        );
+    }
+}
@@ -1,12 +1,246 @@
+use crate::constants::{
+    ARCHIVE_RATIO_CHECK_MIN_BYTES, MAX_ARCHIVE_COMPRESSION_RATIO, MAX_ARCHIVE_DEPTH,
+    MAX_ARCHIVE_ENTRIES, MAX_ARCHIVE_TOTAL_MB,
+};
 use anyhow::{anyhow, Result};
 use calamine::{open_workbook, Data, Reader, Xls, Xlsx};
 use docx_rs::*;
 use encoding_rs::{Encoding, UTF_8, WINDOWS_1252};
+use serde::Serialize;
 use serde_json::Value as JsonValue;
-use std::io::Read;
-use std::path::PathBuf;
+use sha2::{Digest, Sha256};
+use std::io::{BufRead, BufReader, Cursor, Read};
+use std::path::{Path, PathBuf};
 use tokio::fs;
 
+/// Zip-bomb protection limits for archive ingestion
+#[derive(Debug, Clone)]
+pub struct ArchiveLimits {
+    pub max_entries: usize,
+    pub max_total_bytes: u64,
+    pub max_compression_ratio: u64,
+    pub max_depth: usize,
+}
+
+impl Default for ArchiveLimits {
+    fn default() -> Self {
+        Self {
+            max_entries: MAX_ARCHIVE_ENTRIES,
+            max_total_bytes: MAX_ARCHIVE_TOTAL_MB * 1024 * 1024,
+            max_compression_ratio: MAX_ARCHIVE_COMPRESSION_RATIO,
+            max_depth: MAX_ARCHIVE_DEPTH,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchiveEntryStatus {
+    Extracted,
+    Unsupported,
+    Failed,
+}
+
+/// Outcome of extracting one file from an archive
+#[derive(Debug, Clone, Serialize)]
+pub struct ArchiveEntry {
+    /// Path inside the archive, nested archives joined with '/'
+    pub path: String,
+    pub file_type: String,
+    pub status: ArchiveEntryStatus,
+    pub error: Option<String>,
+    /// SHA-256 of the entry's bytes; None when the entry was not read
+    pub sha256: Option<String>,
+    #[serde(skip)]
+    pub text: Option<String>,
+}
+
+/// Text extracted from a file
+#[derive(Debug, Clone, Serialize)]
+pub struct ExtractedText {
+    pub text: String,
+    /// The text was recognised from page images because the PDF had no
+    /// usable text layer
+    pub ocr_used: bool,
+    /// SHA-256 of the file bytes the text was extracted from
+    pub source_sha256: String,
+}
+
+impl ExtractedText {
+    fn plain(text: String) -> Self {
+        Self {
+            text,
+            ocr_used: false,
+            source_sha256: String::new(),
+        }
+    }
+}
+
+/// Extracted text handed out a chunk at a time
+///
+/// Plain text and CSV files are read from disk a chunk at a time, so at most
+/// about one chunk is held in memory however large the file is, or however
+/// long its lines. Bytes that are not valid UTF-8 are decoded lossily. Other
+/// formats are extracted in full first and then split. Chunks end on a line
+/// break, or failing that at whitespace, where possible.
+pub struct TextChunkReader {
+    source: ChunkSource,
+    max_chunk_bytes: usize,
+}
+
+enum ChunkSource {
+    Lines {
+        reader: BufReader<std::fs::File>,
+        /// Bytes read past the end of the previous chunk
+        carry: Vec<u8>,
+    },
+    Text {
+        text: String,
+        offset: usize,
+    },
+}
+
+impl TextChunkReader {
+    /// Chunks of text that is already in memory
+    pub fn from_text(text: String, max_chunk_bytes: usize) -> Self {
+        Self {
+            source: ChunkSource::Text { text, offset: 0 },
+            max_chunk_bytes: max_chunk_bytes.max(1),
+        }
+    }
+
+    fn from_file(file: std::fs::File, max_chunk_bytes: usize) -> Self {
+        Self {
+            source: ChunkSource::Lines {
+                reader: BufReader::new(file),
+                carry: Vec::new(),
+            },
+            max_chunk_bytes: max_chunk_bytes.max(1),
+        }
+    }
+}
+
+impl Iterator for TextChunkReader {
+    type Item = Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.source {
+            ChunkSource::Lines { reader, carry } => {
+                let mut chunk = std::mem::take(carry);
+                let mut eof = false;
+                while chunk.len() < self.max_chunk_bytes {
+                    // A line is read no further than the chunk size
+                    let limit = (self.max_chunk_bytes - chunk.len()) as u64;
+                    match reader.by_ref().take(limit).read_until(b'\n', &mut chunk) {
+                        Ok(0) => {
+                            eof = true;
+                            break;
+                        }
+                        Ok(_) => {}
+                        Err(e) => return Some(Err(e.into())),
+                    }
+                }
+                if !eof {
+                    let end = byte_chunk_end(&chunk);
+                    *carry = chunk.split_off(end);
+                }
+                (!chunk.is_empty()).then(|| Ok(String::from_utf8_lossy(&chunk).into_owned()))
+            }
+            ChunkSource::Text { text, offset } => {
+                let start = *offset;
+                let end = chunk_end(text, start, self.max_chunk_bytes)?;
+                *offset = end;
+                Some(Ok(text[start..end].to_string()))
+            }
+        }
+    }
+}
+
+/// Chunks of `text` at most `max_chunk_bytes` long (unless a single character
+/// is longer), ending after a line break or whitespace where one is available
+pub fn split_text_chunks(text: &str, max_chunk_bytes: usize) -> impl Iterator<Item = &str> {
+    let max_chunk_bytes = max_chunk_bytes.max(1);
+    let mut start = 0;
+    std::iter::from_fn(move || {
+        let end = chunk_end(text, start, max_chunk_bytes)?;
+        let chunk = &text[start..end];
+        start = end;
+        Some(chunk)
+    })
+}
+
+/// Where to cut a full chunk of raw bytes read from a file: after its last
+/// line break, or after its last whitespace, or else before a UTF-8 sequence
+/// that runs past the end; the rest starts the next chunk
+fn byte_chunk_end(chunk: &[u8]) -> usize {
+    if let Some(newline) = chunk.iter().rposition(|&b| b == b'\n') {
+        return newline + 1;
+    }
+    if let Some(space) = chunk.iter().rposition(u8::is_ascii_whitespace) {
+        return space + 1;
+    }
+    // Back up over continuation bytes to the start of the last character
+    let last_start = chunk.iter().rposition(|&b| b & 0xC0 != 0x80).unwrap_or(0);
+    let char_len = match chunk.get(last_start) {
+        Some(&b) if b >= 0xF0 => 4,
+        Some(&b) if b >= 0xE0 => 3,
+        Some(&b) if b >= 0xC0 => 2,
+        _ => 1,
+    };
+    if last_start > 0 && last_start + char_len > chunk.len() {
+        last_start
+    } else {
+        chunk.len()
+    }
+}
+
+/// End of the chunk starting at `start`: after the last line break within
+/// `max_chunk_bytes`, or after the last whitespace when the line is longer, so
+/// words are not cut in half
+fn chunk_end(text: &str, start: usize, max_chunk_bytes: usize) -> Option<usize> {
+    if start >= text.len() {
+        return None;
+    }
+    let limit = start + max_chunk_bytes;
+    if limit >= text.len() {
+        return Some(text.len());
+    }
+
+    let mut end = limit;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    if let Some(newline) = text[start..end].rfind('\n') {
+        return Some(start + newline + 1);
+    }
+    if let Some(space) = text[start..end].rfind(char::is_whitespace) {
+        let space_len = text[start + space..]
+            .chars()
+            .next()
+            .map_or(1, char::len_utf8);
+        return Some(start + space + space_len);
+    }
+    if end == start {
+        // A single character longer than the chunk size
+        end = start + text[start..].chars().next().map_or(1, char::len_utf8);
+    }
+    Some(end)
+}
+
+/// Raw entry bytes collected before text extraction
+struct RawArchiveEntry {
+    path: String,
+    file_type: String,
+    data: Vec<u8>,
+}
+
+/// Running totals shared across nested archives
+#[derive(Default)]
+struct ArchiveBudget {
+    entries: usize,
+    total_bytes: u64,
+}
+
 pub struct FileProcessor {
     max_file_size: usize,
     supported_formats: Vec<String>,
@@ -20,8 +254,13 @@ impl FileProcessor {
     }
 
     pub fn with_base_dir(allowed_base_dir: Option<PathBuf>) -> Self {
+        Self::with_limits(allowed_base_dir, 50 * 1024 * 1024) // 50MB
+    }
+
+    /// Like `with_base_dir`, with a custom cap on the size of files processed
+    pub fn with_limits(allowed_base_dir: Option<PathBuf>, max_file_size: usize) -> Self {
         Self {
-            max_file_size: 50 * 1024 * 1024, // 50MB
+            max_file_size,
             supported_formats: vec![
                 "txt".to_string(),
                 "pdf".to_string(),
@@ -37,6 +276,9 @@ impl FileProcessor {
                 "json".to_string(),
                 "xml".to_string(),
                 "html".to_string(),
+                "odt".to_string(),
+                "ods".to_string(),
+                "epub".to_string(),
             ],
             allowed_base_dir,
         }
@@ -83,7 +325,93 @@ impl FileProcessor {
         Ok(canonical)
     }
 
-    pub async fn process_file(&self, file_path: &str, _file_type: &str) -> Result<String> {
+    /// SECURITY: Validate a path the application is about to write to.
+    ///
+    /// The file itself may not exist yet, so the parent directory is validated
+    /// with the same rules as `validate_path`, and an existing target must not
+    /// be a symlink.
+    pub fn validate_output_path(&self, file_path: &str) -> Result<PathBuf> {
+        let path = PathBuf::from(file_path);
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| anyhow!("Output path has no file name: {}", file_path))?;
+
+        if let Ok(metadata) = std::fs::symlink_metadata(&path) {
+            if metadata.file_type().is_symlink() {
+                return Err(anyhow!(
+                    "Security violation: Symbolic links are not allowed. \
+                    Please use the direct file path instead."
+                ));
+            }
+            if metadata.is_dir() {
+                return Err(anyhow!("Output path is a directory: {}", file_path));
+            }
+        }
+
+        let parent = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        let parent_str = parent
+            .to_str()
+            .ok_or_else(|| anyhow!("Invalid UTF-8 in file path"))?;
+        let validated_parent = self.validate_path(parent_str)?;
+
+        Ok(validated_parent.join(file_name))
+    }
+
+    pub async fn process_file(&self, file_path: &str, file_type: &str) -> Result<String> {
+        Ok(self.process_file_detailed(file_path, file_type).await?.text)
+    }
+
+    /// Like `process_file`, but also reports whether OCR was needed and the
+    /// hash of the bytes the text came from. The file is read once and the
+    /// text extracted from that copy, so a file changed in between can't
+    /// give the text and the hash different sources.
+    pub async fn process_file_detailed(
+        &self,
+        file_path: &str,
+        _file_type: &str,
+    ) -> Result<ExtractedText> {
+        let (validated_path, extension) = self.validate_input_file(file_path).await?;
+
+        let data = fs::read(&validated_path).await?;
+        let mut extracted = self.extract_bytes(&data, &extension).await?;
+        extracted.source_sha256 = hex::encode(Sha256::digest(&data));
+        Ok(extracted)
+    }
+
+    /// Extract a file's text as chunks of at most about `max_chunk_bytes`.
+    ///
+    /// TXT, MD and CSV files are streamed from disk; other formats are
+    /// extracted in full and then split.
+    pub async fn open_text_chunks(
+        &self,
+        file_path: &str,
+        max_chunk_bytes: usize,
+    ) -> Result<TextChunkReader> {
+        let (validated_path, extension) = self.validate_input_file(file_path).await?;
+
+        match extension.to_lowercase().as_str() {
+            "txt" | "md" | "csv" => {
+                let file = std::fs::File::open(&validated_path)?;
+                Ok(TextChunkReader::from_file(file, max_chunk_bytes))
+            }
+            _ => {
+                let validated_path_str = validated_path
+                    .to_str()
+                    .ok_or_else(|| anyhow!("Invalid UTF-8 in file path"))?;
+                let extracted = self
+                    .extract_by_extension(validated_path_str, &extension)
+                    .await?;
+                Ok(TextChunkReader::from_text(extracted.text, max_chunk_bytes))
+            }
+        }
+    }
+
+    /// Path, size and format checks shared by every way of reading a file;
+    /// returns the validated path and its extension
+    async fn validate_input_file(&self, file_path: &str) -> Result<(PathBuf, String)> {
         // SECURITY: Validate path first to prevent traversal attacks
         let validated_path = self.validate_path(file_path)?;
 
@@ -93,34 +421,136 @@ impl FileProcessor {
 
         let metadata = fs::metadata(&validated_path).await?;
         if metadata.len() as usize > self.max_file_size {
-            return Err(anyhow!("File size exceeds maximum limit of 50MB"));
+            return Err(self.file_too_large());
         }
 
         let extension = validated_path
             .extension()
             .and_then(|ext| ext.to_str())
-            .ok_or_else(|| anyhow!("Could not determine file extension"))?;
+            .ok_or_else(|| anyhow!("Could not determine file extension"))?
+            .to_string();
 
         if !self.supported_formats.contains(&extension.to_lowercase()) {
             return Err(anyhow!("Unsupported file format: {}", extension));
         }
 
-        // Use validated_path string for further processing
-        let validated_path_str = validated_path
-            .to_str()
-            .ok_or_else(|| anyhow!("Invalid UTF-8 in file path"))?;
+        Ok((validated_path, extension))
+    }
 
-        match extension.to_lowercase().as_str() {
+    fn file_too_large(&self) -> anyhow::Error {
+        anyhow!(
+            "File size exceeds maximum limit of {}MB",
+            self.max_file_size / (1024 * 1024)
+        )
+    }
+
+    async fn extract_by_extension(
+        &self,
+        validated_path_str: &str,
+        extension: &str,
+    ) -> Result<ExtractedText> {
+        let text = match extension.to_lowercase().as_str() {
             "txt" | "md" => self.process_text_file(validated_path_str).await,
-            "pdf" => self.process_pdf_file(validated_path_str).await,
+            "pdf" => return self.process_pdf_file(validated_path_str).await,
             "docx" | "doc" => self.process_word_file(validated_path_str).await,
             "xlsx" | "xls" => self.process_excel_file(validated_path_str).await,
             "csv" => self.process_csv_file(validated_path_str).await,
             "pptx" | "ppt" => self.process_powerpoint_file(validated_path_str).await,
             "json" => self.process_json_file(validated_path_str).await,
             "xml" | "html" => self.process_markup_file(validated_path_str).await,
+            "odt" | "ods" => self.process_opendocument_file(validated_path_str).await,
+            "epub" => self.process_epub_file(validated_path_str).await,
             _ => Err(anyhow!("Unsupported file type: {}", extension)),
+        };
+        text.map(ExtractedText::plain)
+    }
+
+    /// Extract every supported file from a ZIP archive.
+    ///
+    /// The whole archive is rejected if it exceeds the entry count, total size,
+    /// compression-ratio or nesting-depth limits. Individual entries that are
+    /// unsupported or fail to extract are reported without failing the archive.
+    pub async fn process_archive(
+        &self,
+        archive_name: &str,
+        bytes: &[u8],
+        limits: &ArchiveLimits,
+    ) -> Result<Vec<ArchiveEntry>> {
+        let mut raw_entries = Vec::new();
+        let mut rejected = Vec::new();
+        collect_archive_entries(
+            archive_name,
+            bytes,
+            0,
+            limits,
+            &mut ArchiveBudget::default(),
+            &mut raw_entries,
+            &mut rejected,
+        )?;
+
+        let mut entries = rejected;
+        for raw in raw_entries {
+            let sha256 = Some(hex::encode(Sha256::digest(&raw.data)));
+            if !self.is_supported(&raw.file_type) {
+                entries.push(ArchiveEntry {
+                    error: Some(format!("Unsupported file format: {}", raw.file_type)),
+                    path: raw.path,
+                    file_type: raw.file_type,
+                    status: ArchiveEntryStatus::Unsupported,
+                    sha256,
+                    text: None,
+                });
+                continue;
+            }
+
+            match self.extract_archive_entry(&raw).await {
+                Ok(text) => entries.push(ArchiveEntry {
+                    path: raw.path,
+                    file_type: raw.file_type,
+                    status: ArchiveEntryStatus::Extracted,
+                    error: None,
+                    sha256,
+                    text: Some(text),
+                }),
+                Err(e) => entries.push(ArchiveEntry {
+                    path: raw.path,
+                    file_type: raw.file_type,
+                    status: ArchiveEntryStatus::Failed,
+                    error: Some(e.to_string()),
+                    sha256,
+                    text: None,
+                }),
+            }
+        }
+
+        Ok(entries)
+    }
+
+    async fn extract_archive_entry(&self, raw: &RawArchiveEntry) -> Result<String> {
+        Ok(self.extract_bytes(&raw.data, &raw.file_type).await?.text)
+    }
+
+    /// Extract the text of a file already read into memory
+    async fn extract_bytes(&self, data: &[u8], extension: &str) -> Result<ExtractedText> {
+        use std::io::Write;
+
+        if data.len() > self.max_file_size {
+            return Err(self.file_too_large());
         }
+
+        // Extractors work on paths, so stage the bytes in a secure temp file
+        let mut temp_file = tempfile::Builder::new()
+            .prefix("bear_ai_extract_")
+            .suffix(&format!(".{}", extension))
+            .tempfile()?;
+        temp_file.write_all(data)?;
+        temp_file.flush()?;
+
+        let temp_path = temp_file
+            .path()
+            .to_str()
+            .ok_or_else(|| anyhow!("Invalid UTF-8 in temp path"))?;
+        self.extract_by_extension(temp_path, extension).await
     }
 
     async fn process_text_file(&self, file_path: &str) -> Result<String> {
@@ -128,17 +558,51 @@ impl FileProcessor {
         Ok(content)
     }
 
-    async fn process_pdf_file(&self, file_path: &str) -> Result<String> {
+    async fn process_pdf_file(&self, file_path: &str) -> Result<ExtractedText> {
         // PDF parsing using pdf-extract crate
-        match pdf_extract::extract_text(file_path) {
-            Ok(text) => Ok(text),
+        let extracted = pdf_extract::extract_text(file_path);
+
+        // Scanned PDFs have no (or a garbage) text layer; read the page images instead
+        #[cfg(feature = "ocr")]
+        {
+            let needs_ocr = match &extracted {
+                Ok(text) => ocr::is_sparse(file_path, text),
+                Err(_) => true,
+            };
+            if needs_ocr {
+                let path = file_path.to_string();
+                match tokio::task::spawn_blocking(move || ocr::recognise_pdf(&path)).await? {
+                    Ok(text) if !text.trim().is_empty() => {
+                        return Ok(ExtractedText {
+                            text,
+                            ocr_used: true,
+                            source_sha256: String::new(),
+                        })
+                    }
+                    Ok(_) => tracing::warn!(file_path, "OCR found no text in PDF"),
+                    Err(e) => tracing::warn!(file_path, "OCR fallback failed: {}", e),
+                }
+            }
+        }
+
+        match extracted {
+            Ok(text) => {
+                #[cfg(not(feature = "ocr"))]
+                if text.trim().is_empty() {
+                    tracing::warn!(
+                        file_path,
+                        "PDF has no text layer; build with the `ocr` feature to read scanned documents"
+                    );
+                }
+                Ok(ExtractedText::plain(text))
+            }
             Err(e) => {
                 // Fallback to basic text extraction
                 println!("PDF parsing failed, using fallback: {}", e);
-                Ok(format!(
+                Ok(ExtractedText::plain(format!(
                     "PDF content from: {} (Advanced PDF parsing requires additional dependencies)",
                     file_path
-                ))
+                )))
             }
         }
     }
@@ -246,6 +710,195 @@ impl FileProcessor {
         Ok(text)
     }
 
+    // OpenDocument text and spreadsheets keep their content in content.xml
+    async fn process_opendocument_file(&self, file_path: &str) -> Result<String> {
+        let file = std::fs::File::open(file_path)?;
+        let mut archive = zip::ZipArchive::new(file)?;
+
+        let limits = ArchiveLimits::default();
+        let mut budget = limits.max_total_bytes;
+        let entry = archive
+            .by_name("content.xml")
+            .map_err(|e| anyhow!("Not an OpenDocument file (no content.xml): {}", e))?;
+        let content_xml = String::from_utf8(read_document_entry(entry, &limits, &mut budget)?)?;
+
+        Ok(self.extract_text_from_odf_xml(&content_xml))
+    }
+
+    // Paragraphs and headings one per line; table rows as tab-separated cells,
+    // like range_to_text, so ODS sheets and ODT tables keep their structure
+    fn extract_text_from_odf_xml(&self, xml: &str) -> String {
+        let token_regex =
+            regex::Regex::new(r#"<(/?)([A-Za-z0-9_.:-]+)([^>]*?)(/?)>|([^<]+)"#).unwrap();
+        let repeat_regex = regex::Regex::new(r#"table:number-columns-repeated="(\d+)""#).unwrap();
+
+        let mut lines = Vec::new();
+        let mut line = String::new();
+        let mut paragraph_depth = 0usize;
+        let mut row: Option<Vec<String>> = None;
+        let mut in_cell = false;
+        let mut cell_repeats = 1usize;
+
+        for token in token_regex.captures_iter(xml) {
+            if let Some(text) = token.get(5) {
+                if paragraph_depth > 0 {
+                    line.push_str(&decode_xml_entities(text.as_str()));
+                }
+                continue;
+            }
+
+            let closing = !token[1].is_empty();
+            let self_closing = !token[4].is_empty();
+            let attributes = &token[3];
+            match &token[2] {
+                "text:p" | "text:h" if !closing && !self_closing => paragraph_depth += 1,
+                "text:p" | "text:h" if closing => {
+                    paragraph_depth = paragraph_depth.saturating_sub(1);
+                    if paragraph_depth == 0 {
+                        if in_cell {
+                            line.push(' ');
+                        } else {
+                            let paragraph = line.trim().to_string();
+                            if !paragraph.is_empty() {
+                                lines.push(paragraph);
+                            }
+                            line.clear();
+                        }
+                    }
+                }
+                "text:s" | "text:tab" if paragraph_depth > 0 => line.push(' '),
+                "text:line-break" if paragraph_depth > 0 => {
+                    line.push(if in_cell { ' ' } else { '\n' })
+                }
+                "table:table-row" if !closing => row = Some(Vec::new()),
+                "table:table-row" => {
+                    let mut cells = row.take().unwrap_or_default();
+                    while cells.last().is_some_and(|cell| cell.is_empty()) {
+                        cells.pop();
+                    }
+                    if !cells.is_empty() {
+                        lines.push(cells.join("\t"));
+                    }
+                }
+                "table:table-cell" | "table:covered-table-cell" if !closing => {
+                    line.clear();
+                    in_cell = !self_closing;
+                    // Spreadsheets repeat identical cells rather than storing each
+                    cell_repeats = repeat_regex
+                        .captures(attributes)
+                        .and_then(|c| c[1].parse::<usize>().ok())
+                        .unwrap_or(1);
+                    if self_closing {
+                        if let Some(cells) = row.as_mut() {
+                            cells.push(String::new());
+                        }
+                    }
+                }
+                "table:table-cell" | "table:covered-table-cell" => {
+                    let cell = line.split_whitespace().collect::<Vec<_>>().join(" ");
+                    line.clear();
+                    in_cell = false;
+                    if let Some(cells) = row.as_mut() {
+                        let repeats = if cell.is_empty() {
+                            1
+                        } else {
+                            cell_repeats.min(100)
+                        };
+                        cells.extend(std::iter::repeat(cell).take(repeats));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        lines.join("\n")
+    }
+
+    // EPUB: read the chapters in spine (reading) order and strip their XHTML
+    async fn process_epub_file(&self, file_path: &str) -> Result<String> {
+        let file = std::fs::File::open(file_path)?;
+        let mut archive = zip::ZipArchive::new(file)?;
+        // Chapters share one decompression budget, like the entries of an archive
+        let limits = ArchiveLimits::default();
+        let mut budget = limits.max_total_bytes;
+        let mut read_entry =
+            |archive: &mut zip::ZipArchive<std::fs::File>, name: &str| -> Result<Vec<u8>> {
+                let entry = archive
+                    .by_name(name)
+                    .map_err(|e| anyhow!("EPUB entry {} is missing: {}", name, e))?;
+                read_document_entry(entry, &limits, &mut budget)
+            };
+
+        let container = String::from_utf8(read_entry(&mut archive, "META-INF/container.xml")?)?;
+        let package_path =
+            regex::Regex::new(r#"<rootfile\b[^>]*\bfull-path\s*=\s*["']([^"']+)["']"#)
+                .unwrap()
+                .captures(&container)
+                .map(|c| c[1].to_string())
+                .ok_or_else(|| anyhow!("EPUB container does not name a package file"))?;
+        let package = String::from_utf8(read_entry(&mut archive, &package_path)?)?;
+        let package_dir = package_path
+            .rsplit_once('/')
+            .map(|(dir, _)| format!("{}/", dir))
+            .unwrap_or_default();
+
+        let attribute = |tag: &str, name: &str| -> Option<String> {
+            regex::Regex::new(&format!(r#"\b{}\s*=\s*["']([^"']*)["']"#, name))
+                .ok()?
+                .captures(tag)
+                .map(|c| decode_xml_entities(&c[1]))
+        };
+        let mut manifest = std::collections::HashMap::new();
+        for item in regex::Regex::new(r"<item\b[^>]*>")
+            .unwrap()
+            .find_iter(&package)
+        {
+            if let (Some(id), Some(href)) = (
+                attribute(item.as_str(), "id"),
+                attribute(item.as_str(), "href"),
+            ) {
+                manifest.insert(id, href);
+            }
+        }
+
+        let mut chapters = Vec::new();
+        for itemref in regex::Regex::new(r"<itemref\b[^>]*>")
+            .unwrap()
+            .find_iter(&package)
+        {
+            let Some(href) = attribute(itemref.as_str(), "idref").and_then(|id| manifest.get(&id))
+            else {
+                continue;
+            };
+            let href = urlencoding::decode(href.split('#').next().unwrap_or(href))
+                .map(|decoded| decoded.into_owned())
+                .unwrap_or_else(|_| href.clone());
+            let path = normalize_zip_path(&format!("{}{}", package_dir, href));
+            let entry = match archive.by_name(&path) {
+                Ok(entry) => entry,
+                Err(e) => {
+                    tracing::warn!("Skipping EPUB chapter {}: {}", path, e);
+                    continue;
+                }
+            };
+            // Size limits reject the whole book rather than skipping the chapter
+            match String::from_utf8(read_document_entry(entry, &limits, &mut budget)?) {
+                Ok(xhtml) => {
+                    let text = self.strip_html_tags(&xhtml);
+                    if !text.is_empty() {
+                        chapters.push(text);
+                    }
+                }
+                Err(e) => tracing::warn!("Skipping EPUB chapter {}: {}", path, e),
+            }
+        }
+
+        if chapters.is_empty() {
+            return Err(anyhow!("EPUB has no readable chapters"));
+        }
+        Ok(chapters.join("\n\n"))
+    }
+
     fn strip_html_tags(&self, html: &str) -> String {
         let tag_regex = regex::Regex::new(r"<[^>]+>").unwrap();
         let script_regex = regex::Regex::new(r"(?s)<script[^>]*>.*?</script>").unwrap();
@@ -366,26 +1019,63 @@ impl FileProcessor {
     // Helper method to extract text from docx elements
     fn extract_text_from_element(&self, element: &DocumentChild) -> String {
         match element {
-            DocumentChild::Paragraph(paragraph) => {
-                let mut paragraph_text = Vec::new();
-                for child in &paragraph.children {
-                                    if let ParagraphChild::Run(run) = child {
-                                        for run_child in &run.children {
-                                            if let RunChild::Text(text) = run_child {
-                                                paragraph_text.push(text.text.clone());
-                                            }
-                                        }
-                                    }                }
-                paragraph_text.join("")
-            }
-            DocumentChild::Table(_table) => {
-                // Simplified table handling - extract basic table indicator
-                "[TABLE_CONTENT]".to_string()
-            }
+            DocumentChild::Paragraph(paragraph) => self.extract_paragraph_text(paragraph),
+            DocumentChild::Table(table) => self.extract_table_text(table),
             _ => String::new(),
         }
     }
 
+    fn extract_paragraph_text(&self, paragraph: &Paragraph) -> String {
+        let mut paragraph_text = Vec::new();
+        for child in &paragraph.children {
+            if let ParagraphChild::Run(run) = child {
+                for run_child in &run.children {
+                    if let RunChild::Text(text) = run_child {
+                        paragraph_text.push(text.text.clone());
+                    }
+                }
+            }
+        }
+        paragraph_text.join("")
+    }
+
+    // Tables become tab-separated rows, one line per row, like spreadsheets in
+    // range_to_text
+    fn extract_table_text(&self, table: &Table) -> String {
+        let mut rows = Vec::new();
+
+        for TableChild::TableRow(row) in &table.rows {
+            let row_text: Vec<String> = row
+                .cells
+                .iter()
+                .map(|TableRowChild::TableCell(cell)| self.extract_cell_text(cell))
+                .collect();
+            if !row_text.iter().all(|s| s.is_empty()) {
+                rows.push(row_text.join("\t"));
+            }
+        }
+
+        rows.join("\n")
+    }
+
+    // A cell's paragraphs (and any nested table) on one line, so the cell
+    // cannot break the row and column structure
+    fn extract_cell_text(&self, cell: &TableCell) -> String {
+        let parts: Vec<String> = cell
+            .children
+            .iter()
+            .map(|content| match content {
+                TableCellContent::Paragraph(paragraph) => self.extract_paragraph_text(paragraph),
+                TableCellContent::Table(table) => self.extract_table_text(table),
+                #[allow(unreachable_patterns)]
+                _ => String::new(),
+            })
+            .map(|text| text.split_whitespace().collect::<Vec<_>>().join(" "))
+            .filter(|text| !text.is_empty())
+            .collect();
+        parts.join(" ")
+    }
+
     // PPTX text extraction using ZIP-based approach
     async fn extract_pptx_text(&self, file_path: &str) -> Result<String> {
         use std::io::Read;
@@ -739,3 +1429,981 @@ impl FileProcessor {
         text.join(" ")
     }
 }
+
+/// Walk a ZIP archive (recursing into nested ZIPs), enforcing zip-bomb limits
+fn collect_archive_entries(
+    archive_path: &str,
+    bytes: &[u8],
+    depth: usize,
+    limits: &ArchiveLimits,
+    budget: &mut ArchiveBudget,
+    out: &mut Vec<RawArchiveEntry>,
+    rejected: &mut Vec<ArchiveEntry>,
+) -> Result<()> {
+    if depth > limits.max_depth {
+        return Err(anyhow!(
+            "Nested archive {} exceeds maximum depth of {}",
+            archive_path,
+            limits.max_depth
+        ));
+    }
+
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes))
+        .map_err(|e| anyhow!("Invalid ZIP archive {}: {}", archive_path, e))?;
+
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)?;
+        if file.is_dir() {
+            continue;
+        }
+
+        budget.entries += 1;
+        if budget.entries > limits.max_entries {
+            return Err(anyhow!(
+                "Archive rejected: more than {} entries",
+                limits.max_entries
+            ));
+        }
+
+        // SECURITY: Reject absolute paths and '..' components (zip-slip)
+        let Some(entry_name) = file
+            .enclosed_name()
+            .map(|p| p.to_string_lossy().replace('\\', "/"))
+        else {
+            rejected.push(ArchiveEntry {
+                path: format!("{}/{}", archive_path, file.name()),
+                file_type: String::new(),
+                status: ArchiveEntryStatus::Failed,
+                error: Some("Unsafe entry path".to_string()),
+                sha256: None,
+                text: None,
+            });
+            continue;
+        };
+        let entry_path = format!("{}/{}", archive_path, entry_name);
+
+        let remaining = limits.max_total_bytes.saturating_sub(budget.total_bytes);
+        check_compression_ratio(&entry_path, file.size(), file.compressed_size(), limits)?;
+        if file.size() > remaining {
+            return Err(anyhow!(
+                "Archive rejected: uncompressed size exceeds {} MB",
+                limits.max_total_bytes / (1024 * 1024)
+            ));
+        }
+
+        // Declared sizes can lie, so cap the actual read as well
+        let mut data = Vec::new();
+        file.by_ref().take(remaining + 1).read_to_end(&mut data)?;
+        if data.len() as u64 > remaining {
+            return Err(anyhow!(
+                "Archive rejected: uncompressed size exceeds {} MB",
+                limits.max_total_bytes / (1024 * 1024)
+            ));
+        }
+        check_compression_ratio(
+            &entry_path,
+            data.len() as u64,
+            file.compressed_size(),
+            limits,
+        )?;
+        budget.total_bytes += data.len() as u64;
+
+        let file_type = Path::new(&entry_name)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+
+        if file_type == "zip" {
+            collect_archive_entries(&entry_path, &data, depth + 1, limits, budget, out, rejected)?;
+        } else {
+            out.push(RawArchiveEntry {
+                path: entry_path,
+                file_type,
+                data,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Replace the predefined XML entities and numeric character references
+fn decode_xml_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+
+    let entity_regex = regex::Regex::new(r"&(#x[0-9A-Fa-f]+|#[0-9]+|[a-z]+);").unwrap();
+    entity_regex
+        .replace_all(text, |caps: &regex::Captures| {
+            let entity = &caps[1];
+            let decoded = match entity {
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "amp" => Some('&'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                _ => entity
+                    .strip_prefix("#x")
+                    .map(|hex| u32::from_str_radix(hex, 16).ok())
+                    .unwrap_or_else(|| entity.strip_prefix('#').and_then(|n| n.parse().ok()))
+                    .and_then(char::from_u32),
+            };
+            decoded.map_or_else(|| caps[0].to_string(), String::from)
+        })
+        .into_owned()
+}
+
+/// Resolve `.` and `..` segments in a path inside a ZIP archive
+fn normalize_zip_path(path: &str) -> String {
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            _ => segments.push(segment),
+        }
+    }
+    segments.join("/")
+}
+
+/// Read one member of a ZIP-based document (ODT, EPUB) under the archive
+/// zip-bomb limits. `budget` is the decompressed size the document may still
+/// use and shrinks by what is read.
+fn read_document_entry(
+    mut entry: zip::read::ZipFile<'_>,
+    limits: &ArchiveLimits,
+    budget: &mut u64,
+) -> Result<Vec<u8>> {
+    let name = entry.name().to_string();
+    let too_large = || {
+        anyhow!(
+            "Document rejected: uncompressed size exceeds {} MB",
+            limits.max_total_bytes / (1024 * 1024)
+        )
+    };
+
+    check_compression_ratio(&name, entry.size(), entry.compressed_size(), limits)?;
+    if entry.size() > *budget {
+        return Err(too_large());
+    }
+
+    // Declared sizes can lie, so cap the actual read as well
+    let mut data = Vec::new();
+    entry.by_ref().take(*budget + 1).read_to_end(&mut data)?;
+    if data.len() as u64 > *budget {
+        return Err(too_large());
+    }
+    check_compression_ratio(&name, data.len() as u64, entry.compressed_size(), limits)?;
+    *budget -= data.len() as u64;
+    Ok(data)
+}
+
+fn check_compression_ratio(
+    entry_path: &str,
+    uncompressed: u64,
+    compressed: u64,
+    limits: &ArchiveLimits,
+) -> Result<()> {
+    if uncompressed < ARCHIVE_RATIO_CHECK_MIN_BYTES {
+        return Ok(());
+    }
+    if uncompressed / compressed.max(1) > limits.max_compression_ratio {
+        return Err(anyhow!(
+            "Archive rejected: {} exceeds compression ratio limit of {}:1",
+            entry_path,
+            limits.max_compression_ratio
+        ));
+    }
+    Ok(())
+}
+
+/// OCR of image-only PDF pages with Tesseract
+#[cfg(feature = "ocr")]
+mod ocr {
+    use crate::constants::{MAX_PDF_PAGES, PDF_OCR_MIN_CHARS_PER_PAGE};
+    use anyhow::{anyhow, Result};
+    use leptess::LepTess;
+    use lopdf::Document;
+    use std::io::Cursor;
+
+    /// Whether `text` is too thin for the PDF's page count to be its real content
+    pub fn is_sparse(file_path: &str, text: &str) -> bool {
+        let pages = Document::load(file_path)
+            .map(|doc| doc.get_pages().len())
+            .unwrap_or(1)
+            .max(1);
+        let chars = text.chars().filter(|c| c.is_alphanumeric()).count();
+        chars < pages * PDF_OCR_MIN_CHARS_PER_PAGE
+    }
+
+    /// Recognise the text of every image on every page, pages separated by a
+    /// blank line
+    pub fn recognise_pdf(file_path: &str) -> Result<String> {
+        let doc = Document::load(file_path)?;
+        let mut tesseract =
+            LepTess::new(None, "eng").map_err(|e| anyhow!("Tesseract is not available: {}", e))?;
+
+        let mut pages = Vec::new();
+        for (page_number, page_id) in doc.get_pages().into_iter().take(MAX_PDF_PAGES) {
+            let mut page_text = String::new();
+            let images = doc.get_page_images(page_id)?;
+            let mut skipped = Vec::new();
+            for image in &images {
+                let Some(encoded) = encode_image(&doc, image)? else {
+                    let filters = image.filters.clone().unwrap_or_default().join("+");
+                    tracing::debug!(
+                        page_number,
+                        filters,
+                        "Skipping PDF image in an unsupported encoding"
+                    );
+                    skipped.push(filters);
+                    continue;
+                };
+                tesseract
+                    .set_image_from_mem(&encoded)
+                    .map_err(|e| anyhow!("Failed to load page {} image: {}", page_number, e))?;
+                tesseract.set_source_resolution(300);
+                page_text.push_str(&tesseract.get_utf8_text()?);
+            }
+            if !images.is_empty() && skipped.len() == images.len() {
+                tracing::warn!(
+                    page_number,
+                    encodings = skipped.join(", "),
+                    "No image on this PDF page could be decoded for OCR; its text is missing"
+                );
+            }
+            pages.push(page_text.trim().to_string());
+        }
+
+        Ok(pages.join("\n\n"))
+    }
+
+    /// An image file Leptonica can read for a PDF image XObject
+    fn encode_image(doc: &Document, image: &lopdf::xobject::PdfImage) -> Result<Option<Vec<u8>>> {
+        let filters = image.filters.clone().unwrap_or_default();
+        match filters
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .as_slice()
+        {
+            // Scanners usually embed pages as JPEG, which Leptonica decodes itself
+            ["DCTDecode"] => Ok(Some(image.content.to_vec())),
+            // Black-and-white scans are mostly fax-coded; Leptonica reads them
+            // once wrapped in a TIFF
+            ["CCITTFaxDecode"] => {
+                let stream = doc.get_object(image.id)?.as_stream()?;
+                let parms = match stream.dict.get(b"DecodeParms") {
+                    Ok(lopdf::Object::Dictionary(parms)) => Some(parms),
+                    Ok(lopdf::Object::Array(items)) => {
+                        items.first().and_then(|item| item.as_dict().ok())
+                    }
+                    _ => None,
+                };
+                let int = |key: &[u8], default: i64| {
+                    parms
+                        .and_then(|parms| parms.get(key).ok())
+                        .and_then(|value| value.as_i64().ok())
+                        .unwrap_or(default)
+                };
+                let flag = |key: &[u8]| {
+                    parms
+                        .and_then(|parms| parms.get(key).ok())
+                        .and_then(|value| value.as_bool().ok())
+                        .unwrap_or(false)
+                };
+                Ok(Some(ccitt_tiff(
+                    image.content,
+                    CcittParams {
+                        width: int(b"Columns", 1728) as u32,
+                        height: image.height as u32,
+                        k: int(b"K", 0),
+                        black_is_1: flag(b"BlackIs1"),
+                        byte_aligned: flag(b"EncodedByteAlign"),
+                    },
+                )))
+            }
+            [] | ["FlateDecode"] => {
+                let pixels = if filters.is_empty() {
+                    image.content.to_vec()
+                } else {
+                    doc.get_object(image.id)?
+                        .as_stream()?
+                        .decompressed_content()?
+                };
+                let (width, height) = (image.width as u32, image.height as u32);
+                let decoded = match (image.bits_per_component, image.color_space.as_deref()) {
+                    (Some(1), Some("DeviceGray")) => {
+                        image::GrayImage::from_raw(width, height, unpack_bits(&pixels, width))
+                            .map(image::DynamicImage::ImageLuma8)
+                    }
+                    (Some(8), Some("DeviceGray")) => {
+                        image::GrayImage::from_raw(width, height, pixels)
+                            .map(image::DynamicImage::ImageLuma8)
+                    }
+                    (Some(8), Some("DeviceRGB")) => {
+                        image::RgbImage::from_raw(width, height, pixels)
+                            .map(image::DynamicImage::ImageRgb8)
+                    }
+                    _ => return Ok(None),
+                };
+                let decoded =
+                    decoded.ok_or_else(|| anyhow!("PDF image data does not match its size"))?;
+
+                let mut png = Vec::new();
+                decoded.write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)?;
+                Ok(Some(png))
+            }
+            // JBIG2 has no decoder here
+            _ => Ok(None),
+        }
+    }
+
+    /// Decode parameters of a CCITT fax-coded image
+    pub(super) struct CcittParams {
+        pub width: u32,
+        pub height: u32,
+        /// Negative for Group 4, otherwise Group 3
+        pub k: i64,
+        pub black_is_1: bool,
+        pub byte_aligned: bool,
+    }
+
+    /// A single-strip little-endian TIFF holding CCITT-coded image data as is
+    pub(super) fn ccitt_tiff(data: &[u8], params: CcittParams) -> Vec<u8> {
+        const SHORT: u16 = 3;
+        const LONG: u16 = 4;
+        let group_4 = params.k < 0;
+        let mut entries: Vec<(u16, u16, u32)> = vec![
+            (256, LONG, params.width),
+            (257, LONG, params.height),
+            (258, SHORT, 1),
+            (259, SHORT, if group_4 { 4 } else { 3 }),
+            // PDF's default of 0 for black matches TIFF's WhiteIsZero
+            (262, SHORT, u32::from(params.black_is_1)),
+            (273, LONG, 0),
+            (277, SHORT, 1),
+            (278, LONG, params.height),
+            (279, LONG, data.len() as u32),
+        ];
+        if !group_4 {
+            let two_dimensional = u32::from(params.k > 0);
+            let fill_bits = if params.byte_aligned { 4 } else { 0 };
+            entries.push((292, LONG, two_dimensional | fill_bits));
+        }
+        let data_offset = 8 + 2 + entries.len() as u32 * 12 + 4;
+        if let Some(entry) = entries.iter_mut().find(|entry| entry.0 == 273) {
+            entry.2 = data_offset;
+        }
+
+        let mut tiff = Vec::with_capacity(data_offset as usize + data.len());
+        tiff.extend_from_slice(b"II");
+        tiff.extend_from_slice(&42u16.to_le_bytes());
+        tiff.extend_from_slice(&8u32.to_le_bytes());
+        tiff.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        for (tag, kind, value) in entries {
+            tiff.extend_from_slice(&tag.to_le_bytes());
+            tiff.extend_from_slice(&kind.to_le_bytes());
+            tiff.extend_from_slice(&1u32.to_le_bytes());
+            if kind == SHORT {
+                tiff.extend_from_slice(&(value as u16).to_le_bytes());
+                tiff.extend_from_slice(&[0, 0]);
+            } else {
+                tiff.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        tiff.extend_from_slice(&0u32.to_le_bytes());
+        tiff.extend_from_slice(data);
+        tiff
+    }
+
+    /// 8-bit gray pixels of a 1-bit DeviceGray image whose rows are padded to
+    /// whole bytes; a set bit is white
+    pub(super) fn unpack_bits(packed: &[u8], width: u32) -> Vec<u8> {
+        let row_bytes = (width as usize).div_ceil(8);
+        packed
+            .chunks(row_bytes.max(1))
+            .flat_map(|row| {
+                (0..width as usize).map(move |x| {
+                    let bit = row.get(x / 8).map_or(0, |byte| (byte >> (7 - x % 8)) & 1);
+                    if bit == 1 {
+                        255
+                    } else {
+                        0
+                    }
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::FileOptions;
+
+    fn build_zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options = FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        for (name, data) in files {
+            writer.start_file(*name, options).unwrap();
+            writer.write_all(data).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[tokio::test]
+    async fn test_archive_reports_per_entry_results() {
+        let bundle = build_zip(&[
+            ("discovery/letter.txt", b"Letter to opposing counsel."),
+            ("discovery/notes.md", b"# Notes\nDeposition scheduled."),
+            ("discovery/viewer.exe", b"MZ\x90\x00"),
+        ]);
+
+        let processor = FileProcessor::new();
+        let entries = processor
+            .process_archive("bundle.zip", &bundle, &ArchiveLimits::default())
+            .await
+            .unwrap();
+
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].path, "bundle.zip/discovery/letter.txt");
+        assert_eq!(entries[0].status, ArchiveEntryStatus::Extracted);
+        assert_eq!(
+            entries[0].text.as_deref(),
+            Some("Letter to opposing counsel.")
+        );
+        assert_eq!(entries[1].status, ArchiveEntryStatus::Extracted);
+        assert!(entries[1].text.as_deref().unwrap().contains("Deposition"));
+        assert_eq!(entries[2].status, ArchiveEntryStatus::Unsupported);
+        assert!(entries[2].text.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_zip_bomb_and_deep_nesting_rejected() {
+        let processor = FileProcessor::new();
+
+        let zeros = vec![0u8; 8 * 1024 * 1024];
+        let bomb = build_zip(&[("filler.txt", &zeros)]);
+        let err = processor
+            .process_archive("bomb.zip", &bomb, &ArchiveLimits::default())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("compression ratio"));
+
+        let limits = ArchiveLimits {
+            max_depth: 1,
+            ..Default::default()
+        };
+        let innermost = build_zip(&[("a.txt", b"deep")]);
+        let middle = build_zip(&[("inner.zip", &innermost)]);
+        let outer = build_zip(&[("middle.zip", &middle)]);
+        let err = processor
+            .process_archive("outer.zip", &outer, &limits)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("maximum depth"));
+    }
+
+    #[tokio::test]
+    async fn test_docx_tables_keep_rows_and_cells() {
+        let cell = |text: &str| {
+            TableCell::new().add_paragraph(Paragraph::new().add_run(Run::new().add_text(text)))
+        };
+        let table = Table::new(vec![
+            TableRow::new(vec![cell("Party"), cell("Payment")]),
+            TableRow::new(vec![cell("Acme Corp"), cell("$5,000 on signing")]),
+        ]);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("schedule.docx");
+        Docx::new()
+            .add_paragraph(Paragraph::new().add_run(Run::new().add_text("Payment schedule")))
+            .add_table(table)
+            .add_paragraph(Paragraph::new().add_run(Run::new().add_text("Signed below.")))
+            .build()
+            .pack(std::fs::File::create(&path).unwrap())
+            .unwrap();
+
+        let text = FileProcessor::new()
+            .process_file(path.to_str().unwrap(), "docx")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            text,
+            "Payment schedule\nParty\tPayment\nAcme Corp\t$5,000 on signing\nSigned below."
+        );
+    }
+
+    #[tokio::test]
+    async fn test_opendocument_text_and_sheets() {
+        let content = br#"<?xml version="1.0" encoding="UTF-8"?>
+<office:document-content xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0" xmlns:text="urn:oasis:names:tc:opendocument:xmlns:text:1.0" xmlns:table="urn:oasis:names:tc:opendocument:xmlns:table:1.0">
+<office:body><office:text>
+<text:h text:outline-level="1">Lease &amp; Terms</text:h>
+<text:p text:style-name="P1">Rent is due<text:s/>on the <text:span>first</text:span> day.</text:p>
+<table:table><table:table-row>
+<table:table-cell><text:p>Tenant</text:p></table:table-cell>
+<table:table-cell table:number-columns-repeated="2"><text:p>Paid</text:p></table:table-cell>
+<table:table-cell table:number-columns-repeated="1000"/>
+</table:table-row></table:table>
+</office:text></office:body></office:document-content>"#;
+
+        let dir = tempfile::tempdir().unwrap();
+        let processor = FileProcessor::new();
+        for extension in ["odt", "ods"] {
+            let path = dir.path().join(format!("lease.{}", extension));
+            std::fs::write(
+                &path,
+                build_zip(&[
+                    ("mimetype", b"application/vnd.oasis.opendocument.text"),
+                    ("content.xml", content),
+                ]),
+            )
+            .unwrap();
+
+            let text = processor
+                .process_file(path.to_str().unwrap(), extension)
+                .await
+                .unwrap();
+            assert_eq!(
+                text,
+                "Lease & Terms\nRent is due on the first day.\nTenant\tPaid\tPaid"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_epub_chapters_follow_spine_order() {
+        let container = br#"<?xml version="1.0"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+<rootfiles><rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/></rootfiles>
+</container>"#;
+        let package = br#"<?xml version="1.0"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+<manifest>
+<item id="intro" href="text/intro.xhtml" media-type="application/xhtml+xml"/>
+<item id="ch1" href="text/chapter%201.xhtml" media-type="application/xhtml+xml"/>
+<item id="css" href="../styles/book.css" media-type="text/css"/>
+</manifest>
+<spine><itemref idref="ch1"/><itemref idref="intro"/></spine>
+</package>"#;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("casebook.epub");
+        std::fs::write(
+            &path,
+            build_zip(&[
+                ("mimetype", b"application/epub+zip"),
+                ("META-INF/container.xml", container),
+                ("OEBPS/content.opf", package),
+                (
+                    "OEBPS/text/intro.xhtml",
+                    b"<html><head><style>p { color: red; }</style></head><body><p>Second in reading order.</p></body></html>",
+                ),
+                (
+                    "OEBPS/text/chapter 1.xhtml",
+                    b"<html><body><h1>Chapter One</h1><p>Contract formation.</p></body></html>",
+                ),
+            ]),
+        )
+        .unwrap();
+
+        let text = FileProcessor::new()
+            .process_file(path.to_str().unwrap(), "epub")
+            .await
+            .unwrap();
+
+        let first = text.find("Contract formation.").unwrap();
+        let second = text.find("Second in reading order.").unwrap();
+        assert!(text.contains("Chapter One"));
+        assert!(first < second);
+        assert!(!text.contains("color"));
+        assert!(text.contains("\n\n"));
+    }
+
+    #[tokio::test]
+    async fn test_opendocument_and_epub_bombs_rejected() {
+        let blank = vec![b' '; 8 * 1024 * 1024];
+        let container =
+            br#"<container><rootfiles><rootfile full-path="content.opf"/></rootfiles></container>"#;
+        let package = br#"<package><manifest><item id="ch1" href="ch1.xhtml"/></manifest><spine><itemref idref="ch1"/></spine></package>"#;
+
+        let dir = tempfile::tempdir().unwrap();
+        let odt = dir.path().join("bomb.odt");
+        std::fs::write(&odt, build_zip(&[("content.xml", &blank)])).unwrap();
+        let epub = dir.path().join("bomb.epub");
+        std::fs::write(
+            &epub,
+            build_zip(&[
+                ("META-INF/container.xml", container),
+                ("content.opf", package),
+                ("ch1.xhtml", &blank),
+            ]),
+        )
+        .unwrap();
+
+        let processor = FileProcessor::new();
+        for (path, extension) in [(&odt, "odt"), (&epub, "epub")] {
+            let err = processor
+                .process_file(path.to_str().unwrap(), extension)
+                .await
+                .unwrap_err();
+            assert!(
+                err.to_string().contains("compression ratio"),
+                "{}: {}",
+                extension,
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn test_text_chunks_without_line_breaks_end_at_whitespace() {
+        let text = "SSN 123-45-6789 belongs to jane.doe@example.com ".repeat(50);
+        let chunks: Vec<String> = TextChunkReader::from_text(text.clone(), 100)
+            .map(Result::unwrap)
+            .collect();
+
+        assert_eq!(chunks.concat(), text);
+        for chunk in &chunks {
+            assert!(chunk.len() <= 100);
+            assert!(chunk.ends_with(' '), "chunk cut mid-word: {:?}", chunk);
+        }
+    }
+
+    /// Counts heap use on threads that opt in, so a test can measure its own
+    /// peak allocation while other tests run in parallel
+    mod alloc_tracking {
+        use std::alloc::{GlobalAlloc, Layout, System};
+        use std::cell::Cell;
+
+        thread_local! {
+            static TRACKING: Cell<bool> = const { Cell::new(false) };
+            static CURRENT: Cell<isize> = const { Cell::new(0) };
+            static PEAK: Cell<isize> = const { Cell::new(0) };
+        }
+
+        pub struct TrackingAllocator;
+
+        fn record(delta: isize) {
+            let _ = TRACKING.try_with(|tracking| {
+                if tracking.get() {
+                    let current = CURRENT.with(|c| {
+                        c.set(c.get() + delta);
+                        c.get()
+                    });
+                    PEAK.with(|peak| peak.set(peak.get().max(current)));
+                }
+            });
+        }
+
+        unsafe impl GlobalAlloc for TrackingAllocator {
+            unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+                record(layout.size() as isize);
+                System.alloc(layout)
+            }
+
+            unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+                record(-(layout.size() as isize));
+                System.dealloc(ptr, layout)
+            }
+
+            unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+                record(new_size as isize - layout.size() as isize);
+                System.realloc(ptr, layout, new_size)
+            }
+        }
+
+        /// Run `f` and return the most heap it held at once on this thread
+        pub fn peak_during<T>(f: impl FnOnce() -> T) -> (T, usize) {
+            CURRENT.with(|c| c.set(0));
+            PEAK.with(|p| p.set(0));
+            TRACKING.with(|t| t.set(true));
+            let result = f();
+            TRACKING.with(|t| t.set(false));
+            (result, PEAK.with(|p| p.get()).max(0) as usize)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: alloc_tracking::TrackingAllocator = alloc_tracking::TrackingAllocator;
+
+    #[tokio::test]
+    async fn test_large_csv_streams_in_bounded_memory() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("clients.csv");
+        {
+            let mut file = std::io::BufWriter::new(std::fs::File::create(&path).unwrap());
+            writeln!(file, "id,name,email").unwrap();
+            let mut i = 0;
+            while file.get_ref().metadata().unwrap().len() < 10 * 1024 * 1024 {
+                for _ in 0..1000 {
+                    writeln!(file, "{},Client {},client{}@example.com", i, i, i).unwrap();
+                    i += 1;
+                }
+                file.flush().unwrap();
+            }
+        }
+        let file_size = std::fs::metadata(&path).unwrap().len() as usize;
+        let path = path.to_str().unwrap();
+
+        let chunks = FileProcessor::new()
+            .open_text_chunks(path, 64 * 1024)
+            .await
+            .unwrap();
+        let ((total, count), peak) = alloc_tracking::peak_during(|| {
+            chunks.fold((0, 0), |(total, count), chunk| {
+                let chunk = chunk.unwrap();
+                assert!(chunk.ends_with('\n'));
+                (total + chunk.len(), count + 1)
+            })
+        });
+
+        assert_eq!(total, file_size);
+        assert!(count > 100);
+        assert!(
+            peak < 1024 * 1024,
+            "streaming a {} byte CSV peaked at {} bytes",
+            file_size,
+            peak
+        );
+
+        // The size cap is configurable
+        let err = FileProcessor::with_limits(None, 1024 * 1024)
+            .open_text_chunks(path, 64 * 1024)
+            .await
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("maximum limit of 1MB"));
+    }
+
+    #[tokio::test]
+    async fn test_text_chunks_cap_long_lines_and_decode_lossily() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("scan.txt");
+        // One long line with no whitespace, multibyte characters straddling
+        // chunk boundaries, and a byte that is not UTF-8
+        let mut bytes = "é".repeat(300).into_bytes();
+        bytes.push(0xFF);
+        bytes.extend_from_slice("ß".repeat(300).as_bytes());
+        std::fs::write(&path, &bytes).unwrap();
+
+        let chunks: Vec<String> = FileProcessor::new()
+            .open_text_chunks(path.to_str().unwrap(), 101)
+            .await
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+
+        assert!(chunks.len() > 5);
+        for chunk in &chunks {
+            assert!(chunk.len() <= 101 + 3, "chunk of {} bytes", chunk.len());
+        }
+        let text = chunks.concat();
+        assert_eq!(
+            text,
+            format!("{}\u{FFFD}{}", "é".repeat(300), "ß".repeat(300))
+        );
+    }
+
+    /// A one-page PDF holding only a grayscale picture of `word` drawn in a
+    /// blocky 5x7 font, like a scan with no text layer; `one_bit` stores it as
+    /// a bilevel image, the way black-and-white scans are
+    #[cfg(feature = "ocr")]
+    fn scanned_pdf(word: &str, path: &Path, one_bit: bool) {
+        use lopdf::content::{Content, Operation};
+        use lopdf::{dictionary, Document, Object, Stream};
+
+        const SCALE: usize = 12;
+        let glyph = |c: char| -> [&str; 7] {
+            match c {
+                'L' => [
+                    "#....", "#....", "#....", "#....", "#....", "#....", "#####",
+                ],
+                'E' => [
+                    "#####", "#....", "#....", "####.", "#....", "#....", "#####",
+                ],
+                'A' => [
+                    ".###.", "#...#", "#...#", "#####", "#...#", "#...#", "#...#",
+                ],
+                'S' => [
+                    ".####", "#....", "#....", ".###.", "....#", "....#", "####.",
+                ],
+                _ => panic!("no glyph for {}", c),
+            }
+        };
+
+        let (width, height) = ((word.len() * 6 + 4) * SCALE, 11 * SCALE);
+        let mut pixels = vec![255u8; width * height];
+        for (i, c) in word.chars().enumerate() {
+            for (row, line) in glyph(c).iter().enumerate() {
+                for (col, cell) in line.chars().enumerate() {
+                    if cell != '#' {
+                        continue;
+                    }
+                    let (x0, y0) = ((2 + i * 6 + col) * SCALE, (2 + row) * SCALE);
+                    for y in y0..y0 + SCALE {
+                        pixels[y * width + x0..y * width + x0 + SCALE].fill(0);
+                    }
+                }
+            }
+        }
+
+        let (bits, pixels) = if one_bit {
+            let packed = pixels
+                .chunks(width)
+                .flat_map(|row| {
+                    row.chunks(8).map(|byte| {
+                        byte.iter().enumerate().fold(0u8, |packed, (i, pixel)| {
+                            packed | (u8::from(*pixel == 255) << (7 - i))
+                        })
+                    })
+                })
+                .collect();
+            (1, packed)
+        } else {
+            (8, pixels)
+        };
+
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let image_id = doc.add_object(Stream::new(
+            dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Image",
+                "Width" => width as i64,
+                "Height" => height as i64,
+                "ColorSpace" => "DeviceGray",
+                "BitsPerComponent" => bits,
+            },
+            pixels,
+        ));
+        let content = Content {
+            operations: vec![
+                Operation::new("q", vec![]),
+                Operation::new(
+                    "cm",
+                    vec![
+                        (width as i64).into(),
+                        0.into(),
+                        0.into(),
+                        (height as i64).into(),
+                        0.into(),
+                        0.into(),
+                    ],
+                ),
+                Operation::new("Do", vec!["Im0".into()]),
+                Operation::new("Q", vec![]),
+            ],
+        };
+        let content_id = doc.add_object(Stream::new(dictionary! {}, content.encode().unwrap()));
+        let page_id = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "Contents" => content_id,
+            "MediaBox" => vec![0.into(), 0.into(), (width as i64).into(), (height as i64).into()],
+            "Resources" => dictionary! { "XObject" => dictionary! { "Im0" => image_id } },
+        });
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => vec![page_id.into()],
+                "Count" => 1,
+            }),
+        );
+        let catalog_id = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+        doc.trailer.set("Root", catalog_id);
+        doc.save(path).unwrap();
+    }
+
+    #[cfg(feature = "ocr")]
+    #[tokio::test]
+    async fn test_scanned_pdf_falls_back_to_ocr() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("scanned.pdf");
+        scanned_pdf("LEASE", &path, false);
+
+        let extracted = FileProcessor::new()
+            .process_file_detailed(path.to_str().unwrap(), "pdf")
+            .await
+            .unwrap();
+
+        assert!(extracted.ocr_used);
+        assert!(extracted.text.to_uppercase().contains("LEASE"));
+
+        // Bilevel scans are decoded as well
+        let path = dir.path().join("bilevel.pdf");
+        scanned_pdf("SALE", &path, true);
+        let extracted = FileProcessor::new()
+            .process_file_detailed(path.to_str().unwrap(), "pdf")
+            .await
+            .unwrap();
+        assert!(extracted.ocr_used);
+        assert!(extracted.text.to_uppercase().contains("SALE"));
+    }
+
+    #[cfg(feature = "ocr")]
+    #[test]
+    fn test_fax_coded_images_are_wrapped_in_tiff() {
+        use super::ocr::{ccitt_tiff, unpack_bits, CcittParams};
+
+        let data = [0xAA; 16];
+        let tiff = ccitt_tiff(
+            &data,
+            CcittParams {
+                width: 2480,
+                height: 3508,
+                k: -1,
+                black_is_1: false,
+                byte_aligned: false,
+            },
+        );
+        assert_eq!(&tiff[..4], b"II*\0");
+        let entries = u16::from_le_bytes([tiff[8], tiff[9]]) as usize;
+        let entry = |index: usize| {
+            let at = 10 + index * 12;
+            (
+                u16::from_le_bytes([tiff[at], tiff[at + 1]]),
+                u32::from_le_bytes([tiff[at + 8], tiff[at + 9], tiff[at + 10], tiff[at + 11]]),
+            )
+        };
+        let tags: Vec<(u16, u32)> = (0..entries).map(entry).collect();
+        assert!(tags.contains(&(256, 2480)));
+        assert!(tags.contains(&(259, 4)));
+        assert!(tags.contains(&(279, 16)));
+        let offset = tags.iter().find(|(tag, _)| *tag == 273).unwrap().1 as usize;
+        assert_eq!(&tiff[offset..], &data);
+
+        // Rows of 1-bit images are padded to whole bytes
+        assert_eq!(
+            unpack_bits(&[0b1010_0000, 0b0100_0000], 3),
+            vec![255, 0, 255, 0, 255, 0]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_extracted_text_reports_hash_of_its_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("lease.txt");
+        std::fs::write(&path, "The tenant pays rent monthly.").unwrap();
+
+        let extracted = FileProcessor::new()
+            .process_file_detailed(path.to_str().unwrap(), "txt")
+            .await
+            .unwrap();
+
+        assert_eq!(extracted.text, "The tenant pays rent monthly.");
+        assert_eq!(
+            extracted.source_sha256,
+            hex::encode(Sha256::digest(b"The tenant pays rent monthly."))
+        );
+    }
+}
@@ -0,0 +1,245 @@
+/// Graceful fallback responses for failed generations
+///
+/// When the engine fails mid-request (OOM, model not ready, context overflow)
+/// the chat commands return a structured apology with an error category and
+/// a suggestion instead of the raw engine error.
+use crate::rate_limiter::RateLimitExceeded;
+use serde::{Deserialize, Serialize};
+use std::collections::TryReserveError;
+use std::io::ErrorKind;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GenerationErrorCategory {
+    OutOfMemory,
+    ModelUnavailable,
+    ContextTooLong,
+    ResourceLimit,
+    Internal,
+}
+
+impl GenerationErrorCategory {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::OutOfMemory => "out_of_memory",
+            Self::ModelUnavailable => "model_unavailable",
+            Self::ContextTooLong => "context_too_long",
+            Self::ResourceLimit => "resource_limit",
+            Self::Internal => "internal",
+        }
+    }
+
+    /// Whether the user can resolve the failure themselves
+    pub fn is_user_actionable(&self) -> bool {
+        !matches!(self, Self::Internal)
+    }
+
+    pub fn suggestion(&self) -> &str {
+        match self {
+            Self::OutOfMemory => {
+                "Try a smaller model, close other applications, or reduce the maximum response length."
+            }
+            Self::ModelUnavailable => {
+                "Check that the model is downloaded in Model Manager, then try again."
+            }
+            Self::ContextTooLong => {
+                "Shorten your message or attach fewer documents, then try again."
+            }
+            Self::ResourceLimit => "Wait a moment for system load to drop, then try again.",
+            Self::Internal => {
+                "Please try again. If the problem persists, restart the application."
+            }
+        }
+    }
+}
+
+/// Classify an engine error, by its typed causes first and its message otherwise
+pub fn classify_generation_error(error: &anyhow::Error) -> GenerationErrorCategory {
+    for cause in error.chain() {
+        if cause.is::<TryReserveError>() {
+            return GenerationErrorCategory::OutOfMemory;
+        }
+        if cause.is::<RateLimitExceeded>() {
+            return GenerationErrorCategory::ResourceLimit;
+        }
+        if let Some(io) = cause.downcast_ref::<std::io::Error>() {
+            match io.kind() {
+                ErrorKind::OutOfMemory => return GenerationErrorCategory::OutOfMemory,
+                ErrorKind::NotFound => return GenerationErrorCategory::ModelUnavailable,
+                _ => {}
+            }
+        }
+    }
+
+    // `{:#}` includes every context layer, not just the outermost
+    classify_error_message(&format!("{:#}", error))
+}
+
+/// Classify an engine error message (Candle and GPU runtimes only report text)
+fn classify_error_message(error: &str) -> GenerationErrorCategory {
+    let error = error.to_lowercase();
+    let matches_any = |needles: &[&str]| needles.iter().any(|n| error.contains(n));
+    // "OOM" only as a word of its own, not inside "room" or "zoom"
+    let oom_word = error
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .any(|word| word == "oom");
+
+    if oom_word
+        || matches_any(&[
+            "out of memory",
+            "out_of_memory",
+            "memory allocation",
+            "failed to allocate",
+            "insufficient memory",
+        ])
+    {
+        GenerationErrorCategory::OutOfMemory
+    } else if matches_any(&[
+        "not loaded",
+        "not downloaded",
+        "model file not found",
+        "not found in registry",
+        "currently downloading",
+        "currently loading",
+        "failed to load",
+    ]) {
+        GenerationErrorCategory::ModelUnavailable
+    } else if matches_any(&[
+        "context length",
+        "context window",
+        "too many tokens",
+        "prompt is too long",
+    ]) {
+        GenerationErrorCategory::ContextTooLong
+    } else if matches_any(&["resource limit", "critically high"]) {
+        GenerationErrorCategory::ResourceLimit
+    } else {
+        GenerationErrorCategory::Internal
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FallbackConfig {
+    /// Return a structured apology instead of the raw error
+    pub enabled: bool,
+    /// Attach the raw engine error to the fallback (for debugging)
+    pub include_error_detail: bool,
+}
+
+impl Default for FallbackConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            include_error_detail: false,
+        }
+    }
+}
+
+/// Structured apology returned when generation fails
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenerationFallback {
+    pub message: String,
+    pub category: GenerationErrorCategory,
+    pub user_actionable: bool,
+    pub suggestion: String,
+    pub error_detail: Option<String>,
+}
+
+impl GenerationFallback {
+    pub fn from_error(error: &anyhow::Error, config: &FallbackConfig) -> Self {
+        let category = classify_generation_error(error);
+        let message = if category.is_user_actionable() {
+            "Sorry, I couldn't generate a response."
+        } else {
+            "Sorry, something went wrong while generating a response."
+        };
+
+        Self {
+            message: message.to_string(),
+            category,
+            user_actionable: category.is_user_actionable(),
+            suggestion: category.suggestion().to_string(),
+            error_detail: config.include_error_detail.then(|| error.to_string()),
+        }
+    }
+}
+
+/// Error returned by chat commands: a plain message, or a generation fallback
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum SendMessageError {
+    Message(String),
+    Fallback(GenerationFallback),
+}
+
+impl From<String> for SendMessageError {
+    fn from(message: String) -> Self {
+        Self::Message(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_oom_failure_returns_structured_fallback() {
+        let error = anyhow::anyhow!("Generation failed: CUDA_ERROR_OUT_OF_MEMORY: out of memory");
+        let fallback = GenerationFallback::from_error(&error, &FallbackConfig::default());
+
+        assert_eq!(fallback.category, GenerationErrorCategory::OutOfMemory);
+        assert!(fallback.user_actionable);
+        assert!(fallback.suggestion.contains("smaller model"));
+        assert!(fallback.error_detail.is_none());
+
+        let json = serde_json::to_value(SendMessageError::Fallback(fallback)).unwrap();
+        assert_eq!(json["category"], "out_of_memory");
+    }
+
+    #[test]
+    fn test_unknown_failure_is_internal() {
+        let config = FallbackConfig {
+            include_error_detail: true,
+            ..Default::default()
+        };
+        let error = anyhow::anyhow!("tensor shape mismatch");
+        let fallback = GenerationFallback::from_error(&error, &config);
+
+        assert_eq!(fallback.category, GenerationErrorCategory::Internal);
+        assert!(!fallback.user_actionable);
+        assert_eq!(
+            fallback.error_detail.as_deref(),
+            Some("tensor shape mismatch")
+        );
+    }
+
+    #[test]
+    fn test_oom_matches_typed_errors_not_substrings() {
+        // "room" in a model error is not an out-of-memory failure
+        let error = anyhow::anyhow!("No room left in the KV cache for the prompt");
+        assert_eq!(
+            classify_generation_error(&error),
+            GenerationErrorCategory::Internal
+        );
+        let error = anyhow::anyhow!("Metal OOM while allocating tensor");
+        assert_eq!(
+            classify_generation_error(&error),
+            GenerationErrorCategory::OutOfMemory
+        );
+
+        let mut buffer: Vec<u8> = Vec::new();
+        let reserve = buffer.try_reserve(usize::MAX).unwrap_err();
+        let error = anyhow::Error::new(reserve).context("Generation failed");
+        assert_eq!(
+            classify_generation_error(&error),
+            GenerationErrorCategory::OutOfMemory
+        );
+
+        let missing = std::io::Error::new(ErrorKind::NotFound, "model.gguf");
+        let error = anyhow::Error::new(missing).context("Opening weights");
+        assert_eq!(
+            classify_generation_error(&error),
+            GenerationErrorCategory::ModelUnavailable
+        );
+    }
+}
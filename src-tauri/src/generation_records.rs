@@ -0,0 +1,325 @@
+/// Per-generation parameter records for reproducing responses
+///
+/// When enabled, the model, sampling parameters, stop sequences and prompt
+/// token count behind a reply are stored in the `metadata` of its
+/// `chat_messages` row under `generation`, encrypted with the message when it
+/// is. The prompt itself is never stored, only its SHA-256, so a record carries
+/// no PII but still shows whether a reproduction used the same prompt. The
+/// opt-in is stored in the database.
+use crate::compliance::run_migration;
+use crate::database::chat_encryption_integration::ChatEncryptionLayer;
+use crate::llm_manager::GenerationConfig;
+use crate::text_segmentation::TokenCounter;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Key of the record within a message's metadata object
+pub const METADATA_KEY: &str = "generation";
+
+/// Everything needed to rerun one generation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GenerationRecord {
+    pub model: String,
+    pub seed: Option<u64>,
+    pub temperature: f32,
+    pub top_p: f32,
+    pub top_k: usize,
+    pub repetition_penalty: f32,
+    pub max_tokens: usize,
+    pub stop_sequences: Vec<String>,
+    #[serde(default)]
+    pub grammar: Option<String>,
+    #[serde(default)]
+    pub logit_bias: HashMap<u32, f32>,
+    #[serde(default)]
+    pub banned_tokens: Vec<u32>,
+    pub prompt_tokens: usize,
+    pub prompt_sha256: String,
+    pub recorded_at: DateTime<Utc>,
+}
+
+impl GenerationRecord {
+    pub fn capture(
+        model: &str,
+        config: &GenerationConfig,
+        prompt: &str,
+        counter: &dyn TokenCounter,
+    ) -> Self {
+        Self {
+            model: model.to_string(),
+            seed: config.seed,
+            temperature: config.temperature,
+            top_p: config.top_p,
+            top_k: config.top_k,
+            repetition_penalty: config.repetition_penalty,
+            max_tokens: config.max_tokens,
+            stop_sequences: config.stop_sequences.clone(),
+            grammar: config.grammar.clone(),
+            logit_bias: config.logit_bias.clone(),
+            banned_tokens: config.banned_tokens.clone(),
+            prompt_tokens: counter.count_tokens(prompt),
+            prompt_sha256: hex::encode(Sha256::digest(prompt.as_bytes())),
+            recorded_at: Utc::now(),
+        }
+    }
+
+    /// Generation settings that reproduce this record
+    #[cfg(test)]
+    pub fn generation_config(&self) -> GenerationConfig {
+        GenerationConfig {
+            temperature: self.temperature,
+            max_tokens: self.max_tokens,
+            top_p: self.top_p,
+            top_k: self.top_k,
+            repetition_penalty: self.repetition_penalty,
+            seed: self.seed,
+            stop_sequences: self.stop_sequences.clone(),
+            grammar: self.grammar.clone(),
+            json_schema: None,
+            logit_bias: self.logit_bias.clone(),
+            banned_tokens: self.banned_tokens.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GenerationRecordConfig {
+    /// Store a record with each reply; off unless the user opts in
+    pub enabled: bool,
+}
+
+/// Stores and reads records in the `chat_messages` table
+pub struct GenerationRecorder {
+    db_path: PathBuf,
+    config: GenerationRecordConfig,
+}
+
+impl GenerationRecorder {
+    pub fn new(db_path: PathBuf) -> Self {
+        Self {
+            db_path,
+            config: GenerationRecordConfig::default(),
+        }
+    }
+
+    /// Initialize the settings table and load the stored opt-in
+    pub fn initialize(&mut self) -> Result<()> {
+        let conn = Connection::open(&self.db_path)?;
+
+        let migration = include_str!("../migrations/011_create_generation_record_settings.sql");
+        run_migration(&conn, migration)?;
+
+        if let Some(enabled) = conn
+            .query_row(
+                "SELECT enabled FROM generation_record_settings WHERE id = 1",
+                [],
+                |row| row.get(0),
+            )
+            .optional()?
+        {
+            self.config = GenerationRecordConfig { enabled };
+        }
+        Ok(())
+    }
+
+    pub fn get_config(&self) -> GenerationRecordConfig {
+        self.config.clone()
+    }
+
+    /// Replace and persist the recording settings
+    pub fn set_config(&mut self, config: GenerationRecordConfig) -> Result<()> {
+        let conn = Connection::open(&self.db_path)?;
+        conn.execute(
+            "INSERT OR REPLACE INTO generation_record_settings (id, enabled, updated_at)
+             VALUES (1, ?1, CURRENT_TIMESTAMP)",
+            params![config.enabled],
+        )?;
+        self.config = config;
+        Ok(())
+    }
+
+    /// Add `record` to a message's metadata, keeping any other metadata keys.
+    /// The metadata is decrypted, merged and re-encrypted through `layer`.
+    pub fn attach(
+        &self,
+        layer: &ChatEncryptionLayer,
+        message_id: i64,
+        record: &GenerationRecord,
+    ) -> Result<()> {
+        let conn = Connection::open(&self.db_path)?;
+        let (_, metadata) = layer
+            .message_metadata(&conn, message_id)?
+            .ok_or_else(|| anyhow!("Chat message not found: {}", message_id))?;
+
+        let mut metadata = match metadata.as_deref().map(serde_json::from_str::<JsonValue>) {
+            Some(Ok(JsonValue::Object(map))) => map,
+            _ => serde_json::Map::new(),
+        };
+        metadata.insert(METADATA_KEY.to_string(), serde_json::to_value(record)?);
+
+        layer.update_message_metadata(&conn, message_id, &JsonValue::Object(metadata).to_string())
+    }
+
+    /// Record stored with a message of `user_id`'s, if it has one; another
+    /// user's message is refused
+    pub fn load(
+        &self,
+        layer: &ChatEncryptionLayer,
+        message_id: i64,
+        user_id: &str,
+    ) -> Result<Option<GenerationRecord>> {
+        let conn = Connection::open(&self.db_path)?;
+        let (owner, metadata) = layer
+            .message_metadata(&conn, message_id)?
+            .ok_or_else(|| anyhow!("Chat message not found: {}", message_id))?;
+        if owner != user_id {
+            return Err(anyhow!(
+                "Chat message {} does not belong to this user",
+                message_id
+            ));
+        }
+
+        let Some(Ok(JsonValue::Object(mut metadata))) =
+            metadata.as_deref().map(serde_json::from_str::<JsonValue>)
+        else {
+            return Ok(None);
+        };
+        metadata
+            .remove(METADATA_KEY)
+            .map(serde_json::from_value)
+            .transpose()
+            .map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::security::KeyManager;
+    use crate::text_segmentation::WordCounter;
+    use std::sync::Arc;
+
+    fn chat_store(db_path: &std::path::Path) -> r2d2::Pool<r2d2_sqlite::SqliteConnectionManager> {
+        let pool = r2d2::Pool::builder()
+            .max_size(1)
+            .build(r2d2_sqlite::SqliteConnectionManager::file(db_path))
+            .unwrap();
+        pool.get()
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE chat_messages (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    chat_id TEXT NOT NULL,
+                    role TEXT NOT NULL,
+                    content TEXT NOT NULL,
+                    timestamp DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    metadata TEXT,
+                    user_id TEXT DEFAULT '',
+                    encrypted INTEGER DEFAULT 0,
+                    encryption_version INTEGER
+                );",
+            )
+            .unwrap();
+        pool
+    }
+
+    #[test]
+    fn test_record_stored_with_message_reproduces_parameters() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("bear_ai.db");
+        let pool = chat_store(&db_path);
+        let conn = pool.get().unwrap();
+        let layer = ChatEncryptionLayer::new(Arc::new(KeyManager::new().unwrap())).unwrap();
+        let message_id = layer
+            .store_encrypted_message(
+                &conn,
+                "chat1",
+                "assistant",
+                "The notice period is 30 days.",
+                "alice",
+                Some("{\"pinned\":true}"),
+            )
+            .unwrap();
+
+        let config = GenerationConfig {
+            temperature: 0.2,
+            max_tokens: 256,
+            top_p: 0.9,
+            top_k: 20,
+            repetition_penalty: 1.3,
+            seed: Some(42),
+            stop_sequences: vec!["</s>".to_string()],
+            grammar: None,
+            json_schema: None,
+            logit_bias: HashMap::from([(7, -5.0)]),
+            banned_tokens: vec![13],
+        };
+        let prompt = "What notice period does the lease for [PERSON] require?";
+        let record = GenerationRecord::capture("tinyllama-1.1b", &config, prompt, &WordCounter);
+
+        let mut recorder = GenerationRecorder::new(db_path.clone());
+        recorder.initialize().unwrap();
+        recorder
+            .set_config(GenerationRecordConfig { enabled: true })
+            .unwrap();
+        recorder.attach(&layer, message_id, &record).unwrap();
+
+        let stored = recorder.load(&layer, message_id, "alice").unwrap().unwrap();
+        assert_eq!(stored, record);
+        assert_eq!(stored.model, "tinyllama-1.1b");
+        assert_eq!(stored.prompt_tokens, 9);
+        let reproduced = stored.generation_config();
+        assert_eq!(reproduced.seed, Some(42));
+        assert_eq!(reproduced.temperature, 0.2);
+        assert_eq!(reproduced.top_p, 0.9);
+        assert_eq!(reproduced.top_k, 20);
+        assert_eq!(reproduced.repetition_penalty, 1.3);
+        assert_eq!(reproduced.logit_bias, config.logit_bias);
+        assert_eq!(reproduced.banned_tokens, vec![13]);
+        assert_eq!(reproduced.max_tokens, 256);
+        assert_eq!(reproduced.stop_sequences, vec!["</s>"]);
+
+        // Existing metadata survives, still readable through the layer, and
+        // neither the record nor the prompt is stored in the clear
+        let (_, _, _, metadata) = layer.retrieve_decrypted_message(&conn, message_id).unwrap();
+        let metadata = metadata.expect("metadata decrypts");
+        assert!(metadata.contains("\"pinned\":true"));
+        assert!(metadata.contains("tinyllama-1.1b"));
+        let raw: String = conn
+            .query_row(
+                "SELECT metadata FROM chat_messages WHERE id = ?1",
+                [message_id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!(!raw.contains("tinyllama-1.1b"));
+        assert!(!raw.contains("notice period"));
+
+        // Only the owner of the message reads its record
+        assert!(recorder.load(&layer, message_id, "bob").is_err());
+        assert!(recorder.load(&layer, message_id + 1, "alice").is_err());
+    }
+
+    #[test]
+    fn test_opt_in_survives_restart() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("bear_ai.db");
+
+        let mut recorder = GenerationRecorder::new(db_path.clone());
+        recorder.initialize().unwrap();
+        assert!(!recorder.get_config().enabled);
+        recorder
+            .set_config(GenerationRecordConfig { enabled: true })
+            .unwrap();
+
+        let mut restarted = GenerationRecorder::new(db_path);
+        restarted.initialize().unwrap();
+        assert!(restarted.get_config().enabled);
+    }
+}
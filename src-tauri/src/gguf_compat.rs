@@ -0,0 +1,325 @@
+/// GGUF compatibility check run before a model is handed to the Candle engine
+///
+/// Candle's quantized llama loader fails with low-level errors ("unknown dtype",
+/// "cannot find llama.attention.head_count") on architectures and quantization
+/// types it does not implement. This reads only the GGUF header and tensor
+/// table so an unsupported file is rejected with a plain reason instead.
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+const GGUF_MAGIC: &[u8; 4] = b"GGUF";
+
+/// `general.architecture` values the quantized llama loader can run
+///
+/// Mistral and most Llama derivatives are exported under "llama".
+pub const SUPPORTED_ARCHITECTURES: &[&str] = &["llama"];
+
+/// GGUF container versions with 64-bit lengths, as read by Candle
+const SUPPORTED_VERSIONS: &[u32] = &[2, 3];
+
+/// Guards against corrupt headers claiming absurd sizes
+const MAX_STRING_BYTES: u64 = 16 * 1024 * 1024;
+const MAX_ENTRIES: u64 = 1_000_000;
+const MAX_ARRAY_ITEMS: u64 = 100_000_000;
+
+/// Verdict on whether a GGUF file can be loaded by the inference engine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GgufCompatibility {
+    pub path: String,
+    pub compatible: bool,
+    pub gguf_version: Option<u32>,
+    pub architecture: Option<String>,
+    pub model_name: Option<String>,
+    /// Distinct tensor types in the file, e.g. "Q4_K", "F16"
+    pub quantizations: Vec<String>,
+    /// Why the file cannot be loaded; empty when compatible
+    pub reasons: Vec<String>,
+}
+
+/// Tensor type name, and whether Candle can load it
+fn tensor_type(id: u32) -> (String, bool) {
+    let (name, supported) = match id {
+        0 => ("F32", true),
+        1 => ("F16", true),
+        2 => ("Q4_0", true),
+        3 => ("Q4_1", true),
+        6 => ("Q5_0", true),
+        7 => ("Q5_1", true),
+        8 => ("Q8_0", true),
+        9 => ("Q8_1", true),
+        10 => ("Q2_K", true),
+        11 => ("Q3_K", true),
+        12 => ("Q4_K", true),
+        13 => ("Q5_K", true),
+        14 => ("Q6_K", true),
+        15 => ("Q8_K", true),
+        16 => ("IQ2_XXS", false),
+        17 => ("IQ2_XS", false),
+        18 => ("IQ3_XXS", false),
+        19 => ("IQ1_S", false),
+        20 => ("IQ4_NL", false),
+        21 => ("IQ3_S", false),
+        22 => ("IQ2_S", false),
+        23 => ("IQ4_XS", false),
+        24 => ("I8", false),
+        25 => ("I16", false),
+        26 => ("I32", false),
+        27 => ("I64", false),
+        28 => ("F64", false),
+        29 => ("IQ1_M", false),
+        30 => ("BF16", true),
+        34 => ("TQ1_0", false),
+        35 => ("TQ2_0", false),
+        other => return (format!("unknown type {}", other), false),
+    };
+    (name.to_string(), supported)
+}
+
+/// Header fields needed for the compatibility verdict
+#[derive(Debug, Default)]
+struct GgufHeader {
+    version: u32,
+    architecture: Option<String>,
+    model_name: Option<String>,
+    tensor_types: Vec<u32>,
+}
+
+struct HeaderReader<R: Read> {
+    inner: R,
+}
+
+impl<R: Read> HeaderReader<R> {
+    fn bytes<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut buf = [0u8; N];
+        self.inner.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.bytes()?))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.bytes()?))
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let len = self.u64()?;
+        if len > MAX_STRING_BYTES {
+            return Err(anyhow!("String of {} bytes in GGUF header", len));
+        }
+        let mut buf = vec![0u8; len as usize];
+        self.inner.read_exact(&mut buf)?;
+        Ok(String::from_utf8_lossy(&buf).into_owned())
+    }
+
+    /// Read a metadata value, returning it only when it is a string
+    fn value(&mut self, value_type: u32) -> Result<Option<String>> {
+        match value_type {
+            0 | 1 | 7 => self.skip(1)?,
+            2 | 3 => self.skip(2)?,
+            4..=6 => self.skip(4)?,
+            10..=12 => self.skip(8)?,
+            8 => return Ok(Some(self.string()?)),
+            9 => {
+                let item_type = self.u32()?;
+                let count = self.u64()?;
+                if count > MAX_ARRAY_ITEMS {
+                    return Err(anyhow!("Array of {} items in GGUF header", count));
+                }
+                for _ in 0..count {
+                    self.value(item_type)?;
+                }
+            }
+            other => return Err(anyhow!("Unknown GGUF metadata value type {}", other)),
+        }
+        Ok(None)
+    }
+
+    fn skip(&mut self, n: u64) -> Result<()> {
+        let copied = std::io::copy(&mut (&mut self.inner).take(n), &mut std::io::sink())?;
+        if copied != n {
+            return Err(anyhow!("GGUF header is truncated"));
+        }
+        Ok(())
+    }
+}
+
+fn read_header(reader: impl Read) -> Result<GgufHeader> {
+    let mut reader = HeaderReader { inner: reader };
+
+    if &reader.bytes::<4>()? != GGUF_MAGIC {
+        return Err(anyhow!("Not a GGUF file (missing GGUF magic bytes)"));
+    }
+    let mut header = GgufHeader {
+        version: reader.u32()?,
+        ..Default::default()
+    };
+    if !SUPPORTED_VERSIONS.contains(&header.version) {
+        // Older layouts use 32-bit lengths; nothing past the version is readable
+        return Ok(header);
+    }
+
+    let tensor_count = reader.u64()?;
+    let kv_count = reader.u64()?;
+    if tensor_count > MAX_ENTRIES || kv_count > MAX_ENTRIES {
+        return Err(anyhow!("GGUF header declares implausible entry counts"));
+    }
+
+    for _ in 0..kv_count {
+        let key = reader.string()?;
+        let value_type = reader.u32()?;
+        let value = reader.value(value_type)?;
+        match key.as_str() {
+            "general.architecture" => header.architecture = value,
+            "general.name" => header.model_name = value,
+            _ => {}
+        }
+    }
+
+    for _ in 0..tensor_count {
+        reader.string()?;
+        let n_dims = reader.u32()?;
+        reader.skip(8 * n_dims as u64)?;
+        header.tensor_types.push(reader.u32()?);
+        reader.u64()?; // data offset
+    }
+
+    Ok(header)
+}
+
+fn verdict(path: &Path, header: &GgufHeader) -> GgufCompatibility {
+    let mut reasons = Vec::new();
+
+    if !SUPPORTED_VERSIONS.contains(&header.version) {
+        reasons.push(format!(
+            "GGUF version {} is not supported (supported: {:?}); \
+            re-convert the model with a current llama.cpp",
+            header.version, SUPPORTED_VERSIONS
+        ));
+    } else {
+        match &header.architecture {
+            Some(arch) if SUPPORTED_ARCHITECTURES.contains(&arch.as_str()) => {}
+            Some(arch) => reasons.push(format!(
+                "Architecture '{}' is not supported by the inference engine (supported: {})",
+                arch,
+                SUPPORTED_ARCHITECTURES.join(", ")
+            )),
+            None => reasons.push("Model does not declare general.architecture".to_string()),
+        }
+    }
+
+    let mut quantizations = BTreeSet::new();
+    let mut unsupported = BTreeSet::new();
+    for id in &header.tensor_types {
+        let (name, supported) = tensor_type(*id);
+        if !supported {
+            unsupported.insert(name.clone());
+        }
+        quantizations.insert(name);
+    }
+    if !unsupported.is_empty() {
+        reasons.push(format!(
+            "Quantization type(s) {} are not supported by the inference engine; \
+            use a Q4_K_M, Q5_K_M or Q8_0 build instead",
+            unsupported.into_iter().collect::<Vec<_>>().join(", ")
+        ));
+    }
+
+    GgufCompatibility {
+        path: path.display().to_string(),
+        compatible: reasons.is_empty(),
+        gguf_version: Some(header.version),
+        architecture: header.architecture.clone(),
+        model_name: header.model_name.clone(),
+        quantizations: quantizations.into_iter().collect(),
+        reasons,
+    }
+}
+
+/// Check a GGUF file's architecture and quantization against the engine
+/// without loading its weights
+///
+/// Unreadable or malformed files yield an incompatible verdict rather than an error.
+pub fn validate_gguf_compatibility(path: &Path) -> Result<GgufCompatibility> {
+    let file = std::fs::File::open(path)
+        .map_err(|e| anyhow!("Cannot open model file {:?}: {}", path, e))?;
+
+    match read_header(BufReader::new(file)) {
+        Ok(header) => Ok(verdict(path, &header)),
+        Err(e) => Ok(GgufCompatibility {
+            path: path.display().to_string(),
+            compatible: false,
+            gguf_version: None,
+            architecture: None,
+            model_name: None,
+            quantizations: Vec::new(),
+            reasons: vec![format!("Unreadable GGUF header: {}", e)],
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn push_string(buf: &mut Vec<u8>, s: &str) {
+        buf.extend((s.len() as u64).to_le_bytes());
+        buf.extend(s.as_bytes());
+    }
+
+    /// Minimal GGUF v3 file: string metadata plus one tensor of the given type
+    fn fixture(architecture: &str, tensor_type: u32) -> Vec<u8> {
+        let mut buf = GGUF_MAGIC.to_vec();
+        buf.extend(3u32.to_le_bytes());
+        buf.extend(1u64.to_le_bytes()); // tensors
+        buf.extend(3u64.to_le_bytes()); // metadata entries
+        for (key, value) in [
+            ("general.architecture", architecture),
+            ("general.name", "Fixture"),
+        ] {
+            push_string(&mut buf, key);
+            buf.extend(8u32.to_le_bytes());
+            push_string(&mut buf, value);
+        }
+        push_string(&mut buf, "general.file_type");
+        buf.extend(4u32.to_le_bytes());
+        buf.extend(15u32.to_le_bytes());
+
+        push_string(&mut buf, "token_embd.weight");
+        buf.extend(2u32.to_le_bytes());
+        buf.extend(4096u64.to_le_bytes());
+        buf.extend(32000u64.to_le_bytes());
+        buf.extend(tensor_type.to_le_bytes());
+        buf.extend(0u64.to_le_bytes());
+        buf
+    }
+
+    #[test]
+    fn test_rejects_unsupported_architecture_with_reason() {
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        let llama = temp_dir.path().join("llama.gguf");
+        std::fs::write(&llama, fixture("llama", 12)).unwrap();
+        let verdict = validate_gguf_compatibility(&llama).unwrap();
+        assert!(verdict.compatible, "{:?}", verdict.reasons);
+        assert_eq!(verdict.quantizations, vec!["Q4_K"]);
+
+        let mamba = temp_dir.path().join("mamba.gguf");
+        std::fs::write(&mamba, fixture("mamba", 12)).unwrap();
+        let verdict = validate_gguf_compatibility(&mamba).unwrap();
+        assert!(!verdict.compatible);
+        assert_eq!(verdict.architecture.as_deref(), Some("mamba"));
+        assert_eq!(verdict.reasons.len(), 1);
+        assert!(verdict.reasons[0].contains("Architecture 'mamba' is not supported"));
+
+        let iq = temp_dir.path().join("iq.gguf");
+        std::fs::write(&iq, fixture("llama", 23)).unwrap();
+        let verdict = validate_gguf_compatibility(&iq).unwrap();
+        assert!(!verdict.compatible);
+        assert!(verdict.reasons[0].contains("IQ4_XS"));
+    }
+}
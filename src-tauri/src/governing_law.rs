@@ -0,0 +1,169 @@
+/// Governing-law and venue extraction for contract analysis
+///
+/// Finds "governed by the laws of the State of New York" style clauses and
+/// forum/venue clauses ("the courts located in London shall have exclusive
+/// jurisdiction"), returning the named jurisdiction in a normalized form so
+/// downstream disclaimers can mention it.
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+lazy_static! {
+    static ref GOVERNING_LAW_PATTERN: Regex = Regex::new(
+        r"(?:(?i:governed\s+by|construed\s+(?:in\s+accordance\s+with|under)|interpreted\s+(?:in\s+accordance\s+with|under)|subject\s+to)(?:\s+(?i:and\s+(?:construed|interpreted)\s+(?:in\s+accordance\s+with|under)))?\s+(?i:the\s+)?(?i:laws?)\s+(?i:of|in\s+force\s+in)\s+)(?i:the\s+)?(?:(?i:state|commonwealth|province|republic|kingdom|federal\s+republic)\s+of\s+)?(?P<jurisdiction>[A-Z][\w.'-]*(?:\s+(?:and\s+|&\s+)?[A-Z][\w.'-]*)*)"
+    )
+    .expect("Governing law regex is invalid");
+    static ref VENUE_PATTERN: Regex = Regex::new(
+        r"(?:(?i:courts?|tribunals?)\s+(?i:(?:located|sitting|situated)\s+)?(?i:in|of|at)|(?i:venue)\s+(?i:(?:shall|will)\s+(?:lie|be)\s+(?:exclusively\s+)?(?:in|at)))\s+(?i:the\s+)?(?:(?i:state|county|city|borough|province|commonwealth)\s+of\s+)?(?P<venue>[A-Z][\w.'-]*(?:\s+(?:and\s+|&\s+)?[A-Z][\w.'-]*)*)"
+    )
+    .expect("Venue regex is invalid");
+    static ref VENUE_KEYWORDS: Regex = Regex::new(
+        r"(?i)\b(?:venue|exclusive\s+jurisdiction|non-exclusive\s+jurisdiction|submit\s+to\s+the\s+jurisdiction|forum)\b"
+    )
+    .expect("Venue keyword regex is invalid");
+}
+
+/// Spellings folded onto one canonical jurisdiction name
+const JURISDICTION_ALIASES: &[(&str, &str)] = &[
+    ("NY", "New York"),
+    ("N.Y.", "New York"),
+    ("England & Wales", "England and Wales"),
+    ("England", "England and Wales"),
+    ("U.S.", "United States"),
+    ("USA", "United States"),
+    ("United States of America", "United States"),
+    ("UK", "United Kingdom"),
+    ("U.K.", "United Kingdom"),
+    ("Holland", "Netherlands"),
+    ("The Netherlands", "Netherlands"),
+];
+
+/// Governing law and venue named in a contract
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct GoverningLaw {
+    /// Normalized jurisdiction whose law governs the contract, e.g. "New York"
+    pub jurisdiction: Option<String>,
+    /// Sentence the governing law was taken from
+    pub clause: Option<String>,
+    /// Normalized forum for disputes, e.g. "New York County"
+    pub venue: Option<String>,
+    pub venue_clause: Option<String>,
+}
+
+impl GoverningLaw {
+    pub fn is_empty(&self) -> bool {
+        self.jurisdiction.is_none() && self.venue.is_none()
+    }
+}
+
+/// Extract the governing law and venue clauses from contract text
+pub fn extract_governing_law(content: &str) -> GoverningLaw {
+    let mut result = GoverningLaw::default();
+
+    if let Some(caps) = GOVERNING_LAW_PATTERN.captures(content) {
+        let whole = caps.get(0).expect("capture 0 always exists");
+        result.jurisdiction = Some(normalize_jurisdiction(&caps["jurisdiction"]));
+        result.clause = Some(enclosing_sentence(content, whole.start(), whole.end()));
+    }
+
+    for caps in VENUE_PATTERN.captures_iter(content) {
+        let whole = caps.get(0).expect("capture 0 always exists");
+        let sentence = enclosing_sentence(content, whole.start(), whole.end());
+        if !VENUE_KEYWORDS.is_match(&sentence) {
+            continue;
+        }
+        result.venue = Some(normalize_jurisdiction(&caps["venue"]));
+        result.venue_clause = Some(sentence);
+        break;
+    }
+
+    result
+}
+
+/// Trim trailing punctuation and fold known aliases onto a canonical name
+pub fn normalize_jurisdiction(raw: &str) -> String {
+    let trimmed = raw
+        .trim()
+        .trim_end_matches([',', ';', ':'])
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    // Keep the dot of abbreviations like "N.Y." but drop a sentence-final one
+    let trimmed = if trimmed.ends_with('.') && trimmed.matches('.').count() == 1 {
+        trimmed.trim_end_matches('.').to_string()
+    } else {
+        trimmed
+    };
+
+    JURISDICTION_ALIASES
+        .iter()
+        .find(|(alias, _)| alias.eq_ignore_ascii_case(&trimmed))
+        .map(|(_, canonical)| canonical.to_string())
+        .unwrap_or(trimmed)
+}
+
+/// Sentence (or line) containing the byte range `start..end`
+fn enclosing_sentence(content: &str, start: usize, end: usize) -> String {
+    let is_break = |window: &str| window.starts_with(". ") || window.starts_with('\n');
+
+    let mut from = start;
+    while from > 0 {
+        let prev = content[..from]
+            .char_indices()
+            .next_back()
+            .map(|(i, _)| i)
+            .unwrap_or(0);
+        if is_break(&content[prev..]) {
+            break;
+        }
+        from = prev;
+    }
+
+    let mut to = end;
+    while to < content.len() && !is_break(&content[to..]) {
+        to += content[to..]
+            .chars()
+            .next()
+            .map(char::len_utf8)
+            .unwrap_or(1);
+    }
+    if content[to..].starts_with('.') {
+        to += 1;
+    }
+
+    content[from..to].trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLAUSE_FIXTURE: &str = "12. MISCELLANEOUS\n\
+        12.1 Governing Law. This Agreement shall be governed by and construed in accordance with the laws of the State of New York, without regard to its conflict of laws principles. \
+        12.2 Venue. Any action arising out of this Agreement shall be brought exclusively in the state or federal courts located in New York County, and the parties submit to the exclusive jurisdiction of such courts.\n";
+
+    #[test]
+    fn test_new_york_extracted_as_governing_jurisdiction() {
+        let governing = extract_governing_law(CLAUSE_FIXTURE);
+
+        assert_eq!(governing.jurisdiction.as_deref(), Some("New York"));
+        assert!(governing.clause.unwrap().contains(
+            "governed by and construed in accordance with the laws of the State of New York"
+        ));
+        assert_eq!(governing.venue.as_deref(), Some("New York County"));
+        assert!(governing
+            .venue_clause
+            .unwrap()
+            .contains("exclusive jurisdiction"));
+    }
+
+    #[test]
+    fn test_aliases_are_normalized() {
+        let governing =
+            extract_governing_law("This Agreement is governed by the laws of England & Wales.");
+        assert_eq!(governing.jurisdiction.as_deref(), Some("England and Wales"));
+        assert!(governing.venue.is_none());
+
+        assert!(extract_governing_law("The tenant shall pay rent monthly.").is_empty());
+    }
+}
@@ -0,0 +1,633 @@
+/// GBNF grammars for constrained generation
+///
+/// A grammar is parsed into rules, each a list of alternative sequences of
+/// character sets and references to other rules; groups and the `*`, `+` and
+/// `?` operators become generated rules. `GrammarMatcher` follows every way
+/// the text so far can continue, so the sampler can mask tokens whose text
+/// would leave the grammar. Left-recursive rules are not supported: their
+/// recursive alternatives never match.
+///
+/// `json_schema_to_grammar` compiles a JSON schema into a grammar whose
+/// output always parses as JSON of the schema's shape.
+use anyhow::{anyhow, bail, Result};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+
+/// Rule a grammar starts from
+pub const ROOT_RULE: &str = "root";
+
+/// Rule nesting beyond which a continuation is dropped
+const MAX_STACK_DEPTH: usize = 128;
+
+/// Rules for any JSON value, appended to every grammar compiled from a schema
+const JSON_PRIMITIVES: &str = r#"
+value ::= object | array | string | number | boolean | null
+object ::= "{" ws ( string ws ":" ws value ( ws "," ws string ws ":" ws value )* )? ws "}"
+array ::= "[" ws ( value ( ws "," ws value )* )? ws "]"
+string ::= "\"" ( [^"\\\x00-\x1f] | "\\" ["\\/bfnrt] | "\\u" [0-9a-fA-F] [0-9a-fA-F] [0-9a-fA-F] [0-9a-fA-F] )* "\""
+number ::= integer ( "." [0-9]+ )? ( [eE] [-+]? [0-9]+ )?
+integer ::= "-"? ( "0" | [1-9] [0-9]* )
+boolean ::= "true" | "false"
+null ::= "null"
+ws ::= [ \t\n]?
+"#;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Element {
+    /// One character inside the ranges, or outside them when negated
+    Chars {
+        ranges: Vec<(char, char)>,
+        negated: bool,
+    },
+    Rule(usize),
+}
+
+impl Element {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Element::Chars { ranges, negated } => {
+                ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi) != *negated
+            }
+            Element::Rule(_) => false,
+        }
+    }
+}
+
+type Alternative = Vec<Element>;
+
+/// Position in a rule: (rule, alternative, next element)
+type Frame = (usize, usize, usize);
+type Stack = Vec<Frame>;
+
+#[derive(Debug, Clone)]
+pub struct Grammar {
+    rules: Vec<Vec<Alternative>>,
+    root: usize,
+}
+
+impl Grammar {
+    pub fn parse(gbnf: &str) -> Result<Self> {
+        Parser {
+            chars: gbnf.chars().collect(),
+            pos: 0,
+            names: HashMap::new(),
+            rules: Vec::new(),
+        }
+        .parse()
+    }
+
+    /// Matcher at the start of the root rule
+    pub fn matcher(&self) -> GrammarMatcher<'_> {
+        let mut stacks = HashSet::new();
+        for alt in 0..self.rules[self.root].len() {
+            self.expand(vec![(self.root, alt, 0)], 0, &mut stacks);
+        }
+        GrammarMatcher {
+            grammar: self,
+            stacks: stacks.into_iter().collect(),
+        }
+    }
+
+    fn element(&self, (rule, alt, index): Frame) -> Option<&Element> {
+        self.rules[rule][alt].get(index)
+    }
+
+    /// Resolve rule references at the top of `stack` until it ends at a
+    /// character set, or is empty when the root rule is complete, adding each
+    /// result to `out`. Frames from `fresh_from` on were entered without
+    /// consuming a character, so re-entering their rule is left recursion.
+    fn expand(&self, mut stack: Stack, mut fresh_from: usize, out: &mut HashSet<Stack>) {
+        loop {
+            let Some(&top) = stack.last() else {
+                out.insert(stack);
+                return;
+            };
+            match self.element(top) {
+                None => {
+                    stack.pop();
+                    fresh_from = fresh_from.min(stack.len());
+                }
+                Some(Element::Chars { .. }) => {
+                    out.insert(stack);
+                    return;
+                }
+                Some(&Element::Rule(rule)) => {
+                    if stack.len() >= MAX_STACK_DEPTH
+                        || stack[fresh_from..].iter().any(|frame| frame.0 == rule)
+                    {
+                        return;
+                    }
+                    if let Some(top) = stack.last_mut() {
+                        top.2 += 1;
+                    }
+                    // Nothing is left to match in a finished frame, so drop it to
+                    // keep repetition from growing the stack. Fresh frames stay
+                    // for the left recursion check; there are few of them.
+                    if stack.len() - 1 < fresh_from
+                        && stack.last().is_some_and(|&top| self.element(top).is_none())
+                    {
+                        stack.pop();
+                        fresh_from = fresh_from.min(stack.len());
+                    }
+                    for alt in 0..self.rules[rule].len() {
+                        let mut next = stack.clone();
+                        next.push((rule, alt, 0));
+                        self.expand(next, fresh_from, out);
+                    }
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Tracks how far generated text has come through a grammar
+#[derive(Debug, Clone)]
+pub struct GrammarMatcher<'g> {
+    grammar: &'g Grammar,
+    /// Every possible continuation; an empty stack means the text so far is complete
+    stacks: Vec<Stack>,
+}
+
+impl GrammarMatcher<'_> {
+    /// Advance past `text`; false, leaving the matcher unchanged, when the
+    /// grammar does not allow it
+    pub fn accept(&mut self, text: &str) -> bool {
+        let mut stacks = self.stacks.clone();
+        for c in text.chars() {
+            let mut next = HashSet::new();
+            for stack in &stacks {
+                let Some(&top) = stack.last() else {
+                    continue;
+                };
+                if self.grammar.element(top).is_some_and(|e| e.matches(c)) {
+                    let mut advanced = stack.clone();
+                    if let Some(top) = advanced.last_mut() {
+                        top.2 += 1;
+                    }
+                    let fresh_from = advanced.len();
+                    self.grammar.expand(advanced, fresh_from, &mut next);
+                }
+            }
+            if next.is_empty() {
+                return false;
+            }
+            stacks = next.into_iter().collect();
+        }
+        self.stacks = stacks;
+        true
+    }
+
+    /// Whether the grammar allows `text` next
+    pub fn allows(&self, text: &str) -> bool {
+        self.clone().accept(text)
+    }
+
+    /// The text so far is a complete match and may end here
+    pub fn is_complete(&self) -> bool {
+        self.stacks.iter().any(Vec::is_empty)
+    }
+
+    /// The text so far is complete and nothing more may follow
+    pub fn is_finished(&self) -> bool {
+        self.stacks.iter().all(Vec::is_empty)
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    names: HashMap<String, usize>,
+    /// None until a named rule's definition is seen
+    rules: Vec<Option<Vec<Alternative>>>,
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '-' || c == '_'
+}
+
+impl Parser {
+    fn parse(mut self) -> Result<Grammar> {
+        loop {
+            self.skip_space(true);
+            if self.peek().is_none() {
+                break;
+            }
+            let name = self
+                .parse_name()
+                .ok_or_else(|| self.error("expected a rule name"))?;
+            self.skip_space(false);
+            if !self.eat("::=") {
+                return Err(self.error("expected '::='"));
+            }
+            let alternatives = self.parse_alternatives(false)?;
+            let id = self.rule_id(&name);
+            if self.rules[id].is_some() {
+                bail!("Grammar rule '{}' is defined twice", name);
+            }
+            self.rules[id] = Some(alternatives);
+        }
+
+        let root = *self
+            .names
+            .get(ROOT_RULE)
+            .ok_or_else(|| anyhow!("Grammar has no '{}' rule", ROOT_RULE))?;
+        if let Some((name, _)) = self.names.iter().find(|(_, &id)| self.rules[id].is_none()) {
+            bail!("Grammar rule '{}' is used but not defined", name);
+        }
+        Ok(Grammar {
+            rules: self
+                .rules
+                .into_iter()
+                .map(Option::unwrap_or_default)
+                .collect(),
+            root,
+        })
+    }
+
+    fn error(&self, message: &str) -> anyhow::Error {
+        let line = self.chars[..self.pos.min(self.chars.len())]
+            .iter()
+            .filter(|&&c| c == '\n')
+            .count()
+            + 1;
+        anyhow!("Invalid grammar at line {}: {}", line, message)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        let matches = token
+            .chars()
+            .enumerate()
+            .all(|(i, c)| self.chars.get(self.pos + i) == Some(&c));
+        if matches {
+            self.pos += token.chars().count();
+        }
+        matches
+    }
+
+    /// Skip blanks and `#` comments, and newlines too when `newlines` is set
+    fn skip_space(&mut self, newlines: bool) {
+        while let Some(c) = self.peek() {
+            match c {
+                ' ' | '\t' | '\r' => self.pos += 1,
+                '\n' if newlines => self.pos += 1,
+                '#' => {
+                    while self.peek().is_some_and(|c| c != '\n') {
+                        self.pos += 1;
+                    }
+                }
+                _ => break,
+            }
+        }
+    }
+
+    fn parse_name(&mut self) -> Option<String> {
+        let start = self.pos;
+        while self.peek().is_some_and(is_name_char) {
+            self.pos += 1;
+        }
+        (self.pos > start).then(|| self.chars[start..self.pos].iter().collect())
+    }
+
+    /// After a newline outside parentheses: whether the rule goes on, rather
+    /// than the input ending or the next rule starting
+    fn rule_continues(&self) -> bool {
+        let mut lookahead = Parser {
+            chars: self.chars[self.pos..].to_vec(),
+            pos: 0,
+            names: HashMap::new(),
+            rules: Vec::new(),
+        };
+        lookahead.skip_space(true);
+        if lookahead.peek().is_none() {
+            return false;
+        }
+        if lookahead.parse_name().is_some() {
+            lookahead.skip_space(false);
+            return !lookahead.eat("::=");
+        }
+        true
+    }
+
+    fn rule_id(&mut self, name: &str) -> usize {
+        if let Some(&id) = self.names.get(name) {
+            return id;
+        }
+        self.rules.push(None);
+        self.names.insert(name.to_string(), self.rules.len() - 1);
+        self.rules.len() - 1
+    }
+
+    fn generated_rule(&mut self, alternatives: Vec<Alternative>) -> usize {
+        self.rules.push(Some(alternatives));
+        self.rules.len() - 1
+    }
+
+    fn parse_alternatives(&mut self, nested: bool) -> Result<Vec<Alternative>> {
+        let mut alternatives = vec![self.parse_sequence(nested)?];
+        while self.peek() == Some('|') {
+            self.pos += 1;
+            alternatives.push(self.parse_sequence(nested)?);
+        }
+        Ok(alternatives)
+    }
+
+    fn parse_sequence(&mut self, nested: bool) -> Result<Alternative> {
+        let mut sequence = Vec::new();
+        loop {
+            self.skip_space(nested);
+            if self.peek() == Some('\n') {
+                if !self.rule_continues() {
+                    break;
+                }
+                self.skip_space(true);
+                continue;
+            }
+
+            let mut term = match self.peek() {
+                Some('"') => {
+                    self.pos += 1;
+                    let mut literal = Vec::new();
+                    while self.peek() != Some('"') {
+                        let c = self.parse_char()?;
+                        literal.push(Element::Chars {
+                            ranges: vec![(c, c)],
+                            negated: false,
+                        });
+                    }
+                    self.pos += 1;
+                    literal
+                }
+                Some('[') => vec![self.parse_class()?],
+                Some('(') => {
+                    self.pos += 1;
+                    let alternatives = self.parse_alternatives(true)?;
+                    self.skip_space(true);
+                    if !self.eat(")") {
+                        return Err(self.error("expected ')'"));
+                    }
+                    vec![Element::Rule(self.generated_rule(alternatives))]
+                }
+                Some('.') => {
+                    self.pos += 1;
+                    vec![Element::Chars {
+                        ranges: Vec::new(),
+                        negated: true,
+                    }]
+                }
+                Some(c) if is_name_char(c) => {
+                    let name = self.parse_name().unwrap_or_default();
+                    vec![Element::Rule(self.rule_id(&name))]
+                }
+                _ => break,
+            };
+
+            if let Some(op @ ('*' | '+' | '?')) = self.peek() {
+                self.pos += 1;
+                let item = match term.len() {
+                    1 => term.remove(0),
+                    _ => Element::Rule(self.generated_rule(vec![term])),
+                };
+                term = match op {
+                    '?' => vec![Element::Rule(
+                        self.generated_rule(vec![vec![item], Vec::new()]),
+                    )],
+                    _ => {
+                        // item* ::= item item* | (empty)
+                        let star = self.rules.len();
+                        self.rules.push(Some(vec![
+                            vec![item.clone(), Element::Rule(star)],
+                            Vec::new(),
+                        ]));
+                        if op == '+' {
+                            vec![item, Element::Rule(star)]
+                        } else {
+                            vec![Element::Rule(star)]
+                        }
+                    }
+                };
+            }
+            sequence.extend(term);
+        }
+        Ok(sequence)
+    }
+
+    fn parse_class(&mut self) -> Result<Element> {
+        self.pos += 1;
+        let negated = self.eat("^");
+        let mut ranges = Vec::new();
+        while self.peek() != Some(']') {
+            let lo = self.parse_char()?;
+            let hi = if self.peek() == Some('-') && self.chars.get(self.pos + 1) != Some(&']') {
+                self.pos += 1;
+                self.parse_char()?
+            } else {
+                lo
+            };
+            ranges.push((lo, hi));
+        }
+        self.pos += 1;
+        Ok(Element::Chars { ranges, negated })
+    }
+
+    /// One character of a literal or class, resolving escapes
+    fn parse_char(&mut self) -> Result<char> {
+        let c = self
+            .peek()
+            .ok_or_else(|| self.error("unterminated literal or character class"))?;
+        self.pos += 1;
+        if c != '\\' {
+            return Ok(c);
+        }
+        let escaped = self
+            .peek()
+            .ok_or_else(|| self.error("unterminated escape"))?;
+        self.pos += 1;
+        let hex_digits = match escaped {
+            'n' => return Ok('\n'),
+            't' => return Ok('\t'),
+            'r' => return Ok('\r'),
+            '\\' | '"' | '[' | ']' | '-' | '^' => return Ok(escaped),
+            'x' => 2,
+            'u' => 4,
+            _ => return Err(self.error(&format!("unknown escape '\\{}'", escaped))),
+        };
+        let digits: String = self.chars[self.pos..].iter().take(hex_digits).collect();
+        self.pos += digits.len();
+        u32::from_str_radix(&digits, 16)
+            .ok()
+            .filter(|_| digits.len() == hex_digits)
+            .and_then(char::from_u32)
+            .ok_or_else(|| self.error("invalid hex escape"))
+    }
+}
+
+/// Compile a JSON schema into a GBNF grammar for JSON of its shape. Supports
+/// `type` (one or a list), `properties` (all emitted, in schema order),
+/// `items`, `enum`, `const`, `anyOf` and `oneOf`; other keywords such as
+/// formats and length limits are not enforced.
+pub fn json_schema_to_grammar(schema: &Value) -> Result<String> {
+    Ok(format!(
+        "{} ::= {}\n{}",
+        ROOT_RULE,
+        schema_expression(schema)?,
+        JSON_PRIMITIVES.trim_start()
+    ))
+}
+
+/// GBNF literal matching `text` exactly
+fn gbnf_literal(text: &str) -> String {
+    let mut literal = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            '\t' => literal.push_str("\\t"),
+            '\r' => literal.push_str("\\r"),
+            c => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+fn json_literal(value: &Value) -> String {
+    gbnf_literal(&value.to_string())
+}
+
+fn alternatives(expressions: Vec<String>) -> String {
+    format!("( {} )", expressions.join(" | "))
+}
+
+fn schema_expression(schema: &Value) -> Result<String> {
+    let Some(object) = schema.as_object() else {
+        return match schema {
+            Value::Bool(true) => Ok("value".to_string()),
+            _ => bail!("Unsupported JSON schema: {}", schema),
+        };
+    };
+
+    if let Some(value) = object.get("const") {
+        return Ok(json_literal(value));
+    }
+    if let Some(values) = object.get("enum") {
+        let values = values
+            .as_array()
+            .filter(|values| !values.is_empty())
+            .ok_or_else(|| anyhow!("JSON schema enum must be a non-empty array"))?;
+        return Ok(alternatives(values.iter().map(json_literal).collect()));
+    }
+    if let Some(options) = object.get("anyOf").or_else(|| object.get("oneOf")) {
+        let options = options
+            .as_array()
+            .ok_or_else(|| anyhow!("JSON schema anyOf/oneOf must be an array"))?;
+        return Ok(alternatives(
+            options
+                .iter()
+                .map(schema_expression)
+                .collect::<Result<_>>()?,
+        ));
+    }
+
+    match object.get("type") {
+        Some(Value::String(kind)) => typed_expression(kind, object),
+        Some(Value::Array(kinds)) => Ok(alternatives(
+            kinds
+                .iter()
+                .map(|kind| {
+                    let kind = kind
+                        .as_str()
+                        .ok_or_else(|| anyhow!("JSON schema type must be a string"))?;
+                    typed_expression(kind, object)
+                })
+                .collect::<Result<_>>()?,
+        )),
+        Some(other) => bail!("Unsupported JSON schema type: {}", other),
+        None if object.contains_key("properties") => typed_expression("object", object),
+        None => Ok("value".to_string()),
+    }
+}
+
+fn typed_expression(kind: &str, schema: &serde_json::Map<String, Value>) -> Result<String> {
+    Ok(match kind {
+        "object" => match schema.get("properties").and_then(Value::as_object) {
+            Some(properties) if !properties.is_empty() => {
+                let members = properties
+                    .iter()
+                    .map(|(name, property)| {
+                        Ok(format!(
+                            "{} ws \":\" ws {}",
+                            json_literal(&Value::String(name.clone())),
+                            schema_expression(property)?
+                        ))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                format!("\"{{\" ws {} ws \"}}\"", members.join(" ws \",\" ws "))
+            }
+            _ => "object".to_string(),
+        },
+        "array" => match schema.get("items") {
+            Some(items) => {
+                let item = schema_expression(items)?;
+                format!("\"[\" ws ( {} ( ws \",\" ws {} )* )? ws \"]\"", item, item)
+            }
+            None => "array".to_string(),
+        },
+        "string" | "number" | "integer" | "boolean" | "null" => kind.to_string(),
+        other => bail!("Unsupported JSON schema type: {}", other),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_schema_grammar_accepts_only_conforming_json() {
+        let grammar = json_schema_to_grammar(&json!({
+            "type": "object",
+            "properties": {
+                "risk": {"enum": ["low", "medium", "high"]},
+                "clauses": {"type": "array", "items": {"type": "integer"}}
+            }
+        }))
+        .unwrap();
+        let grammar = Grammar::parse(&grammar).unwrap();
+
+        let mut matcher = grammar.matcher();
+        assert!(matcher.accept(r#"{"risk": "medium", "clauses": [4, 12]}"#));
+        assert!(matcher.is_finished());
+
+        // Partial output is accepted while it can still become valid
+        let mut matcher = grammar.matcher();
+        assert!(matcher.accept(r#"{"risk":"hi"#));
+        assert!(!matcher.is_complete());
+        assert!(!matcher.allows("x"));
+        assert!(matcher.accept(r#"gh","clauses":[]}"#));
+        assert!(matcher.is_complete());
+
+        for invalid in [
+            r#"{"risk": "extreme""#,
+            r#"{"clauses": []}"#,
+            r#"Sure! {"risk""#,
+            r#"{"risk": "low", "clauses": [1.5]}"#,
+        ] {
+            assert!(!grammar.matcher().allows(invalid), "allowed {}", invalid);
+        }
+
+        assert!(Grammar::parse("root ::= item").is_err());
+        assert!(Grammar::parse("item ::= \"x\"").is_err());
+        let repeated = Grammar::parse("root ::= (\"ab\")+ \"c\"?\n  | [0-9]").unwrap();
+        for text in ["ab", "ababc", "7"] {
+            let mut matcher = repeated.matcher();
+            assert!(matcher.accept(text) && matcher.is_complete(), "{}", text);
+        }
+        assert!(!repeated.matcher().allows("abca"));
+    }
+}
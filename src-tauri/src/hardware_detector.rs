@@ -1,6 +1,8 @@
 use anyhow::{anyhow, Result};
 use nvml_wrapper::Nvml;
 use serde::{Deserialize, Serialize};
+#[cfg(any(target_os = "linux", test))]
+use std::path::Path;
 use sysinfo::System;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -52,6 +54,213 @@ pub struct ModelRecommendation {
     pub compatibility_score: f64, // 0.0-1.0
 }
 
+/// Every GPU found: NVIDIA devices through NVML, otherwise AMD devices
+/// through the platform (sysfs on Linux, WMI on Windows, system_profiler on
+/// macOS). Blocks on driver calls, so call it from a blocking thread.
+pub fn detect_gpus() -> Vec<GpuInfo> {
+    let gpus = nvml_gpus();
+    if !gpus.is_empty() {
+        return gpus;
+    }
+    platform_gpus()
+}
+
+#[cfg(target_os = "linux")]
+fn platform_gpus() -> Vec<GpuInfo> {
+    drm_gpus(Path::new("/sys/class/drm"))
+}
+
+/// AMD GPUs from WMI. `AdapterRAM` is a 32-bit field, so cards with more than
+/// 4GB report at most 4GB, which only makes offload planning conservative.
+/// WMI has no usage figures, so all VRAM is reported free.
+#[cfg(target_os = "windows")]
+fn platform_gpus() -> Vec<GpuInfo> {
+    use wmi::{COMLibrary, WMIConnection};
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct Win32VideoController {
+        name: Option<String>,
+        adapter_ram: Option<u64>,
+        driver_version: Option<String>,
+    }
+
+    let controllers = COMLibrary::new()
+        .and_then(WMIConnection::new)
+        .and_then(|wmi| wmi.query::<Win32VideoController>());
+    let controllers = match controllers {
+        Ok(controllers) => controllers,
+        Err(e) => {
+            tracing::warn!("Could not query video controllers through WMI: {}", e);
+            return Vec::new();
+        }
+    };
+
+    controllers
+        .into_iter()
+        .filter_map(|controller| {
+            let name = controller.name?;
+            if !(name.contains("AMD") || name.contains("Radeon")) {
+                return None;
+            }
+            let vram_mb = controller.adapter_ram? / 1024 / 1024;
+            Some(GpuInfo {
+                name,
+                memory_total: vram_mb,
+                memory_free: vram_mb,
+                compute_capability: None,
+                driver_version: controller
+                    .driver_version
+                    .unwrap_or_else(|| "Unknown".to_string()),
+            })
+        })
+        .collect()
+}
+
+#[cfg(target_os = "macos")]
+fn platform_gpus() -> Vec<GpuInfo> {
+    let output = std::process::Command::new("system_profiler")
+        .args(["SPDisplaysDataType", "-json"])
+        .output();
+    match output {
+        Ok(output) if output.status.success() => serde_json::from_slice(&output.stdout)
+            .map(|report| system_profiler_gpus(&report))
+            .unwrap_or_default(),
+        Ok(output) => {
+            tracing::warn!("system_profiler exited with {}", output.status);
+            Vec::new()
+        }
+        Err(e) => {
+            tracing::warn!("Could not run system_profiler: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
+fn platform_gpus() -> Vec<GpuInfo> {
+    Vec::new()
+}
+
+/// GPUs with dedicated VRAM in `system_profiler SPDisplaysDataType -json`
+/// output, i.e. discrete AMD cards; integrated and Apple silicon GPUs share
+/// system memory and report none. All VRAM is reported free.
+#[cfg(any(target_os = "macos", test))]
+fn system_profiler_gpus(report: &serde_json::Value) -> Vec<GpuInfo> {
+    let Some(displays) = report["SPDisplaysDataType"].as_array() else {
+        return Vec::new();
+    };
+    displays
+        .iter()
+        .filter_map(|display| {
+            let vram_mb = parse_vram_mb(display["spdisplays_vram"].as_str()?)?;
+            let name = display["sppci_model"]
+                .as_str()
+                .or_else(|| display["_name"].as_str())
+                .unwrap_or("AMD GPU")
+                .to_string();
+            Some(GpuInfo {
+                name,
+                memory_total: vram_mb,
+                memory_free: vram_mb,
+                compute_capability: None,
+                driver_version: "Unknown".to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Megabytes in a size such as "8 GB" or "1536 MB"
+#[cfg(any(target_os = "macos", test))]
+fn parse_vram_mb(size: &str) -> Option<u64> {
+    let (amount, unit) = size.trim().split_once(' ')?;
+    let amount: u64 = amount.parse().ok()?;
+    match unit.trim() {
+        "GB" => Some(amount * 1024),
+        "MB" => Some(amount),
+        _ => None,
+    }
+}
+
+fn nvml_gpus() -> Vec<GpuInfo> {
+    let Ok(nvml) = Nvml::init() else {
+        return Vec::new();
+    };
+    let driver_version = nvml
+        .sys_driver_version()
+        .unwrap_or_else(|_| "Unknown".to_string());
+    let device_count = nvml.device_count().unwrap_or(0);
+
+    (0..device_count)
+        .filter_map(|index| nvml.device_by_index(index).ok())
+        .map(|device| {
+            let memory_info = device.memory_info().ok();
+            GpuInfo {
+                name: device
+                    .name()
+                    .unwrap_or_else(|_| "Unknown NVIDIA GPU".to_string()),
+                memory_total: memory_info
+                    .as_ref()
+                    .map(|m| m.total / 1024 / 1024)
+                    .unwrap_or(0),
+                memory_free: memory_info
+                    .as_ref()
+                    .map(|m| m.free / 1024 / 1024)
+                    .unwrap_or(0),
+                compute_capability: None, // Could be detected with more advanced NVML calls
+                driver_version: driver_version.clone(),
+            }
+        })
+        .collect()
+}
+
+/// GPUs under a `/sys/class/drm`-style directory whose `cardN/device`
+/// reports `mem_info_vram_total` and `mem_info_vram_used` in bytes
+#[cfg(any(target_os = "linux", test))]
+fn drm_gpus(drm_dir: &Path) -> Vec<GpuInfo> {
+    let read = |path: &Path| std::fs::read_to_string(path).ok();
+    let read_bytes = |path: &Path| read(path).and_then(|s| s.trim().parse::<u64>().ok());
+
+    let Ok(entries) = std::fs::read_dir(drm_dir) else {
+        return Vec::new();
+    };
+    let mut cards: Vec<(String, std::path::PathBuf)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            // cardN only; cardN-DP-1 and the like are connectors
+            let is_card = name
+                .strip_prefix("card")
+                .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()));
+            is_card.then(|| (name, entry.path().join("device")))
+        })
+        .collect();
+    cards.sort();
+
+    cards
+        .into_iter()
+        .filter_map(|(card, device)| {
+            let total = read_bytes(&device.join("mem_info_vram_total"))?;
+            let used = read_bytes(&device.join("mem_info_vram_used")).unwrap_or(0);
+            let vendor = read(&device.join("vendor")).unwrap_or_default();
+            let name = read(&device.join("product_name"))
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .unwrap_or_else(|| match vendor.trim() {
+                    "0x1002" => format!("AMD GPU ({})", card),
+                    _ => format!("GPU ({})", card),
+                });
+            Some(GpuInfo {
+                name,
+                memory_total: total / 1024 / 1024,
+                memory_free: total.saturating_sub(used) / 1024 / 1024,
+                compute_capability: None,
+                driver_version: "Unknown".to_string(),
+            })
+        })
+        .collect()
+}
+
 pub struct HardwareDetector {
     system: System,
 }
@@ -101,41 +310,10 @@ impl HardwareDetector {
     }
 
     fn detect_gpu(&self) -> Result<GpuInfo> {
-        // Try NVML for NVIDIA GPUs first
-        if let Ok(nvml) = Nvml::init() {
-            if let Ok(device_count) = nvml.device_count() {
-                if device_count > 0 {
-                    if let Ok(device) = nvml.device_by_index(0) {
-                        let name = device
-                            .name()
-                            .unwrap_or_else(|_| "Unknown NVIDIA GPU".to_string());
-                        let memory_info = device.memory_info().ok();
-                        let memory_total = memory_info
-                            .as_ref()
-                            .map(|m| m.total / 1024 / 1024)
-                            .unwrap_or(0);
-                        let memory_free = memory_info
-                            .as_ref()
-                            .map(|m| m.free / 1024 / 1024)
-                            .unwrap_or(0);
-                        let driver_version = nvml
-                            .sys_driver_version()
-                            .unwrap_or_else(|_| "Unknown".to_string());
-
-                        return Ok(GpuInfo {
-                            name,
-                            memory_total,
-                            memory_free,
-                            compute_capability: None, // Could be detected with more advanced NVML calls
-                            driver_version,
-                        });
-                    }
-                }
-            }
-        }
-
-        // Fallback to system detection for other GPUs
-        Err(anyhow!("No compatible GPU detected"))
+        detect_gpus()
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("No compatible GPU detected"))
     }
 
     fn classify_system_type(
@@ -426,3 +604,37 @@ impl Default for HardwareDetector {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_amd_gpus_from_sysfs_and_system_profiler() {
+        let drm = tempfile::tempdir().unwrap();
+        let device = drm.path().join("card0").join("device");
+        std::fs::create_dir_all(&device).unwrap();
+        std::fs::create_dir_all(drm.path().join("card0-DP-1")).unwrap();
+        std::fs::write(device.join("mem_info_vram_total"), "8589934592\n").unwrap();
+        std::fs::write(device.join("mem_info_vram_used"), "1073741824\n").unwrap();
+        std::fs::write(device.join("vendor"), "0x1002\n").unwrap();
+
+        let gpus = drm_gpus(drm.path());
+        assert_eq!(gpus.len(), 1);
+        assert_eq!(gpus[0].name, "AMD GPU (card0)");
+        assert_eq!((gpus[0].memory_total, gpus[0].memory_free), (8192, 7168));
+
+        let report = serde_json::json!({"SPDisplaysDataType": [
+            {"_name": "Intel UHD Graphics 630", "spdisplays_vram_shared": "1536 MB"},
+            {"_name": "kHW_AMDRadeonPro5500M", "sppci_model": "AMD Radeon Pro 5500M",
+             "spdisplays_vram": "8 GB"},
+            {"_name": "Apple M2", "sppci_model": "Apple M2"}
+        ]});
+        let gpus = system_profiler_gpus(&report);
+        assert_eq!(gpus.len(), 1);
+        assert_eq!(gpus[0].name, "AMD Radeon Pro 5500M");
+        assert_eq!((gpus[0].memory_total, gpus[0].memory_free), (8192, 8192));
+        assert_eq!(parse_vram_mb("1536 MB"), Some(1536));
+        assert_eq!(parse_vram_mb("lots"), None);
+    }
+}
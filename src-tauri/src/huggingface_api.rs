@@ -1,8 +1,22 @@
 #![allow(dead_code)]
+use crate::constants::{HF_SEARCH_CACHE_TTL_SECS, HF_SEARCH_MAX_RESULTS, HTTP_TIMEOUT_SECS};
+use crate::model_updates::is_offline_mode;
 use crate::utils::{estimate_model_size_mb, parse_model_params_from_id};
 use anyhow::{anyhow, Result};
-
+use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const HF_HUB_URL: &str = "https://huggingface.co";
+
+/// Search results by (hub, query, limit), kept to stay clear of Hub rate limits
+type GgufSearchCache = HashMap<(String, String, usize), (Instant, Vec<GgufModelRepo>)>;
+
+lazy_static! {
+    static ref GGUF_SEARCH_CACHE: Mutex<GgufSearchCache> = Mutex::new(HashMap::new());
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct HuggingFaceModel {
@@ -34,6 +48,27 @@ struct HFApiModel {
     pipeline_tag: Option<String>,
     #[allow(dead_code)]
     library_name: Option<String>,
+    /// Repo files, only listed when the search asks for `full=true`
+    siblings: Option<Vec<HFApiSibling>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HFApiSibling {
+    rfilename: String,
+}
+
+/// A GGUF model repo on the Hub with the quantizations it offers
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct GgufModelRepo {
+    /// Serialized as `model_id`, the key the model browser reads
+    #[serde(rename = "model_id", alias = "repo_id")]
+    pub repo_id: String,
+    pub name: String,
+    pub size: String,
+    pub downloads: u64,
+    pub likes: u32,
+    /// `.gguf` files in the repo, one per quantization
+    pub gguf_files: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -116,6 +151,147 @@ async fn search_hf_api(params: &ModelSearchParams) -> Result<Vec<HuggingFaceMode
     Ok(models)
 }
 
+/// Search the Hub for GGUF repos matching `query`. Results are cached for
+/// `HF_SEARCH_CACHE_TTL_SECS`; offline or when the Hub cannot be reached the
+/// curated list is searched instead. `limit` is clamped to
+/// `1..=HF_SEARCH_MAX_RESULTS`.
+pub async fn search_gguf_models(query: &str, limit: usize) -> Vec<GgufModelRepo> {
+    search_gguf_models_at(HF_HUB_URL, query, limit).await
+}
+
+async fn search_gguf_models_at(hub_url: &str, query: &str, limit: usize) -> Vec<GgufModelRepo> {
+    let limit = limit.clamp(1, HF_SEARCH_MAX_RESULTS);
+    let key = (hub_url.to_string(), query.trim().to_lowercase(), limit);
+    let ttl = Duration::from_secs(HF_SEARCH_CACHE_TTL_SECS);
+    if let Ok(cache) = GGUF_SEARCH_CACHE.lock() {
+        if let Some((fetched_at, repos)) = cache.get(&key) {
+            if fetched_at.elapsed() < ttl {
+                return repos.clone();
+            }
+        }
+    }
+
+    if is_offline_mode() {
+        return get_curated_gguf_repos(query, limit);
+    }
+
+    match fetch_gguf_repos(hub_url, query, limit).await {
+        Ok(repos) => {
+            if let Ok(mut cache) = GGUF_SEARCH_CACHE.lock() {
+                cache.retain(|_, (fetched_at, _)| fetched_at.elapsed() < ttl);
+                cache.insert(key, (Instant::now(), repos.clone()));
+            }
+            repos
+        }
+        Err(e) => {
+            tracing::warn!("HuggingFace model search failed, using curated list: {}", e);
+            get_curated_gguf_repos(query, limit)
+        }
+    }
+}
+
+async fn fetch_gguf_repos(hub_url: &str, query: &str, limit: usize) -> Result<Vec<GgufModelRepo>> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(HTTP_TIMEOUT_SECS))
+        .build()?;
+
+    let url = format!(
+        "{}/api/models?search={}&filter=gguf&sort=downloads&direction=-1&limit={}&full=true",
+        hub_url.trim_end_matches('/'),
+        urlencoding::encode(query.trim()),
+        limit
+    );
+
+    let response = client
+        .get(&url)
+        .header("User-Agent", "BEAR-AI-LLM/1.0")
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(anyhow!(
+            "HuggingFace API returned error: {}",
+            response.status()
+        ));
+    }
+
+    let hf_models: Vec<HFApiModel> = response.json().await?;
+
+    Ok(hf_models
+        .into_iter()
+        .filter(|m| !m.private.unwrap_or(false))
+        .map(|m| {
+            let gguf_files = m
+                .siblings
+                .unwrap_or_default()
+                .into_iter()
+                .map(|sibling| sibling.rfilename)
+                .filter(|file| file.to_lowercase().ends_with(".gguf"))
+                .collect();
+
+            GgufModelRepo {
+                name: m.id.split('/').next_back().unwrap_or(&m.id).to_string(),
+                size: estimate_model_size(&m.id),
+                downloads: m.downloads.unwrap_or(0) as u64,
+                likes: m.likes.unwrap_or(0),
+                repo_id: m.id,
+                gguf_files,
+            }
+        })
+        .take(limit)
+        .collect())
+}
+
+/// Well-known GGUF repos searched when the Hub is unavailable
+fn get_curated_gguf_repos(query: &str, limit: usize) -> Vec<GgufModelRepo> {
+    let repos = [
+        (
+            "TheBloke/Llama-2-7B-Chat-GGUF",
+            "Llama 2 7B Chat",
+            "7B",
+            "llama-2-7b-chat",
+        ),
+        (
+            "TheBloke/Mistral-7B-Instruct-v0.2-GGUF",
+            "Mistral 7B Instruct",
+            "7B",
+            "mistral-7b-instruct-v0.2",
+        ),
+        (
+            "TheBloke/TinyLlama-1.1B-Chat-v1.0-GGUF",
+            "TinyLlama 1.1B",
+            "1.1B",
+            "tinyllama-1.1b-chat-v1.0",
+        ),
+        (
+            "TheBloke/CodeLlama-7B-Instruct-GGUF",
+            "CodeLlama 7B",
+            "7B",
+            "codellama-7b-instruct",
+        ),
+    ];
+
+    let query_lower = query.trim().to_lowercase();
+    repos
+        .iter()
+        .filter(|(id, name, _, _)| {
+            id.to_lowercase().contains(&query_lower) || name.to_lowercase().contains(&query_lower)
+        })
+        .take(limit)
+        .map(|(id, name, size, stem)| GgufModelRepo {
+            repo_id: id.to_string(),
+            name: name.to_string(),
+            size: size.to_string(),
+            downloads: 0,
+            likes: 0,
+            gguf_files: ["Q4_K_M", "Q5_K_M", "Q8_0"]
+                .iter()
+                .map(|quant| format!("{}.{}.gguf", stem, quant))
+                .collect(),
+        })
+        .collect()
+}
+
 /// Estimate model size based on model ID
 ///
 /// Uses improved heuristics to determine model size based on:
@@ -318,4 +494,87 @@ where
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Local stand-in for the Hub answering every request with `body`,
+    /// counting the requests it receives
+    async fn fake_hub(body: &'static str) -> (String, Arc<AtomicUsize>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                counter.fetch_add(1, Ordering::SeqCst);
+                let mut request = [0u8; 2048];
+                let _ = socket.read(&mut request).await;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        (url, requests)
+    }
+
+    #[tokio::test]
+    async fn test_gguf_search_lists_quantizations_and_caches() {
+        let (hub_url, requests) = fake_hub(
+            r#"[{"id": "bartowski/Phi-3-mini-GGUF", "downloads": 1200, "likes": 35,
+                 "siblings": [{"rfilename": "README.md"},
+                              {"rfilename": "Phi-3-mini-Q4_K_M.gguf"},
+                              {"rfilename": "Phi-3-mini-Q8_0.gguf"}]}]"#,
+        )
+        .await;
+
+        let repos = search_gguf_models_at(&hub_url, "phi-3", 5).await;
+        assert_eq!(repos.len(), 1);
+        assert_eq!(repos[0].repo_id, "bartowski/Phi-3-mini-GGUF");
+        assert_eq!(repos[0].downloads, 1200);
+        assert_eq!(repos[0].likes, 35);
+        assert_eq!(
+            repos[0].gguf_files,
+            vec!["Phi-3-mini-Q4_K_M.gguf", "Phi-3-mini-Q8_0.gguf"]
+        );
+
+        // A repeated search is served from the cache
+        assert_eq!(search_gguf_models_at(&hub_url, "Phi-3 ", 5).await, repos);
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+        // An unreachable Hub falls back to the curated list
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let unreachable = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+        let fallback = search_gguf_models_at(&unreachable, "tinyllama", 5).await;
+        assert_eq!(
+            fallback[0].repo_id,
+            "TheBloke/TinyLlama-1.1B-Chat-v1.0-GGUF"
+        );
+        assert!(fallback[0]
+            .gguf_files
+            .contains(&"tinyllama-1.1b-chat-v1.0.Q4_K_M.gguf".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_gguf_search_keeps_model_id_key_and_clamps_limit() {
+        let (hub_url, _) = fake_hub(r#"[{"id": "bartowski/Qwen2-GGUF", "siblings": []}]"#).await;
+
+        let repos = search_gguf_models_at(&hub_url, "qwen2", usize::MAX).await;
+        let json = serde_json::to_value(&repos[0]).unwrap();
+        assert_eq!(json["model_id"], "bartowski/Qwen2-GGUF");
+        assert!(json.get("repo_id").is_none());
+
+        // The cache key holds the clamped limit, not the requested one
+        let cache = GGUF_SEARCH_CACHE.lock().unwrap();
+        assert!(cache.contains_key(&(hub_url.clone(), "qwen2".to_string(), HF_SEARCH_MAX_RESULTS)));
+    }
+}
@@ -3,21 +3,50 @@
 
 pub mod ai_transparency;
 pub mod candle_inference; // Pure Rust inference (Candle-based GGUF)
+pub mod chat_template;
+pub mod chat_titles;
+pub mod clause_outline;
 pub mod commands;
+pub mod completion;
 pub mod compliance;
+pub mod config_snapshot;
 pub mod constants;
+pub mod contract_lifecycle;
 pub mod database;
+pub mod document_diff;
+pub mod document_redaction;
+pub mod embedding_projection;
+pub mod ensemble;
 pub mod export_engine;
+pub mod factory_reset;
+pub mod file_processor;
+pub mod file_generation;
+pub mod generation_fallback;
+pub mod generation_records;
+pub mod gguf_compat;
+pub mod governing_law;
+pub mod grammar;
+pub mod hardware_detector;
 pub mod hardware_monitor;
 pub mod llm_manager;
+pub mod mcp_server;
 pub mod middleware;
+pub mod model_updates;
+pub mod obligations;
 pub mod pii_detector;
 pub mod process_helper;
+pub mod progressive_download;
+pub mod query_expansion;
 pub mod rag_engine;
+pub mod rate_limiter;
+pub mod read_only_sql;
 pub mod risk_assessment;
 pub mod scheduler;
 pub mod security;
+pub mod shutdown;
+pub mod structured_extraction;
 pub mod system;
+pub mod text_segmentation;
 pub mod utils;
 
 // Re-export commonly used types
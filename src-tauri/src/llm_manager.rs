@@ -1,14 +1,25 @@
-use crate::candle_inference::{GGUFInferenceConfig, GGUFInferenceEngine}; // Now using Candle (Pure Rust)
+use crate::candle_inference::{
+    CancellationToken, GGUFInferenceConfig, GGUFInferenceEngine, StopReason,
+}; // Now using Candle (Pure Rust)
+use crate::chat_template::{ChatRole, ChatTemplate, ChatTurn};
 use crate::constants::*;
+use crate::hardware_detector::{self, GpuInfo};
+use crate::model_updates::{
+    backup_path, is_offline_mode, partial_path, read_local_revision, sidecar_path, verify_file,
+    write_local_revision, DownloadProgress, HuggingFaceHub, ModelHub, ModelRevision,
+    ModelUpdateConfig, ModelUpdateStatus,
+};
+use crate::text_segmentation::truncate_to_tokens;
 use anyhow::{anyhow, Result};
 use candle_core::Device;
 use hf_hub::api::tokio::Api;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokenizers::Tokenizer;
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, OnceCell, RwLock, Semaphore};
 
 // Production LLM Manager with real model downloading and inference
 // This is the single source of truth for LLM management in BEAR AI
@@ -28,6 +39,15 @@ pub struct ModelConfig {
     pub requires_gpu: bool,
     pub recommended_gpu_layers: Option<u32>, // Recommended GPU layers for this model
     pub recommended_vram_mb: Option<u64>,    // Recommended VRAM for full offload
+    #[serde(default)]
+    pub chat_template: ChatTemplate, // Instruction format; Raw passes prompts through
+}
+
+impl ModelConfig {
+    /// Prompt for the next assistant reply, wrapped in this model's chat template
+    pub fn format_prompt(&self, system: Option<&str>, messages: &[ChatTurn]) -> String {
+        self.chat_template.format_prompt(system, messages)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -39,6 +59,20 @@ pub struct GenerationConfig {
     pub repetition_penalty: f32,
     pub seed: Option<u64>,
     pub stop_sequences: Vec<String>,
+    /// GBNF grammar the output must follow, e.g. from `json_schema_to_grammar`
+    #[serde(default)]
+    pub grammar: Option<String>,
+    /// JSON schema the output must match; compiled into `grammar` when
+    /// generation starts, so it cannot be set together with one
+    #[serde(default)]
+    pub json_schema: Option<serde_json::Value>,
+    /// Token id to bias added to its logit; ids are specific to the loaded
+    /// model's tokenizer, see `resolve_token_ids`
+    #[serde(default)]
+    pub logit_bias: HashMap<u32, f32>,
+    /// Token ids that are never sampled
+    #[serde(default)]
+    pub banned_tokens: Vec<u32>,
 }
 
 impl Default for GenerationConfig {
@@ -51,10 +85,36 @@ impl Default for GenerationConfig {
             repetition_penalty: 1.1,
             seed: None,
             stop_sequences: vec!["</s>".to_string(), "[/INST]".to_string()],
+            grammar: None,
+            json_schema: None,
+            logit_bias: HashMap::new(),
+            banned_tokens: Vec::new(),
+        }
+    }
+}
+
+impl GenerationConfig {
+    /// Replace `json_schema` by the grammar compiled from it, so the grammar
+    /// a reply was constrained by is what gets recorded
+    fn compile_json_schema(&mut self) -> Result<()> {
+        if let Some(schema) = self.json_schema.take() {
+            if self.grammar.is_some() {
+                return Err(anyhow!("Set either a grammar or a JSON schema, not both"));
+            }
+            self.grammar = Some(crate::grammar::json_schema_to_grammar(&schema)?);
         }
+        Ok(())
     }
 }
 
+/// Generation settings the user set explicitly; these survive model loads,
+/// while unset ones follow the loaded model's registry defaults
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GenerationOverrides {
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<usize>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ModelStatus {
     NotDownloaded,
@@ -71,8 +131,455 @@ pub struct InferenceResult {
     pub tokens_generated: usize,
     pub time_ms: u128,
     pub tokens_per_second: f32,
+    /// Generation was cancelled; `text` holds what was generated until then
+    #[serde(default)]
+    pub cancelled: bool,
+    /// Settings the text was generated with, including the seed drawn when
+    /// none was set
+    #[serde(default)]
+    pub config: GenerationConfig,
+}
+
+/// User-registered models, kept in the models directory
+const CUSTOM_MODELS_FILE: &str = "custom_models.json";
+
+/// Saved download settings, kept in the models directory
+const DOWNLOAD_SETTINGS_FILE: &str = "download_settings.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct DownloadSettings {
+    headroom_mb: u64,
+}
+
+/// End-of-turn markers recognised in tokenizer configs and chat templates
+const KNOWN_STOP_TOKENS: &[&str] = &[
+    "<|im_end|>",    // ChatML (Qwen, OpenHermes, ...)
+    "<|end|>",       // Phi-3
+    "<|eot_id|>",    // Llama 3
+    "<|endoftext|>", // Phi-2 / GPT-style
+    "<end_of_turn>", // Gemma
+    "</s>",          // Llama 2 / Mistral
+    "[/INST]",       // Llama 2 / Mistral instruction template
+];
+
+/// Stop sequences in effect for the loaded model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StopSequences {
+    /// Detected from the tokenizer and `tokenizer_config.json`
+    pub detected: Vec<String>,
+    pub overrides: Vec<String>,
+    pub effective: Vec<String>,
+}
+
+/// Detect stop sequences from a model's tokenizer config, chat template and
+/// special tokens
+pub fn detect_stop_sequences(model_dir: &Path, tokenizer: Option<&Tokenizer>) -> Vec<String> {
+    fn token_content(value: &serde_json::Value) -> Option<&str> {
+        value.as_str().or_else(|| value["content"].as_str())
+    }
+
+    let mut found: Vec<String> = Vec::new();
+    let mut push = |token: &str| {
+        if !token.is_empty() && !found.iter().any(|t| t == token) {
+            found.push(token.to_string());
+        }
+    };
+
+    let config = std::fs::read_to_string(model_dir.join("tokenizer_config.json"))
+        .ok()
+        .and_then(|s| serde_json::from_str::<serde_json::Value>(&s).ok());
+
+    if let Some(config) = config {
+        if let Some(eos) = token_content(&config["eos_token"]) {
+            push(eos);
+        }
+
+        // chat_template is either a string or a list of named templates
+        let templates: Vec<&str> = match &config["chat_template"] {
+            serde_json::Value::String(t) => vec![t.as_str()],
+            serde_json::Value::Array(list) => {
+                list.iter().filter_map(|t| t["template"].as_str()).collect()
+            }
+            _ => Vec::new(),
+        };
+        for token in KNOWN_STOP_TOKENS {
+            if templates.iter().any(|t| t.contains(token)) {
+                push(token);
+            }
+        }
+
+        if let Some(added) = config["added_tokens_decoder"].as_object() {
+            for token in added.values() {
+                let special = token["special"].as_bool().unwrap_or(false);
+                if let Some(content) = token_content(token) {
+                    if special && KNOWN_STOP_TOKENS.contains(&content) {
+                        push(content);
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(tokenizer) = tokenizer {
+        for token in tokenizer.get_added_tokens_decoder().values() {
+            if token.special && KNOWN_STOP_TOKENS.contains(&token.content.as_str()) {
+                push(&token.content);
+            }
+        }
+    }
+
+    found
+}
+
+/// Probe returning the free space (in MB) on the volume holding a path
+pub type DiskSpaceProbe = Arc<dyn Fn(&Path) -> Option<u64> + Send + Sync>;
+
+/// Check of a model's license before it is downloaded or loaded: an error
+/// refuses the model, a message is a warning it loads with
+pub type LicenseCheck = Arc<dyn Fn(&str) -> Result<Option<String>, String> + Send + Sync>;
+
+/// Limits on concurrent downloads and loads of different models
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelLoadLimits {
+    pub max_concurrent_downloads: usize,
+    pub max_concurrent_loads: usize,
+}
+
+impl Default for ModelLoadLimits {
+    fn default() -> Self {
+        Self {
+            max_concurrent_downloads: 2,
+            max_concurrent_loads: 1,
+        }
+    }
+}
+
+/// Policy for unloading the model after a period without generation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdleUnloadConfig {
+    pub enabled: bool,
+    pub idle_timeout_seconds: u64,
+}
+
+impl Default for IdleUnloadConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            idle_timeout_seconds: DEFAULT_MODEL_IDLE_TIMEOUT_SECS,
+        }
+    }
+}
+
+/// Tauri event carrying `DownloadProgressEvent`s
+pub const DOWNLOAD_PROGRESS_EVENT: &str = "model-download-progress";
+
+/// Progress of a model file download, broadcast while it runs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DownloadProgressEvent {
+    pub model_name: String,
+    pub downloaded_bytes: u64,
+    pub total_bytes: Option<u64>,
+    /// Fraction downloaded (0.0-1.0); None when the total size is unknown
+    pub progress: Option<f32>,
+    /// The total size is unknown, so only `downloaded_bytes` is meaningful
+    pub indeterminate: bool,
+}
+
+/// Counters for `ensure_model_ready` deduplication
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ModelLoadStats {
+    /// Download/load runs actually performed
+    pub preparations: u64,
+    /// Calls that awaited another caller's in-flight run for the same model
+    pub coalesced: u64,
+}
+
+/// Shared outcome of one in-flight `ensure_model_ready` run
+type InFlightPreparation = Arc<OnceCell<std::result::Result<(), String>>>;
+
+/// Result of comparing a model's download size against free disk space
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiskSpaceCheck {
+    pub model_name: String,
+    pub required_mb: u64,
+    pub available_mb: Option<u64>,
+    pub headroom_mb: u64,
+    pub shortfall_mb: u64,
+    pub sufficient: bool,
+}
+
+/// Hardware resources relevant to running a model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SystemResources {
+    pub available_ram_mb: u64,
+    /// Free VRAM on the first GPU; `None` when no GPU is usable
+    pub free_vram_mb: Option<u64>,
+}
+
+/// Whether a registry model can be downloaded and run, assessed before download
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelFeasibility {
+    pub model_name: String,
+    pub already_downloaded: bool,
+    pub can_download: bool,
+    pub disk: DiskSpaceCheck,
+    pub can_load: bool,
+    pub required_ram_mb: u64,
+    pub available_ram_mb: u64,
+    pub recommended_gpu_layers: u32,
+    pub estimated_tokens_per_second: f32,
+    pub feasible: bool,
+    /// Human-readable explanation of each blocking or limiting factor
+    pub notes: Vec<String>,
+}
+
+/// GPU layers to offload for a model given the free VRAM (scaled down for partial offload)
+pub fn gpu_layers_for_vram(model_config: &ModelConfig, available_vram_mb: u64) -> u32 {
+    let recommended_layers = model_config.recommended_gpu_layers.unwrap_or(0);
+    let recommended_vram = model_config
+        .recommended_vram_mb
+        .unwrap_or(model_config.size_mb);
+
+    // Keep some VRAM free for context and computation (based on VRAM_USAGE_RATIO)
+    let usable_vram = (available_vram_mb as f32 * VRAM_USAGE_RATIO) as u64;
+
+    if usable_vram < recommended_vram {
+        let ratio = usable_vram as f32 / recommended_vram as f32;
+        (recommended_layers as f32 * ratio) as u32
+    } else {
+        recommended_layers
+    }
+}
+
+/// VRAM assumed when a GPU backend is available but no GPU reports its memory
+const FALLBACK_VRAM_MB: u64 = 4096;
+
+/// GPU offload chosen when a model was loaded, and the VRAM it was based on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GpuOffload {
+    /// Whether the inference device is a GPU; layers stay on the CPU otherwise
+    pub gpu_backend: bool,
+    pub gpu_count: usize,
+    /// GPU with the most free VRAM, the one offload is planned for
+    pub gpu_name: Option<String>,
+    /// Free VRAM on that GPU, None when no GPU reported its memory
+    pub available_vram_mb: Option<u64>,
+    pub gpu_layers: u32,
+    pub recommended_layers: u32,
+}
+
+impl GpuOffload {
+    /// One-line explanation of the offload for the user
+    pub fn summary(&self) -> String {
+        let vram = match (&self.gpu_name, self.available_vram_mb) {
+            (Some(name), Some(vram)) => format!("{}MB free VRAM on {}", vram, name),
+            _ => "no GPU memory detected".to_string(),
+        };
+        if !self.gpu_backend {
+            format!("Running on CPU ({}; no GPU inference backend)", vram)
+        } else if self.gpu_layers < self.recommended_layers {
+            format!(
+                "Partial GPU offload: {} of {} layers ({})",
+                self.gpu_layers, self.recommended_layers, vram
+            )
+        } else {
+            format!("Full GPU offload: {} layers ({})", self.gpu_layers, vram)
+        }
+    }
+}
+
+/// Offload for a model on the GPU with the most free VRAM; a model runs on a
+/// single device, so VRAM is not summed across GPUs
+pub fn plan_gpu_offload(
+    model_config: &ModelConfig,
+    gpus: &[GpuInfo],
+    gpu_backend: bool,
+) -> GpuOffload {
+    let chosen = gpus.iter().max_by_key(|gpu| gpu.memory_free);
+    let available_vram_mb = chosen.map(|gpu| gpu.memory_free);
+    let gpu_layers = if gpu_backend {
+        gpu_layers_for_vram(model_config, available_vram_mb.unwrap_or(FALLBACK_VRAM_MB))
+    } else {
+        0
+    };
+    GpuOffload {
+        gpu_backend,
+        gpu_count: gpus.len(),
+        gpu_name: chosen.map(|gpu| gpu.name.clone()),
+        available_vram_mb,
+        gpu_layers,
+        recommended_layers: model_config.recommended_gpu_layers.unwrap_or(0),
+    }
+}
+
+/// Rough generation speed from model size and how much of it runs on the GPU
+fn estimate_tokens_per_second(model_config: &ModelConfig, gpu_fraction: f32) -> f32 {
+    // Q4 quantised weights take roughly 0.6MB per million parameters
+    let params_billions = model_config.size_mb as f32 / 600.0;
+    let (cpu_speed, gpu_speed) = match params_billions {
+        p if p <= 3.0 => (5.0, 50.0),
+        p if p <= 7.5 => (2.0, 30.0),
+        p if p <= 13.5 => (0.5, 15.0),
+        _ => (0.1, 3.0),
+    };
+    cpu_speed + (gpu_speed - cpu_speed) * gpu_fraction.clamp(0.0, 1.0)
 }
 
+/// Assess whether a model fits the given disk space and hardware without loading it
+pub fn assess_feasibility(
+    model_config: &ModelConfig,
+    resources: &SystemResources,
+    disk: DiskSpaceCheck,
+    already_downloaded: bool,
+) -> ModelFeasibility {
+    let mut notes = Vec::new();
+
+    let can_download = already_downloaded || disk.sufficient;
+    if !can_download {
+        notes.push(format!(
+            "Not enough disk space: {}MB required, {}MB short",
+            disk.required_mb, disk.shortfall_mb
+        ));
+    }
+
+    let recommended_gpu_layers = resources
+        .free_vram_mb
+        .map(|vram| gpu_layers_for_vram(model_config, vram))
+        .unwrap_or(0);
+    let gpu_fraction = match model_config.recommended_gpu_layers {
+        Some(total) if total > 0 => recommended_gpu_layers as f32 / total as f32,
+        _ => 0.0,
+    };
+
+    // Layers offloaded to the GPU don't need to be held in system RAM
+    let cpu_resident_mb = model_config.size_mb as f32 * (1.0 - gpu_fraction);
+    let required_ram_mb = (cpu_resident_mb * MODEL_RAM_OVERHEAD_RATIO) as u64 + MODEL_RAM_BASE_MB;
+    let can_load = resources.available_ram_mb >= required_ram_mb;
+    if !can_load {
+        notes.push(format!(
+            "Not enough memory to load: ~{}MB RAM required, {}MB available",
+            required_ram_mb, resources.available_ram_mb
+        ));
+    }
+    if model_config.requires_gpu && recommended_gpu_layers == 0 {
+        notes.push("No usable GPU: the model will run on CPU and be slow".to_string());
+    }
+
+    ModelFeasibility {
+        model_name: model_config.name.clone(),
+        already_downloaded,
+        can_download,
+        disk,
+        can_load,
+        required_ram_mb,
+        available_ram_mb: resources.available_ram_mb,
+        recommended_gpu_layers,
+        estimated_tokens_per_second: estimate_tokens_per_second(model_config, gpu_fraction),
+        feasible: can_download && can_load,
+        notes,
+    }
+}
+
+/// Returned by `download_model` when the models volume is too small
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InsufficientDiskSpace {
+    pub model_name: String,
+    pub required_mb: u64,
+    pub available_mb: u64,
+    pub shortfall_mb: u64,
+}
+
+impl std::fmt::Display for InsufficientDiskSpace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Insufficient disk space to download '{}': {}MB required, {}MB available ({}MB short). Please free up disk space.",
+            self.model_name, self.required_mb, self.available_mb, self.shortfall_mb
+        )
+    }
+}
+
+impl std::error::Error for InsufficientDiskSpace {}
+
+/// Check a Hub repository id has the form `owner/name`, each part made of
+/// `[A-Za-z0-9._-]` and neither `.` nor `..`
+pub fn validate_repo_id(repo_id: &str) -> Result<()> {
+    let valid_part = |part: &str| {
+        !part.is_empty()
+            && part != "."
+            && part != ".."
+            && part
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+    };
+    match repo_id.split_once('/') {
+        Some((owner, name)) if valid_part(owner) && valid_part(name) => Ok(()),
+        _ => Err(anyhow!(
+            "Invalid repo id '{}': expected owner/name using letters, digits, '.', '_' or '-'",
+            repo_id
+        )),
+    }
+}
+
+/// Check a user-supplied registry entry before it is added
+fn validate_model_config(model_config: &ModelConfig) -> Result<()> {
+    if model_config.name.trim().is_empty() {
+        return Err(anyhow!("Model name must not be empty"));
+    }
+    validate_repo_id(&model_config.repo_id)?;
+    // The file name is joined onto the model directory
+    let file = &model_config.model_file;
+    if file.contains(['/', '\\']) || file.contains("..") {
+        return Err(anyhow!("Model file '{}' must be a plain file name", file));
+    }
+    if !file.to_lowercase().ends_with(".gguf") {
+        return Err(anyhow!("Model file '{}' is not a .gguf file", file));
+    }
+    if model_config.size_mb == 0 || model_config.context_length == 0 {
+        return Err(anyhow!(
+            "Model '{}' needs a nonzero size_mb and context_length",
+            model_config.name
+        ));
+    }
+    Ok(())
+}
+
+/// Where `file` of `repo_id` is saved: the repo's directory under
+/// `models_dir`, refused unless both stay inside it
+fn hub_file_destination(models_dir: &Path, repo_id: &str, file: &str) -> Result<PathBuf> {
+    let plain = |path: &Path| {
+        path.components().count() > 0
+            && path
+                .components()
+                .all(|c| matches!(c, std::path::Component::Normal(_)))
+    };
+    validate_repo_id(repo_id)?;
+    let repo_dir = repo_id.replace('/', "_");
+    if !plain(Path::new(&repo_dir)) || !plain(Path::new(file)) {
+        return Err(anyhow!(
+            "Refusing to download '{}' from '{}': it would be saved outside the models directory",
+            file,
+            repo_id
+        ));
+    }
+    Ok(models_dir.join(repo_dir).join(file))
+}
+
+/// Free space (in MB) on the disk whose mount point is the longest prefix of `path`
+fn available_disk_space_mb(path: &Path) -> Option<u64> {
+    use sysinfo::Disks;
+
+    let disks = Disks::new_with_refreshed_list();
+    disks
+        .iter()
+        .filter(|d| path.starts_with(d.mount_point()))
+        .max_by_key(|d| d.mount_point().as_os_str().len())
+        .map(|d| d.available_space() / (1024 * 1024))
+}
+
+/// Clones are handles to the same models, settings and in-flight runs, so a
+/// long download can proceed without holding the app's lock on the manager
+#[derive(Clone)]
 pub struct LLMManager {
     models_registry: Arc<RwLock<HashMap<String, ModelConfig>>>,
     model_status: Arc<RwLock<HashMap<String, ModelStatus>>>,
@@ -81,7 +588,30 @@ pub struct LLMManager {
     tokenizer: Arc<RwLock<Option<Tokenizer>>>,
     models_dir: PathBuf,
     generation_config: Arc<RwLock<GenerationConfig>>,
+    generation_overrides: Arc<RwLock<GenerationOverrides>>,
+    /// Model whose tokenizer the ids in `logit_bias` and `banned_tokens` were
+    /// resolved with
+    token_ids_model: Arc<RwLock<Option<String>>>,
     device: Device,
+    download_headroom_mb: Arc<RwLock<u64>>,
+    disk_space_probe: DiskSpaceProbe,
+    detected_stop_sequences: Arc<RwLock<Vec<String>>>,
+    stop_sequence_overrides: Arc<RwLock<Vec<String>>>,
+    model_hub: Arc<dyn ModelHub>,
+    update_config: Arc<RwLock<ModelUpdateConfig>>,
+    in_flight: Arc<std::sync::Mutex<HashMap<String, InFlightPreparation>>>,
+    load_limits: Arc<RwLock<ModelLoadLimits>>,
+    download_slots: Arc<RwLock<Arc<Semaphore>>>,
+    load_slots: Arc<RwLock<Arc<Semaphore>>>,
+    load_stats: Arc<std::sync::Mutex<ModelLoadStats>>,
+    idle_unload: Arc<RwLock<IdleUnloadConfig>>,
+    last_activity: Arc<std::sync::Mutex<Instant>>,
+    /// Shared by generations in progress; replaced after each cancellation
+    generation_cancel: Arc<std::sync::Mutex<CancellationToken>>,
+    download_events: broadcast::Sender<DownloadProgressEvent>,
+    gpu_offload: Arc<RwLock<Option<GpuOffload>>>,
+    /// None loads every model, e.g. in tests
+    license_check: Option<LicenseCheck>,
 }
 
 impl LLMManager {
@@ -102,6 +632,7 @@ impl LLMManager {
         // Initialize GGUF inference engine
         let gguf_engine = GGUFInferenceEngine::new()
             .map_err(|e| anyhow!("Failed to initialize GGUF engine: {}", e))?;
+        let load_limits = ModelLoadLimits::default();
 
         Ok(Self {
             models_registry: Arc::new(RwLock::new(HashMap::new())),
@@ -111,7 +642,30 @@ impl LLMManager {
             tokenizer: Arc::new(RwLock::new(None)),
             models_dir,
             generation_config: Arc::new(RwLock::new(GenerationConfig::default())),
+            generation_overrides: Arc::new(RwLock::new(GenerationOverrides::default())),
+            token_ids_model: Arc::new(RwLock::new(None)),
             device,
+            download_headroom_mb: Arc::new(RwLock::new(DOWNLOAD_DISK_HEADROOM_MB)),
+            disk_space_probe: Arc::new(available_disk_space_mb),
+            detected_stop_sequences: Arc::new(RwLock::new(Vec::new())),
+            stop_sequence_overrides: Arc::new(RwLock::new(Vec::new())),
+            model_hub: Arc::new(HuggingFaceHub::new()),
+            update_config: Arc::new(RwLock::new(ModelUpdateConfig::default())),
+            in_flight: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            load_limits: Arc::new(RwLock::new(load_limits.clone())),
+            download_slots: Arc::new(RwLock::new(Arc::new(Semaphore::new(
+                load_limits.max_concurrent_downloads,
+            )))),
+            load_slots: Arc::new(RwLock::new(Arc::new(Semaphore::new(
+                load_limits.max_concurrent_loads,
+            )))),
+            load_stats: Arc::new(std::sync::Mutex::new(ModelLoadStats::default())),
+            idle_unload: Arc::new(RwLock::new(IdleUnloadConfig::default())),
+            last_activity: Arc::new(std::sync::Mutex::new(Instant::now())),
+            generation_cancel: Arc::new(std::sync::Mutex::new(CancellationToken::new())),
+            download_events: broadcast::channel(64).0,
+            gpu_offload: Arc::new(RwLock::new(None)),
+            license_check: None,
         })
     }
 
@@ -119,11 +673,26 @@ impl LLMManager {
     ///
     /// This ensures consistency between download, load, and scan operations.
     /// Uses sanitized repo_id as the directory name for uniqueness and clarity.
-    fn get_model_dir(&self, model_config: &ModelConfig) -> PathBuf {
+    /// Directory of a model's files under `models_dir`, refused when the repo id
+    /// is malformed or the directory resolves outside `models_dir` (e.g. through
+    /// a symlink)
+    fn get_model_dir(&self, model_config: &ModelConfig) -> Result<PathBuf> {
+        validate_repo_id(&model_config.repo_id)?;
         // Sanitize repo_id by replacing "/" with "_"
         // Example: "TheBloke/TinyLlama-1.1B-Chat-v1.0-GGUF" -> "TheBloke_TinyLlama-1.1B-Chat-v1.0-GGUF"
-        let sanitized_repo_id = model_config.repo_id.replace("/", "_");
-        self.models_dir.join(sanitized_repo_id)
+        let sanitized_repo_id = model_config.repo_id.replace('/', "_");
+        let model_dir = self.models_dir.join(sanitized_repo_id);
+        if model_dir.exists()
+            && !model_dir
+                .canonicalize()?
+                .starts_with(self.models_dir.canonicalize()?)
+        {
+            return Err(anyhow!(
+                "Model directory for '{}' resolves outside the models directory",
+                model_config.repo_id
+            ));
+        }
+        Ok(model_dir)
     }
 
     pub async fn initialize(&self) -> Result<()> {
@@ -132,6 +701,8 @@ impl LLMManager {
 
         // Load model registry
         self.load_model_registry().await;
+        self.load_custom_models().await;
+        self.load_download_settings().await;
 
         // Scan for already downloaded models
         self.scan_local_models().await?;
@@ -158,6 +729,7 @@ impl LLMManager {
                 requires_gpu: false,
                 recommended_gpu_layers: Some(TINYLLAMA_GPU_LAYERS),
                 recommended_vram_mb: Some(TINYLLAMA_VRAM_MB),
+                chat_template: ChatTemplate::Zephyr,
             },
             ModelConfig {
                 name: "phi-2".to_string(),
@@ -173,6 +745,7 @@ impl LLMManager {
                 requires_gpu: false,
                 recommended_gpu_layers: Some(PHI2_GPU_LAYERS),
                 recommended_vram_mb: Some(PHI2_VRAM_MB),
+                chat_template: ChatTemplate::Phi,
             },
             ModelConfig {
                 name: "mistral-7b-instruct".to_string(),
@@ -188,6 +761,7 @@ impl LLMManager {
                 requires_gpu: true,
                 recommended_gpu_layers: Some(MISTRAL_7B_GPU_LAYERS),
                 recommended_vram_mb: Some(MISTRAL_7B_VRAM_MB),
+                chat_template: ChatTemplate::Mistral,
             },
             ModelConfig {
                 name: "llama2-7b-chat".to_string(),
@@ -203,6 +777,7 @@ impl LLMManager {
                 requires_gpu: true,
                 recommended_gpu_layers: Some(LLAMA2_7B_GPU_LAYERS),
                 recommended_vram_mb: Some(LLAMA2_7B_VRAM_MB),
+                chat_template: ChatTemplate::Llama2,
             },
         ];
 
@@ -215,12 +790,97 @@ impl LLMManager {
         }
     }
 
+    fn custom_models_path(&self) -> PathBuf {
+        self.models_dir.join(CUSTOM_MODELS_FILE)
+    }
+
+    fn read_custom_models(path: &Path) -> Result<Vec<ModelConfig>> {
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Add user-registered models to the registry; invalid entries are skipped
+    async fn load_custom_models(&self) {
+        let custom = match Self::read_custom_models(&self.custom_models_path()) {
+            Ok(custom) => custom,
+            Err(e) => {
+                tracing::warn!(error = %e, "Could not read custom model registry");
+                return;
+            }
+        };
+
+        let mut registry = self.models_registry.write().await;
+        let mut status = self.model_status.write().await;
+        for model in custom {
+            if let Err(e) = validate_model_config(&model) {
+                tracing::warn!(model = %model.name, error = %e, "Skipping invalid custom model");
+                continue;
+            }
+            status
+                .entry(model.name.clone())
+                .or_insert(ModelStatus::NotDownloaded);
+            registry.insert(model.name.clone(), model);
+        }
+    }
+
+    /// Add a GGUF model from any Hub repository to the registry, persisted so
+    /// it is available after a restart. Registering an existing custom model
+    /// again replaces it; built-in models cannot be replaced.
+    pub async fn register_model(&self, model_config: ModelConfig) -> Result<()> {
+        validate_model_config(&model_config)?;
+        if self.active_model.read().await.as_deref() == Some(model_config.name.as_str()) {
+            return Err(anyhow!(
+                "Unload model '{}' before registering it again",
+                model_config.name
+            ));
+        }
+
+        let path = self.custom_models_path();
+        let mut custom = Self::read_custom_models(&path)?;
+        let is_custom = custom.iter().any(|m| m.name == model_config.name);
+        if !is_custom
+            && self
+                .models_registry
+                .read()
+                .await
+                .contains_key(&model_config.name)
+        {
+            return Err(anyhow!(
+                "Model '{}' is a built-in model and cannot be replaced",
+                model_config.name
+            ));
+        }
+
+        custom.retain(|m| m.name != model_config.name);
+        custom.push(model_config.clone());
+        custom.sort_by(|a, b| a.name.cmp(&b.name));
+        tokio::fs::create_dir_all(&self.models_dir).await?;
+        tokio::fs::write(&path, serde_json::to_string_pretty(&custom)?).await?;
+
+        tracing::info!(model = %model_config.name, repo_id = %model_config.repo_id, "Registered custom model");
+        self.model_status
+            .write()
+            .await
+            .insert(model_config.name.clone(), ModelStatus::NotDownloaded);
+        self.models_registry
+            .write()
+            .await
+            .insert(model_config.name.clone(), model_config);
+        self.scan_local_models().await
+    }
+
     async fn scan_local_models(&self) -> Result<()> {
         let mut status = self.model_status.write().await;
 
         for (name, config) in self.models_registry.read().await.iter() {
             // Use standardized model directory
-            let model_dir = self.get_model_dir(config);
+            let Ok(model_dir) = self.get_model_dir(config) else {
+                tracing::warn!(model = %name, repo_id = %config.repo_id, "Skipping model with an invalid repo id");
+                continue;
+            };
 
             if model_dir.exists() {
                 // Check if model file exists
@@ -240,7 +900,21 @@ impl LLMManager {
                     Err(_) => false,
                 };
 
-                if has_model {
+                // A file shorter than its recorded size is an interrupted download
+                let model_path = model_dir.join(&config.model_file);
+                let truncated = match read_local_revision(&model_path) {
+                    Some(revision) if model_path.exists() => {
+                        !verify_file(&model_path, &revision, false).unwrap_or(false)
+                    }
+                    _ => false,
+                };
+
+                if truncated {
+                    tracing::warn!(
+                        model = %name,
+                        "Local model file does not match its recorded size; it will be downloaded again"
+                    );
+                } else if has_model {
                     status.insert(name.clone(), ModelStatus::Downloaded);
                     tracing::info!(model = %name, "Found local model");
                 }
@@ -250,6 +924,140 @@ impl LLMManager {
         Ok(())
     }
 
+    /// Check whether a registry model can be downloaded and loaded on this hardware,
+    /// without downloading or loading anything
+    pub async fn assess_model_feasibility(
+        &self,
+        model_name: &str,
+        resources: &SystemResources,
+    ) -> Result<ModelFeasibility> {
+        let disk = self.check_download_space(model_name).await?;
+        let model_config = {
+            let registry = self.models_registry.read().await;
+            registry
+                .get(model_name)
+                .ok_or_else(|| anyhow!("Model '{}' not found in registry", model_name))?
+                .clone()
+        };
+        let already_downloaded = matches!(
+            self.model_status.read().await.get(model_name),
+            Some(ModelStatus::Downloaded | ModelStatus::Loading | ModelStatus::Loaded)
+        );
+
+        Ok(assess_feasibility(
+            &model_config,
+            resources,
+            disk,
+            already_downloaded,
+        ))
+    }
+
+    /// Check every model's license with `check` before downloading or loading it
+    pub fn set_license_check(&mut self, check: LicenseCheck) {
+        self.license_check = Some(check);
+    }
+
+    /// Refuse `model_name` when its license check fails; a warning is logged
+    fn check_license(&self, model_name: &str) -> Result<()> {
+        let Some(check) = &self.license_check else {
+            return Ok(());
+        };
+        if let Some(warning) = check(model_name).map_err(|e| anyhow!(e))? {
+            tracing::warn!(model = %model_name, "⚠️ {}", warning);
+        }
+        Ok(())
+    }
+
+    fn download_settings_path(&self) -> PathBuf {
+        self.models_dir.join(DOWNLOAD_SETTINGS_FILE)
+    }
+
+    /// Restore the saved download headroom; the default is kept when none was saved
+    async fn load_download_settings(&self) {
+        let path = self.download_settings_path();
+        if !path.exists() {
+            return;
+        }
+        let settings = std::fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|content| Ok(serde_json::from_str::<DownloadSettings>(&content)?));
+        match settings {
+            Ok(settings) => *self.download_headroom_mb.write().await = settings.headroom_mb,
+            Err(e) => tracing::warn!(error = %e, "Could not read download settings"),
+        }
+    }
+
+    /// Set the extra free space required beyond the model size before
+    /// downloading, saved so it applies after a restart
+    pub async fn set_download_headroom_mb(&self, headroom_mb: u64) -> Result<()> {
+        let settings = DownloadSettings { headroom_mb };
+        tokio::fs::create_dir_all(&self.models_dir).await?;
+        tokio::fs::write(
+            self.download_settings_path(),
+            serde_json::to_string_pretty(&settings)?,
+        )
+        .await?;
+        *self.download_headroom_mb.write().await = headroom_mb;
+        Ok(())
+    }
+
+    /// Compare a registry model's size (plus headroom) against free space on the models volume
+    pub async fn check_download_space(&self, model_name: &str) -> Result<DiskSpaceCheck> {
+        let model_config = {
+            let registry = self.models_registry.read().await;
+            registry
+                .get(model_name)
+                .ok_or_else(|| anyhow!("Model '{}' not found in registry", model_name))?
+                .clone()
+        };
+
+        let headroom_mb = *self.download_headroom_mb.read().await;
+        let required_mb = model_config.size_mb + headroom_mb;
+        let available_mb = (self.disk_space_probe)(&self.models_dir);
+        let shortfall_mb = available_mb
+            .map(|available| required_mb.saturating_sub(available))
+            .unwrap_or(0);
+
+        Ok(DiskSpaceCheck {
+            model_name: model_name.to_string(),
+            required_mb,
+            available_mb,
+            headroom_mb,
+            shortfall_mb,
+            sufficient: shortfall_mb == 0,
+        })
+    }
+
+    /// Fail with `InsufficientDiskSpace` when the models volume is known to
+    /// be too small for another copy of `model_name`
+    async fn ensure_download_space(&self, model_name: &str) -> Result<()> {
+        let space = self.check_download_space(model_name).await?;
+        match space.available_mb {
+            Some(available_mb) if !space.sufficient => {
+                tracing::error!(
+                    model = %model_name,
+                    required_mb = space.required_mb,
+                    available_mb,
+                    "Refusing download: insufficient disk space"
+                );
+                Err(anyhow!(InsufficientDiskSpace {
+                    model_name: model_name.to_string(),
+                    required_mb: space.required_mb,
+                    available_mb,
+                    shortfall_mb: space.shortfall_mb,
+                }))
+            }
+            Some(_) => Ok(()),
+            None => {
+                tracing::warn!(
+                    models_dir = ?self.models_dir,
+                    "Could not determine disk space for models directory, proceeding anyway"
+                );
+                Ok(())
+            }
+        }
+    }
+
     pub async fn download_model(&self, model_name: &str) -> Result<()> {
         let model_config = {
             let registry = self.models_registry.read().await;
@@ -259,6 +1067,32 @@ impl LLMManager {
                 .clone()
         };
 
+        let model_path = self
+            .get_model_dir(&model_config)?
+            .join(&model_config.model_file);
+
+        // Replace a truncated or corrupt file left by an earlier attempt
+        if model_path.exists() {
+            match self.verify_model(model_name).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    tracing::warn!(
+                        model = %model_name,
+                        "Existing model file does not match the expected size or hash, downloading again"
+                    );
+                    tokio::fs::remove_file(&model_path).await?;
+                }
+                Err(e) => {
+                    tracing::warn!(model = %model_name, error = %e, "Could not verify existing model file, keeping it");
+                }
+            }
+        }
+
+        // Refuse before touching the disk if the model file would not fit
+        if !model_path.exists() {
+            self.ensure_download_space(model_name).await?;
+        }
+
         // Update status
         {
             let mut status = self.model_status.write().await;
@@ -271,7 +1105,7 @@ impl LLMManager {
         tracing::info!(model = %model_name, "Starting model download");
 
         // Create model directory using standardized path
-        let model_dir = self.get_model_dir(&model_config);
+        let model_dir = self.get_model_dir(&model_config)?;
         tokio::fs::create_dir_all(&model_dir).await?;
 
         tracing::debug!(
@@ -281,18 +1115,12 @@ impl LLMManager {
             model_dir
         );
 
-        // Download using HuggingFace Hub
-        let api = Api::new()?;
-        let repo = api.model(model_config.repo_id.clone());
-
         // Download model file
-        let model_path = model_dir.join(&model_config.model_file);
         if !model_path.exists() {
             tracing::debug!(file = %model_config.model_file, "Downloading model file");
 
-            match repo.get(&model_config.model_file).await {
-                Ok(downloaded_path) => {
-                    tokio::fs::copy(&downloaded_path, &model_path).await?;
+            match self.fetch_model_file(&model_config, &model_path).await {
+                Ok(()) => {
                     tracing::info!(file = %model_config.model_file, "Model file downloaded successfully");
                 }
                 Err(e) => {
@@ -313,7 +1141,7 @@ impl LLMManager {
             if !tokenizer_path.exists() {
                 tracing::debug!(repo = %tokenizer_repo, "Downloading tokenizer");
 
-                let tokenizer_api = api.model(tokenizer_repo.clone());
+                let tokenizer_api = Api::new()?.model(tokenizer_repo.clone());
                 if let Ok(downloaded_path) = tokenizer_api.get("tokenizer.json").await {
                     tokio::fs::copy(&downloaded_path, &tokenizer_path).await?;
                     tracing::info!("Tokenizer downloaded successfully");
@@ -339,15 +1167,20 @@ impl LLMManager {
     }
 
     pub async fn load_model(&self, model_path: &str) -> Result<()> {
+        self.check_license(model_path)?;
+
         // Check if this is a local file path
         if model_path.ends_with(".gguf") {
             let path = PathBuf::from(model_path);
             if !path.exists() {
                 return Err(anyhow!("Model file not found: {}", model_path));
             }
-            
+
             // Load local GGUF file directly
             self.gguf_engine.load_model(&path, 0).await?;
+            if let Some(model_dir) = path.parent() {
+                self.apply_detected_stop_sequences(model_dir).await;
+            }
             return Ok(());
         }
 
@@ -395,7 +1228,7 @@ impl LLMManager {
         };
 
         // Find GGUF model file using standardized path
-        let model_dir = self.get_model_dir(&model_config);
+        let model_dir = self.get_model_dir(&model_config)?;
         let model_file = model_dir.join(&model_config.model_file);
 
         tracing::debug!(
@@ -415,7 +1248,9 @@ impl LLMManager {
         }
 
         // Calculate optimal GPU layers based on available VRAM
-        let n_gpu_layers = self.calculate_optimal_gpu_layers(&model_config).await;
+        let offload = self.calculate_optimal_gpu_layers(&model_config).await;
+        let n_gpu_layers = offload.gpu_layers;
+        *self.gpu_offload.write().await = Some(offload);
 
         // Load model into GGUF engine
         self.gguf_engine
@@ -441,6 +1276,9 @@ impl LLMManager {
             }
         }
 
+        self.apply_detected_stop_sequences(&model_dir).await;
+        self.apply_model_generation_defaults(&model_config).await?;
+
         // Update active model
         {
             let mut active = self.active_model.write().await;
@@ -458,7 +1296,69 @@ impl LLMManager {
         Ok(())
     }
 
+    /// Download (if needed) and load a model; concurrent calls for the same
+    /// model share one run and all receive its result
     pub async fn ensure_model_ready(&self, model_name: &str) -> Result<()> {
+        self.run_coalesced(model_name, self.prepare_model(model_name))
+            .await
+    }
+
+    /// Download a model without loading it, within the concurrent download
+    /// limit; concurrent calls for the same model share one download
+    pub async fn ensure_model_downloaded(&self, model_name: &str) -> Result<()> {
+        let key = format!("download:{}", model_name);
+        self.run_coalesced(&key, self.download_with_limit(model_name))
+            .await
+    }
+
+    /// Run `run` unless a run under `key` is already in flight, in which case
+    /// wait for that one and return its result
+    async fn run_coalesced(
+        &self,
+        key: &str,
+        run: impl std::future::Future<Output = Result<()>>,
+    ) -> Result<()> {
+        let cell = {
+            let mut in_flight = self.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+            in_flight
+                .entry(key.to_string())
+                .or_insert_with(|| Arc::new(OnceCell::new()))
+                .clone()
+        };
+
+        let mut ran = false;
+        let result = cell
+            .get_or_init(|| {
+                ran = true;
+                async move { run.await.map_err(|e| e.to_string()) }
+            })
+            .await
+            .clone();
+
+        {
+            let mut in_flight = self.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+            if in_flight
+                .get(key)
+                .is_some_and(|current| Arc::ptr_eq(current, &cell))
+            {
+                in_flight.remove(key);
+            }
+        }
+        {
+            let mut stats = self.load_stats.lock().unwrap_or_else(|e| e.into_inner());
+            if ran {
+                stats.preparations += 1;
+            } else {
+                stats.coalesced += 1;
+                tracing::debug!(key = %key, "Joined in-flight model preparation");
+            }
+        }
+        self.record_activity();
+
+        result.map_err(|e| anyhow!(e))
+    }
+
+    async fn prepare_model(&self, model_name: &str) -> Result<()> {
         let status = self
             .model_status
             .read()
@@ -469,10 +1369,10 @@ impl LLMManager {
 
         match status {
             ModelStatus::Loaded => Ok(()),
-            ModelStatus::Downloaded => self.load_model(model_name).await,
+            ModelStatus::Downloaded => self.load_with_limit(model_name).await,
             ModelStatus::NotDownloaded => {
-                self.download_model(model_name).await?;
-                self.load_model(model_name).await
+                self.download_with_limit(model_name).await?;
+                self.load_with_limit(model_name).await
             }
             ModelStatus::Downloading { .. } => Err(anyhow!("Model is currently downloading")),
             ModelStatus::Loading => Err(anyhow!("Model is currently loading")),
@@ -480,25 +1380,282 @@ impl LLMManager {
         }
     }
 
-    pub async fn generate(
+    async fn download_with_limit(&self, model_name: &str) -> Result<()> {
+        let status = self.model_status.read().await.get(model_name).cloned();
+        if matches!(
+            status,
+            Some(ModelStatus::Downloaded) | Some(ModelStatus::Loaded)
+        ) {
+            return Ok(());
+        }
+        // Checked before the download as well as on load, so a refused model
+        // is not fetched first
+        self.check_license(model_name)?;
+        let slots = self.download_slots.read().await.clone();
+        let _permit = slots.acquire_owned().await?;
+        self.download_model(model_name).await
+    }
+
+    async fn load_with_limit(&self, model_name: &str) -> Result<()> {
+        let slots = self.load_slots.read().await.clone();
+        let _permit = slots.acquire_owned().await?;
+        self.load_model(model_name).await
+    }
+
+    /// Tokens in `text` under the loaded model's tokenizer, without special
+    /// tokens; fails when no model is loaded rather than estimating
+    pub async fn count_tokens(&self, text: &str) -> Result<usize> {
+        let tokenizer = self.tokenizer.read().await;
+        let tokenizer = tokenizer
+            .as_ref()
+            .ok_or_else(|| anyhow!("No tokenizer loaded; load a model to count tokens"))?;
+        let encoding = tokenizer
+            .encode(text, false)
+            .map_err(|e| anyhow!("Tokenization failed: {}", e))?;
+        Ok(encoding.len())
+    }
+
+    /// Whether `prompt` plus `reserve_for_completion` generated tokens fit in
+    /// the loaded model's context length
+    pub async fn fits_in_context(
         &self,
         prompt: &str,
-        config: Option<GenerationConfig>,
-    ) -> Result<InferenceResult> {
-        let active_model = self.active_model.read().await;
+        reserve_for_completion: usize,
+    ) -> Result<bool> {
+        let prompt_tokens = self.count_tokens(prompt).await?;
+        let context_length = self.active_context_length().await?;
+        Ok(prompt_tokens + reserve_for_completion <= context_length)
+    }
+
+    /// The end of `text` that fits in `max_tokens` of the loaded model's
+    /// tokenizer, trimmed from the front at a token boundary
+    pub async fn truncate_to_tokens(&self, text: &str, max_tokens: usize) -> Result<String> {
+        let tokenizer = self.tokenizer.read().await;
+        let tokenizer = tokenizer
+            .as_ref()
+            .ok_or_else(|| anyhow!("No tokenizer loaded; load a model to truncate by tokens"))?;
+        truncate_to_tokens(tokenizer, text, max_tokens)
+    }
+
+    /// Context length of the loaded model from its registry entry
+    async fn active_context_length(&self) -> Result<usize> {
+        let active = self.active_model.read().await;
+        let name = active
+            .as_ref()
+            .ok_or_else(|| anyhow!("No model is currently loaded"))?;
+        self.models_registry
+            .read()
+            .await
+            .get(name)
+            .map(|config| config.context_length)
+            .ok_or_else(|| anyhow!("Model '{}' is not in the registry", name))
+    }
+
+    /// Prompt for the next assistant reply in the loaded model's chat
+    /// template; passed through unwrapped when no registered model is loaded
+    pub async fn format_prompt(&self, system: Option<&str>, messages: &[ChatTurn]) -> String {
+        let active = self.active_model.read().await.clone();
+        let registry = self.models_registry.read().await;
+        match active.and_then(|name| registry.get(&name)) {
+            Some(config) => config.format_prompt(system, messages),
+            None => ChatTemplate::Raw.format_prompt(system, messages),
+        }
+    }
+
+    /// Prompt for the next reply to a conversation in the loaded model's chat
+    /// template, leaving `reserve_for_completion` tokens of context free. Turns
+    /// that do not fit are dropped oldest first, so that the conversation
+    /// still opens with a user turn; the latest turn is always kept, cut from
+    /// the front when it does not fit on its own.
+    pub async fn format_conversation(
+        &self,
+        system: Option<&str>,
+        turns: &[ChatTurn],
+        reserve_for_completion: usize,
+    ) -> Result<String> {
+        let mut start = 0;
+        loop {
+            let prompt = self.format_prompt(system, &turns[start..]).await;
+            if self
+                .fits_in_context(&prompt, reserve_for_completion)
+                .await?
+            {
+                if start > 0 {
+                    tracing::debug!(
+                        dropped = start,
+                        "Oldest chat turns dropped to fit the context window"
+                    );
+                }
+                return Ok(prompt);
+            }
+            if start + 1 >= turns.len() {
+                return self
+                    .format_truncated_turn(system, &turns[start..], reserve_for_completion)
+                    .await;
+            }
+            start += 1;
+            while start + 1 < turns.len() && turns[start].role == ChatRole::Assistant {
+                start += 1;
+            }
+        }
+    }
+
+    /// Prompt for a single turn too long for the context, keeping the end of
+    /// its content that fits
+    async fn format_truncated_turn(
+        &self,
+        system: Option<&str>,
+        turns: &[ChatTurn],
+        reserve_for_completion: usize,
+    ) -> Result<String> {
+        let Some(turn) = turns.last() else {
+            return Ok(self.format_prompt(system, turns).await);
+        };
+        let budget = self
+            .active_context_length()
+            .await?
+            .saturating_sub(reserve_for_completion);
+        let mut keep = self.count_tokens(&turn.content).await?;
+        loop {
+            let content = self.truncate_to_tokens(&turn.content, keep).await?;
+            let prompt = self
+                .format_prompt(
+                    system,
+                    &[ChatTurn {
+                        role: turn.role,
+                        content,
+                    }],
+                )
+                .await;
+            let overflow = self.count_tokens(&prompt).await?.saturating_sub(budget);
+            if overflow == 0 || keep == 0 {
+                tracing::warn!(
+                    kept_tokens = keep,
+                    "Latest chat turn truncated to fit the context window"
+                );
+                return Ok(prompt);
+            }
+            keep = keep.saturating_sub(overflow);
+        }
+    }
+
+    /// Token ids the loaded model's tokenizer encodes `text` as, without
+    /// special tokens, for use in `logit_bias` and `banned_tokens`. Fails when
+    /// no model is loaded, since ids differ between tokenizers; ids resolved
+    /// for one model should be resolved again after switching models.
+    pub async fn resolve_token_ids(&self, text: &str) -> Result<Vec<u32>> {
+        let tokenizer = self.tokenizer.read().await;
+        let tokenizer = tokenizer
+            .as_ref()
+            .ok_or_else(|| anyhow!("No tokenizer loaded; load a model to resolve token ids"))?;
+        let encoding = tokenizer
+            .encode(text, false)
+            .map_err(|e| anyhow!("Tokenization failed: {}", e))?;
+        Ok(encoding.get_ids().to_vec())
+    }
+
+    /// Tokenizer of the loaded model, shared so other components follow model switches
+    pub fn tokenizer_handle(&self) -> Arc<RwLock<Option<Tokenizer>>> {
+        self.tokenizer.clone()
+    }
+
+    pub async fn get_model_load_limits(&self) -> ModelLoadLimits {
+        self.load_limits.read().await.clone()
+    }
+
+    /// Replace the concurrency limits; runs already holding a slot finish under the old limits
+    pub async fn set_model_load_limits(&self, limits: ModelLoadLimits) -> Result<()> {
+        if limits.max_concurrent_downloads == 0 || limits.max_concurrent_loads == 0 {
+            return Err(anyhow!("Download and load limits must be at least 1"));
+        }
+        *self.download_slots.write().await =
+            Arc::new(Semaphore::new(limits.max_concurrent_downloads));
+        *self.load_slots.write().await = Arc::new(Semaphore::new(limits.max_concurrent_loads));
+        *self.load_limits.write().await = limits;
+        Ok(())
+    }
+
+    pub fn get_model_load_stats(&self) -> ModelLoadStats {
+        self.load_stats
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    pub async fn get_idle_unload_config(&self) -> IdleUnloadConfig {
+        self.idle_unload.read().await.clone()
+    }
+
+    pub async fn set_idle_unload_config(&self, config: IdleUnloadConfig) -> Result<()> {
+        if config.enabled && config.idle_timeout_seconds == 0 {
+            return Err(anyhow!("Idle timeout must be at least one second"));
+        }
+        *self.idle_unload.write().await = config;
+        self.record_activity();
+        Ok(())
+    }
+
+    /// Postpone the idle unload; called on every load and generation
+    fn record_activity(&self) {
+        *self.last_activity.lock().unwrap_or_else(|e| e.into_inner()) = Instant::now();
+    }
+
+    /// Unload the active model if it has been idle past the configured timeout,
+    /// returning its name. The next `ensure_model_ready` loads it again.
+    pub async fn unload_if_idle(&self) -> Result<Option<String>> {
+        self.unload_if_idle_at(Instant::now()).await
+    }
+
+    async fn unload_if_idle_at(&self, now: Instant) -> Result<Option<String>> {
+        let config = self.idle_unload.read().await.clone();
+        if !config.enabled {
+            return Ok(None);
+        }
+
+        // The write lock waits for running generations, which hold it for reading
+        let mut active = self.active_model.write().await;
+        let Some(model_name) = active.clone() else {
+            return Ok(None);
+        };
+        let last_activity = *self.last_activity.lock().unwrap_or_else(|e| e.into_inner());
+        let idle = now.saturating_duration_since(last_activity);
+        if idle < Duration::from_secs(config.idle_timeout_seconds) {
+            return Ok(None);
+        }
+
+        self.unload_active(&mut active).await?;
+        tracing::info!(
+            model = %model_name,
+            idle_seconds = idle.as_secs(),
+            "Model unloaded after idle period"
+        );
+        Ok(Some(model_name))
+    }
+
+    pub async fn generate(
+        &self,
+        prompt: &str,
+        config: Option<GenerationConfig>,
+    ) -> Result<InferenceResult> {
+        let active_model = self.active_model.read().await;
         let _model_name = active_model
             .as_ref()
             .ok_or_else(|| anyhow!("No model is currently loaded"))?;
 
-        let gen_config = match config {
+        let mut gen_config = match config {
             Some(cfg) => cfg,
             None => self.generation_config.read().await.clone(),
         };
+        gen_config.compile_json_schema()?;
+        // Draw a seed when none is set, so the reply can still be reproduced
+        let seed = *gen_config.seed.get_or_insert_with(rand::random);
+        let engine_config = self.engine_config(&gen_config, seed).await;
 
         // Check if GGUF model is loaded
         if !self.gguf_engine.is_model_loaded().await {
             return Err(anyhow!("GGUF model not loaded. Call load_model() first."));
         }
+        self.record_activity();
 
         tracing::debug!(
             "Generating text for prompt: {}",
@@ -512,8 +1669,12 @@ impl LLMManager {
                 prompt,
                 gen_config.max_tokens,
                 gen_config.stop_sequences.clone(),
+                gen_config.grammar.as_deref(),
+                &engine_config,
+                &self.current_cancellation(),
             )
             .await?;
+        self.record_activity();
 
         tracing::info!(
             "Generated {} tokens in {:.2}s ({:.2} tok/s)",
@@ -527,9 +1688,26 @@ impl LLMManager {
             tokens_generated: result.tokens_generated,
             time_ms: result.time_ms,
             tokens_per_second: result.tokens_per_second,
+            cancelled: matches!(result.stop_reason, StopReason::Cancelled),
+            config: gen_config,
         })
     }
 
+    /// Engine settings for one generation: the engine's own, with the
+    /// sampling parameters of `config` and `seed`
+    async fn engine_config(&self, config: &GenerationConfig, seed: u64) -> GGUFInferenceConfig {
+        GGUFInferenceConfig {
+            temperature: config.temperature,
+            top_k: config.top_k as i32,
+            top_p: config.top_p,
+            repeat_penalty: config.repetition_penalty,
+            seed,
+            logit_bias: config.logit_bias.clone(),
+            banned_tokens: config.banned_tokens.clone(),
+            ..self.gguf_engine.get_config().await
+        }
+    }
+
     /// Generate text with streaming support
     #[allow(dead_code)] // Part of public API for streaming generation
     pub async fn generate_stream<F>(
@@ -546,15 +1724,20 @@ impl LLMManager {
             .as_ref()
             .ok_or_else(|| anyhow!("No model is currently loaded"))?;
 
-        let gen_config = match config {
+        let mut gen_config = match config {
             Some(cfg) => cfg,
             None => self.generation_config.read().await.clone(),
         };
+        gen_config.compile_json_schema()?;
+        // Draw a seed when none is set, so the reply can still be reproduced
+        let seed = *gen_config.seed.get_or_insert_with(rand::random);
+        let engine_config = self.engine_config(&gen_config, seed).await;
 
         // Check if GGUF model is loaded
         if !self.gguf_engine.is_model_loaded().await {
             return Err(anyhow!("GGUF model not loaded. Call load_model() first."));
         }
+        self.record_activity();
 
         tracing::debug!(
             "Streaming generation for prompt: {}",
@@ -568,9 +1751,13 @@ impl LLMManager {
                 prompt,
                 gen_config.max_tokens,
                 gen_config.stop_sequences.clone(),
+                gen_config.grammar.as_deref(),
+                &engine_config,
+                &self.current_cancellation(),
                 on_token,
             )
             .await?;
+        self.record_activity();
 
         tracing::info!(
             "Streamed {} tokens in {:.2}s ({:.2} tok/s)",
@@ -584,6 +1771,8 @@ impl LLMManager {
             tokens_generated: result.tokens_generated,
             time_ms: result.time_ms,
             tokens_per_second: result.tokens_per_second,
+            cancelled: matches!(result.stop_reason, StopReason::Cancelled),
+            config: gen_config,
         })
     }
 
@@ -614,9 +1803,31 @@ impl LLMManager {
         self.active_model.read().await.clone()
     }
 
+    /// Token observed by a generation starting now
+    fn current_cancellation(&self) -> CancellationToken {
+        self.generation_cancel
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Stop every generation currently in progress at its next token; each
+    /// returns the text generated so far. Later generations are unaffected.
+    pub fn cancel_generations(&self) {
+        let mut current = self
+            .generation_cancel
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        current.cancel();
+        *current = CancellationToken::new();
+    }
+
     pub async fn unload_model(&self) -> Result<()> {
         let mut active = self.active_model.write().await;
+        self.unload_active(&mut active).await
+    }
 
+    async fn unload_active(&self, active: &mut Option<String>) -> Result<()> {
         if let Some(model_name) = active.as_ref() {
             let mut status = self.model_status.write().await;
             status.insert(model_name.clone(), ModelStatus::Downloaded);
@@ -630,12 +1841,148 @@ impl LLMManager {
 
         let mut tokenizer = self.tokenizer.write().await;
         *tokenizer = None;
+        *self.gpu_offload.write().await = None;
 
         Ok(())
     }
 
-    #[allow(dead_code)] // Part of public API for runtime config updates
+    /// Detect the model's stop sequences and merge them with user overrides
+    async fn apply_detected_stop_sequences(&self, model_dir: &Path) {
+        let detected = {
+            let tokenizer = self.tokenizer.read().await;
+            detect_stop_sequences(model_dir, tokenizer.as_ref())
+        };
+        if detected.is_empty() {
+            tracing::debug!("No stop sequences detected, keeping defaults");
+        } else {
+            tracing::info!(stop_sequences = ?detected, "Detected model stop sequences");
+        }
+
+        *self.detected_stop_sequences.write().await = detected;
+        self.refresh_stop_sequences().await;
+    }
+
+    /// Recompute effective stop sequences: detected (or defaults) plus overrides
+    async fn refresh_stop_sequences(&self) -> Vec<String> {
+        let detected = self.detected_stop_sequences.read().await.clone();
+        let overrides = self.stop_sequence_overrides.read().await.clone();
+
+        let mut effective = if detected.is_empty() {
+            GenerationConfig::default().stop_sequences
+        } else {
+            detected
+        };
+        for sequence in overrides {
+            if !effective.contains(&sequence) {
+                effective.push(sequence);
+            }
+        }
+
+        self.generation_config.write().await.stop_sequences = effective.clone();
+        effective
+    }
+
+    /// Set user stop sequences, merged with the detected ones
+    pub async fn set_stop_sequence_overrides(&self, overrides: Vec<String>) -> StopSequences {
+        *self.stop_sequence_overrides.write().await =
+            overrides.into_iter().filter(|s| !s.is_empty()).collect();
+        self.refresh_stop_sequences().await;
+        self.get_stop_sequences().await
+    }
+
+    /// Stop sequences in effect for generation
+    pub async fn get_stop_sequences(&self) -> StopSequences {
+        StopSequences {
+            detected: self.detected_stop_sequences.read().await.clone(),
+            overrides: self.stop_sequence_overrides.read().await.clone(),
+            effective: self.generation_config.read().await.stop_sequences.clone(),
+        }
+    }
+
+    pub async fn get_generation_config(&self) -> GenerationConfig {
+        self.generation_config.read().await.clone()
+    }
+
+    /// Set generation parameters; changed temperature and max tokens are
+    /// kept as overrides when another model is loaded
     pub async fn update_generation_config(&self, config: GenerationConfig) -> Result<()> {
+        // Rejects a bad schema now rather than on every generation
+        config.clone().compile_json_schema()?;
+        let active_model = self.active_model.read().await.clone();
+        {
+            let current = self.generation_config.read().await;
+            let mut overrides = self.generation_overrides.write().await;
+            if config.temperature != current.temperature {
+                overrides.temperature = Some(config.temperature);
+            }
+            if config.max_tokens != current.max_tokens {
+                overrides.max_tokens = Some(config.max_tokens);
+            }
+            if config.logit_bias != current.logit_bias
+                || config.banned_tokens != current.banned_tokens
+            {
+                *self.token_ids_model.write().await = active_model;
+            }
+        }
+        self.apply_generation_config(config).await
+    }
+
+    pub async fn get_generation_overrides(&self) -> GenerationOverrides {
+        self.generation_overrides.read().await.clone()
+    }
+
+    /// Drop user overrides and return to the active model's defaults
+    pub async fn clear_generation_overrides(&self) -> Result<GenerationConfig> {
+        *self.generation_overrides.write().await = GenerationOverrides::default();
+        let active_config = match self.active_model.read().await.as_ref() {
+            Some(name) => self.models_registry.read().await.get(name).cloned(),
+            None => None,
+        };
+        match active_config {
+            Some(model_config) => self.apply_model_generation_defaults(&model_config).await?,
+            None => {
+                let defaults = GenerationConfig::default();
+                let mut config = self.get_generation_config().await;
+                config.temperature = defaults.temperature;
+                config.max_tokens = defaults.max_tokens;
+                self.apply_generation_config(config).await?;
+            }
+        }
+        Ok(self.get_generation_config().await)
+    }
+
+    /// Seed generation parameters from a model's registry entry, keeping
+    /// overrides. Token ids resolved with another model's tokenizer are
+    /// dropped, since they name unrelated tokens in this one.
+    async fn apply_model_generation_defaults(&self, model_config: &ModelConfig) -> Result<()> {
+        let overrides = self.generation_overrides.read().await.clone();
+        let mut config = self.get_generation_config().await;
+        config.temperature = overrides.temperature.unwrap_or(model_config.temperature);
+        config.max_tokens = overrides.max_tokens.unwrap_or(model_config.max_tokens);
+        {
+            let mut token_ids_model = self.token_ids_model.write().await;
+            if token_ids_model.as_deref() != Some(model_config.name.as_str()) {
+                if !config.logit_bias.is_empty() || !config.banned_tokens.is_empty() {
+                    tracing::warn!(
+                        model = %model_config.name,
+                        "Cleared logit bias and banned tokens set for another model's tokenizer"
+                    );
+                }
+                config.logit_bias.clear();
+                config.banned_tokens.clear();
+                *token_ids_model = Some(model_config.name.clone());
+            }
+        }
+        tracing::debug!(
+            model = %model_config.name,
+            temperature = config.temperature,
+            max_tokens = config.max_tokens,
+            "Generation parameters seeded from model defaults"
+        );
+        self.apply_generation_config(config).await
+    }
+
+    async fn apply_generation_config(&self, config: GenerationConfig) -> Result<()> {
         let mut gen_config = self.generation_config.write().await;
         *gen_config = config.clone();
 
@@ -652,7 +1999,9 @@ impl LLMManager {
             top_k: config.top_k as i32,
             top_p: config.top_p,
             repeat_penalty: config.repetition_penalty,
-            seed: config.seed.unwrap_or(42) as u32,
+            seed: config.seed.unwrap_or(42),
+            logit_bias: config.logit_bias.clone(),
+            banned_tokens: config.banned_tokens.clone(),
         };
 
         self.gguf_engine.update_config(gguf_config).await?;
@@ -672,117 +2021,458 @@ impl LLMManager {
         registry.get(model_name).cloned()
     }
 
-    #[allow(dead_code)]
-    pub async fn delete_model(&self, model_name: &str) -> Result<()> {
-        // Check if model is currently loaded
-        let active = self.active_model.read().await;
-        if active.as_ref() == Some(&model_name.to_string()) {
-            return Err(anyhow!("Cannot delete currently loaded model"));
+    /// Fetch the Hub's latest revision of a model file and move it into place
+    /// only once it matches that revision's size and hash, recording the
+    /// revision it came from. Until then it is kept under a `.part` name, so an
+    /// interrupted download never looks like a complete model.
+    async fn fetch_model_file(&self, model_config: &ModelConfig, model_path: &Path) -> Result<()> {
+        self.fetch_hub_file(
+            &model_config.repo_id,
+            &model_config.model_file,
+            &model_config.name,
+            model_path,
+        )
+        .await
+    }
+
+    /// Download `file` of `repo_id` to `model_path` through a partial file,
+    /// reporting progress under `progress_name` and checking the result
+    /// against the size and SHA-256 the Hub published for it
+    async fn fetch_hub_file(
+        &self,
+        repo_id: &str,
+        file: &str,
+        progress_name: &str,
+        model_path: &Path,
+    ) -> Result<()> {
+        let revision = self.model_hub.latest_revision(repo_id, file).await?;
+        let partial = self
+            .stage_hub_file(repo_id, file, progress_name, &revision, model_path)
+            .await?;
+        tokio::fs::rename(&partial, model_path).await?;
+
+        if let Err(e) = write_local_revision(model_path, &revision) {
+            tracing::warn!(file = %file, error = %e, "Could not record model revision");
         }
+        Ok(())
+    }
+
+    /// Download `file` at `revision` to the partial file beside `model_path`
+    /// and return its path once it matches the published size and SHA-256.
+    /// A download that does not match is deleted.
+    async fn stage_hub_file(
+        &self,
+        repo_id: &str,
+        file: &str,
+        progress_name: &str,
+        revision: &ModelRevision,
+        model_path: &Path,
+    ) -> Result<PathBuf> {
+        let partial = partial_path(model_path);
+        let on_progress = self.download_progress_reporter(progress_name);
+        self.model_hub
+            .download(repo_id, file, revision, &partial, &on_progress)
+            .await?;
 
-        // Delete model files
-        let model_dir = self.models_dir.join(model_name);
-        if model_dir.exists() {
-            tokio::fs::remove_dir_all(&model_dir).await?;
+        let (check_path, expected) = (partial.clone(), revision.clone());
+        let matches =
+            tokio::task::spawn_blocking(move || verify_file(&check_path, &expected, true))
+                .await??;
+        if !matches {
+            tokio::fs::remove_file(&partial).await?;
+            return Err(anyhow!(
+                "{} does not match the size or SHA-256 published for it",
+                file
+            ));
         }
+        Ok(partial)
+    }
 
-        // Update status
-        let mut status = self.model_status.write().await;
-        status.insert(model_name.to_string(), ModelStatus::NotDownloaded);
+    /// Download `file` from any Hub repo into the models directory, reporting
+    /// progress as `DownloadProgressEvent`s named after the file, and return
+    /// where it was saved. Runs within the concurrent download limit, and
+    /// concurrent calls for the same file share one download.
+    pub async fn download_hub_file(&self, repo_id: &str, file: &str) -> Result<PathBuf> {
+        let model_path = hub_file_destination(&self.models_dir, repo_id, file)?;
+        let key = format!("hub:{}/{}", repo_id, file);
+        self.run_coalesced(
+            &key,
+            self.hub_download_with_limit(repo_id, file, &model_path),
+        )
+        .await?;
+        Ok(model_path)
+    }
 
-        tracing::info!(model = %model_name, "Model deleted");
+    async fn hub_download_with_limit(
+        &self,
+        repo_id: &str,
+        file: &str,
+        model_path: &Path,
+    ) -> Result<()> {
+        if let Some(parent) = model_path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
 
-        Ok(())
+        let slots = self.download_slots.read().await.clone();
+        let _permit = slots.acquire_owned().await?;
+        let result = self.fetch_hub_file(repo_id, file, file, model_path).await;
+        // Progress also updates the status of a model by that name; files
+        // outside the registry keep none once the download ends
+        if !self.models_registry.read().await.contains_key(file) {
+            self.model_status.write().await.remove(file);
+        }
+        result
     }
 
-    /// Calculate optimal number of GPU layers to offload based on available VRAM
-    async fn calculate_optimal_gpu_layers(&self, model_config: &ModelConfig) -> u32 {
-        // If no GPU available, return 0
-        if self.device.is_cpu() {
-            tracing::info!("CPU mode: No GPU layers will be offloaded");
-            return 0;
+    /// Receive progress of model downloads as they run
+    pub fn subscribe_download_progress(&self) -> broadcast::Receiver<DownloadProgressEvent> {
+        self.download_events.subscribe()
+    }
+
+    /// Progress callback for one model download: updates the model's
+    /// `Downloading` status and broadcasts a `DownloadProgressEvent`, at most
+    /// every `DOWNLOAD_PROGRESS_INTERVAL_MS` and always on completion
+    fn download_progress_reporter(
+        &self,
+        model_name: &str,
+    ) -> impl Fn(DownloadProgress) + Send + Sync {
+        let model_name = model_name.to_string();
+        let model_status = self.model_status.clone();
+        let events = self.download_events.clone();
+        let last_report = std::sync::Mutex::new(None::<Instant>);
+        let interval = Duration::from_millis(DOWNLOAD_PROGRESS_INTERVAL_MS);
+
+        move |update: DownloadProgress| {
+            let complete = update.total_bytes == Some(update.downloaded_bytes);
+            {
+                let mut last_report = last_report.lock().unwrap_or_else(|e| e.into_inner());
+                if !complete && last_report.is_some_and(|at| at.elapsed() < interval) {
+                    return;
+                }
+                *last_report = Some(Instant::now());
+            }
+
+            let progress = update
+                .total_bytes
+                .filter(|total| *total > 0)
+                .map(|total| (update.downloaded_bytes as f32 / total as f32).min(1.0));
+            // Skipped if the status is busy; the next update catches up
+            if let (Some(progress), Ok(mut status)) = (progress, model_status.try_write()) {
+                status.insert(model_name.clone(), ModelStatus::Downloading { progress });
+            }
+            let _ = events.send(DownloadProgressEvent {
+                model_name: model_name.clone(),
+                downloaded_bytes: update.downloaded_bytes,
+                total_bytes: update.total_bytes,
+                progress,
+                indeterminate: progress.is_none(),
+            });
         }
+    }
 
-        // Try to get GPU VRAM info using nvml-wrapper
-        let available_vram_mb = match self.get_available_vram_mb() {
-            Some(vram) => vram,
+    /// Whether a downloaded model file matches the size and SHA-256 the Hub
+    /// published for it: the revision recorded at download time, or the Hub's
+    /// current one for files downloaded before revisions were recorded.
+    /// False when the file is missing.
+    pub async fn verify_model(&self, model_name: &str) -> Result<bool> {
+        let model_config = {
+            let registry = self.models_registry.read().await;
+            registry
+                .get(model_name)
+                .ok_or_else(|| anyhow!("Model '{}' not found in registry", model_name))?
+                .clone()
+        };
+        let model_path = self
+            .get_model_dir(&model_config)?
+            .join(&model_config.model_file);
+        if !model_path.exists() {
+            return Ok(false);
+        }
+
+        let revision = match read_local_revision(&model_path) {
+            Some(revision) => revision,
+            None if is_offline_mode() => {
+                return Err(anyhow!(
+                    "No recorded revision to verify '{}' against while offline",
+                    model_name
+                ))
+            }
             None => {
-                // Fallback: assume conservative 4GB if detection fails
-                tracing::warn!("Could not detect GPU VRAM, assuming 4GB");
-                4096
+                self.model_hub
+                    .latest_revision(&model_config.repo_id, &model_config.model_file)
+                    .await?
             }
         };
+        if revision.size.is_none() && revision.sha256().is_none() {
+            return Err(anyhow!(
+                "No expected size or hash is known for model '{}'",
+                model_name
+            ));
+        }
 
-        tracing::info!("GPU detected with ~{}MB VRAM available", available_vram_mb);
+        tokio::task::spawn_blocking(move || verify_file(&model_path, &revision, true)).await?
+    }
 
-        // Get recommended layers and VRAM for this model
-        let recommended_layers = model_config.recommended_gpu_layers.unwrap_or(0);
-        let recommended_vram = model_config
-            .recommended_vram_mb
-            .unwrap_or(model_config.size_mb);
+    pub async fn get_model_update_config(&self) -> ModelUpdateConfig {
+        self.update_config.read().await.clone()
+    }
 
-        // Calculate what percentage of the model we can offload
-        // Keep some VRAM free for context and computation (based on VRAM_USAGE_RATIO)
-        let usable_vram = (available_vram_mb as f32 * VRAM_USAGE_RATIO) as u64;
+    pub async fn set_model_update_config(&self, config: ModelUpdateConfig) {
+        *self.update_config.write().await = ModelUpdateConfig {
+            check_interval_hours: config.check_interval_hours.max(1),
+            ..config
+        };
+    }
 
-        if usable_vram < recommended_vram {
-            // Partial offload: scale layers proportionally
-            let ratio = usable_vram as f32 / recommended_vram as f32;
-            let scaled_layers = (recommended_layers as f32 * ratio) as u32;
+    /// Compare each downloaded model's recorded revision with the Hub's latest
+    pub async fn check_model_updates(&self) -> Result<Vec<ModelUpdateStatus>> {
+        if is_offline_mode() {
+            return Err(anyhow!(
+                "Offline mode is enabled; model update check skipped"
+            ));
+        }
 
-            tracing::info!(
-                "Partial GPU offload: {} of {} layers ({}% of model) due to VRAM constraints",
-                scaled_layers,
-                recommended_layers,
-                (ratio * 100.0) as u32
-            );
+        let mut models: Vec<ModelConfig> = self
+            .models_registry
+            .read()
+            .await
+            .values()
+            .cloned()
+            .collect();
+        models.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut statuses = Vec::new();
+        for model_config in models {
+            let Ok(model_dir) = self.get_model_dir(&model_config) else {
+                continue;
+            };
+            let model_path = model_dir.join(&model_config.model_file);
+            if !model_path.exists() {
+                continue;
+            }
+            statuses.push(self.update_status(&model_config, &model_path).await);
+        }
 
-            scaled_layers
-        } else {
-            // Full offload possible
-            tracing::info!(
-                "Full GPU offload: {} layers (sufficient VRAM: {}MB >= {}MB)",
-                recommended_layers,
-                usable_vram,
-                recommended_vram
-            );
+        let available = statuses.iter().filter(|s| s.update_available).count();
+        tracing::info!(
+            checked = statuses.len(),
+            available,
+            "Model update check complete"
+        );
+        Ok(statuses)
+    }
+
+    async fn update_status(
+        &self,
+        model_config: &ModelConfig,
+        model_path: &Path,
+    ) -> ModelUpdateStatus {
+        let local = read_local_revision(model_path);
+        let (remote, error) = match self
+            .model_hub
+            .latest_revision(&model_config.repo_id, &model_config.model_file)
+            .await
+        {
+            Ok(revision) => (Some(revision), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        // Without a recorded local revision there is nothing to compare against
+        let update_available = matches!(
+            (&local, &remote),
+            (Some(local), Some(remote)) if local.etag != remote.etag
+        );
 
-            recommended_layers
+        ModelUpdateStatus {
+            model_name: model_config.name.clone(),
+            local,
+            remote,
+            update_available,
+            error,
         }
     }
 
-    /// Get available VRAM in MB using NVML
-    fn get_available_vram_mb(&self) -> Option<u64> {
-        use nvml_wrapper::Nvml;
+    /// Replace a downloaded model with the Hub's latest file, keeping the old one as `.bak`
+    pub async fn update_model(&self, model_name: &str) -> Result<ModelUpdateStatus> {
+        if is_offline_mode() {
+            return Err(anyhow!("Offline mode is enabled; model update skipped"));
+        }
+        if self.active_model.read().await.as_deref() == Some(model_name) {
+            return Err(anyhow!("Unload model '{}' before updating it", model_name));
+        }
 
-        match Nvml::init() {
-            Ok(nvml) => {
-                // Get first GPU device
-                match nvml.device_by_index(0) {
-                    Ok(device) => {
-                        // Get memory info
-                        match device.memory_info() {
-                            Ok(mem_info) => {
-                                let available_mb = mem_info.free / (1024 * 1024);
-                                Some(available_mb)
-                            }
-                            Err(e) => {
-                                tracing::warn!("Failed to get GPU memory info: {}", e);
-                                None
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        tracing::warn!("Failed to get GPU device: {}", e);
-                        None
-                    }
+        let model_config = {
+            let registry = self.models_registry.read().await;
+            registry
+                .get(model_name)
+                .ok_or_else(|| anyhow!("Model '{}' not found in registry", model_name))?
+                .clone()
+        };
+        let model_path = self
+            .get_model_dir(&model_config)?
+            .join(&model_config.model_file);
+        if !model_path.exists() {
+            return Err(anyhow!("Model '{}' is not downloaded", model_name));
+        }
+
+        let remote = self
+            .model_hub
+            .latest_revision(&model_config.repo_id, &model_config.model_file)
+            .await?;
+        if read_local_revision(&model_path).map(|local| local.etag) == Some(remote.etag.clone()) {
+            return Ok(self.update_status(&model_config, &model_path).await);
+        }
+
+        // The current file stays in place while the new one downloads
+        self.ensure_download_space(model_name).await?;
+
+        tracing::info!(model = %model_name, etag = %remote.etag, "Updating model file");
+        let staged = self
+            .stage_hub_file(
+                &model_config.repo_id,
+                &model_config.model_file,
+                model_name,
+                &remote,
+                &model_path,
+            )
+            .await;
+        // Progress left the model marked as downloading; its file is still usable
+        self.model_status
+            .write()
+            .await
+            .insert(model_name.to_string(), ModelStatus::Downloaded);
+        let staged = staged?;
+
+        // Only a verified download replaces the working file
+        let backup = backup_path(&model_path);
+        if backup.exists() {
+            tokio::fs::remove_file(&backup).await?;
+        }
+        tokio::fs::rename(&model_path, &backup).await?;
+        if let Err(e) = tokio::fs::rename(&staged, &model_path).await {
+            // Put the previous file back so the model stays usable
+            tokio::fs::rename(&backup, &model_path).await?;
+            return Err(anyhow!("Failed to install updated model: {}", e));
+        }
+        write_local_revision(&model_path, &remote)?;
+
+        tracing::info!(model = %model_name, backup = ?backup, "Model updated");
+        Ok(ModelUpdateStatus {
+            model_name: model_name.to_string(),
+            local: Some(remote.clone()),
+            remote: Some(remote),
+            update_available: false,
+            error: None,
+        })
+    }
+
+    /// Delete a downloaded model file with its revision record, update backup and
+    /// any partial download. Other files in the repository directory, such as the
+    /// tokenizer or another quantization, are kept.
+    pub async fn delete_model(&self, model_name: &str) -> Result<()> {
+        // Check if model is currently loaded
+        if self.active_model.read().await.as_deref() == Some(model_name) {
+            return Err(anyhow!("Cannot delete currently loaded model"));
+        }
+
+        let model_config = {
+            let registry = self.models_registry.read().await;
+            registry
+                .get(model_name)
+                .ok_or_else(|| anyhow!("Model '{}' not found in registry", model_name))?
+                .clone()
+        };
+        let model_path = self
+            .get_model_dir(&model_config)?
+            .join(&model_config.model_file);
+        for path in [
+            sidecar_path(&model_path),
+            backup_path(&model_path),
+            partial_path(&model_path),
+            model_path,
+        ] {
+            if path.exists() {
+                tokio::fs::remove_file(&path).await?;
+            }
+        }
+
+        // Update status
+        let mut status = self.model_status.write().await;
+        status.insert(model_name.to_string(), ModelStatus::NotDownloaded);
+
+        tracing::info!(model = %model_name, "Model deleted");
+
+        Ok(())
+    }
+
+    /// Unload the active model and delete every downloaded model and custom
+    /// registration, returning the number of model directories removed
+    pub async fn delete_all_models(&self) -> Result<usize> {
+        self.unload_model().await?;
+
+        let mut removed = 0;
+        if self.models_dir.exists() {
+            let mut entries = tokio::fs::read_dir(&self.models_dir).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                if entry.file_type().await?.is_dir() {
+                    tokio::fs::remove_dir_all(entry.path()).await?;
+                    removed += 1;
+                } else {
+                    tokio::fs::remove_file(entry.path()).await?;
                 }
             }
-            Err(e) => {
-                tracing::debug!("NVML not available: {}", e);
-                None
+        }
+
+        // Back to the built-in registry, none of it downloaded, and the
+        // default headroom now that its saved setting is gone
+        *self.download_headroom_mb.write().await = DOWNLOAD_DISK_HEADROOM_MB;
+        self.models_registry.write().await.clear();
+        self.model_status.write().await.clear();
+        self.load_model_registry().await;
+
+        tracing::info!(removed, "All models deleted");
+        Ok(removed)
+    }
+
+    /// Plan GPU offload from the VRAM of the detected GPUs
+    async fn calculate_optimal_gpu_layers(&self, model_config: &ModelConfig) -> GpuOffload {
+        let gpus = tokio::task::spawn_blocking(hardware_detector::detect_gpus)
+            .await
+            .unwrap_or_else(|e| {
+                tracing::warn!("GPU detection failed: {}", e);
+                Vec::new()
+            });
+        let offload = plan_gpu_offload(model_config, &gpus, !self.device.is_cpu());
+
+        match (&offload.gpu_name, offload.available_vram_mb) {
+            (Some(name), Some(vram)) => tracing::info!(
+                gpu = %name,
+                gpu_count = offload.gpu_count,
+                "GPU detected with ~{}MB VRAM available",
+                vram
+            ),
+            _ if offload.gpu_backend => {
+                tracing::warn!("Could not detect GPU VRAM, assuming {}MB", FALLBACK_VRAM_MB)
             }
+            _ => {}
         }
+        if !offload.gpu_backend {
+            tracing::info!("CPU mode: No GPU layers will be offloaded");
+        } else if offload.gpu_layers < offload.recommended_layers {
+            tracing::info!(
+                "Partial GPU offload: {} of {} layers due to VRAM constraints",
+                offload.gpu_layers,
+                offload.recommended_layers
+            );
+        } else {
+            tracing::info!("Full GPU offload: {} layers", offload.gpu_layers);
+        }
+
+        offload
+    }
+
+    /// GPU offload chosen for the loaded model
+    pub async fn get_gpu_offload(&self) -> Option<GpuOffload> {
+        self.gpu_offload.read().await.clone()
     }
 
     #[allow(dead_code)] // Part of public API for direct model loading
@@ -814,3 +2504,899 @@ impl LLMManager {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text_segmentation::tests::word_tokenizer;
+
+    #[tokio::test]
+    async fn test_chatml_tokenizer_config_sets_stop_sequences() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            temp_dir.path().join("tokenizer_config.json"),
+            serde_json::json!({
+                "eos_token": "<|im_end|>",
+                "chat_template": "{% for message in messages %}{{'<|im_start|>' + message['role'] + '\\n' + message['content'] + '<|im_end|>' + '\\n'}}{% endfor %}",
+                "added_tokens_decoder": {
+                    "151645": {"content": "<|im_end|>", "special": true},
+                    "151643": {"content": "<|endoftext|>", "special": true}
+                }
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        let manager = LLMManager::new().unwrap();
+        manager
+            .set_stop_sequence_overrides(vec!["###".to_string()])
+            .await;
+        manager.apply_detected_stop_sequences(temp_dir.path()).await;
+
+        let stops = manager.get_stop_sequences().await;
+        assert_eq!(stops.detected, vec!["<|im_end|>", "<|endoftext|>"]);
+        assert!(stops.effective.contains(&"<|im_end|>".to_string()));
+        assert!(stops.effective.contains(&"###".to_string()));
+        assert!(!stops.effective.contains(&"[/INST]".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_loaded_model_seeds_generation_defaults() {
+        let manager = LLMManager::new().unwrap();
+        manager.load_model_registry().await;
+        {
+            let mut registry = manager.models_registry.write().await;
+            registry.get_mut("tinyllama-1.1b").unwrap().temperature = 0.5;
+            registry.get_mut("mistral-7b-instruct").unwrap().temperature = 0.3;
+        }
+        let registry = manager.models_registry.read().await.clone();
+        let load = |name: &str| manager.apply_model_generation_defaults(&registry[name]);
+
+        load("tinyllama-1.1b").await.unwrap();
+        let config = manager.get_generation_config().await;
+        assert_eq!((config.temperature, config.max_tokens), (0.5, 1024));
+
+        load("mistral-7b-instruct").await.unwrap();
+        let config = manager.get_generation_config().await;
+        assert_eq!(
+            (config.temperature, config.max_tokens),
+            (0.3, DEFAULT_MAX_TOKENS)
+        );
+
+        // A user-chosen temperature survives the next load; max tokens still follow the model
+        manager
+            .update_generation_config(GenerationConfig {
+                temperature: 0.9,
+                ..config
+            })
+            .await
+            .unwrap();
+        load("tinyllama-1.1b").await.unwrap();
+        let config = manager.get_generation_config().await;
+        assert_eq!((config.temperature, config.max_tokens), (0.9, 1024));
+        assert_eq!(
+            manager.get_generation_overrides().await,
+            GenerationOverrides {
+                temperature: Some(0.9),
+                max_tokens: None,
+            }
+        );
+
+        // Token ids stay with the model they were resolved for, and are
+        // dropped when another model loads
+        *manager.active_model.write().await = Some("tinyllama-1.1b".to_string());
+        manager
+            .update_generation_config(GenerationConfig {
+                logit_bias: HashMap::from([(7, -5.0)]),
+                banned_tokens: vec![13],
+                ..config
+            })
+            .await
+            .unwrap();
+        manager.clear_generation_overrides().await.unwrap();
+        let config = manager.get_generation_config().await;
+        assert_eq!(config.banned_tokens, vec![13]);
+        load("mistral-7b-instruct").await.unwrap();
+        let config = manager.get_generation_config().await;
+        assert!(config.logit_bias.is_empty());
+        assert!(config.banned_tokens.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_download_refused_when_disk_space_low() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut manager = LLMManager::new().unwrap();
+        manager.models_dir = temp_dir.path().join("models");
+        manager.disk_space_probe = Arc::new(|_| Some(100));
+        manager.load_model_registry().await;
+
+        let space = manager
+            .check_download_space("tinyllama-1.1b")
+            .await
+            .unwrap();
+        assert!(!space.sufficient);
+        assert_eq!(space.required_mb, 638 + DOWNLOAD_DISK_HEADROOM_MB);
+        assert_eq!(space.shortfall_mb, space.required_mb - 100);
+
+        let err = manager.download_model("tinyllama-1.1b").await.unwrap_err();
+        let insufficient = err
+            .downcast_ref::<InsufficientDiskSpace>()
+            .expect("expected InsufficientDiskSpace error");
+        assert_eq!(insufficient.available_mb, 100);
+        assert_eq!(insufficient.shortfall_mb, space.shortfall_mb);
+
+        // Nothing was fetched or created on disk
+        assert!(!manager.models_dir.exists());
+        assert!(matches!(
+            manager.get_model_status("tinyllama-1.1b").await,
+            Some(ModelStatus::NotDownloaded)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_download_headroom_survives_restart_until_reset() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut manager = LLMManager::new().unwrap();
+        manager.models_dir = temp_dir.path().join("models");
+        manager.disk_space_probe = Arc::new(|_| Some(100_000));
+        manager.initialize().await.unwrap();
+        manager.set_download_headroom_mb(2048).await.unwrap();
+
+        let mut restarted = LLMManager::new().unwrap();
+        restarted.models_dir = manager.models_dir.clone();
+        restarted.disk_space_probe = Arc::new(|_| Some(100_000));
+        restarted.initialize().await.unwrap();
+        let space = restarted
+            .check_download_space("tinyllama-1.1b")
+            .await
+            .unwrap();
+        assert_eq!(space.required_mb, 638 + 2048);
+
+        restarted.delete_all_models().await.unwrap();
+        let space = restarted
+            .check_download_space("tinyllama-1.1b")
+            .await
+            .unwrap();
+        assert_eq!(space.required_mb, 638 + DOWNLOAD_DISK_HEADROOM_MB);
+    }
+
+    #[tokio::test]
+    async fn test_license_check_refuses_download_and_load() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut manager = LLMManager::new().unwrap();
+        manager.models_dir = temp_dir.path().join("models");
+        manager.load_model_registry().await;
+        let checked = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = checked.clone();
+        manager.set_license_check(Arc::new(move |model: &str| {
+            seen.lock().unwrap().push(model.to_string());
+            Err(format!("{} is blocked by the license policy", model))
+        }));
+
+        let err = manager
+            .ensure_model_ready("tinyllama-1.1b")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("blocked by the license policy"));
+        assert!(!manager.models_dir.exists());
+
+        let local = temp_dir.path().join("local.gguf");
+        std::fs::write(&local, b"GGUF").unwrap();
+        let local = local.to_string_lossy().to_string();
+        assert!(manager.load_model(&local).await.is_err());
+        assert_eq!(
+            *checked.lock().unwrap(),
+            vec!["tinyllama-1.1b".to_string(), local]
+        );
+    }
+
+    /// Hub whose latest revision is fixed and whose files are served from disk
+    struct MockHub {
+        latest: crate::model_updates::ModelRevision,
+        file: PathBuf,
+    }
+
+    #[async_trait::async_trait]
+    impl ModelHub for MockHub {
+        async fn latest_revision(
+            &self,
+            _repo_id: &str,
+            _file: &str,
+        ) -> Result<crate::model_updates::ModelRevision> {
+            Ok(self.latest.clone())
+        }
+
+        async fn fetch(
+            &self,
+            _repo_id: &str,
+            _file: &str,
+            _revision: &crate::model_updates::ModelRevision,
+        ) -> Result<PathBuf> {
+            Ok(self.file.clone())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_newer_hub_revision_reported_and_installed() {
+        use crate::model_updates::ModelRevision;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut manager = LLMManager::new().unwrap();
+        manager.models_dir = temp_dir.path().join("models");
+        manager.disk_space_probe = Arc::new(|_| None);
+        manager.load_model_registry().await;
+
+        let config = manager.get_model_info("tinyllama-1.1b").await.unwrap();
+        let model_path = manager
+            .get_model_dir(&config)
+            .unwrap()
+            .join(&config.model_file);
+        std::fs::create_dir_all(model_path.parent().unwrap()).unwrap();
+        std::fs::write(&model_path, b"old quant").unwrap();
+        write_local_revision(
+            &model_path,
+            &ModelRevision {
+                commit: "aaa111".to_string(),
+                etag: "etag-old".to_string(),
+                size: None,
+            },
+        )
+        .unwrap();
+
+        let fetched = temp_dir.path().join("fetched.gguf");
+        std::fs::write(&fetched, b"fixed quant").unwrap();
+        let latest = ModelRevision {
+            commit: "bbb222".to_string(),
+            etag: "etag-new".to_string(),
+            size: None,
+        };
+        manager.model_hub = Arc::new(MockHub {
+            latest: latest.clone(),
+            file: fetched,
+        });
+
+        let statuses = manager.check_model_updates().await.unwrap();
+        assert_eq!(statuses.len(), 1);
+        assert_eq!(statuses[0].model_name, config.name);
+        assert!(statuses[0].update_available);
+        assert_eq!(statuses[0].remote.as_ref(), Some(&latest));
+
+        let updated = manager.update_model("tinyllama-1.1b").await.unwrap();
+        assert!(!updated.update_available);
+        assert_eq!(std::fs::read(&model_path).unwrap(), b"fixed quant");
+        assert_eq!(
+            std::fs::read(backup_path(&model_path)).unwrap(),
+            b"old quant"
+        );
+        assert_eq!(read_local_revision(&model_path), Some(latest));
+        assert!(!manager.check_model_updates().await.unwrap()[0].update_available);
+    }
+
+    #[tokio::test]
+    async fn test_update_rejected_when_download_does_not_verify() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut manager = LLMManager::new().unwrap();
+        manager.models_dir = temp_dir.path().join("models");
+        manager.disk_space_probe = Arc::new(|_| None);
+        manager.load_model_registry().await;
+
+        let config = manager.get_model_info("tinyllama-1.1b").await.unwrap();
+        let model_path = manager
+            .get_model_dir(&config)
+            .unwrap()
+            .join(&config.model_file);
+        std::fs::create_dir_all(model_path.parent().unwrap()).unwrap();
+        std::fs::write(&model_path, b"old quant").unwrap();
+
+        // The Hub publishes a larger file than arrives, as with a cut-off download
+        let fetched = temp_dir.path().join("fetched.gguf");
+        std::fs::write(&fetched, b"trunc").unwrap();
+        manager.model_hub = Arc::new(MockHub {
+            latest: ModelRevision {
+                commit: "ccc333".to_string(),
+                etag: "etag-new".to_string(),
+                size: Some(4096),
+            },
+            file: fetched,
+        });
+
+        let err = manager.update_model("tinyllama-1.1b").await.unwrap_err();
+        assert!(err.to_string().contains("does not match"));
+        assert_eq!(std::fs::read(&model_path).unwrap(), b"old quant");
+        assert!(!backup_path(&model_path).exists());
+        assert!(!partial_path(&model_path).exists());
+        assert!(matches!(
+            manager.get_model_status("tinyllama-1.1b").await,
+            Some(ModelStatus::Downloaded)
+        ));
+
+        // Not enough room for the new copy beside the old one
+        manager.disk_space_probe = Arc::new(|_| Some(1));
+        let err = manager.update_model("tinyllama-1.1b").await.unwrap_err();
+        assert!(err.downcast_ref::<InsufficientDiskSpace>().is_some());
+        assert_eq!(std::fs::read(&model_path).unwrap(), b"old quant");
+    }
+
+    #[tokio::test]
+    async fn test_download_reports_progress_events() {
+        use crate::model_updates::ModelRevision;
+        use sha2::{Digest, Sha256};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut manager = LLMManager::new().unwrap();
+        manager.models_dir = temp_dir.path().join("models");
+        manager.disk_space_probe = Arc::new(|_| None);
+        manager.load_model_registry().await;
+
+        let contents = vec![7u8; 4096];
+        let fetched = temp_dir.path().join("fetched.gguf");
+        std::fs::write(&fetched, &contents).unwrap();
+        manager.model_hub = Arc::new(MockHub {
+            latest: ModelRevision {
+                commit: "ddd444".to_string(),
+                etag: hex::encode(Sha256::digest(&contents)),
+                size: Some(contents.len() as u64),
+            },
+            file: fetched,
+        });
+        let config = manager.get_model_info("phi-2").await.unwrap();
+        let model_dir = manager.get_model_dir(&config).unwrap();
+        std::fs::create_dir_all(&model_dir).unwrap();
+        std::fs::write(model_dir.join("tokenizer.json"), b"{}").unwrap();
+
+        let mut events = manager.subscribe_download_progress();
+        manager.download_model(&config.name).await.unwrap();
+        let event = events.try_recv().unwrap();
+        assert_eq!(event.model_name, config.name);
+        assert_eq!(event.downloaded_bytes, 4096);
+        assert_eq!(event.total_bytes, Some(4096));
+        assert_eq!(event.progress, Some(1.0));
+        assert!(!event.indeterminate);
+
+        // Without a known length progress is indeterminate; updates are throttled
+        let report = manager.download_progress_reporter("phi-2");
+        for downloaded_bytes in [100, 200] {
+            report(DownloadProgress {
+                downloaded_bytes,
+                total_bytes: None,
+            });
+        }
+        let event = events.try_recv().unwrap();
+        assert!(event.indeterminate);
+        assert_eq!(event.progress, None);
+        assert_eq!(event.downloaded_bytes, 100);
+        assert!(events.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_delete_model_removes_only_its_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut manager = LLMManager::new().unwrap();
+        manager.models_dir = temp_dir.path().join("models");
+        manager.load_model_registry().await;
+
+        let config = manager.get_model_info("tinyllama-1.1b").await.unwrap();
+        let model_dir = manager.get_model_dir(&config).unwrap();
+        let model_path = model_dir.join(&config.model_file);
+        std::fs::create_dir_all(&model_dir).unwrap();
+        std::fs::write(&model_path, b"quant").unwrap();
+        std::fs::write(backup_path(&model_path), b"old quant").unwrap();
+        std::fs::write(model_dir.join("tokenizer.json"), b"{}").unwrap();
+
+        manager.delete_model("tinyllama-1.1b").await.unwrap();
+        assert!(!model_path.exists());
+        assert!(!backup_path(&model_path).exists());
+        assert!(model_dir.join("tokenizer.json").exists());
+        assert!(matches!(
+            manager.get_model_status("tinyllama-1.1b").await,
+            Some(ModelStatus::NotDownloaded)
+        ));
+
+        assert!(manager.delete_model("../escape").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_hub_file_downloaded_under_models_dir() {
+        use crate::model_updates::ModelRevision;
+        use sha2::{Digest, Sha256};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut manager = LLMManager::new().unwrap();
+        manager.models_dir = temp_dir.path().join("models");
+
+        let contents = b"gguf quant".to_vec();
+        let fetched = temp_dir.path().join("fetched.gguf");
+        std::fs::write(&fetched, &contents).unwrap();
+        manager.model_hub = Arc::new(MockHub {
+            latest: ModelRevision {
+                commit: "eee555".to_string(),
+                etag: hex::encode(Sha256::digest(&contents)),
+                size: Some(contents.len() as u64),
+            },
+            file: fetched,
+        });
+
+        let mut events = manager.subscribe_download_progress();
+        let path = manager
+            .download_hub_file("bartowski/Phi-3-mini-GGUF", "Phi-3-mini-Q4_K_M.gguf")
+            .await
+            .unwrap();
+        assert_eq!(
+            path,
+            manager
+                .models_dir
+                .join("bartowski_Phi-3-mini-GGUF")
+                .join("Phi-3-mini-Q4_K_M.gguf")
+        );
+        assert_eq!(std::fs::read(&path).unwrap(), contents);
+        let event = events.try_recv().unwrap();
+        assert_eq!(event.model_name, "Phi-3-mini-Q4_K_M.gguf");
+        assert_eq!(event.progress, Some(1.0));
+        assert!(manager
+            .get_model_status("Phi-3-mini-Q4_K_M.gguf")
+            .await
+            .is_none());
+        // The download went through the shared, deduplicated path
+        assert_eq!(manager.get_model_load_stats().preparations, 1);
+        assert!(manager.in_flight.lock().unwrap().is_empty());
+
+        for (repo_id, file) in [
+            ("bartowski/Phi-3-mini-GGUF", "../../escape.gguf"),
+            ("bartowski/Phi-3-mini-GGUF", "/etc/escape.gguf"),
+            ("..", "escape.gguf"),
+        ] {
+            assert!(manager.download_hub_file(repo_id, file).await.is_err());
+        }
+        assert!(!temp_dir.path().join("escape.gguf").exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_model_dir_symlinked_outside_models_dir_is_refused() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut manager = LLMManager::new().unwrap();
+        manager.models_dir = temp_dir.path().join("models");
+        manager.load_model_registry().await;
+
+        let config = manager.get_model_info("tinyllama-1.1b").await.unwrap();
+        let outside = temp_dir.path().join("outside");
+        std::fs::create_dir_all(&outside).unwrap();
+        std::fs::create_dir_all(&manager.models_dir).unwrap();
+        assert!(manager.get_model_dir(&config).is_ok());
+
+        std::os::unix::fs::symlink(
+            &outside,
+            manager.models_dir.join(config.repo_id.replace('/', "_")),
+        )
+        .unwrap();
+        assert!(manager.get_model_dir(&config).is_err());
+        assert!(manager.download_model("tinyllama-1.1b").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_registered_model_persists_and_is_scanned() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let open = || {
+            let mut manager = LLMManager::new().unwrap();
+            manager.models_dir = temp_dir.path().join("models");
+            manager
+        };
+        let manager = open();
+        manager.initialize().await.unwrap();
+
+        let mut custom = manager.get_model_info("tinyllama-1.1b").await.unwrap();
+        custom.name = "qwen2-0.5b".to_string();
+        custom.repo_id = "Qwen/Qwen2-0.5B-Instruct-GGUF".to_string();
+        custom.model_file = "qwen2-0_5b-instruct-q4_k_m.gguf".to_string();
+
+        let invalid = |edit: fn(&mut ModelConfig)| {
+            let mut config = custom.clone();
+            edit(&mut config);
+            config
+        };
+        for config in [
+            invalid(|c| c.model_file = "model.safetensors".to_string()),
+            invalid(|c| c.model_file = "../escape.gguf".to_string()),
+            invalid(|c| c.repo_id = "../../outside".to_string()),
+            invalid(|c| c.repo_id = "Qwen/..".to_string()),
+            invalid(|c| c.repo_id = "Qwen".to_string()),
+            invalid(|c| c.size_mb = 0),
+            invalid(|c| c.context_length = 0),
+            invalid(|c| c.name = "phi-2".to_string()),
+        ] {
+            assert!(manager.register_model(config).await.is_err());
+        }
+
+        manager.register_model(custom.clone()).await.unwrap();
+        assert!(matches!(
+            manager.model_status.read().await.get(&custom.name),
+            Some(ModelStatus::NotDownloaded)
+        ));
+
+        // Downloaded separately, then found after a restart
+        let model_path = manager
+            .get_model_dir(&custom)
+            .unwrap()
+            .join(&custom.model_file);
+        std::fs::create_dir_all(model_path.parent().unwrap()).unwrap();
+        std::fs::write(&model_path, b"gguf").unwrap();
+
+        let restarted = open();
+        restarted.initialize().await.unwrap();
+        let info = restarted.get_model_info(&custom.name).await.unwrap();
+        assert_eq!(info.repo_id, custom.repo_id);
+        assert!(matches!(
+            restarted.model_status.read().await.get(&custom.name),
+            Some(ModelStatus::Downloaded)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_truncated_model_detected_and_downloaded_again() {
+        use crate::model_updates::ModelRevision;
+        use sha2::{Digest, Sha256};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut manager = LLMManager::new().unwrap();
+        manager.models_dir = temp_dir.path().join("models");
+        manager.disk_space_probe = Arc::new(|_| None);
+        manager.load_model_registry().await;
+
+        let full = b"complete gguf contents".to_vec();
+        let revision = ModelRevision {
+            commit: "ccc333".to_string(),
+            etag: hex::encode(Sha256::digest(&full)),
+            size: Some(full.len() as u64),
+        };
+        let fetched = temp_dir.path().join("fetched.gguf");
+        std::fs::write(&fetched, &full).unwrap();
+        manager.model_hub = Arc::new(MockHub {
+            latest: revision.clone(),
+            file: fetched.clone(),
+        });
+
+        // An interrupted download: part of the file, with the revision it came from
+        let config = manager.get_model_info("tinyllama-1.1b").await.unwrap();
+        let model_dir = manager.get_model_dir(&config).unwrap();
+        let model_path = model_dir.join(&config.model_file);
+        std::fs::create_dir_all(&model_dir).unwrap();
+        std::fs::write(&model_path, &full[..8]).unwrap();
+        std::fs::write(model_dir.join("tokenizer.json"), b"{}").unwrap();
+        write_local_revision(&model_path, &revision).unwrap();
+
+        manager.scan_local_models().await.unwrap();
+        assert!(matches!(
+            manager.model_status.read().await.get(&config.name),
+            Some(ModelStatus::NotDownloaded)
+        ));
+        assert!(!manager.verify_model(&config.name).await.unwrap());
+
+        manager.download_model(&config.name).await.unwrap();
+        assert_eq!(std::fs::read(&model_path).unwrap(), full);
+        assert!(manager.verify_model(&config.name).await.unwrap());
+        assert!(matches!(
+            manager.model_status.read().await.get(&config.name),
+            Some(ModelStatus::Downloaded)
+        ));
+
+        // A corrupt fetch is rejected rather than installed
+        std::fs::remove_file(&model_path).unwrap();
+        std::fs::write(&fetched, b"complete gguf c0ntents").unwrap();
+        assert!(manager.download_model(&config.name).await.is_err());
+        assert!(!model_path.exists());
+        assert!(!partial_path(&model_path).exists());
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_ensure_model_ready_shares_one_load() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut manager = LLMManager::new().unwrap();
+        manager.models_dir = temp_dir.path().join("models");
+        manager.load_model_registry().await;
+
+        // A downloaded but corrupt model: the single load fails and both callers see it
+        let config = manager.get_model_info("tinyllama-1.1b").await.unwrap();
+        let model_path = manager
+            .get_model_dir(&config)
+            .unwrap()
+            .join(&config.model_file);
+        std::fs::create_dir_all(model_path.parent().unwrap()).unwrap();
+        std::fs::write(&model_path, b"not a gguf file").unwrap();
+        manager
+            .model_status
+            .write()
+            .await
+            .insert(config.name.clone(), ModelStatus::Downloaded);
+
+        // Hold the status lock so both calls are in flight before the load starts
+        let status_guard = manager.model_status.write().await;
+        let (first, second, _) = tokio::join!(
+            manager.ensure_model_ready("tinyllama-1.1b"),
+            manager.ensure_model_ready("tinyllama-1.1b"),
+            async {
+                tokio::task::yield_now().await;
+                drop(status_guard);
+            }
+        );
+
+        let (first, second) = (first.unwrap_err(), second.unwrap_err());
+        assert_eq!(first.to_string(), second.to_string());
+        let stats = manager.get_model_load_stats();
+        assert_eq!(stats.preparations, 1);
+        assert_eq!(stats.coalesced, 1);
+        assert!(manager.in_flight.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_background_download_through_a_clone_shares_state() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut manager = LLMManager::new().unwrap();
+        manager.models_dir = temp_dir.path().join("models");
+        manager.load_model_registry().await;
+        manager.set_license_check(Arc::new(|model: &str| {
+            Err(format!("License of {} is not allowed", model))
+        }));
+        manager
+            .model_status
+            .write()
+            .await
+            .insert("tinyllama-1.1b".to_string(), ModelStatus::Downloaded);
+
+        let handle = manager.clone();
+        handle
+            .ensure_model_downloaded("tinyllama-1.1b")
+            .await
+            .unwrap();
+        let error = handle.ensure_model_downloaded("phi-2").await.unwrap_err();
+        assert!(error.to_string().contains("not allowed"));
+
+        // Both runs are counted on the original; nothing is left in flight
+        assert_eq!(manager.get_model_load_stats().preparations, 2);
+        assert!(manager.in_flight.lock().unwrap().is_empty());
+        assert!(!manager.models_dir.exists());
+    }
+
+    #[tokio::test]
+    async fn test_idle_model_unloads_and_reloads_on_next_request() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut manager = LLMManager::new().unwrap();
+        manager.models_dir = temp_dir.path().join("models");
+        manager.load_model_registry().await;
+        manager
+            .set_idle_unload_config(IdleUnloadConfig {
+                enabled: true,
+                idle_timeout_seconds: 60,
+            })
+            .await
+            .unwrap();
+
+        // Stand-in for a loaded model; its file on disk is not a real GGUF
+        let config = manager.get_model_info("tinyllama-1.1b").await.unwrap();
+        let model_path = manager
+            .get_model_dir(&config)
+            .unwrap()
+            .join(&config.model_file);
+        std::fs::create_dir_all(model_path.parent().unwrap()).unwrap();
+        std::fs::write(&model_path, b"not a gguf file").unwrap();
+        manager
+            .model_status
+            .write()
+            .await
+            .insert(config.name.clone(), ModelStatus::Loaded);
+        *manager.active_model.write().await = Some(config.name.clone());
+
+        let start = Instant::now();
+        *manager.last_activity.lock().unwrap() = start;
+        let before_timeout = start + Duration::from_secs(59);
+        assert_eq!(
+            manager.unload_if_idle_at(before_timeout).await.unwrap(),
+            None
+        );
+        assert!(manager.ensure_model_ready("tinyllama-1.1b").await.is_ok());
+
+        *manager.last_activity.lock().unwrap() = start;
+        let past_timeout = start + Duration::from_secs(61);
+        assert_eq!(
+            manager.unload_if_idle_at(past_timeout).await.unwrap(),
+            Some(config.name.clone())
+        );
+        assert_eq!(manager.get_active_model().await, None);
+        assert!(matches!(
+            manager.get_model_status("tinyllama-1.1b").await,
+            Some(ModelStatus::Downloaded)
+        ));
+
+        // The next request loads the model from disk again
+        let err = manager
+            .ensure_model_ready("tinyllama-1.1b")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("not compatible"), "{}", err);
+
+        // Disabled policy never unloads
+        manager
+            .set_idle_unload_config(IdleUnloadConfig {
+                enabled: false,
+                idle_timeout_seconds: 60,
+            })
+            .await
+            .unwrap();
+        *manager.active_model.write().await = Some(config.name.clone());
+        let much_later = start + Duration::from_secs(3600);
+        assert_eq!(manager.unload_if_idle_at(much_later).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_feasibility_rejects_7b_on_low_ram_machine() {
+        let mut manager = LLMManager::new().unwrap();
+        manager.disk_space_probe = Arc::new(|_| Some(50_000));
+        manager.load_model_registry().await;
+
+        let low_ram = SystemResources {
+            available_ram_mb: 3072,
+            free_vram_mb: None,
+        };
+
+        let mistral = manager
+            .assess_model_feasibility("mistral-7b-instruct", &low_ram)
+            .await
+            .unwrap();
+        assert!(mistral.can_download);
+        assert!(!mistral.can_load);
+        assert!(!mistral.feasible);
+        assert_eq!(mistral.recommended_gpu_layers, 0);
+
+        let tiny = manager
+            .assess_model_feasibility("tinyllama-1.1b", &low_ram)
+            .await
+            .unwrap();
+        assert!(tiny.feasible);
+        assert!(tiny.estimated_tokens_per_second > mistral.estimated_tokens_per_second);
+    }
+
+    #[tokio::test]
+    async fn test_token_budget_uses_loaded_tokenizer() {
+        let manager = LLMManager::new().unwrap();
+        manager.load_model_registry().await;
+        let err = manager
+            .count_tokens("The tenant pays rent.")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("No tokenizer loaded"));
+        assert!(manager.fits_in_context("", 0).await.is_err());
+
+        *manager.tokenizer.write().await = Some(word_tokenizer());
+        *manager.active_model.write().await = Some("tinyllama-1.1b".to_string());
+
+        assert_eq!(manager.count_tokens("").await.unwrap(), 0);
+        assert!(manager.fits_in_context("", 2048).await.unwrap());
+        let long = "rent ".repeat(2000);
+        assert_eq!(manager.count_tokens(&long).await.unwrap(), 2000);
+        assert!(manager.fits_in_context(&long, 48).await.unwrap());
+        assert!(!manager.fits_in_context(&long, 49).await.unwrap());
+        assert_eq!(
+            manager.truncate_to_tokens(&long, 2).await.unwrap(),
+            "rent rent "
+        );
+    }
+
+    #[tokio::test]
+    async fn test_conversation_drops_oldest_turns_to_fit_context() {
+        let manager = LLMManager::new().unwrap();
+        manager.load_model_registry().await;
+        *manager.tokenizer.write().await = Some(word_tokenizer());
+        *manager.active_model.write().await = Some("tinyllama-1.1b".to_string());
+
+        let turns = [
+            ChatTurn::user("alpha ".repeat(900)),
+            ChatTurn::assistant("beta ".repeat(900)),
+            ChatTurn::user("gamma ".repeat(100)),
+        ];
+        let full = manager
+            .format_conversation(None, &turns, 100)
+            .await
+            .unwrap();
+        assert!(full.starts_with("<|user|>\nalpha"));
+        assert!(full.ends_with("<|assistant|>\n"));
+
+        // Dropping the first question would leave the conversation opening
+        // with a reply, so only the latest question remains
+        let trimmed = manager
+            .format_conversation(None, &turns, 200)
+            .await
+            .unwrap();
+        assert!(!trimmed.contains("alpha") && !trimmed.contains("beta"));
+        assert!(trimmed.starts_with("<|user|>\ngamma"));
+        assert!(manager.count_tokens(&trimmed).await.unwrap() + 200 <= 2048);
+
+        assert_eq!(
+            manager.format_conversation(None, &[], 100).await.unwrap(),
+            "<|assistant|>\n"
+        );
+
+        // A latest turn too long on its own keeps the end that fits
+        let long = [ChatTurn::user(format!("{}question", "delta ".repeat(3000)))];
+        let truncated = manager.format_conversation(None, &long, 200).await.unwrap();
+        assert!(truncated.starts_with("<|user|>\ndelta"));
+        assert!(truncated.contains("question"));
+        assert!(manager.fits_in_context(&truncated, 200).await.unwrap());
+    }
+
+    #[test]
+    fn test_gpu_layers_scale_with_vram() {
+        let mut config = ModelConfig {
+            name: "mistral-7b-instruct".to_string(),
+            model_type: "mistral".to_string(),
+            repo_id: "test/mistral".to_string(),
+            model_file: "mistral.gguf".to_string(),
+            tokenizer_repo: None,
+            max_tokens: 1024,
+            temperature: 0.7,
+            context_length: 4096,
+            size_mb: 4370,
+            quantization: "Q4_K_M".to_string(),
+            requires_gpu: true,
+            recommended_gpu_layers: Some(35),
+            recommended_vram_mb: Some(6144),
+            chat_template: ChatTemplate::Mistral,
+        };
+
+        // 80% of 4096MB is usable: 3276 / 6144 of 35 layers
+        assert_eq!(gpu_layers_for_vram(&config, 4096), 18);
+        assert_eq!(gpu_layers_for_vram(&config, 8192), 35);
+        assert_eq!(gpu_layers_for_vram(&config, 0), 0);
+        config.recommended_vram_mb = None;
+        assert_eq!(gpu_layers_for_vram(&config, 4096), 26);
+        config.recommended_vram_mb = Some(6144);
+
+        let gpu = |name: &str, free: u64| GpuInfo {
+            name: name.to_string(),
+            memory_total: free,
+            memory_free: free,
+            compute_capability: None,
+            driver_version: "Unknown".to_string(),
+        };
+        let offload = plan_gpu_offload(&config, &[gpu("small", 2048), gpu("large", 4096)], true);
+        assert_eq!(offload.gpu_name.as_deref(), Some("large"));
+        assert_eq!((offload.gpu_count, offload.gpu_layers), (2, 18));
+        assert!(offload
+            .summary()
+            .starts_with("Partial GPU offload: 18 of 35 layers"));
+
+        let cpu = plan_gpu_offload(&config, &[gpu("large", 4096)], false);
+        assert_eq!((cpu.available_vram_mb, cpu.gpu_layers), (Some(4096), 0));
+        assert_eq!(plan_gpu_offload(&config, &[], true).gpu_layers, 18);
+    }
+
+    #[tokio::test]
+    async fn test_json_schema_compiles_into_grammar() {
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": {"party": {"type": "string"}, "days": {"type": "integer"}}
+        });
+        let mut config = GenerationConfig {
+            json_schema: Some(schema.clone()),
+            ..Default::default()
+        };
+        config.compile_json_schema().unwrap();
+        assert!(config.json_schema.is_none());
+        assert_eq!(
+            config.grammar,
+            Some(crate::grammar::json_schema_to_grammar(&schema).unwrap())
+        );
+
+        let mut both = GenerationConfig {
+            json_schema: Some(schema),
+            ..config
+        };
+        assert!(both.compile_json_schema().is_err());
+
+        let manager = LLMManager::new().unwrap();
+        let unsupported = GenerationConfig {
+            json_schema: Some(serde_json::json!({"type": "date"})),
+            ..Default::default()
+        };
+        assert!(manager.update_generation_config(unsupported).await.is_err());
+        assert!(manager.get_generation_config().await.json_schema.is_none());
+    }
+}
@@ -3,7 +3,7 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::env;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 use sysinfo::System;
@@ -17,55 +17,96 @@ mod pii_detector;
 mod rag_engine;
 
 // Core modules
+mod chat_template;
+mod chat_titles;
 mod commands;
+mod completion;
 mod constants;
 // database is in lib.rs, use bear_ai_llm::database
+mod clause_outline;
+mod config_snapshot;
+mod contract_lifecycle;
+mod document_diff;
+mod document_redaction;
+mod embedding_projection;
+mod ensemble;
+mod factory_reset;
+mod file_generation;
 mod file_processor;
+mod generation_fallback;
+mod generation_records;
+mod gguf_compat;
+mod grammar;
 mod hardware_detector;
 mod hardware_monitor;
 mod huggingface_api;
 mod mcp_server;
 mod model_manager;
+mod model_updates;
+mod obligations;
 mod presidio_bridge;
 mod presidio_service;
 mod process_helper;
-// mod rate_limiter; // REMOVED - Not needed for single-user desktop app, hardware monitor handles resource limits
+mod progressive_download;
+mod query_expansion;
+mod rate_limiter;
+mod read_only_sql;
 mod setup_manager;
+mod shutdown;
+mod structured_extraction;
 mod system;
 mod system_monitor;
+mod text_segmentation;
 mod utils;
 
 // GDPR Compliance module
 mod compliance;
 
-// AI Transparency module
-mod ai_transparency;
-
 // Middleware for consent enforcement
 mod middleware;
 
 // Scheduler for automated tasks
 mod scheduler;
 
+// Contract analysis uses the lib's jurisdiction disclaimers and the GoverningLaw they take
+use bear_ai_llm::{governing_law, risk_assessment};
+
+// Transparency notices and confidence scoring live in lib.rs; bin modules reach
+// them as crate::ai_transparency
+use bear_ai_llm::ai_transparency;
+
+// Encryption lives in lib.rs; bin modules reach it as crate::security
+use bear_ai_llm::security;
+// and the encrypted chat store as crate::database
+use bear_ai_llm::database;
+
 // Import commands - removed non-existent commands
 
 // Use core AI modules
 use llm_manager::LLMManager;
-use pii_detector::{PIIDetector, PresidioMode};
+use pii_detector::streaming::StreamingRedactor;
+use pii_detector::{ActiveExclusions, ExclusionCategory, PIIDetector, PresidioMode};
 use rag_engine::RAGEngine;
 
 // Use other modules
-use file_processor::FileProcessor;
+use file_processor::{ArchiveEntryStatus, ArchiveLimits, FileProcessor, TextChunkReader};
 use hardware_monitor::HardwareMonitor;
 use presidio_bridge::PresidioBridge;
 use setup_manager::SetupManager;
 // DatabaseManager is internal to the database module
 use bear_ai_llm::commands::transparency_commands::TransparencyState;
-use compliance::ComplianceManager;
+use bear_ai_llm::database::chat_encryption_integration::ChatEncryptionLayer;
+use bear_ai_llm::database::export_integration::ExportIntegration;
+use bear_ai_llm::database::field_encryption::{
+    FieldEncryptionConfig, FieldEncryptionLayer, FieldMigrationStats,
+};
+use bear_ai_llm::export_engine;
+use compliance::{AuditAction, ComplianceManager, EntityType};
+use generation_fallback::{FallbackConfig, GenerationFallback, SendMessageError};
 use hardware_detector::{HardwareDetector, HardwareSpecs, ModelRecommendation};
 use mcp_server::{AgentOrchestrator, MCPServer};
 use middleware::{ConsentGuard, ConsentGuardBuilder};
-// use rate_limiter::RateLimiter; // REMOVED - Hardware monitor provides resource protection
+use rate_limiter::{RateLimitConfig, RateLimitUsage, RateLimiter};
 use scheduler::{RetentionScheduler, SchedulerHandle};
 
 // SECURITY FIX: Use tempfile crate for atomic temporary file creation
@@ -177,19 +218,6 @@ impl DatabaseManager {
         Ok(true)
     }
 
-    fn execute_sql_query(&self, _query: &str) -> Result<serde_json::Value, String> {
-        Ok(serde_json::json!({"error": "Database not implemented"}))
-    }
-
-    fn store_document(
-        &self,
-        _filename: &str,
-        _content: &str,
-        _file_type: &str,
-    ) -> Result<i64, String> {
-        Ok(1)
-    }
-
     fn get_document_statistics(&self) -> Result<serde_json::Value, String> {
         Ok(serde_json::json!({
             "total_documents": 0,
@@ -220,7 +248,6 @@ struct AppState {
     // MCP and agent orchestration
     #[allow(dead_code)]
     mcp_server: Arc<MCPServer>,
-    #[allow(dead_code)]
     agent_orchestrator: Arc<AgentOrchestrator>,
 
     // GDPR Compliance
@@ -236,6 +263,45 @@ struct AppState {
 
     // AI Transparency
     transparency_state: Arc<TransparencyState>,
+
+    // Second-opinion ensemble for high-risk queries
+    ensemble_config: Arc<RwLock<ensemble::EnsembleConfig>>,
+
+    // Obligations extracted from uploaded contracts, keyed by document id
+    contract_obligations: Arc<obligations::ObligationStore>,
+
+    // Structured apology returned when generation fails
+    fallback_config: Arc<RwLock<FallbackConfig>>,
+
+    // Per-user request limits for generation commands
+    rate_limiter: Arc<RateLimiter>,
+
+    // Raised to stop an in-progress generate_to_file
+    file_generation_cancel: Arc<std::sync::atomic::AtomicBool>,
+
+    // Raised to stop an in-progress send_message_stream
+    message_stream_cancel: Arc<std::sync::atomic::AtomicBool>,
+
+    // Task forwarding live audit events to the UI, if subscribed
+    audit_tail: Arc<tokio::sync::Mutex<Option<tauri::async_runtime::JoinHandle<()>>>>,
+
+    // Automatic titles for persisted chat sessions
+    chat_titles: Arc<RwLock<chat_titles::ChatTitleManager>>,
+
+    // Opt-in generation parameter records stored with chat messages
+    generation_records: Arc<RwLock<generation_records::GenerationRecorder>>,
+
+    // Encrypted chat history read and written by send_chat
+    chat_store: Arc<ChatStore>,
+
+    // Processed documents recorded for exports and retention
+    document_store: Arc<DocumentStore>,
+
+    // Set once shutdown starts; background loops stop on their next pass
+    shutting_down: Arc<std::sync::atomic::AtomicBool>,
+
+    // Pending confirmation token for factory_reset
+    reset_confirmation: Arc<factory_reset::ResetConfirmation>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -262,6 +328,7 @@ struct ProcessedDocument {
     content: String,
     pii_removed: bool,
     metadata: serde_json::Value,
+    receipt: compliance::ProcessingReceipt,
 }
 
 // Unified system status command
@@ -345,291 +412,2404 @@ async fn process_document(
     state: State<'_, AppState>,
     file_path: String,
     file_type: String,
+    operator: Option<String>,
+    trust_weight: Option<f32>,
 ) -> Result<ProcessedDocument, String> {
-    // No rate limiting needed - hardware monitor already prevents resource exhaustion
+    state
+        .rate_limiter
+        .check_rate_limit(
+            operator.as_deref().unwrap_or("default_user"),
+            &format!("process_document:{}", file_path),
+        )
+        .map_err(|e| e.to_string())?;
 
-    let content = state
+    let extracted = state
         .file_processor
-        .process_file(&file_path, &file_type)
+        .process_file_detailed(&file_path, &file_type)
         .await
         .map_err(|e| e.to_string())?;
+    let content = extracted.text;
+    let extraction_method = if extracted.ocr_used {
+        rag_engine::ExtractionMethod::Ocr
+    } else {
+        rag_engine::ExtractionMethod::Native
+    };
 
     let detector = state.pii_detector.read().await;
-    let cleaned_content = detector
-        .redact_pii(&content)
+    let report = detector
+        .redact_pii_with_report(&content, None)
         .await
         .map_err(|e| e.to_string())?;
+    report.ensure_complete().map_err(|e| e.to_string())?;
+    let pii_stats = detector.summarize_detections(&report.detections).await;
+    let redaction_markers = report.redaction_markers();
+    let redacted_detections = redacted_pii_detections(&report.detections, &redaction_markers);
+    let cleaned_content = report.redacted_text;
 
     // Add to RAG engine
+    let mut metadata = serde_json::json!({
+        "filename": file_path.clone(),
+        "file_type": file_type.clone()
+    });
+    metadata[rag_engine::OWNER_KEY] =
+        serde_json::json!(operator.as_deref().unwrap_or("default_user"));
+    if let Some(weight) = trust_weight {
+        metadata[rag_engine::TRUST_WEIGHT_KEY] = serde_json::json!(weight);
+    }
+    let source = rag_engine::IngestSource::with_sha256(
+        extracted.source_sha256,
+        extraction_method,
+        redaction_markers,
+    );
     let rag = state.rag_engine.write().await;
     let doc_id = rag
-        .add_document(
-            &cleaned_content,
-            serde_json::json!({
-                "filename": file_path.clone(),
-                "file_type": file_type.clone()
-            }),
-        )
+        .add_document_from_source(&cleaned_content, metadata, source)
         .await
         .map_err(|e| e.to_string())?;
 
+    match state
+        .document_store
+        .record(
+            file_path.clone(),
+            cleaned_content.clone(),
+            file_type.clone(),
+            operator.as_deref().unwrap_or("default_user").to_string(),
+            redacted_detections,
+        )
+        .await
+    {
+        // Redactions start as a draft awaiting reviewer sign-off, under the
+        // recorded id that exports check
+        Ok(record_id) => {
+            let review = state.compliance_manager.review();
+            let review = review.read().await;
+            if let Err(e) = review.register_document(&record_id.to_string()) {
+                tracing::warn!("Failed to register redaction review: {}", e);
+            }
+        }
+        Err(e) => {
+            tracing::warn!(document = %doc_id, error = %e, "Failed to record document in the database")
+        }
+    }
+
+    let receipt = issue_processing_receipt(
+        &state.compliance_manager,
+        compliance::ProcessingReceipt::new(
+            &doc_id,
+            "process_document",
+            &file_path,
+            &content,
+            &cleaned_content,
+            &pii_stats,
+            rag.document_chunk_count(&doc_id).await,
+            operator.as_deref().unwrap_or("default_user"),
+        ),
+    )
+    .await;
+
     Ok(ProcessedDocument {
         id: doc_id,
         filename: file_path,
         content: cleaned_content,
         pii_removed: true,
-        metadata: serde_json::json!({"type": file_type}),
+        metadata: serde_json::json!({"type": file_type, "ocr_used": extracted.ocr_used}),
+        receipt,
     })
 }
 
+// Detections whose replacement marker appears in the redacted text, as recorded
+// with the document
+fn redacted_pii_detections(
+    entities: &[pii_detector::PIIEntity],
+    redaction_markers: &[String],
+) -> Vec<export_engine::PIIDetection> {
+    entities
+        .iter()
+        .map(|entity| export_engine::PIIDetection {
+            pii_type: entity.entity_type.clone(),
+            replacement_text: format!("[{}]", entity.entity_type),
+            confidence: entity.confidence as f64,
+            position_start: entity.start,
+            position_end: entity.end,
+        })
+        .filter(|detection| redaction_markers.contains(&detection.replacement_text))
+        .collect()
+}
+
+// Check system safety - hardware monitor prevents resource exhaustion
+async fn ensure_hardware_safe(state: &AppState, operation: &str) -> Result<(), String> {
+    let mut hw_monitor = state.hardware_monitor.write().await;
+    if !hw_monitor.check_safety().await.map_err(|e| e.to_string())? {
+        tracing::warn!(operation, "System resources critically high");
+        return Err(
+            "System resources are critically high. Please wait before sending another message."
+                .to_string(),
+        );
+    }
+
+    // Enforce resource limits before proceeding
+    hw_monitor
+        .enforce_resource_limits(operation)
+        .await
+        .map_err(|e| {
+            tracing::error!(operation, error = %e, "Resource limits exceeded");
+            e.to_string()
+        })
+}
+
 // Enhanced message generation using new LLM manager
 #[tauri::command]
 async fn send_message(
     state: State<'_, AppState>,
     message: String,
     model_name: String,
-) -> Result<String, String> {
-    // Check system safety - hardware monitor prevents resource exhaustion
-    {
-        let mut hw_monitor = state.hardware_monitor.write().await;
-        if !hw_monitor.check_safety().await.map_err(|e| e.to_string())? {
-            tracing::warn!("System resources critically high during send_message");
-            return Err(
-                "System resources are critically high. Please wait before sending another message."
-                    .to_string(),
-            );
-        }
+    user_id: Option<String>,
+    session_id: Option<String>,
+    message_id: Option<i64>,
+) -> Result<String, SendMessageError> {
+    let user_id = user_id.unwrap_or_else(|| "default_user".to_string());
+    state
+        .rate_limiter
+        .check_rate_limit(&user_id, "send_message")
+        .map_err(|e| e.to_string())?;
 
-        // Enforce resource limits before proceeding
-        hw_monitor
-            .enforce_resource_limits("send_message")
-            .await
-            .map_err(|e| {
-                tracing::error!(error = %e, "Resource limits exceeded in send_message");
-                e.to_string()
-            })?;
-    } // hw_monitor dropped here
+    ensure_hardware_safe(&state, "send_message").await?;
 
     // Clean PII from message - scope the detector
+    let cleaned_message = {
+        let detector = state.pii_detector.read().await;
+        let report = detector
+            .redact_pii_with_report(&message, None)
+            .await
+            .map_err(|e| e.to_string())?;
+        report.ensure_complete().map_err(|e| e.to_string())?;
+        report.redacted_text
+    }; // detector dropped here
+
+    // Ensure model is ready and generate a reply, wrapped in the model's chat template
+    let chat = [chat_template::ChatTurn::user(cleaned_message.as_str())];
+    let result = {
+        let llm = state.llm_manager.read().await;
+        match llm.ensure_model_ready(&model_name).await {
+            Ok(()) => {
+                let prompt = llm.format_prompt(None, &chat).await;
+                llm.generate(&prompt, None)
+                    .await
+                    .map(|result| (prompt, result))
+            }
+            Err(e) => Err(e),
+        }
+    }; // llm dropped here
+
+    match result {
+        Ok((prompt, result)) => {
+            if let Some(message_id) = message_id {
+                record_generation(&state, message_id, &model_name, &prompt, &result.config).await;
+            }
+            if let Some(session_id) = session_id {
+                // Titled in the background so the reply is not held up
+                let state = state.inner().clone();
+                let reply = result.text.clone();
+                tokio::spawn(async move {
+                    title_chat_session(&state, &session_id, &cleaned_message, &reply).await;
+                });
+            }
+            Ok(result.text)
+        }
+        Err(e) => Err(generation_failure(&state, &model_name, &e).await),
+    }
+}
+
+// Continue a persisted chat session: earlier turns from the encrypted chat store
+// are sent as context, the oldest dropped when they exceed the model's context,
+// and the new exchange is stored if the user consented to chat storage
+#[tauri::command]
+async fn send_chat(
+    state: State<'_, AppState>,
+    session_id: String,
+    message: String,
+    model_name: String,
+    user_id: Option<String>,
+) -> Result<String, SendMessageError> {
+    let user_id = user_id.unwrap_or_else(|| "default_user".to_string());
+    state
+        .rate_limiter
+        .check_rate_limit(&user_id, "send_chat")
+        .map_err(|e| e.to_string())?;
+    ensure_hardware_safe(&state, "send_chat").await?;
+
     let cleaned_message = {
         let detector = state.pii_detector.read().await;
         detector
-            .redact_pii(&message)
+            .redact_pii(&message, None)
             .await
             .map_err(|e| e.to_string())?
-    }; // detector dropped here
+    };
+
+    let mut turns = load_chat_turns(&state.chat_store, &session_id, &user_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    turns.push(chat_template::ChatTurn::user(cleaned_message.as_str()));
 
-    // Ensure model is ready and generate response
     let result = {
         let llm = state.llm_manager.read().await;
-        llm.ensure_model_ready(&model_name)
+        match llm.ensure_model_ready(&model_name).await {
+            Ok(()) => {
+                let reserve = llm.get_generation_config().await.max_tokens;
+                match llm.format_conversation(None, &turns, reserve).await {
+                    Ok(prompt) => llm
+                        .generate(&prompt, None)
+                        .await
+                        .map(|result| (prompt, result)),
+                    Err(e) => Err(e),
+                }
+            }
+            Err(e) => Err(e),
+        }
+    };
+    let (prompt, result) = match result {
+        Ok(generated) => generated,
+        Err(e) => return Err(generation_failure(&state, &model_name, &e).await),
+    };
+    let reply = result.text.clone();
+
+    let consent = state
+        .consent_guard
+        .check_chat_storage(&user_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    if consent.allowed {
+        let exchange = vec![
+            ("user", cleaned_message.clone()),
+            ("assistant", reply.clone()),
+        ];
+        let message_ids = store_chat_messages(&state.chat_store, &session_id, &user_id, exchange)
             .await
             .map_err(|e| e.to_string())?;
+        if let Some(&reply_id) = message_ids.last() {
+            record_generation(&state, reply_id, &model_name, &prompt, &result.config).await;
+        }
 
-        llm.generate(&cleaned_message, None)
-            .await
-            .map_err(|e| e.to_string())?
-    }; // llm dropped here
+        // Titled in the background so the reply is not held up
+        let state = state.inner().clone();
+        let reply = reply.clone();
+        tokio::spawn(async move {
+            title_chat_session(&state, &session_id, &cleaned_message, &reply).await;
+        });
+    } else {
+        tracing::debug!(session_id = %session_id, "No chat storage consent, exchange not stored");
+    }
 
-    Ok(result.text)
+    Ok(reply)
 }
 
-#[tauri::command]
-async fn detect_hardware(state: State<'_, AppState>) -> Result<HardwareSpecs, String> {
-    let mut detector = state.hardware_detector.write().await;
-    detector.detect_hardware().map_err(|e| e.to_string())
+// Pooled connections to the chat store and the layer encrypting its
+// messages, opened on first use and shared by the chat commands
+struct ChatStore {
+    db_path: PathBuf,
+    opened: tokio::sync::OnceCell<(
+        r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>,
+        Arc<ChatEncryptionLayer>,
+    )>,
 }
 
-#[tauri::command]
-async fn get_model_recommendations(
-    state: State<'_, AppState>,
-) -> Result<Vec<ModelRecommendation>, String> {
-    let mut detector = state.hardware_detector.write().await;
-    let hardware = detector.detect_hardware().map_err(|e| e.to_string())?;
-    Ok(detector.recommend_models(&hardware))
+impl ChatStore {
+    fn new(db_path: PathBuf) -> Self {
+        Self {
+            db_path,
+            opened: tokio::sync::OnceCell::new(),
+        }
+    }
+
+    async fn open(
+        &self,
+    ) -> anyhow::Result<(
+        r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>,
+        Arc<ChatEncryptionLayer>,
+    )> {
+        self.opened
+            .get_or_try_init(|| async {
+                let pool = r2d2::Pool::builder()
+                    .max_size(4)
+                    .build_unchecked(r2d2_sqlite::SqliteConnectionManager::file(&self.db_path));
+                let layer = ChatEncryptionLayer::new(Arc::new(security::KeyManager::new()?))?;
+                Ok::<_, anyhow::Error>((pool, Arc::new(layer)))
+            })
+            .await
+            .cloned()
+    }
 }
 
-#[tauri::command]
-async fn get_system_summary(state: State<'_, AppState>) -> Result<String, String> {
-    let mut detector = state.hardware_detector.write().await;
-    let hardware = detector.detect_hardware().map_err(|e| e.to_string())?;
-    Ok(detector.get_system_summary(&hardware))
+// Earlier user and assistant turns of a chat session, decrypted, oldest
+// first; fails when the session belongs to another user
+async fn load_chat_turns(
+    store: &ChatStore,
+    session_id: &str,
+    user_id: &str,
+) -> anyhow::Result<Vec<chat_template::ChatTurn>> {
+    let (pool, layer) = store.open().await?;
+    let session_id = session_id.to_string();
+    let user_id = user_id.to_string();
+    tokio::task::spawn_blocking(move || {
+        let conn = pool.get()?;
+        let messages = layer.retrieve_user_session_messages(&conn, &session_id, &user_id)?;
+        Ok(messages
+            .iter()
+            .filter_map(|message| {
+                let content = message["content"].as_str()?;
+                // Unreadable messages are left out rather than sent as placeholders
+                if content.starts_with("[DECRYPTION FAILED") {
+                    return None;
+                }
+                match message["role"].as_str()? {
+                    "user" => Some(chat_template::ChatTurn::user(content)),
+                    "assistant" => Some(chat_template::ChatTurn::assistant(content)),
+                    _ => None,
+                }
+            })
+            .collect())
+    })
+    .await?
 }
 
-#[tauri::command]
-async fn estimate_model_performance(
-    state: State<'_, AppState>,
-    model_size_gb: f64,
-) -> Result<String, String> {
-    let mut detector = state.hardware_detector.write().await;
-    let hardware = detector.detect_hardware().map_err(|e| e.to_string())?;
-    Ok(detector.estimate_model_performance(&hardware, model_size_gb))
+// Append (role, content) messages to a chat session, encrypted, returning their ids
+async fn store_chat_messages(
+    store: &ChatStore,
+    session_id: &str,
+    user_id: &str,
+    messages: Vec<(&'static str, String)>,
+) -> anyhow::Result<Vec<i64>> {
+    let (pool, layer) = store.open().await?;
+    let session_id = session_id.to_string();
+    let user_id = user_id.to_string();
+    tokio::task::spawn_blocking(move || {
+        let conn = pool.get()?;
+        messages
+            .into_iter()
+            .map(|(role, content)| {
+                layer.store_encrypted_message(&conn, &session_id, role, &content, &user_id, None)
+            })
+            .collect()
+    })
+    .await?
 }
 
-// Enhanced search using new RAG engine
-#[tauri::command]
-async fn search_knowledge_base(
-    state: State<'_, AppState>,
-    query: String,
-    limit: usize,
-) -> Result<Vec<serde_json::Value>, String> {
-    let detector = state.pii_detector.read().await;
-    let cleaned_query = detector
-        .redact_pii(&query)
-        .await
-        .map_err(|e| e.to_string())?;
+// Processed documents and their PII detections recorded in the app database,
+// with content and replacement text encrypted when field encryption is on.
+// The setting is saved next to the database and survives restarts.
+struct DocumentStore {
+    db_path: PathBuf,
+    config_path: PathBuf,
+    layer: tokio::sync::OnceCell<Arc<FieldEncryptionLayer>>,
+}
 
-    let rag = state.rag_engine.read().await;
-    let results = rag
-        .search(&cleaned_query, Some(limit))
-        .await
-        .map_err(|e| e.to_string())?;
+impl DocumentStore {
+    fn new(db_path: PathBuf, app_data_dir: &std::path::Path) -> Self {
+        Self {
+            db_path,
+            config_path: app_data_dir.join("field_encryption.json"),
+            layer: tokio::sync::OnceCell::new(),
+        }
+    }
 
-    // Convert to JSON
-    let json_results = results
-        .into_iter()
-        .map(|r| {
-            serde_json::json!({
-                "document_id": r.document_id,
-                "content": r.content,
-                "score": r.score,
-                "metadata": r.metadata
+    async fn layer(&self) -> anyhow::Result<Arc<FieldEncryptionLayer>> {
+        self.layer
+            .get_or_try_init(|| async {
+                let config = FieldEncryptionConfig::load(&self.config_path);
+                let key_manager = Arc::new(security::KeyManager::new()?);
+                Ok::<_, anyhow::Error>(Arc::new(FieldEncryptionLayer::new(key_manager, config)))
             })
+            .await
+            .cloned()
+    }
+
+    fn config(&self) -> FieldEncryptionConfig {
+        FieldEncryptionConfig::load(&self.config_path)
+    }
+
+    async fn set_config(&self, config: FieldEncryptionConfig) -> anyhow::Result<()> {
+        config.save(&self.config_path)?;
+        if let Some(layer) = self.layer.get() {
+            layer.set_config(config);
+        }
+        Ok(())
+    }
+
+    // Insert a document with the detections that were redacted from it
+    async fn record(
+        &self,
+        filename: String,
+        content: String,
+        file_type: String,
+        user_id: String,
+        detections: Vec<export_engine::PIIDetection>,
+    ) -> anyhow::Result<i64> {
+        let layer = self.layer().await?;
+        let db_path = self.db_path.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut conn = rusqlite::Connection::open(&db_path)?;
+            FieldEncryptionLayer::ensure_tables(&conn)?;
+            let tx = conn.transaction()?;
+            let document_id =
+                layer.insert_document(&tx, &filename, &content, &file_type, &user_id)?;
+            for detection in &detections {
+                layer.insert_pii_detection(&tx, document_id, detection, &user_id)?;
+            }
+            tx.commit()?;
+            Ok(document_id)
         })
-        .collect();
+        .await?
+    }
 
-    Ok(json_results)
+    async fn migrate(&self) -> anyhow::Result<FieldMigrationStats> {
+        let layer = self.layer().await?;
+        let db_path = self.db_path.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut conn = rusqlite::Connection::open(&db_path)?;
+            FieldEncryptionLayer::ensure_tables(&conn)?;
+            layer.migrate_existing_rows(&mut conn)
+        })
+        .await?
+    }
 }
 
-// Add document to new RAG engine
+// Store the settings a reply was generated with, as returned by the generate
+// call, with its chat message if recording is enabled
+async fn record_generation(
+    state: &AppState,
+    message_id: i64,
+    model_name: &str,
+    prompt: &str,
+    config: &llm_manager::GenerationConfig,
+) {
+    let recorder = state.generation_records.read().await;
+    if !recorder.get_config().enabled {
+        return;
+    }
+
+    let tokenizer = state.llm_manager.read().await.tokenizer_handle();
+    let counter = text_segmentation::LoadedTokenizerCounter::new(tokenizer);
+    let record =
+        generation_records::GenerationRecord::capture(model_name, config, prompt, &counter);
+    let attached = match state.chat_store.open().await {
+        Ok((_, layer)) => recorder.attach(&layer, message_id, &record),
+        Err(e) => Err(e),
+    };
+    if let Err(e) = attached {
+        tracing::warn!(message_id, error = %e, "Failed to store generation record");
+    }
+}
+
+// Generation parameters recorded with one of the user's chat messages, if any
 #[tauri::command]
-async fn add_to_knowledge_base(
+async fn get_generation_record(
     state: State<'_, AppState>,
-    content: String,
-    metadata: serde_json::Value,
-) -> Result<String, String> {
-    let detector = state.pii_detector.read().await;
-    let cleaned_content = detector
-        .redact_pii(&content)
-        .await
-        .map_err(|e| e.to_string())?;
-
-    let rag = state.rag_engine.write().await;
-    rag.add_document(&cleaned_content, metadata)
+    message_id: i64,
+    user_id: Option<String>,
+) -> Result<Option<generation_records::GenerationRecord>, String> {
+    let user_id = user_id.unwrap_or_else(|| "default_user".to_string());
+    let (_, layer) = state.chat_store.open().await.map_err(|e| e.to_string())?;
+    state
+        .generation_records
+        .read()
         .await
+        .load(&layer, message_id, &user_id)
         .map_err(|e| e.to_string())
 }
 
-// List models using new LLM manager
 #[tauri::command]
-async fn list_available_models(state: State<'_, AppState>) -> Result<Vec<String>, String> {
-    let llm = state.llm_manager.read().await;
-    let models = llm.list_models().await;
-    Ok(models.into_iter().map(|(name, _, _)| name).collect())
+async fn get_generation_record_config(
+    state: State<'_, AppState>,
+) -> Result<generation_records::GenerationRecordConfig, String> {
+    Ok(state.generation_records.read().await.get_config())
 }
 
-// Download model using new LLM manager
 #[tauri::command]
-async fn download_model(state: State<'_, AppState>, model_name: String) -> Result<String, String> {
-    // Scope the lock so it doesn't cross await boundaries
+async fn set_generation_record_config(
+    state: State<'_, AppState>,
+    config: generation_records::GenerationRecordConfig,
+) -> Result<(), String> {
+    state
+        .generation_records
+        .write()
+        .await
+        .set_config(config)
+        .map_err(|e| e.to_string())
+}
+
+// Title a new chat session after its first exchange; a failure only costs the title
+async fn title_chat_session(
+    state: &AppState,
+    session_id: &str,
+    user_message: &str,
+    assistant_reply: &str,
+) {
+    let llm = state.llm_manager.read().await;
+    let detector = state.pii_detector.read().await;
+    let titles = state.chat_titles.read().await;
+    if let Err(e) = titles
+        .title_after_exchange(
+            session_id,
+            user_message,
+            assistant_reply,
+            Some(&*llm),
+            &detector,
+        )
+        .await
     {
-        let llm = state.llm_manager.read().await;
-        llm.ensure_model_ready(&model_name)
-            .await
-            .map_err(|e| e.to_string())?;
-    } // llm dropped here
+        tracing::warn!(session_id, error = %e, "Failed to title chat session");
+    }
+}
 
-    Ok(format!("Model {} is ready", model_name))
+// Retitle a chat session from its first exchange, read decrypted from the chat
+// store, using the loaded model if any; fails unless the session is the user's
+#[tauri::command]
+async fn regenerate_chat_title(
+    state: State<'_, AppState>,
+    session_id: String,
+    user_id: Option<String>,
+) -> Result<String, String> {
+    let user_id = user_id.unwrap_or_else(|| "default_user".to_string());
+    let turns = load_chat_turns(&state.chat_store, &session_id, &user_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    let llm = state.llm_manager.read().await;
+    let model_loaded = llm.is_model_loaded().await.unwrap_or(false);
+    let model: Option<&dyn completion::CompletionModel> =
+        if model_loaded { Some(&*llm) } else { None };
+    let detector = state.pii_detector.read().await;
+    let titles = state.chat_titles.read().await;
+    titles
+        .regenerate_title(&session_id, &turns, model, &detector)
+        .await
+        .map_err(|e| e.to_string())
 }
 
-// Database commands
 #[tauri::command]
-async fn execute_sql_query(
+async fn get_chat_title_config(
     state: State<'_, AppState>,
-    query: String,
-) -> Result<serde_json::Value, String> {
-    let db = state.database_manager.read().await;
-    db.execute_sql_query(&query).map_err(|e| e.to_string())
+) -> Result<chat_titles::ChatTitleConfig, String> {
+    Ok(state.chat_titles.read().await.get_config())
 }
 
-// Enhanced RAG search with agentic capabilities
 #[tauri::command]
-async fn rag_search(
+async fn set_chat_title_config(
     state: State<'_, AppState>,
-    query: String,
-    _use_agentic: bool,
-    max_results: usize,
-) -> Result<serde_json::Value, String> {
-    let detector = state.pii_detector.read().await;
-    let cleaned_query = detector
-        .redact_pii(&query)
+    config: chat_titles::ChatTitleConfig,
+) -> Result<(), String> {
+    state
+        .chat_titles
+        .write()
         .await
-        .map_err(|e| e.to_string())?;
+        .set_config(config)
+        .map_err(|e| e.to_string())
+}
 
-    let rag = state.rag_engine.read().await;
+// Answer from the knowledge base, attributing each sentence to its supporting source
+#[tauri::command]
+async fn send_message_grounded(
+    state: State<'_, AppState>,
+    message: String,
+    model_name: String,
+    limit: Option<usize>,
+    message_id: Option<i64>,
+) -> Result<serde_json::Value, SendMessageError> {
+    let cleaned_message = {
+        let detector = state.pii_detector.read().await;
+        detector
+            .redact_pii(&message, None)
+            .await
+            .map_err(|e| e.to_string())?
+    };
+
+    let (prompt, sources) = {
+        let rag = state.rag_engine.read().await;
+        rag.build_grounded_prompt(&cleaned_message, limit)
+            .await
+            .map_err(|e| e.to_string())?
+    };
+
+    let result = {
+        let llm = state.llm_manager.read().await;
+        match llm.ensure_model_ready(&model_name).await {
+            Ok(()) => llm.generate(&prompt, None).await,
+            Err(e) => Err(e),
+        }
+    };
+    let answer = match result {
+        Ok(result) => {
+            if let Some(message_id) = message_id {
+                record_generation(&state, message_id, &model_name, &prompt, &result.config).await;
+            }
+            result.text
+        }
+        Err(e) => return Err(generation_failure(&state, &model_name, &e).await),
+    };
+
+    let rag = state.rag_engine.read().await;
+    let attributions = if rag.get_config().await.sentence_attribution {
+        Some(
+            rag.attribute_sentences(&answer, &sources)
+                .await
+                .map_err(|e| e.to_string())?,
+        )
+    } else {
+        None
+    };
+
+    Ok(serde_json::json!({
+        "answer": answer,
+        "sources": sources,
+        "attributions": attributions
+    }))
+}
+
+// Audit a failed generation and build the error returned to the UI
+async fn generation_failure(
+    state: &AppState,
+    model_name: &str,
+    error: &anyhow::Error,
+) -> SendMessageError {
+    let config = state.fallback_config.read().await.clone();
+    let fallback = GenerationFallback::from_error(error, &config);
+    tracing::error!(
+        model = %model_name,
+        category = fallback.category.as_str(),
+        error = %error,
+        "Generation failed"
+    );
+
+    {
+        let audit = state.compliance_manager.audit();
+        let audit = audit.read().await;
+        if let Err(e) = audit.log_failure(
+            "default_user",
+            AuditAction::GenerationFailed,
+            EntityType::ChatMessage,
+            Some(model_name),
+            &format!("{}: {}", fallback.category.as_str(), error),
+        ) {
+            tracing::warn!("Failed to audit generation failure: {}", e);
+        }
+    }
+
+    if config.enabled {
+        SendMessageError::Fallback(fallback)
+    } else {
+        SendMessageError::Message(error.to_string())
+    }
+}
+
+// Get the per-user and global request limits
+#[tauri::command]
+async fn get_rate_limit_config(state: State<'_, AppState>) -> Result<RateLimitConfig, String> {
+    Ok(state.rate_limiter.get_config())
+}
+
+// Update the per-user and global request limits
+#[tauri::command]
+async fn set_rate_limit_config(
+    state: State<'_, AppState>,
+    config: RateLimitConfig,
+) -> Result<RateLimitConfig, String> {
+    state
+        .rate_limiter
+        .update_config(config)
+        .map_err(|e| e.to_string())?;
+    Ok(state.rate_limiter.get_config())
+}
+
+// Limit actions whose key starts with `prefix` (e.g. "upload_document") per user;
+// the limit is saved and applies again after a restart
+#[tauri::command]
+async fn configure_rate_limit(
+    state: State<'_, AppState>,
+    prefix: String,
+    capacity: usize,
+    window_seconds: u64,
+) -> Result<RateLimitConfig, String> {
+    state
+        .rate_limiter
+        .configure(&prefix, capacity, Duration::from_secs(window_seconds))
+        .map_err(|e| e.to_string())?;
+    Ok(state.rate_limiter.get_config())
+}
+
+// Get how much of a user's request allowance is left in the current window
+#[tauri::command]
+async fn get_rate_limit_usage(
+    state: State<'_, AppState>,
+    user_id: Option<String>,
+) -> Result<RateLimitUsage, String> {
+    let user_id = user_id.unwrap_or_else(|| "default_user".to_string());
+    Ok(state.rate_limiter.get_usage(&user_id))
+}
+
+// Configure the fallback returned when generation fails
+#[tauri::command]
+async fn set_generation_fallback(
+    state: State<'_, AppState>,
+    enabled: bool,
+    include_error_detail: Option<bool>,
+) -> Result<FallbackConfig, String> {
+    let mut config = state.fallback_config.write().await;
+    config.enabled = enabled;
+    if let Some(include) = include_error_detail {
+        config.include_error_detail = include;
+    }
+    Ok(config.clone())
+}
+
+// Stream a response as `llm-token` events, redacting PII across token
+// boundaries before it reaches the UI, then emit `llm-done` with timing stats
+#[tauri::command]
+async fn send_message_stream(
+    window: tauri::Window,
+    state: State<'_, AppState>,
+    message: String,
+    model_name: String,
+    user_id: Option<String>,
+    message_id: Option<i64>,
+) -> Result<String, String> {
+    let user_id = user_id.unwrap_or_else(|| "default_user".to_string());
+    state
+        .rate_limiter
+        .check_rate_limit(&user_id, "send_message_stream")
+        .map_err(|e| e.to_string())?;
+    ensure_hardware_safe(&state, "send_message_stream").await?;
+
+    let cleaned_message = {
+        let detector = state.pii_detector.read().await;
+        detector
+            .redact_pii(&message, None)
+            .await
+            .map_err(|e| e.to_string())?
+    };
+
+    // Generation callbacks are synchronous, so tokens are handed to an async
+    // task that runs the streaming redactor and emits only confirmed-safe text
+    let (token_tx, mut token_rx) = tokio::sync::mpsc::unbounded_channel::<String>();
+    let detector = state.pii_detector.clone();
+    let emit_window = window.clone();
+    let redaction = tokio::spawn(async move {
+        let detector = detector.read().await;
+        let mut redactor = StreamingRedactor::for_detector(&detector).await;
+        let mut output = String::new();
+
+        while let Some(token) = token_rx.recv().await {
+            let chunk = redactor.push(&detector, &token).await?;
+            if !chunk.is_empty() {
+                let _ = emit_window.emit("llm-token", &chunk);
+                output.push_str(&chunk);
+            }
+        }
+
+        let tail = redactor.finish(&detector).await?;
+        if !tail.is_empty() {
+            let _ = emit_window.emit("llm-token", &tail);
+            output.push_str(&tail);
+        }
+        Ok::<String, anyhow::Error>(output)
+    });
+
+    let cancel = state.message_stream_cancel.clone();
+    cancel.store(false, std::sync::atomic::Ordering::SeqCst);
+    let (prompt, result) = {
+        let llm = state.llm_manager.read().await;
+        llm.ensure_model_ready(&model_name)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let chat = [chat_template::ChatTurn::user(cleaned_message.as_str())];
+        let prompt = llm.format_prompt(None, &chat).await;
+        let cancel = cancel.clone();
+        let result = llm
+            .generate_stream(&prompt, None, move |token| {
+                !cancel.load(std::sync::atomic::Ordering::SeqCst)
+                    && token_tx.send(token.to_string()).is_ok()
+            })
+            .await
+            .map_err(|e| e.to_string())?;
+        (prompt, result)
+    }; // token sender dropped here, letting the redactor flush
+
+    let output = redaction
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+    let _ = window.emit(
+        "llm-done",
+        serde_json::json!({
+            "text": output,
+            "tokens_generated": result.tokens_generated,
+            "time_ms": result.time_ms,
+            "tokens_per_second": result.tokens_per_second,
+            "cancelled": cancel.load(std::sync::atomic::Ordering::SeqCst)
+        }),
+    );
+    if let Some(message_id) = message_id {
+        record_generation(&state, message_id, &model_name, &prompt, &result.config).await;
+    }
+
+    Ok(output)
+}
+
+// Emit each new audit log entry as an `audit-event`, replacing any earlier
+// subscription; details and error messages are never included
+#[tauri::command]
+async fn subscribe_audit_events(
+    window: tauri::Window,
+    state: State<'_, AppState>,
+    filter: Option<compliance::audit::AuditEventFilter>,
+) -> Result<(), String> {
+    let filter = filter.unwrap_or_default();
+    let mut events = compliance::audit::subscribe_audit_events();
+    let tail = tauri::async_runtime::spawn(async move {
+        loop {
+            match events.recv().await {
+                Ok(event) if filter.matches(&event) => {
+                    let _ = window.emit("audit-event", &event);
+                }
+                Ok(_) => {}
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!(skipped, "Audit event subscriber fell behind");
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+
+    if let Some(previous) = state.audit_tail.lock().await.replace(tail) {
+        previous.abort();
+    }
+    Ok(())
+}
+
+// Stop emitting audit events; returns false if there was no subscription
+#[tauri::command]
+async fn unsubscribe_audit_events(state: State<'_, AppState>) -> Result<bool, String> {
+    match state.audit_tail.lock().await.take() {
+        Some(tail) => {
+            tail.abort();
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+// Stop the in-progress send_message_stream; tokens already emitted are kept
+#[tauri::command]
+async fn cancel_message_stream(state: State<'_, AppState>) -> Result<(), String> {
+    state
+        .message_stream_cancel
+        .store(true, std::sync::atomic::Ordering::SeqCst);
+    Ok(())
+}
+
+// Run a multi-step tool-using agent task, emitting each step as it completes
+#[tauri::command]
+async fn run_agent_task(
+    window: tauri::Window,
+    state: State<'_, AppState>,
+    task: String,
+    context: Option<String>,
+    model_name: String,
+    user_id: Option<String>,
+) -> Result<mcp_server::AgentRun, String> {
+    let user_id = user_id.unwrap_or_else(|| "default_user".to_string());
+    state
+        .rate_limiter
+        .check_rate_limit(&user_id, "run_agent_task")
+        .map_err(|e| e.to_string())?;
+
+    let (task, context) = {
+        let detector = state.pii_detector.read().await;
+        let task = detector
+            .redact_pii(&task, None)
+            .await
+            .map_err(|e| e.to_string())?;
+        let context = detector
+            .redact_pii(context.as_deref().unwrap_or(""), None)
+            .await
+            .map_err(|e| e.to_string())?;
+        (task, context)
+    };
+
+    let llm = state.llm_manager.read().await;
+    llm.ensure_model_ready(&model_name)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    state
+        .agent_orchestrator
+        .execute_agent_task(&*llm, &user_id, &task, &context, |step| {
+            let _ = window.emit("agent-step", step);
+        })
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// Extract data conforming to a JSON schema, retrying with validation errors.
+// The document is not redacted: the parties and dates are what is being asked for,
+// and it never leaves the local model.
+#[tauri::command]
+async fn extract_structured(
+    state: State<'_, AppState>,
+    content: String,
+    schema: serde_json::Value,
+    instructions: String,
+) -> Result<structured_extraction::StructuredExtraction, String> {
+    ensure_hardware_safe(&state, "extract_structured").await?;
+
+    let llm = state.llm_manager.read().await;
+    if !llm.is_model_loaded().await.unwrap_or(false) {
+        return Err("No model loaded. Load a model to extract structured data.".to_string());
+    }
+    structured_extraction::extract_structured(
+        &*llm,
+        &content,
+        &schema,
+        &instructions,
+        constants::STRUCTURED_EXTRACTION_MAX_RETRIES,
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+// Stream a long generation straight to a file, redacting PII on the way out
+#[tauri::command]
+async fn generate_to_file(
+    state: State<'_, AppState>,
+    prompt: String,
+    output_path: String,
+    model_name: String,
+    gen_config: Option<llm_manager::GenerationConfig>,
+) -> Result<file_generation::FileGenerationResult, String> {
+    let output_path = state
+        .file_processor
+        .validate_output_path(&output_path)
+        .map_err(|e| e.to_string())?;
+
+    let cleaned_prompt = {
+        let detector = state.pii_detector.read().await;
+        detector
+            .redact_pii(&prompt, None)
+            .await
+            .map_err(|e| e.to_string())?
+    };
+
+    state
+        .file_generation_cancel
+        .store(false, std::sync::atomic::Ordering::SeqCst);
+
+    let llm = state.llm_manager.read().await;
+    llm.ensure_model_ready(&model_name)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    file_generation::generate_to_file(
+        &*llm,
+        state.pii_detector.clone(),
+        &cleaned_prompt,
+        &output_path,
+        gen_config,
+        state.file_generation_cancel.clone(),
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+// Stop the in-progress generate_to_file; its output is kept as a .partial file
+#[tauri::command]
+async fn cancel_generate_to_file(state: State<'_, AppState>) -> Result<(), String> {
+    state
+        .file_generation_cancel
+        .store(true, std::sync::atomic::Ordering::SeqCst);
+    Ok(())
+}
+
+// Stop every subsystem cleanly; None if shutdown already ran
+async fn shutdown_app(state: &AppState, db_path: &Path) -> Option<shutdown::ShutdownReport> {
+    if state
+        .shutting_down
+        .swap(true, std::sync::atomic::Ordering::SeqCst)
+    {
+        return None;
+    }
+    state
+        .file_generation_cancel
+        .store(true, std::sync::atomic::Ordering::SeqCst);
+
+    let scheduler = match &state.scheduler_handle {
+        Some(handle) => Some(handle.read().await.clone()),
+        None => None,
+    };
+    let llm = state.llm_manager.read().await;
+    Some(
+        shutdown::shutdown_subsystems(
+            &*llm,
+            scheduler.as_ref(),
+            db_path,
+            Duration::from_secs(constants::SHUTDOWN_STEP_TIMEOUT_SECS),
+        )
+        .await,
+    )
+}
+
+// Lists what factory_reset would delete, including legal hold warnings, and
+// issues the one-time token it must be confirmed with
+#[tauri::command]
+async fn prepare_factory_reset(
+    state: State<'_, AppState>,
+    db_path: State<'_, PathBuf>,
+) -> Result<factory_reset::FactoryResetPreview, String> {
+    factory_reset_plan(&state, &db_path)
+        .preview(&state.reset_confirmation)
+        .map_err(|e| e.to_string())
+}
+
+// Deletes models, the knowledge base and the database and resets all settings;
+// irreversible, so only runs with the token from prepare_factory_reset
+#[tauri::command]
+async fn factory_reset(
+    state: State<'_, AppState>,
+    db_path: State<'_, PathBuf>,
+    transparency: State<'_, commands::ModelTransparencyState>,
+    confirm_token: String,
+) -> Result<factory_reset::FactoryResetReport, String> {
+    let mut report = factory_reset_plan(&state, &db_path)
+        .run(&state.reset_confirmation, &confirm_token, "system")
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // Settings held only in memory or beside the database
+    if let Err(e) = transparency.reset_settings().await {
+        report.failures.insert("model_settings".to_string(), e);
+    }
+    *state.ensemble_config.write().await = ensemble::EnsembleConfig::default();
+    *state.fallback_config.write().await = FallbackConfig::default();
+
+    tracing::warn!(failures = report.failures.len(), "Factory reset completed");
+    Ok(report)
+}
+
+fn factory_reset_plan(state: &AppState, db_path: &Path) -> factory_reset::FactoryReset {
+    factory_reset::FactoryReset::new(db_path.to_path_buf())
+        .with_store(state.llm_manager.clone())
+        .with_store(state.rag_engine.clone())
+        .with_store(state.pii_detector.clone())
+        .with_store(state.rate_limiter.clone())
+}
+
+// Gracefully stop generations, the scheduler, the database and the model before exit
+#[tauri::command]
+async fn shutdown_application(
+    state: State<'_, AppState>,
+    db_path: State<'_, PathBuf>,
+) -> Result<shutdown::ShutdownReport, String> {
+    shutdown_app(&state, &db_path)
+        .await
+        .ok_or_else(|| "Shutdown already in progress".to_string())
+}
+
+// Configure the second-opinion ensemble used for high-risk queries
+#[tauri::command]
+async fn set_ensemble_mode(
+    state: State<'_, AppState>,
+    enabled: bool,
+    primary_model: Option<String>,
+    secondary_model: Option<String>,
+    agreement_threshold: Option<f32>,
+) -> Result<ensemble::EnsembleConfig, String> {
+    let mut config = state.ensemble_config.write().await;
+    config.enabled = enabled;
+    if let Some(model) = primary_model {
+        config.primary_model = model;
+    }
+    if let Some(model) = secondary_model {
+        config.secondary_model = model;
+    }
+    if let Some(threshold) = agreement_threshold {
+        config.agreement_threshold = threshold.clamp(0.0, 1.0);
+    }
+    Ok(config.clone())
+}
+
+// Generate a response, running both ensemble models when the query is high-risk
+#[tauri::command]
+async fn send_message_with_second_opinion(
+    state: State<'_, AppState>,
+    message: String,
+    model_name: String,
+    risk_level: ai_transparency::RiskLevel,
+) -> Result<ensemble::EnsembleResponse, String> {
+    let cleaned_message = {
+        let detector = state.pii_detector.read().await;
+        detector
+            .redact_pii(&message, None)
+            .await
+            .map_err(|e| e.to_string())?
+    };
+
+    let config = state.ensemble_config.read().await.clone();
+    let backend = ensemble::LocalEnsembleBackend {
+        llm_manager: state.llm_manager.clone(),
+        rag_engine: state.rag_engine.clone(),
+    };
+
+    ensemble::run_ensemble(&backend, &config, risk_level, &model_name, &cleaned_message)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn detect_hardware(state: State<'_, AppState>) -> Result<HardwareSpecs, String> {
+    probe_hardware(&state).await
+}
+
+// Current hardware specs, probed on a blocking thread since GPU detection
+// waits on driver calls
+async fn probe_hardware(state: &AppState) -> Result<HardwareSpecs, String> {
+    let mut detector = state.hardware_detector.clone().write_owned().await;
+    tokio::task::spawn_blocking(move || detector.detect_hardware())
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_model_recommendations(
+    state: State<'_, AppState>,
+) -> Result<Vec<ModelRecommendation>, String> {
+    let hardware = probe_hardware(&state).await?;
+    let detector = state.hardware_detector.read().await;
+    Ok(detector.recommend_models(&hardware))
+}
+
+#[tauri::command]
+async fn get_system_summary(state: State<'_, AppState>) -> Result<String, String> {
+    let hardware = probe_hardware(&state).await?;
+    let detector = state.hardware_detector.read().await;
+    Ok(detector.get_system_summary(&hardware))
+}
+
+#[tauri::command]
+async fn estimate_model_performance(
+    state: State<'_, AppState>,
+    model_size_gb: f64,
+) -> Result<String, String> {
+    let hardware = probe_hardware(&state).await?;
+    let detector = state.hardware_detector.read().await;
+    Ok(detector.estimate_model_performance(&hardware, model_size_gb))
+}
+
+// Paraphrases for expanded retrieval, by the loaded model when there is one;
+// the model lock is released before retrieval starts
+async fn query_paraphrases(
+    state: &AppState,
+    rag: &rag_engine::RAGEngine,
+    query: &str,
+) -> Vec<String> {
+    let llm = state.llm_manager.read().await;
+    let model_loaded = llm.is_model_loaded().await.unwrap_or(false);
+    let paraphraser: Option<&dyn completion::CompletionModel> =
+        if model_loaded { Some(&*llm) } else { None };
+    rag.expansion_paraphrases(query, paraphraser).await
+}
+
+// Enhanced search using new RAG engine
+#[tauri::command]
+async fn search_knowledge_base(
+    state: State<'_, AppState>,
+    query: String,
+    limit: usize,
+) -> Result<Vec<serde_json::Value>, String> {
+    let detector = state.pii_detector.read().await;
+    let cleaned_query = detector
+        .redact_pii(&query, None)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let rag = state.rag_engine.read().await;
+    let paraphrases = query_paraphrases(&state, &rag, &cleaned_query).await;
+    let results = rag
+        .search_paraphrased(&cleaned_query, &paraphrases, Some(limit), None)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // Convert to JSON
+    let json_results = results
+        .into_iter()
+        .map(|r| {
+            serde_json::json!({
+                "document_id": r.document_id,
+                "content": r.content,
+                "score": r.score,
+                "fused_score": r.fused_score,
+                "metadata": r.metadata,
+                "provenance": r.provenance
+            })
+        })
+        .collect();
+
+    Ok(json_results)
+}
+
+// How an indexed chunk was derived: source hash, extraction, redaction, embedding model
+#[tauri::command]
+async fn get_chunk_provenance(
+    state: State<'_, AppState>,
+    chunk_id: String,
+) -> Result<rag_engine::ChunkProvenance, String> {
+    let rag = state.rag_engine.read().await;
+    rag.chunk_provenance(&chunk_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// Add document to new RAG engine
+#[tauri::command]
+async fn add_to_knowledge_base(
+    state: State<'_, AppState>,
+    content: String,
+    metadata: serde_json::Value,
+) -> Result<String, String> {
+    let detector = state.pii_detector.read().await;
+    let cleaned_content = detector
+        .redact_pii(&content, None)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let rag = state.rag_engine.write().await;
+    rag.add_document(&cleaned_content, metadata)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// Re-chunk and re-embed an indexed document with new content and metadata
+#[tauri::command]
+async fn update_knowledge_base_document(
+    state: State<'_, AppState>,
+    document_id: String,
+    content: String,
+    metadata: serde_json::Value,
+) -> Result<usize, String> {
+    let detector = state.pii_detector.read().await;
+    let cleaned_content = detector
+        .redact_pii(&content, None)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let rag = state.rag_engine.write().await;
+    rag.update_document(&document_id, &cleaned_content, metadata)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// Remove a document's chunks and embeddings from the index, returning how many were removed
+#[tauri::command]
+async fn delete_knowledge_base_document(
+    state: State<'_, AppState>,
+    document_id: String,
+) -> Result<usize, String> {
+    let rag = state.rag_engine.read().await;
+    let removed = rag.document_chunk_count(&document_id).await;
+    rag.delete_document(&document_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(removed)
+}
+
+// Change how strongly a document's chunks rank in search results
+#[tauri::command]
+async fn set_document_trust(
+    state: State<'_, AppState>,
+    document_id: String,
+    trust_weight: f32,
+) -> Result<usize, String> {
+    let rag = state.rag_engine.read().await;
+    rag.set_document_trust(&document_id, trust_weight)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// Replace a prior document version after reviewing its change report,
+// removing the old version's chunks from the index
+#[tauri::command]
+async fn replace_previous_document_version(
+    state: State<'_, AppState>,
+    previous_rag_document_id: String,
+) -> Result<usize, String> {
+    let rag = state.rag_engine.read().await;
+    let removed = rag.document_chunk_count(&previous_rag_document_id).await;
+    rag.delete_document(&previous_rag_document_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(removed)
+}
+
+// Re-embed the index with a new dimension reduction and report memory saved
+#[tauri::command]
+async fn set_rag_dimension_reduction(
+    state: State<'_, AppState>,
+    reduction: embedding_projection::DimensionReduction,
+    normalize: bool,
+) -> Result<rag_engine::DimensionReductionReport, String> {
+    let rag = state.rag_engine.read().await;
+    rag.set_dimension_reduction(reduction, normalize)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// List models using new LLM manager
+#[tauri::command]
+async fn list_available_models(state: State<'_, AppState>) -> Result<Vec<String>, String> {
+    let llm = state.llm_manager.read().await;
+    let models = llm.list_models().await;
+    Ok(models.into_iter().map(|(name, _, _)| name).collect())
+}
+
+// Download model using new LLM manager
+#[tauri::command]
+async fn download_model(state: State<'_, AppState>, model_name: String) -> Result<String, String> {
+    // Scope the lock so it doesn't cross await boundaries
+    {
+        let llm = state.llm_manager.read().await;
+        llm.ensure_model_ready(&model_name)
+            .await
+            .map_err(|e| e.to_string())?;
+    } // llm dropped here
+
+    Ok(format!("Model {} is ready", model_name))
+}
+
+// First-run downloads: the smallest usable model is downloaded and loaded
+// before this returns, so chat works at once; larger models the hardware can
+// run follow in the background. Stages are emitted as first-run-download.
+#[tauri::command]
+async fn start_progressive_download(
+    state: State<'_, AppState>,
+    window: tauri::Window,
+) -> Result<progressive_download::DownloadPlan, String> {
+    use progressive_download::DownloadStage;
+
+    let setup_config = state.setup_manager.read().await.get_config().await;
+    let hardware = probe_hardware(&state).await?;
+    let resources = llm_manager::SystemResources {
+        available_ram_mb: hardware.available_memory,
+        free_vram_mb: hardware.gpu_info.map(|gpu| gpu.memory_free),
+    };
+
+    // A handle rather than the guard, so loads and unloads are not blocked
+    // behind the starter download
+    let llm = state.llm_manager.read().await.clone();
+    let mut candidates = Vec::new();
+    for (name, config, _) in llm.list_models().await {
+        let feasibility = llm
+            .assess_model_feasibility(&name, &resources)
+            .await
+            .map_err(|e| e.to_string())?;
+        candidates.push((config, feasibility));
+    }
+    let max_background = if setup_config.download_recommended_models {
+        setup_config.max_background_models
+    } else {
+        0
+    };
+    let plan = progressive_download::plan_downloads(&candidates, max_background)
+        .ok_or_else(|| "No model fits this machine's free disk space and memory".to_string())?;
+
+    let emit = emit_first_run_stage;
+    for (position, name) in plan.queue().into_iter().enumerate() {
+        emit(&window, name, position == 0, DownloadStage::Queued, None);
+    }
+
+    emit(&window, &plan.starter, true, DownloadStage::Started, None);
+    if let Err(e) = llm.ensure_model_ready(&plan.starter).await {
+        let error = e.to_string();
+        emit(&window, &plan.starter, true, DownloadStage::Failed, Some(error.clone()));
+        return Err(error);
+    }
+    emit(&window, &plan.starter, true, DownloadStage::Ready, None);
+
+    let background = plan.background.clone();
+    tokio::spawn(async move {
+        for name in background {
+            emit(&window, &name, false, DownloadStage::Started, None);
+            let result = llm.ensure_model_downloaded(&name).await;
+            match result {
+                Ok(()) => emit(&window, &name, false, DownloadStage::Ready, None),
+                Err(e) => {
+                    tracing::warn!(model = %name, error = %e, "Background model download failed");
+                    let error = Some(e.to_string());
+                    emit(&window, &name, false, DownloadStage::Failed, error);
+                }
+            }
+        }
+    });
+
+    Ok(plan)
+}
+
+fn emit_first_run_stage(
+    window: &tauri::Window,
+    model_name: &str,
+    starter: bool,
+    stage: progressive_download::DownloadStage,
+    error: Option<String>,
+) {
+    let event = progressive_download::FirstRunDownloadEvent {
+        model_name: model_name.to_string(),
+        stage,
+        starter,
+        error,
+    };
+    let _ = window.emit(progressive_download::FIRST_RUN_DOWNLOAD_EVENT, &event);
+}
+
+// Concurrency limits for model downloads/loads, plus deduplication counters
+#[tauri::command]
+async fn get_model_load_limits(
+    state: State<'_, AppState>,
+) -> Result<llm_manager::ModelLoadLimits, String> {
+    let llm = state.llm_manager.read().await;
+    Ok(llm.get_model_load_limits().await)
+}
+
+#[tauri::command]
+async fn set_model_load_limits(
+    state: State<'_, AppState>,
+    limits: llm_manager::ModelLoadLimits,
+) -> Result<(), String> {
+    let llm = state.llm_manager.read().await;
+    llm.set_model_load_limits(limits)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_model_load_stats(
+    state: State<'_, AppState>,
+) -> Result<llm_manager::ModelLoadStats, String> {
+    let llm = state.llm_manager.read().await;
+    Ok(llm.get_model_load_stats())
+}
+
+// Idle-unload policy: free the model's memory after a period without generation
+#[tauri::command]
+async fn get_idle_unload_config(
+    state: State<'_, AppState>,
+) -> Result<llm_manager::IdleUnloadConfig, String> {
+    let llm = state.llm_manager.read().await;
+    Ok(llm.get_idle_unload_config().await)
+}
+
+#[tauri::command]
+async fn set_idle_unload_config(
+    state: State<'_, AppState>,
+    config: llm_manager::IdleUnloadConfig,
+) -> Result<(), String> {
+    let llm = state.llm_manager.read().await;
+    llm.set_idle_unload_config(config)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// Compare downloaded models with their latest Hugging Face revision
+#[tauri::command]
+async fn check_model_updates(
+    state: State<'_, AppState>,
+) -> Result<Vec<model_updates::ModelUpdateStatus>, String> {
+    let llm = state.llm_manager.read().await;
+    llm.check_model_updates().await.map_err(|e| e.to_string())
+}
+
+// Fetch the newer revision of a model, keeping the previous file as a backup
+#[tauri::command]
+async fn update_model(
+    state: State<'_, AppState>,
+    model_name: String,
+) -> Result<model_updates::ModelUpdateStatus, String> {
+    let llm = state.llm_manager.read().await;
+    llm.update_model(&model_name)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// Remove a downloaded model file; the active model must be unloaded first
+#[tauri::command]
+async fn delete_model(state: State<'_, AppState>, model_name: String) -> Result<(), String> {
+    let llm = state.llm_manager.read().await;
+    llm.delete_model(&model_name)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// Add a GGUF model from any Hugging Face repository to the managed registry
+#[tauri::command]
+async fn register_custom_model(
+    state: State<'_, AppState>,
+    config: llm_manager::ModelConfig,
+) -> Result<String, String> {
+    let name = config.name.clone();
+    let llm = state.llm_manager.read().await;
+    llm.register_model(config)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(format!("Model '{}' registered", name))
+}
+
+// Check a downloaded model file against its published size and SHA-256
+#[tauri::command]
+async fn verify_model(state: State<'_, AppState>, model_name: String) -> Result<bool, String> {
+    let llm = state.llm_manager.read().await;
+    llm.verify_model(&model_name)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_model_update_config(
+    state: State<'_, AppState>,
+) -> Result<model_updates::ModelUpdateConfig, String> {
+    let llm = state.llm_manager.read().await;
+    Ok(llm.get_model_update_config().await)
+}
+
+#[tauri::command]
+async fn set_model_update_config(
+    state: State<'_, AppState>,
+    config: model_updates::ModelUpdateConfig,
+) -> Result<(), String> {
+    let llm = state.llm_manager.read().await;
+    llm.set_model_update_config(config).await;
+    Ok(())
+}
+
+// Pre-validate free disk space before a model download
+#[tauri::command]
+async fn check_download_space(
+    state: State<'_, AppState>,
+    model_name: String,
+) -> Result<llm_manager::DiskSpaceCheck, String> {
+    let llm = state.llm_manager.read().await;
+    llm.check_download_space(&model_name)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// Set the free space (MB) that must remain after a model download
+#[tauri::command]
+async fn set_download_headroom(state: State<'_, AppState>, headroom_mb: u64) -> Result<(), String> {
+    let llm = state.llm_manager.read().await;
+    llm.set_download_headroom_mb(headroom_mb)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// Check disk, memory and GPU fit for a model before committing to a download
+#[tauri::command]
+async fn assess_model_feasibility(
+    state: State<'_, AppState>,
+    model_name: String,
+) -> Result<llm_manager::ModelFeasibility, String> {
+    let hardware = probe_hardware(&state).await?;
+    let resources = llm_manager::SystemResources {
+        available_ram_mb: hardware.available_memory,
+        free_vram_mb: hardware.gpu_info.map(|gpu| gpu.memory_free),
+    };
+
+    let llm = state.llm_manager.read().await;
+    llm.assess_model_feasibility(&model_name, &resources)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// Check a GGUF file's architecture and quantization before attempting a load
+#[tauri::command]
+async fn validate_gguf_compatibility(
+    path: String,
+) -> Result<gguf_compat::GgufCompatibility, String> {
+    let path = PathBuf::from(path);
+    tokio::task::spawn_blocking(move || gguf_compat::validate_gguf_compatibility(&path))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+// Stop sequences in effect for the loaded model (detected + user overrides)
+#[tauri::command]
+async fn get_stop_sequences(
+    state: State<'_, AppState>,
+) -> Result<llm_manager::StopSequences, String> {
+    let llm = state.llm_manager.read().await;
+    Ok(llm.get_stop_sequences().await)
+}
+
+// Replace generation parameters, including logit bias and banned token ids
+#[tauri::command]
+async fn set_generation_config(
+    state: State<'_, AppState>,
+    config: llm_manager::GenerationConfig,
+) -> Result<llm_manager::GenerationConfig, String> {
+    let llm = state.llm_manager.read().await;
+    llm.update_generation_config(config)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(llm.get_generation_config().await)
+}
+
+// Generation settings the user set explicitly, which survive model loads
+#[tauri::command]
+async fn get_generation_overrides(
+    state: State<'_, AppState>,
+) -> Result<llm_manager::GenerationOverrides, String> {
+    let llm = state.llm_manager.read().await;
+    Ok(llm.get_generation_overrides().await)
+}
+
+// Drop explicit generation settings and return to the loaded model's defaults
+#[tauri::command]
+async fn clear_generation_overrides(
+    state: State<'_, AppState>,
+) -> Result<llm_manager::GenerationConfig, String> {
+    let llm = state.llm_manager.read().await;
+    llm.clear_generation_overrides()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// Token ids of a string under the loaded model's tokenizer, for biasing or banning
+#[tauri::command]
+async fn resolve_token_ids(state: State<'_, AppState>, text: String) -> Result<Vec<u32>, String> {
+    let llm = state.llm_manager.read().await;
+    llm.resolve_token_ids(&text)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn set_stop_sequence_overrides(
+    state: State<'_, AppState>,
+    stop_sequences: Vec<String>,
+) -> Result<llm_manager::StopSequences, String> {
+    let llm = state.llm_manager.read().await;
+    Ok(llm.set_stop_sequence_overrides(stop_sequences).await)
+}
+
+// Back up the RAG index (chunks, metadata, embeddings) to a portable archive
+#[tauri::command]
+async fn export_knowledge_base(
+    state: State<'_, AppState>,
+    path: String,
+) -> Result<rag_engine::KnowledgeBaseTransfer, String> {
+    let rag = state.rag_engine.read().await;
+    ensure_documents_exportable(&state, rag.source_document_ids().await).await?;
+    rag.export_knowledge_base(std::path::Path::new(&path))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// Restore a knowledge base archive, re-embedding if the embedding model differs
+#[tauri::command]
+async fn import_knowledge_base(
+    state: State<'_, AppState>,
+    path: String,
+) -> Result<rag_engine::KnowledgeBaseTransfer, String> {
+    let rag = state.rag_engine.read().await;
+    rag.import_knowledge_base(std::path::Path::new(&path))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// Database commands; only a single SELECT runs, on a read-only connection
+#[tauri::command]
+async fn execute_sql_query(
+    state: State<'_, AppState>,
+    db_path: State<'_, PathBuf>,
+    query: String,
+) -> Result<read_only_sql::SqlRows, String> {
+    let sql = read_only_sql::ReadOnlySql::new(db_path.to_path_buf());
+    let mut rows = tokio::task::spawn_blocking(move || sql.execute(&query))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+    let detector = state.pii_detector.read().await;
+    rows.redact_pii(&detector)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(rows)
+}
+
+// Enhanced RAG search with agentic capabilities; `filter` scopes it to chunks
+// whose metadata matches, e.g. {"file_type": "pdf"} or {"document_id": {"$in": [...]}}
+#[tauri::command]
+async fn rag_search(
+    state: State<'_, AppState>,
+    query: String,
+    _use_agentic: bool,
+    max_results: usize,
+    filter: Option<serde_json::Value>,
+) -> Result<serde_json::Value, String> {
+    let filter = filter
+        .as_ref()
+        .map(rag_engine::MetadataFilter::from_json)
+        .transpose()
+        .map_err(|e| e.to_string())?;
+    let detector = state.pii_detector.read().await;
+    let cleaned_query = detector
+        .redact_pii(&query, None)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let rag = state.rag_engine.read().await;
+    let paraphrases = query_paraphrases(&state, &rag, &cleaned_query).await;
 
     // Agentic search delegates to standard RAG search
     let results = rag
-        .search(&cleaned_query, Some(max_results))
+        .search_paraphrased(
+            &cleaned_query,
+            &paraphrases,
+            Some(max_results),
+            filter.as_ref(),
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let confidence = if !results.is_empty() { 0.85 } else { 0.0 };
+
+    Ok(serde_json::json!({
+        "answer": format!("Found {} relevant documents for your query.", results.len()),
+        "sources": results.iter().map(|r| serde_json::json!({
+            "title": r.metadata.get("title").unwrap_or(&serde_json::Value::String("Document".to_string())),
+            "snippet": &r.content,
+            "relevance": r.score,
+            "source": "Knowledge Base",
+            "reasoning": r.reasoning
+        })).collect::<Vec<_>>(),
+        "reasoning": None::<String>,
+        "confidence": confidence
+    }))
+}
+
+#[tauri::command]
+async fn upload_document(
+    state: State<'_, AppState>,
+    filename: String,
+    content: Vec<u8>,
+    operator: Option<String>,
+    trust_weight: Option<f32>,
+) -> Result<serde_json::Value, String> {
+    let operator = operator.unwrap_or_else(|| "default_user".to_string());
+    state
+        .rate_limiter
+        .check_rate_limit(&operator, &format!("upload_document:{}", filename))
+        .map_err(|e| e.to_string())?;
+    if filename.to_lowercase().ends_with(".zip") {
+        return ingest_archive(&state, &filename, &content, &operator, trust_weight).await;
+    }
+
+    let content_str = String::from_utf8_lossy(&content);
+    let source =
+        rag_engine::IngestSource::new(&content, rag_engine::ExtractionMethod::Native, Vec::new());
+    ingest_document_text(
+        &state,
+        &filename,
+        &content_str,
+        source,
+        &operator,
+        trust_weight,
+    )
+    .await
+}
+
+// Store a chain-of-custody receipt; the receipt is returned even if storing fails
+async fn issue_processing_receipt(
+    compliance: &ComplianceManager,
+    receipt: compliance::ProcessingReceipt,
+) -> compliance::ProcessingReceipt {
+    let receipts = compliance.receipts();
+    let receipts = receipts.read().await;
+    if let Err(e) = receipts.record(&receipt) {
+        tracing::warn!("Failed to store processing receipt: {}", e);
+    }
+    receipt
+}
+
+// Redact, store and index one document's text
+async fn ingest_document_text(
+    state: &AppState,
+    filename: &str,
+    content_str: &str,
+    mut source: rag_engine::IngestSource,
+    operator: &str,
+    trust_weight: Option<f32>,
+) -> Result<serde_json::Value, String> {
+    // Obligations are extracted from the original text so party names survive redaction
+    let extracted_obligations = obligations::extract_obligations(content_str);
+
+    // Process with PII detection, a chunk at a time so large documents stay bounded
+    let detector = state.pii_detector.read().await;
+    let report = detector
+        .redact_pii_chunked(
+            file_processor::split_text_chunks(content_str, constants::PII_STREAM_CHUNK_BYTES),
+            None,
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+    report.ensure_complete().map_err(|e| e.to_string())?;
+    let pii_stats = detector.summarize_detections(&report.detections).await;
+    let redaction_markers = report.redaction_markers();
+    let redacted_detections = redacted_pii_detections(&report.detections, &redaction_markers);
+    let cleaned_content = report.redacted_text;
+
+    // Record the document and its detections, encrypted when field encryption
+    // is on; its row id keys every compliance record kept for it
+    let file_type = filename.split('.').next_back().unwrap_or("txt");
+    let doc_id = state
+        .document_store
+        .record(
+            filename.to_string(),
+            cleaned_content.clone(),
+            file_type.to_string(),
+            operator.to_string(),
+            redacted_detections,
+        )
+        .await
+        .map_err(|e| format!("Failed to record document: {}", e))?;
+
+    // Add to enhanced RAG engine
+    let mut metadata = serde_json::json!({
+        "filename": filename,
+        "document_id": doc_id
+    });
+    metadata[rag_engine::OWNER_KEY] = serde_json::json!(operator);
+    if let Some(weight) = trust_weight {
+        metadata[rag_engine::TRUST_WEIGHT_KEY] = serde_json::json!(weight);
+    }
+    let rag = state.rag_engine.write().await;
+    // A re-upload is indexed alongside the prior version; the report lets the
+    // user decide whether to replace it
+    let change_report = rag
+        .compare_with_prior_version(&cleaned_content, &metadata)
+        .await
+        .map_err(|e| e.to_string())?;
+    source.redaction_markers = redaction_markers;
+    let rag_doc_id = rag
+        .add_document_from_source(&cleaned_content, metadata, source)
+        .await
+        .map_err(|e| e.to_string())?;
+    let chunk_count = rag.document_chunk_count(&rag_doc_id).await;
+
+    let obligation_count = extracted_obligations.len();
+    let (original_retained, receipt) = record_ingest_compliance(
+        &state.compliance_manager,
+        &state.contract_obligations,
+        &IngestedDocument {
+            document_id: &doc_id.to_string(),
+            filename,
+            operator,
+            original: content_str,
+            redacted: &cleaned_content,
+            obligations: &extracted_obligations,
+            pii_stats: &pii_stats,
+            chunk_count,
+        },
+    )
+    .await;
+
+    Ok(serde_json::json!({
+        "chunks": chunk_count,
+        "document_id": doc_id,
+        "rag_document_id": rag_doc_id,
+        "obligations": obligation_count,
+        "pii_by_tier": pii_stats.by_tier,
+        "original_retained": original_retained,
+        "receipt": receipt,
+        "change_report": change_report
+    }))
+}
+
+// A stored and indexed document, for the compliance records kept with it
+struct IngestedDocument<'a> {
+    document_id: &'a str,
+    filename: &'a str,
+    operator: &'a str,
+    original: &'a str,
+    redacted: &'a str,
+    obligations: &'a [obligations::Obligation],
+    pii_stats: &'a pii_detector::PIIStatistics,
+    chunk_count: usize,
+}
+
+// Keep an ingested document's obligations, retained original, draft redaction
+// review, PII audit entry and processing receipt, all under its document id.
+// Returns whether the original was retained, and the receipt.
+async fn record_ingest_compliance(
+    compliance: &ComplianceManager,
+    contract_obligations: &obligations::ObligationStore,
+    document: &IngestedDocument<'_>,
+) -> (bool, compliance::ProcessingReceipt) {
+    if let Err(e) = contract_obligations.save(
+        document.document_id,
+        document.operator,
+        document.obligations,
+    ) {
+        tracing::warn!("Failed to store extracted obligations: {}", e);
+    }
+
+    // Originals are kept, encrypted, only under legal hold or explicit opt-in
+    let original_retained = {
+        let originals = compliance.originals();
+        let mut originals = originals.write().await;
+        originals
+            .retain_if_enabled(document.document_id, document.operator, document.original)
+            .unwrap_or_else(|e| {
+                tracing::warn!("Failed to retain original document: {}", e);
+                false
+            })
+    };
+
+    // Redactions start as a draft awaiting reviewer sign-off
+    {
+        let review = compliance.review();
+        let review = review.read().await;
+        if let Err(e) = review.register_document(document.document_id) {
+            tracing::warn!("Failed to register redaction review: {}", e);
+        }
+    }
+
+    // Record what was detected, by sensitivity tier, without the PII itself
+    {
+        let audit = compliance.audit();
+        let audit = audit.read().await;
+        if let Err(e) = audit.log_success(
+            document.operator,
+            AuditAction::DataModified,
+            EntityType::Document,
+            Some(document.document_id),
+            Some(serde_json::json!({
+                "action": "pii_redacted",
+                "filename": document.filename,
+                "total_entities": document.pii_stats.total_entities,
+                "by_type": document.pii_stats.by_type,
+                "by_tier": document.pii_stats.by_tier,
+            })),
+        ) {
+            tracing::warn!("Failed to audit PII detection: {}", e);
+        }
+    }
+
+    let receipt = issue_processing_receipt(
+        compliance,
+        compliance::ProcessingReceipt::new(
+            document.document_id,
+            "upload_document",
+            document.filename,
+            document.original,
+            document.redacted,
+            document.pii_stats,
+            document.chunk_count,
+            document.operator,
+        ),
+    )
+    .await;
+
+    (original_retained, receipt)
+}
+
+// Ingest every supported file in a ZIP archive, returning a per-entry summary
+async fn ingest_archive(
+    state: &AppState,
+    filename: &str,
+    content: &[u8],
+    operator: &str,
+    trust_weight: Option<f32>,
+) -> Result<serde_json::Value, String> {
+    let entries = state
+        .file_processor
+        .process_archive(filename, content, &ArchiveLimits::default())
         .await
         .map_err(|e| e.to_string())?;
 
-    let confidence = if !results.is_empty() { 0.85 } else { 0.0 };
+    let mut summary = Vec::with_capacity(entries.len());
+    let mut ingested = 0;
+    for entry in entries {
+        let mut result = serde_json::to_value(&entry).map_err(|e| e.to_string())?;
+        if let Some(text) = entry.text.as_deref() {
+            let source = rag_engine::IngestSource {
+                source_sha256: entry.sha256.clone().unwrap_or_default(),
+                extraction_method: rag_engine::ExtractionMethod::Native,
+                redaction_markers: Vec::new(),
+            };
+            match ingest_document_text(state, &entry.path, text, source, operator, trust_weight)
+                .await
+            {
+                Ok(ingest) => {
+                    ingested += 1;
+                    result["ingest"] = ingest;
+                }
+                Err(e) => {
+                    result["status"] = serde_json::json!(ArchiveEntryStatus::Failed);
+                    result["error"] = serde_json::json!(e);
+                }
+            }
+        }
+        summary.push(result);
+    }
 
     Ok(serde_json::json!({
-        "answer": format!("Found {} relevant documents for your query.", results.len()),
-        "sources": results.iter().map(|r| serde_json::json!({
-            "title": r.metadata.get("title").unwrap_or(&serde_json::Value::String("Document".to_string())),
-            "snippet": &r.content,
-            "relevance": r.score,
-            "source": "Knowledge Base",
-            "reasoning": r.reasoning
-        })).collect::<Vec<_>>(),
-        "reasoning": None::<String>,
-        "confidence": confidence
+        "archive": filename,
+        "ingested": ingested,
+        "entries": summary
     }))
 }
 
+// Chain-of-custody receipt of how a document was processed
 #[tauri::command]
-async fn upload_document(
+async fn get_processing_receipt(
     state: State<'_, AppState>,
-    filename: String,
-    content: Vec<u8>,
-) -> Result<serde_json::Value, String> {
-    let content_str = String::from_utf8_lossy(&content);
+    document_id: String,
+) -> Result<compliance::ProcessingReceipt, String> {
+    let receipts = state.compliance_manager.receipts();
+    let receipts = receipts.read().await;
+    receipts
+        .get_receipt(&document_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("No processing receipt for document {}", document_id))
+}
 
-    // Process with PII detection
-    let detector = state.pii_detector.read().await;
-    let cleaned_content = detector
-        .redact_pii(&content_str)
-        .await
-        .map_err(|e| e.to_string())?;
+// Write a rendered export file and audit it as a data export
+async fn write_export_file(
+    state: &AppState,
+    user_id: &str,
+    output_path: &str,
+    contents: &[u8],
+    details: serde_json::Value,
+) -> Result<String, String> {
+    let path = PathBuf::from(output_path);
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    std::fs::write(&path, contents).map_err(|e| format!("Failed to write export: {}", e))?;
+
+    let audit = state.compliance_manager.audit();
+    let audit = audit.read().await;
+    if let Err(e) = audit.log_success(
+        user_id,
+        AuditAction::DataExported,
+        EntityType::UserSetting,
+        None,
+        Some(details),
+    ) {
+        tracing::warn!("Failed to audit export: {}", e);
+    }
 
-    // Store in database
-    let db = state.database_manager.read().await;
-    let file_type = filename.split('.').next_back().unwrap_or("txt");
-    let doc_id = db
-        .store_document(&filename, &cleaned_content, file_type)
-        .map_err(|e| e.to_string())?;
+    Ok(path.to_string_lossy().to_string())
+}
 
-    // Add to enhanced RAG engine
-    let rag = state.rag_engine.write().await;
-    rag.add_document(
-        &cleaned_content,
+// Documents only leave the system once a reviewer has approved their redactions
+async fn ensure_documents_exportable<I, S>(state: &AppState, document_ids: I) -> Result<(), String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let review = state.compliance_manager.review();
+    let review = review.read().await;
+    review
+        .ensure_all_exportable(document_ids)
+        .map_err(|e| e.to_string())
+}
+
+// Export the user's chats, documents, PII detections and consent history as
+// a PDF at a path the user chose (data subject access requests)
+#[tauri::command]
+async fn export_user_data_pdf(
+    state: State<'_, AppState>,
+    db_path: State<'_, PathBuf>,
+    output_path: String,
+) -> Result<String, String> {
+    let data = ExportIntegration::new(db_path.inner().clone())
+        .fetch_user_data()
+        .map_err(|e| format!("Failed to fetch user data: {}", e))?;
+    ensure_documents_exportable(&state, data.documents.iter().map(|d| d.id.to_string())).await?;
+    let pdf = bear_ai_llm::ExportEngine::new()
+        .to_pdf(&data)
+        .map_err(|e| format!("Failed to render PDF: {}", e))?;
+
+    write_export_file(
+        &state,
+        &data.user_id,
+        &output_path,
+        &pdf,
         serde_json::json!({
-            "filename": filename,
-            "document_id": doc_id
+            "export_type": "full_user_data",
+            "format": "pdf",
+            "export_hash": data.metadata.export_hash,
         }),
     )
     .await
-    .map_err(|e| e.to_string())?;
+}
 
-    let chunk_count = (cleaned_content.len() / 512).max(1);
+// Export the user's data as JSON encrypted with a passphrase (Argon2id key,
+// AES-256-GCM) at a path the user chose, for sending to a client
+#[tauri::command]
+async fn export_user_data_encrypted(
+    state: State<'_, AppState>,
+    db_path: State<'_, PathBuf>,
+    output_path: String,
+    passphrase: String,
+) -> Result<String, String> {
+    let data = ExportIntegration::new(db_path.inner().clone())
+        .fetch_user_data()
+        .map_err(|e| format!("Failed to fetch user data: {}", e))?;
+    ensure_documents_exportable(&state, data.documents.iter().map(|d| d.id.to_string())).await?;
+    let encrypted = bear_ai_llm::ExportEngine::new()
+        .export_encrypted(&data, &passphrase)
+        .map_err(|e| format!("Failed to encrypt export: {}", e))?;
+
+    write_export_file(
+        &state,
+        &data.user_id,
+        &output_path,
+        &encrypted,
+        serde_json::json!({
+            "export_type": "full_user_data",
+            "format": "json",
+            "encrypted": true,
+            "export_hash": data.metadata.export_hash,
+        }),
+    )
+    .await
+}
 
-    Ok(serde_json::json!({
-        "chunks": chunk_count,
-        "document_id": doc_id
-    }))
+// Export the GDPR compliance report for a user as a PDF at a path the user chose
+#[tauri::command]
+async fn export_compliance_report_pdf(
+    state: State<'_, AppState>,
+    user_id: String,
+    output_path: String,
+) -> Result<String, String> {
+    let report = state
+        .compliance_manager
+        .generate_compliance_report(&user_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    let pdf = bear_ai_llm::ExportEngine::new()
+        .report_to_pdf(&report)
+        .map_err(|e| format!("Failed to render PDF: {}", e))?;
+
+    write_export_file(
+        &state,
+        &user_id,
+        &output_path,
+        &pdf,
+        serde_json::json!({"export_type": "compliance_report", "format": "pdf"}),
+    )
+    .await
+}
+
+// Re-encrypt the SQLCipher database under a freshly generated key. Refused when
+// SQLite lacks SQLCipher, or while another connection holds the database lock
+#[tauri::command]
+async fn rotate_database_key(
+    state: State<'_, AppState>,
+    db_path: State<'_, PathBuf>,
+) -> Result<(), String> {
+    let db_path = db_path.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        security::EncryptedDatabase::with_default_config(&db_path)?.rotate_key()
+    })
+    .await
+    .map_err(|e| format!("Key rotation task failed: {}", e))?
+    .map_err(|e| format!("Failed to rotate database key: {:#}", e))?;
+
+    let audit = state.compliance_manager.audit();
+    let audit = audit.read().await;
+    if let Err(e) = audit.log_success(
+        "system",
+        AuditAction::SettingChanged,
+        EntityType::UserSetting,
+        None,
+        Some(serde_json::json!({"action": "database_key_rotated"})),
+    ) {
+        tracing::warn!("Failed to audit database key rotation: {}", e);
+    }
+    Ok(())
+}
+
+// Whether document content and PII replacement text are encrypted when stored
+#[tauri::command]
+async fn get_field_encryption(state: State<'_, AppState>) -> Result<FieldEncryptionConfig, String> {
+    Ok(state.document_store.config())
+}
+
+// Turn field-level encryption of stored documents on or off. Rows stored
+// earlier keep their form until migrate_field_encryption is run
+#[tauri::command]
+async fn set_field_encryption(state: State<'_, AppState>, enabled: bool) -> Result<(), String> {
+    state
+        .document_store
+        .set_config(FieldEncryptionConfig { enabled })
+        .await
+        .map_err(|e| format!("Failed to save field encryption setting: {}", e))
+}
+
+// Encrypt documents and PII detections stored before field encryption was enabled
+#[tauri::command]
+async fn migrate_field_encryption(
+    state: State<'_, AppState>,
+) -> Result<FieldMigrationStats, String> {
+    state
+        .document_store
+        .migrate()
+        .await
+        .map_err(|e| format!("Field encryption migration failed: {:#}", e))
+}
+
+// Export a document's obligations as CSV or an iCalendar file of deadlines
+#[tauri::command]
+async fn export_obligations(
+    state: State<'_, AppState>,
+    document_id: String,
+    format: String,
+) -> Result<String, String> {
+    let format = obligations::ObligationExportFormat::parse(&format).map_err(|e| e.to_string())?;
+    ensure_documents_exportable(&state, [document_id.as_str()]).await?;
+    let records = state
+        .contract_obligations
+        .load(&document_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("No obligations recorded for document {}", document_id))?;
+
+    Ok(obligations::export_obligations(&records, &document_id, format))
+}
+
+// Redaction approval workflow: draft -> pending review -> approved/rejected
+#[tauri::command]
+async fn submit_for_review(
+    state: State<'_, AppState>,
+    document_id: String,
+    actor: String,
+) -> Result<compliance::RedactionReview, String> {
+    let review = state.compliance_manager.review();
+    let review = review.read().await;
+    review
+        .submit_for_review(&document_id, &actor)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn approve_redaction(
+    state: State<'_, AppState>,
+    document_id: String,
+    actor: String,
+) -> Result<compliance::RedactionReview, String> {
+    let review = state.compliance_manager.review();
+    let review = review.read().await;
+    review
+        .approve(&document_id, &actor)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn reject_redaction(
+    state: State<'_, AppState>,
+    document_id: String,
+    actor: String,
+    reason: String,
+) -> Result<compliance::RedactionReview, String> {
+    let review = state.compliance_manager.review();
+    let review = review.read().await;
+    review
+        .reject(&document_id, &actor, &reason)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_redaction_review(
+    state: State<'_, AppState>,
+    document_id: String,
+) -> Result<Option<compliance::RedactionReview>, String> {
+    let review = state.compliance_manager.review();
+    let review = review.read().await;
+    review.get_review(&document_id).map_err(|e| e.to_string())
+}
+
+// Keep encrypted pre-redaction originals (opt-in or legal hold)
+#[tauri::command]
+async fn set_original_retention(
+    state: State<'_, AppState>,
+    policy: compliance::OriginalRetentionPolicy,
+) -> Result<compliance::OriginalRetentionPolicy, String> {
+    let originals = state.compliance_manager.originals();
+    let mut originals = originals.write().await;
+    originals
+        .set_policy(policy.clone())
+        .map_err(|e| e.to_string())?;
+
+    let audit = state.compliance_manager.audit();
+    let audit = audit.read().await;
+    if let Err(e) = audit.log_success(
+        "default_user",
+        AuditAction::SettingChanged,
+        EntityType::UserSetting,
+        None,
+        Some(serde_json::json!({"action": "original_retention_changed", "policy": policy})),
+    ) {
+        tracing::warn!("Failed to audit original retention change: {}", e);
+    }
+    Ok(policy)
+}
+
+#[tauri::command]
+async fn get_original_retention(
+    state: State<'_, AppState>,
+) -> Result<compliance::OriginalRetentionPolicy, String> {
+    let originals = state.compliance_manager.originals();
+    let originals = originals.read().await;
+    Ok(originals.policy().clone())
+}
+
+// Decrypted original for a subject-access request
+#[tauri::command]
+async fn get_document_original(
+    state: State<'_, AppState>,
+    document_id: String,
+    actor: String,
+) -> Result<Option<String>, String> {
+    let originals = state.compliance_manager.originals();
+    let mut originals = originals.write().await;
+    originals
+        .get_original(&document_id, &actor)
+        .map_err(|e| e.to_string())
+}
+
+// Re-run PII detection on a retained original, e.g. after a configuration change
+#[tauri::command]
+async fn rescan_document_original(
+    state: State<'_, AppState>,
+    document_id: String,
+    actor: String,
+) -> Result<pii_detector::RedactionReport, String> {
+    let original = {
+        let originals = state.compliance_manager.originals();
+        let mut originals = originals.write().await;
+        originals
+            .get_original(&document_id, &actor)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("No original retained for document {}", document_id))?
+    };
+
+    let detector = state.pii_detector.read().await;
+    detector
+        .redact_pii_with_report(&original, None)
+        .await
+        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -641,20 +2821,38 @@ async fn analyze_document_pii(
     let start_time = std::time::Instant::now();
 
     let file_type = filename.split('.').next_back().unwrap_or("unknown");
-    let original_text = if state.file_processor.is_supported(file_type) {
+    let lossy_chunks = || {
+        TextChunkReader::from_text(
+            String::from_utf8_lossy(&content).into_owned(),
+            constants::PII_STREAM_CHUNK_BYTES,
+        )
+    };
+    let (preview, preview_truncated, report) = if state.file_processor.is_supported(file_type) {
         // SECURITY FIX: Atomically create temporary file with content
         // Uses tempfile crate for atomic creation, preventing TOCTOU race conditions
         let temp_guard = TempFileGuard::create_with_content(&filename, &content)?;
 
-        // Process the file - path is guaranteed to exist and be secure
-        let result = state
+        // Process the file - path is guaranteed to exist and be secure. Text and
+        // CSV are streamed from the temp file, so it must outlive the reads.
+        let chunks = state
             .file_processor
-            .process_file(
+            .open_text_chunks(
                 temp_guard.path().to_str().ok_or("Invalid temp path")?,
-                file_type,
+                constants::PII_STREAM_CHUNK_BYTES,
             )
             .await
-            .unwrap_or_else(|_| String::from_utf8_lossy(&content).to_string());
+            .unwrap_or_else(|_| lossy_chunks());
+
+        let detector = state.pii_detector.read().await;
+        let result = match redact_text_chunks(&detector, chunks).await {
+            Ok(result) => result,
+            Err(e) => {
+                tracing::debug!("Chunked extraction failed, using raw content: {}", e);
+                redact_text_chunks(&detector, lossy_chunks())
+                    .await
+                    .map_err(|e| e.to_string())?
+            }
+        };
 
         // temp_guard is automatically dropped here, cleaning up the file atomically
         result
@@ -671,25 +2869,15 @@ async fn analyze_document_pii(
         }));
     };
 
-    let detector = state.pii_detector.read().await;
-    let detections = detector
-        .detect_pii(&original_text)
-        .await
-        .map_err(|e| e.to_string())?;
-
-    let cleaned_text = detector
-        .redact_pii(&original_text)
-        .await
-        .map_err(|e| e.to_string())?;
-
     let processing_time = start_time.elapsed().as_millis();
 
     Ok(serde_json::json!({
         "filename": filename,
         "fileType": file_type,
-        "originalText": original_text,
-        "cleanedText": cleaned_text,
-        "piiDetections": detections.iter().map(|d| serde_json::json!({
+        "originalText": preview,
+        "originalTruncated": preview_truncated,
+        "cleanedText": report.redacted_text,
+        "piiDetections": report.detections.iter().map(|d| serde_json::json!({
             "type": d.entity_type,
             "text": d.text,
             "startIndex": d.start,
@@ -698,10 +2886,92 @@ async fn analyze_document_pii(
             "replacement": format!("[REDACTED_{}]", d.entity_type.to_uppercase())
         })).collect::<Vec<_>>(),
         "processingTime": processing_time,
-        "supported": true
+        "supported": true,
+        "truncated": report.truncated
+    }))
+}
+
+// Redact text a chunk at a time, returning the start of the text that was
+// scanned (capped at PII_ANALYSIS_PREVIEW_BYTES, with whether it was cut short)
+// alongside a report whose detection spans are relative to the full text
+async fn redact_text_chunks(
+    detector: &PIIDetector,
+    chunks: impl Iterator<Item = anyhow::Result<String>> + Send,
+) -> anyhow::Result<(String, bool, pii_detector::RedactionReport)> {
+    let mut preview = String::new();
+    let mut preview_truncated = false;
+    let mut failed = None;
+    let chunks = chunks.map_while(|chunk| match chunk {
+        Ok(chunk) => {
+            let room = constants::PII_ANALYSIS_PREVIEW_BYTES - preview.len();
+            if chunk.len() <= room {
+                preview.push_str(&chunk);
+            } else {
+                let mut end = room;
+                while !chunk.is_char_boundary(end) {
+                    end -= 1;
+                }
+                preview.push_str(&chunk[..end]);
+                preview_truncated = true;
+            }
+            Some(chunk)
+        }
+        Err(e) => {
+            failed = Some(e);
+            None
+        }
+    });
+    let report = detector.redact_pii_chunked(chunks, None).await?;
+    match failed {
+        Some(e) => Err(e),
+        None => Ok((preview, preview_truncated, report)),
+    }
+}
+
+// Redacted copy of a document in its original format, for formats that support it
+#[tauri::command]
+async fn redact_document_in_place(
+    state: State<'_, AppState>,
+    filename: String,
+    content: Vec<u8>,
+) -> Result<serde_json::Value, String> {
+    let file_type = filename
+        .split('.')
+        .next_back()
+        .unwrap_or("unknown")
+        .to_lowercase();
+    if !document_redaction::supports_in_place_redaction(&file_type) {
+        return Ok(serde_json::json!({
+            "filename": filename,
+            "fileType": file_type,
+            "supported": false,
+            "error": format!("In-place redaction is not supported for {} files", file_type)
+        }));
+    }
+
+    let detector = state.pii_detector.read().await;
+    let redacted = document_redaction::redact_docx(&content, &detector)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(serde_json::json!({
+        "filename": filename,
+        "fileType": file_type,
+        "supported": true,
+        "content": redacted.content,
+        "redactedCount": redacted.redacted_count,
+        "truncated": redacted.truncated
     }))
 }
 
+// Numbered clause hierarchy for the document-navigation sidebar
+#[tauri::command]
+async fn extract_document_outline(
+    content: String,
+) -> Result<clause_outline::DocumentOutline, String> {
+    Ok(clause_outline::extract_outline(&content))
+}
+
 #[tauri::command]
 async fn get_database_stats(state: State<'_, AppState>) -> Result<serde_json::Value, String> {
     let db = state.database_manager.read().await;
@@ -711,10 +2981,7 @@ async fn get_database_stats(state: State<'_, AppState>) -> Result<serde_json::Va
 // System specification commands
 #[tauri::command]
 async fn get_system_specs(state: State<'_, AppState>) -> Result<serde_json::Value, String> {
-    let specs = {
-        let mut detector = state.hardware_detector.write().await;
-        detector.detect_hardware().map_err(|e| e.to_string())?
-    };
+    let specs = probe_hardware(&state).await?;
 
     Ok(serde_json::json!({
         "cpu_cores": specs.cpu_cores,
@@ -734,10 +3001,7 @@ async fn check_model_compatibility(
     model_name: String,
     model_size_gb: f64,
 ) -> Result<serde_json::Value, String> {
-    let specs = {
-        let mut detector = state.hardware_detector.write().await;
-        detector.detect_hardware().map_err(|e| e.to_string())?
-    };
+    let specs = probe_hardware(&state).await?;
 
     // Check if system can run the model
     let required_ram_gb = model_size_gb * 1.5; // Model + context overhead
@@ -776,18 +3040,37 @@ async fn get_resource_usage(state: State<'_, AppState>) -> Result<serde_json::Va
 }
 
 // LLM Model Management Commands
+// Models whose license conflicts with the declared use case or is not known
+// load with a warning, or not at all when the license policy blocks them; the
+// LLM manager enforces this, the warning is repeated here for the caller
 #[tauri::command]
 async fn load_model(
     state: State<'_, AppState>,
+    transparency: State<'_, commands::ModelTransparencyState>,
     model_path: String,
     _n_gpu_layers: Option<u32>,
 ) -> Result<String, String> {
+    let license_warning = transparency.check_model_license(&model_path)?;
+
     let llm = state.llm_manager.write().await;
     llm.load_model(&model_path)
         .await
         .map_err(|e| e.to_string())?;
+    let offload = match llm.get_gpu_offload().await {
+        Some(offload) => format!(" {}.", offload.summary()),
+        None => String::new(),
+    };
 
-    Ok(format!("Model loaded successfully: {}", model_path))
+    match license_warning {
+        Some(warning) => Ok(format!(
+            "Model loaded successfully: {}.{} License warning: {}",
+            model_path, offload, warning
+        )),
+        None => Ok(format!(
+            "Model loaded successfully: {}.{}",
+            model_path, offload
+        )),
+    }
 }
 
 #[tauri::command]
@@ -798,9 +3081,9 @@ async fn unload_model(state: State<'_, AppState>) -> Result<String, String> {
 }
 
 #[tauri::command]
-async fn emergency_stop(_state: State<'_, AppState>) -> Result<String, String> {
-    // Stop all ongoing operations
-    // Note: cancel_generation method doesn't exist, just return success
+async fn emergency_stop(state: State<'_, AppState>) -> Result<String, String> {
+    // Interrupt in-flight generations; each returns its partial output
+    state.llm_manager.read().await.cancel_generations();
     Ok("All operations stopped".to_string())
 }
 
@@ -824,40 +3107,23 @@ async fn set_resource_limits(
 }
 
 // HuggingFace Integration Commands
+// Download a file of any Hub repo into the models directory, with progress events
 #[tauri::command]
 async fn download_model_from_huggingface(
+    state: State<'_, AppState>,
     model_id: String,
     filename: Option<String>,
 ) -> Result<serde_json::Value, String> {
-    use hf_hub::api::tokio::Api;
-
-    let download_dir = dirs::data_local_dir()
-        .map(|mut p| {
-            p.push("bear-ai/models");
-            p
-        })
-        .ok_or("Failed to get data directory")?;
-
-    std::fs::create_dir_all(&download_dir).map_err(|e| e.to_string())?;
-
-    // Use pure Rust hf-hub crate (no external CLI dependency)
-    let api = Api::new().map_err(|e| format!("Failed to initialize HF API: {}", e))?;
-    let repo = api.model(model_id.clone());
-
     // Download the specified file (or default to model.gguf)
     let file = filename.unwrap_or_else(|| "model.gguf".to_string());
 
-    let downloaded_path = repo
-        .get(&file)
+    // A handle, so the manager lock is not held for the whole download
+    let llm = state.llm_manager.read().await.clone();
+    let output_path = llm
+        .download_hub_file(&model_id, &file)
         .await
         .map_err(|e| format!("Failed to download {}: {}", file, e))?;
 
-    // Copy to our models directory
-    let output_path = download_dir.join(&file);
-    tokio::fs::copy(&downloaded_path, &output_path)
-        .await
-        .map_err(|e| format!("Failed to copy model: {}", e))?;
-
     Ok(serde_json::json!({
         "success": true,
         "model_id": model_id,
@@ -866,48 +3132,18 @@ async fn download_model_from_huggingface(
     }))
 }
 
+// Search the HuggingFace Hub for GGUF models and the quantizations each offers
 #[tauri::command]
 async fn search_huggingface_models(
     query: String,
     limit: Option<usize>,
 ) -> Result<serde_json::Value, String> {
-    // Simple search implementation - in production use HF API
-          let popular_models = [
-            ("TheBloke/Llama-2-7B-Chat-GGUF", "Llama 2 7B Chat", "7B"),
-            (
-                "TheBloke/Mistral-7B-Instruct-v0.2-GGUF",
-                "Mistral 7B Instruct",
-                "7B",
-            ),
-            (
-                "TheBloke/TinyLlama-1.1B-Chat-v1.0-GGUF",
-                "TinyLlama 1.1B",
-                "1.1B",
-            ),
-            ("TheBloke/CodeLlama-7B-Instruct-GGUF", "CodeLlama 7B", "7B"),
-        ];
-    let results: Vec<serde_json::Value> = popular_models
-        .iter()
-        .filter(|(id, name, _)| {
-            id.to_lowercase().contains(&query.to_lowercase())
-                || name.to_lowercase().contains(&query.to_lowercase())
-        })
-        .take(limit.unwrap_or(10))
-        .map(|(id, name, size)| {
-            serde_json::json!({
-                "model_id": id,
-                "name": name,
-                "size": size,
-                "downloads": 0,
-                "likes": 0
-            })
-        })
-        .collect();
+    let results = huggingface_api::search_gguf_models(&query, limit.unwrap_or(10)).await;
 
     Ok(serde_json::json!({
         "query": query,
-        "results": results,
-        "total": results.len()
+        "total": results.len(),
+        "results": results
     }))
 }
 
@@ -951,28 +3187,64 @@ async fn get_available_rag_models() -> Result<serde_json::Value, String> {
     }))
 }
 
+// Active embedding model and the dimension of the vectors stored with it, so
+// the frontend can warn when they do not match
 #[tauri::command]
 async fn get_active_rag_model(state: State<'_, AppState>) -> Result<serde_json::Value, String> {
     let rag = state.rag_engine.read().await;
     let model_name = rag.get_active_model().await;
+    let statistics = rag
+        .get_index_statistics()
+        .await
+        .map_err(|e| e.to_string())?;
 
     Ok(serde_json::json!({
         "model_name": model_name,
-        "is_loaded": rag.is_initialized()
+        "is_loaded": rag.is_initialized(),
+        "embedding_dimension": statistics.embedding_dimension,
+        "stale_chunks": rag.stale_vector_chunks().await
     }))
 }
 
+// Switching re-embeds indexed chunks with the new model when `force` is set
+// and is refused otherwise; progress is emitted as rag-embedding-progress
 #[tauri::command]
 async fn switch_rag_model(
     state: State<'_, AppState>,
+    window: tauri::Window,
     model_name: String,
-) -> Result<String, String> {
+    force: Option<bool>,
+) -> Result<rag_engine::IndexRebuildReport, String> {
     let rag = state.rag_engine.write().await;
-    rag.switch_rag_model(model_name.clone())
-        .await
-        .map_err(|e| e.to_string())?;
+    rag.switch_embedding_model(&model_name, force.unwrap_or(false), &|progress| {
+        let _ = window.emit("rag-embedding-progress", &progress);
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+// Re-embed the whole knowledge base with the active embedding model; progress
+// is emitted as rag-embedding-progress
+#[tauri::command]
+async fn rebuild_index(
+    state: State<'_, AppState>,
+    window: tauri::Window,
+) -> Result<rag_engine::IndexRebuildReport, String> {
+    let rag = state.rag_engine.read().await;
+    rag.rebuild_index(&|progress| {
+        let _ = window.emit("rag-embedding-progress", &progress);
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
 
-    Ok(format!("Switched to model: {}", model_name))
+// Knowledge base size, date range and embedding model mix
+#[tauri::command]
+async fn get_rag_statistics(
+    state: State<'_, AppState>,
+) -> Result<rag_engine::RAGIndexStatistics, String> {
+    let rag = state.rag_engine.read().await;
+    rag.get_index_statistics().await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -987,20 +3259,51 @@ async fn get_rag_config(state: State<'_, AppState>) -> Result<serde_json::Value,
         "similarity_threshold": config.similarity_threshold,
         "embedding_model": config.embedding_model,
         "enable_reranking": config.enable_reranking,
-        "enable_hybrid_search": config.enable_hybrid_search
+        "enable_hybrid_search": config.enable_hybrid_search,
+        "hybrid_alpha": config.hybrid_alpha,
+        "embedding_batch_size": config.embedding_batch_size,
+        "embedding_parallelism": config.embedding_parallelism,
+        "sentence_attribution": config.sentence_attribution,
+        "attribution_min_similarity": config.attribution_min_similarity,
+        "language_aware_chunking": config.language_aware_chunking,
+        "merge_overlapping_citations": config.merge_overlapping_citations,
+        "dimension_reduction": config.dimension_reduction,
+        "normalize_embeddings": config.normalize_embeddings,
+        "auto_rechunk": config.auto_rechunk,
+        "expand_query": config.expand_query,
+        "query_paraphrases": config.query_paraphrases,
+        "chunking_strategy": config.chunking_strategy,
+        "chunk_overlap_units": config.chunk_overlap_units
     }))
 }
 
+// Changing chunk_size/chunk_overlap/chunking_strategy with auto_rechunk on re-chunks the index,
+// which must be confirmed; progress is emitted as rag-rechunk-progress
 #[tauri::command]
 async fn update_rag_config(
     state: State<'_, AppState>,
+    window: tauri::Window,
     chunk_size: Option<usize>,
     chunk_overlap: Option<usize>,
     max_results: Option<usize>,
     similarity_threshold: Option<f32>,
+    embedding_batch_size: Option<usize>,
+    embedding_parallelism: Option<usize>,
+    sentence_attribution: Option<bool>,
+    attribution_min_similarity: Option<f32>,
+    language_aware_chunking: Option<bool>,
+    merge_overlapping_citations: Option<bool>,
+    hybrid_alpha: Option<f32>,
+    auto_rechunk: Option<bool>,
+    confirm_rechunk: Option<bool>,
+    expand_query: Option<bool>,
+    query_paraphrases: Option<usize>,
+    chunking_strategy: Option<rag_engine::ChunkingStrategy>,
+    chunk_overlap_units: Option<usize>,
 ) -> Result<String, String> {
     let rag = state.rag_engine.write().await;
-    let mut config = rag.get_config().await;
+    let current = rag.get_config().await;
+    let mut config = current.clone();
 
     // Update only provided fields
     if let Some(size) = chunk_size {
@@ -1015,10 +3318,101 @@ async fn update_rag_config(
     if let Some(threshold) = similarity_threshold {
         config.similarity_threshold = threshold;
     }
+    if let Some(batch_size) = embedding_batch_size {
+        config.embedding_batch_size = batch_size.max(1);
+    }
+    if let Some(parallelism) = embedding_parallelism {
+        config.embedding_parallelism = parallelism.max(1);
+    }
+    if let Some(enabled) = sentence_attribution {
+        config.sentence_attribution = enabled;
+    }
+    if let Some(min_similarity) = attribution_min_similarity {
+        config.attribution_min_similarity = min_similarity.clamp(0.0, 1.0);
+    }
+    if let Some(enabled) = language_aware_chunking {
+        config.language_aware_chunking = enabled;
+    }
+    if let Some(enabled) = merge_overlapping_citations {
+        config.merge_overlapping_citations = enabled;
+    }
+    if let Some(alpha) = hybrid_alpha {
+        config.hybrid_alpha = alpha.clamp(0.0, 1.0);
+    }
+    if let Some(enabled) = auto_rechunk {
+        config.auto_rechunk = enabled;
+    }
+    if let Some(enabled) = expand_query {
+        config.expand_query = enabled;
+    }
+    if let Some(count) = query_paraphrases {
+        config.query_paraphrases = count.min(constants::RAG_MAX_QUERY_PARAPHRASES);
+    }
+    if let Some(strategy) = chunking_strategy {
+        config.chunking_strategy = strategy;
+    }
+    if let Some(units) = chunk_overlap_units {
+        config.chunk_overlap_units = units;
+    }
+
+    let chunking_changed = config.chunk_size != current.chunk_size
+        || config.chunk_overlap != current.chunk_overlap
+        || config.chunking_strategy != current.chunking_strategy;
+    let affected = if chunking_changed {
+        rag.stale_chunking_documents(&config).await.len()
+    } else {
+        0
+    };
+    if affected > 0 && config.auto_rechunk && confirm_rechunk != Some(true) {
+        return Err(format!(
+            "Changing the chunking re-chunks and re-embeds {} indexed documents; \
+             confirm to proceed, or disable auto_rechunk",
+            affected
+        ));
+    }
 
+    let auto_rechunk = config.auto_rechunk;
     rag.update_config(config).await.map_err(|e| e.to_string())?;
+    drop(rag);
+
+    if affected == 0 {
+        return Ok("RAG configuration updated".to_string());
+    }
+    if !auto_rechunk {
+        return Ok(format!(
+            "RAG configuration updated; {} indexed documents keep their previous chunking \
+             (mixed chunking) until re-chunked",
+            affected
+        ));
+    }
+
+    // Searches keep running on the old chunks until the rebuilt ones are swapped in
+    let rag = state.rag_engine.read().await;
+    let report = rag
+        .rechunk_documents(|progress| {
+            let _ = window.emit("rag-rechunk-progress", &progress);
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(format!(
+        "RAG configuration updated; re-chunked {} documents ({} chunks, previously {})",
+        report.documents, report.chunks_after, report.chunks_before
+    ))
+}
 
-    Ok("RAG configuration updated".to_string())
+// Re-chunk documents left with outdated chunking, e.g. after a change made
+// with auto_rechunk off; progress is emitted as rag-rechunk-progress
+#[tauri::command]
+async fn rechunk_knowledge_base(
+    state: State<'_, AppState>,
+    window: tauri::Window,
+) -> Result<rag_engine::RechunkReport, String> {
+    let rag = state.rag_engine.read().await;
+    rag.rechunk_documents(|progress| {
+        let _ = window.emit("rag-rechunk-progress", &progress);
+    })
+    .await
+    .map_err(|e| e.to_string())
 }
 
 // PII Detection Configuration Commands
@@ -1096,6 +3490,50 @@ async fn get_pii_config(state: State<'_, AppState>) -> Result<serde_json::Value,
     }))
 }
 
+#[tauri::command]
+async fn get_active_exclusions(
+    state: State<'_, AppState>,
+    category: Option<String>,
+) -> Result<ActiveExclusions, String> {
+    let category = match category {
+        Some(name) => Some(
+            ExclusionCategory::parse(&name)
+                .ok_or_else(|| format!("Invalid exclusion category: {}", name))?,
+        ),
+        None => None,
+    };
+
+    let detector = state.pii_detector.read().await;
+    Ok(detector.get_active_exclusions(category).await)
+}
+
+#[tauri::command]
+async fn add_pii_exclusion(
+    state: State<'_, AppState>,
+    category: String,
+    term: String,
+) -> Result<String, String> {
+    let category = ExclusionCategory::parse(&category)
+        .ok_or_else(|| format!("Invalid exclusion category: {}", category))?;
+
+    let detector = state.pii_detector.read().await;
+    detector
+        .add_exclusion(category, term.clone())
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(format!("Added '{}' to {} exclusions", term.trim(), category.as_str()))
+}
+
+// Re-read the exclusion files after they were edited, keeping runtime additions
+#[tauri::command]
+async fn reload_pii_exclusions(state: State<'_, AppState>) -> Result<usize, String> {
+    let detector = state.pii_detector.read().await;
+    detector
+        .reload_exclusions()
+        .await
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 async fn set_pii_mode(state: State<'_, AppState>, mode: String) -> Result<String, String> {
     let detector = state.pii_detector.write().await;
@@ -1146,6 +3584,124 @@ async fn check_presidio_status(state: State<'_, AppState>) -> Result<serde_json:
     }))
 }
 
+// Effective configuration for support bundles (settings only, no secrets or PII)
+#[tauri::command]
+async fn export_config_snapshot(
+    state: State<'_, AppState>,
+) -> Result<config_snapshot::ConfigSnapshot, String> {
+    let scheduler = match &state.scheduler_handle {
+        Some(handle) => Some(handle.read().await.clone()),
+        None => None,
+    };
+    let pii = state.pii_detector.read().await;
+    let rag = state.rag_engine.read().await;
+    let llm = state.llm_manager.read().await;
+
+    Ok(config_snapshot::capture_snapshot(
+        &pii,
+        &rag,
+        &llm,
+        scheduler.as_ref(),
+        &state.consent_guard,
+    )
+    .await)
+}
+
+// Apply a configuration snapshot to reproduce a user's setup
+#[tauri::command]
+async fn import_config_snapshot(
+    state: State<'_, AppState>,
+    snapshot: config_snapshot::ConfigSnapshot,
+) -> Result<Vec<String>, String> {
+    let scheduler = match &state.scheduler_handle {
+        Some(handle) => Some(handle.read().await.clone()),
+        None => None,
+    };
+    let pii = state.pii_detector.read().await;
+    let rag = state.rag_engine.read().await;
+    let llm = state.llm_manager.read().await;
+
+    let notes = config_snapshot::apply_snapshot(
+        &snapshot,
+        &pii,
+        &rag,
+        &llm,
+        scheduler.as_ref(),
+        &state.consent_guard,
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let audit = state.compliance_manager.audit();
+    let audit = audit.read().await;
+    if let Err(e) = audit.log_success(
+        "default_user",
+        AuditAction::SettingChanged,
+        EntityType::UserSetting,
+        None,
+        Some(serde_json::json!({
+            "action": "config_snapshot_imported",
+            "snapshot_created_at": snapshot.created_at,
+        })),
+    ) {
+        tracing::warn!("Failed to audit config snapshot import: {}", e);
+    }
+    Ok(notes)
+}
+
+// Compare detections under two PII configurations without changing the active one
+#[tauri::command]
+async fn compare_pii_configs(
+    state: State<'_, AppState>,
+    text: String,
+    config_a: pii_detector::PIIDetectionConfig,
+    config_b: pii_detector::PIIDetectionConfig,
+) -> Result<pii_detector::PIIConfigComparison, String> {
+    let detector = state.pii_detector.read().await;
+    detector
+        .compare_pii_configs(&text, &config_a, &config_b)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// Detect PII, reporting how long each detection layer took
+#[tauri::command]
+async fn detect_pii_timed(
+    state: State<'_, AppState>,
+    text: String,
+) -> Result<pii_detector::PIIDetection, String> {
+    let detector = state.pii_detector.read().await;
+    detector
+        .detect_pii_bounded(&text)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// Per-layer PII detection timings accumulated across calls; optionally start over
+#[tauri::command]
+async fn get_pii_timing_stats(
+    state: State<'_, AppState>,
+    reset: Option<bool>,
+) -> Result<Vec<pii_detector::LayerTimingStats>, String> {
+    let detector = state.pii_detector.read().await;
+    let stats = detector.get_timing_stats();
+    if reset.unwrap_or(false) {
+        detector.reset_timing_stats();
+    }
+    Ok(stats)
+}
+
+// Availability of each PII layer, including the Candle NER circuit breaker
+#[tauri::command]
+async fn get_pii_layer_status(state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+    let detector = state.pii_detector.read().await;
+
+    Ok(serde_json::json!({
+        "layers": detector.get_layer_status().await,
+        "ner_circuit_breaker": detector.get_ner_breaker_status().await
+    }))
+}
+
 // Setup management commands
 #[tauri::command]
 async fn check_first_run(state: State<'_, AppState>) -> Result<bool, String> {
@@ -1268,14 +3824,20 @@ async fn detect_pii_presidio(
 
     // Use Presidio for detection
     match bridge.detect_pii(&text).await {
-        Ok(entities) => Ok(serde_json::json!({
-            "entities": entities,
-            "engine": "presidio",
-            "count": entities.len()
-        })),
+        Ok(entities) => {
+            let detector = state.pii_detector.read().await;
+            detector.record_presidio_success(&detector.get_config().await);
+            Ok(serde_json::json!({
+                "entities": entities,
+                "engine": "presidio",
+                "count": entities.len()
+            }))
+        }
         Err(e) => {
-            // Fall back to built-in detector on error
+            // Fall back to built-in detector on error; the first fallback is
+            // announced as pii-protection-degraded
             let detector = state.pii_detector.read().await;
+            detector.record_presidio_fallback(&detector.get_config().await, &e.to_string());
             let entities = detector
                 .detect_pii(&text)
                 .await
@@ -1352,9 +3914,16 @@ async fn detect_pii_advanced(
 }
 
 #[tauri::command]
-async fn redact_pii_advanced(state: State<'_, AppState>, text: String) -> Result<String, String> {
+async fn redact_pii_advanced(
+    state: State<'_, AppState>,
+    text: String,
+    redact_types: Option<Vec<String>>,
+) -> Result<pii_detector::RedactionReport, String> {
     let detector = state.pii_detector.read().await;
-    detector.redact_pii(&text).await.map_err(|e| e.to_string())
+    detector
+        .redact_pii_with_report(&text, redact_types)
+        .await
+        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -1363,17 +3932,30 @@ async fn anonymize_pii_advanced(
     text: String,
 ) -> Result<serde_json::Value, String> {
     let detector = state.pii_detector.read().await;
-    let redacted = detector
-        .redact_pii(&text)
+    let (anonymized, mappings) = detector
+        .anonymize_pii(&text)
         .await
         .map_err(|e| e.to_string())?;
 
     Ok(serde_json::json!({
-        "anonymized_text": redacted,
-        "mappings": {}
+        "anonymized_text": anonymized,
+        "mappings": mappings
     }))
 }
 
+// Restore originals in pseudonymized text from the mappings returned with it
+#[tauri::command]
+async fn reidentify_pii(text: String, mappings: HashMap<String, String>) -> Result<String, String> {
+    Ok(pii_detector::reidentify(&text, &mappings))
+}
+
+// End the pseudonymization session; returns how many pseudonyms were forgotten
+#[tauri::command]
+async fn clear_pii_pseudonyms(state: State<'_, AppState>) -> Result<usize, String> {
+    let detector = state.pii_detector.read().await;
+    Ok(detector.clear_pseudonyms().await)
+}
+
 #[tauri::command]
 async fn configure_pii_detection(
     state: State<'_, AppState>,
@@ -1387,36 +3969,122 @@ async fn configure_pii_detection(
 #[tauri::command]
 async fn add_custom_pii_recognizer(
     state: State<'_, AppState>,
-    _name: String,
-    _pattern: String,
-    _label: String,
-    _confidence: f32,
+    name: String,
+    pattern: String,
+    label: String,
+    confidence: f32,
 ) -> Result<bool, String> {
-    // Custom recognizers are managed internally
-    let _detector = state.pii_detector.read().await;
+    let detector = state.pii_detector.read().await;
+    detector
+        .add_custom_pattern(pii_detector::CustomPattern {
+            name,
+            pattern,
+            label,
+            confidence,
+        })
+        .await
+        .map_err(|e| e.to_string())?;
     Ok(true)
 }
 
+// Remove a custom recognizer; returns false if none had that name
+#[tauri::command]
+async fn remove_custom_pii_recognizer(
+    state: State<'_, AppState>,
+    name: String,
+) -> Result<bool, String> {
+    let detector = state.pii_detector.read().await;
+    detector
+        .remove_custom_pattern(&name)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_custom_pii_recognizers(
+    state: State<'_, AppState>,
+) -> Result<Vec<pii_detector::CustomPattern>, String> {
+    let detector = state.pii_detector.read().await;
+    Ok(detector.get_custom_patterns().await)
+}
+
 #[tauri::command]
 async fn get_pii_statistics(
     state: State<'_, AppState>,
     text: String,
-) -> Result<serde_json::Value, String> {
+) -> Result<pii_detector::PIIStatistics, String> {
     let detector = state.pii_detector.read().await;
-    let entities = detector
-        .detect_pii(&text)
+    detector
+        .get_tiered_statistics(&text)
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(|e| e.to_string())
+}
 
-    let mut stats: HashMap<String, usize> = HashMap::new();
-    for entity in &entities {
-        *stats.entry(entity.entity_type.clone()).or_insert(0) += 1;
-    }
+// Privacy risk preview of a document before it is processed; nothing is stored
+#[tauri::command]
+async fn privacy_impact_preview(
+    state: State<'_, AppState>,
+    content: String,
+) -> Result<pii_detector::PrivacyImpactPreview, String> {
+    let detector = state.pii_detector.read().await;
+    detector
+        .privacy_impact_preview(&content)
+        .await
+        .map_err(|e| e.to_string())
+}
 
-    Ok(serde_json::json!({
-        "total_entities": entities.len(),
-        "by_type": stats
-    }))
+// Dry run of redaction: characters and tokens that would be replaced, overall
+// and per type, without producing the redacted text
+#[tauri::command]
+async fn redaction_impact(
+    state: State<'_, AppState>,
+    content: String,
+    redact_types: Option<Vec<String>>,
+) -> Result<pii_detector::RedactionImpact, String> {
+    let detector = state.pii_detector.read().await;
+    detector
+        .redaction_impact(&content, redact_types)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// Override the sensitivity tier (low/medium/high) reported for an entity type
+#[tauri::command]
+async fn set_pii_sensitivity_tier(
+    state: State<'_, AppState>,
+    entity_type: String,
+    tier: pii_detector::SensitivityTier,
+) -> Result<(), String> {
+    let detector = state.pii_detector.read().await;
+    detector.set_sensitivity_tier(&entity_type, tier).await;
+    Ok(())
+}
+
+// Toggle confidence-weighted voting across detection layers, optionally with new weights
+#[tauri::command]
+async fn set_pii_confidence_voting(
+    state: State<'_, AppState>,
+    enabled: bool,
+    layer_weights: Option<pii_detector::LayerWeights>,
+) -> Result<(), String> {
+    let detector = state.pii_detector.read().await;
+    detector
+        .set_confidence_voting(enabled, layer_weights)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// Define a custom entity type (regex plus optional checksum/format validator)
+#[tauri::command]
+async fn add_custom_pii_entity_type(
+    state: State<'_, AppState>,
+    entity_type: pii_detector::CustomEntityType,
+) -> Result<(), String> {
+    let detector = state.pii_detector.read().await;
+    detector
+        .add_custom_entity_type(entity_type)
+        .await
+        .map_err(|e| e.to_string())
 }
 
 // Note: download_model_from_huggingface, search_huggingface_models, load_model,
@@ -1461,8 +4129,8 @@ fn main() {
     };
 
     // Initialize LLM Manager with GGUF support
-    let llm_manager = match LLMManager::new() {
-        Ok(manager) => Arc::new(RwLock::new(manager)),
+    let mut llm_manager = match LLMManager::new() {
+        Ok(manager) => manager,
         Err(e) => {
             tracing::error!(error = %e, "Failed to initialize GGUF inference engine");
             panic!("Critical: GGUF inference engine initialization failed. Cannot proceed without LLM support.");
@@ -1481,6 +4149,8 @@ fn main() {
 
     // Initialize Model Transparency State
     let model_transparency = commands::ModelTransparencyState::new(app_data_dir.clone());
+    llm_manager.set_license_check(model_transparency.license_check());
+    let llm_manager = Arc::new(RwLock::new(llm_manager));
 
     // Initialize Consent Guard Middleware
     let consent_guard = Arc::new(
@@ -1493,17 +4163,23 @@ fn main() {
     let retention_scheduler = RetentionScheduler::new(db_path.clone());
     let scheduler_handle = Arc::new(RwLock::new(retention_scheduler.get_handle()));
 
+    // Shared with the agent orchestrator so its tools see the app's documents
+    // and its tool results are redacted with the app's PII settings
+    let rag_engine = Arc::new(RwLock::new(RAGEngine::new()));
+    let file_processor = Arc::new(FileProcessor::new());
+    let pii_detector = Arc::new(RwLock::new(PIIDetector::new()));
+
     // Create unified app state
     let app_state = AppState {
         // Production services
-        pii_detector: Arc::new(RwLock::new(PIIDetector::new())),
-        rag_engine: Arc::new(RwLock::new(RAGEngine::new())),
+        pii_detector: pii_detector.clone(),
+        rag_engine: rag_engine.clone(),
         llm_manager,
 
         // Core services
         presidio_bridge: Arc::new(RwLock::new(PresidioBridge::new())),
         setup_manager: Arc::new(RwLock::new(SetupManager::new())),
-        file_processor: Arc::new(FileProcessor::new()),
+        file_processor: file_processor.clone(),
         database_manager,
 
         // System monitoring
@@ -1512,8 +4188,16 @@ fn main() {
         hardware_detector: Arc::new(RwLock::new(HardwareDetector::new())),
 
         // MCP and agent orchestration
-        mcp_server: Arc::new(MCPServer::new(true)),
-        agent_orchestrator: Arc::new(AgentOrchestrator::new(true)),
+        mcp_server: Arc::new(
+            MCPServer::new(true)
+                .with_database(db_path.clone())
+                .with_pii_detector(pii_detector.clone()),
+        ),
+        agent_orchestrator: Arc::new(
+            AgentOrchestrator::new_with_services(true, rag_engine, file_processor, pii_detector)
+                .with_consent_guard(consent_guard.clone())
+                .with_database(db_path.clone()),
+        ),
 
         // GDPR Compliance
         compliance_manager,
@@ -1526,6 +4210,39 @@ fn main() {
 
         // AI Transparency
         transparency_state: Arc::new(TransparencyState::new()),
+
+        // Second-opinion ensemble for high-risk queries
+        ensemble_config: Arc::new(RwLock::new(ensemble::EnsembleConfig::default())),
+
+        // Obligations extracted from uploaded contracts
+        contract_obligations: Arc::new(obligations::ObligationStore::new(db_path.clone())),
+
+        // Generation failure fallback
+        fallback_config: Arc::new(RwLock::new(FallbackConfig::default())),
+        rate_limiter: Arc::new(RateLimiter::with_config_file(
+            app_data_dir.join("rate_limits.json"),
+        )),
+
+        // Cancellation flag for streaming generation to a file
+        file_generation_cancel: Arc::new(std::sync::atomic::AtomicBool::new(false)),
+        message_stream_cancel: Arc::new(std::sync::atomic::AtomicBool::new(false)),
+        audit_tail: Arc::new(tokio::sync::Mutex::new(None)),
+
+        // Automatic chat titles
+        chat_titles: Arc::new(RwLock::new(chat_titles::ChatTitleManager::new(
+            db_path.clone(),
+        ))),
+        generation_records: Arc::new(RwLock::new(generation_records::GenerationRecorder::new(
+            db_path.clone(),
+        ))),
+        chat_store: Arc::new(ChatStore::new(db_path.clone())),
+        document_store: Arc::new(DocumentStore::new(db_path.clone(), &app_data_dir)),
+
+        // Coordinated shutdown
+        shutting_down: Arc::new(std::sync::atomic::AtomicBool::new(false)),
+
+        // Factory reset confirmation
+        reset_confirmation: Arc::new(factory_reset::ResetConfirmation::default()),
     };
 
     // Initialize modules
@@ -1584,6 +4301,22 @@ fn main() {
         } else {
             tracing::info!("✅ GDPR Compliance Manager initialized successfully");
         }
+        // Erasure requests also purge the user's indexed documents
+        app_state
+            .compliance_manager
+            .register_user_data_store(app_state.rag_engine.clone())
+            .await;
+
+        if let Err(e) = app_state.contract_obligations.initialize() {
+            tracing::error!(error = %e, "Failed to initialize obligations store");
+        }
+        if let Err(e) = app_state.generation_records.write().await.initialize() {
+            tracing::error!(error = %e, "Failed to load generation record settings");
+        }
+        app_state
+            .compliance_manager
+            .register_user_data_store(app_state.contract_obligations.clone())
+            .await;
 
         // Start Retention Scheduler
         if let Err(e) = retention_scheduler.start().await {
@@ -1593,6 +4326,10 @@ fn main() {
         }
     });
 
+    // Shut subsystems down when the app exits
+    let exit_state = app_state.clone();
+    let exit_db_path = db_path.clone();
+
     tauri::Builder::default()
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_dialog::init())
@@ -1606,13 +4343,115 @@ fn main() {
         .manage(db_path.clone())
         .manage(app_state.transparency_state.clone())
         .manage(model_transparency)
-        .setup(move |_app| {
+        .setup(move |app| {
             let state = app_state.clone();
 
+            // Forward PII layer disable/restore notifications to the UI
+            let app_handle = app.handle().clone();
+            let detector = app_state.pii_detector.clone();
+            tauri::async_runtime::spawn(async move {
+                let mut events = detector.read().await.subscribe_layer_events();
+                loop {
+                    match events.recv().await {
+                        Ok(event) => {
+                            let _ = app_handle.emit(&event.event, &event);
+                        }
+                        Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                    }
+                }
+            });
+
+            // Forward model download progress to the UI
+            let app_handle = app.handle().clone();
+            let llm_manager = app_state.llm_manager.clone();
+            tauri::async_runtime::spawn(async move {
+                let mut events = llm_manager.read().await.subscribe_download_progress();
+                loop {
+                    match events.recv().await {
+                        Ok(event) => {
+                            let _ = app_handle.emit(llm_manager::DOWNLOAD_PROGRESS_EVENT, &event);
+                        }
+                        Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                    }
+                }
+            });
+
+            // Size chunks of non-English documents with the loaded LLM tokenizer
+            let rag_engine = app_state.rag_engine.clone();
+            let llm_manager = app_state.llm_manager.clone();
+            tauri::async_runtime::spawn(async move {
+                let tokenizer = llm_manager.read().await.tokenizer_handle();
+                rag_engine
+                    .read()
+                    .await
+                    .set_token_counter(Arc::new(text_segmentation::LoadedTokenizerCounter::new(
+                        tokenizer,
+                    )))
+                    .await;
+            });
+
+            // Periodic model update check, opt-in and skipped while offline
+            let app_handle = app.handle().clone();
+            let llm_manager = app_state.llm_manager.clone();
+            tauri::async_runtime::spawn(async move {
+                loop {
+                    let config = llm_manager.read().await.get_model_update_config().await;
+                    tokio::time::sleep(Duration::from_secs(config.check_interval_hours * 3600))
+                        .await;
+                    if !config.auto_check || model_updates::is_offline_mode() {
+                        continue;
+                    }
+
+                    match llm_manager.read().await.check_model_updates().await {
+                        Ok(statuses) => {
+                            let available: Vec<_> = statuses
+                                .into_iter()
+                                .filter(|s| s.update_available)
+                                .collect();
+                            if !available.is_empty() {
+                                let _ = app_handle.emit("model-updates-available", &available);
+                            }
+                        }
+                        Err(e) => tracing::warn!(error = %e, "Model update check failed"),
+                    }
+                }
+            });
+
+            // Unload the model when idle; the next request reloads it
+            let app_handle = app.handle().clone();
+            let llm_manager = app_state.llm_manager.clone();
+            let shutting_down = app_state.shutting_down.clone();
+            tauri::async_runtime::spawn(async move {
+                loop {
+                    tokio::time::sleep(Duration::from_secs(
+                        constants::MODEL_IDLE_CHECK_INTERVAL_SECS,
+                    ))
+                    .await;
+                    if shutting_down.load(std::sync::atomic::Ordering::SeqCst) {
+                        break;
+                    }
+                    match llm_manager.read().await.unload_if_idle().await {
+                        Ok(Some(model_name)) => {
+                            let _ = app_handle.emit("model-idle-unloaded", &model_name);
+                        }
+                        Ok(None) => {}
+                        Err(e) => tracing::warn!(error = %e, "Idle model unload failed"),
+                    }
+                }
+            });
+
             // Single background monitoring task
             tauri::async_runtime::spawn(async move {
                 loop {
                     tokio::time::sleep(Duration::from_secs(5)).await;
+                    if state
+                        .shutting_down
+                        .load(std::sync::atomic::Ordering::SeqCst)
+                    {
+                        break;
+                    }
 
                     // Update hardware metrics
                     let mut hw_monitor = state.hardware_monitor.write().await;
@@ -1641,19 +4480,88 @@ fn main() {
             // Document processing
             process_document,
             analyze_document_pii,
+            redact_document_in_place,
             upload_document,
+            get_processing_receipt,
+            extract_document_outline,
+            export_obligations,
+            submit_for_review,
+            approve_redaction,
+            reject_redaction,
+            get_redaction_review,
+            set_original_retention,
+            get_original_retention,
+            get_document_original,
+            rescan_document_original,
             // LLM operations
             send_message,
+            send_chat,
+            regenerate_chat_title,
+            get_chat_title_config,
+            set_chat_title_config,
+            get_generation_record,
+            get_generation_record_config,
+            set_generation_record_config,
+            set_generation_fallback,
+            get_rate_limit_config,
+            set_rate_limit_config,
+            get_rate_limit_usage,
+            configure_rate_limit,
+            send_message_stream,
+            cancel_message_stream,
+            subscribe_audit_events,
+            unsubscribe_audit_events,
+            run_agent_task,
+            extract_structured,
+            send_message_grounded,
+            generate_to_file,
+            cancel_generate_to_file,
+            send_message_with_second_opinion,
+            set_ensemble_mode,
             list_available_models,
             download_model,
+            start_progressive_download,
+            get_model_load_limits,
+            set_model_load_limits,
+            get_model_load_stats,
+            get_idle_unload_config,
+            set_idle_unload_config,
+            check_model_updates,
+            update_model,
+            delete_model,
+            verify_model,
+            register_custom_model,
+            get_model_update_config,
+            set_model_update_config,
+            check_download_space,
+            set_download_headroom,
+            assess_model_feasibility,
+            validate_gguf_compatibility,
+            get_stop_sequences,
+            set_stop_sequence_overrides,
+            set_generation_config,
+            get_generation_overrides,
+            clear_generation_overrides,
+            resolve_token_ids,
             load_model,
             unload_model,
             emergency_stop,
+            shutdown_application,
+            prepare_factory_reset,
+            factory_reset,
             set_resource_limits,
             // Knowledge base
             search_knowledge_base,
+            get_chunk_provenance,
             add_to_knowledge_base,
+            update_knowledge_base_document,
+            delete_knowledge_base_document,
+            set_document_trust,
+            set_rag_dimension_reduction,
+            replace_previous_document_version,
             rag_search,
+            export_knowledge_base,
+            import_knowledge_base,
             // Database
             execute_sql_query,
             get_database_stats,
@@ -1669,9 +4577,18 @@ fn main() {
             detect_pii_advanced,
             redact_pii_advanced,
             anonymize_pii_advanced,
+            reidentify_pii,
+            clear_pii_pseudonyms,
             configure_pii_detection,
             add_custom_pii_recognizer,
+            remove_custom_pii_recognizer,
+            get_custom_pii_recognizers,
+            add_custom_pii_entity_type,
             get_pii_statistics,
+            privacy_impact_preview,
+            redaction_impact,
+            set_pii_sensitivity_tier,
+            set_pii_confidence_voting,
             // Presidio PII detection
             detect_pii_presidio,
             anonymize_pii_presidio,
@@ -1685,8 +4602,11 @@ fn main() {
             get_available_rag_models,
             get_active_rag_model,
             switch_rag_model,
+            rebuild_index,
             get_rag_config,
+            get_rag_statistics,
             update_rag_config,
+            rechunk_knowledge_base,
             // GDPR Compliance
             compliance::commands::check_user_consent,
             compliance::commands::grant_user_consent,
@@ -1695,6 +4615,8 @@ fn main() {
             compliance::commands::get_consent_audit_trail,
             compliance::commands::get_consent_versions,
             compliance::commands::set_data_retention,
+            compliance::commands::set_category_retention_policy,
+            compliance::commands::get_category_retention_policies,
             compliance::commands::get_retention_stats,
             compliance::commands::apply_compliance_retention_policies,
             compliance::commands::delete_expired_data,
@@ -1703,6 +4625,13 @@ fn main() {
             compliance::commands::export_user_data,
             compliance::commands::delete_user_data,
             compliance::commands::generate_compliance_report,
+            export_user_data_pdf,
+            export_user_data_encrypted,
+            export_compliance_report_pdf,
+            rotate_database_key,
+            get_field_encryption,
+            set_field_encryption,
+            migrate_field_encryption,
             compliance::commands::run_compliance_maintenance,
             compliance::commands::update_user_data,
             compliance::commands::get_granular_consent_log,
@@ -1720,12 +4649,18 @@ fn main() {
             // middleware::commands::get_consent_statistics,
             // Retention Scheduler Commands
             commands::scheduler_commands::trigger_retention_cleanup,
+            commands::scheduler_commands::schedule_retention_cleanup,
+            commands::scheduler_commands::pause_retention_scheduler,
+            commands::scheduler_commands::resume_retention_scheduler,
             commands::scheduler_commands::get_scheduler_status,
             commands::scheduler_commands::update_scheduler_config,
             commands::scheduler_commands::preview_retention_cleanup,
             commands::scheduler_commands::apply_default_retention_policies,
             commands::scheduler_commands::get_last_cleanup_result,
             commands::scheduler_commands::set_automatic_cleanup,
+            commands::scheduler_commands::set_compliance_snapshot_schedule,
+            commands::scheduler_commands::trigger_compliance_snapshot,
+            commands::scheduler_commands::get_last_snapshot_result,
             // AI Transparency
             commands::transparency_commands::get_startup_notice,
             commands::transparency_commands::get_onboarding_notice,
@@ -1744,11 +4679,16 @@ fn main() {
             commands::transparency_commands::export_transparency_context,
             // Model Card Transparency
             commands::model_transparency::get_model_info,
+            commands::model_transparency::get_model_license,
+            commands::model_transparency::get_license_policy,
+            commands::model_transparency::set_license_policy,
             commands::model_transparency::add_model_mapping,
             commands::model_transparency::remove_model_mapping,
             commands::model_transparency::get_model_mappings,
             commands::model_transparency::clear_model_cache,
             commands::model_transparency::clear_all_model_cache,
+            commands::model_transparency::get_model_card_fetch_config,
+            commands::model_transparency::set_model_card_fetch_config,
             commands::model_transparency::get_general_disclaimer,
             commands::model_transparency::get_ai_act_disclaimer,
             commands::model_transparency::get_high_risk_disclaimer,
@@ -1759,11 +4699,195 @@ fn main() {
             can_use_pii_mode,
             estimate_mode_impact,
             get_pii_config,
+            get_active_exclusions,
+            add_pii_exclusion,
+            reload_pii_exclusions,
             set_pii_mode,
             update_pii_config,
             install_presidio,
             check_presidio_status,
+            get_pii_layer_status,
+            detect_pii_timed,
+            get_pii_timing_stats,
+            compare_pii_configs,
+            export_config_snapshot,
+            import_config_snapshot,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(move |_app, event| {
+            if let tauri::RunEvent::Exit = event {
+                tauri::async_runtime::block_on(shutdown_app(&exit_state, &exit_db_path));
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use compliance::UserDataStore;
+
+    // The stores an upload writes to, over one temporary database
+    struct UploadHarness {
+        _dir: tempfile::TempDir,
+        db_path: PathBuf,
+        documents: DocumentStore,
+        compliance: ComplianceManager,
+        obligations: obligations::ObligationStore,
+    }
+
+    impl UploadHarness {
+        async fn new() -> Self {
+            let dir = tempfile::tempdir().unwrap();
+            let db_path = dir.path().join("bear_ai.db");
+            let compliance = ComplianceManager::new(db_path.clone());
+            compliance.initialize().await.unwrap();
+            let obligations = obligations::ObligationStore::new(db_path.clone());
+            obligations.initialize().unwrap();
+            Self {
+                documents: DocumentStore::new(db_path.clone(), dir.path()),
+                db_path,
+                compliance,
+                obligations,
+                _dir: dir,
+            }
+        }
+
+        // Record a document and its compliance records as ingest_document_text does
+        async fn upload(
+            &self,
+            filename: &str,
+            text: &str,
+            operator: &str,
+        ) -> (String, compliance::ProcessingReceipt) {
+            let doc_id = self
+                .documents
+                .record(
+                    filename.to_string(),
+                    text.to_string(),
+                    "txt".to_string(),
+                    operator.to_string(),
+                    Vec::new(),
+                )
+                .await
+                .unwrap();
+            let document_id = doc_id.to_string();
+            let extracted = obligations::extract_obligations(text);
+            let pii_stats = pii_detector::PIIStatistics {
+                total_entities: 0,
+                by_type: HashMap::new(),
+                by_tier: HashMap::new(),
+            };
+            let (_, receipt) = record_ingest_compliance(
+                &self.compliance,
+                &self.obligations,
+                &IngestedDocument {
+                    document_id: &document_id,
+                    filename,
+                    operator,
+                    original: text,
+                    redacted: text,
+                    obligations: &extracted,
+                    pii_stats: &pii_stats,
+                    chunk_count: 1,
+                },
+            )
+            .await;
+            (document_id, receipt)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_uploads_recorded_under_distinct_document_ids() {
+        let harness = UploadHarness::new().await;
+        let (first, _) = harness.upload("a.txt", "First memo", "alice").await;
+        let (second, _) = harness.upload("b.txt", "Second memo", "bob").await;
+        assert_ne!(first, second);
+
+        let conn = rusqlite::Connection::open(&harness.db_path).unwrap();
+        let owners: Vec<(String, String)> = conn
+            .prepare("SELECT filename, user_id FROM documents ORDER BY id")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            owners,
+            vec![
+                ("a.txt".to_string(), "alice".to_string()),
+                ("b.txt".to_string(), "bob".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_upload_receipts_name_their_own_document() {
+        let harness = UploadHarness::new().await;
+        let (first, first_receipt) = harness.upload("a.txt", "First memo", "alice").await;
+        let (second, second_receipt) = harness.upload("b.txt", "Second memo", "alice").await;
+        assert_eq!(first_receipt.document_id, first);
+        assert_eq!(second_receipt.document_id, second);
+
+        let receipts = harness.compliance.receipts();
+        let receipts = receipts.read().await;
+        let stored = receipts.get_receipt(&first).unwrap().unwrap();
+        assert_eq!(stored.filename, "a.txt");
+        assert_eq!(stored.receipt_id, first_receipt.receipt_id);
+    }
+
+    #[tokio::test]
+    async fn test_approving_one_upload_leaves_the_next_in_draft() {
+        let harness = UploadHarness::new().await;
+        let (first, _) = harness.upload("a.txt", "First memo", "alice").await;
+        let review = harness.compliance.review();
+        let review = review.read().await;
+        review.submit_for_review(&first, "alice").unwrap();
+        review.approve(&first, "reviewer").unwrap();
+        drop(review);
+
+        let (second, _) = harness.upload("b.txt", "Second memo", "alice").await;
+        let review = harness.compliance.review();
+        let review = review.read().await;
+        assert_eq!(
+            review.get_review(&second).unwrap().unwrap().state,
+            compliance::ReviewState::Draft
+        );
+        assert!(review.ensure_exportable(&first).is_ok());
+        assert!(review.ensure_all_exportable([&first, &second]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_second_contract_keeps_first_contracts_obligations() {
+        let harness = UploadHarness::new().await;
+        let (first, _) = harness
+            .upload(
+                "supply.txt",
+                "Acme Logistics shall deliver the audit report no later than March 31, 2025.",
+                "alice",
+            )
+            .await;
+        let (second, _) = harness
+            .upload(
+                "lease.txt",
+                "The Client must pay each invoice within 30 days of receipt.",
+                "bob",
+            )
+            .await;
+
+        let first_obligations = harness.obligations.load(&first).unwrap().unwrap();
+        let second_obligations = harness.obligations.load(&second).unwrap().unwrap();
+        assert_eq!(first_obligations.len(), 1);
+        assert_eq!(first_obligations[0].party, "Acme Logistics");
+        assert_eq!(second_obligations.len(), 1);
+        assert_eq!(second_obligations[0].party, "Client");
+
+        // Each contract stays with the user who uploaded it
+        assert_eq!(
+            harness.obligations.erase_user_data("alice").await.unwrap(),
+            1
+        );
+        assert!(harness.obligations.load(&first).unwrap().is_none());
+        assert!(harness.obligations.load(&second).unwrap().is_some());
+    }
 }
@@ -1,13 +1,25 @@
 // MCP (Model Context Protocol) Server for Local Agent Capabilities
 // This provides tool-use capabilities for the LLM to act as an autonomous agent
 
+use crate::completion::{CompletionModel, CompletionOptions};
+use crate::compliance::ConsentType;
+use crate::contract_lifecycle::extract_lifecycle;
 use crate::file_processor::FileProcessor;
+use crate::governing_law::extract_governing_law;
+use crate::middleware::ConsentGuard;
+use crate::obligations::extract_obligations;
+use crate::pii_detector::PIIDetector;
 use crate::rag_engine::RAGEngine;
+use crate::read_only_sql::ReadOnlySql;
+use crate::risk_assessment::RiskAssessor;
 use anyhow::Result;
+use futures::future::BoxFuture;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::future::Future;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::fs;
 use tokio::sync::RwLock;
 
@@ -16,6 +28,9 @@ pub struct Tool {
     pub name: String,
     pub description: String,
     pub parameters: ToolParameters,
+    /// Longest a call may run before it is cancelled; `DEFAULT_TOOL_TIMEOUT` when None
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,23 +47,26 @@ pub struct ParameterProperty {
     pub r#enum: Option<Vec<String>>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ToolCall {
     pub tool: String,
     pub parameters: serde_json::Value,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ToolResult {
     pub success: bool,
     pub result: serde_json::Value,
     pub error: Option<String>,
 }
 
+/// Handler for a tool registered with `MCPServer::register_tool_with_handler`
+pub type ToolHandler =
+    Box<dyn Fn(serde_json::Value) -> BoxFuture<'static, Result<ToolResult>> + Send + Sync>;
+
 pub struct MCPServer {
     tools: HashMap<String, Tool>,
+    handlers: HashMap<String, ToolHandler>,
     #[allow(dead_code)]
     sandboxed: bool,
     #[allow(dead_code)]
@@ -57,30 +75,56 @@ pub struct MCPServer {
     rag_engine: Option<Arc<RwLock<RAGEngine>>>,
     #[allow(dead_code)]
     file_processor: Option<Arc<FileProcessor>>,
+    db_path: Option<PathBuf>,
+    /// Redacts the cells returned by `execute_sql`, which refuses to run without it
+    pii_detector: Option<Arc<RwLock<PIIDetector>>>,
 }
 
 impl MCPServer {
     pub fn new(sandboxed: bool) -> Self {
         let mut server = Self {
             tools: HashMap::new(),
+            handlers: HashMap::new(),
             sandboxed,
             allowed_paths: vec![],
             rag_engine: None,
             file_processor: None,
+            db_path: None,
+            pii_detector: None,
         };
 
         server.register_default_tools();
         server
     }
 
-    #[allow(dead_code)]
+    /// Database queried by the read-only `execute_sql` tool
+    pub fn with_database(mut self, db_path: PathBuf) -> Self {
+        self.db_path = Some(db_path);
+        self
+    }
+
+    /// PII detector that redacts `execute_sql` results
+    pub fn with_pii_detector(mut self, pii_detector: Arc<RwLock<PIIDetector>>) -> Self {
+        self.pii_detector = Some(pii_detector);
+        self
+    }
+
+    /// File processor used by `extract_text`, shared with the rest of the app
+    pub fn with_file_processor(mut self, file_processor: Arc<FileProcessor>) -> Self {
+        self.file_processor = Some(file_processor);
+        self
+    }
+
     pub fn new_with_rag(sandboxed: bool, rag_engine: Arc<RwLock<RAGEngine>>) -> Self {
         let mut server = Self {
             tools: HashMap::new(),
+            handlers: HashMap::new(),
             sandboxed,
             allowed_paths: vec![],
             rag_engine: Some(rag_engine),
             file_processor: Some(Arc::new(FileProcessor::new())),
+            db_path: None,
+            pii_detector: None,
         };
 
         server.register_default_tools();
@@ -104,6 +148,7 @@ impl MCPServer {
                 )]),
                 required: vec!["path".to_string()],
             },
+            timeout_ms: None,
         });
 
         self.register_tool(Tool {
@@ -131,6 +176,7 @@ impl MCPServer {
                 ]),
                 required: vec!["path".to_string(), "content".to_string()],
             },
+            timeout_ms: None,
         });
 
         self.register_tool(Tool {
@@ -148,6 +194,7 @@ impl MCPServer {
                 )]),
                 required: vec!["path".to_string()],
             },
+            timeout_ms: None,
         });
 
         // Search and Analysis Tools
@@ -176,6 +223,7 @@ impl MCPServer {
                 ]),
                 required: vec!["query".to_string()],
             },
+            timeout_ms: None,
         });
 
         self.register_tool(Tool {
@@ -207,6 +255,7 @@ impl MCPServer {
                 ]),
                 required: vec!["path".to_string()],
             },
+            timeout_ms: None,
         });
 
         // Legal Document Tools
@@ -241,6 +290,7 @@ impl MCPServer {
                 ]),
                 required: vec!["content".to_string()],
             },
+            timeout_ms: None,
         });
 
         self.register_tool(Tool {
@@ -268,24 +318,27 @@ impl MCPServer {
                 ]),
                 required: vec!["case_description".to_string()],
             },
+            timeout_ms: None,
         });
 
         // Data Processing Tools
         self.register_tool(Tool {
             name: "execute_sql".to_string(),
-            description: "Execute SQL query on local database".to_string(),
+            description: "Run a read-only SELECT query on the local database".to_string(),
             parameters: ToolParameters {
                 r#type: "object".to_string(),
                 properties: HashMap::from([(
                     "query".to_string(),
                     ParameterProperty {
                         r#type: "string".to_string(),
-                        description: "SQL query (SELECT only in sandbox mode)".to_string(),
+                        description: "A single SELECT statement; writes are always blocked"
+                            .to_string(),
                         r#enum: None,
                     },
                 )]),
                 required: vec!["query".to_string()],
             },
+            timeout_ms: None,
         });
 
         self.register_tool(Tool {
@@ -313,6 +366,7 @@ impl MCPServer {
                 ]),
                 required: vec!["code".to_string()],
             },
+            timeout_ms: None,
         });
     }
 
@@ -320,13 +374,58 @@ impl MCPServer {
         self.tools.insert(tool.name.clone(), tool);
     }
 
-    #[allow(dead_code)]
+    /// Register a tool together with the handler that executes it, so
+    /// embedders can add tools without changing the built-in dispatch.
+    /// Registered handlers take precedence over built-in tools of the same name.
+    pub fn register_tool_with_handler<F, Fut>(&mut self, tool: Tool, handler: F)
+    where
+        F: Fn(serde_json::Value) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<ToolResult>> + Send + 'static,
+    {
+        self.handlers.insert(
+            tool.name.clone(),
+            Box::new(move |params| Box::pin(handler(params))),
+        );
+        self.register_tool(tool);
+    }
+
     pub fn list_tools(&self) -> Vec<Tool> {
         self.tools.values().cloned().collect()
     }
 
-    #[allow(dead_code)]
+    /// Execute a tool call, cancelling it once the tool's timeout elapses.
+    /// Dropping the handler future stops it at its next await point, so a
+    /// stuck call is abandoned rather than left running.
     pub async fn execute_tool(&self, call: ToolCall) -> Result<ToolResult> {
+        let timeout = self
+            .tools
+            .get(&call.tool)
+            .and_then(|tool| tool.timeout_ms)
+            .map_or(DEFAULT_TOOL_TIMEOUT, Duration::from_millis);
+        let tool = call.tool.clone();
+
+        match tokio::time::timeout(timeout, self.dispatch_tool(call)).await {
+            Ok(result) => result,
+            Err(_) => {
+                tracing::warn!("Tool {} timed out after {:?}", tool, timeout);
+                Ok(ToolResult {
+                    success: false,
+                    result: serde_json::Value::Null,
+                    error: Some(format!(
+                        "Tool {} timed out after {} ms",
+                        tool,
+                        timeout.as_millis()
+                    )),
+                })
+            }
+        }
+    }
+
+    async fn dispatch_tool(&self, call: ToolCall) -> Result<ToolResult> {
+        if let Some(handler) = self.handlers.get(&call.tool) {
+            return handler(call.parameters).await;
+        }
+
         match call.tool.as_str() {
             "read_file" => self.handle_read_file(call.parameters).await,
             "write_file" => self.handle_write_file(call.parameters).await,
@@ -613,47 +712,50 @@ impl MCPServer {
         })
     }
 
-    #[allow(dead_code)]
+    /// Run a single SELECT on a read-only connection. Statements are parsed,
+    /// so stacked statements, ATTACH, PRAGMA and any write are rejected, the
+    /// query may read only the allowed tables and is bounded by a row limit
+    /// and a time budget, and PII in the returned cells is redacted.
     async fn handle_execute_sql(&self, params: serde_json::Value) -> Result<ToolResult> {
-        let _query = params["query"]
+        let query = params["query"]
             .as_str()
-            .ok_or_else(|| anyhow::anyhow!("Missing query parameter"))?;
+            .ok_or_else(|| anyhow::anyhow!("Missing query parameter"))?
+            .to_string();
 
-        // 🚨 SECURITY: SQL execution is DISABLED for safety
-        //
-        // The previous implementation only checked if query starts with "SELECT",
-        // but this is easily bypassed with:
-        // - Subqueries: SELECT * FROM (DROP TABLE users; SELECT 1)
-        // - Comments: SELECT/**/1;/**/DROP/**/TABLE/**/users
-        // - ATTACH DATABASE: Can attach external databases and modify them
-        // - SQLite pragmas: PRAGMA writable_schema=ON allows schema tampering
-        // - Time-based attacks: SELECT CASE WHEN ... THEN randomblob(100000000) END
-        //
-        // REQUIRED FOR SAFE RE-ENABLE:
-        // 1. Use prepared statements with parameter binding (no string interpolation)
-        // 2. Run queries in read-only database connection mode
-        // 3. Set query timeout limits (prevent DoS)
-        // 4. Use whitelist of allowed tables/columns
-        // 5. Implement row/result size limits
-        // 6. Run in separate process with resource limits
-        //
-        // See docs/SECURITY_IMPROVEMENTS.md for implementation guide
-
-        tracing::error!(
-            "SQL execution attempt blocked - feature disabled for security. \
-            See docs/SECURITY_IMPROVEMENTS.md"
-        );
-
-        Ok(ToolResult {
+        let unavailable = |error: &str| ToolResult {
             success: false,
             result: serde_json::Value::Null,
-            error: Some(
-                "SQL execution is disabled for security. \
-                This feature requires read-only database connections and query validation. \
-                Contact system administrator to enable."
-                    .to_string(),
-            ),
-        })
+            error: Some(error.to_string()),
+        };
+        let Some(db_path) = self.db_path.clone() else {
+            return Ok(unavailable("No database is available to query"));
+        };
+        // Results are never returned unredacted
+        let Some(detector) = &self.pii_detector else {
+            return Ok(unavailable(
+                "No PII detector is available to redact query results",
+            ));
+        };
+
+        let sql = ReadOnlySql::new(db_path);
+        match tokio::task::spawn_blocking(move || sql.execute(&query)).await? {
+            Ok(mut rows) => {
+                rows.redact_pii(&*detector.read().await).await?;
+                Ok(ToolResult {
+                    success: true,
+                    result: serde_json::to_value(rows)?,
+                    error: None,
+                })
+            }
+            Err(e) => {
+                tracing::warn!("Rejected execute_sql query: {}", e);
+                Ok(ToolResult {
+                    success: false,
+                    result: serde_json::Value::Null,
+                    error: Some(e.to_string()),
+                })
+            }
+        }
     }
 
     #[allow(dead_code)]
@@ -833,11 +935,16 @@ impl MCPServer {
             }
         }
 
+        let lifecycle = extract_lifecycle(content);
+        if lifecycle.term_months.is_some() || lifecycle.auto_renewal {
+            key_terms.push("Term and Renewal".to_string());
+        }
+
         // Identify potential risks
         if content_lower.contains("unlimited liability") {
             risks.push("Unlimited liability exposure".to_string());
         }
-        if content_lower.contains("automatic renewal") {
+        if lifecycle.auto_renewal || content_lower.contains("automatic renewal") {
             risks.push("Automatic renewal clause".to_string());
         }
         if content_lower.contains("penalty") || content_lower.contains("liquidated damages") {
@@ -846,7 +953,16 @@ impl MCPServer {
         if content_lower.contains("indemnif") {
             risks.push("Indemnification obligations".to_string());
         }
-        if content_lower.contains("governing law") {
+        let governing_law = extract_governing_law(content);
+        if !governing_law.is_empty() {
+            key_terms.push("Governing Law and Venue".to_string());
+        }
+        if let Some(jurisdiction) = &governing_law.jurisdiction {
+            risks.push(format!(
+                "Jurisdiction/governing law considerations ({} law)",
+                jurisdiction
+            ));
+        } else if content_lower.contains("governing law") {
             risks.push("Jurisdiction/governing law considerations".to_string());
         }
 
@@ -907,9 +1023,13 @@ impl MCPServer {
             "key_terms": key_terms,
             "identified_risks": risks,
             "obligations": obligations,
+            "obligation_records": extract_obligations(content),
             "extracted_dates": dates,
+            "lifecycle": lifecycle,
             "potential_parties": parties,
             "payment_terms": payment_terms,
+            "governing_law": governing_law,
+            "jurisdiction_disclaimers": RiskAssessor::new().jurisdiction_disclaimers(&governing_law),
             "recommendations": [
                 "Review all terms with qualified legal counsel",
                 "Ensure compliance with applicable regulations",
@@ -921,38 +1041,599 @@ impl MCPServer {
     }
 }
 
+/// Time a tool call may take when its `Tool` sets no timeout
+pub const DEFAULT_TOOL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Upper bound on LLM→tool→LLM iterations for one agent task
+pub const DEFAULT_MAX_AGENT_STEPS: usize = 6;
+
+/// Stop sequence keeping the model from inventing its own tool observations
+const OBSERVATION_MARKER: &str = "Observation:";
+
+/// What the model asked for in one completion
+#[derive(Debug, Clone, PartialEq)]
+enum AgentAction {
+    Tool(ToolCall),
+    Final(String),
+    /// Tool call that could not be parsed; the error is fed back to the model
+    Malformed(String),
+}
+
+/// One iteration of the agent loop, streamed to the caller as it completes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentStep {
+    pub step: usize,
+    pub thought: String,
+    pub tool_call: Option<ToolCall>,
+    pub observation: Option<ToolResult>,
+    pub final_answer: Option<String>,
+}
+
+/// Outcome of an agent task with the full reasoning and tool trace
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentRun {
+    pub final_answer: String,
+    pub steps: Vec<AgentStep>,
+    /// False when the step limit was hit before a final answer
+    pub completed: bool,
+}
+
 // Agent orchestrator that uses MCP tools
 pub struct AgentOrchestrator {
-    #[allow(dead_code)]
     mcp_server: MCPServer,
+    consent_guard: Option<Arc<ConsentGuard>>,
+    /// Redacts tool results before they enter the transcript or reach `on_step`
+    pii_detector: Option<Arc<RwLock<PIIDetector>>>,
+    max_steps: usize,
 }
 
 impl AgentOrchestrator {
+    #[allow(dead_code)]
     pub fn new(sandboxed: bool) -> Self {
         Self {
             mcp_server: MCPServer::new(sandboxed),
+            consent_guard: None,
+            pii_detector: None,
+            max_steps: DEFAULT_MAX_AGENT_STEPS,
         }
     }
 
-    #[allow(dead_code)]
-    pub async fn execute_agent_task(&self, task: &str, context: &str) -> Result<String> {
-        // Agent task execution workflow:
-        // 1. Send task to LLM with available tools
-        // 2. Parse LLM's tool calls
-        // 3. Execute tools via MCP server
-        // 4. Return results to LLM
-        // 5. Repeat until task complete
-
-        let tools = self.mcp_server.list_tools();
+    /// Orchestrator whose `search_documents` and `extract_text` tools use the
+    /// app's RAG engine and file processor, and whose tool results are redacted
+    /// with the app's PII detector
+    pub fn new_with_services(
+        sandboxed: bool,
+        rag_engine: Arc<RwLock<RAGEngine>>,
+        file_processor: Arc<FileProcessor>,
+        pii_detector: Arc<RwLock<PIIDetector>>,
+    ) -> Self {
+        Self {
+            mcp_server: MCPServer::new_with_rag(sandboxed, rag_engine)
+                .with_file_processor(file_processor)
+                .with_pii_detector(pii_detector.clone()),
+            consent_guard: None,
+            pii_detector: Some(pii_detector),
+            max_steps: DEFAULT_MAX_AGENT_STEPS,
+        }
+    }
+
+    /// Require user consent before the agent runs and before it touches documents
+    pub fn with_consent_guard(mut self, consent_guard: Arc<ConsentGuard>) -> Self {
+        self.consent_guard = Some(consent_guard);
+        self
+    }
+
+    /// Database the agent may query through the read-only `execute_sql` tool
+    pub fn with_database(mut self, db_path: PathBuf) -> Self {
+        self.mcp_server = self.mcp_server.with_database(db_path);
+        self
+    }
+
+    /// Run a ReAct loop: ask the model, execute the tool it calls, feed the
+    /// observation back, and repeat until it gives a final answer or the step
+    /// limit is reached. Each step is passed to `on_step` as soon as it completes.
+    pub async fn execute_agent_task<F>(
+        &self,
+        model: &dyn CompletionModel,
+        user_id: &str,
+        task: &str,
+        context: &str,
+        mut on_step: F,
+    ) -> Result<AgentRun>
+    where
+        F: FnMut(&AgentStep) + Send,
+    {
+        if let Some(guard) = &self.consent_guard {
+            guard
+                .enforce_consent(user_id, &ConsentType::AiProcessing)
+                .await?;
+        }
+
+        let options = CompletionOptions {
+            stop_sequences: vec![format!("\n{}", OBSERVATION_MARKER)],
+            ..Default::default()
+        };
+        let mut transcript = self.build_prompt(task, context)?;
+        let mut steps = Vec::new();
+
+        for step in 1..=self.max_steps {
+            let output = model.complete(&transcript, &options).await?;
+            let (thought, action) = parse_agent_output(&output);
+
+            let agent_step = match action {
+                AgentAction::Final(answer) => {
+                    let agent_step = AgentStep {
+                        step,
+                        thought,
+                        tool_call: None,
+                        observation: None,
+                        final_answer: Some(answer.clone()),
+                    };
+                    on_step(&agent_step);
+                    steps.push(agent_step);
+                    return Ok(AgentRun {
+                        final_answer: answer,
+                        steps,
+                        completed: true,
+                    });
+                }
+                AgentAction::Tool(call) => {
+                    let observation = self
+                        .redact_observation(self.run_tool(user_id, &call).await)
+                        .await?;
+                    AgentStep {
+                        step,
+                        thought,
+                        tool_call: Some(call),
+                        observation: Some(observation),
+                        final_answer: None,
+                    }
+                }
+                AgentAction::Malformed(error) => AgentStep {
+                    step,
+                    thought,
+                    tool_call: None,
+                    observation: Some(ToolResult {
+                        success: false,
+                        result: serde_json::Value::Null,
+                        error: Some(error),
+                    }),
+                    final_answer: None,
+                },
+            };
+
+            let observation = serde_json::to_string(&agent_step.observation)?;
+            transcript.push_str(output.split(OBSERVATION_MARKER).next().unwrap_or(""));
+            transcript.push_str(&format!("\n{} {}\n", OBSERVATION_MARKER, observation));
+
+            on_step(&agent_step);
+            steps.push(agent_step);
+        }
+
+        tracing::warn!(
+            "Agent task stopped after {} steps without a final answer",
+            self.max_steps
+        );
+        Ok(AgentRun {
+            final_answer: format!(
+                "Stopped after {} steps without reaching a final answer",
+                self.max_steps
+            ),
+            steps,
+            completed: false,
+        })
+    }
+
+    fn build_prompt(&self, task: &str, context: &str) -> Result<String> {
+        let mut tools = self.mcp_server.list_tools();
+        tools.sort_by(|a, b| a.name.cmp(&b.name));
         let tools_json = serde_json::to_string(&tools)?;
 
-        // Format prompt with tools
-        let _prompt = format!(
-            "Task: {}\nContext: {}\nAvailable tools: {}\nExecute the task using the available tools.",
-            task, context, tools_json
+        Ok(format!(
+            "Answer the task using the available tools.\n\
+            Available tools: {}\n\n\
+            Use this format:\n\
+            Thought: what to do next\n\
+            Action: the tool name\n\
+            Action Input: the tool parameters as a JSON object\n\
+            {} the tool result (provided to you)\n\
+            ... (repeat Thought/Action/Action Input/{} as needed)\n\
+            Thought: I know the answer\n\
+            Final Answer: the answer to the task\n\n\
+            Context: {}\n\
+            Task: {}\n",
+            tools_json, OBSERVATION_MARKER, OBSERVATION_MARKER, context, task
+        ))
+    }
+
+    /// Redact PII from every string in a tool result. File and document tools
+    /// return raw content, which would otherwise be fed to the model and
+    /// emitted with the step.
+    async fn redact_observation(&self, mut observation: ToolResult) -> Result<ToolResult> {
+        let Some(detector) = &self.pii_detector else {
+            return Ok(observation);
+        };
+        let detector = detector.read().await;
+
+        let mut pending = vec![&mut observation.result];
+        while let Some(value) = pending.pop() {
+            match value {
+                serde_json::Value::String(text) => {
+                    *text = detector.redact_pii(text, None).await?;
+                }
+                serde_json::Value::Array(items) => pending.extend(items.iter_mut()),
+                serde_json::Value::Object(fields) => pending.extend(fields.values_mut()),
+                _ => {}
+            }
+        }
+        if let Some(error) = observation.error.as_mut() {
+            *error = detector.redact_pii(error, None).await?;
+        }
+        Ok(observation)
+    }
+
+    /// Execute a tool call, turning refusals and failures into observations
+    async fn run_tool(&self, user_id: &str, call: &ToolCall) -> ToolResult {
+        let denied = |error: String| ToolResult {
+            success: false,
+            result: serde_json::Value::Null,
+            error: Some(error),
+        };
+
+        if !self.mcp_server.tools.contains_key(&call.tool) {
+            return denied(format!("Unknown tool: {}", call.tool));
+        }
+
+        // Tools reading or writing user files need document processing consent
+        let needs_document_consent = matches!(
+            call.tool.as_str(),
+            "read_file" | "write_file" | "list_directory" | "search_documents" | "extract_text"
+        );
+        if let (true, Some(guard)) = (needs_document_consent, &self.consent_guard) {
+            if let Err(e) = guard
+                .enforce_consent(user_id, &ConsentType::DocumentProcessing)
+                .await
+            {
+                return denied(e.to_string());
+            }
+        }
+
+        // Path sandboxing is enforced by the MCP server's own handlers
+        match self.mcp_server.execute_tool(call.clone()).await {
+            Ok(result) => result,
+            Err(e) => denied(e.to_string()),
+        }
+    }
+}
+
+/// Split a ReAct completion into its thought and requested action
+///
+/// Whichever of `Action:` or `Final Answer:` comes first wins; a completion with
+/// neither is taken as the final answer.
+fn parse_agent_output(output: &str) -> (String, AgentAction) {
+    // Anything after an invented observation is not the model's to decide
+    let output = output.split(OBSERVATION_MARKER).next().unwrap_or("");
+
+    let action_at = output.find("Action:");
+    let final_at = output.find("Final Answer:");
+    let thought_end = match (action_at, final_at) {
+        (Some(a), Some(f)) => a.min(f),
+        (Some(a), None) => a,
+        (None, Some(f)) => f,
+        (None, None) => output.len(),
+    };
+    let thought = output[..thought_end]
+        .trim()
+        .trim_start_matches("Thought:")
+        .trim()
+        .to_string();
+
+    let action = match (action_at, final_at) {
+        (Some(a), f) if f.is_none_or(|f| a < f) => parse_tool_call(&output[a..]),
+        (_, Some(f)) => AgentAction::Final(output[f + "Final Answer:".len()..].trim().to_string()),
+        _ => AgentAction::Final(output.trim().to_string()),
+    };
+
+    (thought, action)
+}
+
+fn parse_tool_call(text: &str) -> AgentAction {
+    let after_action = &text["Action:".len()..];
+    let tool = after_action.lines().next().unwrap_or("").trim().to_string();
+    if tool.is_empty() {
+        return AgentAction::Malformed("Action is missing a tool name".to_string());
+    }
+
+    let parameters = match after_action.find("Action Input:") {
+        Some(idx) => {
+            let input = &after_action[idx + "Action Input:".len()..];
+            let json = match (input.find('{'), input.rfind('}')) {
+                (Some(start), Some(end)) if start < end => &input[start..=end],
+                _ => input.trim(),
+            };
+            match serde_json::from_str(json) {
+                Ok(value) => value,
+                Err(e) => {
+                    return AgentAction::Malformed(format!(
+                        "Action Input for {} is not valid JSON: {}",
+                        tool, e
+                    ))
+                }
+            }
+        }
+        None => serde_json::json!({}),
+    };
+
+    AgentAction::Tool(ToolCall { tool, parameters })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::completion::mock::MockCompletion;
+    use async_trait::async_trait;
+
+    /// Calls analyze_contract, then answers from whatever risk level it observed
+    fn risk_model() -> MockCompletion {
+        MockCompletion::new(|prompt, call| {
+            if call == 0 {
+                return Ok("Thought: I should analyze the lease first.\n\
+                    Action: analyze_contract\n\
+                    Action Input: {\"content\": \"The tenant pays rent monthly.\", \
+                    \"contract_type\": \"lease\"}\n\
+                    Observation: made up"
+                    .to_string());
+            }
+
+            let observation = prompt.rsplit("Observation:").next().unwrap();
+            let observed: ToolResult = serde_json::from_str(observation.trim()).unwrap();
+            let risk = &observed.result["analysis_summary"]["risk_level"];
+            Ok(format!(
+                "Thought: I know the answer\nFinal Answer: The lease risk is {}.",
+                risk.as_str().unwrap()
+            ))
+        })
+    }
+
+    #[tokio::test]
+    async fn test_agent_loop_executes_tool_and_uses_result() {
+        let orchestrator = AgentOrchestrator::new(true);
+        let model = risk_model();
+        let mut streamed = Vec::new();
+
+        let run = orchestrator
+            .execute_agent_task(&model, "default_user", "Assess this lease", "", |step| {
+                streamed.push(step.step)
+            })
+            .await
+            .unwrap();
+
+        assert!(run.completed);
+        assert_eq!(run.final_answer, "The lease risk is Low.");
+        assert_eq!(streamed, vec![1, 2]);
+
+        let first = &run.steps[0];
+        assert_eq!(first.thought, "I should analyze the lease first.");
+        assert_eq!(first.tool_call.as_ref().unwrap().tool, "analyze_contract");
+        let observation = first.observation.as_ref().unwrap();
+        assert!(observation.success);
+        assert_eq!(observation.result["contract_type"], "lease");
+
+        // The model's invented observation never reaches the next prompt
+        let calls = model.calls.lock().unwrap();
+        assert!(!calls[1].0.contains("made up"));
+        assert_eq!(
+            calls[0].1.stop_sequences,
+            vec!["\nObservation:".to_string()]
+        );
+    }
+
+    /// Calls the `client_record` tool once, then answers
+    fn record_model() -> MockCompletion {
+        MockCompletion::replies(vec![
+            "Thought: I need the record.\nAction: client_record\nAction Input: {}",
+            "Thought: I know the answer\nFinal Answer: Done.",
+        ])
+    }
+
+    #[tokio::test]
+    async fn test_tool_results_are_redacted_before_transcript_and_steps() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut orchestrator = AgentOrchestrator::new_with_services(
+            true,
+            Arc::new(RwLock::new(RAGEngine::with_index_path(
+                temp_dir.path().to_path_buf(),
+            ))),
+            Arc::new(FileProcessor::new()),
+            Arc::new(RwLock::new(PIIDetector::new())),
         );
+        orchestrator.mcp_server.register_tool_with_handler(
+            Tool {
+                name: "client_record".to_string(),
+                description: "Return the client record".to_string(),
+                parameters: ToolParameters {
+                    r#type: "object".to_string(),
+                    properties: HashMap::new(),
+                    required: vec![],
+                },
+                timeout_ms: None,
+            },
+            |_| async move {
+                Ok(ToolResult {
+                    success: true,
+                    result: serde_json::json!({
+                        "records": [{"note": "Client SSN 123-45-6789, email jane@example.com"}]
+                    }),
+                    error: None,
+                })
+            },
+        );
+
+        let mut emitted = Vec::new();
+        let run = orchestrator
+            .execute_agent_task(&record_model(), "default_user", "Summarize", "", |step| {
+                emitted.push(serde_json::to_string(step).unwrap())
+            })
+            .await
+            .unwrap();
+
+        assert!(run.completed);
+        let stored = serde_json::to_string(&run.steps).unwrap();
+        for text in emitted.iter().chain(std::iter::once(&stored)) {
+            assert!(!text.contains("123-45-6789"), "{}", text);
+            assert!(!text.contains("jane@example.com"), "{}", text);
+        }
+    }
+
+    /// Embeds by whether a text mentions rent
+    struct RentBackend;
+
+    #[async_trait]
+    impl crate::rag_engine::EmbeddingBackend for RentBackend {
+        async fn embed_batch(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+            Ok(texts
+                .iter()
+                .map(|t| {
+                    let rent = t.to_lowercase().matches("rent").count() as f32;
+                    vec![rent, if rent == 0.0 { 1.0 } else { 0.0 }]
+                })
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_orchestrator_search_documents_uses_app_rag_engine() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let rag_engine = Arc::new(RwLock::new(RAGEngine::with_index_path(
+            temp_dir.path().to_path_buf(),
+        )));
+        rag_engine
+            .read()
+            .await
+            .set_embedding_backend(Arc::new(RentBackend))
+            .await;
+        let orchestrator = AgentOrchestrator::new_with_services(
+            true,
+            rag_engine.clone(),
+            Arc::new(FileProcessor::new()),
+            Arc::new(RwLock::new(PIIDetector::new())),
+        );
+
+        rag_engine
+            .read()
+            .await
+            .add_document(
+                "The tenant shall pay rent of EUR 2,000 on the first day of each month.",
+                serde_json::json!({"filename": "lease.txt"}),
+            )
+            .await
+            .unwrap();
+
+        let result = orchestrator
+            .mcp_server
+            .execute_tool(ToolCall {
+                tool: "search_documents".to_string(),
+                parameters: serde_json::json!({"query": "When is rent due?"}),
+            })
+            .await
+            .unwrap();
+
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.result["total"], 1);
+        assert_eq!(result.result["results"][0]["title"], "lease.txt");
+        assert!(result.result["results"][0]["snippet"]
+            .as_str()
+            .unwrap()
+            .contains("EUR 2,000"));
+    }
+
+    #[tokio::test]
+    async fn test_registered_handler_executes_custom_tool() {
+        let mut server = MCPServer::new(true);
+        server.register_tool_with_handler(
+            Tool {
+                name: "echo".to_string(),
+                description: "Return the parameters unchanged".to_string(),
+                parameters: ToolParameters {
+                    r#type: "object".to_string(),
+                    properties: HashMap::new(),
+                    required: vec![],
+                },
+                timeout_ms: None,
+            },
+            |params| async move {
+                Ok(ToolResult {
+                    success: true,
+                    result: params,
+                    error: None,
+                })
+            },
+        );
+
+        assert!(server.list_tools().iter().any(|tool| tool.name == "echo"));
+        let result = server
+            .execute_tool(ToolCall {
+                tool: "echo".to_string(),
+                parameters: serde_json::json!({"citation": "Donoghue v Stevenson"}),
+            })
+            .await
+            .unwrap();
+        assert!(result.success);
+        assert_eq!(result.result["citation"], "Donoghue v Stevenson");
+    }
+
+    #[tokio::test]
+    async fn test_slow_tool_times_out_and_is_cancelled() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        /// Set when the handler future is dropped
+        struct DropFlag(Arc<AtomicBool>);
+        impl Drop for DropFlag {
+            fn drop(&mut self) {
+                self.0.store(true, Ordering::SeqCst);
+            }
+        }
+
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let flag = cancelled.clone();
+        let mut server = MCPServer::new(true);
+        server.register_tool_with_handler(
+            Tool {
+                name: "slow_read".to_string(),
+                description: "Never finishes in time".to_string(),
+                parameters: ToolParameters {
+                    r#type: "object".to_string(),
+                    properties: HashMap::new(),
+                    required: vec![],
+                },
+                timeout_ms: Some(50),
+            },
+            move |_| {
+                let guard = DropFlag(flag.clone());
+                async move {
+                    tokio::time::sleep(Duration::from_secs(60)).await;
+                    drop(guard);
+                    Ok(ToolResult {
+                        success: true,
+                        result: serde_json::Value::Null,
+                        error: None,
+                    })
+                }
+            },
+        );
+
+        let started = std::time::Instant::now();
+        let result = server
+            .execute_tool(ToolCall {
+                tool: "slow_read".to_string(),
+                parameters: serde_json::json!({}),
+            })
+            .await
+            .unwrap();
 
-        // Complete task orchestration and return result
-        Ok("Task completed".to_string())
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("timed out"));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(cancelled.load(Ordering::SeqCst));
     }
 }
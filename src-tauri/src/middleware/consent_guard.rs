@@ -6,6 +6,7 @@ use crate::compliance::{ConsentManager, ConsentType};
 use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
@@ -22,7 +23,7 @@ pub struct ConsentCheckResult {
 /// Consent guard for data processing operations
 pub struct ConsentGuard {
     consent_manager: Arc<RwLock<ConsentManager>>,
-    strict_mode: bool,
+    strict_mode: AtomicBool,
 }
 
 impl ConsentGuard {
@@ -30,7 +31,7 @@ impl ConsentGuard {
     pub fn new(db_path: PathBuf, strict_mode: bool) -> Self {
         Self {
             consent_manager: Arc::new(RwLock::new(ConsentManager::new(db_path))),
-            strict_mode,
+            strict_mode: AtomicBool::new(strict_mode),
         }
     }
 
@@ -38,7 +39,7 @@ impl ConsentGuard {
     pub fn from_manager(consent_manager: Arc<RwLock<ConsentManager>>, strict_mode: bool) -> Self {
         Self {
             consent_manager,
-            strict_mode,
+            strict_mode: AtomicBool::new(strict_mode),
         }
     }
 
@@ -96,7 +97,7 @@ impl ConsentGuard {
         // Check if re-consent is needed (version update)
         let needs_reconsent = manager.needs_reconsent(user_id, consent_type)?;
 
-        let allowed = if self.strict_mode {
+        let allowed = if self.is_strict_mode() {
             // Strict mode: require valid, up-to-date consent
             has_consent && !needs_reconsent
         } else {
@@ -135,7 +136,7 @@ impl ConsentGuard {
             ));
         }
 
-        if result.requires_reconsent && self.strict_mode {
+        if result.requires_reconsent && self.is_strict_mode() {
             return Err(anyhow!(
                 "Re-consent required for {} due to version update",
                 result.consent_type
@@ -196,8 +197,13 @@ impl ConsentGuard {
     }
 
     /// Set strict mode (requires up-to-date consent)
-    pub fn set_strict_mode(&mut self, strict: bool) {
-        self.strict_mode = strict;
+    pub fn set_strict_mode(&self, strict: bool) {
+        self.strict_mode.store(strict, Ordering::SeqCst);
+    }
+
+    /// Whether up-to-date consent is required
+    pub fn is_strict_mode(&self) -> bool {
+        self.strict_mode.load(Ordering::SeqCst)
     }
 
     /// Check if user needs to re-consent for any active consents
@@ -422,7 +422,7 @@ async fn test_consent_manager_reference() {
 #[tokio::test]
 async fn test_dynamic_strict_mode_change() {
     let db_path = get_test_db();
-    let mut guard = ConsentGuardBuilder::new(db_path.clone())
+    let guard = ConsentGuardBuilder::new(db_path.clone())
         .strict_mode(true)
         .build();
 
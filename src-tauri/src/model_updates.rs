@@ -0,0 +1,285 @@
+/// Detect and fetch newer revisions of downloaded models
+///
+/// Model repos on the Hugging Face Hub are re-uploaded with fixed quants or
+/// tokenizer changes. Each download records the file's ETag and commit in a
+/// `<model file>.revision.json` sidecar; an update check compares it with the
+/// Hub's current ETag for the same file. Nothing is contacted while offline
+/// mode (`HF_HUB_OFFLINE`) is set.
+///
+/// The recorded size and ETag (the SHA-256 of LFS files) also let a download
+/// be checked for truncation or corruption before it is used.
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use hf_hub::api::tokio::Api;
+use hf_hub::{Repo, RepoType};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+
+/// Suffix of the sidecar recording which revision a model file came from
+pub const REVISION_SUFFIX: &str = ".revision.json";
+
+/// Suffix of the previous model file kept while an update is installed
+pub const BACKUP_SUFFIX: &str = ".bak";
+
+/// Suffix of a model file still being written; renamed once verified
+pub const PARTIAL_SUFFIX: &str = ".part";
+
+/// Whether Hub access is disabled through `HF_HUB_OFFLINE`
+pub fn is_offline_mode() -> bool {
+    std::env::var("HF_HUB_OFFLINE")
+        .map(|value| matches!(value.trim(), "1" | "true" | "TRUE" | "yes"))
+        .unwrap_or(false)
+}
+
+/// Revision of a single file in a Hub repository
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelRevision {
+    /// Commit the file was resolved at
+    pub commit: String,
+    /// Content ETag (the LFS sha256 for large files)
+    pub etag: String,
+    /// File size in bytes, when the Hub reported it
+    #[serde(default)]
+    pub size: Option<u64>,
+}
+
+impl ModelRevision {
+    /// Expected SHA-256 of the file, if the ETag is one
+    pub fn sha256(&self) -> Option<&str> {
+        let etag = self.etag.as_str();
+        (etag.len() == 64 && etag.bytes().all(|b| b.is_ascii_hexdigit())).then_some(etag)
+    }
+}
+
+/// Update availability for one registered model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelUpdateStatus {
+    pub model_name: String,
+    /// None when the model was downloaded before revisions were recorded
+    pub local: Option<ModelRevision>,
+    pub remote: Option<ModelRevision>,
+    pub update_available: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelUpdateConfig {
+    /// Check downloaded models for updates in the background
+    pub auto_check: bool,
+    pub check_interval_hours: u64,
+}
+
+impl Default for ModelUpdateConfig {
+    fn default() -> Self {
+        Self {
+            auto_check: false,
+            check_interval_hours: 24,
+        }
+    }
+}
+
+/// Bytes of a file received so far
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DownloadProgress {
+    pub downloaded_bytes: u64,
+    /// None when the server did not report the file's length
+    pub total_bytes: Option<u64>,
+}
+
+/// Source of model revisions and files
+#[async_trait]
+pub trait ModelHub: Send + Sync {
+    async fn latest_revision(&self, repo_id: &str, file: &str) -> Result<ModelRevision>;
+
+    /// Download `file` at `revision`, returning the path of the fetched copy
+    async fn fetch(&self, repo_id: &str, file: &str, revision: &ModelRevision) -> Result<PathBuf>;
+
+    /// Download `file` at `revision` to `dest`, reporting progress as it arrives.
+    /// By default the file is fetched whole and reported once complete.
+    async fn download(
+        &self,
+        repo_id: &str,
+        file: &str,
+        revision: &ModelRevision,
+        dest: &Path,
+        on_progress: &(dyn Fn(DownloadProgress) + Send + Sync),
+    ) -> Result<()> {
+        let fetched = self.fetch(repo_id, file, revision).await?;
+        let bytes = tokio::fs::copy(&fetched, dest).await?;
+        on_progress(DownloadProgress {
+            downloaded_bytes: bytes,
+            total_bytes: Some(bytes),
+        });
+        Ok(())
+    }
+}
+
+/// Hugging Face Hub, honouring `HF_ENDPOINT`
+pub struct HuggingFaceHub {
+    endpoint: String,
+}
+
+impl Default for HuggingFaceHub {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HuggingFaceHub {
+    pub fn new() -> Self {
+        Self {
+            endpoint: std::env::var("HF_ENDPOINT")
+                .unwrap_or_else(|_| "https://huggingface.co".to_string()),
+        }
+    }
+}
+
+#[async_trait]
+impl ModelHub for HuggingFaceHub {
+    async fn latest_revision(&self, repo_id: &str, file: &str) -> Result<ModelRevision> {
+        // LFS files redirect to the CDN; the revision headers are on the first response
+        let client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()?;
+        let url = format!("{}/{}/resolve/main/{}", self.endpoint, repo_id, file);
+        let response = client.head(&url).send().await?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(anyhow!("{} has no file named {}", repo_id, file));
+        }
+        if response.status().is_client_error() || response.status().is_server_error() {
+            return Err(anyhow!("Hub returned {} for {}", response.status(), url));
+        }
+
+        let header = |name: &str| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.trim_matches('"').to_string())
+        };
+        let etag = header("x-linked-etag")
+            .or_else(|| header("etag"))
+            .ok_or_else(|| anyhow!("No ETag returned for {}", url))?;
+        let commit = header("x-repo-commit").unwrap_or_default();
+        // A redirect's Content-Length is its own body, not the file's
+        let size = header("x-linked-size")
+            .or_else(|| header("content-length").filter(|_| response.status().is_success()))
+            .and_then(|v| v.parse().ok());
+
+        Ok(ModelRevision { commit, etag, size })
+    }
+
+    async fn fetch(&self, repo_id: &str, file: &str, revision: &ModelRevision) -> Result<PathBuf> {
+        let api = Api::new()?;
+        let repo = if revision.commit.is_empty() {
+            api.model(repo_id.to_string())
+        } else {
+            api.repo(Repo::with_revision(
+                repo_id.to_string(),
+                RepoType::Model,
+                revision.commit.clone(),
+            ))
+        };
+        Ok(repo.get(file).await?)
+    }
+
+    async fn download(
+        &self,
+        repo_id: &str,
+        file: &str,
+        revision: &ModelRevision,
+        dest: &Path,
+        on_progress: &(dyn Fn(DownloadProgress) + Send + Sync),
+    ) -> Result<()> {
+        let reference = if revision.commit.is_empty() {
+            "main"
+        } else {
+            revision.commit.as_str()
+        };
+        let url = format!(
+            "{}/{}/resolve/{}/{}",
+            self.endpoint, repo_id, reference, file
+        );
+        let mut request = reqwest::Client::new().get(&url);
+        if let Ok(token) = std::env::var("HF_TOKEN") {
+            request = request.bearer_auth(token);
+        }
+        let mut response = request.send().await?;
+        if !response.status().is_success() {
+            return Err(anyhow!("Hub returned {} for {}", response.status(), url));
+        }
+
+        let total_bytes = response.content_length().or(revision.size);
+        let mut output = tokio::fs::File::create(dest).await?;
+        let mut downloaded_bytes = 0;
+        on_progress(DownloadProgress {
+            downloaded_bytes,
+            total_bytes,
+        });
+        while let Some(chunk) = response.chunk().await? {
+            output.write_all(&chunk).await?;
+            downloaded_bytes += chunk.len() as u64;
+            on_progress(DownloadProgress {
+                downloaded_bytes,
+                total_bytes,
+            });
+        }
+        output.flush().await?;
+        Ok(())
+    }
+}
+
+/// Where the Hub revision of a model file is recorded
+pub fn sidecar_path(model_path: &Path) -> PathBuf {
+    let mut name = model_path.as_os_str().to_os_string();
+    name.push(REVISION_SUFFIX);
+    PathBuf::from(name)
+}
+
+/// Backup location of a model file during an update
+pub fn backup_path(model_path: &Path) -> PathBuf {
+    let mut name = model_path.as_os_str().to_os_string();
+    name.push(BACKUP_SUFFIX);
+    PathBuf::from(name)
+}
+
+/// Where a model file is written until it has been verified
+pub fn partial_path(model_path: &Path) -> PathBuf {
+    let mut name = model_path.as_os_str().to_os_string();
+    name.push(PARTIAL_SUFFIX);
+    PathBuf::from(name)
+}
+
+/// Whether a file has the size recorded for `revision` and, with `check_hash`,
+/// its SHA-256. Hashing reads the whole file; the size check alone catches
+/// truncated downloads cheaply.
+pub fn verify_file(path: &Path, revision: &ModelRevision, check_hash: bool) -> Result<bool> {
+    let len = std::fs::metadata(path)?.len();
+    if revision.size.is_some_and(|size| size != len) {
+        return Ok(false);
+    }
+    match revision.sha256() {
+        Some(expected) if check_hash => {
+            let mut hasher = Sha256::new();
+            std::io::copy(&mut std::fs::File::open(path)?, &mut hasher)?;
+            Ok(hex::encode(hasher.finalize()).eq_ignore_ascii_case(expected))
+        }
+        _ => Ok(true),
+    }
+}
+
+/// Revision recorded for a downloaded model file, if any
+pub fn read_local_revision(model_path: &Path) -> Option<ModelRevision> {
+    let content = std::fs::read_to_string(sidecar_path(model_path)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+pub fn write_local_revision(model_path: &Path, revision: &ModelRevision) -> Result<()> {
+    std::fs::write(
+        sidecar_path(model_path),
+        serde_json::to_string_pretty(revision)?,
+    )?;
+    Ok(())
+}
@@ -0,0 +1,396 @@
+/// Structured obligations tracker for analysed contracts
+///
+/// Turns "X shall do Y by Z" sentences into records with the responsible
+/// party, the action, an optional deadline and the clause it came from, and
+/// exports them as CSV or an iCalendar file of deadlines. Extraction must run
+/// on the original (pre-redaction) text so party names survive.
+use crate::clause_outline::{extract_outline, DocumentOutline, OutlineSection};
+use crate::compliance::{run_migration, UserDataStore};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{NaiveDate, Utc};
+use lazy_static::lazy_static;
+use regex::Regex;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+lazy_static! {
+    static ref OBLIGATION_PATTERN: Regex = Regex::new(
+        r"^(.+?)\s+(shall|must|agrees to|undertakes to|is required to|will)\s+(.+)$"
+    )
+    .expect("Obligation regex is invalid");
+    static ref CLAUSE_PREFIX_PATTERN: Regex =
+        Regex::new(r"^(?:\d{1,3}(?:\.\d{1,3})*\.?|\(?[a-z]{1,4}\)|\(\d{1,3}\))\s+")
+            .expect("Clause prefix regex is invalid");
+    static ref DEADLINE_PATTERN: Regex = Regex::new(
+        r"(?i)\b(?:(?:on or before|no later than|not later than|by|before|on)\s+)?(\d{4}-\d{2}-\d{2}|(?:january|february|march|april|may|june|july|august|september|october|november|december)\s+\d{1,2},?\s+\d{4}|\d{1,2}\s+(?:january|february|march|april|may|june|july|august|september|october|november|december)\s+\d{4}|\d{1,2}/\d{1,2}/\d{4})\b"
+    )
+    .expect("Deadline date regex is invalid");
+    static ref RELATIVE_DEADLINE_PATTERN: Regex = Regex::new(
+        r"(?i)\bwithin\s+(?:\w+\s+\()?\d{1,4}\)?\s+(?:business\s+|calendar\s+|working\s+)?(?:days|weeks|months)\b(?:\s+(?:of|after|from|following)\s+[^,;.]+)?"
+    )
+    .expect("Relative deadline regex is invalid");
+}
+
+/// Longest pre-modal phrase still treated as a party name
+const MAX_PARTY_CHARS: usize = 80;
+
+/// A single obligation owed by a contract party
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Obligation {
+    pub party: String,
+    pub action: String,
+    /// Parsed calendar deadline, when the text names an absolute date
+    pub deadline: Option<NaiveDate>,
+    /// Deadline as written ("March 31, 2025", "within 30 days of invoice")
+    pub deadline_text: Option<String>,
+    /// Innermost numbered clause containing the obligation, e.g. "4.2"
+    pub source_clause: Option<String>,
+    pub sentence: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ObligationExportFormat {
+    Csv,
+    Ics,
+}
+
+impl ObligationExportFormat {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "csv" => Ok(Self::Csv),
+            "ics" | "ical" | "calendar" => Ok(Self::Ics),
+            other => Err(anyhow!("Unsupported obligations export format: {}", other)),
+        }
+    }
+}
+
+/// Extract structured obligations from contract text
+pub fn extract_obligations(content: &str) -> Vec<Obligation> {
+    let outline = extract_outline(content);
+
+    sentences_with_offsets(content)
+        .into_iter()
+        .filter_map(|(offset, sentence)| {
+            parse_obligation(sentence).map(|mut obligation| {
+                obligation.source_clause = innermost_clause(&outline, offset);
+                obligation
+            })
+        })
+        .collect()
+}
+
+fn parse_obligation(sentence: &str) -> Option<Obligation> {
+    let body = CLAUSE_PREFIX_PATTERN.replace(sentence, "");
+    let caps = OBLIGATION_PATTERN.captures(&body)?;
+
+    // "Within 30 days, the Buyer shall" -> "the Buyer"
+    let lead = caps[1].rsplit(',').next().unwrap_or("").trim();
+    let party = ["The ", "the ", "Each ", "each "]
+        .iter()
+        .find_map(|article| lead.strip_prefix(article))
+        .unwrap_or(lead)
+        .trim();
+    if party.is_empty()
+        || party.len() > MAX_PARTY_CHARS
+        || !party.chars().next().is_some_and(|c| c.is_uppercase())
+    {
+        return None;
+    }
+
+    let rest = caps[3].trim();
+    let (action, deadline, deadline_text) = if let Some(m) = DEADLINE_PATTERN.captures(rest) {
+        let whole = m.get(0).expect("match has a full capture");
+        let date_text = m[1].to_string();
+        (
+            rest[..whole.start()].to_string(),
+            parse_date(&date_text),
+            Some(date_text),
+        )
+    } else if let Some(m) = RELATIVE_DEADLINE_PATTERN.find(rest) {
+        (
+            rest[..m.start()].to_string(),
+            None,
+            Some(m.as_str().to_string()),
+        )
+    } else {
+        (rest.to_string(), None, None)
+    };
+
+    let action = action.trim().trim_end_matches([',', ';', '.', ':']).trim();
+    if action.is_empty() {
+        return None;
+    }
+
+    Some(Obligation {
+        party: party.to_string(),
+        action: action.to_string(),
+        deadline,
+        deadline_text,
+        source_clause: None,
+        sentence: sentence.trim().to_string(),
+    })
+}
+
+/// Parse an absolute date in one of the formats contracts commonly use
+pub fn parse_date(text: &str) -> Option<NaiveDate> {
+    let normalised = text.replace(',', "");
+    ["%Y-%m-%d", "%B %d %Y", "%d %B %Y", "%m/%d/%Y"]
+        .iter()
+        .find_map(|fmt| NaiveDate::parse_from_str(&normalised, fmt).ok())
+}
+
+/// Split into sentences, keeping each one's byte offset into `content`
+fn sentences_with_offsets(content: &str) -> Vec<(usize, &str)> {
+    let mut sentences = Vec::new();
+    let mut line_offset = 0;
+
+    for line in content.split_inclusive('\n') {
+        let mut start = 0;
+        for (idx, _) in line.match_indices(['.', ';']) {
+            let end = idx + 1;
+            if line[end..].starts_with(char::is_whitespace) || end == line.len() {
+                push_sentence(&mut sentences, line_offset + start, &line[start..end]);
+                start = end;
+            }
+        }
+        push_sentence(&mut sentences, line_offset + start, &line[start..]);
+        line_offset += line.len();
+    }
+
+    sentences
+}
+
+fn push_sentence<'a>(sentences: &mut Vec<(usize, &'a str)>, offset: usize, text: &'a str) {
+    let leading = text.len() - text.trim_start().len();
+    let trimmed = text.trim();
+    if !trimmed.is_empty() {
+        sentences.push((offset + leading, trimmed));
+    }
+}
+
+fn innermost_clause(outline: &DocumentOutline, offset: usize) -> Option<String> {
+    fn walk(sections: &[OutlineSection], offset: usize) -> Option<&OutlineSection> {
+        let section = sections
+            .iter()
+            .find(|s| s.start <= offset && offset < s.end)?;
+        walk(&section.children, offset).or(Some(section))
+    }
+    walk(&outline.sections, offset).map(|s| s.number.clone())
+}
+
+/// Render obligations in the requested export format
+pub fn export_obligations(
+    obligations: &[Obligation],
+    document_id: &str,
+    format: ObligationExportFormat,
+) -> String {
+    match format {
+        ObligationExportFormat::Csv => to_csv(obligations),
+        ObligationExportFormat::Ics => to_ics(obligations, document_id),
+    }
+}
+
+fn to_csv(obligations: &[Obligation]) -> String {
+    fn field(value: &str) -> String {
+        if value.contains([',', '"', '\n']) {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        } else {
+            value.to_string()
+        }
+    }
+
+    let mut csv = String::from("party,action,deadline,deadline_text,source_clause\n");
+    for o in obligations {
+        let row = [
+            field(&o.party),
+            field(&o.action),
+            o.deadline.map(|d| d.to_string()).unwrap_or_default(),
+            field(o.deadline_text.as_deref().unwrap_or("")),
+            field(o.source_clause.as_deref().unwrap_or("")),
+        ];
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+/// iCalendar file with one all-day event per dated obligation
+fn to_ics(obligations: &[Obligation], document_id: &str) -> String {
+    fn escape(value: &str) -> String {
+        value
+            .replace('\\', "\\\\")
+            .replace(';', "\\;")
+            .replace(',', "\\,")
+            .replace('\n', "\\n")
+    }
+
+    let stamp = Utc::now().format("%Y%m%dT%H%M%SZ");
+    let mut ics = String::from(
+        "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:-//BEAR AI//Obligations Tracker//EN\r\n",
+    );
+    for (idx, o) in obligations.iter().enumerate() {
+        let Some(deadline) = o.deadline else {
+            continue;
+        };
+        let clause = o
+            .source_clause
+            .as_deref()
+            .map(|c| format!("Clause {}: ", c))
+            .unwrap_or_default();
+        ics.push_str("BEGIN:VEVENT\r\n");
+        ics.push_str(&format!("UID:{}-{}@bear-ai\r\n", document_id, idx));
+        ics.push_str(&format!("DTSTAMP:{}\r\n", stamp));
+        ics.push_str(&format!(
+            "DTSTART;VALUE=DATE:{}\r\n",
+            deadline.format("%Y%m%d")
+        ));
+        ics.push_str(&format!(
+            "SUMMARY:{}\r\n",
+            escape(&format!("{}: {}", o.party, o.action))
+        ));
+        ics.push_str(&format!(
+            "DESCRIPTION:{}\r\n",
+            escape(&format!("{}{}", clause, o.sentence))
+        ));
+        ics.push_str("END:VEVENT\r\n");
+    }
+    ics.push_str("END:VCALENDAR\r\n");
+    ics
+}
+
+/// Persists each document's obligations so exports survive a restart
+pub struct ObligationStore {
+    db_path: PathBuf,
+}
+
+impl ObligationStore {
+    pub fn new(db_path: PathBuf) -> Self {
+        Self { db_path }
+    }
+
+    /// Initialize contract obligations table
+    pub fn initialize(&self) -> Result<()> {
+        let conn = Connection::open(&self.db_path)?;
+
+        let migration = include_str!("../migrations/010_create_contract_obligations.sql");
+        run_migration(&conn, migration)?;
+
+        Ok(())
+    }
+
+    /// Store a document's obligations, replacing any from an earlier upload
+    pub fn save(&self, document_id: &str, user_id: &str, obligations: &[Obligation]) -> Result<()> {
+        let conn = Connection::open(&self.db_path)?;
+        conn.execute(
+            "INSERT OR REPLACE INTO contract_obligations (document_id, user_id, obligations)
+             VALUES (?1, ?2, ?3)",
+            params![document_id, user_id, serde_json::to_string(obligations)?],
+        )?;
+        Ok(())
+    }
+
+    /// Obligations recorded for a document; None if it was never analysed
+    pub fn load(&self, document_id: &str) -> Result<Option<Vec<Obligation>>> {
+        let conn = Connection::open(&self.db_path)?;
+        let stored: Option<String> = conn
+            .query_row(
+                "SELECT obligations FROM contract_obligations WHERE document_id = ?1",
+                params![document_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        stored
+            .map(|json| serde_json::from_str(&json))
+            .transpose()
+            .map_err(Into::into)
+    }
+}
+
+#[async_trait]
+impl UserDataStore for ObligationStore {
+    fn name(&self) -> &str {
+        "contract_obligations"
+    }
+
+    async fn erase_user_data(&self, user_id: &str) -> Result<usize> {
+        let conn = Connection::open(&self.db_path)?;
+        Ok(conn.execute(
+            "DELETE FROM contract_obligations WHERE user_id = ?1",
+            params![user_id],
+        )?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTRACT: &str = "\
+SUPPLY AGREEMENT
+4. Delivery
+4.1 Scope. The parties agree on the scope in Schedule 1.
+4.2 Reports. Acme Logistics shall deliver the final audit report to the Client no later than March 31, 2025.
+4.3 Fees. The Client must pay each invoice within 30 days of receipt.
+";
+
+    #[test]
+    fn test_dated_obligation_captures_party_action_and_deadline() {
+        let obligations = extract_obligations(CONTRACT);
+        assert_eq!(obligations.len(), 2);
+
+        let report = &obligations[0];
+        assert_eq!(report.party, "Acme Logistics");
+        assert_eq!(
+            report.action,
+            "deliver the final audit report to the Client"
+        );
+        assert_eq!(report.deadline, NaiveDate::from_ymd_opt(2025, 3, 31));
+        assert_eq!(report.deadline_text.as_deref(), Some("March 31, 2025"));
+        assert_eq!(report.source_clause.as_deref(), Some("4.2"));
+
+        let fees = &obligations[1];
+        assert_eq!(fees.party, "Client");
+        assert_eq!(fees.action, "pay each invoice");
+        assert!(fees.deadline.is_none());
+        assert_eq!(
+            fees.deadline_text.as_deref(),
+            Some("within 30 days of receipt")
+        );
+    }
+
+    #[test]
+    fn test_ics_export_contains_dated_obligations_only() {
+        let obligations = extract_obligations(CONTRACT);
+        let ics = export_obligations(&obligations, "doc-1", ObligationExportFormat::Ics);
+        assert_eq!(ics.matches("BEGIN:VEVENT").count(), 1);
+        assert!(ics.contains("DTSTART;VALUE=DATE:20250331"));
+        assert!(ics.contains("SUMMARY:Acme Logistics: deliver the final audit report"));
+
+        let csv = export_obligations(&obligations, "doc-1", ObligationExportFormat::Csv);
+        assert_eq!(csv.lines().count(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_obligations_persist_across_store_instances() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("bear_ai.db");
+        let obligations = extract_obligations(CONTRACT);
+
+        let store = ObligationStore::new(db_path.clone());
+        store.initialize().unwrap();
+        store.save("doc-1", "user-a", &obligations).unwrap();
+        drop(store);
+
+        // A fresh store, as after a restart, still has the records
+        let reopened = ObligationStore::new(db_path);
+        reopened.initialize().unwrap();
+        assert_eq!(reopened.load("doc-1").unwrap(), Some(obligations));
+        assert_eq!(reopened.load("doc-2").unwrap(), None);
+
+        assert_eq!(reopened.erase_user_data("user-a").await.unwrap(), 1);
+        assert_eq!(reopened.load("doc-1").unwrap(), None);
+    }
+}
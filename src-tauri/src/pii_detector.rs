@@ -9,6 +9,7 @@
 //! - **Context Enhancement**: Boost confidence based on surrounding text
 //! - **Async Operations**: Full async/await support for non-blocking detection
 //! - **Custom Patterns**: Support for adding domain-specific PII patterns
+//! - **Custom Entity Types**: Named entity types with checksum/format validators
 //!
 //! ## Usage
 //! ```rust,no_run
@@ -21,7 +22,7 @@
 //!
 //! let text = "John Doe's email is john@example.com";
 //! let entities = detector.detect_pii(text).await?;
-//! let redacted = detector.redact_pii(text).await?;
+//! let redacted = detector.redact_pii(text, None).await?;
 //!
 //! println!("Entities: {:?}", entities);
 //! println!("Redacted: {}", redacted);
@@ -40,22 +41,39 @@
 //! - Person names (with context awareness)
 //! - Organizations (companies, law firms)
 //! - Custom patterns (configurable)
+//! - Custom entity types with validators (e.g. matter numbers)
 
+use crate::constants::PII_STREAM_OVERLAP_BYTES;
 use crate::process_helper::ProcessCommandExt;
+use crate::text_segmentation::{HeuristicTokenCounter, TokenCounter};
 use anyhow::{anyhow, Result};
 use lazy_static::lazy_static;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::SystemTime;
 use tokio::process::Command as AsyncCommand;
 use tokio::sync::RwLock;
 use candle_core::Device;
 
 pub mod candle_ner;
+pub mod circuit_breaker;
+pub mod streaming;
 use crate::pii_detector::candle_ner::NerModel;
+use crate::pii_detector::circuit_breaker::{
+    BreakerState, BreakerStatus, CircuitBreaker, CircuitBreakerConfig, LayerEvent,
+    LAYER_DISABLED_EVENT, LAYER_RESTORED_EVENT,
+};
+use tokio::sync::broadcast;
+
+/// Sent the first time Presidio fails and detection falls back to built-in layers
+pub const PROTECTION_DEGRADED_EVENT: &str = "pii-protection-degraded";
+/// Sent when Presidio succeeds again after a fallback
+pub const PROTECTION_RESTORED_EVENT: &str = "pii-protection-restored";
 
 // Layer 2: Planned for ML-enhanced detection (currently blocked by dependency conflict)
 // TODO: Implement with candle-transformers or wait for gline-rs dependency fix
@@ -96,6 +114,143 @@ pub struct PIIEntity {
     pub engine: String, // "presidio", "transformer", or "regex"
 }
 
+/// Outcome of a redaction pass
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RedactionReport {
+    pub redacted_text: String,
+    /// All detections, including types that were left in place
+    pub detections: Vec<PIIEntity>,
+    pub redacted_count: usize,
+    /// PII was still detected in the redacted text and removed by a second pass
+    #[serde(default)]
+    pub second_pass_applied: bool,
+    /// Detection stopped at the per-document entity cap; PII may remain and
+    /// the document should be reviewed manually
+    #[serde(default)]
+    pub truncated: bool,
+}
+
+impl RedactionReport {
+    /// Placeholders left in `redacted_text`, one per redacted entity type
+    pub fn redaction_markers(&self) -> Vec<String> {
+        let mut markers: Vec<String> = self
+            .detections
+            .iter()
+            .map(|entity| format!("[{}]", entity.entity_type))
+            .filter(|marker| self.redacted_text.contains(marker.as_str()))
+            .collect();
+        markers.sort();
+        markers.dedup();
+        markers
+    }
+
+    /// Add the report for the next chunk of a document. `offset` is the byte
+    /// length of the original text before the chunk, so detection spans stay
+    /// relative to the whole document.
+    pub fn append(&mut self, chunk: RedactionReport, offset: usize) {
+        self.redacted_text.push_str(&chunk.redacted_text);
+        self.detections
+            .extend(chunk.detections.into_iter().map(|mut entity| {
+                entity.start += offset;
+                entity.end += offset;
+                entity
+            }));
+        self.redacted_count += chunk.redacted_count;
+        self.second_pass_applied |= chunk.second_pass_applied;
+        self.truncated |= chunk.truncated;
+    }
+
+    /// Fail when detection stopped at the entity cap, so partially redacted
+    /// text is neither stored nor sent on
+    pub fn ensure_complete(&self) -> Result<()> {
+        if self.truncated {
+            return Err(anyhow!(
+                "PII detection stopped at the per-document entity limit, so the text may \
+                 still contain PII; split it or raise max_entities_per_document and retry"
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Detections in one document
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PIIDetection {
+    pub entities: Vec<PIIEntity>,
+    /// More entities were found than `max_entities_per_document` allows
+    pub truncated: bool,
+    /// Stages that ran, in order, with how long each took
+    #[serde(default)]
+    pub timings: Vec<LayerTiming>,
+}
+
+/// Wall-clock time of one detection stage: "regex", "candle", "presidio",
+/// "context" or "dedup"
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LayerTiming {
+    pub layer: String,
+    pub millis: f64,
+}
+
+impl LayerTiming {
+    fn since(layer: &str, started: std::time::Instant) -> Self {
+        Self {
+            layer: layer.to_string(),
+            millis: started.elapsed().as_secs_f64() * 1000.0,
+        }
+    }
+}
+
+/// Timings of one detection stage accumulated across runs
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LayerTimingStats {
+    pub layer: String,
+    pub runs: u64,
+    pub total_millis: f64,
+    pub mean_millis: f64,
+    pub max_millis: f64,
+}
+
+/// Gathers detections up to the per-document cap
+struct EntityCollector {
+    entities: Vec<PIIEntity>,
+    limit: usize,
+    truncated: bool,
+}
+
+impl EntityCollector {
+    fn new(limit: usize) -> Self {
+        Self {
+            entities: Vec::new(),
+            limit,
+            truncated: false,
+        }
+    }
+
+    /// Add a detection; false once the cap is exceeded, when detection should stop
+    fn push(&mut self, entity: PIIEntity) -> bool {
+        if self.entities.len() >= self.limit {
+            self.truncated = true;
+            return false;
+        }
+        self.entities.push(entity);
+        true
+    }
+
+    fn extend(&mut self, entities: Vec<PIIEntity>) -> bool {
+        entities.into_iter().all(|entity| self.push(entity))
+    }
+}
+
+/// Detections under two configurations, matched by type and span
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PIIConfigComparison {
+    pub only_a: Vec<PIIEntity>,
+    pub only_b: Vec<PIIEntity>,
+    /// Found by both; confidence and engine are taken from configuration A
+    pub both: Vec<PIIEntity>,
+}
+
 /// Detection layer configuration
 /// Layer 1 (Regex): Fast, always-on basic patterns
 /// Layer 2 (ML): Planned Rust-native ML detection (coming soon)
@@ -219,6 +374,366 @@ pub struct PIIDetectionConfig {
     pub detect_legal: bool,
     pub use_context_enhancement: bool,
     pub candle_model_language: String,
+    /// Characters held back when redacting streamed output
+    #[serde(default = "default_stream_holdback_chars")]
+    pub stream_holdback_chars: usize,
+    /// Merge overlapping detections by weighted vote instead of keeping the highest
+    #[serde(default)]
+    pub use_confidence_voting: bool,
+    /// Per-layer reliability used by confidence voting
+    #[serde(default)]
+    pub layer_weights: LayerWeights,
+    /// Sensitivity tier assigned to each entity type for audit reporting
+    #[serde(default)]
+    pub sensitivity_tiers: SensitivityTiers,
+    /// When to disable Layer 2 after repeated NER failures and when to probe it again
+    #[serde(default)]
+    pub ner_circuit_breaker: CircuitBreakerConfig,
+    /// User-defined entity types, detected by the regex layer
+    #[serde(default)]
+    pub custom_entity_types: Vec<CustomEntityType>,
+    /// Re-run detection on redacted output and redact anything left behind
+    #[serde(default = "default_verify_redaction")]
+    pub verify_redaction: bool,
+    /// Stop detecting after this many entities in one document
+    #[serde(default = "default_max_entities_per_document")]
+    pub max_entities_per_document: usize,
+    /// Notify when a Presidio failure degrades protection and when it recovers
+    #[serde(default = "default_notify_protection_changes")]
+    pub notify_protection_changes: bool,
+}
+
+/// Token classifier behind Layer 2
+pub trait NerPredictor: Send + Sync {
+    fn predict(&mut self, text: &str) -> Result<Vec<PIIEntity>>;
+}
+
+impl NerPredictor for NerModel {
+    fn predict(&mut self, text: &str) -> Result<Vec<PIIEntity>> {
+        NerModel::predict(self, text)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SensitivityTier {
+    Low,
+    Medium,
+    High,
+}
+
+impl SensitivityTier {
+    pub fn as_str(&self) -> &str {
+        match self {
+            SensitivityTier::Low => "low",
+            SensitivityTier::Medium => "medium",
+            SensitivityTier::High => "high",
+        }
+    }
+}
+
+/// Entity type -> sensitivity tier mapping
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SensitivityTiers {
+    pub mapping: HashMap<String, SensitivityTier>,
+    /// Tier for entity types missing from the mapping
+    pub default_tier: SensitivityTier,
+}
+
+impl Default for SensitivityTiers {
+    fn default() -> Self {
+        let mapping = [
+            ("SSN", SensitivityTier::High),
+            ("CREDIT_CARD", SensitivityTier::High),
+            ("MEDICAL_RECORD", SensitivityTier::High),
+            ("PERSON", SensitivityTier::Medium),
+            ("EMAIL", SensitivityTier::Medium),
+            ("CASE_NUMBER", SensitivityTier::Medium),
+            ("PHONE", SensitivityTier::Low),
+            ("ORGANIZATION", SensitivityTier::Low),
+            ("LOCATION", SensitivityTier::Low),
+        ]
+        .into_iter()
+        .map(|(entity_type, tier)| (entity_type.to_string(), tier))
+        .collect();
+
+        Self {
+            mapping,
+            default_tier: SensitivityTier::Medium,
+        }
+    }
+}
+
+impl SensitivityTiers {
+    pub fn tier_for(&self, entity_type: &str) -> SensitivityTier {
+        self.mapping
+            .get(&entity_type.to_uppercase())
+            .copied()
+            .unwrap_or(self.default_tier)
+    }
+
+    /// Count entities per tier; every tier is present, even with a zero count
+    pub fn distribution(&self, entities: &[PIIEntity]) -> HashMap<String, usize> {
+        let mut counts: HashMap<String, usize> = [
+            SensitivityTier::Low,
+            SensitivityTier::Medium,
+            SensitivityTier::High,
+        ]
+        .iter()
+        .map(|tier| (tier.as_str().to_string(), 0))
+        .collect();
+
+        for entity in entities {
+            let tier = self.tier_for(&entity.entity_type);
+            *counts.entry(tier.as_str().to_string()).or_insert(0) += 1;
+        }
+        counts
+    }
+}
+
+/// Detection counts by entity type and sensitivity tier
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PIIStatistics {
+    pub total_entities: usize,
+    pub by_type: HashMap<String, usize>,
+    pub by_tier: HashMap<String, usize>,
+}
+
+/// Risk weight of one entity in each sensitivity tier
+const PRIVACY_RISK_WEIGHTS: [(SensitivityTier, f32); 3] = [
+    (SensitivityTier::Low, 1.0),
+    (SensitivityTier::Medium, 4.0),
+    (SensitivityTier::High, 10.0),
+];
+
+/// Weighted entity count at which the risk score reaches ~63 (1 - 1/e)
+const PRIVACY_RISK_SCALE: f32 = 20.0;
+
+/// Number of entity types listed in a privacy impact preview
+const PRIVACY_TOP_TYPES: usize = 5;
+
+/// Count of one entity type found by a privacy impact preview
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntityTypeSummary {
+    pub entity_type: String,
+    pub tier: SensitivityTier,
+    pub count: usize,
+}
+
+/// Privacy risk of a document, assessed before it is processed or shared
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrivacyImpactPreview {
+    /// 0-100, from entity counts weighted by sensitivity tier
+    pub risk_score: u8,
+    pub risk_level: SensitivityTier,
+    pub total_entities: usize,
+    pub by_tier: HashMap<String, usize>,
+    /// Most sensitive types first, then most frequent
+    pub top_types: Vec<EntityTypeSummary>,
+    pub recommendation: String,
+    pub detection_layer: DetectionLayer,
+}
+
+/// How much of a text redaction would replace, estimated without redacting it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedactionImpact {
+    pub total_chars: usize,
+    pub redacted_chars: usize,
+    /// Estimated tokens, as counted for RAG chunking
+    pub total_tokens: usize,
+    pub redacted_tokens: usize,
+    /// Share of the text's characters that would be replaced, 0-100
+    pub percent_affected: f32,
+    /// Largest contribution first
+    pub by_type: Vec<RedactionTypeImpact>,
+    /// Detection stopped at `max_entities_per_document`, so the impact is a lower bound
+    pub truncated: bool,
+}
+
+/// Contribution of one entity type to a redaction impact estimate
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedactionTypeImpact {
+    pub entity_type: String,
+    pub count: usize,
+    pub redacted_chars: usize,
+    pub percent_affected: f32,
+}
+
+/// Reliability weight of each detection layer, used by confidence voting
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LayerWeights {
+    pub regex: f32,
+    pub candle: f32,
+    pub presidio: f32,
+}
+
+impl Default for LayerWeights {
+    fn default() -> Self {
+        Self {
+            regex: 0.8,
+            candle: 0.9,
+            presidio: 1.0,
+        }
+    }
+}
+
+impl LayerWeights {
+    pub fn weight_for(&self, engine: &str) -> f32 {
+        match engine {
+            "regex" => self.regex,
+            "candle" => self.candle,
+            "presidio" => self.presidio,
+            _ => 1.0,
+        }
+    }
+}
+
+fn default_stream_holdback_chars() -> usize {
+    crate::constants::PII_STREAM_HOLDBACK_CHARS
+}
+
+fn default_verify_redaction() -> bool {
+    true
+}
+
+fn default_max_entities_per_document() -> usize {
+    crate::constants::PII_MAX_ENTITIES_PER_DOCUMENT
+}
+
+fn default_notify_protection_changes() -> bool {
+    true
+}
+
+/// Largest char boundary of `text` at or before `index`
+fn floor_char_boundary(text: &str, index: usize) -> usize {
+    let mut index = index.min(text.len());
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+/// Smallest char boundary of `text` at or after `index`
+fn ceil_char_boundary(text: &str, index: usize) -> usize {
+    let mut index = index.min(text.len());
+    while !text.is_char_boundary(index) {
+        index += 1;
+    }
+    index
+}
+
+/// Where to stop redacting a chunk window: `PII_STREAM_OVERLAP_BYTES` before
+/// its end, moved back to the start of any entity crossing that point
+fn carry_point(window: &str, entities: &[PIIEntity]) -> usize {
+    let mut cut = floor_char_boundary(
+        window,
+        window.len().saturating_sub(PII_STREAM_OVERLAP_BYTES),
+    );
+    while let Some(start) = entities
+        .iter()
+        .filter(|entity| entity.start < cut && entity.end > cut)
+        .map(|entity| entity.start)
+        .min()
+    {
+        cut = floor_char_boundary(window, start);
+    }
+    cut
+}
+
+/// Span widened outwards to whole UTF-8 characters, so slicing cannot panic
+/// and no partial character of the entity is left behind
+fn char_aligned_span(text: &str, start: usize, end: usize) -> (usize, usize) {
+    (
+        floor_char_boundary(text, start),
+        ceil_char_boundary(text, end),
+    )
+}
+
+/// Align an entity reported by any layer to char boundaries; None if the span
+/// is empty or lies outside the text
+fn align_to_char_boundaries(text: &str, mut entity: PIIEntity) -> Option<PIIEntity> {
+    let (start, end) = char_aligned_span(text, entity.start, entity.end);
+    if start >= end {
+        return None;
+    }
+    if (start, end) != (entity.start, entity.end) {
+        tracing::debug!(
+            "Aligned {} span {}..{} to char boundaries {}..{}",
+            entity.entity_type,
+            entity.start,
+            entity.end,
+            start,
+            end
+        );
+        entity.start = start;
+        entity.end = end;
+        entity.text = text[start..end].to_string();
+    }
+    Some(entity)
+}
+
+/// Byte offset of the `char_index`th character (Presidio reports char offsets)
+fn char_to_byte_offset(text: &str, char_index: usize) -> usize {
+    text.char_indices()
+        .nth(char_index)
+        .map(|(offset, _)| offset)
+        .unwrap_or(text.len())
+}
+
+/// Replace each entity's span with `[TYPE]`; spans overlapping one already
+/// replaced are skipped
+fn apply_redactions(text: &str, entities: &[&PIIEntity]) -> String {
+    let mut result = text.to_string();
+    for (start, end, entity) in redaction_spans(text, entities) {
+        let replacement = format!("[{}]", entity.entity_type);
+        result.replace_range(start..end, &replacement);
+    }
+    result
+}
+
+/// Char-aligned spans `apply_redactions` replaces, last first; an entity
+/// overlapping one further along the text is skipped
+fn redaction_spans<'a>(
+    text: &str,
+    entities: &[&'a PIIEntity],
+) -> Vec<(usize, usize, &'a PIIEntity)> {
+    let mut sorted = entities.to_vec();
+    sorted.sort_by_key(|e| std::cmp::Reverse(e.start));
+
+    let mut spans = Vec::new();
+    let mut replaced_from = text.len();
+    for entity in sorted {
+        let (start, end) = char_aligned_span(text, entity.start, entity.end);
+        if end > replaced_from || start >= end {
+            continue;
+        }
+        spans.push((start, end, entity));
+        replaced_from = start;
+    }
+    spans
+}
+
+/// Whether `redact_types` (all types when None) selects an entity for redaction
+fn selected_for_redaction(entity: &PIIEntity, redact_types: Option<&[String]>) -> bool {
+    match redact_types {
+        Some(types) => types
+            .iter()
+            .any(|t| t.eq_ignore_ascii_case(&entity.entity_type)),
+        None => true,
+    }
+}
+
+/// Whether a detection is just the label inside a `[TYPE]` redaction placeholder
+fn is_placeholder(text: &str, entity: &PIIEntity) -> bool {
+    text.get(..entity.start)
+        .is_some_and(|before| before.ends_with('['))
+        && text
+            .get(entity.end..)
+            .is_some_and(|after| after.starts_with(']'))
+        && entity
+            .text
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c == '_')
 }
 
 impl Default for PIIDetectionConfig {
@@ -239,10 +754,270 @@ impl Default for PIIDetectionConfig {
             detect_legal: true,
             use_context_enhancement: true,
             candle_model_language: "english".to_string(),
+            stream_holdback_chars: default_stream_holdback_chars(),
+            use_confidence_voting: false,
+            layer_weights: LayerWeights::default(),
+            sensitivity_tiers: SensitivityTiers::default(),
+            ner_circuit_breaker: CircuitBreakerConfig::default(),
+            custom_entity_types: Vec::new(),
+            verify_redaction: default_verify_redaction(),
+            max_entities_per_document: default_max_entities_per_document(),
+            notify_protection_changes: default_notify_protection_changes(),
+        }
+    }
+}
+
+/// Check a custom entity match must pass before it is reported
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum EntityValidator {
+    /// Luhn checksum over the match's digits
+    Luhn,
+    /// ISO 7064 MOD 97-10 over the match's digits (remainder must be 1)
+    Mod97,
+    /// The whole match must also satisfy this regex
+    Format { pattern: String },
+}
+
+impl EntityValidator {
+    fn digits(candidate: &str) -> Vec<u32> {
+        candidate.chars().filter_map(|c| c.to_digit(10)).collect()
+    }
+
+    fn luhn(digits: &[u32]) -> bool {
+        let sum: u32 = digits
+            .iter()
+            .rev()
+            .enumerate()
+            .map(|(i, d)| match (i % 2 == 1, d * 2) {
+                (true, doubled) if doubled > 9 => doubled - 9,
+                (true, doubled) => doubled,
+                (false, _) => *d,
+            })
+            .sum();
+        !digits.is_empty() && sum % 10 == 0
+    }
+
+    fn mod97(digits: &[u32]) -> bool {
+        !digits.is_empty() && digits.iter().fold(0, |acc, d| (acc * 10 + d) % 97) == 1
+    }
+}
+
+fn default_custom_entity_confidence() -> f32 {
+    0.9
+}
+
+/// Semantic entity type defined by the user, e.g. a firm's matter numbers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomEntityType {
+    /// Entity type reported in detections and used in redaction placeholders
+    pub name: String,
+    pub pattern: String,
+    #[serde(default)]
+    pub validator: Option<EntityValidator>,
+    #[serde(default = "default_custom_entity_confidence")]
+    pub confidence: f32,
+}
+
+impl CustomEntityType {
+    fn compile(&self) -> Result<CompiledEntityType> {
+        if self.name.trim().is_empty() {
+            return Err(anyhow!("Custom entity type needs a name"));
+        }
+        if !(0.0..=1.0).contains(&self.confidence) {
+            return Err(anyhow!(
+                "Confidence for '{}' must be between 0 and 1",
+                self.name
+            ));
+        }
+        let format = match &self.validator {
+            Some(EntityValidator::Format { pattern }) => {
+                Some(Regex::new(&format!("^(?:{})$", pattern))?)
+            }
+            _ => None,
+        };
+        Ok(CompiledEntityType {
+            definition: self.clone(),
+            pattern: Regex::new(&self.pattern)?,
+            format,
+        })
+    }
+}
+
+/// Custom entity type with its regexes compiled once
+struct CompiledEntityType {
+    definition: CustomEntityType,
+    pattern: Regex,
+    format: Option<Regex>,
+}
+
+impl CompiledEntityType {
+    fn accepts(&self, candidate: &str) -> bool {
+        match &self.definition.validator {
+            None => true,
+            Some(EntityValidator::Luhn) => {
+                EntityValidator::luhn(&EntityValidator::digits(candidate))
+            }
+            Some(EntityValidator::Mod97) => {
+                EntityValidator::mod97(&EntityValidator::digits(candidate))
+            }
+            Some(EntityValidator::Format { .. }) => self
+                .format
+                .as_ref()
+                .is_some_and(|format| format.is_match(candidate)),
+        }
+    }
+}
+
+/// File under the app config dir holding user-defined recognizers
+const CUSTOM_PATTERNS_FILE: &str = "custom_pii_patterns.json";
+
+/// User-defined regex recognizer, persisted across restarts
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CustomPattern {
+    pub name: String,
+    pub pattern: String,
+    /// Entity type reported for matches
+    pub label: String,
+    pub confidence: f32,
+}
+
+impl CustomPattern {
+    fn compile(&self) -> Result<CompiledPattern> {
+        if self.name.trim().is_empty() {
+            return Err(anyhow!("Custom recognizer needs a name"));
+        }
+        if self.label.trim().is_empty() {
+            return Err(anyhow!("Custom recognizer '{}' needs a label", self.name));
+        }
+        if !(0.0..=1.0).contains(&self.confidence) {
+            return Err(anyhow!(
+                "Confidence for '{}' must be between 0 and 1",
+                self.name
+            ));
+        }
+        let regex = Regex::new(&self.pattern).map_err(|e| {
+            anyhow!(
+                "Pattern for custom recognizer '{}' does not compile: {}",
+                self.name,
+                e
+            )
+        })?;
+        Ok(CompiledPattern {
+            definition: self.clone(),
+            regex,
+        })
+    }
+}
+
+/// Custom recognizer with its regex compiled once
+struct CompiledPattern {
+    definition: CustomPattern,
+    regex: Regex,
+}
+
+/// Pseudonyms assigned during this session, so an entity keeps its placeholder
+/// across documents. Held in memory only: the originals are the PII itself.
+#[derive(Debug, Default)]
+pub struct PseudonymStore {
+    /// Placeholder by SHA-256 of the entity text
+    by_hash: HashMap<String, String>,
+    /// Placeholders issued so far per entity type
+    counters: HashMap<String, usize>,
+}
+
+impl PseudonymStore {
+    /// Placeholder for `text`, issuing the next one for `entity_type` if unseen
+    pub fn placeholder(&mut self, entity_type: &str, text: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(text.as_bytes());
+        let hash = hex::encode(hasher.finalize());
+        if let Some(placeholder) = self.by_hash.get(&hash) {
+            return placeholder.clone();
+        }
+
+        let counter = self.counters.entry(entity_type.to_string()).or_insert(0);
+        *counter += 1;
+        let placeholder = format!("{}_{:03}", entity_type, counter);
+        self.by_hash.insert(hash, placeholder.clone());
+        placeholder
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_hash.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_hash.is_empty()
+    }
+}
+
+/// Restore the originals in pseudonymized `text` from its placeholder mappings
+pub fn reidentify(text: &str, mappings: &HashMap<String, String>) -> String {
+    if mappings.is_empty() {
+        return text.to_string();
+    }
+    // Longest first so PERSON_0010 is not read as PERSON_001 followed by "0"
+    let mut placeholders: Vec<&String> = mappings.keys().collect();
+    placeholders.sort_by_key(|p| std::cmp::Reverse(p.len()));
+    let alternation = placeholders
+        .iter()
+        .map(|p| regex::escape(p))
+        .collect::<Vec<_>>()
+        .join("|");
+    match Regex::new(&format!(r"\b(?:{})\b", alternation)) {
+        Ok(pattern) => pattern
+            .replace_all(text, |caps: &regex::Captures| mappings[&caps[0]].clone())
+            .into_owned(),
+        Err(e) => {
+            tracing::warn!("Failed to build re-identification pattern: {}", e);
+            text.to_string()
         }
     }
 }
 
+/// Regions whose `pii_exclusions_<region>.toml` files are merged
+const EXCLUSION_REGIONS: &[&str] = &[
+    "en",
+    "eu",
+    "apac",
+    "latam",
+    "mena",
+    "africa",
+    "south_asia",
+    "cis",
+];
+
+/// Modification time and size of every candidate exclusions file (None when absent)
+type ExclusionsFingerprint = Vec<Option<(SystemTime, u64)>>;
+
+fn exclusions_fingerprint(search_dirs: &[PathBuf]) -> ExclusionsFingerprint {
+    EXCLUSION_REGIONS
+        .iter()
+        .flat_map(|region| {
+            let base_name = format!("pii_exclusions_{}.toml", region);
+            search_dirs.iter().map(move |dir| {
+                fs::metadata(dir.join(&base_name))
+                    .and_then(|meta| Ok((meta.modified()?, meta.len())))
+                    .ok()
+            })
+        })
+        .collect()
+}
+
+struct CachedExclusions {
+    fingerprint: ExclusionsFingerprint,
+    config: PIIExclusionsConfig,
+    /// Times the files have been parsed for these search dirs
+    parses: usize,
+}
+
+lazy_static! {
+    /// Merged exclusions per set of search dirs, shared by all detectors
+    static ref EXCLUSIONS_CACHE: std::sync::Mutex<HashMap<Vec<PathBuf>, CachedExclusions>> =
+        std::sync::Mutex::new(HashMap::new());
+}
+
 /// PII exclusions configuration loaded from TOML file
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct PIIExclusionsConfig {
@@ -250,6 +1025,59 @@ pub struct PIIExclusionsConfig {
     pub exclusions: PIIExclusions,
     #[serde(default)]
     pub settings: PIIExclusionSettings,
+    /// Patterns contributed by each regional file (and "runtime" additions)
+    #[serde(skip)]
+    pub region_counts: HashMap<String, usize>,
+}
+
+/// Exclusion categories exposed for filtering
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExclusionCategory {
+    Locations,
+    LegalTerms,
+    Organizations,
+    TimeTerms,
+}
+
+impl ExclusionCategory {
+    /// Parse a category name ("locations", "legal", "org", "time", ...)
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "location" | "locations" => Some(Self::Locations),
+            "legal" | "legal_terms" => Some(Self::LegalTerms),
+            "org" | "orgs" | "organization" | "organizations" => Some(Self::Organizations),
+            "time" | "time_terms" => Some(Self::TimeTerms),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Locations => "locations",
+            Self::LegalTerms => "legal_terms",
+            Self::Organizations => "organizations",
+            Self::TimeTerms => "time_terms",
+        }
+    }
+
+    /// Key under which runtime additions are stored; matches the category filter
+    fn runtime_key(&self) -> String {
+        format!("runtime_{}", self.as_str())
+    }
+}
+
+/// Effective exclusion set, exactly as consulted by the false-positive check
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActiveExclusions {
+    pub category: Option<ExclusionCategory>,
+    pub terms: Vec<String>,
+    pub total: usize,
+    pub counts_by_category: HashMap<String, usize>,
+    pub counts_by_region: HashMap<String, usize>,
+    pub settings: PIIExclusionSettings,
+    /// Detection confidence threshold applied before exclusions
+    pub confidence_threshold: f32,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
@@ -305,6 +1133,16 @@ impl PIIExclusions {
     pub fn all(&self) -> impl Iterator<Item = &String> {
         self.all_exclusions.values().flat_map(|v| v.iter())
     }
+
+    /// Get exclusions for a single category
+    pub fn by_category(&self, category: ExclusionCategory) -> Vec<&String> {
+        match category {
+            ExclusionCategory::Locations => self.locations(),
+            ExclusionCategory::LegalTerms => self.legal_terms(),
+            ExclusionCategory::Organizations => self.organizations(),
+            ExclusionCategory::TimeTerms => self.time_terms(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -341,6 +1179,8 @@ impl Default for PIIExclusionsConfig {
             vec!["First Amendment".to_string(), "Supreme Court".to_string()],
         );
 
+        let region_counts = HashMap::from([("default".to_string(), 4)]);
+
         Self {
             exclusions: PIIExclusions { all_exclusions },
             settings: PIIExclusionSettings {
@@ -352,6 +1192,7 @@ impl Default for PIIExclusionsConfig {
                 languages: Some(vec!["English".to_string()]),
                 countries: Some(vec!["United States".to_string()]),
             },
+            region_counts,
         }
     }
 }
@@ -375,8 +1216,18 @@ pub struct PIIDetector {
     exclusions_config: Arc<RwLock<PIIExclusionsConfig>>,
     python_path: Arc<RwLock<Option<PathBuf>>>,
     presidio_available: Arc<RwLock<bool>>,
-    custom_patterns: Arc<RwLock<HashMap<String, Regex>>>,
-    candle_ner_model: Arc<RwLock<Option<NerModel>>>,
+    custom_patterns: Arc<RwLock<HashMap<String, CompiledPattern>>>,
+    /// Where custom recognizers are saved; None keeps them in memory only
+    custom_patterns_path: Option<PathBuf>,
+    custom_entity_types: Arc<RwLock<Vec<CompiledEntityType>>>,
+    candle_ner_model: Arc<RwLock<Option<Box<dyn NerPredictor>>>>,
+    ner_breaker: Arc<RwLock<CircuitBreaker>>,
+    layer_events: broadcast::Sender<LayerEvent>,
+    pseudonyms: Arc<RwLock<PseudonymStore>>,
+    /// Presidio failed and detection is running on the built-in layers only
+    protection_degraded: Arc<std::sync::atomic::AtomicBool>,
+    /// Per-stage timings of every detection run since startup or the last reset
+    timing_stats: Arc<std::sync::Mutex<BTreeMap<String, LayerTimingStats>>>,
 }
 
 impl Default for PIIDetector {
@@ -415,81 +1266,209 @@ impl PIIDetector {
             PIIExclusionsConfig::default()
         });
 
+        let detector = Self::with_exclusions(exclusions_config);
+        match dirs::config_dir() {
+            Some(dir) => detector
+                .with_custom_patterns_file(dir.join("bear-ai-llm").join(CUSTOM_PATTERNS_FILE)),
+            None => detector,
+        }
+    }
+
+    /// Create a detector with an already-loaded exclusions configuration
+    pub fn with_exclusions(exclusions_config: PIIExclusionsConfig) -> Self {
         Self {
             config: Arc::new(RwLock::new(PIIDetectionConfig::default())),
             exclusions_config: Arc::new(RwLock::new(exclusions_config)),
             python_path: Arc::new(RwLock::new(None)),
             presidio_available: Arc::new(RwLock::new(false)),
             custom_patterns: Arc::new(RwLock::new(HashMap::new())),
+            custom_patterns_path: None,
+            custom_entity_types: Arc::new(RwLock::new(Vec::new())),
             candle_ner_model: Arc::new(RwLock::new(None)),
+            ner_breaker: Arc::new(RwLock::new(CircuitBreaker::new())),
+            layer_events: broadcast::channel(16).0,
+            pseudonyms: Arc::new(RwLock::new(PseudonymStore::default())),
+            protection_degraded: Arc::new(std::sync::atomic::AtomicBool::new(false)),
+            timing_stats: Arc::new(std::sync::Mutex::new(BTreeMap::new())),
         }
     }
 
-    /// Load PII exclusions configuration from ALL regional TOML files
-    /// Loads and merges: en, eu, apac, latam, mena, africa, south_asia, cis
+    /// Persist custom recognizers to `path`, loading any already saved there
+    pub fn with_custom_patterns_file(mut self, path: PathBuf) -> Self {
+        match Self::load_custom_patterns(&path) {
+            Ok(patterns) => {
+                if !patterns.is_empty() {
+                    tracing::info!(count = patterns.len(), "Loaded custom PII recognizers");
+                }
+                self.custom_patterns = Arc::new(RwLock::new(patterns));
+            }
+            Err(e) => tracing::warn!(
+                path = %path.display(),
+                "Failed to load custom PII recognizers: {}",
+                e
+            ),
+        }
+        self.custom_patterns_path = Some(path);
+        self
+    }
+
+    fn load_custom_patterns(path: &Path) -> Result<HashMap<String, CompiledPattern>> {
+        if !path.exists() {
+            return Ok(HashMap::new());
+        }
+        let definitions: Vec<CustomPattern> = serde_json::from_str(&fs::read_to_string(path)?)?;
+        let mut patterns = HashMap::new();
+        for definition in definitions {
+            // One bad entry should not discard the rest
+            match definition.compile() {
+                Ok(compiled) => {
+                    patterns.insert(definition.name.clone(), compiled);
+                }
+                Err(e) => tracing::warn!("Skipping saved custom recognizer: {}", e),
+            }
+        }
+        Ok(patterns)
+    }
+
+    async fn save_custom_patterns(
+        &self,
+        patterns: &HashMap<String, CompiledPattern>,
+    ) -> Result<()> {
+        let Some(path) = &self.custom_patterns_path else {
+            return Ok(());
+        };
+        let mut definitions: Vec<&CustomPattern> =
+            patterns.values().map(|p| &p.definition).collect();
+        definitions.sort_by(|a, b| a.name.cmp(&b.name));
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(path, serde_json::to_string_pretty(&definitions)?).await?;
+        Ok(())
+    }
+
+    /// Load PII exclusions configuration from ALL regional TOML files
+    /// Loads and merges: en, eu, apac, latam, mena, africa, south_asia, cis
     /// This ensures comprehensive multilingual PII detection regardless of document language
     fn load_exclusions_config() -> Result<PIIExclusionsConfig> {
-        let regions = vec!["en", "eu", "apac", "latam", "mena", "africa", "south_asia", "cis"];
+        Self::load_exclusions_from_dirs(&Self::exclusion_search_dirs())
+    }
+
+    /// Locations searched for the regional exclusion files, in priority order
+    fn exclusion_search_dirs() -> Vec<PathBuf> {
+        vec![
+            PathBuf::new(),
+            PathBuf::from("src-tauri"),
+            dirs::config_dir()
+                .map(|p| p.join("bear-ai-llm"))
+                .unwrap_or_default(),
+        ]
+    }
+
+    /// Load and merge regional exclusion files, taking the first match per region
+    ///
+    /// The merged result is cached per set of search directories and reused until
+    /// a candidate file is added, removed or modified.
+    fn load_exclusions_from_dirs(search_dirs: &[PathBuf]) -> Result<PIIExclusionsConfig> {
+        let fingerprint = exclusions_fingerprint(search_dirs);
+        // Held while parsing so concurrent constructions share a single parse
+        let mut cache = EXCLUSIONS_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(cached) = cache.get(search_dirs) {
+            if cached.fingerprint == fingerprint {
+                tracing::debug!("Using cached PII exclusions");
+                return Ok(cached.config.clone());
+            }
+        }
+
+        let config = Self::parse_exclusions_from_dirs(search_dirs)?;
+        let parses = cache.get(search_dirs).map_or(0, |cached| cached.parses) + 1;
+        cache.insert(
+            search_dirs.to_vec(),
+            CachedExclusions {
+                fingerprint,
+                config: config.clone(),
+                parses,
+            },
+        );
+        Ok(config)
+    }
+
+    /// First readable and parseable file for a region across the search dirs
+    fn load_region_exclusions(
+        search_dirs: &[PathBuf],
+        region: &str,
+    ) -> Option<(PathBuf, PIIExclusionsConfig)> {
+        let base_name = format!("pii_exclusions_{}.toml", region);
+
+        for path in search_dirs.iter().map(|dir| dir.join(&base_name)) {
+            if !path.exists() {
+                continue;
+            }
+            match fs::read_to_string(&path) {
+                Ok(content) => match Self::parse_exclusions_toml(&content) {
+                    Ok(config) => return Some((path, config)),
+                    Err(e) => {
+                        tracing::warn!("  ⚠️  Failed to parse {}: {}", path.display(), e);
+                    }
+                },
+                Err(e) => {
+                    tracing::warn!("  ⚠️  Failed to read {}: {}", path.display(), e);
+                }
+            }
+        }
+        None
+    }
+
+    fn parse_exclusions_from_dirs(search_dirs: &[PathBuf]) -> Result<PIIExclusionsConfig> {
         let mut merged_exclusions = HashMap::new();
         let mut merged_settings = PIIExclusionSettings::default();
+        let mut region_counts = HashMap::new();
         let mut total_loaded = 0;
         let mut loaded_regions = Vec::new();
 
         tracing::info!("Loading PII exclusions from all regional files...");
 
-        for region in &regions {
-            let base_name = format!("pii_exclusions_{}.toml", region);
+        // Read and parse the regions in parallel, then merge in a fixed order
+        let region_configs: Vec<_> = std::thread::scope(|scope| {
+            let handles: Vec<_> = EXCLUSION_REGIONS
+                .iter()
+                .map(|region| {
+                    scope.spawn(move || Self::load_region_exclusions(search_dirs, region))
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap_or(None))
+                .collect()
+        });
 
-            // Try multiple possible locations
-            let possible_paths = vec![
-                PathBuf::from(&base_name),
-                PathBuf::from("src-tauri").join(&base_name),
-                dirs::config_dir()
-                    .map(|p| p.join("bear-ai-llm").join(&base_name))
-                    .unwrap_or_else(|| PathBuf::from(&base_name)),
-            ];
+        for (region, loaded) in EXCLUSION_REGIONS.iter().zip(region_configs) {
+            let Some((path, config)) = loaded else {
+                continue;
+            };
+            let count = config.exclusions.total_count();
+            tracing::info!(
+                "  ✅ Loaded {} patterns from {} ({})",
+                count,
+                region,
+                path.display()
+            );
 
-            for path in possible_paths {
-                if path.exists() {
-                    match fs::read_to_string(&path) {
-                        Ok(content) => {
-                            match toml::from_str::<PIIExclusionsConfig>(&content) {
-                                Ok(config) => {
-                                    let count = config.exclusions.total_count();
-                                    tracing::info!(
-                                        "  ✅ Loaded {} patterns from {} ({})",
-                                        count,
-                                        region,
-                                        path.display()
-                                    );
-
-                                    // Merge all exclusions
-                                    for (key, values) in config.exclusions.all_exclusions {
-                                        merged_exclusions.entry(key)
-                                            .or_insert_with(Vec::new)
-                                            .extend(values);
-                                    }
-
-                                    total_loaded += count;
-                                    loaded_regions.push(region.to_string());
-
-                                    // Use first loaded settings as base
-                                    if total_loaded == count {
-                                        merged_settings = config.settings;
-                                    }
-
-                                    break; // Found the file, stop searching paths
-                                }
-                                Err(e) => {
-                                    tracing::warn!("  ⚠️  Failed to parse {}: {}", path.display(), e);
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            tracing::warn!("  ⚠️  Failed to read {}: {}", path.display(), e);
-                        }
-                    }
-                }
+            // Merge all exclusions
+            for (key, values) in config.exclusions.all_exclusions {
+                merged_exclusions
+                    .entry(key)
+                    .or_insert_with(Vec::new)
+                    .extend(values);
+            }
+
+            total_loaded += count;
+            loaded_regions.push(region.to_string());
+            region_counts.insert(region.to_string(), count);
+
+            // Use first loaded settings as base
+            if total_loaded == count {
+                merged_settings = config.settings;
             }
         }
 
@@ -512,6 +1491,7 @@ impl PIIDetector {
                 all_exclusions: merged_exclusions,
             },
             settings: merged_settings,
+            region_counts,
         };
 
         let locations_count = merged_config.exclusions.locations().len();
@@ -527,6 +1507,18 @@ impl PIIDetector {
         Ok(merged_config)
     }
 
+    /// Parse a regional exclusions file. Terms may sit at the top level or
+    /// under an `[exclusions]` table (as in the en/eu files).
+    fn parse_exclusions_toml(content: &str) -> Result<PIIExclusionsConfig> {
+        let mut value: toml::Table = toml::from_str(content)?;
+        if let Some(toml::Value::Table(nested)) = value.remove("exclusions") {
+            for (key, terms) in nested {
+                value.entry(key).or_insert(terms);
+            }
+        }
+        Ok(toml::Value::Table(value).try_into()?)
+    }
+
     pub async fn initialize(&self) -> Result<()> {
         // Check for Python and Presidio (Layer 3)
         self.check_presidio_availability().await;
@@ -549,7 +1541,8 @@ impl PIIDetector {
                 };
                 match NerModel::new_local(PathBuf::from(model_id), device) {
                     Ok(model) => {
-                        *candle_ner_model = Some(model);
+                        *candle_ner_model = Some(Box::new(model));
+                        self.ner_breaker.write().await.reset();
                         tracing::info!("✅ Candle NER model initialized successfully.");
                     },
                     Err(e) => {
@@ -595,8 +1588,51 @@ impl PIIDetector {
     }
 
     pub async fn detect_pii(&self, text: &str) -> Result<Vec<PIIEntity>> {
+        Ok(self.detect_pii_bounded(text).await?.entities)
+    }
+
+    /// Detect PII, reporting whether detection stopped at the entity cap
+    pub async fn detect_pii_bounded(&self, text: &str) -> Result<PIIDetection> {
         let config = self.config.read().await;
-        let mut all_entities = Vec::new();
+        self.detect_pii_with_config(text, &config).await
+    }
+
+    /// Run detection under both configurations without touching the stored one
+    pub async fn compare_pii_configs(
+        &self,
+        text: &str,
+        config_a: &PIIDetectionConfig,
+        config_b: &PIIDetectionConfig,
+    ) -> Result<PIIConfigComparison> {
+        let found_a = self.detect_pii_with_config(text, config_a).await?.entities;
+        let found_b = self.detect_pii_with_config(text, config_b).await?.entities;
+
+        let key = |e: &PIIEntity| (e.entity_type.clone(), e.start, e.end);
+        let keys_a: std::collections::HashSet<_> = found_a.iter().map(key).collect();
+        let keys_b: std::collections::HashSet<_> = found_b.iter().map(key).collect();
+
+        let (both, only_a) = found_a
+            .into_iter()
+            .partition(|e| keys_b.contains(&key(e)));
+        let only_b = found_b
+            .into_iter()
+            .filter(|e| !keys_a.contains(&key(e)))
+            .collect();
+
+        Ok(PIIConfigComparison {
+            only_a,
+            only_b,
+            both,
+        })
+    }
+
+    async fn detect_pii_with_config(
+        &self,
+        text: &str,
+        config: &PIIDetectionConfig,
+    ) -> Result<PIIDetection> {
+        let mut found = EntityCollector::new(config.max_entities_per_document);
+        let mut timings = Vec::new();
 
         // === 3-LAYER PII DETECTION SYSTEM ===
         // Layer 1: Regex (always active, fast baseline)
@@ -607,22 +1643,42 @@ impl PIIDetector {
 
         // LAYER 1: Regex-based detection (ALWAYS RUN - fast baseline)
         let layer1_start = std::time::Instant::now();
-        let layer1_entities = self.detect_with_regex(text, &config).await?;
-        tracing::debug!("Layer 1 (Regex): {} entities in {:?}", layer1_entities.len(), layer1_start.elapsed());
-        all_entities.extend(layer1_entities);
+        self.detect_with_regex(text, config, &mut found).await?;
+        tracing::debug!("Layer 1 (Regex): {} entities in {:?}", found.entities.len(), layer1_start.elapsed());
+        timings.push(LayerTiming::since("regex", layer1_start));
 
         // LAYER 2: Candle NER (optional, if configured)
-        if matches!(config.detection_layer, DetectionLayer::WithCandle | DetectionLayer::FullStack) {
+        if !found.truncated && matches!(config.detection_layer, DetectionLayer::WithCandle | DetectionLayer::FullStack) {
             let mut candle_ner_model_guard = self.candle_ner_model.write().await;
             if let Some(ner_model) = candle_ner_model_guard.as_mut() {
-                let layer2_start = std::time::Instant::now();
-                match ner_model.predict(text) {
-                    Ok(entities) => {
-                        tracing::debug!("Layer 2 (Candle): {} entities in {:?}", entities.len(), layer2_start.elapsed());
-                        all_entities.extend(entities);
-                    }
-                    Err(e) => {
-                        tracing::warn!("Layer 2 (Candle) failed: {}. Falling back to Layer 1 results.", e);
+                let breaker_config = &config.ner_circuit_breaker;
+                let mut breaker = self.ner_breaker.write().await;
+                if breaker.allow_request(breaker_config, std::time::Instant::now()) {
+                    let layer2_start = std::time::Instant::now();
+                    let prediction = ner_model.predict(text);
+                    timings.push(LayerTiming::since("candle", layer2_start));
+                    match prediction {
+                        Ok(entities) => {
+                            tracing::debug!("Layer 2 (Candle): {} entities in {:?}", entities.len(), layer2_start.elapsed());
+                            found.extend(entities);
+                            if breaker.record_success() {
+                                tracing::info!("✅ Layer 2 (Candle) recovered, re-enabling NER detection");
+                                self.emit_layer_event(LAYER_RESTORED_EVENT, "layer2_candle", 0, None);
+                            }
+                        }
+                        Err(e) => {
+                            tracing::warn!("Layer 2 (Candle) failed: {}. Falling back to Layer 1 results.", e);
+                            let error = e.to_string();
+                            if breaker.record_failure(breaker_config, &error, std::time::Instant::now()) {
+                                let failures = breaker.status(breaker_config, std::time::Instant::now()).consecutive_failures;
+                                tracing::error!(
+                                    "❌ Layer 2 (Candle) disabled after {} consecutive failures; retrying in {}s",
+                                    failures,
+                                    breaker_config.probe_interval_secs
+                                );
+                                self.emit_layer_event(LAYER_DISABLED_EVENT, "layer2_candle", failures, Some(error));
+                            }
+                        }
                     }
                 }
             } else {
@@ -631,7 +1687,7 @@ impl PIIDetector {
         }
 
         // LAYER 3: MS Presidio (optional, post-install, if configured)
-        if matches!(config.detection_layer, DetectionLayer::FullStack) {
+        if !found.truncated && matches!(config.detection_layer, DetectionLayer::FullStack) {
             let should_use_presidio = match config.presidio_mode {
                 PresidioMode::Disabled => false,
                 PresidioMode::SpacyOnly | PresidioMode::FullML => true,
@@ -639,42 +1695,74 @@ impl PIIDetector {
 
             if should_use_presidio && *self.presidio_available.read().await {
                 let layer3_start = std::time::Instant::now();
-                match self.detect_with_presidio(text).await {
+                let detection = self.detect_with_presidio(text).await;
+                timings.push(LayerTiming::since("presidio", layer3_start));
+                match detection {
                     Ok(entities) => {
                         tracing::debug!("Layer 3 (Presidio): {} entities in {:?}", entities.len(), layer3_start.elapsed());
-                        all_entities.extend(entities);
+                        found.extend(entities);
+                        self.record_presidio_success(config);
                     }
                     Err(e) => {
                         tracing::warn!("Layer 3 (Presidio) failed: {}. Falling back to Layer 1/2 results.", e);
                         // Fallback: Layer 1/2 results already added
+                        self.record_presidio_fallback(config, &e.to_string());
                     }
                 }
             }
         }
 
+        if found.truncated {
+            tracing::warn!(
+                "PII detection stopped at {} entities; manual review of this document is recommended",
+                config.max_entities_per_document
+            );
+        }
+
+        // Layers report spans their own way; widen any that split a UTF-8 character
+        let mut all_entities: Vec<PIIEntity> = found
+            .entities
+            .into_iter()
+            .filter_map(|entity| align_to_char_boundaries(text, entity))
+            .collect();
+
         // Post-processing: Context enhancement
         if config.use_context_enhancement {
+            let context_start = std::time::Instant::now();
             all_entities = self.enhance_with_context(text, all_entities);
+            timings.push(LayerTiming::since("context", context_start));
         }
 
         // Final step: Deduplicate and filter by confidence
-        let filtered = self.deduplicate_and_filter(all_entities, config.confidence_threshold);
+        let dedup_start = std::time::Instant::now();
+        let filtered = if config.use_confidence_voting {
+            self.vote_and_filter(all_entities, config)
+        } else {
+            self.deduplicate_and_filter(all_entities, config.confidence_threshold)
+        };
+        timings.push(LayerTiming::since("dedup", dedup_start));
+        self.record_timings(&timings);
 
         tracing::info!("PII detection complete: {} entities found (mode: {:?})",
             filtered.len(),
             config.detection_layer
         );
 
-        Ok(filtered)
+        Ok(PIIDetection {
+            entities: filtered,
+            truncated: found.truncated,
+            timings,
+        })
     }
     /// Layer 1: Regex-based detection (renamed from detect_with_builtin)
     async fn detect_with_regex(
         &self,
         text: &str,
         config: &PIIDetectionConfig,
-    ) -> Result<Vec<PIIEntity>> {
+        found: &mut EntityCollector,
+    ) -> Result<()> {
         // This is the original detect_with_builtin logic
-        self.detect_with_builtin(text, config).await
+        self.detect_with_builtin(text, config, found).await
     }
 
     async fn detect_with_presidio(&self, text: &str) -> Result<Vec<PIIEntity>> {
@@ -721,7 +1809,11 @@ print(json.dumps(entities))
         }
 
         let json_str = String::from_utf8(output.stdout)?;
-        let entities: Vec<PIIEntity> = serde_json::from_str(&json_str)?;
+        let mut entities: Vec<PIIEntity> = serde_json::from_str(&json_str)?;
+        for entity in &mut entities {
+            entity.start = char_to_byte_offset(text, entity.start);
+            entity.end = char_to_byte_offset(text, entity.end);
+        }
 
         Ok(entities)
     }
@@ -730,116 +1822,153 @@ print(json.dumps(entities))
         &self,
         text: &str,
         config: &PIIDetectionConfig,
-    ) -> Result<Vec<PIIEntity>> {
-        let mut entities = Vec::new();
-
+        found: &mut EntityCollector,
+    ) -> Result<()> {
         // High-confidence regex patterns
         if config.detect_ssn {
             for m in SSN_PATTERN.find_iter(text) {
-                entities.push(PIIEntity {
+                if !found.push(PIIEntity {
                     entity_type: "SSN".to_string(),
                     text: m.as_str().to_string(),
                     start: m.start(),
                     end: m.end(),
                     confidence: 1.0,
                     engine: "regex".to_string(),
-                });
+                }) {
+                    return Ok(());
+                }
             }
         }
 
         if config.detect_credit_cards {
             for m in CREDIT_CARD_PATTERN.find_iter(text) {
-                if self.validate_credit_card(m.as_str()) {
-                    entities.push(PIIEntity {
+                if self.validate_credit_card(m.as_str())
+                    && !found.push(PIIEntity {
                         entity_type: "CREDIT_CARD".to_string(),
                         text: m.as_str().to_string(),
                         start: m.start(),
                         end: m.end(),
                         confidence: 1.0,
                         engine: "regex".to_string(),
-                    });
+                    })
+                {
+                    return Ok(());
                 }
             }
         }
 
         if config.detect_emails {
             for m in EMAIL_PATTERN.find_iter(text) {
-                entities.push(PIIEntity {
+                if !found.push(PIIEntity {
                     entity_type: "EMAIL".to_string(),
                     text: m.as_str().to_string(),
                     start: m.start(),
                     end: m.end(),
                     confidence: 1.0,
                     engine: "regex".to_string(),
-                });
+                }) {
+                    return Ok(());
+                }
             }
         }
 
         if config.detect_phones {
             for m in PHONE_PATTERN.find_iter(text) {
-                entities.push(PIIEntity {
+                if !found.push(PIIEntity {
                     entity_type: "PHONE".to_string(),
                     text: m.as_str().to_string(),
                     start: m.start(),
                     end: m.end(),
                     confidence: 0.95,
                     engine: "regex".to_string(),
-                });
+                }) {
+                    return Ok(());
+                }
             }
         }
 
         if config.detect_legal {
             for m in CASE_NUMBER_PATTERN.find_iter(text) {
-                entities.push(PIIEntity {
+                if !found.push(PIIEntity {
                     entity_type: "CASE_NUMBER".to_string(),
                     text: m.as_str().to_string(),
                     start: m.start(),
                     end: m.end(),
                     confidence: 0.9,
                     engine: "regex".to_string(),
-                });
+                }) {
+                    return Ok(());
+                }
             }
         }
 
         if config.detect_medical {
             for m in MEDICAL_RECORD_PATTERN.find_iter(text) {
-                entities.push(PIIEntity {
+                if !found.push(PIIEntity {
                     entity_type: "MEDICAL_RECORD".to_string(),
                     text: m.as_str().to_string(),
                     start: m.start(),
                     end: m.end(),
                     confidence: 0.9,
                     engine: "regex".to_string(),
-                });
+                }) {
+                    return Ok(());
+                }
             }
         }
 
         // Advanced name detection
-        if config.detect_names {
-            entities.extend(self.detect_names_advanced(text));
+        if config.detect_names && !found.extend(self.detect_names_advanced(text)) {
+            return Ok(());
         }
 
         // Organization detection
-        if config.detect_organizations {
-            entities.extend(self.detect_organizations_advanced(text));
+        if config.detect_organizations && !found.extend(self.detect_organizations_advanced(text)) {
+            return Ok(());
         }
 
         // Custom patterns
         let custom = self.custom_patterns.read().await;
-        for (name, pattern) in custom.iter() {
-            for m in pattern.find_iter(text) {
-                entities.push(PIIEntity {
-                    entity_type: name.clone(),
+        for pattern in custom.values() {
+            for m in pattern.regex.find_iter(text) {
+                if !found.push(PIIEntity {
+                    entity_type: pattern.definition.label.clone(),
                     text: m.as_str().to_string(),
                     start: m.start(),
                     end: m.end(),
-                    confidence: 0.85,
+                    confidence: pattern.definition.confidence,
                     engine: "regex".to_string(),
-                });
+                }) {
+                    return Ok(());
+                }
             }
         }
 
-        Ok(entities)
+        // Custom entity types, reported only when their validator accepts the match
+        let entity_types = self.custom_entity_types.read().await;
+        for entity_type in entity_types.iter() {
+            for m in entity_type.pattern.find_iter(text) {
+                if !entity_type.accepts(m.as_str()) {
+                    tracing::debug!(
+                        entity_type = %entity_type.definition.name,
+                        "Candidate rejected by custom entity validator"
+                    );
+                    continue;
+                }
+                if !found.push(PIIEntity {
+                    entity_type: entity_type.definition.name.clone(),
+                    text: m.as_str().to_string(),
+                    start: m.start(),
+                    end: m.end(),
+                    confidence: entity_type.definition.confidence,
+                    engine: "regex".to_string(),
+                }) {
+                    return Ok(());
+                }
+            }
+        }
+
+        Ok(())
     }
 
     fn detect_names_advanced(&self, text: &str) -> Vec<PIIEntity> {
@@ -927,8 +2056,8 @@ print(json.dumps(entities))
     fn enhance_with_context(&self, text: &str, mut entities: Vec<PIIEntity>) -> Vec<PIIEntity> {
         // Boost confidence based on surrounding context
         for entity in &mut entities {
-            let context_start = entity.start.saturating_sub(50);
-            let context_end = (entity.end + 50).min(text.len());
+            let (context_start, context_end) =
+                char_aligned_span(text, entity.start.saturating_sub(50), entity.end + 50);
             let context = &text[context_start..context_end].to_lowercase();
 
             match entity.entity_type.as_str() {
@@ -1007,6 +2136,97 @@ print(json.dumps(entities))
         filtered
     }
 
+    /// Merge overlapping detections by confidence-weighted vote across layers.
+    ///
+    /// Each cluster of overlapping entities is grouped by entity type. Within a
+    /// type, every layer contributes its best confidence weighted by the layer's
+    /// reliability; each additional agreeing layer then boosts the weighted
+    /// average towards 1.0. The best-scoring type wins the cluster.
+    fn vote_and_filter(
+        &self,
+        mut entities: Vec<PIIEntity>,
+        config: &PIIDetectionConfig,
+    ) -> Vec<PIIEntity> {
+        entities.sort_by_key(|e| e.start);
+
+        let mut clusters: Vec<Vec<PIIEntity>> = Vec::new();
+        let mut cluster_end = 0;
+        for entity in entities {
+            match clusters.last_mut() {
+                Some(cluster) if entity.start < cluster_end => {
+                    cluster_end = cluster_end.max(entity.end);
+                    cluster.push(entity);
+                }
+                _ => {
+                    cluster_end = entity.end;
+                    clusters.push(vec![entity]);
+                }
+            }
+        }
+
+        clusters
+            .into_iter()
+            .filter_map(|cluster| {
+                let mut types: Vec<&str> = cluster.iter().map(|e| e.entity_type.as_str()).collect();
+                types.sort_unstable();
+                types.dedup();
+
+                types
+                    .into_iter()
+                    .map(|entity_type| {
+                        let votes: Vec<&PIIEntity> = cluster
+                            .iter()
+                            .filter(|e| e.entity_type == entity_type)
+                            .collect();
+                        let merged = self.merge_votes(&votes, &config.layer_weights);
+                        (votes, merged)
+                    })
+                    .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+                    .map(|(votes, merged)| {
+                        // Span and engine come from the most reliable vote
+                        let best = votes
+                            .iter()
+                            .max_by(|a, b| {
+                                let wa = a.confidence * config.layer_weights.weight_for(&a.engine);
+                                let wb = b.confidence * config.layer_weights.weight_for(&b.engine);
+                                wa.partial_cmp(&wb).unwrap_or(std::cmp::Ordering::Equal)
+                            })
+                            .expect("entity type group is never empty");
+                        PIIEntity {
+                            confidence: merged,
+                            ..(*best).clone()
+                        }
+                    })
+            })
+            .filter(|entity| entity.confidence >= config.confidence_threshold)
+            .collect()
+    }
+
+    /// Weighted average of each layer's best confidence, boosted by agreement count
+    fn merge_votes(&self, votes: &[&PIIEntity], weights: &LayerWeights) -> f32 {
+        let mut per_layer: HashMap<&str, f32> = HashMap::new();
+        for vote in votes {
+            let best = per_layer.entry(vote.engine.as_str()).or_insert(0.0);
+            *best = best.max(vote.confidence);
+        }
+
+        let mut weighted_sum = 0.0;
+        let mut weight_total = 0.0;
+        for (engine, confidence) in &per_layer {
+            let weight = weights.weight_for(engine);
+            weighted_sum += confidence * weight;
+            weight_total += weight;
+        }
+        if weight_total <= 0.0 {
+            return 0.0;
+        }
+
+        let average = weighted_sum / weight_total;
+        let agreeing = per_layer.len().saturating_sub(1) as i32;
+        let boost = 1.0 - (1.0 - crate::constants::PII_VOTE_AGREEMENT_BOOST).powi(agreeing);
+        (average + (1.0 - average) * boost).min(1.0)
+    }
+
     fn validate_credit_card(&self, number: &str) -> bool {
         // Luhn algorithm validation
         let digits: Vec<u32> = number
@@ -1071,57 +2291,313 @@ print(json.dumps(entities))
         }
     }
 
-    pub async fn redact_pii(&self, text: &str) -> Result<String> {
-        let entities = self.detect_pii(text).await?;
-        let mut result = text.to_string();
+    /// Add an exclusion at runtime; it takes effect immediately
+    pub async fn add_exclusion(&self, category: ExclusionCategory, term: String) -> Result<()> {
+        let term = term.trim().to_string();
+        if term.is_empty() {
+            return Err(anyhow!("Exclusion term cannot be empty"));
+        }
 
-        // Sort by position (reverse) for safe replacement
-        let mut sorted_entities = entities;
-        sorted_entities.sort_by_key(|e| std::cmp::Reverse(e.start));
+        let mut config = self.exclusions_config.write().await;
+        let terms = config
+            .exclusions
+            .all_exclusions
+            .entry(category.runtime_key())
+            .or_default();
+        if terms.contains(&term) {
+            return Ok(());
+        }
+        terms.push(term);
+        *config.region_counts.entry("runtime".to_string()).or_insert(0) += 1;
+        Ok(())
+    }
 
-        for entity in sorted_entities {
-            let replacement = format!("[{}]", entity.entity_type);
-            result.replace_range(entity.start..entity.end, &replacement);
+    /// Re-read the regional exclusion files, keeping terms added at runtime.
+    /// Unchanged files are served from the cache. Returns the active term count.
+    pub async fn reload_exclusions(&self) -> Result<usize> {
+        let mut reloaded = Self::load_exclusions_config()?;
+
+        let mut config = self.exclusions_config.write().await;
+        for (key, terms) in &config.exclusions.all_exclusions {
+            if key.starts_with("runtime_") {
+                reloaded
+                    .exclusions
+                    .all_exclusions
+                    .insert(key.clone(), terms.clone());
+            }
+        }
+        if let Some(runtime) = config.region_counts.get("runtime") {
+            reloaded
+                .region_counts
+                .insert("runtime".to_string(), *runtime);
         }
+        *config = reloaded;
+        Ok(config.exclusions.total_count())
+    }
 
-        Ok(result)
+    /// Get the merged exclusion set currently consulted by `is_false_positive_name`
+    pub async fn get_active_exclusions(
+        &self,
+        category: Option<ExclusionCategory>,
+    ) -> ActiveExclusions {
+        let confidence_threshold = self.config.read().await.confidence_threshold;
+        let config = self.exclusions_config.read().await;
+
+        let mut terms: Vec<String> = match category {
+            Some(category) => config
+                .exclusions
+                .by_category(category)
+                .into_iter()
+                .cloned()
+                .collect(),
+            None => config.exclusions.all().cloned().collect(),
+        };
+        terms.sort();
+        terms.dedup();
+
+        let counts_by_category = [
+            ExclusionCategory::Locations,
+            ExclusionCategory::LegalTerms,
+            ExclusionCategory::Organizations,
+            ExclusionCategory::TimeTerms,
+        ]
+        .iter()
+        .map(|c| (c.as_str().to_string(), config.exclusions.by_category(*c).len()))
+        .collect();
+
+        ActiveExclusions {
+            category,
+            total: terms.len(),
+            terms,
+            counts_by_category,
+            counts_by_region: config.region_counts.clone(),
+            settings: config.settings.clone(),
+            confidence_threshold,
+        }
     }
 
-    #[allow(dead_code)]
-    pub async fn anonymize_pii(&self, text: &str) -> Result<(String, HashMap<String, String>)> {
-        let entities = self.detect_pii(text).await?;
-        let mut result = text.to_string();
-        let mut mappings = HashMap::new();
-        let mut counters: HashMap<String, usize> = HashMap::new();
+    /// Redact detected PII. `redact_types` restricts which entity types are
+    /// replaced (case-insensitive); `None` redacts everything detected.
+    /// Fails when detection stopped at the entity cap, since the text could
+    /// then still contain PII.
+    pub async fn redact_pii(
+        &self,
+        text: &str,
+        redact_types: Option<Vec<String>>,
+    ) -> Result<String> {
+        let report = self.redact_pii_with_report(text, redact_types).await?;
+        report.ensure_complete()?;
+        Ok(report.redacted_text)
+    }
+
+    /// Redact like `redact_pii`, also reporting every detection (redacted or not)
+    pub async fn redact_pii_with_report(
+        &self,
+        text: &str,
+        redact_types: Option<Vec<String>>,
+    ) -> Result<RedactionReport> {
+        let detection = self.detect_pii_bounded(text).await?;
+        self.redact_detected(text, detection, redact_types.as_deref())
+            .await
+    }
 
-        // Sort by position (reverse) for safe replacement
-        let mut sorted_entities = entities;
-        sorted_entities.sort_by_key(|e| std::cmp::Reverse(e.start));
+    /// Redact the entities already detected in `text`, then verify the result
+    async fn redact_detected(
+        &self,
+        text: &str,
+        detection: PIIDetection,
+        redact_types: Option<&[String]>,
+    ) -> Result<RedactionReport> {
+        let verify = self.config.read().await.verify_redaction;
+        let PIIDetection {
+            entities,
+            mut truncated,
+            ..
+        } = detection;
+
+        let should_redact = |entity: &PIIEntity| selected_for_redaction(entity, redact_types);
+
+        let to_redact: Vec<&PIIEntity> = entities.iter().filter(|e| should_redact(e)).collect();
+        let mut result = apply_redactions(text, &to_redact);
+        let mut redacted_count = to_redact.len();
+        let mut second_pass_applied = false;
+
+        // Verification pass: anything still detected slipped through, e.g. part
+        // of an entity dropped while resolving overlaps
+        if verify && redacted_count > 0 {
+            let residual = self.detect_pii_bounded(&result).await?;
+            truncated |= residual.truncated;
+            let residual: Vec<&PIIEntity> = residual
+                .entities
+                .iter()
+                .filter(|e| should_redact(e) && !is_placeholder(&result, e))
+                .collect();
+            if !residual.is_empty() {
+                tracing::warn!(
+                    "PII still detected after redaction ({} entities), applying second pass",
+                    residual.len()
+                );
+                result = apply_redactions(&result, &residual);
+                redacted_count += residual.len();
+                second_pass_applied = true;
+            }
+        }
 
-        for entity in sorted_entities {
-            let counter = counters.entry(entity.entity_type.clone()).or_insert(0);
-            *counter += 1;
+        Ok(RedactionReport {
+            redacted_text: result,
+            redacted_count,
+            detections: entities,
+            second_pass_applied,
+            truncated,
+        })
+    }
 
-            let placeholder = format!("{}_{:03}", entity.entity_type, counter);
-            mappings.insert(placeholder.clone(), entity.text.clone());
-            result.replace_range(entity.start..entity.end, &placeholder);
+    /// Redact a document chunk by chunk, so detection never works on more
+    /// than one chunk at a time; spans in the report are document-relative.
+    /// The tail of each chunk is held back and scanned again with the next,
+    /// so PII cut by a chunk boundary is still detected whole.
+    /// The entity cap applies to the whole document: once the chunks so far
+    /// exceed it, the report is marked truncated and the rest is not scanned.
+    pub async fn redact_pii_chunked<S: AsRef<str>>(
+        &self,
+        chunks: impl IntoIterator<Item = S>,
+        redact_types: Option<Vec<String>>,
+    ) -> Result<RedactionReport> {
+        let limit = self.config.read().await.max_entities_per_document;
+        let mut report = RedactionReport::default();
+        let mut offset = 0;
+        // Original text held back from the previous chunk
+        let mut carry = String::new();
+        let mut chunks = chunks.into_iter().peekable();
+        while let Some(chunk) = chunks.next() {
+            carry.push_str(chunk.as_ref());
+            let window = std::mem::take(&mut carry);
+            let detection = self.detect_pii_bounded(&window).await?;
+            let cut = if chunks.peek().is_some() {
+                carry_point(&window, &detection.entities)
+            } else {
+                window.len()
+            };
+
+            // Entities past the cut are found again in the next window
+            let detection = PIIDetection {
+                entities: detection
+                    .entities
+                    .into_iter()
+                    .filter(|entity| entity.end <= cut)
+                    .collect(),
+                ..detection
+            };
+            let chunk_report = self
+                .redact_detected(&window[..cut], detection, redact_types.as_deref())
+                .await?;
+            report.append(chunk_report, offset);
+            offset += cut;
+            carry.push_str(&window[cut..]);
+            if report.detections.len() > limit {
+                report.truncated = true;
+            }
+            if report.truncated {
+                break;
+            }
         }
+        Ok(report)
+    }
+
+    /// Replace PII with session-stable pseudonyms such as `PERSON_001`,
+    /// returning the text and the placeholder-to-original mappings it uses
+    pub async fn anonymize_pii(&self, text: &str) -> Result<(String, HashMap<String, String>)> {
+        let mut entities = self.detect_pii(text).await?;
+        // Document order, so numbering follows first appearance
+        entities.sort_by_key(|e| e.start);
+
+        let mut pseudonyms = self.pseudonyms.write().await;
+        let mut result = String::with_capacity(text.len());
+        let mut mappings = HashMap::new();
+        let mut copied_to = 0;
+        for entity in entities {
+            let (start, end) = char_aligned_span(text, entity.start, entity.end);
+            if start < copied_to || start >= end {
+                continue;
+            }
+            let original = &text[start..end];
+            let placeholder = pseudonyms.placeholder(&entity.entity_type, original);
+            mappings.insert(placeholder.clone(), original.to_string());
+            result.push_str(&text[copied_to..start]);
+            result.push_str(&placeholder);
+            copied_to = end;
+        }
+        result.push_str(&text[copied_to..]);
 
         Ok((result, mappings))
     }
 
-    #[allow(dead_code)]
-    pub async fn add_custom_pattern(&self, name: String, pattern: String) -> Result<()> {
-        let regex = Regex::new(&pattern)?;
+    /// Forget this session's pseudonyms; numbering starts again from 001
+    pub async fn clear_pseudonyms(&self) -> usize {
+        let mut pseudonyms = self.pseudonyms.write().await;
+        let cleared = pseudonyms.len();
+        *pseudonyms = PseudonymStore::default();
+        cleared
+    }
+
+    /// Add a custom recognizer, replacing any existing one with the same name
+    pub async fn add_custom_pattern(&self, pattern: CustomPattern) -> Result<()> {
+        let compiled = pattern.compile()?;
         let mut patterns = self.custom_patterns.write().await;
-        patterns.insert(name, regex);
+        patterns.insert(pattern.name, compiled);
+        self.save_custom_patterns(&patterns).await
+    }
+
+    /// Remove a custom recognizer, returning whether it existed
+    pub async fn remove_custom_pattern(&self, name: &str) -> Result<bool> {
+        let mut patterns = self.custom_patterns.write().await;
+        if patterns.remove(name).is_none() {
+            return Ok(false);
+        }
+        self.save_custom_patterns(&patterns).await?;
+        Ok(true)
+    }
+
+    pub async fn get_custom_patterns(&self) -> Vec<CustomPattern> {
+        let mut definitions: Vec<CustomPattern> = self
+            .custom_patterns
+            .read()
+            .await
+            .values()
+            .map(|p| p.definition.clone())
+            .collect();
+        definitions.sort_by(|a, b| a.name.cmp(&b.name));
+        definitions
+    }
+
+    /// Add a custom entity type, replacing any existing type with the same name
+    pub async fn add_custom_entity_type(&self, entity_type: CustomEntityType) -> Result<()> {
+        let compiled = entity_type.compile()?;
+
+        let mut config = self.config.write().await;
+        config
+            .custom_entity_types
+            .retain(|existing| existing.name != entity_type.name);
+        config.custom_entity_types.push(entity_type);
+
+        let mut entity_types = self.custom_entity_types.write().await;
+        entity_types.retain(|existing| existing.definition.name != compiled.definition.name);
+        entity_types.push(compiled);
         Ok(())
     }
 
     #[allow(dead_code)]
     pub async fn update_config(&self, config: PIIDetectionConfig) -> Result<()> {
+        // Compile first so an invalid custom entity type leaves the old config in place
+        let compiled = config
+            .custom_entity_types
+            .iter()
+            .map(CustomEntityType::compile)
+            .collect::<Result<Vec<_>>>()?;
+
         let mut current = self.config.write().await;
         *current = config;
+        *self.custom_entity_types.write().await = compiled;
         Ok(())
     }
 
@@ -1151,6 +2627,24 @@ print(json.dumps(entities))
         Ok(())
     }
 
+    /// Enable or disable confidence voting, optionally replacing the layer weights
+    pub async fn set_confidence_voting(
+        &self,
+        enabled: bool,
+        layer_weights: Option<LayerWeights>,
+    ) -> Result<()> {
+        let mut config = self.config.write().await;
+        config.use_confidence_voting = enabled;
+        if let Some(weights) = layer_weights {
+            config.layer_weights = weights;
+        }
+        tracing::info!(
+            "Confidence voting {}",
+            if enabled { "enabled" } else { "disabled" }
+        );
+        Ok(())
+    }
+
     /// Get current detection layer configuration
     #[allow(dead_code)]
     pub async fn get_detection_layer(&self) -> DetectionLayer {
@@ -1181,7 +2675,7 @@ print(json.dumps(entities))
                 };
                 match NerModel::new_local(PathBuf::from(model_id), device) {
                     Ok(model) => {
-                        *self.candle_ner_model.write().await = Some(model);
+                        self.set_ner_predictor(Some(Box::new(model))).await;
                         tracing::info!("✅ Candle NER model loaded successfully.");
                     },
                     Err(e) => {
@@ -1191,28 +2685,349 @@ print(json.dumps(entities))
                 }
             }
         } else {
-            *self.candle_ner_model.write().await = None;
+            self.set_ner_predictor(None).await;
             tracing::info!("Candle NER model unloaded.");
         }
         Ok(())
     }
 
-    /// Check if Candle NER Layer 2 is available
+    /// Replace the Layer 2 model; a fresh model starts with a closed breaker
+    pub async fn set_ner_predictor(&self, predictor: Option<Box<dyn NerPredictor>>) {
+        *self.candle_ner_model.write().await = predictor;
+        self.ner_breaker.write().await.reset();
+    }
+
+    /// Check if Candle NER Layer 2 is loaded and not disabled by the circuit breaker
     #[allow(dead_code)]
     pub async fn is_candle_available(&self) -> bool {
         self.candle_ner_model.read().await.is_some()
+            && self.ner_breaker.read().await.state() != BreakerState::Open
     }
 
     /// Get layer status information
-    #[allow(dead_code)]
     pub async fn get_layer_status(&self) -> HashMap<String, bool> {
         let mut status = HashMap::new();
         status.insert("layer1_regex".to_string(), true); // Always available
         status.insert("layer2_candle".to_string(), self.is_candle_available().await);
+        status.insert(
+            "layer2_candle_circuit_open".to_string(),
+            self.ner_breaker.read().await.state() == BreakerState::Open,
+        );
         status.insert("layer3_presidio".to_string(), self.is_presidio_available().await);
+        status.insert(
+            "layer3_presidio_degraded".to_string(),
+            self.is_protection_degraded(),
+        );
         status
     }
 
+    /// Circuit breaker state of the Candle NER layer
+    pub async fn get_ner_breaker_status(&self) -> BreakerStatus {
+        let config = self.config.read().await;
+        self.ner_breaker
+            .read()
+            .await
+            .status(&config.ner_circuit_breaker, std::time::Instant::now())
+    }
+
+    /// Receive `pii-layer-disabled` / `pii-layer-restored` and
+    /// `pii-protection-degraded` / `pii-protection-restored` notifications
+    pub fn subscribe_layer_events(&self) -> broadcast::Receiver<LayerEvent> {
+        self.layer_events.subscribe()
+    }
+
+    fn emit_layer_event(
+        &self,
+        event: &str,
+        layer: &str,
+        consecutive_failures: u32,
+        reason: Option<String>,
+    ) {
+        // No receivers simply means nobody is listening yet
+        let _ = self.layer_events.send(LayerEvent {
+            event: event.to_string(),
+            layer: layer.to_string(),
+            consecutive_failures,
+            reason,
+        });
+    }
+
+    /// Note that Presidio failed and built-in detection was used instead. Only
+    /// the change into the degraded state is announced, not every fallback.
+    pub fn record_presidio_fallback(&self, config: &PIIDetectionConfig, reason: &str) {
+        let was_degraded = self
+            .protection_degraded
+            .swap(true, std::sync::atomic::Ordering::SeqCst);
+        if !was_degraded {
+            tracing::warn!("PII protection degraded: Presidio unavailable ({})", reason);
+            if config.notify_protection_changes {
+                self.emit_layer_event(
+                    PROTECTION_DEGRADED_EVENT,
+                    "layer3_presidio",
+                    1,
+                    Some(reason.to_string()),
+                );
+            }
+        }
+    }
+
+    /// Note that Presidio succeeded, announcing recovery after a fallback
+    pub fn record_presidio_success(&self, config: &PIIDetectionConfig) {
+        let was_degraded = self
+            .protection_degraded
+            .swap(false, std::sync::atomic::Ordering::SeqCst);
+        if was_degraded {
+            tracing::info!("PII protection restored: Presidio is responding again");
+            if config.notify_protection_changes {
+                self.emit_layer_event(PROTECTION_RESTORED_EVENT, "layer3_presidio", 0, None);
+            }
+        }
+    }
+
+    /// Whether detection is currently falling back from Presidio
+    pub fn is_protection_degraded(&self) -> bool {
+        self.protection_degraded
+            .load(std::sync::atomic::Ordering::SeqCst)
+    }
+
+    fn record_timings(&self, timings: &[LayerTiming]) {
+        let mut stats = self.timing_stats.lock().unwrap_or_else(|e| e.into_inner());
+        for timing in timings {
+            let entry = stats
+                .entry(timing.layer.clone())
+                .or_insert_with(|| LayerTimingStats {
+                    layer: timing.layer.clone(),
+                    ..Default::default()
+                });
+            entry.runs += 1;
+            entry.total_millis += timing.millis;
+            entry.mean_millis = entry.total_millis / entry.runs as f64;
+            entry.max_millis = entry.max_millis.max(timing.millis);
+        }
+    }
+
+    /// Per-stage detection timings accumulated since startup or the last reset
+    pub fn get_timing_stats(&self) -> Vec<LayerTimingStats> {
+        let stats = self.timing_stats.lock().unwrap_or_else(|e| e.into_inner());
+        stats.values().cloned().collect()
+    }
+
+    pub fn reset_timing_stats(&self) {
+        self.timing_stats
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+    }
+
+    /// Return to the default detection config and sensitivity tiers, dropping
+    /// custom recognizers and entity types, runtime exclusions, pseudonyms and
+    /// timing stats. Returns the number of user-defined items removed.
+    pub async fn reset_settings(&self) -> Result<usize> {
+        let mut removed = 0;
+        {
+            let mut patterns = self.custom_patterns.write().await;
+            removed += patterns.len();
+            patterns.clear();
+            if let Some(path) = &self.custom_patterns_path {
+                if path.exists() {
+                    tokio::fs::remove_file(path).await?;
+                }
+            }
+        }
+        {
+            let mut config = self.config.write().await;
+            removed += config.custom_entity_types.len();
+            *config = PIIDetectionConfig::default();
+            self.custom_entity_types.write().await.clear();
+        }
+        {
+            let mut exclusions = self.exclusions_config.write().await;
+            let runtime_keys: Vec<String> = exclusions
+                .exclusions
+                .all_exclusions
+                .keys()
+                .filter(|key| key.starts_with("runtime_"))
+                .cloned()
+                .collect();
+            for key in runtime_keys {
+                if let Some(terms) = exclusions.exclusions.all_exclusions.remove(&key) {
+                    removed += terms.len();
+                }
+            }
+            exclusions.region_counts.remove("runtime");
+        }
+        removed += self.clear_pseudonyms().await;
+        self.reset_timing_stats();
+        Ok(removed)
+    }
+
+    /// Detection counts by entity type and by configured sensitivity tier
+    pub async fn get_tiered_statistics(&self, text: &str) -> Result<PIIStatistics> {
+        let entities = self.detect_pii(text).await?;
+        Ok(self.summarize_detections(&entities).await)
+    }
+
+    /// Summarise already-detected entities by type and sensitivity tier
+    pub async fn summarize_detections(&self, entities: &[PIIEntity]) -> PIIStatistics {
+        let mut by_type = HashMap::new();
+        for entity in entities {
+            *by_type.entry(entity.entity_type.clone()).or_insert(0) += 1;
+        }
+
+        PIIStatistics {
+            total_entities: entities.len(),
+            by_type,
+            by_tier: self
+                .config
+                .read()
+                .await
+                .sensitivity_tiers
+                .distribution(entities),
+        }
+    }
+
+    /// Score a text's privacy risk without storing or logging anything about it
+    pub async fn privacy_impact_preview(&self, text: &str) -> Result<PrivacyImpactPreview> {
+        let entities = self.detect_pii(text).await?;
+        let stats = self.summarize_detections(&entities).await;
+        let config = self.config.read().await;
+
+        let weighted: f32 = PRIVACY_RISK_WEIGHTS
+            .iter()
+            .map(|(tier, weight)| *stats.by_tier.get(tier.as_str()).unwrap_or(&0) as f32 * weight)
+            .sum();
+        let risk_score = (100.0 * (1.0 - (-weighted / PRIVACY_RISK_SCALE).exp())).round() as u8;
+        let risk_level = match risk_score {
+            60.. => SensitivityTier::High,
+            25..=59 => SensitivityTier::Medium,
+            _ => SensitivityTier::Low,
+        };
+
+        let mut top_types: Vec<EntityTypeSummary> = stats
+            .by_type
+            .iter()
+            .map(|(entity_type, count)| EntityTypeSummary {
+                entity_type: entity_type.clone(),
+                tier: config.sensitivity_tiers.tier_for(entity_type),
+                count: *count,
+            })
+            .collect();
+        top_types.sort_by(|a, b| {
+            b.tier
+                .cmp(&a.tier)
+                .then(b.count.cmp(&a.count))
+                .then(a.entity_type.cmp(&b.entity_type))
+        });
+        top_types.truncate(PRIVACY_TOP_TYPES);
+
+        let layer = config.detection_layer.clone();
+        let recommendation = if entities.is_empty() {
+            format!("No personal data detected with {} detection", layer)
+        } else {
+            match risk_level {
+                SensitivityTier::High if layer != DetectionLayer::FullStack => {
+                    "High-sensitivity: enable FullStack detection and review redactions \
+                    before processing"
+                        .to_string()
+                }
+                SensitivityTier::High => {
+                    "High-sensitivity: review redactions before processing".to_string()
+                }
+                SensitivityTier::Medium => {
+                    "Moderate sensitivity: redact before sharing outside the firm".to_string()
+                }
+                SensitivityTier::Low => {
+                    "Low sensitivity: standard redaction is sufficient".to_string()
+                }
+            }
+        };
+
+        Ok(PrivacyImpactPreview {
+            risk_score,
+            risk_level,
+            total_entities: stats.total_entities,
+            by_tier: stats.by_tier,
+            top_types,
+            recommendation,
+            detection_layer: layer,
+        })
+    }
+
+    /// Dry run of `redact_pii`: how many characters and tokens would be
+    /// replaced, overall and per entity type, without producing redacted text
+    pub async fn redaction_impact(
+        &self,
+        text: &str,
+        redact_types: Option<Vec<String>>,
+    ) -> Result<RedactionImpact> {
+        let PIIDetection {
+            entities,
+            truncated,
+            ..
+        } = self.detect_pii_bounded(text).await?;
+        let to_redact: Vec<&PIIEntity> = entities
+            .iter()
+            .filter(|e| selected_for_redaction(e, redact_types.as_deref()))
+            .collect();
+
+        let counter = HeuristicTokenCounter;
+        let total_chars = text.chars().count();
+        let percent_of_text = |chars: usize| {
+            if total_chars == 0 {
+                0.0
+            } else {
+                100.0 * chars as f32 / total_chars as f32
+            }
+        };
+
+        let mut redacted_chars = 0;
+        let mut redacted_tokens = 0;
+        let mut by_type: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+        for (start, end, entity) in redaction_spans(text, &to_redact) {
+            let chars = text[start..end].chars().count();
+            redacted_chars += chars;
+            redacted_tokens += counter.count_tokens(&text[start..end]);
+            let entry = by_type.entry(entity.entity_type.as_str()).or_default();
+            entry.0 += 1;
+            entry.1 += chars;
+        }
+
+        let mut by_type: Vec<RedactionTypeImpact> = by_type
+            .into_iter()
+            .map(|(entity_type, (count, chars))| RedactionTypeImpact {
+                entity_type: entity_type.to_string(),
+                count,
+                redacted_chars: chars,
+                percent_affected: percent_of_text(chars),
+            })
+            .collect();
+        by_type.sort_by(|a, b| b.redacted_chars.cmp(&a.redacted_chars));
+
+        Ok(RedactionImpact {
+            total_chars,
+            redacted_chars,
+            total_tokens: counter.count_tokens(text),
+            redacted_tokens,
+            percent_affected: percent_of_text(redacted_chars),
+            by_type,
+            truncated,
+        })
+    }
+
+    /// Override the sensitivity tier of an entity type
+    pub async fn set_sensitivity_tier(&self, entity_type: &str, tier: SensitivityTier) {
+        let mut config = self.config.write().await;
+        config
+            .sensitivity_tiers
+            .mapping
+            .insert(entity_type.to_uppercase(), tier);
+        tracing::info!(
+            "Sensitivity tier for {} set to {}",
+            entity_type,
+            tier.as_str()
+        );
+    }
+
     #[allow(dead_code)]
     pub async fn get_statistics(&self, text: &str) -> Result<HashMap<String, usize>> {
         let entities = self.detect_pii(text).await?;
@@ -1225,3 +3040,688 @@ print(json.dumps(entities))
         Ok(stats)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_active_exclusions_include_toml_and_runtime_terms() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(
+            temp_dir.path().join("pii_exclusions_en.toml"),
+            "[exclusions]\nus_courts = [\"Supreme Court\"]\nus_locations = [\"Ohio\"]\n\n[settings]\ncase_sensitive = false\n",
+        )
+        .unwrap();
+
+        let config =
+            PIIDetector::load_exclusions_from_dirs(&[temp_dir.path().to_path_buf()]).unwrap();
+        let detector = PIIDetector::with_exclusions(config);
+        detector
+            .add_exclusion(ExclusionCategory::LegalTerms, "Lex Mercatoria".to_string())
+            .await
+            .unwrap();
+
+        let active = detector.get_active_exclusions(None).await;
+        assert!(active.terms.contains(&"Supreme Court".to_string()));
+        assert!(active.terms.contains(&"Lex Mercatoria".to_string()));
+        assert_eq!(active.counts_by_region.get("en"), Some(&2));
+        assert_eq!(active.counts_by_region.get("runtime"), Some(&1));
+        assert!(!active.settings.case_sensitive);
+
+        let legal = detector
+            .get_active_exclusions(Some(ExclusionCategory::LegalTerms))
+            .await;
+        assert_eq!(legal.terms, vec!["Lex Mercatoria", "Supreme Court"]);
+        assert!(detector.is_false_positive_name("lex mercatoria"));
+    }
+
+    #[tokio::test]
+    async fn test_redact_types_limits_replacement_but_reports_all() {
+        let detector = PIIDetector::new();
+        let text = "The supplier Acme Widgets Inc confirmed SSN 123-45-6789.";
+
+        let report = detector
+            .redact_pii_with_report(text, Some(vec!["ssn".to_string()]))
+            .await
+            .unwrap();
+
+        assert!(report.redacted_text.contains("Acme Widgets Inc"));
+        assert!(report.redacted_text.contains("[SSN]"));
+        assert!(!report.redacted_text.contains("123-45-6789"));
+        assert_eq!(report.redacted_count, 1);
+        let types: Vec<&str> = report
+            .detections
+            .iter()
+            .map(|e| e.entity_type.as_str())
+            .collect();
+        assert!(types.contains(&"SSN"));
+        assert!(types.contains(&"ORGANIZATION"));
+    }
+
+    #[tokio::test]
+    async fn test_redaction_impact_counts_detected_span_lengths() {
+        let detector = PIIDetector::new();
+        let text = "Contact jane.doe@example.com or call 555-123-4567. SSN: 123-45-6789. \
+            The remaining clauses describe delivery and payment in ordinary terms.";
+
+        let entities = detector.detect_pii(text).await.unwrap();
+        assert!(entities.len() >= 2);
+        let span_chars: usize = entities
+            .iter()
+            .map(|e| text[e.start..e.end].chars().count())
+            .sum();
+
+        let impact = detector.redaction_impact(text, None).await.unwrap();
+        assert_eq!(impact.redacted_chars, span_chars);
+        let by_type_chars: usize = impact.by_type.iter().map(|t| t.redacted_chars).sum();
+        assert_eq!(by_type_chars, span_chars);
+        assert!(impact.percent_affected > 0.0 && impact.percent_affected < 100.0);
+        assert!(impact.redacted_tokens > 0 && impact.redacted_tokens < impact.total_tokens);
+
+        let ssn_only = detector
+            .redaction_impact(text, Some(vec!["ssn".to_string()]))
+            .await
+            .unwrap();
+        assert_eq!(ssn_only.redacted_chars, "123-45-6789".len());
+    }
+
+    fn layer_entity(engine: &str, confidence: f32) -> PIIEntity {
+        PIIEntity {
+            entity_type: "EMAIL".to_string(),
+            text: "jane@example.com".to_string(),
+            start: 8,
+            end: 24,
+            confidence,
+            engine: engine.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_agreeing_layers_vote_above_either_score() {
+        let detector = PIIDetector::new();
+        let config = PIIDetectionConfig {
+            use_confidence_voting: true,
+            ..Default::default()
+        };
+        let entities = vec![layer_entity("regex", 0.86), layer_entity("presidio", 0.9)];
+
+        let voted = detector.vote_and_filter(entities.clone(), &config);
+        assert_eq!(voted.len(), 1);
+        assert!(voted[0].confidence > 0.9, "merged {}", voted[0].confidence);
+        assert_eq!(voted[0].engine, "presidio");
+
+        // Keep-highest behaviour is unchanged when voting is off
+        let kept = detector.deduplicate_and_filter(entities, config.confidence_threshold);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].confidence, 0.9);
+    }
+
+    /// Layer 2 model that always fails, counting how often it is called
+    struct FailingNer {
+        calls: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    impl NerPredictor for FailingNer {
+        fn predict(&mut self, _text: &str) -> Result<Vec<PIIEntity>> {
+            self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Err(anyhow!("CUDA device lost"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_repeated_ner_failures_open_breaker_and_fall_back_to_regex() {
+        let detector = PIIDetector::new();
+        detector
+            .set_detection_layer(DetectionLayer::WithCandle)
+            .await
+            .unwrap();
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        detector
+            .set_ner_predictor(Some(Box::new(FailingNer {
+                calls: calls.clone(),
+            })))
+            .await;
+        let mut events = detector.subscribe_layer_events();
+
+        for _ in 0..5 {
+            let entities = detector
+                .detect_pii("Contact counsel at jane.doe@example.com")
+                .await
+                .unwrap();
+            assert!(entities.iter().any(|e| e.entity_type == "EMAIL"));
+        }
+
+        // Default threshold is 3; later calls skip Layer 2 entirely
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 3);
+        let event = events.try_recv().unwrap();
+        assert_eq!(event.event, LAYER_DISABLED_EVENT);
+        assert_eq!(event.consecutive_failures, 3);
+
+        let status = detector.get_layer_status().await;
+        assert_eq!(status.get("layer2_candle"), Some(&false));
+        assert_eq!(status.get("layer2_candle_circuit_open"), Some(&true));
+        assert_eq!(
+            detector.get_ner_breaker_status().await.state,
+            BreakerState::Open
+        );
+    }
+
+    /// Layer 2 model that recognises one fixed name
+    struct FixedNameNer(&'static str);
+
+    impl NerPredictor for FixedNameNer {
+        fn predict(&mut self, text: &str) -> Result<Vec<PIIEntity>> {
+            Ok(text
+                .match_indices(self.0)
+                .map(|(start, name)| PIIEntity {
+                    entity_type: "PERSON".to_string(),
+                    text: name.to_string(),
+                    start,
+                    end: start + name.len(),
+                    confidence: 0.95,
+                    engine: "candle".to_string(),
+                })
+                .collect())
+        }
+    }
+
+    /// Layer 2 model whose name span runs into the email that follows it
+    struct OverreachingNer;
+
+    impl NerPredictor for OverreachingNer {
+        fn predict(&mut self, text: &str) -> Result<Vec<PIIEntity>> {
+            Ok(text
+                .match_indices("jane okonkwo")
+                .map(|(start, name)| PIIEntity {
+                    entity_type: "PERSON".to_string(),
+                    text: name.to_string(),
+                    start,
+                    end: start + name.len(),
+                    confidence: 1.0,
+                    engine: "candle".to_string(),
+                })
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_verification_pass_removes_pii_left_by_overlapping_entities() {
+        let detector = PIIDetector::new();
+        detector
+            .set_detection_layer(DetectionLayer::WithCandle)
+            .await
+            .unwrap();
+        detector
+            .set_ner_predictor(Some(Box::new(OverreachingNer)))
+            .await;
+        // The name (3..15) wins the overlap with the email (8..31), so the
+        // first pass leaves "lee@example.com" behind
+        let text = "cc jane okonkwo.lee@example.com";
+
+        let report = detector.redact_pii_with_report(text, None).await.unwrap();
+        assert!(report.second_pass_applied);
+        assert_eq!(report.redacted_text, "cc [PERSON].[EMAIL]");
+        assert_eq!(report.redacted_count, 2);
+
+        let mut config = detector.get_config().await;
+        config.verify_redaction = false;
+        detector.update_config(config).await.unwrap();
+        let unverified = detector.redact_pii_with_report(text, None).await.unwrap();
+        assert!(!unverified.second_pass_applied);
+        assert!(unverified.redacted_text.contains("lee@example.com"));
+    }
+
+    /// Layer 2 model reporting a span that ends inside the "ü" of "Müller"
+    struct MisalignedNer;
+
+    impl NerPredictor for MisalignedNer {
+        fn predict(&mut self, text: &str) -> Result<Vec<PIIEntity>> {
+            Ok(text
+                .match_indices("Mü")
+                .map(|(start, _)| PIIEntity {
+                    entity_type: "PERSON".to_string(),
+                    text: "M".to_string(),
+                    start,
+                    end: start + 2,
+                    confidence: 0.95,
+                    engine: "candle".to_string(),
+                })
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_redact_multibyte_text_without_panicking() {
+        let detector = PIIDetector::new();
+        let text = "Müller üemail: x@y.com";
+
+        let redacted = detector.redact_pii(text, None).await.unwrap();
+        assert_eq!(redacted, "Müller üemail: [EMAIL]");
+
+        // A span splitting a character is widened to cover it whole
+        detector
+            .set_detection_layer(DetectionLayer::WithCandle)
+            .await
+            .unwrap();
+        detector
+            .set_ner_predictor(Some(Box::new(MisalignedNer)))
+            .await;
+        let entities = detector.detect_pii(text).await.unwrap();
+        let person = entities.iter().find(|e| e.entity_type == "PERSON").unwrap();
+        assert_eq!((person.start, person.end), (0, 3));
+        assert_eq!(person.text, "Mü");
+
+        let redacted = detector.redact_pii(text, None).await.unwrap();
+        assert_eq!(redacted, "[PERSON]ller üemail: [EMAIL]");
+        let (anonymized, mappings) = detector.anonymize_pii(text).await.unwrap();
+        assert_eq!(anonymized, "PERSON_001ller üemail: EMAIL_001");
+        assert_eq!(mappings["PERSON_001"], "Mü");
+    }
+
+    #[tokio::test]
+    async fn test_compare_configs_reports_name_only_found_with_candle() {
+        let detector = PIIDetector::new();
+        detector
+            .set_ner_predictor(Some(Box::new(FixedNameNer("okonkwo"))))
+            .await;
+        // Lower-case name slips past the capitalised-name regex
+        let text = "Signed by okonkwo; reply to counsel@example.com";
+
+        let regex_only = PIIDetectionConfig {
+            detection_layer: DetectionLayer::RegexOnly,
+            ..Default::default()
+        };
+        let with_candle = PIIDetectionConfig {
+            detection_layer: DetectionLayer::WithCandle,
+            ..Default::default()
+        };
+
+        let diff = detector
+            .compare_pii_configs(text, &regex_only, &with_candle)
+            .await
+            .unwrap();
+
+        assert!(diff.only_a.is_empty());
+        assert!(diff
+            .only_b
+            .iter()
+            .any(|e| e.entity_type == "PERSON" && e.text == "okonkwo"));
+        assert!(diff.both.iter().any(|e| e.entity_type == "EMAIL"));
+        // The stored configuration is untouched
+        assert!(matches!(
+            detector.get_detection_layer().await,
+            DetectionLayer::RegexOnly
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_sensitivity_tiers_default_mapping_and_statistics() {
+        let tiers = SensitivityTiers::default();
+        assert_eq!(tiers.tier_for("SSN"), SensitivityTier::High);
+        assert_eq!(tiers.tier_for("MEDICAL_RECORD"), SensitivityTier::High);
+        assert_eq!(tiers.tier_for("email"), SensitivityTier::Medium);
+        assert_eq!(tiers.tier_for("PHONE"), SensitivityTier::Low);
+
+        let detector = PIIDetector::new();
+        let stats = detector
+            .get_tiered_statistics("SSN 123-45-6789, contact jane.doe@example.com")
+            .await
+            .unwrap();
+        assert_eq!(stats.by_type["SSN"], 1);
+        assert_eq!(stats.by_type["EMAIL"], 1);
+        assert_eq!(stats.by_tier["high"], 1);
+        assert_eq!(stats.by_tier["medium"], 1);
+        assert_eq!(stats.by_tier["low"], 0);
+
+        // Mappings are configurable
+        detector
+            .set_sensitivity_tier("EMAIL", SensitivityTier::High)
+            .await;
+        let stats = detector
+            .get_tiered_statistics("contact jane.doe@example.com")
+            .await
+            .unwrap();
+        assert_eq!(stats.by_tier["high"], 1);
+    }
+
+    /// Client intake note mixing high-, medium- and low-sensitivity identifiers
+    const CLIENT_INTAKE_TEXT: &str = "
+        Client Information:
+        Name: John Smith
+        Email: john.smith@example.com
+        Phone: +1 (555) 123-4567
+        SSN: 123-45-6789
+        Credit Card: 4532-1234-5678-9010
+
+        Case Number: 2024-CV-001234
+        Medical Record Number: MRN: ABC123456
+    ";
+
+    #[tokio::test]
+    async fn test_privacy_impact_preview_flags_sensitive_document() {
+        let detector = PIIDetector::new();
+        let preview = detector
+            .privacy_impact_preview(CLIENT_INTAKE_TEXT)
+            .await
+            .unwrap();
+
+        assert!(
+            preview.risk_score >= 60,
+            "Expected a high score, got {}",
+            preview.risk_score
+        );
+        assert_eq!(preview.risk_level, SensitivityTier::High);
+        assert!(preview.recommendation.starts_with("High-sensitivity"));
+
+        let top: Vec<&str> = preview
+            .top_types
+            .iter()
+            .map(|t| t.entity_type.as_str())
+            .collect();
+        assert!(top.contains(&"SSN"), "SSN should be a top type: {:?}", top);
+        assert!(
+            top.contains(&"MEDICAL_RECORD"),
+            "Medical record should be a top type: {:?}",
+            top
+        );
+
+        let clean = detector
+            .privacy_impact_preview("The First Amendment protects freedom of speech.")
+            .await
+            .unwrap();
+        assert!(clean.risk_score < preview.risk_score);
+    }
+
+    #[tokio::test]
+    async fn test_pseudonyms_stable_across_documents_and_reversible() {
+        let detector = PIIDetector::with_exclusions(PIIExclusionsConfig::default());
+        detector
+            .set_detection_layer(DetectionLayer::RegexOnly)
+            .await
+            .unwrap();
+
+        let first = "Email jane@example.com, cc omar@example.com.";
+        let (anonymized, mappings) = detector.anonymize_pii(first).await.unwrap();
+        assert_eq!(anonymized, "Email EMAIL_001, cc EMAIL_002.");
+        assert_eq!(reidentify(&anonymized, &mappings), first);
+
+        // Same address, later document: same placeholder; new ones continue the count
+        let second = "Reply to lee@example.com and omar@example.com.";
+        let (anonymized, mappings) = detector.anonymize_pii(second).await.unwrap();
+        assert_eq!(anonymized, "Reply to EMAIL_003 and EMAIL_002.");
+        assert_eq!(mappings.len(), 2);
+        assert_eq!(reidentify(&anonymized, &mappings), second);
+
+        assert_eq!(detector.clear_pseudonyms().await, 3);
+        let (anonymized, _) = detector.anonymize_pii(second).await.unwrap();
+        assert_eq!(anonymized, "Reply to EMAIL_001 and EMAIL_002.");
+    }
+
+    #[tokio::test]
+    async fn test_custom_patterns_persist_across_restarts() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join(CUSTOM_PATTERNS_FILE);
+        let open = || {
+            PIIDetector::with_exclusions(PIIExclusionsConfig::default())
+                .with_custom_patterns_file(path.clone())
+        };
+        let text = "Re: docket LX-88213 hearing";
+        let dockets = |entities: Vec<PIIEntity>| {
+            entities
+                .into_iter()
+                .filter(|e| e.entity_type == "DOCKET")
+                .collect::<Vec<_>>()
+        };
+
+        let detector = open();
+        let broken = CustomPattern {
+            name: "broken".to_string(),
+            pattern: "LX-(".to_string(),
+            label: "DOCKET".to_string(),
+            confidence: 0.9,
+        };
+        let err = detector.add_custom_pattern(broken).await.unwrap_err();
+        assert!(err.to_string().contains("'broken' does not compile"));
+        detector
+            .add_custom_pattern(CustomPattern {
+                name: "docket".to_string(),
+                pattern: r"\bLX-\d{5}\b".to_string(),
+                label: "DOCKET".to_string(),
+                confidence: 0.9,
+            })
+            .await
+            .unwrap();
+
+        let restarted = open();
+        assert_eq!(restarted.get_custom_patterns().await.len(), 1);
+        let found = dockets(restarted.detect_pii(text).await.unwrap());
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].text, "LX-88213");
+        assert_eq!(found[0].confidence, 0.9);
+
+        assert!(restarted.remove_custom_pattern("docket").await.unwrap());
+        assert!(!restarted.remove_custom_pattern("docket").await.unwrap());
+        assert!(dockets(open().detect_pii(text).await.unwrap()).is_empty());
+    }
+
+    #[test]
+    fn test_presidio_fallback_announced_once_until_recovery() {
+        let detector = PIIDetector::with_exclusions(PIIExclusionsConfig::default());
+        let config = PIIDetectionConfig::default();
+        let mut events = detector.subscribe_layer_events();
+
+        detector.record_presidio_success(&config);
+        detector.record_presidio_fallback(&config, "connection refused");
+        detector.record_presidio_fallback(&config, "connection refused");
+        assert!(detector.is_protection_degraded());
+        detector.record_presidio_success(&config);
+        detector.record_presidio_success(&config);
+        assert!(!detector.is_protection_degraded());
+
+        let mut received = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            received.push(event);
+        }
+        let names: Vec<&str> = received.iter().map(|e| e.event.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![PROTECTION_DEGRADED_EVENT, PROTECTION_RESTORED_EVENT]
+        );
+        assert_eq!(received[0].layer, "layer3_presidio");
+        assert_eq!(received[0].reason.as_deref(), Some("connection refused"));
+
+        // Still tracked, but not announced, when notifications are off
+        let quiet = PIIDetectionConfig {
+            notify_protection_changes: false,
+            ..Default::default()
+        };
+        detector.record_presidio_fallback(&quiet, "timeout");
+        assert!(detector.is_protection_degraded());
+        assert!(events.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_detection_timed_per_active_layer() {
+        let detector = PIIDetector::with_exclusions(PIIExclusionsConfig::default());
+        let text = "Contact John Smith at john.smith@example.com or 555-123-4567.";
+
+        let detection = detector.detect_pii_bounded(text).await.unwrap();
+        let layers: Vec<&str> = detection.timings.iter().map(|t| t.layer.as_str()).collect();
+        // Regex-only by default: no Candle or Presidio stage ran
+        assert_eq!(layers, vec!["regex", "context", "dedup"]);
+        assert!(detection.timings.iter().map(|t| t.millis).sum::<f64>() > 0.0);
+
+        detector.detect_pii_bounded(text).await.unwrap();
+        let stats = detector.get_timing_stats();
+        assert_eq!(stats.len(), 3);
+        for layer in &stats {
+            assert_eq!(layer.runs, 2);
+            assert!(layer.max_millis >= layer.mean_millis);
+        }
+
+        detector.reset_timing_stats();
+        assert!(detector.get_timing_stats().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_detection_stops_at_entity_cap() {
+        let detector = PIIDetector::with_exclusions(PIIExclusionsConfig::default());
+        let mut config = detector.get_config().await;
+        config.max_entities_per_document = 50;
+        detector.update_config(config).await.unwrap();
+
+        let text: String = (0..300)
+            .map(|i| format!("user{}@example.com ", i))
+            .collect();
+
+        let detection = detector.detect_pii_bounded(&text).await.unwrap();
+        assert!(detection.truncated);
+        assert!(detection.entities.len() <= 50);
+
+        let report = detector.redact_pii_with_report(&text, None).await.unwrap();
+        assert!(report.truncated);
+        assert!(report.detections.len() <= 50);
+        assert!(report.ensure_complete().is_err());
+        assert!(detector.redact_pii(&text, None).await.is_err());
+
+        let small = detector
+            .redact_pii_with_report("Mail jane.doe@example.com", None)
+            .await
+            .unwrap();
+        assert!(!small.truncated);
+        assert!(small.ensure_complete().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_chunked_redaction_keeps_document_spans_and_cap() {
+        let detector = PIIDetector::with_exclusions(PIIExclusionsConfig::default());
+        let text = "Mail jane.doe@example.com\nor john.roe@example.com\n";
+        let report = detector
+            .redact_pii_chunked(crate::file_processor::split_text_chunks(text, 30), None)
+            .await
+            .unwrap();
+        let whole = detector.redact_pii_with_report(text, None).await.unwrap();
+        assert_eq!(report.redacted_text, whole.redacted_text);
+        for entity in &report.detections {
+            assert_eq!(&text[entity.start..entity.end], entity.text);
+        }
+
+        // The cap counts detections across chunks, not per chunk
+        let mut config = detector.get_config().await;
+        config.max_entities_per_document = 1;
+        detector.update_config(config).await.unwrap();
+        let report = detector
+            .redact_pii_chunked(crate::file_processor::split_text_chunks(text, 30), None)
+            .await
+            .unwrap();
+        assert!(report.truncated);
+        assert!(report.ensure_complete().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_chunked_redaction_finds_pii_across_chunk_boundaries() {
+        let detector = PIIDetector::with_exclusions(PIIExclusionsConfig::default());
+        let filler = "x ".repeat(500);
+
+        // An email split between two chunks
+        let split = [
+            format!("{}Mail jane.do", filler),
+            format!("e@example.com today {}", filler),
+            "end".to_string(),
+        ];
+        // An email crossing the point where the first chunk's tail is held back
+        let crossing = [
+            format!(
+                "{}Mail john.roe@example.com {}",
+                filler,
+                "y ".repeat(PII_STREAM_OVERLAP_BYTES / 2 - 4)
+            ),
+            "end".to_string(),
+        ];
+
+        for chunks in [&split[..], &crossing[..]] {
+            let text = chunks.concat();
+            let report = detector.redact_pii_chunked(chunks, None).await.unwrap();
+            let whole = detector.redact_pii_with_report(&text, None).await.unwrap();
+            assert_eq!(report.redacted_text, whole.redacted_text);
+            assert!(!report.redacted_text.contains("example.com"));
+            assert_eq!(report.detections.len(), whole.detections.len());
+            for entity in &report.detections {
+                assert_eq!(&text[entity.start..entity.end], entity.text);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_entity_type_validator_rejects_bad_checksum() {
+        let detector = PIIDetector::with_exclusions(PIIExclusionsConfig::default());
+        let mut config = detector.get_config().await;
+        config.custom_entity_types.push(CustomEntityType {
+            name: "MATTER_NUMBER".to_string(),
+            pattern: r"\bMAT-\d{7}\b".to_string(),
+            validator: Some(EntityValidator::Luhn),
+            confidence: 0.95,
+        });
+        detector.update_config(config).await.unwrap();
+
+        // 2024016 passes the Luhn check; 2024017 does not
+        let text = "Billing for MAT-2024016 and MAT-2024017 is attached.";
+        let matters: Vec<_> = detector
+            .detect_pii(text)
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|e| e.entity_type == "MATTER_NUMBER")
+            .collect();
+        assert_eq!(matters.len(), 1);
+        assert_eq!(matters[0].text, "MAT-2024016");
+        assert_eq!(matters[0].confidence, 0.95);
+
+        let redacted = detector.redact_pii(text, None).await.unwrap();
+        assert!(redacted.contains("Billing for [MATTER_NUMBER] and"));
+
+        let invalid = CustomEntityType {
+            name: "BROKEN".to_string(),
+            pattern: "(".to_string(),
+            validator: None,
+            confidence: 0.9,
+        };
+        assert!(detector.add_custom_entity_type(invalid).await.is_err());
+    }
+
+    #[test]
+    fn test_exclusions_parsed_once_until_file_changes() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let en_path = temp_dir.path().join("pii_exclusions_en.toml");
+        fs::write(&en_path, "[exclusions]\nus_courts = [\"Supreme Court\"]\n").unwrap();
+        let search_dirs = vec![temp_dir.path().to_path_buf()];
+        let parses = || EXCLUSIONS_CACHE.lock().unwrap()[&search_dirs].parses;
+
+        for _ in 0..4 {
+            let config = PIIDetector::load_exclusions_from_dirs(&search_dirs).unwrap();
+            let detector = PIIDetector::with_exclusions(config);
+            assert!(detector.is_false_positive_name("Supreme Court"));
+        }
+        assert_eq!(parses(), 1);
+
+        // Editing the file invalidates the cached merge
+        fs::write(
+            &en_path,
+            "[exclusions]\nus_courts = [\"Supreme Court\", \"Tax Court\"]\n",
+        )
+        .unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&en_path)
+            .unwrap()
+            .set_modified(SystemTime::now() + std::time::Duration::from_secs(60))
+            .unwrap();
+
+        let config = PIIDetector::load_exclusions_from_dirs(&search_dirs).unwrap();
+        assert_eq!(config.exclusions.legal_terms().len(), 2);
+        assert_eq!(parses(), 2);
+        PIIDetector::load_exclusions_from_dirs(&search_dirs).unwrap();
+        assert_eq!(parses(), 2);
+    }
+}
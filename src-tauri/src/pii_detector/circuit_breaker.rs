@@ -0,0 +1,199 @@
+//! Circuit breaker for the Candle NER layer
+//!
+//! A corrupted model or a lost GPU makes every Layer 2 call fail. After
+//! `failure_threshold` consecutive failures the breaker opens and detection
+//! runs on Layer 1 alone; once `probe_interval_secs` has passed a single call
+//! is let through as a probe, closing the breaker again if it succeeds.
+
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Event emitted to the UI when a detection layer is disabled
+pub const LAYER_DISABLED_EVENT: &str = "pii-layer-disabled";
+/// Event emitted to the UI when a disabled detection layer recovers
+pub const LAYER_RESTORED_EVENT: &str = "pii-layer-restored";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive failures before the layer is disabled
+    pub failure_threshold: u32,
+    /// Seconds to wait before probing a disabled layer for recovery
+    pub probe_interval_secs: u64,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 3,
+            probe_interval_secs: 300,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    /// Layer in use
+    Closed,
+    /// Layer skipped until the probe interval elapses
+    Open,
+    /// Next call is a recovery probe
+    HalfOpen,
+}
+
+/// Breaker state reported through `get_layer_status`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BreakerStatus {
+    pub state: BreakerState,
+    pub consecutive_failures: u32,
+    pub failure_threshold: u32,
+    /// Seconds until the next recovery probe, while open
+    pub next_probe_in_secs: Option<u64>,
+    pub last_error: Option<String>,
+}
+
+/// Layer state change broadcast by the detector
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LayerEvent {
+    /// `LAYER_DISABLED_EVENT` or `LAYER_RESTORED_EVENT`
+    pub event: String,
+    pub layer: String,
+    pub consecutive_failures: u32,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    state: BreakerState,
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    last_error: Option<String>,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CircuitBreaker {
+    pub fn new() -> Self {
+        Self {
+            state: BreakerState::Closed,
+            consecutive_failures: 0,
+            opened_at: None,
+            last_error: None,
+        }
+    }
+
+    pub fn state(&self) -> BreakerState {
+        self.state
+    }
+
+    /// Whether the layer should be called now; moves an expired open breaker to half-open
+    pub fn allow_request(&mut self, config: &CircuitBreakerConfig, now: Instant) -> bool {
+        match self.state {
+            BreakerState::Closed | BreakerState::HalfOpen => true,
+            BreakerState::Open => {
+                let probe_due = self.opened_at.map_or(true, |opened| {
+                    now.duration_since(opened) >= Duration::from_secs(config.probe_interval_secs)
+                });
+                if probe_due {
+                    self.state = BreakerState::HalfOpen;
+                }
+                probe_due
+            }
+        }
+    }
+
+    /// Record a successful call; returns true if this closed an open breaker
+    pub fn record_success(&mut self) -> bool {
+        let recovered = self.state != BreakerState::Closed;
+        self.state = BreakerState::Closed;
+        self.consecutive_failures = 0;
+        self.opened_at = None;
+        self.last_error = None;
+        recovered
+    }
+
+    /// Record a failed call; returns true if this opened the breaker
+    pub fn record_failure(
+        &mut self,
+        config: &CircuitBreakerConfig,
+        error: &str,
+        now: Instant,
+    ) -> bool {
+        self.consecutive_failures += 1;
+        self.last_error = Some(error.to_string());
+
+        match self.state {
+            BreakerState::HalfOpen => {
+                // Failed probe: stay disabled for another interval
+                self.state = BreakerState::Open;
+                self.opened_at = Some(now);
+                false
+            }
+            BreakerState::Closed
+                if self.consecutive_failures >= config.failure_threshold.max(1) =>
+            {
+                self.state = BreakerState::Open;
+                self.opened_at = Some(now);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Close the breaker without a probe, e.g. after the model is reloaded
+    pub fn reset(&mut self) {
+        self.record_success();
+    }
+
+    pub fn status(&self, config: &CircuitBreakerConfig, now: Instant) -> BreakerStatus {
+        let next_probe_in_secs = match (self.state, self.opened_at) {
+            (BreakerState::Open, Some(opened)) => Some(
+                config
+                    .probe_interval_secs
+                    .saturating_sub(now.duration_since(opened).as_secs()),
+            ),
+            _ => None,
+        };
+
+        BreakerStatus {
+            state: self.state,
+            consecutive_failures: self.consecutive_failures,
+            failure_threshold: config.failure_threshold,
+            next_probe_in_secs,
+            last_error: self.last_error.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breaker_probes_after_interval_and_recovers() {
+        let config = CircuitBreakerConfig {
+            failure_threshold: 2,
+            probe_interval_secs: 60,
+        };
+        let start = Instant::now();
+        let mut breaker = CircuitBreaker::new();
+
+        assert!(!breaker.record_failure(&config, "device lost", start));
+        assert!(breaker.record_failure(&config, "device lost", start));
+        assert_eq!(breaker.state(), BreakerState::Open);
+        assert!(!breaker.allow_request(&config, start + Duration::from_secs(30)));
+
+        // Failed probe re-opens for another interval
+        assert!(breaker.allow_request(&config, start + Duration::from_secs(61)));
+        assert!(!breaker.record_failure(&config, "device lost", start + Duration::from_secs(61)));
+        assert!(!breaker.allow_request(&config, start + Duration::from_secs(90)));
+
+        assert!(breaker.allow_request(&config, start + Duration::from_secs(122)));
+        assert!(breaker.record_success());
+        assert_eq!(breaker.state(), BreakerState::Closed);
+    }
+}
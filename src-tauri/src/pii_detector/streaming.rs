@@ -0,0 +1,152 @@
+//! Streaming-safe PII redaction for live model output
+//!
+//! Redacting token-by-token misses entities that span token boundaries
+//! (`"jane" ".doe" "@example" ".com"`). [`StreamingRedactor`] keeps a sliding
+//! buffer and only releases text that lies before the trailing holdback
+//! window, ends on a whitespace boundary and does not cut through a detected
+//! entity. Everything left is redacted and released by [`StreamingRedactor::finish`].
+
+use crate::pii_detector::{PIIDetector, PIIEntity};
+use anyhow::Result;
+
+pub struct StreamingRedactor {
+    buffer: String,
+    holdback_chars: usize,
+}
+
+impl StreamingRedactor {
+    pub fn new(holdback_chars: usize) -> Self {
+        Self {
+            buffer: String::new(),
+            holdback_chars,
+        }
+    }
+
+    /// Create a redactor using the detector's configured holdback window
+    pub async fn for_detector(detector: &PIIDetector) -> Self {
+        Self::new(detector.get_config().await.stream_holdback_chars)
+    }
+
+    /// Add a token and return any text that is now safe to emit (may be empty)
+    pub async fn push(&mut self, detector: &PIIDetector, token: &str) -> Result<String> {
+        self.buffer.push_str(token);
+        if self.buffer.len() <= self.holdback_chars {
+            return Ok(String::new());
+        }
+
+        let entities = detector.detect_pii(&self.buffer).await?;
+        let boundary = self.safe_boundary(&entities);
+        Ok(self.release(boundary, &entities))
+    }
+
+    /// Redact and return everything still buffered
+    pub async fn finish(&mut self, detector: &PIIDetector) -> Result<String> {
+        if self.buffer.is_empty() {
+            return Ok(String::new());
+        }
+
+        let entities = detector.detect_pii(&self.buffer).await?;
+        let boundary = self.buffer.len();
+        Ok(self.release(boundary, &entities))
+    }
+
+    /// Text still held back
+    pub fn pending(&self) -> &str {
+        &self.buffer
+    }
+
+    /// Last whitespace boundary before the holdback window that doesn't split an entity
+    fn safe_boundary(&self, entities: &[PIIEntity]) -> usize {
+        let mut limit = self.buffer.len() - self.holdback_chars;
+        while !self.buffer.is_char_boundary(limit) {
+            limit -= 1;
+        }
+
+        let mut boundary = self.buffer[..limit]
+            .char_indices()
+            .rev()
+            .find(|(_, c)| c.is_whitespace())
+            .map(|(i, c)| i + c.len_utf8())
+            .unwrap_or(0);
+
+        // Pull the boundary back to the start of any entity it would split
+        while let Some(entity) = entities
+            .iter()
+            .find(|e| e.start < boundary && e.end > boundary)
+        {
+            boundary = entity.start;
+        }
+
+        boundary
+    }
+
+    /// Drain `buffer[..boundary]`, replacing entities that lie fully inside it
+    fn release(&mut self, boundary: usize, entities: &[PIIEntity]) -> String {
+        let mut inside: Vec<&PIIEntity> = entities.iter().filter(|e| e.end <= boundary).collect();
+        inside.sort_by_key(|e| e.start);
+
+        let mut output = String::new();
+        let mut cursor = 0;
+        for entity in inside {
+            if entity.start < cursor {
+                continue; // Overlaps an entity already replaced
+            }
+            output.push_str(&self.buffer[cursor..entity.start]);
+            output.push_str(&format!("[{}]", entity.entity_type));
+            cursor = entity.end;
+        }
+        output.push_str(&self.buffer[cursor..boundary]);
+
+        self.buffer.drain(..boundary);
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_email_split_across_tokens_is_redacted_before_emission() {
+        let detector = PIIDetector::new();
+        let mut redactor = StreamingRedactor::new(8);
+
+        let tokens = [
+            "Please contact ",
+            "jane",
+            ".doe",
+            "@",
+            "example",
+            ".com",
+            " for the ",
+            "signed copy.",
+        ];
+
+        let mut emitted = Vec::new();
+        for token in tokens {
+            let chunk = redactor.push(&detector, token).await.unwrap();
+            if !chunk.is_empty() {
+                emitted.push(chunk);
+            }
+        }
+        emitted.push(redactor.finish(&detector).await.unwrap());
+
+        for chunk in &emitted {
+            for fragment in ["jane", "doe", "@", "example", ".com"] {
+                assert!(
+                    !chunk.contains(fragment),
+                    "chunk {:?} leaked {:?}",
+                    chunk,
+                    fragment
+                );
+            }
+        }
+        // Text before the email was released while the email was still forming
+        assert_eq!(emitted[0], "Please ");
+        assert_eq!(
+            emitted.concat(),
+            "Please contact [EMAIL] for the signed copy."
+        );
+        assert!(redactor.pending().is_empty());
+    }
+}
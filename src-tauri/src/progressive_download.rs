@@ -0,0 +1,168 @@
+/// First-run model downloads, smallest usable model first
+///
+/// A first run should not wait on a multi-gigabyte model. The smallest model
+/// this machine can run is downloaded and loaded first so chat works at once;
+/// larger models the hardware can also run are then downloaded in the
+/// background, most capable first.
+use crate::llm_manager::{ModelConfig, ModelFeasibility};
+use serde::{Deserialize, Serialize};
+
+/// Tauri event carrying `FirstRunDownloadEvent`s
+pub const FIRST_RUN_DOWNLOAD_EVENT: &str = "first-run-download";
+
+/// Order in which first-run models are downloaded
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DownloadPlan {
+    /// Downloaded and loaded first; chat is available once it is ready
+    pub starter: String,
+    /// Larger models downloaded afterwards, in this order
+    pub background: Vec<String>,
+}
+
+impl DownloadPlan {
+    /// Every planned model in download order
+    pub fn queue(&self) -> Vec<&str> {
+        std::iter::once(self.starter.as_str())
+            .chain(self.background.iter().map(String::as_str))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DownloadStage {
+    Queued,
+    Started,
+    Ready,
+    Failed,
+}
+
+/// Stage change of one planned model; byte progress is reported separately
+/// as `model-download-progress`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FirstRunDownloadEvent {
+    pub model_name: String,
+    pub stage: DownloadStage,
+    /// The starter model, after which chat is usable
+    pub starter: bool,
+    pub error: Option<String>,
+}
+
+/// A model fits when it can be downloaded and loaded, and a GPU model only
+/// when some of it can be offloaded
+fn is_usable(model: &ModelConfig, feasibility: &ModelFeasibility) -> bool {
+    feasibility.feasible && (!model.requires_gpu || feasibility.recommended_gpu_layers > 0)
+}
+
+/// Plan first-run downloads from registry models and their feasibility on
+/// this hardware: the smallest usable model first, then up to
+/// `max_background` larger usable ones not yet downloaded, largest first.
+/// None when no model is usable.
+pub fn plan_downloads(
+    candidates: &[(ModelConfig, ModelFeasibility)],
+    max_background: usize,
+) -> Option<DownloadPlan> {
+    let mut usable: Vec<&(ModelConfig, ModelFeasibility)> = candidates
+        .iter()
+        .filter(|(model, feasibility)| is_usable(model, feasibility))
+        .collect();
+    usable.sort_by(|a, b| a.0.size_mb.cmp(&b.0.size_mb).then(a.0.name.cmp(&b.0.name)));
+
+    let (starter, larger) = usable.split_first()?;
+    let background = larger
+        .iter()
+        .rev()
+        .filter(|(model, feasibility)| {
+            model.size_mb > starter.0.size_mb && !feasibility.already_downloaded
+        })
+        .take(max_background)
+        .map(|(model, _)| model.name.clone())
+        .collect();
+
+    Some(DownloadPlan {
+        starter: starter.0.name.clone(),
+        background,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm_manager::{assess_feasibility, DiskSpaceCheck, SystemResources};
+
+    fn model(name: &str, size_mb: u64, requires_gpu: bool) -> ModelConfig {
+        ModelConfig {
+            name: name.to_string(),
+            model_type: "llama".to_string(),
+            repo_id: format!("test/{}", name),
+            model_file: format!("{}.gguf", name),
+            tokenizer_repo: None,
+            max_tokens: 1024,
+            temperature: 0.7,
+            context_length: 2048,
+            size_mb,
+            quantization: "Q4_K_M".to_string(),
+            requires_gpu,
+            recommended_gpu_layers: Some(32),
+            recommended_vram_mb: Some(size_mb + 500),
+            chat_template: Default::default(),
+        }
+    }
+
+    fn candidate(
+        model: ModelConfig,
+        resources: &SystemResources,
+    ) -> (ModelConfig, ModelFeasibility) {
+        let disk = DiskSpaceCheck {
+            model_name: model.name.clone(),
+            required_mb: model.size_mb,
+            available_mb: Some(100_000),
+            headroom_mb: 0,
+            shortfall_mb: 0,
+            sufficient: true,
+        };
+        let feasibility = assess_feasibility(&model, resources, disk, false);
+        (model, feasibility)
+    }
+
+    #[test]
+    fn test_smallest_model_queued_ahead_of_recommended() {
+        let workstation = SystemResources {
+            available_ram_mb: 32_000,
+            free_vram_mb: Some(8_000),
+        };
+        // Registry order puts the larger models first
+        let candidates: Vec<_> = [
+            model("mistral-7b-instruct", 4370, true),
+            model("phi-2", 1600, false),
+            model("llama2-7b-chat", 3830, true),
+            model("tinyllama-1.1b", 638, false),
+        ]
+        .into_iter()
+        .map(|m| candidate(m, &workstation))
+        .collect();
+
+        let plan = plan_downloads(&candidates, 2).unwrap();
+        assert_eq!(plan.starter, "tinyllama-1.1b");
+        assert_eq!(
+            plan.queue(),
+            vec!["tinyllama-1.1b", "mistral-7b-instruct", "llama2-7b-chat"]
+        );
+
+        // Without a GPU the 7B models are not recommended
+        let laptop = SystemResources {
+            available_ram_mb: 8_000,
+            free_vram_mb: None,
+        };
+        let candidates: Vec<_> = candidates
+            .into_iter()
+            .map(|(m, _)| candidate(m, &laptop))
+            .collect();
+        let plan = plan_downloads(&candidates, 2).unwrap();
+        assert_eq!(plan.queue(), vec!["tinyllama-1.1b", "phi-2"]);
+        assert!(plan_downloads(&candidates, 0)
+            .unwrap()
+            .background
+            .is_empty());
+    }
+}
@@ -0,0 +1,138 @@
+/// Query expansion for knowledge base retrieval
+///
+/// A single query misses chunks that put the same idea in other words. The
+/// query is reworded into paraphrases (by the loaded model, or from templates
+/// when there is none), each wording is retrieved for concurrently, and the
+/// ranked lists are fused with reciprocal rank fusion: a chunk scores the sum
+/// of 1 / (k + rank) over the lists it appears in, so chunks found by several
+/// wordings rise without any one list's raw scores dominating.
+use crate::completion::{CompletionModel, CompletionOptions};
+use crate::rag_engine::SearchResult;
+use anyhow::Result;
+use std::collections::HashMap;
+
+/// Smoothing constant of reciprocal rank fusion; 60 is the customary value
+const RRF_K: f32 = 60.0;
+
+/// Words dropped when reducing a query to its keywords
+const STOP_WORDS: &[&str] = &[
+    "a", "an", "the", "is", "are", "was", "were", "be", "do", "does", "did", "what", "when",
+    "where", "who", "which", "how", "why", "can", "could", "should", "would", "may", "might",
+    "must", "shall", "will", "of", "in", "on", "for", "to", "by", "with", "about", "under", "i",
+    "we", "you", "my", "our", "there", "this", "that",
+];
+
+/// Up to `count` rewordings of `query` by the model, excluding the query itself
+pub async fn model_paraphrases(
+    model: &dyn CompletionModel,
+    query: &str,
+    count: usize,
+) -> Result<Vec<String>> {
+    let prompt = format!(
+        "Rewrite the search query below in {} different ways, using other words a legal \
+         document might use for the same thing. Reply with one rewording per line and \
+         nothing else.\n\nQuery: {}\n\nRewordings:",
+        count,
+        query.trim()
+    );
+    let options = CompletionOptions {
+        max_tokens: Some(40 * count.max(1)),
+        ..Default::default()
+    };
+    let reply = model.complete(&prompt, &options).await?;
+    Ok(parse_paraphrases(&reply, query, count))
+}
+
+/// Paraphrases built from fixed templates around the query's keywords, for
+/// when no model is loaded
+pub fn template_paraphrases(query: &str, count: usize) -> Vec<String> {
+    let keywords = keywords(query);
+    if keywords.is_empty() {
+        return Vec::new();
+    }
+    let candidates = vec![
+        keywords.clone(),
+        format!("What does the agreement say about {}?", keywords),
+        format!("{} obligations, conditions and exceptions", keywords),
+        format!("definition of {}", keywords),
+    ];
+    distinct_from(query, candidates, count)
+}
+
+/// The query without question words, articles and punctuation
+fn keywords(query: &str) -> String {
+    query
+        .split(|c: char| !c.is_alphanumeric() && c != '-' && c != '\'')
+        .filter(|word| !word.is_empty())
+        .filter(|word| !STOP_WORDS.contains(&word.to_lowercase().as_str()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Rewordings in a model reply, one per line, without numbering or quotes
+fn parse_paraphrases(reply: &str, query: &str, count: usize) -> Vec<String> {
+    let lines = reply.lines().map(|line| {
+        line.trim()
+            .trim_start_matches(|c: char| c.is_ascii_digit() || matches!(c, '.' | ')' | '-' | '*'))
+            .trim()
+            .trim_matches('"')
+            .trim()
+            .to_string()
+    });
+    distinct_from(query, lines.collect(), count)
+}
+
+/// The first `count` non-empty candidates differing from the query and each other
+fn distinct_from(query: &str, candidates: Vec<String>, count: usize) -> Vec<String> {
+    let mut seen = vec![query.trim().to_lowercase()];
+    let mut paraphrases = Vec::new();
+    for candidate in candidates {
+        let key = candidate.to_lowercase();
+        if candidate.is_empty() || seen.contains(&key) {
+            continue;
+        }
+        seen.push(key);
+        paraphrases.push(candidate);
+        if paraphrases.len() == count {
+            break;
+        }
+    }
+    paraphrases
+}
+
+/// Fuse ranked result lists into one, scoring each chunk by reciprocal rank
+/// fusion and keeping the best `limit`. A chunk found by several lists appears
+/// once, as first retrieved, with its best retrieval `score` and the fused
+/// score in `fused_score`, which orders the results.
+pub fn reciprocal_rank_fusion(lists: Vec<Vec<SearchResult>>, limit: usize) -> Vec<SearchResult> {
+    let mut fused: HashMap<String, (f32, SearchResult)> = HashMap::new();
+    for list in lists {
+        for (rank, result) in list.into_iter().enumerate() {
+            let score = 1.0 / (RRF_K + rank as f32 + 1.0);
+            let retrieval_score = result.score;
+            fused
+                .entry(result.document_id.clone())
+                .and_modify(|(total, kept)| {
+                    *total += score;
+                    kept.score = kept.score.max(retrieval_score);
+                })
+                .or_insert((score, result));
+        }
+    }
+
+    let mut results: Vec<SearchResult> = fused
+        .into_values()
+        .map(|(score, mut result)| {
+            result.fused_score = Some(score);
+            result
+        })
+        .collect();
+    results.sort_by(|a, b| {
+        b.fused_score
+            .partial_cmp(&a.fused_score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.document_id.cmp(&b.document_id))
+    });
+    results.truncate(limit);
+    results
+}
@@ -1,11 +1,25 @@
+use crate::completion::CompletionModel;
+use crate::compliance::UserDataStore;
+use crate::document_diff::{classify_hunk, diff_sentences, ChangeKind, SentenceChange};
+use crate::embedding_projection::{
+    sample_evenly, DimensionReduction, EmbeddingProjection, PCA_MAX_FIT_SAMPLES,
+};
+use crate::query_expansion::{model_paraphrases, reciprocal_rank_fusion, template_paraphrases};
+use crate::text_segmentation::{
+    chunk_by_tokens, chunk_by_units, detect_script, split_paragraphs, split_sentences,
+    HeuristicTokenCounter, ScriptClass, TokenCounter, WordCounter,
+};
 use crate::utils::cosine_similarity;
 use anyhow::{anyhow, Result};
+use async_trait::async_trait;
 use fastembed::{EmbeddingModel, InitOptions, TextEmbedding};
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
+use sha2::{Digest, Sha256};
 use std::collections::{HashMap, HashSet};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, BufWriter};
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
@@ -21,6 +35,123 @@ pub struct Document {
     pub timestamp: i64,
     pub chunk_index: usize,
     pub total_chunks: usize,
+    /// Model that produced `embeddings`; None for chunks indexed before it was recorded
+    #[serde(default)]
+    pub embedding_model: Option<String>,
+    /// `chunk_size` and `chunk_overlap` the chunk was cut with; None for chunks
+    /// indexed before they were recorded
+    #[serde(default)]
+    pub chunk_size: Option<usize>,
+    #[serde(default)]
+    pub chunk_overlap: Option<usize>,
+    /// Strategy the chunk was cut with; None for chunks indexed before it was recorded
+    #[serde(default)]
+    pub chunking_strategy: Option<ChunkingStrategy>,
+    /// Source the parent document was ingested from; None for chunks indexed
+    /// without one
+    #[serde(default)]
+    pub source: Option<IngestSource>,
+}
+
+/// How a document's text was extracted from its source file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExtractionMethod {
+    /// Text read from the file itself
+    Native,
+    /// Text recognised from page images
+    Ocr,
+}
+
+/// Source of an ingested document, recorded with each of its chunks
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IngestSource {
+    /// SHA-256 of the source file as received, before extraction and redaction
+    pub source_sha256: String,
+    pub extraction_method: ExtractionMethod,
+    /// Placeholders PII redaction put in the text, such as `[EMAIL]`
+    #[serde(default)]
+    pub redaction_markers: Vec<String>,
+}
+
+impl IngestSource {
+    pub fn new(
+        source: &[u8],
+        extraction_method: ExtractionMethod,
+        redaction_markers: Vec<String>,
+    ) -> Self {
+        Self::with_sha256(
+            hex::encode(Sha256::digest(source)),
+            extraction_method,
+            redaction_markers,
+        )
+    }
+
+    /// Source whose bytes were already hashed while they were extracted
+    pub fn with_sha256(
+        source_sha256: String,
+        extraction_method: ExtractionMethod,
+        redaction_markers: Vec<String>,
+    ) -> Self {
+        Self {
+            source_sha256,
+            extraction_method,
+            redaction_markers,
+        }
+    }
+}
+
+/// How an indexed chunk was derived, so a retrieved passage can be traced back
+/// to its source. Fields are None for chunks indexed before they were recorded.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChunkProvenance {
+    pub chunk_id: String,
+    pub document_id: String,
+    pub source_sha256: Option<String>,
+    pub extraction_method: Option<ExtractionMethod>,
+    /// PII was replaced by a placeholder within this chunk
+    pub pii_redacted: Option<bool>,
+    pub embedding_model: Option<String>,
+    /// Unix timestamp the chunk was indexed at
+    pub ingested_at: i64,
+}
+
+impl ChunkProvenance {
+    fn of(chunk: &Document) -> Self {
+        let source = chunk.source.as_ref();
+        Self {
+            chunk_id: chunk.id.clone(),
+            document_id: RAGEngine::parent_document_id(&chunk.id),
+            source_sha256: source.map(|s| s.source_sha256.clone()),
+            extraction_method: source.map(|s| s.extraction_method),
+            pii_redacted: source.map(|s| {
+                s.redaction_markers
+                    .iter()
+                    .any(|marker| chunk.content.contains(marker.as_str()))
+            }),
+            embedding_model: chunk.embedding_model.clone(),
+            ingested_at: chunk.timestamp,
+        }
+    }
+}
+
+/// How documents are cut into chunks before embedding
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChunkingStrategy {
+    /// Windows of `chunk_size` words overlapping by `chunk_overlap` words
+    #[default]
+    #[serde(alias = "fixed")]
+    FixedChar,
+    /// Whole sentences up to `chunk_size` words, overlapping by
+    /// `chunk_overlap_units` sentences
+    Sentence,
+    /// Whole paragraphs up to `chunk_size` words, overlapping by
+    /// `chunk_overlap_units` paragraphs; longer paragraphs fall back to sentences
+    Paragraph,
+    /// Sentences packed up to `chunk_size` tokens of the loaded tokenizer,
+    /// overlapping by `chunk_overlap` tokens
+    TokenAware,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -31,6 +162,32 @@ pub struct SearchResult {
     pub metadata: JsonValue,
     pub highlight: Option<String>,
     pub reasoning: Option<String>,
+    /// First and last chunk index of the parent document this passage covers
+    #[serde(default)]
+    pub chunk_range: Option<(usize, usize)>,
+    /// Source trust weight of the parent document applied to `score`
+    #[serde(default = "default_trust_weight")]
+    pub trust_weight: f32,
+    /// How the first chunk of this passage was derived
+    #[serde(default)]
+    pub provenance: Option<ChunkProvenance>,
+    /// Reciprocal rank fusion score when expanded retrieval merged several
+    /// rankings; results are then ordered by it, while `score` stays the best
+    /// similarity the passage was retrieved with
+    #[serde(default)]
+    pub fused_score: Option<f32>,
+}
+
+/// Best-supporting retrieved chunk for one sentence of a grounded answer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SentenceAttribution {
+    pub sentence: String,
+    /// Index into the sources the answer was grounded on
+    pub source_index: Option<usize>,
+    pub document_id: Option<String>,
+    pub similarity: f32,
+    /// False when no source reaches `attribution_min_similarity`
+    pub supported: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -42,6 +199,145 @@ pub struct RAGConfig {
     pub max_results: usize,
     pub enable_reranking: bool,
     pub enable_hybrid_search: bool,
+    /// Chunks sent to the embedding backend per call
+    #[serde(default = "default_embedding_batch_size")]
+    pub embedding_batch_size: usize,
+    /// Batches embedded concurrently, each on its own model instance; bounds the
+    /// vectors held in memory at once. Takes effect when the model is next loaded.
+    #[serde(default = "default_embedding_parallelism")]
+    pub embedding_parallelism: usize,
+    /// Map each sentence of a grounded answer to its best-supporting source
+    #[serde(default = "default_sentence_attribution")]
+    pub sentence_attribution: bool,
+    /// Minimum sentence/source similarity for a sentence to count as supported
+    #[serde(default = "default_attribution_min_similarity")]
+    pub attribution_min_similarity: f32,
+    /// Chunk unspaced scripts (CJK, Thai, ...) by sentences and tokens; `chunk_size`
+    /// and `chunk_overlap` then count tokens instead of whitespace-separated words
+    #[serde(default = "default_language_aware_chunking")]
+    pub language_aware_chunking: bool,
+    /// Collapse results from overlapping chunks of one document into a single passage
+    #[serde(default = "default_merge_overlapping_citations")]
+    pub merge_overlapping_citations: bool,
+    /// Reduce embeddings before storing them; changed with `set_dimension_reduction`
+    #[serde(default)]
+    pub dimension_reduction: DimensionReduction,
+    /// Store embeddings scaled to unit length
+    #[serde(default)]
+    pub normalize_embeddings: bool,
+    /// Weight of the vector score in hybrid search; the BM25 keyword score gets
+    /// the rest (1.0 is pure vector search, 0.0 pure keyword search)
+    #[serde(default = "default_hybrid_alpha")]
+    pub hybrid_alpha: f32,
+    /// Re-chunk and re-embed indexed documents when `chunk_size` or
+    /// `chunk_overlap` change; otherwise the index is left with mixed chunking
+    #[serde(default)]
+    pub auto_rechunk: bool,
+    /// Also retrieve for paraphrases of the query and fuse the rankings
+    #[serde(default)]
+    pub expand_query: bool,
+    /// Paraphrases retrieved for per query when `expand_query` is on
+    #[serde(default = "default_query_paraphrases")]
+    pub query_paraphrases: usize,
+    #[serde(default)]
+    pub chunking_strategy: ChunkingStrategy,
+    /// Sentences or paragraphs repeated at the start of the next chunk by the
+    /// sentence and paragraph strategies
+    #[serde(default = "default_chunk_overlap_units")]
+    pub chunk_overlap_units: usize,
+}
+
+fn default_embedding_batch_size() -> usize {
+    32
+}
+
+fn default_embedding_parallelism() -> usize {
+    2
+}
+
+fn default_sentence_attribution() -> bool {
+    true
+}
+
+fn default_attribution_min_similarity() -> f32 {
+    0.5
+}
+
+fn default_language_aware_chunking() -> bool {
+    true
+}
+
+fn default_merge_overlapping_citations() -> bool {
+    true
+}
+
+fn default_hybrid_alpha() -> f32 {
+    0.7
+}
+
+fn default_query_paraphrases() -> usize {
+    3
+}
+
+fn default_chunk_overlap_units() -> usize {
+    1
+}
+
+/// BM25 term frequency saturation and document length normalization
+const BM25_K1: f32 = 1.2;
+const BM25_B: f32 = 0.75;
+
+/// Terms of `text` as stored in the keyword index
+fn index_terms(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split_whitespace()
+        .map(str::to_lowercase)
+        .filter(|t| t.len() > 2)
+}
+
+/// Distinct index terms of a query, in order of first occurrence
+fn query_terms(query: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    index_terms(query)
+        .filter(|term| seen.insert(term.clone()))
+        .collect()
+}
+
+/// Shortest shared text treated as chunk overlap rather than coincidence
+const MIN_CITATION_OVERLAP_CHARS: usize = 16;
+
+/// Document metadata key holding its source trust weight
+pub const TRUST_WEIGHT_KEY: &str = "trust_weight";
+
+/// Document metadata key naming the user a document was ingested for
+pub const OWNER_KEY: &str = "user_id";
+
+/// Accepted range for source trust weights; 1.0 is neutral
+const MIN_TRUST_WEIGHT: f32 = 0.1;
+const MAX_TRUST_WEIGHT: f32 = 3.0;
+
+fn default_trust_weight() -> f32 {
+    1.0
+}
+
+fn validate_trust_weight(weight: f32) -> Result<f32> {
+    if !(MIN_TRUST_WEIGHT..=MAX_TRUST_WEIGHT).contains(&weight) {
+        return Err(anyhow!(
+            "Trust weight must be between {} and {}, got {}",
+            MIN_TRUST_WEIGHT,
+            MAX_TRUST_WEIGHT,
+            weight
+        ));
+    }
+    Ok(weight)
+}
+
+/// Trust weight stored in a chunk's metadata, neutral when absent
+fn trust_weight_of(metadata: &JsonValue) -> f32 {
+    metadata
+        .get(TRUST_WEIGHT_KEY)
+        .and_then(JsonValue::as_f64)
+        .map(|w| (w as f32).clamp(MIN_TRUST_WEIGHT, MAX_TRUST_WEIGHT))
+        .unwrap_or_else(default_trust_weight)
 }
 
 impl Default for RAGConfig {
@@ -54,7 +350,85 @@ impl Default for RAGConfig {
             max_results: 10,
             enable_reranking: true,
             enable_hybrid_search: true,
+            embedding_batch_size: default_embedding_batch_size(),
+            embedding_parallelism: default_embedding_parallelism(),
+            sentence_attribution: default_sentence_attribution(),
+            attribution_min_similarity: default_attribution_min_similarity(),
+            language_aware_chunking: default_language_aware_chunking(),
+            merge_overlapping_citations: default_merge_overlapping_citations(),
+            dimension_reduction: DimensionReduction::None,
+            normalize_embeddings: false,
+            hybrid_alpha: default_hybrid_alpha(),
+            auto_rechunk: false,
+            expand_query: false,
+            query_paraphrases: default_query_paraphrases(),
+            chunking_strategy: ChunkingStrategy::FixedChar,
+            chunk_overlap_units: default_chunk_overlap_units(),
+        }
+    }
+}
+
+impl RAGConfig {
+    fn transforms_embeddings(&self) -> bool {
+        self.dimension_reduction != DimensionReduction::None || self.normalize_embeddings
+    }
+}
+
+/// Produces embedding vectors for batches of text
+#[async_trait]
+pub trait EmbeddingBackend: Send + Sync {
+    /// Embed every text, returning one vector per input in the same order
+    async fn embed_batch(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>>;
+}
+
+/// FastEmbed-backed embedding model. ONNX inference is blocking and a model
+/// instance embeds one batch at a time, so the backend keeps a pool of
+/// instances and runs each batch on the blocking thread pool.
+pub struct FastEmbedBackend {
+    models: Vec<Arc<std::sync::Mutex<TextEmbedding>>>,
+    next: std::sync::atomic::AtomicUsize,
+}
+
+impl FastEmbedBackend {
+    /// Load `pool_size` instances of `model_name` (at least one)
+    pub fn new(model_name: &str, pool_size: usize) -> Result<Self> {
+        let embedding_model = EmbeddingModel::try_from(model_name.to_string()).map_err(|e| {
+            anyhow!(
+                "Failed to create EmbeddingModel from name '{}': {}",
+                model_name,
+                e
+            )
+        })?;
+
+        let mut models = Vec::with_capacity(pool_size.max(1));
+        for _ in 0..pool_size.max(1) {
+            let model = TextEmbedding::try_new(
+                InitOptions::new(embedding_model.clone()).with_show_download_progress(true),
+            )?;
+            models.push(Arc::new(std::sync::Mutex::new(model)));
         }
+
+        Ok(Self {
+            models,
+            next: std::sync::atomic::AtomicUsize::new(0),
+        })
+    }
+}
+
+#[async_trait]
+impl EmbeddingBackend for FastEmbedBackend {
+    async fn embed_batch(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        // Round-robin, so concurrent batches up to the pool size get their own instance
+        let slot = self.next.fetch_add(1, std::sync::atomic::Ordering::Relaxed) % self.models.len();
+        let model = self.models[slot].clone();
+        tokio::task::spawn_blocking(move || {
+            let batch_size = texts.len();
+            let mut model = model
+                .lock()
+                .map_err(|_| anyhow!("Embedding model lock poisoned"))?;
+            Ok(model.embed(texts, Some(batch_size))?)
+        })
+        .await?
     }
 }
 
@@ -70,12 +444,242 @@ pub struct RAGModelInfo {
     pub is_active: bool,
 }
 
+/// Chunks embedded by one model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingModelShare {
+    /// "unknown" for chunks without a recorded model
+    pub model: String,
+    pub chunks: usize,
+    /// Fraction of all chunks, 0.0..=1.0
+    pub share: f64,
+}
+
+/// Size and health overview of the knowledge base
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RAGIndexStatistics {
+    pub document_count: usize,
+    pub total_chunks: usize,
+    /// Currently configured embedding model
+    pub embedding_model: String,
+    /// Vector length of stored embeddings; None for an empty index
+    pub embedding_dimension: Option<usize>,
+    /// Dimension produced by the embedding model when stored vectors are reduced
+    pub source_embedding_dimension: Option<usize>,
+    pub average_chunk_chars: f64,
+    /// Estimated in-memory size of chunks, embeddings and keyword index
+    pub index_memory_bytes: usize,
+    /// Size of the persisted index files
+    pub index_disk_bytes: u64,
+    pub oldest_document: Option<String>,
+    pub newest_document: Option<String>,
+    /// Breakdown by embedding model, largest first; more than one entry means a mixed index
+    pub embedding_models: Vec<EmbeddingModelShare>,
+    /// Documents chunked with other settings than the configured ones
+    pub stale_chunking_documents: usize,
+    /// Chunks of the index were cut with different settings
+    pub mixed_chunking: bool,
+}
+
+/// Progress of a re-chunking run, reported after each document
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RechunkProgress {
+    pub processed: usize,
+    pub total: usize,
+    pub document_id: String,
+}
+
+/// Outcome of re-chunking the index with the current chunking settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RechunkReport {
+    pub documents: usize,
+    pub chunks_before: usize,
+    pub chunks_after: usize,
+}
+
+/// Format tag written in the first line of a knowledge base archive
+const KNOWLEDGE_BASE_FORMAT: &str = "bear-ai-knowledge-base";
+const KNOWLEDGE_BASE_VERSION: u32 = 1;
+
+/// Header line of a knowledge base archive (JSON Lines, one chunk per line after it)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KnowledgeBaseHeader {
+    pub format: String,
+    pub version: u32,
+    /// Embedding model that produced the stored vectors
+    pub embedding_model: String,
+    /// Projection applied to the stored vectors, if any
+    #[serde(default)]
+    pub projection: Option<EmbeddingProjection>,
+    pub chunk_count: usize,
+    pub exported_at: String,
+}
+
+/// Summary of a knowledge base export or import
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KnowledgeBaseTransfer {
+    pub path: PathBuf,
+    pub embedding_model: String,
+    pub chunks: usize,
+    pub documents: usize,
+    /// Set on import when the archive came from a different embedding model
+    /// or dimension reduction
+    pub re_embedded: bool,
+}
+
+/// Outcome of changing the dimension reduction of stored embeddings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DimensionReductionReport {
+    pub reduction: DimensionReduction,
+    pub normalize: bool,
+    /// Dimension produced by the embedding model; None for an empty index
+    pub source_dimension: Option<usize>,
+    pub stored_dimension: Option<usize>,
+    pub chunks_re_embedded: usize,
+    /// Embedding memory at the model's full dimension and as stored
+    pub full_embedding_bytes: usize,
+    pub stored_embedding_bytes: usize,
+    pub bytes_saved: usize,
+}
+
+/// Chunks embedded so far while re-embedding the index
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingProgress {
+    pub embedded: usize,
+    pub total: usize,
+}
+
+/// Outcome of re-embedding the index with the configured model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexRebuildReport {
+    pub embedding_model: String,
+    pub chunks_re_embedded: usize,
+    /// Vector length of the stored embeddings; None for an empty index
+    pub embedding_dimension: Option<usize>,
+}
+
+/// Format version of the persisted index, bumped when stored chunks change incompatibly
+const INDEX_FORMAT_VERSION: u32 = 1;
+
+/// Written beside the index files so a reload knows how its vectors were produced
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexManifest {
+    version: u32,
+    embedding_model: String,
+}
+
+/// Metadata key grouping documents by legal matter; versions are matched
+/// on filename within a matter
+pub const MATTER_KEY: &str = "matter";
+
+/// Metadata constraints scoping a search. Every key must match: equal to the
+/// given value, or to one of a list given as `{"$in": [...]}`. `document_id`
+/// falls back to the chunk's parent document id when the metadata lacks it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MetadataFilter {
+    constraints: Vec<(String, Vec<JsonValue>)>,
+}
+
+impl MetadataFilter {
+    /// Parse a filter object such as `{"file_type": "pdf", "matter": {"$in": ["M-1", "M-2"]}}`
+    pub fn from_json(filter: &JsonValue) -> Result<Self> {
+        let fields = filter
+            .as_object()
+            .ok_or_else(|| anyhow!("Metadata filter must be an object of field constraints"))?;
+        let mut constraints = Vec::new();
+        for (key, constraint) in fields {
+            let allowed = match constraint {
+                JsonValue::Object(operator) => match operator.get("$in") {
+                    Some(JsonValue::Array(values)) if operator.len() == 1 => values.clone(),
+                    _ => {
+                        return Err(anyhow!(
+                            "Unsupported constraint on '{}': use a value or {{\"$in\": [...]}}",
+                            key
+                        ))
+                    }
+                },
+                value => vec![value.clone()],
+            };
+            constraints.push((key.clone(), allowed));
+        }
+        Ok(Self { constraints })
+    }
+
+    fn matches(&self, chunk_id: &str, metadata: &JsonValue) -> bool {
+        self.constraints.iter().all(|(key, allowed)| {
+            let value = match metadata.get(key) {
+                Some(value) => value.clone(),
+                None if key == "document_id" => {
+                    JsonValue::String(RAGEngine::parent_document_id(chunk_id))
+                }
+                None => return false,
+            };
+            allowed.contains(&value)
+        })
+    }
+}
+
+/// Similarity at which a rewritten sentence counts as modified rather than
+/// replaced by an unrelated one
+const MODIFIED_SENTENCE_SIMILARITY: f32 = 0.8;
+
+/// Full text of an indexed document, kept so a re-upload can be compared
+/// with it; chunks overlap and cannot reproduce the original exactly
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct DocumentVersion {
+    filename: String,
+    matter: Option<String>,
+    content_hash: String,
+    content: String,
+    indexed_at: i64,
+}
+
+/// Differences between an incoming document and the indexed version with the
+/// same filename and matter
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentChangeReport {
+    pub previous_document_id: String,
+    pub filename: String,
+    pub matter: Option<String>,
+    pub previous_hash: String,
+    pub current_hash: String,
+    pub content_changed: bool,
+    /// Embedding similarity of the two versions as a whole
+    pub semantic_similarity: f32,
+    pub added: usize,
+    pub removed: usize,
+    pub modified: usize,
+    pub changes: Vec<SentenceChange>,
+}
+
+fn content_hash(text: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(text.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+fn version_key(metadata: &JsonValue) -> Option<(String, Option<String>)> {
+    let filename = metadata.get("filename")?.as_str()?.to_string();
+    let matter = metadata
+        .get(MATTER_KEY)
+        .and_then(|m| m.as_str())
+        .map(str::to_string);
+    Some((filename, matter))
+}
+
 pub struct RAGEngine {
     documents: Arc<RwLock<HashMap<String, Document>>>,
-    embeddings_model: Arc<RwLock<Option<TextEmbedding>>>,
+    embeddings_model: Arc<RwLock<Option<Arc<dyn EmbeddingBackend>>>>,
     config: Arc<RwLock<RAGConfig>>,
     index_path: PathBuf,
+    /// Chunk ids by term, one entry per occurrence so term frequencies can be counted
     inverted_index: Arc<RwLock<HashMap<String, Vec<String>>>>,
+    /// Indexed terms per chunk, for BM25 length normalization; rebuilt on load
+    chunk_term_counts: Arc<RwLock<HashMap<String, usize>>>,
+    token_counter: Arc<RwLock<Arc<dyn TokenCounter>>>,
+    /// Fitted reduction of stored embeddings; None stores them as embedded
+    projection: Arc<RwLock<Option<EmbeddingProjection>>>,
+    /// Source text of documents that have a filename, by document id
+    versions: Arc<RwLock<HashMap<String, DocumentVersion>>>,
 }
 
 impl Default for RAGEngine {
@@ -91,12 +695,21 @@ impl RAGEngine {
             .join("bear-ai-llm")
             .join("rag_index");
 
+        Self::with_index_path(index_path)
+    }
+
+    /// Create an engine persisting its index in `index_path`
+    pub fn with_index_path(index_path: PathBuf) -> Self {
         Self {
             documents: Arc::new(RwLock::new(HashMap::new())),
             embeddings_model: Arc::new(RwLock::new(None)),
             config: Arc::new(RwLock::new(RAGConfig::default())),
             index_path,
             inverted_index: Arc::new(RwLock::new(HashMap::new())),
+            chunk_term_counts: Arc::new(RwLock::new(HashMap::new())),
+            token_counter: Arc::new(RwLock::new(Arc::new(HeuristicTokenCounter))),
+            projection: Arc::new(RwLock::new(None)),
+            versions: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 
@@ -152,40 +765,207 @@ impl RAGEngine {
         Ok(())
     }
 
-    async fn ensure_embeddings_model(&self) -> Result<()> {
-    if self.embeddings_model.read().await.is_some() {
-        return Ok(());
+    /// Use `backend` for all embeddings instead of loading a FastEmbed model
+    pub async fn set_embedding_backend(&self, backend: Arc<dyn EmbeddingBackend>) {
+        *self.embeddings_model.write().await = Some(backend);
     }
 
-    let model_name = self.config.read().await.embedding_model.clone();
+    /// Count tokens for language-aware chunking with `counter`, e.g. the loaded LLM tokenizer
+    pub async fn set_token_counter(&self, counter: Arc<dyn TokenCounter>) {
+        *self.token_counter.write().await = counter;
+    }
 
-    // Fix applied here: Convert the String error from try_from into an anyhow::Error.
-    let embedding_model = EmbeddingModel::try_from(model_name.clone())
-        .map_err(|e| anyhow!("Failed to create EmbeddingModel from name '{}': {}", model_name, e))?;
+    async fn embedding_backend(&self) -> Result<Arc<dyn EmbeddingBackend>> {
+        if let Some(backend) = self.embeddings_model.read().await.as_ref() {
+            return Ok(backend.clone());
+        }
 
-    // Initialize embedding model directly
-    let model = TextEmbedding::try_new(
-        InitOptions::new(embedding_model)
-            .with_show_download_progress(true),
-    )?;
+        let (model_name, pool_size) = {
+            let cfg = self.config.read().await;
+            (cfg.embedding_model.clone(), cfg.embedding_parallelism)
+        };
+        let backend: Arc<dyn EmbeddingBackend> =
+            Arc::new(FastEmbedBackend::new(&model_name, pool_size)?);
 
-    let mut lock = self.embeddings_model.write().await;
-    *lock = Some(model);
+        let mut lock = self.embeddings_model.write().await;
+        *lock = Some(backend.clone());
+
+        tracing::info!("✅ Loaded embedding model: {}", model_name);
+        Ok(backend)
+    }
+
+    /// Embed a single piece of text with the active embedding model
+    pub async fn embed_text(&self, text: &str) -> Result<Vec<f32>> {
+        let backend = self.embedding_backend().await?;
+        backend
+            .embed_batch(vec![text.to_string()])
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("Failed to embed text"))
+    }
+
+    /// Embed chunks in batches, running up to `embedding_parallelism` batches at once
+    async fn embed_chunks(&self, chunks: &[String]) -> Result<Vec<Vec<f32>>> {
+        self.embed_chunks_reporting(chunks, &|_| {}).await
+    }
+
+    /// `embed_chunks`, calling `on_progress` as each wave of batches completes
+    async fn embed_chunks_reporting(
+        &self,
+        chunks: &[String],
+        on_progress: &(dyn Fn(EmbeddingProgress) + Send + Sync),
+    ) -> Result<Vec<Vec<f32>>> {
+        let backend = self.embedding_backend().await?;
+        let (batch_size, parallelism) = {
+            let cfg = self.config.read().await;
+            (
+                cfg.embedding_batch_size.max(1),
+                cfg.embedding_parallelism.max(1),
+            )
+        };
+
+        let mut embeddings = Vec::with_capacity(chunks.len());
+        let batches: Vec<&[String]> = chunks.chunks(batch_size).collect();
+        for wave in batches.chunks(parallelism) {
+            let mut tasks = tokio::task::JoinSet::new();
+            for (position, batch) in wave.iter().enumerate() {
+                let backend = backend.clone();
+                let texts = batch.to_vec();
+                tasks.spawn(async move { (position, backend.embed_batch(texts).await) });
+            }
+
+            let mut results: Vec<Option<Vec<Vec<f32>>>> = vec![None; wave.len()];
+            while let Some(joined) = tasks.join_next().await {
+                let (position, result) = joined?;
+                let vectors = result?;
+                if vectors.len() != wave[position].len() {
+                    return Err(anyhow!(
+                        "Embedding backend returned {} vectors for {} chunks",
+                        vectors.len(),
+                        wave[position].len()
+                    ));
+                }
+                results[position] = Some(vectors);
+            }
+            embeddings.extend(results.into_iter().flatten().flatten());
+            on_progress(EmbeddingProgress {
+                embedded: embeddings.len(),
+                total: chunks.len(),
+            });
+        }
+
+        Ok(embeddings)
+    }
 
-    tracing::info!("✅ Loaded embedding model: {}", model_name);
-    Ok(())
-}
     pub fn is_initialized(&self) -> bool {
         true
     }
 
-    pub async fn switch_rag_model(&self, model_id: String) -> Result<()> {
-        self.config.write().await.embedding_model = model_id.clone();
-        *self.embeddings_model.write().await = None;
-        tracing::info!("🔄 RAG model switched to: {}", model_id);
+    /// Switch the embedding model, loading it first. See `switch_embedding_model_with`.
+    pub async fn switch_embedding_model(
+        &self,
+        model_id: &str,
+        force: bool,
+        on_progress: &(dyn Fn(EmbeddingProgress) + Send + Sync),
+    ) -> Result<IndexRebuildReport> {
+        // Refuse before loading a model that would not be used
+        self.check_model_switch(model_id, force).await?;
+        let pool_size = self.config.read().await.embedding_parallelism;
+        let backend: Arc<dyn EmbeddingBackend> =
+            Arc::new(FastEmbedBackend::new(model_id, pool_size)?);
+        self.switch_embedding_model_with(model_id, backend, force, on_progress)
+            .await
+    }
+
+    /// Switch to `model_id`, embedding with `backend`. Vectors of another model
+    /// are meaningless against the new model's queries, so chunks not embedded
+    /// with it are re-embedded when `force` is set, and the switch is refused
+    /// while any exist otherwise. If re-embedding fails the previous model stays.
+    pub async fn switch_embedding_model_with(
+        &self,
+        model_id: &str,
+        backend: Arc<dyn EmbeddingBackend>,
+        force: bool,
+        on_progress: &(dyn Fn(EmbeddingProgress) + Send + Sync),
+    ) -> Result<IndexRebuildReport> {
+        self.check_model_switch(model_id, force).await?;
+        let previous_model = self.get_active_model().await;
+        let previous_backend = self.embeddings_model.read().await.clone();
+        self.config.write().await.embedding_model = model_id.to_string();
+        *self.embeddings_model.write().await = Some(backend);
+
+        match self.rebuild_index(on_progress).await {
+            Ok(report) => {
+                tracing::info!("🔄 RAG model switched to: {}", model_id);
+                Ok(report)
+            }
+            Err(e) => {
+                self.config.write().await.embedding_model = previous_model;
+                *self.embeddings_model.write().await = previous_backend;
+                Err(e)
+            }
+        }
+    }
+
+    async fn check_model_switch(&self, model_id: &str, force: bool) -> Result<()> {
+        let stale = self
+            .documents
+            .read()
+            .await
+            .values()
+            .filter(|doc| doc.embedding_model.as_deref() != Some(model_id))
+            .count();
+        if stale > 0 && !force {
+            return Err(anyhow!(
+                "{} indexed chunks are not embedded with {}; switch with force to \
+                 re-embed them, or clear the index first",
+                stale,
+                model_id
+            ));
+        }
         Ok(())
     }
 
+    /// Chunks whose vectors come from another model than the configured one.
+    /// Chunks indexed before the model was recorded are assumed to match.
+    pub async fn stale_vector_chunks(&self) -> usize {
+        let model = self.config.read().await.embedding_model.clone();
+        self.documents
+            .read()
+            .await
+            .values()
+            .filter(|doc| doc.embedding_model.as_deref().is_some_and(|m| m != model))
+            .count()
+    }
+
+    /// Re-embed every chunk with the configured model, refitting any
+    /// dimension reduction, e.g. for chunks left stale by another model
+    pub async fn rebuild_index(
+        &self,
+        on_progress: &(dyn Fn(EmbeddingProgress) + Send + Sync),
+    ) -> Result<IndexRebuildReport> {
+        let config = self.config.read().await.clone();
+        let report = self
+            .reembed_index(
+                config.dimension_reduction,
+                config.normalize_embeddings,
+                on_progress,
+            )
+            .await?;
+        let embedding_model = config.embedding_model;
+        tracing::info!(
+            model = %embedding_model,
+            chunks = report.chunks_re_embedded,
+            "🔁 RAG index rebuilt"
+        );
+        Ok(IndexRebuildReport {
+            embedding_model,
+            chunks_re_embedded: report.chunks_re_embedded,
+            embedding_dimension: report.stored_dimension,
+        })
+    }
+
     pub async fn get_active_model(&self) -> String {
         self.config.read().await.embedding_model.clone()
     }
@@ -195,179 +975,785 @@ impl RAGEngine {
     }
 
     pub async fn update_config(&self, new_config: RAGConfig) -> Result<()> {
+        if new_config.chunk_size == 0 || new_config.chunk_overlap >= new_config.chunk_size {
+            return Err(anyhow!(
+                "chunk_overlap ({}) must be smaller than a non-zero chunk_size ({})",
+                new_config.chunk_overlap,
+                new_config.chunk_size
+            ));
+        }
+        let current = self.config.read().await.clone();
+        let reduction_changed = new_config.dimension_reduction != current.dimension_reduction
+            || new_config.normalize_embeddings != current.normalize_embeddings;
+        if reduction_changed {
+            if !self.documents.read().await.is_empty() {
+                return Err(anyhow!(
+                    "Changing embedding dimension reduction requires re-embedding the index; \
+                     use set_dimension_reduction"
+                ));
+            }
+            *self.projection.write().await = None;
+        }
         *self.config.write().await = new_config;
         *self.embeddings_model.write().await = None;
         Ok(())
     }
 
-    pub async fn add_document(&self, content: &str, metadata: JsonValue) -> Result<String> {
-        self.ensure_embeddings_model().await?;
-        let doc_id = Uuid::new_v4().to_string();
+    /// Parent documents with chunks cut by other settings than `config`'s
+    /// `chunk_size`/`chunk_overlap`/strategy, sorted
+    pub async fn stale_chunking_documents(&self, config: &RAGConfig) -> Vec<String> {
+        Self::stale_chunking(&*self.documents.read().await, config)
+    }
 
-        let chunks = self.chunk_text(content).await;
-        let total_chunks = chunks.len();
+    fn stale_chunking(docs: &HashMap<String, Document>, config: &RAGConfig) -> Vec<String> {
+        let stale: HashSet<String> = docs
+            .values()
+            .filter(|doc| !Self::chunked_with(doc, config))
+            .map(|doc| Self::parent_document_id(&doc.id))
+            .collect();
+        let mut stale: Vec<String> = stale.into_iter().collect();
+        stale.sort();
+        stale
+    }
 
-        let mut model_lock = self.embeddings_model.write().await;
-        let model = model_lock.as_mut().ok_or_else(|| anyhow!("Model not initialized"))?;
+    /// Whether `doc` was cut with `config`'s chunking settings
+    fn chunked_with(doc: &Document, config: &RAGConfig) -> bool {
+        doc.chunk_size == Some(config.chunk_size)
+            && doc.chunk_overlap == Some(config.chunk_overlap)
+            && doc.chunking_strategy.unwrap_or_default() == config.chunking_strategy
+    }
 
-        let mut documents = self.documents.write().await;
-        let mut inverted_index = self.inverted_index.write().await;
+    /// Re-chunk and re-embed every document whose chunking differs from the
+    /// configured settings, calling `on_progress` after each one. The text is
+    /// taken from the stored version when there is one, otherwise it is
+    /// reassembled from the chunks.
+    ///
+    /// The new chunks are built aside while searches keep using the old ones,
+    /// then swapped in together. A document updated or deleted meanwhile keeps
+    /// what that change stored.
+    pub async fn rechunk_documents<F>(&self, on_progress: F) -> Result<RechunkReport>
+    where
+        F: Fn(RechunkProgress) + Send + Sync,
+    {
+        let config = self.config.read().await.clone();
+        let stale = self.stale_chunking_documents(&config).await;
+        let mut report = RechunkReport {
+            documents: 0,
+            chunks_before: 0,
+            chunks_after: 0,
+        };
 
-        for (idx, chunk) in chunks.iter().enumerate() {
-            let embeddings = model.embed(vec![chunk.as_str()], None)?
-                .into_iter()
-                .next()
-                .ok_or_else(|| anyhow!("Failed to embed text"))?;
-
-            let chunk_id = format!("{}_{}", doc_id, idx);
-            documents.insert(
-                chunk_id.clone(),
-                Document {
-                    id: chunk_id.clone(),
-                    content: chunk.clone(),
-                    embeddings,
-                    metadata: metadata.clone(),
-                    timestamp: chrono::Utc::now().timestamp(),
-                    chunk_index: idx,
-                    total_chunks,
-                },
-            );
-            self.update_inverted_index(&chunk_id, chunk, &mut inverted_index);
+        let mut rebuilt = Vec::with_capacity(stale.len());
+        for (processed, doc_id) in stale.iter().enumerate() {
+            let source = self
+                .versions
+                .read()
+                .await
+                .get(doc_id)
+                .map(|version| version.content.clone());
+            let (old_ids, text, metadata, ingest_source) = {
+                let docs = self.documents.read().await;
+                let mut chunks: Vec<&Document> = docs
+                    .values()
+                    .filter(|doc| Self::parent_document_id(&doc.id) == *doc_id)
+                    .collect();
+                chunks.sort_by_key(|doc| doc.chunk_index);
+                let text = source.unwrap_or_else(|| {
+                    reassemble_chunks(chunks.iter().map(|doc| doc.content.as_str()))
+                });
+                let metadata = chunks
+                    .first()
+                    .map(|doc| doc.metadata.clone())
+                    .unwrap_or(JsonValue::Null);
+                let ingest_source = chunks.first().and_then(|doc| doc.source.clone());
+                let old_ids: Vec<String> = chunks.iter().map(|doc| doc.id.clone()).collect();
+                (old_ids, text, metadata, ingest_source)
+            };
+
+            let chunks = self
+                .build_chunks(doc_id, &text, &metadata, ingest_source)
+                .await?;
+            rebuilt.push((doc_id, old_ids, chunks));
+            on_progress(RechunkProgress {
+                processed: processed + 1,
+                total: stale.len(),
+                document_id: doc_id.clone(),
+            });
         }
 
+        let mut docs = self.documents.write().await;
+        // A projection fitted partway through also covers the chunks built before it
+        if let Some(projection) = self.projection.read().await.as_ref() {
+            let reduces = projection.output_dimension() != projection.source_dimension;
+            for chunk in rebuilt
+                .iter_mut()
+                .flat_map(|(_, _, chunks)| chunks.iter_mut())
+            {
+                if reduces && chunk.embeddings.len() == projection.source_dimension {
+                    chunk.embeddings = projection.project(&chunk.embeddings)?;
+                }
+            }
+        }
+        let mut index = self.inverted_index.write().await;
+        let mut term_counts = self.chunk_term_counts.write().await;
+        for (doc_id, old_ids, chunks) in rebuilt {
+            // Chunks re-indexed or removed since they were read are left as they are
+            let current = docs
+                .keys()
+                .filter(|id| Self::parent_document_id(id) == *doc_id)
+                .count();
+            let unchanged = current == old_ids.len()
+                && old_ids.iter().all(|id| {
+                    docs.get(id)
+                        .is_some_and(|doc| !Self::chunked_with(doc, &config))
+                });
+            if !unchanged {
+                continue;
+            }
+            report.documents += 1;
+            report.chunks_before += old_ids.len();
+            report.chunks_after += chunks.len();
+            self.replace_chunks(doc_id, chunks, &mut docs, &mut index, &mut term_counts);
+        }
+        drop(docs);
+        drop(index);
+        drop(term_counts);
         self.save_index().await?;
-        Ok(doc_id)
-    }
 
-    pub async fn search(&self, query: &str, limit: Option<usize>) -> Result<Vec<SearchResult>> {
-        self.ensure_embeddings_model().await?;
+        tracing::info!(
+            documents = report.documents,
+            chunks_before = report.chunks_before,
+            chunks_after = report.chunks_after,
+            "✂️ Knowledge base re-chunked"
+        );
+        Ok(report)
+    }
 
+    /// Bring freshly embedded vectors into the space of the stored index. With
+    /// `fit`, a missing projection is fitted first: PCA on the stored corpus
+    /// plus `vectors`, which are all still at full dimension, once there are
+    /// enough of them; other reductions on `vectors` alone. Until a projection
+    /// exists vectors are returned as embedded.
+    async fn to_index_space(&self, vectors: Vec<Vec<f32>>, fit: bool) -> Result<Vec<Vec<f32>>> {
         let config = self.config.read().await.clone();
-        let limit = limit.unwrap_or(config.max_results);
-
-        let mut model_lock = self.embeddings_model.write().await;
-        let model = model_lock.as_mut().ok_or_else(|| anyhow!("Model not initialized"))?;
-
-        let query_embedding = model
-            .embed(vec![query], None)?
-            .into_iter()
-            .next()
-            .ok_or_else(|| anyhow!("Failed to embed query"))?;
+        if !config.transforms_embeddings() || vectors.is_empty() {
+            return Ok(vectors);
+        }
 
-        let mut results = if config.enable_hybrid_search {
-            self.hybrid_search(query, &query_embedding, limit).await?
+        // Taken before the projection, in the same order as `reembed_index`
+        let fits_on_corpus = matches!(config.dimension_reduction, DimensionReduction::Pca { .. });
+        let mut docs = if fit && fits_on_corpus {
+            Some(self.documents.write().await)
         } else {
-            self.vector_search(&query_embedding, limit).await?
+            None
         };
 
-        if config.enable_reranking && !results.is_empty() {
-            results = self.rerank_results(query, results).await?;
+        let mut projection = self.projection.write().await;
+        if projection.is_none() {
+            if !fit {
+                return Ok(vectors);
+            }
+            let source_dimension = vectors[0].len();
+            let corpus: Vec<&Vec<f32>> = docs
+                .iter()
+                .flat_map(|docs| docs.values())
+                .map(|doc| &doc.embeddings)
+                .filter(|embeddings| embeddings.len() == source_dimension)
+                .chain(&vectors)
+                .collect();
+            if !EmbeddingProjection::can_fit(&config.dimension_reduction, corpus.len()) {
+                tracing::debug!(
+                    vectors = corpus.len(),
+                    "Too few chunks to fit the projection yet; storing full embeddings"
+                );
+                return Ok(vectors);
+            }
+            let samples: Vec<Vec<f32>> = sample_evenly(&corpus, PCA_MAX_FIT_SAMPLES)
+                .into_iter()
+                .map(|v| (*v).clone())
+                .collect();
+            let fitted = EmbeddingProjection::fit(
+                &config.dimension_reduction,
+                config.normalize_embeddings,
+                &samples,
+            )?;
+
+            // Chunks stored before the fit move into the projected space too
+            if let Some(docs) = docs.as_mut() {
+                for doc in docs.values_mut() {
+                    if doc.embeddings.len() == source_dimension {
+                        doc.embeddings = fitted.project(&doc.embeddings)?;
+                    }
+                }
+            }
+            *projection = Some(fitted);
         }
+        let projection = projection.as_ref().expect("projection fitted above");
+        vectors.iter().map(|v| projection.project(v)).collect()
+    }
 
-        Ok(results)
+    /// Reduce (or stop reducing) stored embeddings. Every chunk is re-embedded
+    /// at full dimension and, for PCA, the projection is fitted on the result.
+    pub async fn set_dimension_reduction(
+        &self,
+        reduction: DimensionReduction,
+        normalize: bool,
+    ) -> Result<DimensionReductionReport> {
+        self.reembed_index(reduction, normalize, &|_| {}).await
     }
 
-    async fn vector_search(&self, query_embedding: &[f32], limit: usize) -> Result<Vec<SearchResult>> {
-        let documents = self.documents.read().await;
-        let config = self.config.read().await;
-        let mut scores: Vec<(String, f32, Document)> = Vec::new();
+    async fn reembed_index(
+        &self,
+        reduction: DimensionReduction,
+        normalize: bool,
+        on_progress: &(dyn Fn(EmbeddingProgress) + Send + Sync),
+    ) -> Result<DimensionReductionReport> {
+        // Held throughout so no chunk is added in the old space meanwhile
+        let mut docs = self.documents.write().await;
+        let ids: Vec<String> = docs.keys().cloned().collect();
+        let contents: Vec<String> = ids.iter().map(|id| docs[id].content.clone()).collect();
+        let full = self.embed_chunks_reporting(&contents, on_progress).await?;
+        let source_dimension = full.first().map(Vec::len);
 
-        for (id, doc) in documents.iter() {
-            let similarity = cosine_similarity(query_embedding, &doc.embeddings);
-            if similarity >= config.similarity_threshold {
-                scores.push((id.clone(), similarity, doc.clone()));
+        let projection = if reduction == DimensionReduction::None && !normalize {
+            None
+        } else if !EmbeddingProjection::can_fit(&reduction, full.len()) {
+            // Fitted once enough chunks are indexed
+            None
+        } else {
+            Some(EmbeddingProjection::fit(&reduction, normalize, &full)?)
+        };
+        let stored = match &projection {
+            Some(projection) => full
+                .iter()
+                .map(|v| projection.project(v))
+                .collect::<Result<Vec<_>>>()?,
+            None => full,
+        };
+        let stored_dimension = stored.first().map(Vec::len);
+
+        let embedding_model = self.config.read().await.embedding_model.clone();
+        for (id, embeddings) in ids.iter().zip(stored) {
+            if let Some(doc) = docs.get_mut(id) {
+                doc.embeddings = embeddings;
+                doc.embedding_model = Some(embedding_model.clone());
             }
         }
+        {
+            let mut config = self.config.write().await;
+            config.dimension_reduction = reduction.clone();
+            config.normalize_embeddings = normalize;
+        }
+        *self.projection.write().await = projection;
+        drop(docs);
+        self.save_index().await?;
 
-        scores.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
-        scores.truncate(limit);
+        let f32_bytes = std::mem::size_of::<f32>();
+        let full_embedding_bytes = ids.len() * source_dimension.unwrap_or(0) * f32_bytes;
+        let stored_embedding_bytes = ids.len() * stored_dimension.unwrap_or(0) * f32_bytes;
+        tracing::info!(
+            reduction = ?reduction,
+            chunks = ids.len(),
+            bytes_saved = full_embedding_bytes - stored_embedding_bytes,
+            "📉 Embedding dimension reduction applied"
+        );
+
+        Ok(DimensionReductionReport {
+            reduction,
+            normalize,
+            source_dimension,
+            stored_dimension,
+            chunks_re_embedded: ids.len(),
+            full_embedding_bytes,
+            stored_embedding_bytes,
+            bytes_saved: full_embedding_bytes - stored_embedding_bytes,
+        })
+    }
+
+    pub async fn add_document(&self, content: &str, metadata: JsonValue) -> Result<String> {
+        let doc_id = Uuid::new_v4().to_string();
+        self.index_document(&doc_id, content, metadata, None)
+            .await?;
+        Ok(doc_id)
+    }
+
+    /// Index a document extracted from `source`, recording the source's
+    /// provenance with every chunk
+    pub async fn add_document_from_source(
+        &self,
+        content: &str,
+        metadata: JsonValue,
+        source: IngestSource,
+    ) -> Result<String> {
+        let doc_id = Uuid::new_v4().to_string();
+        self.index_document(&doc_id, content, metadata, Some(source))
+            .await?;
+        Ok(doc_id)
+    }
+
+    /// Replace the content and metadata of an indexed document, re-chunking and
+    /// re-embedding it under the same id. Returns the new number of chunks.
+    ///
+    /// The stored owner always carries over, so an update can neither orphan a
+    /// document from its user's erasure nor hand it to another user; the stored
+    /// trust weight carries over unless `metadata` sets a new one. The chunks
+    /// keep the source the document was ingested from.
+    pub async fn update_document(
+        &self,
+        doc_id: &str,
+        content: &str,
+        metadata: JsonValue,
+    ) -> Result<usize> {
+        let (stored, source) = self
+            .documents
+            .read()
+            .await
+            .get(&format!("{}_0", doc_id))
+            .map(|doc| (doc.metadata.clone(), doc.source.clone()))
+            .ok_or_else(|| anyhow!("Document {} is not in the index", doc_id))?;
+
+        let mut merged = match metadata {
+            JsonValue::Object(fields) => fields,
+            _ => serde_json::Map::new(),
+        };
+        match stored.get(OWNER_KEY) {
+            Some(owner) => merged.insert(OWNER_KEY.to_string(), owner.clone()),
+            None => merged.remove(OWNER_KEY),
+        };
+        if let Some(weight) = stored.get(TRUST_WEIGHT_KEY) {
+            merged
+                .entry(TRUST_WEIGHT_KEY)
+                .or_insert_with(|| weight.clone());
+        }
+        self.index_document(doc_id, content, JsonValue::Object(merged), source)
+            .await
+    }
+
+    /// Chunk, embed and store `content` as `doc_id`, replacing any chunks the
+    /// document already has. Embedding runs first, so a failure leaves them in place.
+    async fn index_document(
+        &self,
+        doc_id: &str,
+        content: &str,
+        metadata: JsonValue,
+        source: Option<IngestSource>,
+    ) -> Result<usize> {
+        let chunks = self
+            .build_chunks(doc_id, content, &metadata, source)
+            .await?;
+        let total_chunks = chunks.len();
+
+        let mut docs = self.documents.write().await;
+        let mut index = self.inverted_index.write().await;
+        let mut term_counts = self.chunk_term_counts.write().await;
+        self.replace_chunks(doc_id, chunks, &mut docs, &mut index, &mut term_counts);
+        drop(docs);
+        drop(index);
+        drop(term_counts);
+
+        let mut versions = self.versions.write().await;
+        versions.remove(doc_id);
+        if let Some((filename, matter)) = version_key(&metadata) {
+            versions.insert(
+                doc_id.to_string(),
+                DocumentVersion {
+                    filename,
+                    matter,
+                    content_hash: content_hash(content),
+                    content: content.to_string(),
+                    indexed_at: chrono::Utc::now().timestamp(),
+                },
+            );
+        }
+        drop(versions);
+
+        self.save_index().await?;
+        Ok(total_chunks)
+    }
+
+    /// Compare `content` with the latest indexed version sharing its filename
+    /// and matter. None when there is no prior version. The prior version
+    /// stays indexed; remove it with `delete_document` to replace it.
+    pub async fn compare_with_prior_version(
+        &self,
+        content: &str,
+        metadata: &JsonValue,
+    ) -> Result<Option<DocumentChangeReport>> {
+        let Some((filename, matter)) = version_key(metadata) else {
+            return Ok(None);
+        };
+        let prior = self
+            .versions
+            .read()
+            .await
+            .iter()
+            .filter(|(_, v)| v.filename == filename && v.matter == matter)
+            .max_by_key(|(_, v)| v.indexed_at)
+            .map(|(id, v)| (id.clone(), v.clone()));
+        let Some((previous_document_id, previous)) = prior else {
+            return Ok(None);
+        };
+
+        let current_hash = content_hash(content);
+        let mut report = DocumentChangeReport {
+            previous_document_id,
+            filename,
+            matter,
+            content_changed: current_hash != previous.content_hash,
+            previous_hash: previous.content_hash,
+            current_hash,
+            semantic_similarity: 1.0,
+            added: 0,
+            removed: 0,
+            modified: 0,
+            changes: Vec::new(),
+        };
+        if !report.content_changed {
+            return Ok(Some(report));
+        }
+
+        let previous_sentences = split_sentences(&previous.content);
+        let current_sentences = split_sentences(content);
+        let hunks = diff_sentences(&previous_sentences, &current_sentences);
+
+        // Embed both versions whole plus every changed sentence in one pass
+        let mut texts = vec![previous.content.clone(), content.to_string()];
+        for hunk in &hunks {
+            let changed = hunk.removed.iter().chain(&hunk.added);
+            texts.extend(changed.map(|s| s.to_string()));
+        }
+        let embeddings = self.embed_chunks(&texts).await?;
+        report.semantic_similarity = cosine_similarity(&embeddings[0], &embeddings[1]);
+        let sentence_embeddings: HashMap<&str, &Vec<f32>> = texts[2..]
+            .iter()
+            .map(String::as_str)
+            .zip(&embeddings[2..])
+            .collect();
+        let similarity =
+            |a: &str, b: &str| match (sentence_embeddings.get(a), sentence_embeddings.get(b)) {
+                (Some(a), Some(b)) => cosine_similarity(a, b),
+                _ => 0.0,
+            };
+
+        for hunk in &hunks {
+            let changes = classify_hunk(hunk, similarity, MODIFIED_SENTENCE_SIMILARITY);
+            report.changes.extend(changes);
+        }
+        for change in &report.changes {
+            match change.kind {
+                ChangeKind::Added => report.added += 1,
+                ChangeKind::Removed => report.removed += 1,
+                ChangeKind::Modified => report.modified += 1,
+            }
+        }
+        Ok(Some(report))
+    }
+
+    pub async fn search(&self, query: &str, limit: Option<usize>) -> Result<Vec<SearchResult>> {
+        self.search_filtered(query, limit, None).await
+    }
+
+    /// Search only chunks whose metadata passes `filter`. Chunks are filtered
+    /// before ranking, so `limit` counts matching chunks.
+    pub async fn search_filtered(
+        &self,
+        query: &str,
+        limit: Option<usize>,
+        filter: Option<&MetadataFilter>,
+    ) -> Result<Vec<SearchResult>> {
+        let config = self.config.read().await.clone();
+        let limit = limit.unwrap_or(config.max_results);
+
+        let mut results = if config.enable_hybrid_search {
+            self.search_hybrid(query, limit, config.hybrid_alpha, filter)
+                .await?
+        } else {
+            let query_embedding = self.query_embedding(query).await?;
+            self.vector_search(&query_embedding, limit, filter).await?
+        };
+
+        if config.enable_reranking && !results.is_empty() {
+            results = self.rerank_results(query, results).await?;
+        }
+
+        if config.merge_overlapping_citations {
+            results = merge_overlapping_results(results);
+        }
+
+        Ok(results)
+    }
+
+    /// Search with query expansion when `expand_query` is configured: the query
+    /// and its paraphrases are retrieved for concurrently and the rankings
+    /// fused. Paraphrases come from `paraphraser`, or from templates without
+    /// one or when it fails; otherwise this is `search_filtered`.
+    pub async fn search_expanded(
+        &self,
+        query: &str,
+        limit: Option<usize>,
+        paraphraser: Option<&dyn CompletionModel>,
+        filter: Option<&MetadataFilter>,
+    ) -> Result<Vec<SearchResult>> {
+        let paraphrases = self.expansion_paraphrases(query, paraphraser).await;
+        self.search_paraphrased(query, &paraphrases, limit, filter)
+            .await
+    }
+
+    /// Paraphrases `search_expanded` would retrieve for; empty when query
+    /// expansion is off. Callers holding a lock on the model only need it
+    /// for this step.
+    pub async fn expansion_paraphrases(
+        &self,
+        query: &str,
+        paraphraser: Option<&dyn CompletionModel>,
+    ) -> Vec<String> {
+        let config = self.config.read().await.clone();
+        if !config.expand_query || config.query_paraphrases == 0 {
+            return Vec::new();
+        }
+
+        let count = config.query_paraphrases;
+        match paraphraser {
+            Some(paraphraser) => match model_paraphrases(paraphraser, query, count).await {
+                Ok(paraphrases) if !paraphrases.is_empty() => paraphrases,
+                Ok(_) => template_paraphrases(query, count),
+                Err(e) => {
+                    tracing::warn!(error = %e, "Query paraphrasing failed; using templates");
+                    template_paraphrases(query, count)
+                }
+            },
+            None => template_paraphrases(query, count),
+        }
+    }
+
+    /// Retrieve for `query` and each of `paraphrases` concurrently and fuse the
+    /// rankings; without paraphrases this is `search_filtered`
+    pub async fn search_paraphrased(
+        &self,
+        query: &str,
+        paraphrases: &[String],
+        limit: Option<usize>,
+        filter: Option<&MetadataFilter>,
+    ) -> Result<Vec<SearchResult>> {
+        let limit = limit.unwrap_or(self.config.read().await.max_results);
+        if paraphrases.is_empty() {
+            return self.search_filtered(query, Some(limit), filter).await;
+        }
+
+        let queries: Vec<&str> = std::iter::once(query)
+            .chain(paraphrases.iter().map(String::as_str))
+            .collect();
+        let rankings = futures::future::try_join_all(
+            queries
+                .iter()
+                .map(|q| self.search_filtered(q, Some(limit), filter)),
+        )
+        .await?;
+        tracing::debug!(queries = queries.len(), "🔀 Expanded query retrieval");
+        Ok(reciprocal_rank_fusion(rankings, limit))
+    }
+
+    /// Embedding of a query in the space of the stored index
+    async fn query_embedding(&self, query: &str) -> Result<Vec<f32>> {
+        Ok(self
+            .to_index_space(vec![self.embed_text(query).await?], false)
+            .await?
+            .remove(0))
+    }
+
+    async fn vector_search(
+        &self,
+        query_embedding: &[f32],
+        limit: usize,
+        filter: Option<&MetadataFilter>,
+    ) -> Result<Vec<SearchResult>> {
+        let documents = self.documents.read().await;
+        let config = self.config.read().await;
+        let mut scores: Vec<(String, f32, f32, Document)> = Vec::new();
+
+        for (id, doc) in documents.iter() {
+            if filter.is_some_and(|f| !f.matches(id, &doc.metadata)) {
+                continue;
+            }
+            // Vectors from another model are not comparable with the query's
+            if doc
+                .embedding_model
+                .as_deref()
+                .is_some_and(|m| m != config.embedding_model)
+            {
+                continue;
+            }
+            // Trust-weighted before the threshold and the cut-off, so a trusted
+            // source is not dropped in favour of a closer match it would outrank
+            let trust_weight = trust_weight_of(&doc.metadata);
+            let score = cosine_similarity(query_embedding, &doc.embeddings) * trust_weight;
+            if score >= config.similarity_threshold {
+                scores.push((id.clone(), score, trust_weight, doc.clone()));
+            }
+        }
+
+        scores.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        scores.truncate(limit);
 
         Ok(scores
             .into_iter()
-            .map(|(id, score, doc)| SearchResult {
+            .map(|(id, score, trust_weight, doc)| SearchResult {
+                provenance: Some(ChunkProvenance::of(&doc)),
+                fused_score: None,
                 document_id: id,
                 content: doc.content,
                 score,
                 metadata: doc.metadata,
                 highlight: None,
                 reasoning: None,
+                chunk_range: Some((doc.chunk_index, doc.chunk_index)),
+                trust_weight,
             })
             .collect())
     }
 
-    async fn hybrid_search(
+    /// Rank chunks by `alpha` times their cosine similarity to the query plus
+    /// `1 - alpha` times their BM25 keyword score, so exact terms such as case
+    /// numbers and statute references count alongside meaning. BM25 scores are
+    /// divided by the best one and similarities clamped to [0, 1], putting both
+    /// on the same scale. The combined score is scaled by the chunk's source trust
+    /// weight. Each chunk appears once, with its best combined score. Only chunks
+    /// passing `filter` are ranked.
+    pub async fn search_hybrid(
         &self,
         query: &str,
-        query_embedding: &[f32],
         limit: usize,
+        alpha: f32,
+        filter: Option<&MetadataFilter>,
     ) -> Result<Vec<SearchResult>> {
-        let vector_results = self.vector_search(query_embedding, limit * 2).await?;
-        let keyword_results = self.keyword_search(query, limit * 2).await?;
-
-        let mut merged: HashMap<String, (f32, SearchResult)> = HashMap::new();
-        for result in vector_results {
-            merged.insert(result.document_id.clone(), (result.score * 0.7, result));
-        }
-        for result in keyword_results {
-            merged
-                .entry(result.document_id.clone())
-                .and_modify(|e| e.0 += result.score * 0.3)
-                .or_insert((result.score * 0.3, result));
+        let alpha = alpha.clamp(0.0, 1.0);
+        let query_embedding = self.query_embedding(query).await?;
+        let terms = query_terms(query);
+        let keyword_scores = self.keyword_scores(&terms).await;
+        let vector_results = self
+            .vector_search(&query_embedding, limit * 2, filter)
+            .await?;
+        let keyword_results = self
+            .keyword_results(&terms, &keyword_scores, limit * 2, filter)
+            .await;
+
+        // A chunk found by only one method still gets its score from the other
+        let documents = self.documents.read().await;
+        let mut merged: HashMap<String, SearchResult> = HashMap::new();
+        for mut result in vector_results.into_iter().chain(keyword_results) {
+            let Some(doc) = documents.get(&result.document_id) else {
+                continue;
+            };
+            let vector_score = cosine_similarity(&query_embedding, &doc.embeddings).clamp(0.0, 1.0);
+            let keyword_score = keyword_scores
+                .get(&result.document_id)
+                .copied()
+                .unwrap_or(0.0);
+            result.score = (alpha * vector_score + (1.0 - alpha) * keyword_score)
+                * trust_weight_of(&doc.metadata);
+
+            match merged.get_mut(&result.document_id) {
+                Some(kept) => {
+                    kept.score = kept.score.max(result.score);
+                    if kept.highlight.is_none() {
+                        kept.highlight = result.highlight;
+                    }
+                }
+                None => {
+                    merged.insert(result.document_id.clone(), result);
+                }
+            }
         }
 
-        let mut results: Vec<SearchResult> = merged
-            .into_iter()
-            .map(|(_, (score, mut r))| {
-                r.score = score;
-                r
-            })
-            .collect();
-
-        results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+        let mut results: Vec<SearchResult> = merged.into_values().collect();
+        results.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
         results.truncate(limit);
         Ok(results)
     }
 
-    async fn keyword_search(&self, query: &str, limit: usize) -> Result<Vec<SearchResult>> {
-        let docs = self.documents.read().await;
+    /// BM25 score of every chunk containing one of `terms`, divided by the best
+    /// score so they lie in (0, 1]
+    async fn keyword_scores(&self, terms: &[String]) -> HashMap<String, f32> {
         let index = self.inverted_index.read().await;
-
-        let mut scores = HashMap::new();
-        let tokens: Vec<String> = query
-            .to_lowercase()
-            .split_whitespace()
-            .filter(|t| t.len() > 2)
-            .map(|s| s.to_string())
-            .collect();
-
-        for token in &tokens {
-            if let Some(ids) = index.get(token) {
-                for id in ids {
-                    *scores.entry(id.clone()).or_insert(0.0) += 1.0;
-                }
+        let term_counts = self.chunk_term_counts.read().await;
+        let chunk_count = term_counts.len().max(1) as f32;
+        let average_terms = (term_counts.values().sum::<usize>() as f32 / chunk_count).max(1.0);
+
+        let mut scores: HashMap<String, f32> = HashMap::new();
+        for term in terms {
+            let Some(ids) = index.get(term) else {
+                continue;
+            };
+            let mut frequencies: HashMap<&str, f32> = HashMap::new();
+            for id in ids {
+                *frequencies.entry(id.as_str()).or_default() += 1.0;
+            }
+            let containing = frequencies.len() as f32;
+            let idf = ((chunk_count - containing + 0.5) / (containing + 0.5) + 1.0).ln();
+            for (id, frequency) in frequencies {
+                let terms_in_chunk = term_counts.get(id).copied().unwrap_or_default() as f32;
+                let length_norm = 1.0 - BM25_B + BM25_B * terms_in_chunk / average_terms;
+                *scores.entry(id.to_string()).or_default() +=
+                    idf * frequency * (BM25_K1 + 1.0) / (frequency + BM25_K1 * length_norm);
             }
         }
 
         let max_score = scores.values().cloned().fold(0.0, f32::max);
-        let mut results = Vec::new();
         if max_score > 0.0 {
-            for (id, score) in scores {
-                if let Some(doc) = docs.get(&id) {
-                    results.push(SearchResult {
-                        document_id: id,
-                        content: doc.content.clone(),
-                        score: score / max_score,
-                        metadata: doc.metadata.clone(),
-                        highlight: self.generate_highlight(&doc.content, &tokens),
-                        reasoning: None,
-                    });
-                }
-            }
+            scores.values_mut().for_each(|score| *score /= max_score);
         }
+        scores
+    }
 
-        results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
-        results.truncate(limit);
-        Ok(results)
+    /// The `limit` best chunks of `scores` passing `filter` as search results,
+    /// ranked by score times source trust weight
+    async fn keyword_results(
+        &self,
+        terms: &[String],
+        scores: &HashMap<String, f32>,
+        limit: usize,
+        filter: Option<&MetadataFilter>,
+    ) -> Vec<SearchResult> {
+        let docs = self.documents.read().await;
+        let mut ranked: Vec<(&String, f32)> = scores
+            .iter()
+            .filter(|(_, score)| **score > 0.0)
+            .filter(|(id, _)| match (filter, docs.get(*id)) {
+                (Some(filter), Some(doc)) => filter.matches(id, &doc.metadata),
+                _ => true,
+            })
+            .map(|(id, score)| {
+                let weight = docs
+                    .get(id)
+                    .map_or(1.0, |doc| trust_weight_of(&doc.metadata));
+                (id, *score * weight)
+            })
+            .collect();
+        ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        ranked.truncate(limit);
+
+        ranked
+            .into_iter()
+            .filter_map(|(id, score)| {
+                let doc = docs.get(id)?;
+                Some(SearchResult {
+                    document_id: id.clone(),
+                    content: doc.content.clone(),
+                    score,
+                    metadata: doc.metadata.clone(),
+                    highlight: self.generate_highlight(&doc.content, terms),
+                    reasoning: None,
+                    chunk_range: Some((doc.chunk_index, doc.chunk_index)),
+                    trust_weight: trust_weight_of(&doc.metadata),
+                    provenance: Some(ChunkProvenance::of(doc)),
+                    fused_score: None,
+                })
+            })
+            .collect()
     }
 
     async fn rerank_results(&self, query: &str, mut results: Vec<SearchResult>) -> Result<Vec<SearchResult>> {
@@ -458,6 +1844,56 @@ impl RAGEngine {
 
     async fn chunk_text(&self, text: &str) -> Vec<String> {
         let cfg = self.config.read().await;
+        let unspaced = cfg.language_aware_chunking && detect_script(text) == ScriptClass::Unspaced;
+        let token_counter = self.token_counter.read().await.clone();
+        if unspaced || cfg.chunking_strategy == ChunkingStrategy::TokenAware {
+            return chunk_by_tokens(
+                text,
+                token_counter.as_ref(),
+                cfg.chunk_size,
+                cfg.chunk_overlap,
+            );
+        }
+
+        match cfg.chunking_strategy {
+            ChunkingStrategy::Sentence => {
+                let sentences = split_sentences(text);
+                return chunk_by_units(
+                    &sentences,
+                    &WordCounter,
+                    cfg.chunk_size,
+                    cfg.chunk_overlap_units,
+                    " ",
+                );
+            }
+            ChunkingStrategy::Paragraph => {
+                // Paragraphs over the budget are cut by sentences first
+                let mut units: Vec<String> = Vec::new();
+                for paragraph in split_paragraphs(text) {
+                    if WordCounter.count_tokens(paragraph) > cfg.chunk_size {
+                        units.extend(chunk_by_units(
+                            &split_sentences(paragraph),
+                            &WordCounter,
+                            cfg.chunk_size,
+                            cfg.chunk_overlap_units,
+                            " ",
+                        ));
+                    } else {
+                        units.push(paragraph.to_string());
+                    }
+                }
+                let units: Vec<&str> = units.iter().map(String::as_str).collect();
+                return chunk_by_units(
+                    &units,
+                    &WordCounter,
+                    cfg.chunk_size,
+                    cfg.chunk_overlap_units,
+                    "\n\n",
+                );
+            }
+            ChunkingStrategy::FixedChar | ChunkingStrategy::TokenAware => {}
+        }
+
         let words: Vec<&str> = text.split_whitespace().collect();
         let mut chunks = Vec::new();
         if words.is_empty() {
@@ -481,14 +1917,14 @@ impl RAGEngine {
         doc_id: &str,
         text: &str,
         index: &mut HashMap<String, Vec<String>>,
+        term_counts: &mut HashMap<String, usize>,
     ) {
-        for token in text
-            .to_lowercase()
-            .split_whitespace()
-            .filter(|t| t.len() > 2)
-        {
-            index.entry(token.to_string()).or_default().push(doc_id.to_string());
+        let mut count = 0;
+        for token in index_terms(text) {
+            index.entry(token).or_default().push(doc_id.to_string());
+            count += 1;
         }
+        term_counts.insert(doc_id.to_string(), count);
     }
 
     async fn save_index(&self) -> Result<()> {
@@ -507,14 +1943,74 @@ impl RAGEngine {
         )
         .await?;
 
+        tokio::fs::write(
+            self.index_path.join("versions.json"),
+            serde_json::to_string(&*self.versions.read().await)?,
+        )
+        .await?;
+
+        let projection_file = self.index_path.join("projection.json");
+        match &*self.projection.read().await {
+            Some(projection) => {
+                tokio::fs::write(&projection_file, serde_json::to_string(projection)?).await?
+            }
+            None if projection_file.exists() => tokio::fs::remove_file(&projection_file).await?,
+            None => {}
+        }
+
+        drop(docs);
+        drop(index);
+        self.save_manifest().await
+    }
+
+    async fn save_manifest(&self) -> Result<()> {
+        let manifest = IndexManifest {
+            version: INDEX_FORMAT_VERSION,
+            embedding_model: self.config.read().await.embedding_model.clone(),
+        };
+        tokio::fs::create_dir_all(&self.index_path).await?;
+        tokio::fs::write(
+            self.index_path.join("index.json"),
+            serde_json::to_string(&manifest)?,
+        )
+        .await?;
         Ok(())
     }
 
     async fn load_index(&self) -> Result<()> {
+        // Indexes written before the manifest existed are read as version 1
+        let manifest_file = self.index_path.join("index.json");
+        if manifest_file.exists() {
+            let data = tokio::fs::read_to_string(&manifest_file).await?;
+            let manifest: IndexManifest = serde_json::from_str(&data)?;
+            if manifest.version > INDEX_FORMAT_VERSION {
+                return Err(anyhow!(
+                    "RAG index in {} has format version {}; this version reads up to {}",
+                    self.index_path.display(),
+                    manifest.version,
+                    INDEX_FORMAT_VERSION
+                ));
+            }
+            self.config.write().await.embedding_model = manifest.embedding_model;
+        }
+
         let index_file = self.index_path.join("documents.json");
         if index_file.exists() {
             let data = tokio::fs::read_to_string(&index_file).await?;
-            let loaded_docs: HashMap<String, Document> = serde_json::from_str(&data)?;
+            let mut loaded_docs: HashMap<String, Document> = serde_json::from_str(&data)?;
+            // Chunking was not recorded before it could be changed per index; such
+            // chunks are taken as cut with the current settings, not as mixed chunking
+            let config = self.config.read().await.clone();
+            for doc in loaded_docs.values_mut() {
+                doc.chunk_size.get_or_insert(config.chunk_size);
+                doc.chunk_overlap.get_or_insert(config.chunk_overlap);
+                doc.chunking_strategy
+                    .get_or_insert(config.chunking_strategy);
+            }
+            *self.chunk_term_counts.write().await = loaded_docs
+                .iter()
+                .map(|(id, doc)| (id.clone(), index_terms(&doc.content).count()))
+                .collect();
             *self.documents.write().await = loaded_docs;
         }
 
@@ -525,9 +2021,221 @@ impl RAGEngine {
             *self.inverted_index.write().await = loaded_index;
         }
 
+        let versions_file = self.index_path.join("versions.json");
+        if versions_file.exists() {
+            let data = tokio::fs::read_to_string(&versions_file).await?;
+            *self.versions.write().await = serde_json::from_str(&data)?;
+        }
+
+        // Stored vectors are in the projection's space, whatever the default config says
+        let projection_file = self.index_path.join("projection.json");
+        if projection_file.exists() {
+            let data = tokio::fs::read_to_string(&projection_file).await?;
+            let projection: EmbeddingProjection = serde_json::from_str(&data)?;
+            let mut config = self.config.write().await;
+            config.dimension_reduction = projection.reduction.clone();
+            config.normalize_embeddings = projection.normalize;
+            *self.projection.write().await = Some(projection);
+        }
+
+        let stale = self.stale_vector_chunks().await;
+        if stale > 0 {
+            tracing::warn!(
+                chunks = stale,
+                "⚠️ RAG index holds chunks embedded with another model; rebuild the index \
+                 to search them by meaning"
+            );
+        }
+
         Ok(())
     }
 
+    /// Database ids (`document_id` metadata) of the documents in the index
+    pub async fn source_document_ids(&self) -> Vec<String> {
+        let docs = self.documents.read().await;
+        let ids: HashSet<String> = docs
+            .values()
+            .filter_map(|doc| match doc.metadata.get("document_id")? {
+                JsonValue::String(id) => Some(id.clone()),
+                JsonValue::Number(id) => Some(id.to_string()),
+                _ => None,
+            })
+            .collect();
+        let mut ids: Vec<String> = ids.into_iter().collect();
+        ids.sort();
+        ids
+    }
+
+    /// Stream every chunk (content, metadata, embeddings) to a portable archive
+    pub async fn export_knowledge_base(&self, path: &Path) -> Result<KnowledgeBaseTransfer> {
+        let embedding_model = self.config.read().await.embedding_model.clone();
+        let docs = self.documents.read().await;
+
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let mut writer = BufWriter::new(tokio::fs::File::create(path).await?);
+
+        let header = KnowledgeBaseHeader {
+            format: KNOWLEDGE_BASE_FORMAT.to_string(),
+            version: KNOWLEDGE_BASE_VERSION,
+            embedding_model: embedding_model.clone(),
+            projection: self.projection.read().await.clone(),
+            chunk_count: docs.len(),
+            exported_at: chrono::Utc::now().to_rfc3339(),
+        };
+        writer.write_all(serde_json::to_string(&header)?.as_bytes()).await?;
+        writer.write_all(b"\n").await?;
+
+        let mut unique_docs = HashSet::new();
+        for doc in docs.values() {
+            unique_docs.insert(Self::parent_document_id(&doc.id));
+            writer.write_all(serde_json::to_string(doc)?.as_bytes()).await?;
+            writer.write_all(b"\n").await?;
+        }
+        writer.flush().await?;
+
+        tracing::info!(
+            chunks = docs.len(),
+            path = %path.display(),
+            "📦 Knowledge base exported"
+        );
+
+        Ok(KnowledgeBaseTransfer {
+            path: path.to_path_buf(),
+            embedding_model,
+            chunks: docs.len(),
+            documents: unique_docs.len(),
+            re_embedded: false,
+        })
+    }
+
+    /// Restore chunks from an archive, re-embedding if the local model differs
+    pub async fn import_knowledge_base(&self, path: &Path) -> Result<KnowledgeBaseTransfer> {
+        let mut lines = BufReader::new(tokio::fs::File::open(path).await?).lines();
+
+        let header: KnowledgeBaseHeader = match lines.next_line().await? {
+            Some(line) => serde_json::from_str(&line)?,
+            None => return Err(anyhow!("Knowledge base archive is empty")),
+        };
+        if header.format != KNOWLEDGE_BASE_FORMAT {
+            return Err(anyhow!("Not a knowledge base archive: {}", path.display()));
+        }
+        if header.version > KNOWLEDGE_BASE_VERSION {
+            return Err(anyhow!(
+                "Knowledge base archive version {} is newer than supported version {}",
+                header.version,
+                KNOWLEDGE_BASE_VERSION
+            ));
+        }
+
+        let local_model = self.config.read().await.embedding_model.clone();
+        let local_projection = self.projection.read().await.clone();
+        let projection_pending =
+            local_projection.is_none() && self.config.read().await.transforms_embeddings();
+        let re_embed = header.embedding_model != local_model
+            || header.projection != local_projection
+            || projection_pending;
+        if re_embed {
+            tracing::info!(
+                from = %header.embedding_model,
+                to = %local_model,
+                "Embedding model or reduction differs, re-embedding imported chunks"
+            );
+            self.embedding_backend().await?;
+        }
+
+        // Chunks are read and, if needed, re-embedded a wave of batches at a time
+        let wave_size = {
+            let cfg = self.config.read().await;
+            cfg.embedding_batch_size.max(1) * cfg.embedding_parallelism.max(1)
+        };
+        let mut chunks = 0;
+        let mut unique_docs = HashSet::new();
+        let mut pending = Vec::with_capacity(wave_size);
+        loop {
+            let line = lines.next_line().await?;
+            if let Some(line) = &line {
+                if !line.trim().is_empty() {
+                    let mut doc: Document = serde_json::from_str(line)?;
+                    if !re_embed && doc.embedding_model.is_none() {
+                        doc.embedding_model = Some(header.embedding_model.clone());
+                    }
+                    pending.push(doc);
+                }
+            }
+            if pending.len() < wave_size && line.is_some() {
+                continue;
+            }
+
+            if re_embed && !pending.is_empty() {
+                let texts: Vec<String> = pending.iter().map(|doc| doc.content.clone()).collect();
+                let vectors = self
+                    .to_index_space(self.embed_chunks(&texts).await?, true)
+                    .await?;
+                for (doc, embeddings) in pending.iter_mut().zip(vectors) {
+                    doc.embeddings = embeddings;
+                    doc.embedding_model = Some(local_model.clone());
+                }
+            }
+
+            let mut documents = self.documents.write().await;
+            let mut inverted_index = self.inverted_index.write().await;
+            let mut term_counts = self.chunk_term_counts.write().await;
+            for doc in pending.drain(..) {
+                // An imported document replaces any indexed copy, keyword postings included
+                let parent = Self::parent_document_id(&doc.id);
+                if unique_docs.insert(parent.clone()) {
+                    Self::remove_chunks(
+                        &parent,
+                        &mut documents,
+                        &mut inverted_index,
+                        &mut term_counts,
+                    );
+                } else {
+                    Self::remove_chunk(
+                        &doc.id,
+                        &mut documents,
+                        &mut inverted_index,
+                        &mut term_counts,
+                    );
+                }
+                self.update_inverted_index(
+                    &doc.id,
+                    &doc.content,
+                    &mut inverted_index,
+                    &mut term_counts,
+                );
+                documents.insert(doc.id.clone(), doc);
+                chunks += 1;
+            }
+
+            if line.is_none() {
+                break;
+            }
+        }
+
+        self.save_index().await?;
+        tracing::info!(chunks, path = %path.display(), "📥 Knowledge base imported");
+
+        Ok(KnowledgeBaseTransfer {
+            path: path.to_path_buf(),
+            embedding_model: local_model,
+            chunks,
+            documents: unique_docs.len(),
+            re_embedded: re_embed,
+        })
+    }
+
+    /// Chunk ids are "<document uuid>_<chunk index>"
+    fn parent_document_id(chunk_id: &str) -> String {
+        chunk_id
+            .rsplit_once('_')
+            .map(|(doc_id, _)| doc_id)
+            .unwrap_or(chunk_id)
+            .to_string()
+    }
+
     #[allow(dead_code)]
     pub async fn clear_cache(&self) -> Result<()> {
         *self.embeddings_model.write().await = None;
@@ -535,47 +2243,162 @@ impl RAGEngine {
         Ok(())
     }
 
-    #[allow(dead_code)]
-    pub async fn clear_index(&self) -> Result<()> {
+    /// Remove every indexed document, returning the number of chunks removed
+    pub async fn clear_index(&self) -> Result<usize> {
+        let removed = self.documents.read().await.len();
         self.documents.write().await.clear();
         self.inverted_index.write().await.clear();
+        self.chunk_term_counts.write().await.clear();
+        self.versions.write().await.clear();
         self.save_index().await?;
         tracing::info!("🧹 RAG document index cleared");
-        Ok(())
+        Ok(removed)
+    }
+
+    /// Clear the index and return the configuration and embedding backend to
+    /// their defaults, returning the number of chunks removed
+    pub async fn reset(&self) -> Result<usize> {
+        *self.config.write().await = RAGConfig::default();
+        *self.projection.write().await = None;
+        *self.embeddings_model.write().await = None;
+        self.clear_index().await
     }
 
-    #[allow(dead_code)]
     pub async fn delete_document(&self, doc_id: &str) -> Result<()> {
         let mut docs = self.documents.write().await;
         let mut index = self.inverted_index.write().await;
+        let mut term_counts = self.chunk_term_counts.write().await;
+        Self::remove_chunks(doc_id, &mut docs, &mut index, &mut term_counts);
+        self.versions.write().await.remove(doc_id);
 
-        let keys_to_remove: Vec<String> = docs
-            .keys()
-            .filter(|k| k.starts_with(doc_id))
+        drop(docs);
+        drop(index);
+        drop(term_counts);
+        self.save_index().await?;
+        tracing::info!("🗑️ Document {} deleted from index", doc_id);
+        Ok(())
+    }
+
+    /// Delete every document whose `OWNER_KEY` metadata names `user_id`,
+    /// returning the number of documents removed
+    pub async fn delete_user_documents(&self, user_id: &str) -> Result<usize> {
+        let mut docs = self.documents.write().await;
+        let mut index = self.inverted_index.write().await;
+        let mut term_counts = self.chunk_term_counts.write().await;
+
+        let owned: HashSet<String> = docs
+            .values()
+            .filter(|doc| doc.metadata.get(OWNER_KEY).and_then(JsonValue::as_str) == Some(user_id))
+            .map(|doc| Self::parent_document_id(&doc.id))
+            .collect();
+        let mut versions = self.versions.write().await;
+        for doc_id in &owned {
+            Self::remove_chunks(doc_id, &mut docs, &mut index, &mut term_counts);
+            versions.remove(doc_id);
+        }
+
+        drop(versions);
+        drop(docs);
+        drop(index);
+        drop(term_counts);
+        self.save_index().await?;
+        tracing::info!(documents = owned.len(), "🗑️ User documents deleted");
+        Ok(owned.len())
+    }
+
+    /// Chunks of `content` as `doc_id`, embedded into the index space with the
+    /// current chunking settings recorded; nothing is stored
+    async fn build_chunks(
+        &self,
+        doc_id: &str,
+        content: &str,
+        metadata: &JsonValue,
+        source: Option<IngestSource>,
+    ) -> Result<Vec<Document>> {
+        if let Some(weight) = metadata.get(TRUST_WEIGHT_KEY) {
+            let weight = weight
+                .as_f64()
+                .ok_or_else(|| anyhow!("Trust weight must be a number"))?;
+            validate_trust_weight(weight as f32)?;
+        }
+
+        let chunks = self.chunk_text(content).await;
+        let total_chunks = chunks.len();
+        let chunk_embeddings = self
+            .to_index_space(self.embed_chunks(&chunks).await?, true)
+            .await?;
+        let config = self.config.read().await.clone();
+
+        Ok(chunks
+            .into_iter()
+            .zip(chunk_embeddings)
+            .enumerate()
+            .map(|(idx, (chunk, embeddings))| Document {
+                id: format!("{}_{}", doc_id, idx),
+                content: chunk,
+                embeddings,
+                metadata: metadata.clone(),
+                timestamp: chrono::Utc::now().timestamp(),
+                chunk_index: idx,
+                total_chunks,
+                embedding_model: Some(config.embedding_model.clone()),
+                chunk_size: Some(config.chunk_size),
+                chunk_overlap: Some(config.chunk_overlap),
+                chunking_strategy: Some(config.chunking_strategy),
+                source: source.clone(),
+            })
+            .collect())
+    }
+
+    /// Swap the chunks of `doc_id` for `chunks`, keeping the keyword index in step
+    fn replace_chunks(
+        &self,
+        doc_id: &str,
+        chunks: Vec<Document>,
+        docs: &mut HashMap<String, Document>,
+        index: &mut HashMap<String, Vec<String>>,
+        term_counts: &mut HashMap<String, usize>,
+    ) {
+        Self::remove_chunks(doc_id, docs, index, term_counts);
+        for chunk in chunks {
+            self.update_inverted_index(&chunk.id, &chunk.content, index, term_counts);
+            docs.insert(chunk.id.clone(), chunk);
+        }
+    }
+
+    /// Remove every chunk of `doc_id` and its keyword index entries
+    fn remove_chunks(
+        doc_id: &str,
+        docs: &mut HashMap<String, Document>,
+        index: &mut HashMap<String, Vec<String>>,
+        term_counts: &mut HashMap<String, usize>,
+    ) {
+        let keys_to_remove: Vec<String> = docs
+            .keys()
+            .filter(|k| Self::parent_document_id(k) == doc_id)
             .cloned()
             .collect();
 
         for key in &keys_to_remove {
-            if let Some(doc) = docs.get(key) {
-                for token in doc
-                    .content
-                    .to_lowercase()
-                    .split_whitespace()
-                    .filter(|t| t.len() > 2)
-                {
-                    if let Some(ids) = index.get_mut(token) {
-                        ids.retain(|id| id != key);
-                    }
+            Self::remove_chunk(key, docs, index, term_counts);
+        }
+    }
+
+    /// Remove one chunk and its keyword index entries
+    fn remove_chunk(
+        chunk_id: &str,
+        docs: &mut HashMap<String, Document>,
+        index: &mut HashMap<String, Vec<String>>,
+        term_counts: &mut HashMap<String, usize>,
+    ) {
+        if let Some(doc) = docs.remove(chunk_id) {
+            for token in index_terms(&doc.content) {
+                if let Some(ids) = index.get_mut(&token) {
+                    ids.retain(|id| id != chunk_id);
                 }
             }
-            docs.remove(key);
         }
-
-        drop(docs);
-        drop(index);
-        self.save_index().await?;
-        tracing::info!("🗑️ Document {} deleted from index", doc_id);
-        Ok(())
+        term_counts.remove(chunk_id);
     }
 
     #[allow(dead_code)]
@@ -606,7 +2429,126 @@ impl RAGEngine {
         }))
     }
 
-    #[allow(dead_code)]
+    /// Set the source trust weight of a document returned by `add_document`,
+    /// returning the number of chunks updated
+    pub async fn set_document_trust(&self, doc_id: &str, weight: f32) -> Result<usize> {
+        let weight = validate_trust_weight(weight)?;
+        let prefix = format!("{}_", doc_id);
+        let mut docs = self.documents.write().await;
+        let mut updated = 0;
+        for doc in docs.values_mut().filter(|d| d.id.starts_with(&prefix)) {
+            if !doc.metadata.is_object() {
+                doc.metadata = serde_json::json!({});
+            }
+            doc.metadata[TRUST_WEIGHT_KEY] = serde_json::json!(weight);
+            updated += 1;
+        }
+        drop(docs);
+
+        if updated == 0 {
+            return Err(anyhow!("Document not found in index: {}", doc_id));
+        }
+        self.save_index().await?;
+        Ok(updated)
+    }
+
+    /// Provenance of one indexed chunk
+    pub async fn chunk_provenance(&self, chunk_id: &str) -> Result<ChunkProvenance> {
+        let docs = self.documents.read().await;
+        docs.get(chunk_id)
+            .map(ChunkProvenance::of)
+            .ok_or_else(|| anyhow!("Chunk {} is not in the index", chunk_id))
+    }
+
+    /// Number of chunks indexed for a document returned by `add_document`
+    pub async fn document_chunk_count(&self, doc_id: &str) -> usize {
+        let prefix = format!("{}_", doc_id);
+        self.documents
+            .read()
+            .await
+            .keys()
+            .filter(|id| id.starts_with(&prefix))
+            .count()
+    }
+
+    /// Document/chunk counts, sizes, date range and embedding model mix of the index
+    pub async fn get_index_statistics(&self) -> Result<RAGIndexStatistics> {
+        let docs = self.documents.read().await;
+        let index = self.inverted_index.read().await;
+        let config = self.config.read().await.clone();
+        let embedding_model = config.embedding_model.clone();
+        let stale_chunking_documents = Self::stale_chunking(&docs, &config).len();
+
+        let unique_docs: HashSet<String> =
+            docs.keys().map(|id| Self::parent_document_id(id)).collect();
+
+        let mut model_counts: HashMap<String, usize> = HashMap::new();
+        let mut dimension_counts: HashMap<usize, usize> = HashMap::new();
+        for doc in docs.values() {
+            let model = doc.embedding_model.as_deref().unwrap_or("unknown");
+            *model_counts.entry(model.to_string()).or_default() += 1;
+            *dimension_counts.entry(doc.embeddings.len()).or_default() += 1;
+        }
+        let mut embedding_models: Vec<EmbeddingModelShare> = model_counts
+            .into_iter()
+            .map(|(model, chunks)| EmbeddingModelShare {
+                model,
+                chunks,
+                share: chunks as f64 / docs.len() as f64,
+            })
+            .collect();
+        embedding_models.sort_by(|a, b| b.chunks.cmp(&a.chunks).then(a.model.cmp(&b.model)));
+
+        let total_chars: usize = docs.values().map(|d| d.content.chars().count()).sum();
+        let average_chunk_chars = if docs.is_empty() {
+            0.0
+        } else {
+            total_chars as f64 / docs.len() as f64
+        };
+        let timestamps = || docs.values().map(|d| d.timestamp);
+        let format_date = |timestamp: i64| {
+            chrono::DateTime::from_timestamp(timestamp, 0).map(|date| date.to_rfc3339())
+        };
+
+        let mut index_disk_bytes = 0;
+        for file in [
+            "documents.json",
+            "inverted_index.json",
+            "versions.json",
+            "projection.json",
+            "index.json",
+        ] {
+            if let Ok(metadata) = tokio::fs::metadata(self.index_path.join(file)).await {
+                index_disk_bytes += metadata.len();
+            }
+        }
+
+        Ok(RAGIndexStatistics {
+            document_count: unique_docs.len(),
+            total_chunks: docs.len(),
+            embedding_model,
+            embedding_dimension: dimension_counts
+                .into_iter()
+                .max_by_key(|(dimension, count)| (*count, *dimension))
+                .map(|(dimension, _)| dimension),
+            source_embedding_dimension: self
+                .projection
+                .read()
+                .await
+                .as_ref()
+                .filter(|p| p.output_dimension() != p.source_dimension)
+                .map(|p| p.source_dimension),
+            average_chunk_chars,
+            index_memory_bytes: self.estimate_index_size(&docs, &index),
+            index_disk_bytes,
+            oldest_document: timestamps().min().and_then(format_date),
+            newest_document: timestamps().max().and_then(format_date),
+            embedding_models,
+            stale_chunking_documents,
+            mixed_chunking: stale_chunking_documents > 0,
+        })
+    }
+
     fn estimate_index_size(
         &self,
         docs: &HashMap<String, Document>,
@@ -630,12 +2572,24 @@ impl RAGEngine {
         query: &str,
         limit: Option<usize>,
     ) -> Result<String> {
+        Ok(self.build_grounded_prompt(query, limit).await?.0)
+    }
+
+    /// Retrieve context for `query` and build the grounded prompt, returning the sources used
+    pub async fn build_grounded_prompt(
+        &self,
+        query: &str,
+        limit: Option<usize>,
+    ) -> Result<(String, Vec<SearchResult>)> {
         let results = self.search(query, limit).await?;
 
         if results.is_empty() {
-            return Ok(format!(
-                "INSTRUCTION: Answer the following question to the best of your ability.\n\nQUESTION: {}\nANSWER:",
-                query
+            return Ok((
+                format!(
+                    "INSTRUCTION: Answer the following question to the best of your ability.\n\nQUESTION: {}\nANSWER:",
+                    query
+                ),
+                results,
             ));
         }
 
@@ -671,6 +2625,1396 @@ impl RAGEngine {
             prompt.len()
         );
 
-        Ok(prompt)
+        Ok((prompt, results))
+    }
+
+    /// Align each answer sentence with the retrieved chunk it is most similar to
+    pub async fn attribute_sentences(
+        &self,
+        answer: &str,
+        sources: &[SearchResult],
+    ) -> Result<Vec<SentenceAttribution>> {
+        let sentences = split_sentences(answer);
+        if sentences.is_empty() {
+            return Ok(Vec::new());
+        }
+        let min_similarity = self.config.read().await.attribution_min_similarity;
+
+        // One embedding pass over sentences followed by source chunks
+        let mut texts: Vec<String> = sentences.iter().map(|s| s.to_string()).collect();
+        texts.extend(sources.iter().map(|r| r.content.clone()));
+        let embeddings = self.embed_chunks(&texts).await?;
+        let (sentence_vecs, source_vecs) = embeddings.split_at(sentences.len());
+
+        Ok(sentences
+            .iter()
+            .zip(sentence_vecs)
+            .map(|(sentence, sentence_vec)| {
+                let best = source_vecs
+                    .iter()
+                    .enumerate()
+                    .map(|(i, source_vec)| (i, cosine_similarity(sentence_vec, source_vec)))
+                    .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+
+                match best {
+                    Some((index, similarity)) if similarity >= min_similarity => {
+                        SentenceAttribution {
+                            sentence: sentence.to_string(),
+                            source_index: Some(index),
+                            document_id: Some(sources[index].document_id.clone()),
+                            similarity,
+                            supported: true,
+                        }
+                    }
+                    best => SentenceAttribution {
+                        sentence: sentence.to_string(),
+                        source_index: None,
+                        document_id: None,
+                        similarity: best.map(|(_, similarity)| similarity).unwrap_or(0.0),
+                        supported: false,
+                    },
+                }
+            })
+            .collect())
+    }
+}
+
+// Erasing a user's data removes the documents indexed for them
+#[async_trait]
+impl UserDataStore for RwLock<RAGEngine> {
+    fn name(&self) -> &str {
+        "rag_documents"
+    }
+
+    async fn erase_user_data(&self, user_id: &str) -> Result<usize> {
+        self.read().await.delete_user_documents(user_id).await
+    }
+}
+
+/// Parent document id of a chunk result, from its `<document>_<chunk index>` id
+fn parent_document_id(result: &SearchResult) -> Option<&str> {
+    let (start, _) = result.chunk_range?;
+    result
+        .document_id
+        .strip_suffix(&format!("_{}", start))
+        .filter(|parent| !parent.is_empty())
+}
+
+/// Length in bytes of the longest suffix of `earlier` that begins `later`
+fn overlap_len(earlier: &str, later: &str) -> usize {
+    let longest = earlier.len().min(later.len());
+    later
+        .char_indices()
+        .map(|(idx, c)| idx + c.len_utf8())
+        .take_while(|end| *end <= longest)
+        .filter(|end| earlier.ends_with(&later[..*end]))
+        .last()
+        .unwrap_or(0)
+}
+
+/// Text of consecutive chunks with the overlap between neighbours dropped.
+/// In spaced text only whole-word overlaps count, and chunks are rejoined with
+/// a space; whitespace inside the original text is not recovered.
+fn reassemble_chunks<'a>(chunks: impl Iterator<Item = &'a str>) -> String {
+    let mut text = String::new();
+    for chunk in chunks {
+        let spaced = chunk.contains(char::is_whitespace);
+        let mut overlap = overlap_len(&text, chunk);
+        let whole_words = (overlap == chunk.len()
+            || chunk[overlap..].starts_with(char::is_whitespace))
+            && (overlap == text.len()
+                || text[..text.len() - overlap].ends_with(char::is_whitespace));
+        if spaced && !whole_words {
+            overlap = 0;
+        }
+        if overlap == 0 && spaced && !text.is_empty() {
+            text.push(' ');
+        }
+        text.push_str(&chunk[overlap..]);
+    }
+    text
+}
+
+/// Collapse results for neighbouring chunks of the same document whose text
+/// overlaps into one passage, so overlapping text is cited once
+///
+/// A merged passage keeps the best score and highlight of its parts, the id of
+/// its first chunk, and the combined chunk range.
+fn merge_overlapping_results(results: Vec<SearchResult>) -> Vec<SearchResult> {
+    let mut by_parent: HashMap<String, Vec<SearchResult>> = HashMap::new();
+    let mut merged = Vec::new();
+    for result in results {
+        match parent_document_id(&result).map(str::to_string) {
+            Some(parent) => by_parent.entry(parent).or_default().push(result),
+            None => merged.push(result),
+        }
     }
-}
\ No newline at end of file
+
+    for (_, mut parts) in by_parent {
+        parts.sort_by_key(|r| r.chunk_range.map(|(start, _)| start));
+        let mut parts = parts.into_iter();
+        let Some(mut current) = parts.next() else {
+            continue;
+        };
+        for next in parts {
+            let (_, current_end) = current.chunk_range.unwrap_or_default();
+            let (next_start, next_end) = next.chunk_range.unwrap_or_default();
+            let overlap = if next_start == current_end + 1 {
+                overlap_len(&current.content, &next.content)
+            } else {
+                0
+            };
+            let is_overlap =
+                overlap >= MIN_CITATION_OVERLAP_CHARS.min(next.content.len()) && overlap > 0;
+            if !is_overlap {
+                merged.push(std::mem::replace(&mut current, next));
+                continue;
+            }
+
+            current.content.push_str(&next.content[overlap..]);
+            current.chunk_range = Some((current.chunk_range.unwrap_or_default().0, next_end));
+            if let (Some(provenance), Some(next)) = (&mut current.provenance, &next.provenance) {
+                if next.pii_redacted == Some(true) {
+                    provenance.pii_redacted = Some(true);
+                }
+            }
+            if next.score > current.score {
+                current.score = next.score;
+                current.highlight = next.highlight.or(current.highlight);
+                current.reasoning = next.reasoning.or(current.reasoning);
+            }
+        }
+        merged.push(current);
+    }
+
+    merged.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::completion::mock::MockCompletion;
+
+    /// Chunks matching `query` by BM25 alone, best first
+    async fn keyword_hits(engine: &RAGEngine, query: &str) -> Vec<SearchResult> {
+        let terms = query_terms(query);
+        let scores = engine.keyword_scores(&terms).await;
+        engine.keyword_results(&terms, &scores, 5, None).await
+    }
+
+    fn chunk(id: &str, content: &str, embeddings: Vec<f32>) -> Document {
+        Document {
+            id: id.to_string(),
+            content: content.to_string(),
+            embeddings,
+            metadata: serde_json::json!({"filename": "lease.txt"}),
+            timestamp: 0,
+            chunk_index: 0,
+            total_chunks: 1,
+            embedding_model: None,
+            chunk_size: None,
+            chunk_overlap: None,
+            chunking_strategy: None,
+            source: None,
+        }
+    }
+
+    async fn seed(engine: &RAGEngine, docs: Vec<Document>) {
+        let mut documents = engine.documents.write().await;
+        let mut index = engine.inverted_index.write().await;
+        let mut term_counts = engine.chunk_term_counts.write().await;
+        for doc in docs {
+            engine.update_inverted_index(&doc.id, &doc.content, &mut index, &mut term_counts);
+            documents.insert(doc.id.clone(), doc);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_knowledge_base_round_trip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let source = RAGEngine::with_index_path(temp_dir.path().join("source"));
+        seed(
+            &source,
+            vec![
+                chunk("lease_0", "The tenant must give ninety days notice", vec![1.0, 0.0]),
+                chunk("nda_0", "Confidential information excludes public knowledge", vec![0.0, 1.0]),
+            ],
+        )
+        .await;
+
+        let archive = temp_dir.path().join("backup").join("kb.jsonl");
+        let exported = source.export_knowledge_base(&archive).await.unwrap();
+        assert_eq!(exported.chunks, 2);
+        assert_eq!(exported.documents, 2);
+
+        let target = RAGEngine::with_index_path(temp_dir.path().join("target"));
+        let imported = target.import_knowledge_base(&archive).await.unwrap();
+        assert_eq!(imported.chunks, 2);
+        assert!(!imported.re_embedded);
+
+        let restored = target.documents.read().await;
+        let lease = restored.get("lease_0").unwrap();
+        assert_eq!(lease.content, "The tenant must give ninety days notice");
+        assert_eq!(lease.embeddings, vec![1.0, 0.0]);
+        assert_eq!(lease.metadata["filename"], "lease.txt");
+        drop(restored);
+
+        let before = keyword_hits(&source, "tenant notice").await;
+        let after = keyword_hits(&target, "tenant notice").await;
+        assert_eq!(after.len(), before.len());
+        assert_eq!(after[0].document_id, "lease_0");
+        assert_eq!(after[0].content, before[0].content);
+
+        // Imported index is persisted
+        assert!(temp_dir.path().join("target").join("documents.json").exists());
+
+        // Importing the same backup again replaces the chunks instead of
+        // doubling their keyword postings
+        target.import_knowledge_base(&archive).await.unwrap();
+        assert_eq!(target.documents.read().await.len(), 2);
+        assert_eq!(
+            target.inverted_index.read().await["tenant"],
+            vec!["lease_0"]
+        );
+        let again = keyword_hits(&target, "tenant notice").await;
+        assert_eq!(again.len(), after.len());
+        assert!((again[0].score - after[0].score).abs() < 1e-6);
+    }
+
+    #[tokio::test]
+    async fn test_knowledge_base_import_re_embeds_in_batches() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let source = RAGEngine::with_index_path(temp_dir.path().join("source"));
+        seed(
+            &source,
+            (0..5)
+                .map(|i| chunk(&format!("doc{}_0", i), "Rent is due monthly", vec![1.0]))
+                .collect(),
+        )
+        .await;
+        let archive = temp_dir.path().join("kb.jsonl");
+        source.export_knowledge_base(&archive).await.unwrap();
+
+        let target = RAGEngine::with_index_path(temp_dir.path().join("target"));
+        let mut config = target.get_config().await;
+        config.embedding_model = "other-model".into();
+        config.embedding_batch_size = 2;
+        config.embedding_parallelism = 1;
+        target.update_config(config).await.unwrap();
+        let backend = Arc::new(RecordingBackend {
+            batch_sizes: std::sync::Mutex::new(Vec::new()),
+        });
+        target.set_embedding_backend(backend.clone()).await;
+
+        let imported = target.import_knowledge_base(&archive).await.unwrap();
+        assert!(imported.re_embedded);
+        assert_eq!(imported.chunks, 5);
+        assert_eq!(*backend.batch_sizes.lock().unwrap(), vec![2, 2, 1]);
+        let documents = target.documents.read().await;
+        assert!(documents.values().all(|d| d.embeddings.len() == 2));
+    }
+
+    /// Records the size of every batch it is asked to embed
+    struct RecordingBackend {
+        batch_sizes: std::sync::Mutex<Vec<usize>>,
+    }
+
+    #[async_trait]
+    impl EmbeddingBackend for RecordingBackend {
+        async fn embed_batch(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+            self.batch_sizes.lock().unwrap().push(texts.len());
+            Ok(texts.iter().map(|t| vec![t.len() as f32, 1.0]).collect())
+        }
+    }
+
+    /// Embeds by topic: rent, termination, or neither
+    struct TopicBackend;
+
+    #[async_trait]
+    impl EmbeddingBackend for TopicBackend {
+        async fn embed_batch(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+            Ok(texts
+                .iter()
+                .map(|t| {
+                    let t = t.to_lowercase();
+                    let rent = t.matches("rent").count() as f32;
+                    let termination = t.matches("terminat").count() as f32;
+                    let other = if rent + termination == 0.0 { 1.0 } else { 0.0 };
+                    vec![rent, termination, other]
+                })
+                .collect())
+        }
+    }
+
+    /// Embeds by the same topics as `TopicBackend`, in a different vector layout
+    struct ReorderedTopicBackend;
+
+    #[async_trait]
+    impl EmbeddingBackend for ReorderedTopicBackend {
+        async fn embed_batch(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+            Ok(TopicBackend
+                .embed_batch(texts)
+                .await?
+                .into_iter()
+                .map(|v| vec![v[2], 0.0, v[1], v[0]])
+                .collect())
+        }
+    }
+
+    fn source(document_id: &str, content: &str) -> SearchResult {
+        SearchResult {
+            document_id: document_id.to_string(),
+            content: content.to_string(),
+            score: 1.0,
+            metadata: serde_json::json!({}),
+            highlight: None,
+            reasoning: None,
+            chunk_range: None,
+            trust_weight: 1.0,
+            provenance: None,
+            fused_score: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_each_answer_sentence_attributed_to_its_source() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let engine = RAGEngine::with_index_path(temp_dir.path().to_path_buf());
+        engine.set_embedding_backend(Arc::new(TopicBackend)).await;
+
+        let sources = vec![
+            source("lease_0", "The tenant shall pay rent of EUR 2,000 monthly."),
+            source("lease_1", "Either party may terminate on 90 days notice."),
+        ];
+        let answer = "Rent is EUR 2,000 per month. The lease can be terminated on ninety days \
+                      notice. The landlord also owns the building next door.";
+
+        let attributions = engine.attribute_sentences(answer, &sources).await.unwrap();
+
+        assert_eq!(attributions.len(), 3);
+        assert_eq!(attributions[0].sentence, "Rent is EUR 2,000 per month.");
+        assert_eq!(attributions[0].source_index, Some(0));
+        assert_eq!(attributions[0].document_id.as_deref(), Some("lease_0"));
+        assert!(attributions[0].supported);
+        assert_eq!(attributions[1].source_index, Some(1));
+        assert_eq!(attributions[1].document_id.as_deref(), Some("lease_1"));
+        assert!(attributions[1].supported);
+        // Unsupported sentences are flagged rather than forced onto a source
+        assert!(!attributions[2].supported);
+        assert_eq!(attributions[2].source_index, None);
+    }
+
+    #[tokio::test]
+    async fn test_index_statistics_match_indexed_documents() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let engine = RAGEngine::with_index_path(temp_dir.path().to_path_buf());
+        let mut config = engine.get_config().await;
+        config.chunk_size = 5;
+        config.chunk_overlap = 0;
+        engine.update_config(config.clone()).await.unwrap();
+        engine.set_embedding_backend(Arc::new(TopicBackend)).await;
+
+        let empty = engine.get_index_statistics().await.unwrap();
+        assert_eq!(empty.total_chunks, 0);
+        assert_eq!(empty.embedding_dimension, None);
+
+        // 10 words -> 2 chunks, 3 words -> 1 chunk, 5 words -> 1 chunk
+        for text in [
+            "rent is due monthly and terminates the lease on ninety",
+            "termination requires notice",
+            "the tenant pays the rent",
+        ] {
+            engine
+                .add_document(text, serde_json::json!({"filename": "lease.txt"}))
+                .await
+                .unwrap();
+        }
+
+        let stats = engine.get_index_statistics().await.unwrap();
+        assert_eq!(stats.document_count, 3);
+        assert_eq!(stats.total_chunks, 4);
+        assert_eq!(stats.embedding_model, config.embedding_model);
+        assert_eq!(stats.embedding_dimension, Some(3));
+        assert!(stats.average_chunk_chars > 0.0);
+        assert!(stats.index_disk_bytes > 0);
+        assert!(stats.index_memory_bytes > 0);
+        assert!(stats.oldest_document.is_some());
+        assert!(stats.oldest_document <= stats.newest_document);
+        assert_eq!(stats.embedding_models.len(), 1);
+        assert_eq!(stats.embedding_models[0].model, config.embedding_model);
+        assert_eq!(stats.embedding_models[0].chunks, 4);
+
+        // Legacy chunks without a recorded model make the index mixed
+        let legacy = chunk("legacy_0", "old chunk", vec![0.0, 0.0, 1.0]);
+        seed(&engine, vec![legacy]).await;
+        let mixed = engine.get_index_statistics().await.unwrap();
+        assert_eq!(mixed.embedding_models.len(), 2);
+        assert_eq!(mixed.embedding_models[1].model, "unknown");
+        assert!((mixed.embedding_models[0].share - 0.8).abs() < 1e-9);
+    }
+
+    /// One token per two characters, so token and character counts differ
+    struct PairTokenCounter;
+
+    impl TokenCounter for PairTokenCounter {
+        fn count_tokens(&self, text: &str) -> usize {
+            text.chars().count().div_ceil(2)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cjk_document_chunked_by_tokens() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let engine = RAGEngine::with_index_path(temp_dir.path().to_path_buf());
+        let mut config = engine.get_config().await;
+        config.chunk_size = 10;
+        config.chunk_overlap = 0;
+        engine.update_config(config).await.unwrap();
+        engine.set_token_counter(Arc::new(PairTokenCounter)).await;
+
+        // No whitespace at all: word chunking would yield a single chunk
+        let text = "租户应于每月第一日支付租金。任何一方均可提前九十日书面通知解除本合同。\
+                    本合同受中华人民共和国法律管辖。";
+        let chunks = engine.chunk_text(text).await;
+
+        assert!(chunks.len() > 1, "{:?}", chunks);
+        for chunk in &chunks {
+            assert!(PairTokenCounter.count_tokens(chunk) <= 10, "{}", chunk);
+            // Sized by tokens, not bytes: each chunk holds more bytes than the limit
+            assert!(chunk.len() > 10);
+        }
+        // Nothing lost or cut inside a character
+        assert_eq!(chunks.concat(), text);
+        assert_eq!(chunks[0], "租户应于每月第一日支付租金。");
+
+        // English keeps whitespace word chunking
+        let english = engine
+            .chunk_text("one two three four five six seven eight nine ten eleven")
+            .await;
+        assert_eq!(english.len(), 2);
+    }
+
+    const LEASE_TEXT: &str = "The Tenant shall pay rent on the first day of each month. \
+        Late payments accrue interest at five percent per annum. \
+        The Landlord shall maintain the roof and structural walls.\n\n\
+        Either party may terminate this Lease on ninety days written notice. \
+        Termination does not release accrued obligations.\n\n\
+        This Lease is governed by the laws of the State of New York.";
+
+    async fn chunk_lease(
+        engine: &RAGEngine,
+        strategy: ChunkingStrategy,
+        size: usize,
+    ) -> Vec<String> {
+        let mut config = engine.get_config().await;
+        config.chunking_strategy = strategy;
+        config.chunk_size = size;
+        config.chunk_overlap = 0;
+        config.chunk_overlap_units = 1;
+        engine.update_config(config).await.unwrap();
+        engine.chunk_text(LEASE_TEXT).await
+    }
+
+    #[tokio::test]
+    async fn test_sentence_chunking_keeps_whole_sentences() {
+        let default = RAGConfig::default().chunking_strategy;
+        assert_eq!(default, ChunkingStrategy::FixedChar);
+        let legacy: ChunkingStrategy = serde_json::from_str("\"fixed\"").unwrap();
+        assert_eq!(legacy, ChunkingStrategy::FixedChar);
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let engine = RAGEngine::with_index_path(temp_dir.path().to_path_buf());
+        let chunks = chunk_lease(&engine, ChunkingStrategy::Sentence, 25).await;
+
+        assert_eq!(chunks.len(), 5, "{:#?}", chunks);
+        for chunk in &chunks {
+            assert!(chunk.ends_with('.'), "{}", chunk);
+            assert!(chunk.split_whitespace().count() <= 25, "{}", chunk);
+        }
+        // Each chunk starts with the last sentence of the one before
+        for pair in chunks.windows(2) {
+            let last = *split_sentences(&pair[0]).last().unwrap();
+            assert!(pair[1].starts_with(last), "{:?}", pair);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_paragraph_chunking_overlaps_by_paragraph() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let engine = RAGEngine::with_index_path(temp_dir.path().to_path_buf());
+        let paragraphs = split_paragraphs(LEASE_TEXT);
+
+        let chunks = chunk_lease(&engine, ChunkingStrategy::Paragraph, 50).await;
+        assert_eq!(
+            chunks,
+            vec![
+                format!("{}\n\n{}", paragraphs[0], paragraphs[1]),
+                format!("{}\n\n{}", paragraphs[1], paragraphs[2]),
+            ]
+        );
+
+        // The 30-word first paragraph exceeds 20 words and is cut by sentences
+        let chunks = chunk_lease(&engine, ChunkingStrategy::Paragraph, 20).await;
+        assert!(chunks[0].starts_with("The Tenant shall pay rent"));
+        assert!(chunks[0].ends_with("each month."), "{}", chunks[0]);
+        for chunk in &chunks {
+            assert!(chunk.split_whitespace().count() <= 20, "{}", chunk);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_token_aware_chunking_respects_token_budget() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let engine = RAGEngine::with_index_path(temp_dir.path().to_path_buf());
+        engine.set_token_counter(Arc::new(PairTokenCounter)).await;
+
+        let chunks = chunk_lease(&engine, ChunkingStrategy::TokenAware, 40).await;
+        assert!(chunks.len() > 1, "{:?}", chunks);
+        for chunk in &chunks {
+            assert!(PairTokenCounter.count_tokens(chunk) <= 40, "{}", chunk);
+            assert!(chunk.ends_with('.'), "{}", chunk);
+        }
+        // Fixed chunking counts the 60 words instead: two 40-word windows
+        let fixed = chunk_lease(&engine, ChunkingStrategy::FixedChar, 40).await;
+        assert_eq!(fixed.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_overlapping_chunks_cited_as_one_passage() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let engine = RAGEngine::with_index_path(temp_dir.path().to_path_buf());
+        let mut config = engine.get_config().await;
+        config.chunk_size = 8;
+        config.chunk_overlap = 4;
+        engine.update_config(config.clone()).await.unwrap();
+        engine.set_embedding_backend(Arc::new(TopicBackend)).await;
+
+        // Chunks: words 0-7, 4-11, 8-13; "rent monthly. Either party" is in both
+        // of the first two
+        engine
+            .add_document(
+                "The tenant shall pay rent monthly. Either party may terminate on ninety \
+                 days notice.",
+                serde_json::json!({"filename": "lease.txt"}),
+            )
+            .await
+            .unwrap();
+
+        let results = engine.search("rent monthly", None).await.unwrap();
+        assert_eq!(results.len(), 1, "{:?}", results);
+        assert_eq!(results[0].chunk_range, Some((0, 1)));
+        assert_eq!(
+            results[0].content,
+            "The tenant shall pay rent monthly. Either party may terminate on ninety"
+        );
+        assert!(results[0].document_id.ends_with("_0"));
+
+        config.merge_overlapping_citations = false;
+        engine.update_config(config).await.unwrap();
+        engine.set_embedding_backend(Arc::new(TopicBackend)).await;
+        let unmerged = engine.search("rent monthly", None).await.unwrap();
+        assert_eq!(unmerged.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_hybrid_search_weighs_exact_citations() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let engine = RAGEngine::with_index_path(temp_dir.path().to_path_buf());
+        engine.set_embedding_backend(Arc::new(TopicBackend)).await;
+        seed(
+            &engine,
+            vec![
+                chunk(
+                    "rent_0",
+                    "Rent is due monthly and late rent incurs a fee.",
+                    vec![1.0, 0.0, 0.0],
+                ),
+                chunk(
+                    "citation_0",
+                    "Case No. 2023-CV-0456 concerned late rent",
+                    vec![0.3, 0.0, 1.0],
+                ),
+                chunk(
+                    "nda_0",
+                    "Confidential information excludes public knowledge",
+                    vec![0.0, 0.0, 1.0],
+                ),
+            ],
+        )
+        .await;
+
+        let query = "2023-CV-0456 rent";
+        let semantic = engine.search_hybrid(query, 5, 1.0, None).await.unwrap();
+        assert_eq!(semantic[0].document_id, "rent_0");
+
+        // Leaning on keywords, the exact case number wins
+        let results = engine.search_hybrid(query, 5, 0.2, None).await.unwrap();
+        let ids: Vec<&str> = results.iter().map(|r| r.document_id.as_str()).collect();
+        assert_eq!(ids, vec!["citation_0", "rent_0"]);
+        assert!(results[0].score <= 1.0 && results[1].score > 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_deleted_document_no_longer_found() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let engine = RAGEngine::with_index_path(temp_dir.path().to_path_buf());
+        engine.set_embedding_backend(Arc::new(TopicBackend)).await;
+        let in_results = |results: &[SearchResult], doc_id: &str| {
+            results
+                .iter()
+                .any(|r| RAGEngine::parent_document_id(&r.document_id) == doc_id)
+        };
+
+        let lease = engine
+            .add_document(
+                "The rent is due on the first of each month.",
+                serde_json::json!({"filename": "lease.txt", "user_id": "alice"}),
+            )
+            .await
+            .unwrap();
+        let notice = engine
+            .add_document(
+                "Late rent may lead the landlord to terminate the lease.",
+                serde_json::json!({"filename": "notice.txt", "user_id": "bob"}),
+            )
+            .await
+            .unwrap();
+        assert!(in_results(
+            &engine.search("rent", None).await.unwrap(),
+            &lease
+        ));
+
+        engine.set_document_trust(&lease, 1.5).await.unwrap();
+
+        // Updating re-embeds under the same id, keeping owner and trust
+        let chunks = engine
+            .update_document(
+                &lease,
+                "The rent is due weekly.",
+                serde_json::json!({"filename": "lease.txt", "user_id": "mallory"}),
+            )
+            .await
+            .unwrap();
+        assert_eq!(chunks, 1);
+        let updated = engine.documents.read().await[&format!("{}_0", lease)]
+            .metadata
+            .clone();
+        assert_eq!(updated[OWNER_KEY], "alice");
+        assert_eq!(updated[TRUST_WEIGHT_KEY], 1.5);
+        let results = engine.search("rent", None).await.unwrap();
+        assert!(results
+            .iter()
+            .any(|r| r.content == "The rent is due weekly."));
+        assert!(!results
+            .iter()
+            .any(|r| r.content.contains("first of each month")));
+
+        engine.delete_document(&lease).await.unwrap();
+        assert!(!in_results(
+            &engine.search("rent", None).await.unwrap(),
+            &lease
+        ));
+        assert_eq!(engine.document_chunk_count(&lease).await, 0);
+        assert!(engine
+            .update_document(&lease, "rent", serde_json::json!({}))
+            .await
+            .is_err());
+
+        // Erasing a user's data cascades into their indexed documents only
+        let store = RwLock::new(engine);
+        let engine = store.read().await;
+        let again = engine
+            .add_document(
+                "Rent increases yearly.",
+                serde_json::json!({"user_id": "alice"}),
+            )
+            .await
+            .unwrap();
+        drop(engine);
+        assert_eq!(store.erase_user_data("alice").await.unwrap(), 1);
+        let results = store.read().await.search("rent", None).await.unwrap();
+        assert!(!in_results(&results, &again));
+        assert!(in_results(&results, &notice));
+    }
+
+    #[tokio::test]
+    async fn test_rechunk_after_chunk_size_change() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let engine = RAGEngine::with_index_path(temp_dir.path().to_path_buf());
+        engine.set_embedding_backend(Arc::new(TopicBackend)).await;
+
+        // No filename, so no stored version: the text is rebuilt from chunks
+        let words: Vec<String> = (0..100).map(|i| format!("clause{}", i)).collect();
+        let text = words.join(" ");
+        let doc_id = engine
+            .add_document(&text, serde_json::json!({"user_id": "alice"}))
+            .await
+            .unwrap();
+        assert_eq!(engine.document_chunk_count(&doc_id).await, 1);
+
+        let mut config = engine.get_config().await;
+        config.chunk_size = 20;
+        config.chunk_overlap = 5;
+        config.auto_rechunk = true;
+        engine.update_config(config).await.unwrap();
+        engine.set_embedding_backend(Arc::new(TopicBackend)).await;
+        let statistics = engine.get_index_statistics().await.unwrap();
+        assert!(statistics.mixed_chunking);
+        let config = engine.get_config().await;
+        assert_eq!(
+            engine.stale_chunking_documents(&config).await,
+            vec![doc_id.clone()]
+        );
+
+        let progress = std::sync::Mutex::new(Vec::new());
+        let report = engine
+            .rechunk_documents(|p| progress.lock().unwrap().push(p))
+            .await
+            .unwrap();
+        assert_eq!(report.documents, 1);
+        assert_eq!(report.chunks_before, 1);
+        assert_eq!(report.chunks_after, 7);
+        assert_eq!(engine.document_chunk_count(&doc_id).await, 7);
+        let progress = progress.into_inner().unwrap();
+        assert_eq!(progress.len(), 1);
+        assert_eq!((progress[0].processed, progress[0].total), (1, 1));
+        assert!(!engine.get_index_statistics().await.unwrap().mixed_chunking);
+
+        // The overlapping chunks still add up to the original text
+        let docs = engine.documents.read().await;
+        let mut chunks: Vec<&Document> = docs.values().collect();
+        chunks.sort_by_key(|doc| doc.chunk_index);
+        assert_eq!(
+            reassemble_chunks(chunks.iter().map(|doc| doc.content.as_str())),
+            text
+        );
+        assert_eq!(chunks[0].metadata["user_id"], "alice");
+        drop(docs);
+
+        let mut config = engine.get_config().await;
+        config.chunk_overlap = 20;
+        assert!(engine.update_config(config).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_chunks_without_recorded_chunking_are_not_mixed() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let engine = RAGEngine::with_index_path(temp_dir.path().to_path_buf());
+        seed(
+            &engine,
+            vec![chunk("lease_0", "Rent is due monthly.", vec![1.0, 0.0])],
+        )
+        .await;
+        engine.save_index().await.unwrap();
+
+        let engine = RAGEngine::with_index_path(temp_dir.path().to_path_buf());
+        engine.initialize().await.unwrap();
+        assert!(!engine.get_index_statistics().await.unwrap().mixed_chunking);
+
+        // They become stale like any other chunk once the chunking changes
+        let mut config = engine.get_config().await;
+        config.chunk_size = 20;
+        config.chunk_overlap = 5;
+        assert_eq!(
+            engine.stale_chunking_documents(&config).await,
+            vec!["lease".to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_index_reloaded_and_rebuilt_after_model_switch() {
+        async fn vector_hits(engine: &RAGEngine) -> usize {
+            let query = engine.query_embedding("rent").await.unwrap();
+            engine.vector_search(&query, 10, None).await.unwrap().len()
+        }
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        let engine = RAGEngine::with_index_path(temp_dir.path().to_path_buf());
+        engine.set_embedding_backend(Arc::new(TopicBackend)).await;
+        engine
+            .add_document("The rent is due monthly.", serde_json::json!({}))
+            .await
+            .unwrap();
+
+        // A new session finds the document without re-uploading it
+        let engine = RAGEngine::with_index_path(temp_dir.path().to_path_buf());
+        engine.initialize().await.unwrap();
+        engine.set_embedding_backend(Arc::new(TopicBackend)).await;
+        assert_eq!(vector_hits(&engine).await, 1);
+
+        // Vectors of the old model are excluded rather than compared
+        engine.config.write().await.embedding_model = "other-model".into();
+        engine.save_manifest().await.unwrap();
+        assert_eq!(engine.stale_vector_chunks().await, 1);
+        assert_eq!(vector_hits(&engine).await, 0);
+
+        // The switch survives a restart, with the chunk still flagged stale
+        let engine = RAGEngine::with_index_path(temp_dir.path().to_path_buf());
+        engine.initialize().await.unwrap();
+        engine.set_embedding_backend(Arc::new(TopicBackend)).await;
+        assert_eq!(engine.get_active_model().await, "other-model");
+        assert_eq!(engine.stale_vector_chunks().await, 1);
+
+        let report = engine.rebuild_index(&|_| {}).await.unwrap();
+        assert_eq!(report.chunks_re_embedded, 1);
+        assert_eq!(report.embedding_dimension, Some(3));
+        assert_eq!(engine.stale_vector_chunks().await, 0);
+        assert_eq!(vector_hits(&engine).await, 1);
+
+        // An index written by a newer format is refused instead of misread
+        tokio::fs::write(
+            temp_dir.path().join("index.json"),
+            r#"{"version": 99, "embedding_model": "other-model"}"#,
+        )
+        .await
+        .unwrap();
+        let engine = RAGEngine::with_index_path(temp_dir.path().to_path_buf());
+        assert!(engine.initialize().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_search_quality_kept_after_model_switch() {
+        async fn assert_top_results(engine: &RAGEngine) {
+            for (query, expected) in [
+                ("When is the rent due?", "rent.txt"),
+                ("How can the agreement be terminated?", "termination.txt"),
+                ("Where are disputes heard?", "venue.txt"),
+            ] {
+                let results = engine.search(query, Some(1)).await.unwrap();
+                assert_eq!(results[0].metadata["filename"], expected, "{}", query);
+            }
+        }
+        let temp_dir = tempfile::tempdir().unwrap();
+        let engine = RAGEngine::with_index_path(temp_dir.path().to_path_buf());
+        let mut config = engine.get_config().await;
+        config.enable_hybrid_search = false;
+        config.enable_reranking = false;
+        engine.update_config(config.clone()).await.unwrap();
+        engine.set_embedding_backend(Arc::new(TopicBackend)).await;
+
+        for (filename, content) in [
+            ("rent.txt", "The rent is due on the first of each month."),
+            ("termination.txt", "Either party may terminate with notice."),
+            ("venue.txt", "Disputes are heard in Amsterdam."),
+        ] {
+            engine
+                .add_document(content, serde_json::json!({ "filename": filename }))
+                .await
+                .unwrap();
+        }
+        assert_top_results(&engine).await;
+
+        // Without force the switch is refused and the old model stays active
+        let error = engine
+            .switch_embedding_model_with(
+                "reordered-model",
+                Arc::new(ReorderedTopicBackend),
+                false,
+                &|_| {},
+            )
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("3 indexed chunks"));
+        assert_eq!(engine.get_active_model().await, config.embedding_model);
+
+        let progress = std::sync::Mutex::new(Vec::new());
+        let report = engine
+            .switch_embedding_model_with(
+                "reordered-model",
+                Arc::new(ReorderedTopicBackend),
+                true,
+                &|p| progress.lock().unwrap().push(p.embedded),
+            )
+            .await
+            .unwrap();
+        assert_eq!(report.chunks_re_embedded, 3);
+        assert_eq!(report.embedding_dimension, Some(4));
+        assert_eq!(progress.into_inner().unwrap().last(), Some(&3));
+        assert_eq!(engine.get_active_model().await, "reordered-model");
+        assert_eq!(engine.stale_vector_chunks().await, 0);
+
+        // Queries embedded with the new model still find the right documents
+        assert_top_results(&engine).await;
+    }
+
+    #[tokio::test]
+    async fn test_query_expansion_surfaces_missed_chunk() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let engine = RAGEngine::with_index_path(temp_dir.path().to_path_buf());
+        engine.set_embedding_backend(Arc::new(TopicBackend)).await;
+        let termination = engine
+            .add_document(
+                "Either party may terminate with thirty days notice.",
+                serde_json::json!({}),
+            )
+            .await
+            .unwrap();
+        engine
+            .add_document("Parking space 4 is assigned to the unit.", serde_json::json!({}))
+            .await
+            .unwrap();
+        let found = |results: &[SearchResult]| {
+            results
+                .iter()
+                .any(|r| RAGEngine::parent_document_id(&r.document_id) == termination)
+        };
+        let paraphraser = MockCompletion::replies(vec!["1. Can the lease be terminated early?"]);
+
+        let query = "Can the landlord end the lease early?";
+        assert!(!found(&engine.search(query, None).await.unwrap()));
+
+        // Disabled by default: the paraphraser is not consulted
+        let results = engine
+            .search_expanded(query, None, Some(&paraphraser), None)
+            .await
+            .unwrap();
+        assert!(!found(&results));
+        assert!(paraphraser.prompts().is_empty());
+
+        let mut config = engine.get_config().await;
+        config.expand_query = true;
+        config.query_paraphrases = 2;
+        engine.update_config(config).await.unwrap();
+        engine.set_embedding_backend(Arc::new(TopicBackend)).await;
+
+        let results = engine
+            .search_expanded(query, None, Some(&paraphraser), None)
+            .await
+            .unwrap();
+        assert!(found(&results));
+        // Fusion orders the results without replacing their similarity scores
+        let fused: Vec<f32> = results.iter().map(|r| r.fused_score.unwrap()).collect();
+        assert!(fused.windows(2).all(|pair| pair[0] >= pair[1]));
+        let termination_result = results
+            .iter()
+            .find(|r| RAGEngine::parent_document_id(&r.document_id) == termination)
+            .unwrap();
+        assert!(termination_result.score >= 0.7);
+        assert!(termination_result.fused_score.unwrap() < 0.1);
+        let prompts = paraphraser.prompts();
+        assert_eq!(prompts.len(), 1);
+        assert!(prompts[0].contains("in 2 different ways"));
+        let ids: HashSet<&str> = results.iter().map(|r| r.document_id.as_str()).collect();
+        assert_eq!(ids.len(), results.len());
+    }
+
+    /// Three rent clauses, one per file type, returning their document ids
+    async fn seed_file_types(engine: &RAGEngine) -> Vec<String> {
+        engine.set_embedding_backend(Arc::new(TopicBackend)).await;
+        let mut ids = Vec::new();
+        for (file_type, text) in [
+            ("pdf", "Rent is due on the first of each month."),
+            ("docx", "Late rent incurs a fee of five percent."),
+            ("txt", "Rent may be paid by bank transfer."),
+        ] {
+            let metadata = serde_json::json!({"file_type": file_type});
+            ids.push(engine.add_document(text, metadata).await.unwrap());
+        }
+        ids
+    }
+
+    fn file_types(results: &[SearchResult]) -> Vec<&str> {
+        let mut types: Vec<&str> = results
+            .iter()
+            .filter_map(|r| r.metadata["file_type"].as_str())
+            .collect();
+        types.sort();
+        types
+    }
+
+    #[tokio::test]
+    async fn test_equality_filter_scopes_search_before_ranking() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let engine = RAGEngine::with_index_path(temp_dir.path().to_path_buf());
+        let ids = seed_file_types(&engine).await;
+
+        // Limit 1 still yields the docx chunk: the filter applies before top-k
+        let filter = MetadataFilter::from_json(&serde_json::json!({"file_type": "docx"})).unwrap();
+        let results = engine
+            .search_filtered("rent", Some(1), Some(&filter))
+            .await
+            .unwrap();
+        assert_eq!(file_types(&results), vec!["docx"]);
+
+        let filter =
+            MetadataFilter::from_json(&serde_json::json!({"document_id": ids[2]})).unwrap();
+        let results = engine
+            .search_filtered("rent", None, Some(&filter))
+            .await
+            .unwrap();
+        assert_eq!(file_types(&results), vec!["txt"]);
+
+        let filter = MetadataFilter::from_json(&serde_json::json!({"file_type": "xlsx"})).unwrap();
+        let results = engine
+            .search_filtered("rent", None, Some(&filter))
+            .await
+            .unwrap();
+        assert!(results.is_empty());
+
+        assert!(MetadataFilter::from_json(&serde_json::json!(["pdf"])).is_err());
+        let range = serde_json::json!({"file_type": {"$gt": "a"}});
+        assert!(MetadataFilter::from_json(&range).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_in_filter_matches_any_listed_value() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let engine = RAGEngine::with_index_path(temp_dir.path().to_path_buf());
+        seed_file_types(&engine).await;
+        assert_eq!(
+            file_types(&engine.search("rent", None).await.unwrap()),
+            vec!["docx", "pdf", "txt"]
+        );
+
+        let filter =
+            MetadataFilter::from_json(&serde_json::json!({"file_type": {"$in": ["pdf", "txt"]}}))
+                .unwrap();
+        let results = engine
+            .search_expanded("rent", None, None, Some(&filter))
+            .await
+            .unwrap();
+        assert_eq!(file_types(&results), vec!["pdf", "txt"]);
+
+        // Only the docx chunk matches by keyword, and it is filtered out
+        let results = engine.search("late fee", None).await.unwrap();
+        assert_eq!(file_types(&results), vec!["docx"]);
+        let results = engine
+            .search_filtered("late fee", None, Some(&filter))
+            .await
+            .unwrap();
+        assert!(results.is_empty(), "{:?}", file_types(&results));
+    }
+
+    #[tokio::test]
+    async fn test_trusted_source_outranks_closer_match() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let engine = RAGEngine::with_index_path(temp_dir.path().to_path_buf());
+        let mut config = engine.get_config().await;
+        config.enable_hybrid_search = false;
+        config.enable_reranking = false;
+        engine.update_config(config).await.unwrap();
+        engine.set_embedding_backend(Arc::new(TopicBackend)).await;
+
+        engine
+            .add_document(
+                "Forum post: rent is always due on the first.",
+                serde_json::json!({"filename": "forum.txt"}),
+            )
+            .await
+            .unwrap();
+        let statute = engine
+            .add_document(
+                "Statute: rent is due as agreed and the landlord may terminate.",
+                serde_json::json!({"filename": "statute.txt", "trust_weight": 0.9}),
+            )
+            .await
+            .unwrap();
+
+        let results = engine.search("rent", None).await.unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].metadata["filename"], "forum.txt");
+        assert_eq!(results[0].trust_weight, 1.0);
+        assert_eq!(results[1].trust_weight, 0.9);
+
+        assert_eq!(engine.set_document_trust(&statute, 1.5).await.unwrap(), 1);
+        let results = engine.search("rent", None).await.unwrap();
+        assert_eq!(results[0].metadata["filename"], "statute.txt");
+        assert_eq!(results[0].trust_weight, 1.5);
+        assert!(results[0].score > results[1].score);
+
+        // Weighted before the cut-off, so the trusted source survives a limit of one
+        let results = engine.search("rent", Some(1)).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].metadata["filename"], "statute.txt");
+
+        assert!(engine.set_document_trust(&statute, 10.0).await.is_err());
+        assert!(engine
+            .add_document("rent", serde_json::json!({"trust_weight": "high"}))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_reduced_embeddings_keep_ranking() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let engine = RAGEngine::with_index_path(temp_dir.path().to_path_buf());
+        let mut config = engine.get_config().await;
+        config.enable_hybrid_search = false;
+        config.enable_reranking = false;
+        config.dimension_reduction = DimensionReduction::Truncate { dimensions: 2 };
+        engine.update_config(config.clone()).await.unwrap();
+        engine.set_embedding_backend(Arc::new(TopicBackend)).await;
+
+        for (content, filename) in [
+            ("Tenant pays rent monthly.", "rent.txt"),
+            ("Either party may terminate the lease.", "termination.txt"),
+            ("The parties met for coffee.", "coffee.txt"),
+        ] {
+            engine
+                .add_document(content, serde_json::json!({ "filename": filename }))
+                .await
+                .unwrap();
+        }
+        assert!(engine
+            .documents
+            .read()
+            .await
+            .values()
+            .all(|doc| doc.embeddings.len() == 2));
+
+        let results = engine.search("rent", None).await.unwrap();
+        assert_eq!(results[0].metadata["filename"], "rent.txt");
+
+        config.dimension_reduction = DimensionReduction::Pca { dimensions: 2 };
+        assert!(engine.update_config(config).await.is_err());
+
+        let report = engine
+            .set_dimension_reduction(DimensionReduction::Pca { dimensions: 2 }, true)
+            .await
+            .unwrap();
+        assert_eq!(report.chunks_re_embedded, 3);
+        assert_eq!(report.source_dimension, Some(3));
+        assert_eq!(report.stored_dimension, Some(2));
+        assert_eq!(report.bytes_saved, 3 * 4);
+
+        let results = engine.search("rent", None).await.unwrap();
+        assert_eq!(results[0].metadata["filename"], "rent.txt");
+        assert!(temp_dir.path().join("projection.json").exists());
+    }
+
+    #[tokio::test]
+    async fn test_pca_fitted_on_corpus_once_enough_chunks_are_indexed() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let engine = RAGEngine::with_index_path(temp_dir.path().to_path_buf());
+        let mut config = engine.get_config().await;
+        config.enable_hybrid_search = false;
+        config.enable_reranking = false;
+        engine.update_config(config).await.unwrap();
+        engine.set_embedding_backend(Arc::new(TopicBackend)).await;
+
+        // Nothing to fit on yet, so the index stays at full dimension
+        let report = engine
+            .set_dimension_reduction(DimensionReduction::Pca { dimensions: 2 }, false)
+            .await
+            .unwrap();
+        assert_eq!(report.chunks_re_embedded, 0);
+
+        engine
+            .add_document(
+                "Tenant pays rent monthly.",
+                serde_json::json!({"filename": "rent.txt"}),
+            )
+            .await
+            .unwrap();
+        assert!(engine.projection.read().await.is_none());
+        assert!(engine
+            .documents
+            .read()
+            .await
+            .values()
+            .all(|doc| doc.embeddings.len() == 3));
+
+        // The second chunk makes the corpus large enough; both chunks are projected
+        engine
+            .add_document(
+                "Either party may terminate the lease.",
+                serde_json::json!({"filename": "termination.txt"}),
+            )
+            .await
+            .unwrap();
+        assert!(engine.projection.read().await.is_some());
+        assert!(engine
+            .documents
+            .read()
+            .await
+            .values()
+            .all(|doc| doc.embeddings.len() == 2));
+
+        let results = engine.search("rent", None).await.unwrap();
+        assert_eq!(results[0].metadata["filename"], "rent.txt");
+    }
+
+    #[tokio::test]
+    async fn test_reingested_revision_reports_changes_and_replaces_chunks() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let engine = RAGEngine::with_index_path(temp_dir.path().to_path_buf());
+        let mut config = engine.get_config().await;
+        config.enable_hybrid_search = false;
+        config.enable_reranking = false;
+        config.merge_overlapping_citations = false;
+        engine.update_config(config).await.unwrap();
+        engine.set_embedding_backend(Arc::new(TopicBackend)).await;
+
+        let metadata = serde_json::json!({"filename": "lease.txt", "matter": "M-17"});
+        let original = "Tenant pays rent monthly. Either party may terminate with notice.";
+        let previous_id = engine
+            .add_document(original, metadata.clone())
+            .await
+            .unwrap();
+        assert!(engine
+            .compare_with_prior_version(original, &metadata)
+            .await
+            .unwrap()
+            .is_some_and(|report| !report.content_changed));
+        let other_matter = serde_json::json!({"filename": "lease.txt", "matter": "M-18"});
+        assert!(engine
+            .compare_with_prior_version(original, &other_matter)
+            .await
+            .unwrap()
+            .is_none());
+
+        let revised = "Tenant pays rent weekly. Either party may terminate with notice. \
+                       The parties met for coffee.";
+        let report = engine
+            .compare_with_prior_version(revised, &metadata)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(report.content_changed);
+        assert_eq!(report.previous_document_id, previous_id);
+        assert_eq!((report.modified, report.added, report.removed), (1, 1, 0));
+        let modified = report
+            .changes
+            .iter()
+            .find(|c| c.kind == ChangeKind::Modified)
+            .unwrap();
+        assert_eq!(
+            modified.previous.as_deref(),
+            Some("Tenant pays rent monthly.")
+        );
+        assert_eq!(
+            modified.current.as_deref(),
+            Some("Tenant pays rent weekly.")
+        );
+
+        let current_id = engine
+            .add_document(revised, metadata.clone())
+            .await
+            .unwrap();
+        engine
+            .delete_document(&report.previous_document_id)
+            .await
+            .unwrap();
+        assert_eq!(engine.document_chunk_count(&previous_id).await, 0);
+        assert!(engine.document_chunk_count(&current_id).await > 0);
+        let results = engine.search("rent", None).await.unwrap();
+        assert!(!results.is_empty());
+        assert!(results.iter().all(|r| r.content.contains("weekly")));
+
+        let next = engine
+            .compare_with_prior_version(revised, &metadata)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(next.previous_document_id, current_id);
+    }
+
+    #[tokio::test]
+    async fn test_add_document_embeds_in_batches() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let engine = RAGEngine::with_index_path(temp_dir.path().to_path_buf());
+        let mut config = engine.get_config().await;
+        config.chunk_size = 10;
+        config.chunk_overlap = 0;
+        config.embedding_batch_size = 4;
+        config.embedding_parallelism = 3;
+        engine.update_config(config).await.unwrap();
+
+        let backend = Arc::new(RecordingBackend {
+            batch_sizes: std::sync::Mutex::new(Vec::new()),
+        });
+        engine.set_embedding_backend(backend.clone()).await;
+
+        // 100 words at 10 words per chunk -> 10 chunks
+        let content = (0..100)
+            .map(|i| format!("word{}", i))
+            .collect::<Vec<_>>()
+            .join(" ");
+        engine
+            .add_document(&content, serde_json::json!({"filename": "long.txt"}))
+            .await
+            .unwrap();
+
+        let mut batch_sizes = backend.batch_sizes.lock().unwrap().clone();
+        batch_sizes.sort_unstable();
+        assert_eq!(batch_sizes, vec![2, 4, 4]);
+
+        let documents = engine.documents.read().await;
+        assert_eq!(documents.len(), 10);
+        assert!(documents.values().all(|d| d.embeddings.len() == 2));
+        for doc in documents.values() {
+            assert_eq!(doc.embeddings[0], doc.content.len() as f32);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retrieved_chunk_carries_ingest_provenance() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let engine = RAGEngine::with_index_path(temp_dir.path().to_path_buf());
+        engine.set_embedding_backend(Arc::new(TopicBackend)).await;
+        let original = b"The rent of jane@example.com is due monthly.";
+        let source =
+            IngestSource::new(original, ExtractionMethod::Ocr, vec!["[EMAIL]".to_string()]);
+
+        let before = chrono::Utc::now().timestamp();
+        let doc_id = engine
+            .add_document_from_source(
+                "The rent of [EMAIL] is due monthly.",
+                serde_json::json!({"filename": "lease.pdf"}),
+                source.clone(),
+            )
+            .await
+            .unwrap();
+
+        let results = engine.search("rent", None).await.unwrap();
+        let provenance = results[0].provenance.clone().unwrap();
+        assert_eq!(provenance.document_id, doc_id);
+        assert_eq!(provenance.source_sha256, Some(source.source_sha256));
+        assert_eq!(provenance.source_sha256.as_deref().map(str::len), Some(64));
+        assert_eq!(provenance.extraction_method, Some(ExtractionMethod::Ocr));
+        assert_eq!(provenance.pii_redacted, Some(true));
+        assert_eq!(
+            provenance.embedding_model,
+            Some(engine.get_active_model().await)
+        );
+        assert!(provenance.ingested_at >= before);
+        assert_eq!(
+            engine.chunk_provenance(&provenance.chunk_id).await.unwrap(),
+            provenance
+        );
+
+        // Chunks added without a source say so rather than guessing
+        let pasted = engine
+            .add_document("Either party may terminate.", serde_json::json!({}))
+            .await
+            .unwrap();
+        let pasted = engine
+            .chunk_provenance(&format!("{}_0", pasted))
+            .await
+            .unwrap();
+        assert_eq!(pasted.source_sha256, None);
+        assert_eq!(pasted.pii_redacted, None);
+        assert!(engine.chunk_provenance("missing_0").await.is_err());
+
+        // Updating the text keeps the source it was ingested from
+        engine
+            .update_document(
+                &doc_id,
+                "The rent of [EMAIL] is due quarterly.",
+                serde_json::json!({"filename": "lease.pdf"}),
+            )
+            .await
+            .unwrap();
+        let updated = engine
+            .chunk_provenance(&format!("{}_0", doc_id))
+            .await
+            .unwrap();
+        assert_eq!(updated.source_sha256, provenance.source_sha256);
+        assert_eq!(updated.extraction_method, Some(ExtractionMethod::Ocr));
+    }
+}
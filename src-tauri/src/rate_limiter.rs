@@ -0,0 +1,547 @@
+/// Sliding-window request limits per user, under a shared global ceiling
+///
+/// Each user (or session) gets its own bucket so one busy user cannot starve
+/// the others on a shared install, while the global ceiling still protects
+/// the machine when many users are active at once. Actions can additionally be
+/// limited by key prefix (e.g. `upload_document`), so expensive operations are
+/// throttled harder than chat.
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateLimitConfig {
+    /// Requests one user may make per window, unless overridden in `user_limits`
+    pub max_requests_per_user: usize,
+    /// Requests all users together may make per window
+    pub global_max_requests: usize,
+    pub window_seconds: u64,
+    /// Per-user overrides of `max_requests_per_user`
+    #[serde(default)]
+    pub user_limits: HashMap<String, usize>,
+    /// Limits for actions whose key starts with the given prefix, applied per
+    /// user on top of the user and global limits. The longest prefix wins.
+    #[serde(default)]
+    pub action_limits: HashMap<String, ActionLimit>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActionLimit {
+    pub capacity: usize,
+    pub window_seconds: u64,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            max_requests_per_user: 100,
+            global_max_requests: 1000,
+            window_seconds: 60,
+            user_limits: HashMap::new(),
+            action_limits: HashMap::from([
+                (
+                    "process_document".to_string(),
+                    ActionLimit {
+                        capacity: 20,
+                        window_seconds: 60,
+                    },
+                ),
+                (
+                    "upload_document".to_string(),
+                    ActionLimit {
+                        capacity: 20,
+                        window_seconds: 60,
+                    },
+                ),
+            ]),
+        }
+    }
+}
+
+impl RateLimitConfig {
+    pub fn limit_for(&self, user_id: &str) -> usize {
+        self.user_limits
+            .get(user_id)
+            .copied()
+            .unwrap_or(self.max_requests_per_user)
+    }
+
+    /// The configured prefix matching `action`, with its limit
+    pub fn action_limit_for(&self, action: &str) -> Option<(&str, ActionLimit)> {
+        self.action_limits
+            .iter()
+            .filter(|(prefix, _)| action.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(prefix, limit)| (prefix.as_str(), *limit))
+    }
+}
+
+/// A refused request, with how long to wait before the next one is accepted
+#[derive(Debug, Clone)]
+pub struct RateLimitExceeded {
+    pub retry_after: Duration,
+    reason: String,
+}
+
+impl RateLimitExceeded {
+    /// Whole seconds to wait, rounded up so a countdown never ends early
+    pub fn retry_after_seconds(&self) -> u64 {
+        self.retry_after.as_secs() + u64::from(self.retry_after.subsec_nanos() > 0)
+    }
+}
+
+impl fmt::Display for RateLimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}; retry after {}s",
+            self.reason,
+            self.retry_after_seconds()
+        )
+    }
+}
+
+impl std::error::Error for RateLimitExceeded {}
+
+/// Current standing of one user's bucket
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateLimitUsage {
+    pub user_id: String,
+    pub used: usize,
+    pub limit: usize,
+    pub remaining: usize,
+    /// Seconds until the oldest counted request leaves the window
+    pub reset_in_seconds: u64,
+}
+
+#[derive(Default)]
+struct Buckets {
+    per_user: HashMap<String, VecDeque<Instant>>,
+    /// Keyed by (user, action prefix)
+    per_action: HashMap<(String, String), VecDeque<Instant>>,
+    global: VecDeque<Instant>,
+}
+
+pub struct RateLimiter {
+    config: Mutex<RateLimitConfig>,
+    buckets: Mutex<Buckets>,
+    /// Where configured limits are saved, if they persist across restarts
+    config_path: Option<PathBuf>,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(RateLimitConfig::default())
+    }
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config: Mutex::new(config),
+            buckets: Mutex::new(Buckets::default()),
+            config_path: None,
+        }
+    }
+
+    /// Limiter whose configured limits are saved to `path` and loaded from it
+    /// on the next start; the defaults apply when nothing valid is saved
+    pub fn with_config_file(path: PathBuf) -> Self {
+        let config = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<RateLimitConfig>(&content).ok())
+            .filter(|config| validate(config).is_ok())
+            .unwrap_or_default();
+        Self {
+            config_path: Some(path),
+            ..Self::new(config)
+        }
+    }
+
+    fn save(&self, config: &RateLimitConfig) -> Result<()> {
+        if let Some(path) = &self.config_path {
+            let content = serde_json::to_string_pretty(config)?;
+            std::fs::write(path, content)
+                .map_err(|e| anyhow!("Failed to save rate limits: {}", e))?;
+        }
+        Ok(())
+    }
+
+    pub fn get_config(&self) -> RateLimitConfig {
+        self.config
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    pub fn update_config(&self, config: RateLimitConfig) -> Result<()> {
+        validate(&config)?;
+        let mut current = self.config.lock().unwrap_or_else(|e| e.into_inner());
+        self.save(&config)?;
+        *current = config;
+        Ok(())
+    }
+
+    /// Restore the default limits and forget every counted request
+    pub fn reset(&self) -> usize {
+        *self.config.lock().unwrap_or_else(|e| e.into_inner()) = RateLimitConfig::default();
+        if let Some(path) = self.config_path.as_ref().filter(|path| path.exists()) {
+            if let Err(e) = std::fs::remove_file(path) {
+                tracing::warn!(error = %e, "Failed to delete saved rate limits");
+            }
+        }
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        let tracked = buckets.per_user.len();
+        *buckets = Buckets::default();
+        tracked
+    }
+
+    /// Limit actions whose key starts with `prefix` to `capacity` requests per
+    /// user in any `window`
+    pub fn configure(&self, prefix: &str, capacity: usize, window: Duration) -> Result<()> {
+        if prefix.is_empty() {
+            return Err(anyhow!("Rate limit prefix must not be empty"));
+        }
+        let mut current = self.config.lock().unwrap_or_else(|e| e.into_inner());
+        let mut config = current.clone();
+        config.action_limits.insert(
+            prefix.to_string(),
+            ActionLimit {
+                capacity,
+                window_seconds: window.as_secs(),
+            },
+        );
+        validate(&config)?;
+        self.save(&config)?;
+        *current = config;
+        Ok(())
+    }
+
+    /// Count a request from `user_id`, or refuse it if the user's bucket, the
+    /// action's bucket or the global ceiling is full. Refused requests are not
+    /// counted; the error is a [`RateLimitExceeded`] carrying the retry-after.
+    pub fn check_rate_limit(&self, user_id: &str, action: &str) -> Result<()> {
+        self.check_at(user_id, action, Instant::now())
+    }
+
+    fn check_at(&self, user_id: &str, action: &str, now: Instant) -> Result<()> {
+        let config = self.get_config();
+        let window = Duration::from_secs(config.window_seconds);
+        let mut guard = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        let buckets = &mut *guard;
+
+        prune(&mut buckets.global, now, window);
+        if buckets.global.len() >= config.global_max_requests {
+            tracing::warn!(action, "Global rate limit reached");
+            return Err(RateLimitExceeded {
+                retry_after: retry_after(&buckets.global, now, window),
+                reason: "Too many requests across all users".to_string(),
+            }
+            .into());
+        }
+
+        let limit = config.limit_for(user_id);
+        let user_bucket = buckets.per_user.entry(user_id.to_string()).or_default();
+        prune(user_bucket, now, window);
+        if user_bucket.len() >= limit {
+            tracing::warn!(user_id, action, limit, "User rate limit reached");
+            return Err(RateLimitExceeded {
+                retry_after: retry_after(user_bucket, now, window),
+                reason: format!(
+                    "Rate limit of {} requests per {}s reached",
+                    limit, config.window_seconds
+                ),
+            }
+            .into());
+        }
+
+        let action_bucket = match config.action_limit_for(action) {
+            Some((prefix, action_limit)) => {
+                let action_window = Duration::from_secs(action_limit.window_seconds);
+                let bucket = buckets
+                    .per_action
+                    .entry((user_id.to_string(), prefix.to_string()))
+                    .or_default();
+                prune(bucket, now, action_window);
+                if bucket.len() >= action_limit.capacity {
+                    tracing::warn!(user_id, action, prefix, "Action rate limit reached");
+                    return Err(RateLimitExceeded {
+                        retry_after: retry_after(bucket, now, action_window),
+                        reason: format!(
+                            "Rate limit of {} '{}' requests per {}s reached",
+                            action_limit.capacity, prefix, action_limit.window_seconds
+                        ),
+                    }
+                    .into());
+                }
+                Some(bucket)
+            }
+            None => None,
+        };
+
+        if let Some(bucket) = action_bucket {
+            bucket.push_back(now);
+        }
+        if let Some(bucket) = buckets.per_user.get_mut(user_id) {
+            bucket.push_back(now);
+        }
+        buckets.global.push_back(now);
+        // Idle users' empty buckets are dropped to keep the maps bounded
+        buckets.per_user.retain(|_, bucket| {
+            prune(bucket, now, window);
+            !bucket.is_empty()
+        });
+        buckets.per_action.retain(|(_, prefix), bucket| {
+            let action_window = config
+                .action_limits
+                .get(prefix)
+                .map(|limit| Duration::from_secs(limit.window_seconds))
+                .unwrap_or(window);
+            prune(bucket, now, action_window);
+            !bucket.is_empty()
+        });
+        Ok(())
+    }
+
+    pub fn get_usage(&self, user_id: &str) -> RateLimitUsage {
+        let config = self.get_config();
+        let window = Duration::from_secs(config.window_seconds);
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+
+        let limit = config.limit_for(user_id);
+        let (used, reset_in_seconds) = match buckets.per_user.get_mut(user_id) {
+            Some(bucket) => {
+                prune(bucket, now, window);
+                let reset = bucket
+                    .front()
+                    .map(|oldest| (*oldest + window).saturating_duration_since(now).as_secs())
+                    .unwrap_or(0);
+                (bucket.len(), reset)
+            }
+            None => (0, 0),
+        };
+
+        RateLimitUsage {
+            user_id: user_id.to_string(),
+            used,
+            limit,
+            remaining: limit.saturating_sub(used),
+            reset_in_seconds,
+        }
+    }
+}
+
+/// Reject windows shorter than a second, and action limits of zero requests,
+/// which would refuse the action forever behind a retry-after that never ends
+fn validate(config: &RateLimitConfig) -> Result<()> {
+    if config.window_seconds == 0 {
+        return Err(anyhow!("Rate limit window must be at least one second"));
+    }
+    for (prefix, limit) in &config.action_limits {
+        if limit.window_seconds == 0 {
+            return Err(anyhow!(
+                "Rate limit window for '{}' must be at least one second",
+                prefix
+            ));
+        }
+        if limit.capacity == 0 {
+            return Err(anyhow!(
+                "Rate limit for '{}' must allow at least one request",
+                prefix
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Time until the oldest counted request leaves the window
+fn retry_after(bucket: &VecDeque<Instant>, now: Instant, window: Duration) -> Duration {
+    bucket
+        .front()
+        .map(|oldest| (*oldest + window).saturating_duration_since(now))
+        .unwrap_or(window)
+}
+
+fn prune(bucket: &mut VecDeque<Instant>, now: Instant, window: Duration) {
+    while bucket
+        .front()
+        .is_some_and(|oldest| now.duration_since(*oldest) >= window)
+    {
+        bucket.pop_front();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_users_have_independent_buckets_under_global_ceiling() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            max_requests_per_user: 2,
+            global_max_requests: 5,
+            window_seconds: 60,
+            user_limits: HashMap::from([("carol".to_string(), 3)]),
+            action_limits: HashMap::new(),
+        });
+        let start = Instant::now();
+
+        assert!(limiter.check_at("alice", "send_message", start).is_ok());
+        assert!(limiter.check_at("alice", "send_message", start).is_ok());
+        assert!(limiter.check_at("alice", "send_message", start).is_err());
+
+        // Alice being throttled leaves Bob's bucket untouched
+        assert!(limiter.check_at("bob", "send_message", start).is_ok());
+        assert_eq!(limiter.get_usage("alice").remaining, 0);
+        assert_eq!(limiter.get_usage("bob").remaining, 1);
+
+        // Carol's override would allow a third request, but the global ceiling is hit
+        assert!(limiter.check_at("carol", "send_message", start).is_ok());
+        assert!(limiter.check_at("carol", "send_message", start).is_ok());
+        assert!(limiter.check_at("carol", "send_message", start).is_err());
+
+        // Once the window has passed, Alice may send again
+        let later = start + Duration::from_secs(61);
+        assert!(limiter.check_at("alice", "send_message", later).is_ok());
+    }
+
+    fn upload_limiter() -> RateLimiter {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            action_limits: HashMap::new(),
+            ..RateLimitConfig::default()
+        });
+        limiter
+            .configure("upload_document", 3, Duration::from_secs(10))
+            .unwrap();
+        limiter
+    }
+
+    fn retry_after_of(result: Result<()>) -> u64 {
+        result
+            .unwrap_err()
+            .downcast::<RateLimitExceeded>()
+            .unwrap()
+            .retry_after_seconds()
+    }
+
+    #[test]
+    fn test_action_limit_burst() {
+        let limiter = upload_limiter();
+        let start = Instant::now();
+
+        // Three uploads of different files may burst, the fourth must wait
+        for path in ["a.pdf", "b.pdf", "c.pdf"] {
+            let action = format!("upload_document:{}", path);
+            assert!(limiter.check_at("alice", &action, start).is_ok());
+        }
+        let refused = limiter.check_at("alice", "upload_document:d.pdf", start);
+        assert_eq!(retry_after_of(refused), 10);
+
+        // Chat is not throttled by the upload limit, nor are other users
+        assert!(limiter.check_at("alice", "send_message", start).is_ok());
+        assert!(limiter
+            .check_at("bob", "upload_document:a.pdf", start)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_action_limit_steady_state() {
+        let limiter = upload_limiter();
+        let start = Instant::now();
+
+        // One upload every 4s stays under 3 per 10s indefinitely
+        for i in 0..20 {
+            let at = start + Duration::from_secs(4 * i);
+            assert!(limiter.check_at("alice", "upload_document:x", at).is_ok());
+        }
+
+        // Going faster is refused until the oldest request in the window expires
+        let at = start + Duration::from_secs(80);
+        assert!(limiter.check_at("alice", "upload_document:x", at).is_ok());
+        let refused = limiter.check_at(
+            "alice",
+            "upload_document:x",
+            at + Duration::from_millis(500),
+        );
+        assert_eq!(retry_after_of(refused), 2);
+    }
+
+    #[test]
+    fn test_action_limit_window_reset() {
+        let limiter = upload_limiter();
+        let start = Instant::now();
+        for _ in 0..3 {
+            limiter
+                .check_at("alice", "upload_document:x", start)
+                .unwrap();
+        }
+        let refused =
+            limiter.check_at("alice", "upload_document:x", start + Duration::from_secs(4));
+        assert_eq!(retry_after_of(refused), 6);
+        assert!(limiter
+            .check_at("alice", "upload_document:x", start + Duration::from_secs(9))
+            .is_err());
+
+        // A full window after the burst the bucket is empty again
+        let reset = start + Duration::from_secs(10);
+        for _ in 0..3 {
+            assert!(limiter
+                .check_at("alice", "upload_document:x", reset)
+                .is_ok());
+        }
+
+        // The longest configured prefix wins
+        limiter
+            .configure("upload_document:archive", 1, Duration::from_secs(60))
+            .unwrap();
+        let later = start + Duration::from_secs(30);
+        assert!(limiter
+            .check_at("alice", "upload_document:archive.zip", later)
+            .is_ok());
+        assert!(limiter
+            .check_at("alice", "upload_document:archive.zip", later)
+            .is_err());
+        assert!(limiter
+            .check_at("alice", "upload_document:y", later)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_configured_limits_persist_and_reject_zero_capacity() {
+        let path = std::env::temp_dir().join(format!("rate_limits_{}.json", uuid::Uuid::new_v4()));
+        let limiter = RateLimiter::with_config_file(path.clone());
+
+        let err = limiter
+            .configure("upload_document", 0, Duration::from_secs(10))
+            .unwrap_err();
+        assert!(err.to_string().contains("at least one request"));
+        assert_eq!(
+            limiter.get_config().action_limits["upload_document"].capacity,
+            20
+        );
+
+        limiter
+            .configure("export_", 2, Duration::from_secs(30))
+            .unwrap();
+        let restarted = RateLimiter::with_config_file(path.clone());
+        assert_eq!(
+            restarted.get_config().action_limits["export_"],
+            ActionLimit {
+                capacity: 2,
+                window_seconds: 30
+            }
+        );
+
+        restarted.reset();
+        assert!(!path.exists());
+        assert!(!RateLimiter::with_config_file(path)
+            .get_config()
+            .action_limits
+            .contains_key("export_"));
+    }
+}
@@ -0,0 +1,398 @@
+/// Read-only SQL over the application database
+///
+/// Backs the MCP `execute_sql` tool and the `execute_sql_query` command.
+/// Writes stay blocked at three layers: the statement must parse as exactly
+/// one `SELECT` (so stacked statements, `ATTACH`, `PRAGMA` and DML are
+/// rejected before SQLite sees them), the connection is opened with
+/// `SQLITE_OPEN_READ_ONLY`, and the query runs under a row limit and a time
+/// budget enforced by SQLite's progress handler. Only the tables in
+/// `SQL_QUERYABLE_TABLES` can be read, and callers redact PII from the
+/// returned cells with `SqlRows::redact_pii`.
+use crate::constants::{
+    SQL_MAX_ROWS, SQL_PROGRESS_HANDLER_OPS, SQL_QUERYABLE_TABLES, SQL_QUERY_BUDGET_MS,
+};
+use crate::pii_detector::PIIDetector;
+use anyhow::{anyhow, bail, Result};
+use rusqlite::types::ValueRef;
+use rusqlite::{Connection, ErrorCode, OpenFlags, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value as JsonValue};
+use sqlparser::ast::{Expr, Query, SetExpr, Statement, Value};
+use sqlparser::dialect::SQLiteDialect;
+use sqlparser::parser::Parser;
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// Result of a read-only query, one JSON object per row keyed by column name
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SqlRows {
+    pub columns: Vec<String>,
+    pub rows: Vec<Map<String, JsonValue>>,
+    /// The row limit was hit, so more rows may match
+    pub truncated: bool,
+}
+
+impl SqlRows {
+    /// Replace PII in every text cell with placeholders
+    pub async fn redact_pii(&mut self, detector: &PIIDetector) -> Result<()> {
+        for row in &mut self.rows {
+            for value in row.values_mut() {
+                if let JsonValue::String(text) = value {
+                    *text = detector.redact_pii(text, None).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct ReadOnlySql {
+    db_path: PathBuf,
+    max_rows: usize,
+    time_budget: Duration,
+    allowed_tables: Vec<String>,
+}
+
+impl ReadOnlySql {
+    pub fn new(db_path: PathBuf) -> Self {
+        Self {
+            db_path,
+            max_rows: SQL_MAX_ROWS,
+            time_budget: Duration::from_millis(SQL_QUERY_BUDGET_MS),
+            allowed_tables: SQL_QUERYABLE_TABLES.iter().map(|t| t.to_string()).collect(),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn with_time_budget(mut self, time_budget: Duration) -> Self {
+        self.time_budget = time_budget;
+        self
+    }
+
+    /// Validate `sql` and run it on a read-only connection
+    pub fn execute(&self, sql: &str) -> Result<SqlRows> {
+        // One row past the limit tells whether the result was cut off
+        let sql = validate_select(sql, self.max_rows + 1)?;
+
+        let conn = Connection::open_with_flags(
+            &self.db_path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )?;
+        conn.busy_timeout(self.time_budget)?;
+        for table in tables_read(&conn, &sql)? {
+            if !self.allowed_tables.contains(&table) {
+                bail!(
+                    "Table '{}' cannot be queried; allowed tables: {}",
+                    table,
+                    self.allowed_tables.join(", ")
+                );
+            }
+        }
+        let started = Instant::now();
+        let budget = self.time_budget;
+        conn.progress_handler(
+            SQL_PROGRESS_HANDLER_OPS,
+            Some(move || started.elapsed() > budget),
+        );
+
+        self.collect_rows(&conn, &sql).map_err(|e| match e {
+            rusqlite::Error::SqliteFailure(ref failure, _)
+                if failure.code == ErrorCode::OperationInterrupted =>
+            {
+                anyhow!(
+                    "Query exceeded its time budget of {} ms",
+                    budget.as_millis()
+                )
+            }
+            e => e.into(),
+        })
+    }
+
+    fn collect_rows(&self, conn: &Connection, sql: &str) -> rusqlite::Result<SqlRows> {
+        let mut stmt = conn.prepare(sql)?;
+        let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+
+        let mut rows = Vec::new();
+        let mut truncated = false;
+        let mut cursor = stmt.query([])?;
+        while let Some(row) = cursor.next()? {
+            if rows.len() == self.max_rows {
+                truncated = true;
+                break;
+            }
+            let mut object = Map::new();
+            for (i, column) in columns.iter().enumerate() {
+                object.insert(column.clone(), to_json(row.get_ref(i)?));
+            }
+            rows.push(object);
+        }
+
+        Ok(SqlRows {
+            columns,
+            rows,
+            truncated,
+        })
+    }
+}
+
+/// Tables `sql` reads, taken from the program SQLite compiles for it so that
+/// views, subqueries and CTEs cannot hide one
+fn tables_read(conn: &Connection, sql: &str) -> Result<BTreeSet<String>> {
+    let mut stmt = conn.prepare(&format!("EXPLAIN {}", sql))?;
+    let mut program = stmt.query([])?;
+    let mut root_pages = BTreeSet::new();
+    while let Some(instruction) = program.next()? {
+        let opcode: String = instruction.get(1)?;
+        match opcode.as_str() {
+            // p2 is the root page of the table or index, p3 the database
+            "OpenRead" => {
+                if instruction.get::<_, i64>(4)? != 0 {
+                    bail!("Only the main database can be queried");
+                }
+                root_pages.insert(instruction.get::<_, i64>(3)?);
+            }
+            "VOpen" => bail!("Virtual tables cannot be queried"),
+            _ => {}
+        }
+    }
+
+    let mut tables = BTreeSet::new();
+    for root_page in root_pages {
+        // An index resolves to the table it belongs to; the schema table
+        // itself has no entry of its own
+        let table: Option<String> = conn
+            .query_row(
+                "SELECT tbl_name FROM sqlite_master WHERE rootpage = ?1",
+                [root_page],
+                |row| row.get(0),
+            )
+            .optional()?;
+        tables.insert(table.unwrap_or_else(|| "sqlite_master".to_string()));
+    }
+    Ok(tables)
+}
+
+fn to_json(value: ValueRef<'_>) -> JsonValue {
+    match value {
+        ValueRef::Null => JsonValue::Null,
+        ValueRef::Integer(i) => JsonValue::from(i),
+        ValueRef::Real(f) => JsonValue::from(f),
+        ValueRef::Text(text) => JsonValue::from(String::from_utf8_lossy(text).into_owned()),
+        ValueRef::Blob(blob) => JsonValue::from(hex::encode(blob)),
+    }
+}
+
+/// Parse `sql` and accept it only when it is a single `SELECT` query,
+/// returning it re-rendered with a `LIMIT` of at most `max_rows`
+pub fn validate_select(sql: &str, max_rows: usize) -> Result<String> {
+    let statements = Parser::parse_sql(&SQLiteDialect {}, sql)
+        .map_err(|e| anyhow!("Could not parse SQL: {}", e))?;
+
+    let mut query = match statements.as_slice() {
+        [Statement::Query(query)] => query.clone(),
+        [_] => bail!("Only SELECT queries are allowed"),
+        [] => bail!("Empty SQL query"),
+        _ => bail!("Only a single SELECT statement is allowed"),
+    };
+    ensure_read_only(&query)?;
+
+    let requested = match &query.limit {
+        Some(Expr::Value(Value::Number(n, _))) => n.parse::<usize>().ok(),
+        _ => None,
+    };
+    let limit = requested.map_or(max_rows, |n| n.min(max_rows));
+    query.limit = Some(Expr::Value(Value::Number(limit.to_string(), false)));
+
+    Ok(query.to_string())
+}
+
+fn ensure_read_only(query: &Query) -> Result<()> {
+    if let Some(with) = &query.with {
+        for cte in &with.cte_tables {
+            ensure_read_only(&cte.query)?;
+        }
+    }
+    ensure_read_only_body(&query.body)
+}
+
+fn ensure_read_only_body(body: &SetExpr) -> Result<()> {
+    match body {
+        SetExpr::Select(select) if select.into.is_some() => {
+            bail!("SELECT INTO is not allowed")
+        }
+        SetExpr::Select(_) | SetExpr::Values(_) => Ok(()),
+        SetExpr::Query(query) => ensure_read_only(query),
+        SetExpr::SetOperation { left, right, .. } => {
+            ensure_read_only_body(left)?;
+            ensure_read_only_body(right)
+        }
+        _ => bail!("Only SELECT queries are allowed"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn seeded_db() -> (tempfile::TempDir, PathBuf) {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("bear_ai.db");
+        let conn = Connection::open(&db_path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE documents (id INTEGER PRIMARY KEY, filename TEXT);
+             INSERT INTO documents (filename) VALUES ('lease.pdf'), ('nda.docx');",
+        )
+        .unwrap();
+        (temp_dir, db_path)
+    }
+
+    fn document_count(db_path: &Path) -> i64 {
+        Connection::open(db_path)
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM documents", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_select_returns_rows_with_column_names() {
+        let (_temp_dir, db_path) = seeded_db();
+        let result = ReadOnlySql::new(db_path)
+            .execute("SELECT id, filename FROM documents ORDER BY id")
+            .unwrap();
+
+        assert_eq!(result.columns, vec!["id", "filename"]);
+        assert_eq!(result.rows.len(), 2);
+        assert_eq!(result.rows[0]["filename"], "lease.pdf");
+        assert!(!result.truncated);
+
+        // A missing or oversized LIMIT is capped
+        let capped = validate_select("SELECT * FROM documents LIMIT 100000", 50).unwrap();
+        assert!(capped.ends_with("LIMIT 50"));
+        assert!(validate_select("SELECT 1", 50)
+            .unwrap()
+            .ends_with("LIMIT 50"));
+    }
+
+    #[test]
+    fn test_truncated_only_when_rows_remain() {
+        let (_temp_dir, db_path) = seeded_db();
+        let exact = ReadOnlySql {
+            max_rows: 2,
+            ..ReadOnlySql::new(db_path.clone())
+        }
+        .execute("SELECT filename FROM documents")
+        .unwrap();
+        assert_eq!(exact.rows.len(), 2);
+        assert!(!exact.truncated);
+
+        let cut = ReadOnlySql {
+            max_rows: 1,
+            ..ReadOnlySql::new(db_path)
+        }
+        .execute("SELECT filename FROM documents")
+        .unwrap();
+        assert_eq!(cut.rows.len(), 1);
+        assert!(cut.truncated);
+    }
+
+    #[test]
+    fn test_tables_outside_the_allow_list_are_refused() {
+        let (_temp_dir, db_path) = seeded_db();
+        Connection::open(&db_path)
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE audit_log (id INTEGER PRIMARY KEY, user_id TEXT);
+                 CREATE INDEX idx_audit_user ON audit_log (user_id);
+                 CREATE VIEW recent_activity AS SELECT user_id FROM audit_log;
+                 INSERT INTO audit_log (user_id) VALUES ('alice');
+                 CREATE TABLE chat_sessions (id TEXT PRIMARY KEY, user_id TEXT);
+                 CREATE TABLE chat_messages (id INTEGER PRIMARY KEY, chat_id TEXT);",
+            )
+            .unwrap();
+        let sql = ReadOnlySql::new(db_path);
+
+        for hidden in [
+            "SELECT * FROM audit_log",
+            "SELECT user_id FROM audit_log WHERE user_id = 'alice'",
+            "SELECT filename FROM documents WHERE id IN (SELECT id FROM audit_log)",
+            "WITH a AS (SELECT * FROM audit_log) SELECT * FROM a",
+            "SELECT * FROM recent_activity",
+            "SELECT sql FROM sqlite_master",
+            "SELECT * FROM chat_messages",
+            "SELECT id FROM chat_sessions WHERE user_id = 'alice'",
+        ] {
+            let error = sql.execute(hidden).unwrap_err();
+            assert!(
+                error.to_string().contains("cannot be queried"),
+                "accepted: {}",
+                hidden
+            );
+        }
+        assert!(sql.execute("SELECT COUNT(*) FROM documents").is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_redact_pii_replaces_text_cells() {
+        use crate::pii_detector::PIIExclusionsConfig;
+
+        let (_temp_dir, db_path) = seeded_db();
+        Connection::open(&db_path)
+            .unwrap()
+            .execute(
+                "INSERT INTO documents (filename) VALUES ('from alice@example.com.eml')",
+                [],
+            )
+            .unwrap();
+        let mut result = ReadOnlySql::new(db_path)
+            .execute("SELECT id, filename FROM documents ORDER BY id")
+            .unwrap();
+
+        let detector = PIIDetector::with_exclusions(PIIExclusionsConfig::default());
+        result.redact_pii(&detector).await.unwrap();
+        assert_eq!(result.rows[0]["filename"], "lease.pdf");
+        assert!(!result.rows[2]["filename"]
+            .as_str()
+            .unwrap()
+            .contains("alice@example.com"));
+        assert_eq!(result.rows[2]["id"], 3);
+    }
+
+    #[test]
+    fn test_injection_attempts_are_rejected() {
+        let (temp_dir, db_path) = seeded_db();
+        let sql = ReadOnlySql::new(db_path.clone());
+        let attached = temp_dir.path().join("attached.db");
+        let attach = format!("ATTACH DATABASE '{}' AS evil", attached.display());
+
+        for attempt in [
+            "SELECT * FROM documents; DROP TABLE documents",
+            "SELECT * FROM documents WHERE filename = ''; DROP TABLE documents; --'",
+            "SELECT/**/1;/**/DELETE/**/FROM/**/documents",
+            attach.as_str(),
+            "DELETE FROM documents",
+            "PRAGMA writable_schema = ON",
+            "INSERT INTO documents (filename) SELECT filename FROM documents",
+        ] {
+            assert!(sql.execute(attempt).is_err(), "accepted: {}", attempt);
+        }
+
+        assert_eq!(document_count(&db_path), 2);
+        assert!(!attached.exists());
+    }
+
+    #[test]
+    fn test_runaway_query_stops_at_time_budget() {
+        let (_temp_dir, db_path) = seeded_db();
+        let sql = ReadOnlySql::new(db_path).with_time_budget(Duration::from_millis(50));
+
+        let error = sql
+            .execute(
+                "WITH RECURSIVE n(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM n) \
+                 SELECT COUNT(*) FROM n",
+            )
+            .unwrap_err();
+        assert!(error.to_string().contains("time budget"));
+    }
+}
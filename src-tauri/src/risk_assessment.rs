@@ -4,6 +4,7 @@
 // Risk Assessment Module for Model Transparency
 // Provides model risk analysis with graceful degradation when HuggingFace is unavailable
 
+use crate::governing_law::GoverningLaw;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -303,6 +304,39 @@ impl RiskAssessor {
         }
     }
 
+    /// Disclaimers naming the jurisdiction and venue a contract is governed by
+    pub fn jurisdiction_disclaimers(&self, governing_law: &GoverningLaw) -> Vec<Disclaimer> {
+        let mut disclaimers = Vec::new();
+
+        if let Some(jurisdiction) = &governing_law.jurisdiction {
+            disclaimers.push(Disclaimer {
+                category: DisclaimerCategory::Legal,
+                text: format!(
+                    "GOVERNING LAW: This contract is governed by the laws of {}. The analysis is not tailored to {} law; confirm conclusions with counsel qualified in that jurisdiction.",
+                    jurisdiction, jurisdiction
+                ),
+                severity: SeverityLevel::Warning,
+                required: true,
+            });
+        }
+
+        if let Some(venue) = &governing_law.venue {
+            if governing_law.jurisdiction.as_deref() != Some(venue.as_str()) {
+                disclaimers.push(Disclaimer {
+                    category: DisclaimerCategory::Legal,
+                    text: format!(
+                        "VENUE: Disputes are to be heard in {}. Local procedural rules may affect enforcement and deadlines.",
+                        venue
+                    ),
+                    severity: SeverityLevel::Info,
+                    required: false,
+                });
+            }
+        }
+
+        disclaimers
+    }
+
     /// Fallback when Hugging Face is unavailable
     fn create_fallback_assessment(&self, model_id: &str) -> RiskAssessment {
         tracing::info!(
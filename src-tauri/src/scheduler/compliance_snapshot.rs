@@ -0,0 +1,225 @@
+use crate::compliance::{AuditAction, ComplianceManager, EntityType};
+use crate::security::{ChatEncryptor, KeyManager};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// File name prefix shared by all archived snapshots
+const SNAPSHOT_PREFIX: &str = "compliance_snapshot_";
+
+/// Key-derivation context for encrypted snapshots
+const SNAPSHOT_KEY_CONTEXT: &str = "compliance_snapshots";
+
+/// Schedule configuration for periodic compliance snapshots
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotScheduleConfig {
+    /// Snapshot interval in hours
+    pub interval_hours: u64,
+    /// Enable automatic snapshots
+    pub enabled: bool,
+    /// Number of snapshots to keep in the archive directory
+    pub keep_last: usize,
+    /// Encrypt snapshots with a key derived from the OS keychain
+    pub encrypt: bool,
+    /// User whose compliance report is archived
+    pub user_id: String,
+}
+
+impl Default for SnapshotScheduleConfig {
+    fn default() -> Self {
+        Self {
+            interval_hours: 24 * 7, // Weekly by default
+            enabled: false,
+            keep_last: 12,
+            encrypt: false,
+            user_id: "default_user".to_string(),
+        }
+    }
+}
+
+/// Result of a snapshot run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotResult {
+    pub timestamp: DateTime<Utc>,
+    pub path: Option<PathBuf>,
+    pub encrypted: bool,
+    pub snapshots_pruned: usize,
+    pub error: Option<String>,
+    pub success: bool,
+}
+
+/// Task that archives a compliance report snapshot
+pub struct ComplianceSnapshotTask {
+    db_path: PathBuf,
+    archive_dir: PathBuf,
+    config: SnapshotScheduleConfig,
+}
+
+impl ComplianceSnapshotTask {
+    /// Create a new snapshot task
+    pub fn new(db_path: PathBuf, archive_dir: PathBuf, config: SnapshotScheduleConfig) -> Self {
+        Self {
+            db_path,
+            archive_dir,
+            config,
+        }
+    }
+
+    /// Generate the report, write it to the archive and prune old snapshots.
+    /// Returns the snapshot path and the number of snapshots pruned.
+    pub async fn execute(&self) -> Result<(PathBuf, usize)> {
+        info!("Starting compliance snapshot");
+
+        let manager = ComplianceManager::new(self.db_path.clone());
+        let report = manager
+            .generate_compliance_report(&self.config.user_id)
+            .await
+            .context("Failed to generate compliance report")?;
+
+        fs::create_dir_all(&self.archive_dir).with_context(|| {
+            format!(
+                "Failed to create snapshot archive {}",
+                self.archive_dir.display()
+            )
+        })?;
+
+        let report_json = serde_json::to_string_pretty(&report)?;
+        let (contents, extension) = if self.config.encrypt {
+            let key = KeyManager::new()?.derive_key(SNAPSHOT_KEY_CONTEXT)?;
+            let encrypted =
+                ChatEncryptor::new().encrypt_to_json(&report_json, &key, &self.config.user_id)?;
+            (encrypted, "json.enc")
+        } else {
+            (report_json, "json")
+        };
+
+        let file_name = format!(
+            "{}{}.{}",
+            SNAPSHOT_PREFIX,
+            Utc::now().format("%Y%m%dT%H%M%S%.3fZ"),
+            extension
+        );
+        let path = self.archive_dir.join(file_name);
+        fs::write(&path, contents)
+            .with_context(|| format!("Failed to write snapshot {}", path.display()))?;
+
+        let pruned = self.prune_old_snapshots()?;
+
+        let audit = manager.audit();
+        let audit = audit.read().await;
+        audit.log_success(
+            &self.config.user_id,
+            AuditAction::DataExported,
+            EntityType::UserSetting,
+            path.file_name().and_then(|n| n.to_str()),
+            Some(serde_json::json!({
+                "action": "compliance_snapshot",
+                "path": path.display().to_string(),
+                "encrypted": self.config.encrypt,
+                "snapshots_pruned": pruned,
+            })),
+        )?;
+
+        info!(
+            "Compliance snapshot written to {} ({} old snapshots pruned)",
+            path.display(),
+            pruned
+        );
+
+        Ok((path, pruned))
+    }
+
+    /// List archived snapshots, oldest first
+    pub fn list_snapshots(archive_dir: &Path) -> Result<Vec<PathBuf>> {
+        if !archive_dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut snapshots: Vec<PathBuf> = fs::read_dir(archive_dir)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| {
+                path.is_file()
+                    && path
+                        .file_name()
+                        .and_then(|n| n.to_str())
+                        .map(|n| n.starts_with(SNAPSHOT_PREFIX))
+                        .unwrap_or(false)
+            })
+            .collect();
+
+        // Timestamped names sort chronologically
+        snapshots.sort();
+        Ok(snapshots)
+    }
+
+    /// Remove all but the newest `keep_last` snapshots
+    fn prune_old_snapshots(&self) -> Result<usize> {
+        let snapshots = Self::list_snapshots(&self.archive_dir)?;
+        let keep = self.config.keep_last.max(1);
+        if snapshots.len() <= keep {
+            return Ok(0);
+        }
+
+        let mut pruned = 0;
+        for old in &snapshots[..snapshots.len() - keep] {
+            match fs::remove_file(old) {
+                Ok(()) => pruned += 1,
+                Err(e) => warn!("Failed to prune snapshot {}: {}", old.display(), e),
+            }
+        }
+        Ok(pruned)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compliance::AuditQuery;
+
+    #[tokio::test]
+    async fn test_snapshot_written_and_audited() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("compliance.db");
+        let archive_dir = temp_dir.path().join("snapshots");
+
+        let manager = ComplianceManager::new(db_path.clone());
+        manager.initialize().await.unwrap();
+
+        let config = SnapshotScheduleConfig {
+            keep_last: 1,
+            ..Default::default()
+        };
+        let task = ComplianceSnapshotTask::new(db_path.clone(), archive_dir.clone(), config);
+
+        let (first, _) = task.execute().await.unwrap();
+        assert!(first.exists());
+        let written: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&first).unwrap()).unwrap();
+        assert_eq!(written["user_id"], "default_user");
+
+        // Second run prunes the first snapshot down to keep_last
+        let (second, pruned) = task.execute().await.unwrap();
+        assert_eq!(pruned, 1);
+        assert_eq!(
+            ComplianceSnapshotTask::list_snapshots(&archive_dir).unwrap(),
+            vec![second]
+        );
+
+        let audit = manager.audit();
+        let audit = audit.read().await;
+        let entries = audit
+            .query_logs(&AuditQuery {
+                action_type: Some(AuditAction::DataExported.as_str().to_string()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            entries[0].details.as_ref().unwrap()["action"],
+            "compliance_snapshot"
+        );
+    }
+}
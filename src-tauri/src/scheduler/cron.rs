@@ -0,0 +1,191 @@
+//! Cron expressions for the cleanup schedule
+//!
+//! Five fields: `minute hour day-of-month month day-of-week`. Each field is
+//! `*`, a value, a range `a-b`, a step `*/n` or `a-b/n`, or a comma separated
+//! list of those. Days of the week run from 0 (Sunday) to 6, with 7 also
+//! meaning Sunday. As in cron, when both day fields are restricted a day
+//! matching either of them fires. Expressions are evaluated in UTC.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Timelike, Utc};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    any_day_of_month: bool,
+    any_day_of_week: bool,
+}
+
+/// Bit set of the values a field allows
+fn parse_field(field: &str, min: u32, max: u32, name: &str) -> Result<u64> {
+    let mut allowed = 0u64;
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step
+                    .parse()
+                    .map_err(|_| anyhow!("Invalid {} step '{}'", name, step))?;
+                if step == 0 {
+                    return Err(anyhow!("{} step must be positive", name));
+                }
+                (range, Some(step))
+            }
+            None => (part, None),
+        };
+
+        let value = |text: &str| -> Result<u32> {
+            text.parse()
+                .map_err(|_| anyhow!("Invalid {} '{}' in '{}'", name, text, field))
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (value(start)?, value(end)?)
+        } else {
+            let start = value(range)?;
+            // `5/15` steps from 5 to the end of the field
+            (start, if step.is_some() { max } else { start })
+        };
+        if start < min || end > max || start > end {
+            return Err(anyhow!(
+                "{} range '{}' is outside {}-{}",
+                name,
+                range,
+                min,
+                max
+            ));
+        }
+
+        for value in (start..=end).step_by(step.unwrap_or(1) as usize) {
+            allowed |= 1 << value;
+        }
+    }
+
+    Ok(allowed)
+}
+
+fn matches(allowed: u64, value: u32) -> bool {
+    allowed & (1 << value) != 0
+}
+
+fn start_of_day(date: NaiveDate) -> DateTime<Utc> {
+    date.and_hms_opt(0, 0, 0)
+        .expect("midnight is a valid time")
+        .and_utc()
+}
+
+impl CronSchedule {
+    pub fn parse(expression: &str) -> Result<Self> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(anyhow!(
+                "Cron expression '{}' needs 5 fields: minute hour day-of-month month day-of-week",
+                expression
+            ));
+        }
+
+        let mut days_of_week = parse_field(fields[4], 0, 7, "day of week")?;
+        if matches(days_of_week, 7) {
+            days_of_week = (days_of_week | 1) & !(1 << 7);
+        }
+
+        Ok(Self {
+            minutes: parse_field(fields[0], 0, 59, "minute")?,
+            hours: parse_field(fields[1], 0, 23, "hour")?,
+            days_of_month: parse_field(fields[2], 1, 31, "day of month")?,
+            months: parse_field(fields[3], 1, 12, "month")?,
+            days_of_week,
+            any_day_of_month: fields[2].starts_with('*'),
+            any_day_of_week: fields[4].starts_with('*'),
+        })
+    }
+
+    fn day_matches(&self, date: NaiveDate) -> bool {
+        let day_of_month = matches(self.days_of_month, date.day());
+        let day_of_week = matches(self.days_of_week, date.weekday().num_days_from_sunday());
+        match (self.any_day_of_month, self.any_day_of_week) {
+            (true, true) => true,
+            (true, false) => day_of_week,
+            (false, true) => day_of_month,
+            (false, false) => day_of_month || day_of_week,
+        }
+    }
+
+    /// First matching minute after `after`, or `None` if the expression never
+    /// fires (e.g. February 30th)
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let mut next = after.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        // Every satisfiable expression fires within a leap year cycle
+        let limit = next + Duration::days(366 * 4 + 1);
+
+        while next <= limit {
+            let date = next.date_naive();
+            if !matches(self.months, date.month()) {
+                let (year, month) = match date.month() {
+                    12 => (date.year() + 1, 1),
+                    month => (date.year(), month + 1),
+                };
+                next = start_of_day(NaiveDate::from_ymd_opt(year, month, 1)?);
+            } else if !self.day_matches(date) {
+                next = start_of_day(date.succ_opt()?);
+            } else if !matches(self.hours, next.hour()) {
+                next = next.with_minute(0)? + Duration::hours(1);
+            } else if !matches(self.minutes, next.minute()) {
+                next += Duration::minutes(1);
+            } else {
+                return Some(next);
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_next_run_times() {
+        let after = Utc.with_ymd_and_hms(2024, 3, 15, 10, 30, 12).unwrap();
+
+        let nightly = CronSchedule::parse("0 2 * * *").unwrap();
+        assert_eq!(
+            nightly.next_after(after),
+            Some(Utc.with_ymd_and_hms(2024, 3, 16, 2, 0, 0).unwrap())
+        );
+
+        // Sundays at 03:30 (2024-03-15 is a Friday); 7 is also Sunday
+        let weekly = CronSchedule::parse("30 3 * * 7").unwrap();
+        assert_eq!(
+            weekly.next_after(after),
+            Some(Utc.with_ymd_and_hms(2024, 3, 17, 3, 30, 0).unwrap())
+        );
+
+        let quarter_hourly = CronSchedule::parse("*/15 9-17 * * 1-5").unwrap();
+        assert_eq!(
+            quarter_hourly.next_after(after),
+            Some(Utc.with_ymd_and_hms(2024, 3, 15, 10, 45, 0).unwrap())
+        );
+
+        let new_year = CronSchedule::parse("0 0 1 1 *").unwrap();
+        assert_eq!(
+            new_year.next_after(after),
+            Some(Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            CronSchedule::parse("0 0 30 2 *").unwrap().next_after(after),
+            None
+        );
+        assert!(CronSchedule::parse("0 2 * *").is_err());
+        assert!(CronSchedule::parse("60 2 * * *").is_err());
+        assert!(CronSchedule::parse("*/0 * * * *").is_err());
+    }
+}
@@ -1,5 +1,5 @@
 #![allow(dead_code)]
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
@@ -8,8 +8,12 @@ use tokio::sync::{mpsc, RwLock};
 use tokio::time::{interval, Duration};
 use tracing::{debug, error, info};
 
+pub mod compliance_snapshot;
+pub mod cron;
 pub mod retention_tasks;
 
+use compliance_snapshot::{ComplianceSnapshotTask, SnapshotResult, SnapshotScheduleConfig};
+use cron::CronSchedule;
 use retention_tasks::RetentionCleanupTask;
 
 /// Schedule configuration for cleanup tasks
@@ -17,16 +21,33 @@ use retention_tasks::RetentionCleanupTask;
 pub struct ScheduleConfig {
     /// Cleanup interval in hours
     pub interval_hours: u64,
+    /// Cron expression (UTC) for cleanup times; takes precedence over
+    /// `interval_hours`
+    #[serde(default)]
+    pub cron: Option<String>,
     /// Enable automatic cleanup
     pub enabled: bool,
     /// Next scheduled run time
     pub next_run: Option<DateTime<Utc>>,
 }
 
+impl ScheduleConfig {
+    /// Next cleanup time after `now`
+    pub fn next_run_after(&self, now: DateTime<Utc>) -> Result<DateTime<Utc>> {
+        match self.cron.as_deref() {
+            Some(expression) => CronSchedule::parse(expression)?
+                .next_after(now)
+                .ok_or_else(|| anyhow!("Cron expression '{}' never fires", expression)),
+            None => Ok(now + chrono::Duration::hours(self.interval_hours as i64)),
+        }
+    }
+}
+
 impl Default for ScheduleConfig {
     fn default() -> Self {
         Self {
             interval_hours: 24, // Daily by default
+            cron: None,
             enabled: true,
             next_run: None,
         }
@@ -38,8 +59,18 @@ impl Default for ScheduleConfig {
 pub enum SchedulerCommand {
     /// Trigger manual cleanup
     RunCleanup,
+    /// Schedule a one-off cleanup at the given time
+    RunAt(DateTime<Utc>),
+    /// Suspend scheduled cleanups, keeping the next run time
+    Pause,
+    /// Resume scheduled cleanups; runs missed while paused happen on the next check
+    Resume,
     /// Update schedule configuration
     UpdateConfig(ScheduleConfig),
+    /// Trigger a manual compliance snapshot
+    RunSnapshot,
+    /// Update compliance snapshot schedule
+    UpdateSnapshotConfig(SnapshotScheduleConfig),
     /// Get current status
     GetStatus,
     /// Shutdown scheduler
@@ -50,10 +81,17 @@ pub enum SchedulerCommand {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SchedulerStatus {
     pub is_running: bool,
+    /// Scheduled and one-off cleanups are suspended (snapshots still run)
+    pub paused: bool,
     pub last_run: Option<DateTime<Utc>>,
     pub next_run: Option<DateTime<Utc>>,
+    /// One-off cleanup scheduled with `RunAt`
+    pub run_at: Option<DateTime<Utc>>,
     pub total_cleanups: u64,
     pub last_cleanup_result: Option<CleanupResult>,
+    pub next_snapshot: Option<DateTime<Utc>>,
+    pub total_snapshots: u64,
+    pub last_snapshot_result: Option<SnapshotResult>,
 }
 
 /// Result of a cleanup operation
@@ -71,7 +109,9 @@ pub struct CleanupResult {
 /// Background scheduler for automated data retention cleanup
 pub struct RetentionScheduler {
     db_path: PathBuf,
+    archive_dir: PathBuf,
     config: Arc<RwLock<ScheduleConfig>>,
+    snapshot_config: Arc<RwLock<SnapshotScheduleConfig>>,
     status: Arc<RwLock<SchedulerStatus>>,
     command_tx: mpsc::UnboundedSender<SchedulerCommand>,
     command_rx: Option<mpsc::UnboundedReceiver<SchedulerCommand>>,
@@ -79,18 +119,37 @@ pub struct RetentionScheduler {
 
 impl RetentionScheduler {
     /// Create a new retention scheduler
+    ///
+    /// Compliance snapshots are archived next to the database in
+    /// `compliance_snapshots/`.
     pub fn new(db_path: PathBuf) -> Self {
+        let archive_dir = db_path
+            .parent()
+            .map(|dir| dir.join("compliance_snapshots"))
+            .unwrap_or_else(|| PathBuf::from("compliance_snapshots"));
+        Self::with_archive_dir(db_path, archive_dir)
+    }
+
+    /// Create a scheduler with an explicit compliance snapshot archive directory
+    pub fn with_archive_dir(db_path: PathBuf, archive_dir: PathBuf) -> Self {
         let (command_tx, command_rx) = mpsc::unbounded_channel();
 
         Self {
             db_path,
+            archive_dir,
             config: Arc::new(RwLock::new(ScheduleConfig::default())),
+            snapshot_config: Arc::new(RwLock::new(SnapshotScheduleConfig::default())),
             status: Arc::new(RwLock::new(SchedulerStatus {
                 is_running: false,
+                paused: false,
                 last_run: None,
                 next_run: None,
+                run_at: None,
                 total_cleanups: 0,
                 last_cleanup_result: None,
+                next_snapshot: None,
+                total_snapshots: 0,
+                last_snapshot_result: None,
             })),
             command_tx,
             command_rx: Some(command_rx),
@@ -102,6 +161,7 @@ impl RetentionScheduler {
         SchedulerHandle {
             command_tx: self.command_tx.clone(),
             status: Arc::clone(&self.status),
+            config: Arc::clone(&self.config),
         }
     }
 
@@ -121,13 +181,15 @@ impl RetentionScheduler {
 
             let config = self.config.read().await;
             if config.enabled {
-                status.next_run = Some(Self::calculate_next_run(config.interval_hours));
+                status.next_run = Some(Self::next_cleanup_run(&config));
             }
         }
 
         // Clone Arc references for the task
         let db_path = self.db_path.clone();
+        let archive_dir = self.archive_dir.clone();
         let config = Arc::clone(&self.config);
+        let snapshot_config = Arc::clone(&self.snapshot_config);
         let status = Arc::clone(&self.status);
 
         // Spawn background task
@@ -138,28 +200,7 @@ impl RetentionScheduler {
                 tokio::select! {
                     // Handle periodic checks
                     _ = ticker.tick() => {
-                        let cfg = config.read().await;
-                        if !cfg.enabled {
-                            continue;
-                        }
-
-                        let should_run = {
-                            let stat = status.read().await;
-                            if let Some(next_run) = stat.next_run {
-                                Utc::now() >= next_run
-                            } else {
-                                true // First run
-                            }
-                        };
-
-                        if should_run {
-                            debug!("Scheduled cleanup triggered");
-                            Self::execute_cleanup(&db_path, &status).await;
-
-                            // Update next run time
-                            let mut stat = status.write().await;
-                            stat.next_run = Some(Self::calculate_next_run(cfg.interval_hours));
-                        }
+                        Self::on_tick(&db_path, &archive_dir, &config, &snapshot_config, &status).await;
                     }
 
                     // Handle commands
@@ -169,6 +210,18 @@ impl RetentionScheduler {
                                 info!("Manual cleanup triggered");
                                 Self::execute_cleanup(&db_path, &status).await;
                             }
+                            SchedulerCommand::RunAt(at) => {
+                                info!("One-off cleanup scheduled for {}", at);
+                                status.write().await.run_at = Some(at);
+                            }
+                            SchedulerCommand::Pause => {
+                                info!("Scheduled cleanups paused");
+                                status.write().await.paused = true;
+                            }
+                            SchedulerCommand::Resume => {
+                                info!("Scheduled cleanups resumed");
+                                status.write().await.paused = false;
+                            }
                             SchedulerCommand::UpdateConfig(new_config) => {
                                 info!("Updating scheduler configuration");
                                 let mut cfg = config.write().await;
@@ -177,9 +230,24 @@ impl RetentionScheduler {
                                 // Update next run time
                                 if cfg.enabled {
                                     let mut stat = status.write().await;
-                                    stat.next_run = Some(Self::calculate_next_run(cfg.interval_hours));
+                                    stat.next_run = Some(Self::next_cleanup_run(&cfg));
                                 }
                             }
+                            SchedulerCommand::RunSnapshot => {
+                                info!("Manual compliance snapshot triggered");
+                                let snap_cfg = snapshot_config.read().await.clone();
+                                Self::execute_snapshot(&db_path, &archive_dir, snap_cfg, &status).await;
+                            }
+                            SchedulerCommand::UpdateSnapshotConfig(new_config) => {
+                                info!("Updating compliance snapshot schedule");
+                                let mut stat = status.write().await;
+                                stat.next_snapshot = if new_config.enabled {
+                                    Some(Self::calculate_next_run(new_config.interval_hours))
+                                } else {
+                                    None
+                                };
+                                *snapshot_config.write().await = new_config;
+                            }
                             SchedulerCommand::GetStatus => {
                                 // Status is retrieved via handle, nothing to do
                                 debug!("Status requested");
@@ -201,6 +269,56 @@ impl RetentionScheduler {
         Ok(())
     }
 
+    /// Periodic check: runs a due compliance snapshot and, unless paused, a
+    /// due one-off or scheduled cleanup
+    async fn on_tick(
+        db_path: &Path,
+        archive_dir: &Path,
+        config: &Arc<RwLock<ScheduleConfig>>,
+        snapshot_config: &Arc<RwLock<SnapshotScheduleConfig>>,
+        status: &Arc<RwLock<SchedulerStatus>>,
+    ) {
+        Self::check_snapshot_due(db_path, archive_dir, snapshot_config, status).await;
+
+        let (paused, one_off_due) = {
+            let stat = status.read().await;
+            let now = Utc::now();
+            (stat.paused, stat.run_at.is_some_and(|at| now >= at))
+        };
+        if paused {
+            return;
+        }
+
+        if one_off_due {
+            debug!("One-off cleanup triggered");
+            status.write().await.run_at = None;
+            Self::execute_cleanup(db_path, status).await;
+        }
+
+        let cfg = config.read().await.clone();
+        if !cfg.enabled {
+            return;
+        }
+
+        let should_run = {
+            let stat = status.read().await;
+            if let Some(next_run) = stat.next_run {
+                Utc::now() >= next_run
+            } else {
+                true // First run
+            }
+        };
+
+        if should_run {
+            debug!("Scheduled cleanup triggered");
+            Self::execute_cleanup(db_path, status).await;
+
+            // Update next run time
+            let mut stat = status.write().await;
+            stat.next_run = Some(Self::next_cleanup_run(&cfg));
+        }
+    }
+
     /// Execute cleanup task
     async fn execute_cleanup(db_path: &Path, status: &Arc<RwLock<SchedulerStatus>>) {
         let start_time = Utc::now();
@@ -243,6 +361,84 @@ impl RetentionScheduler {
         stat.last_cleanup_result = Some(result);
     }
 
+    /// Run a compliance snapshot if one is enabled and due
+    async fn check_snapshot_due(
+        db_path: &Path,
+        archive_dir: &Path,
+        snapshot_config: &Arc<RwLock<SnapshotScheduleConfig>>,
+        status: &Arc<RwLock<SchedulerStatus>>,
+    ) {
+        let snap_cfg = snapshot_config.read().await.clone();
+        if !snap_cfg.enabled {
+            return;
+        }
+
+        let due = {
+            let stat = status.read().await;
+            match stat.next_snapshot {
+                Some(next_snapshot) => Utc::now() >= next_snapshot,
+                None => true, // First run
+            }
+        };
+
+        if due {
+            debug!("Scheduled compliance snapshot triggered");
+            let interval_hours = snap_cfg.interval_hours;
+            Self::execute_snapshot(db_path, archive_dir, snap_cfg, status).await;
+
+            let mut stat = status.write().await;
+            stat.next_snapshot = Some(Self::calculate_next_run(interval_hours));
+        }
+    }
+
+    /// Execute compliance snapshot task
+    async fn execute_snapshot(
+        db_path: &Path,
+        archive_dir: &Path,
+        config: SnapshotScheduleConfig,
+        status: &Arc<RwLock<SchedulerStatus>>,
+    ) {
+        let start_time = Utc::now();
+        let encrypted = config.encrypt;
+
+        let task =
+            ComplianceSnapshotTask::new(db_path.to_path_buf(), archive_dir.to_path_buf(), config);
+        let result = match task.execute().await {
+            Ok((path, pruned)) => SnapshotResult {
+                timestamp: start_time,
+                path: Some(path),
+                encrypted,
+                snapshots_pruned: pruned,
+                error: None,
+                success: true,
+            },
+            Err(e) => {
+                error!("Compliance snapshot failed: {}", e);
+                SnapshotResult {
+                    timestamp: start_time,
+                    path: None,
+                    encrypted,
+                    snapshots_pruned: 0,
+                    error: Some(e.to_string()),
+                    success: false,
+                }
+            }
+        };
+
+        let mut stat = status.write().await;
+        stat.total_snapshots += 1;
+        stat.last_snapshot_result = Some(result);
+    }
+
+    /// Next cleanup time from the cron expression or interval; an invalid
+    /// expression falls back to the interval
+    fn next_cleanup_run(config: &ScheduleConfig) -> DateTime<Utc> {
+        config.next_run_after(Utc::now()).unwrap_or_else(|e| {
+            error!("Invalid cleanup schedule, using interval: {}", e);
+            Self::calculate_next_run(config.interval_hours)
+        })
+    }
+
     /// Calculate next run time based on interval
     fn calculate_next_run(interval_hours: u64) -> DateTime<Utc> {
         Utc::now() + chrono::Duration::hours(interval_hours as i64)
@@ -254,6 +450,7 @@ impl RetentionScheduler {
 pub struct SchedulerHandle {
     command_tx: mpsc::UnboundedSender<SchedulerCommand>,
     status: Arc<RwLock<SchedulerStatus>>,
+    config: Arc<RwLock<ScheduleConfig>>,
 }
 
 impl SchedulerHandle {
@@ -264,24 +461,84 @@ impl SchedulerHandle {
             .context("Failed to send cleanup command")
     }
 
-    /// Update scheduler configuration
+    /// Schedule a one-off cleanup, e.g. for after-hours maintenance; replaces
+    /// any one-off cleanup already scheduled
+    pub fn run_at(&self, at: DateTime<Utc>) -> Result<()> {
+        self.command_tx
+            .send(SchedulerCommand::RunAt(at))
+            .context("Failed to send run-at command")
+    }
+
+    /// Suspend scheduled cleanups without changing the schedule
+    pub fn pause(&self) -> Result<()> {
+        self.command_tx
+            .send(SchedulerCommand::Pause)
+            .context("Failed to send pause command")
+    }
+
+    /// Resume scheduled cleanups
+    pub fn resume(&self) -> Result<()> {
+        self.command_tx
+            .send(SchedulerCommand::Resume)
+            .context("Failed to send resume command")
+    }
+
+    /// Update scheduler configuration; an invalid cron expression is rejected
     pub fn update_config(&self, config: ScheduleConfig) -> Result<()> {
+        config.next_run_after(Utc::now())?;
         self.command_tx
             .send(SchedulerCommand::UpdateConfig(config))
             .context("Failed to send config update")
     }
 
+    /// Trigger a manual compliance snapshot
+    pub fn trigger_snapshot(&self) -> Result<()> {
+        self.command_tx
+            .send(SchedulerCommand::RunSnapshot)
+            .context("Failed to send snapshot command")
+    }
+
+    /// Update compliance snapshot schedule
+    pub fn update_snapshot_config(&self, config: SnapshotScheduleConfig) -> Result<()> {
+        self.command_tx
+            .send(SchedulerCommand::UpdateSnapshotConfig(config))
+            .context("Failed to send snapshot config update")
+    }
+
     /// Get current scheduler status
     pub async fn get_status(&self) -> SchedulerStatus {
         self.status.read().await.clone()
     }
 
+    /// Get current cleanup schedule configuration
+    pub async fn get_config(&self) -> ScheduleConfig {
+        self.config.read().await.clone()
+    }
+
     /// Shutdown the scheduler
     pub fn shutdown(&self) -> Result<()> {
         self.command_tx
             .send(SchedulerCommand::Shutdown)
             .context("Failed to send shutdown command")
     }
+
+    /// Shutdown the scheduler and wait for it to stop; a cleanup or snapshot
+    /// already running completes first
+    pub async fn shutdown_and_wait(&self, timeout: Duration) -> Result<()> {
+        if !self.status.read().await.is_running {
+            return Ok(());
+        }
+        self.shutdown()?;
+
+        let deadline = tokio::time::Instant::now() + timeout;
+        while self.status.read().await.is_running {
+            if tokio::time::Instant::now() >= deadline {
+                return Err(anyhow!("Scheduler did not stop within {:?}", timeout));
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -308,10 +565,17 @@ mod tests {
         // Test config update
         let config = ScheduleConfig {
             interval_hours: 48,
+            cron: None,
             enabled: true,
             next_run: None,
         };
         assert!(handle.update_config(config).is_ok());
+        assert!(handle
+            .update_config(ScheduleConfig {
+                cron: Some("0 25 * * *".to_string()),
+                ..ScheduleConfig::default()
+            })
+            .is_err());
 
         // Test shutdown
         assert!(handle.shutdown().is_ok());
@@ -326,4 +590,58 @@ mod tests {
         let diff = (next_run - expected).num_seconds().abs();
         assert!(diff < 2);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_cron_schedule_takes_precedence() {
+        let now = Utc::now();
+        let config = ScheduleConfig {
+            cron: Some("0 2 * * *".to_string()),
+            ..ScheduleConfig::default()
+        };
+        let next_run = config.next_run_after(now).unwrap();
+        assert!(next_run > now && next_run <= now + chrono::Duration::hours(24));
+        assert_eq!(next_run.format("%H:%M:%S").to_string(), "02:00:00");
+    }
+
+    #[tokio::test]
+    async fn test_paused_scheduler_skips_ticks() {
+        let dir = std::env::temp_dir().join(format!("test_scheduler_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db_path = dir.join("test.db");
+        let scheduler =
+            RetentionScheduler::with_archive_dir(db_path.clone(), dir.join("snapshots"));
+
+        let due = Utc::now() - chrono::Duration::minutes(5);
+        {
+            let mut status = scheduler.status.write().await;
+            status.paused = true;
+            status.next_run = Some(due);
+            status.run_at = Some(due);
+        }
+
+        let tick = || {
+            RetentionScheduler::on_tick(
+                &scheduler.db_path,
+                &scheduler.archive_dir,
+                &scheduler.config,
+                &scheduler.snapshot_config,
+                &scheduler.status,
+            )
+        };
+
+        tick().await;
+        let status = scheduler.get_handle().get_status().await;
+        assert_eq!(status.total_cleanups, 0);
+        assert_eq!((status.next_run, status.run_at), (Some(due), Some(due)));
+
+        // Resuming runs the one-off and the missed scheduled cleanup
+        scheduler.status.write().await.paused = false;
+        tick().await;
+        let status = scheduler.get_handle().get_status().await;
+        assert_eq!(status.total_cleanups, 2);
+        assert!(status.run_at.is_none());
+        assert!(status.next_run.unwrap() > Utc::now());
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
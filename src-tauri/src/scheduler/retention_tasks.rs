@@ -1,5 +1,7 @@
-use crate::compliance::retention::RetentionManager;
+use crate::compliance::retention::{ExpiredRecord, RetentionManager};
 use anyhow::Result;
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashSet};
 use std::path::PathBuf;
 use tracing::{info, warn};
 
@@ -49,7 +51,7 @@ impl RetentionCleanupTask {
         entity_type: &str,
     ) -> Result<usize> {
         // Get expired entities first (for logging)
-        let expired_ids = manager.get_expired_entities(entity_type)?;
+        let expired_ids = manager.get_expired_records(entity_type)?;
 
         if expired_ids.is_empty() {
             info!("No expired {} entities found", entity_type);
@@ -77,21 +79,57 @@ impl RetentionCleanupTask {
         Ok(deleted_count)
     }
 
-    /// Get cleanup preview without deleting
-    pub async fn preview_cleanup(&self) -> Result<CleanupPreview> {
+    /// Dry run of `execute`: the rows the next cleanup would delete, selected
+    /// the same way, without deleting anything
+    pub async fn execute_preview(&self) -> Result<CleanupPreview> {
         let manager = RetentionManager::new(self.db_path.clone());
+        let generated_at = Utc::now();
+
+        let mut categories = Vec::new();
+        let mut expired_sessions = HashSet::new();
+        for entity_type in ["document", "chat_session", "chat_message", "query_history"] {
+            let mut records = manager.get_expired_records(entity_type)?;
+            match entity_type {
+                "chat_session" => {
+                    expired_sessions = records.iter().map(|record| record.id.clone()).collect();
+                }
+                // Messages of an expired session are removed with the session
+                // before messages are cleaned up, so they are not counted twice
+                "chat_message" => records.retain(|record| {
+                    !matches!(&record.chat_id, Some(chat_id) if expired_sessions.contains(chat_id))
+                }),
+                _ => {}
+            }
 
-        let documents = manager.get_expired_entities("document")?;
-        let sessions = manager.get_expired_entities("chat_session")?;
-        let messages = manager.get_expired_entities("chat_message")?;
-        let queries = manager.get_expired_entities("query_history")?;
+            let policy = manager.get_category_policy(entity_type)?;
+            categories.push(CategoryPreview {
+                entity_type: entity_type.to_string(),
+                to_delete: records.len(),
+                cascaded: manager.get_cascaded_counts(entity_type)?,
+                retention_days: policy
+                    .filter(|policy| policy.auto_delete)
+                    .map(|policy| policy.retention_days),
+                records,
+            });
+        }
+        let count = |entity_type: &str| {
+            categories
+                .iter()
+                .find(|category| category.entity_type == entity_type)
+                .map_or(0, |category| category.to_delete)
+        };
 
         Ok(CleanupPreview {
-            documents_to_delete: documents.len(),
-            sessions_to_delete: sessions.len(),
-            messages_to_delete: messages.len(),
-            queries_to_delete: queries.len(),
-            total: documents.len() + sessions.len() + messages.len() + queries.len(),
+            documents_to_delete: count("document"),
+            sessions_to_delete: count("chat_session"),
+            messages_to_delete: count("chat_message"),
+            queries_to_delete: count("query_history"),
+            total: categories
+                .iter()
+                .map(|category| category.to_delete + category.cascaded.values().sum::<usize>())
+                .sum(),
+            generated_at,
+            categories,
         })
     }
 
@@ -127,14 +165,32 @@ impl RetentionCleanupTask {
     }
 }
 
-/// Preview of what would be deleted
+/// Preview of what the next cleanup would delete
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct CleanupPreview {
     pub documents_to_delete: usize,
     pub sessions_to_delete: usize,
     pub messages_to_delete: usize,
     pub queries_to_delete: usize,
+    /// Every row the cleanup deletes, including chunks, PII detections and
+    /// messages removed with their document or session
     pub total: usize,
+    pub generated_at: DateTime<Utc>,
+    /// Expired entities per data category
+    pub categories: Vec<CategoryPreview>,
+}
+
+/// Expired entities of one data category
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CategoryPreview {
+    pub entity_type: String,
+    pub to_delete: usize,
+    /// Rows of dependent tables deleted with these entities, by table
+    pub cascaded: BTreeMap<String, usize>,
+    /// Age-based retention period of the category's auto-delete policy
+    pub retention_days: Option<i64>,
+    /// Ids, filenames and chat titles of the rows to delete
+    pub records: Vec<ExpiredRecord>,
 }
 
 /// Result of applying retention policies
@@ -147,6 +203,7 @@ pub struct PolicyApplicationResult {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::compliance::EntityType;
     use std::env;
 
     fn get_test_db() -> PathBuf {
@@ -214,7 +271,7 @@ mod tests {
         }
 
         let task = RetentionCleanupTask::new(db_path.clone());
-        let preview = task.preview_cleanup().await.unwrap();
+        let preview = task.execute_preview().await.unwrap();
 
         assert_eq!(preview.total, 0);
         assert_eq!(preview.documents_to_delete, 0);
@@ -222,4 +279,196 @@ mod tests {
         // Cleanup
         let _ = std::fs::remove_file(db_path);
     }
+
+    #[tokio::test]
+    async fn test_category_policies_expire_independently() {
+        let db_path = get_test_db();
+
+        {
+            use rusqlite::Connection;
+            let conn = Connection::open(&db_path).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE documents (
+                    id INTEGER PRIMARY KEY,
+                    filename TEXT,
+                    upload_date DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    retention_until DATETIME
+                );
+                CREATE TABLE document_chunks (id INTEGER PRIMARY KEY, document_id INTEGER);
+                CREATE TABLE pii_detections (id INTEGER PRIMARY KEY, document_id INTEGER);
+                CREATE TABLE chat_sessions (
+                    id INTEGER PRIMARY KEY,
+                    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    retention_until DATETIME
+                );
+                CREATE TABLE chat_messages (
+                    id INTEGER PRIMARY KEY,
+                    chat_id INTEGER,
+                    content TEXT,
+                    timestamp DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    retention_until DATETIME
+                );
+                CREATE TABLE query_history (
+                    id INTEGER PRIMARY KEY,
+                    query TEXT,
+                    retention_until DATETIME
+                );
+                INSERT INTO documents (filename, upload_date)
+                    VALUES ('lease.pdf', datetime('now', '-200 days'));
+                INSERT INTO chat_messages (chat_id, content, timestamp)
+                    VALUES (1, 'old question', datetime('now', '-200 days'));
+                INSERT INTO chat_messages (chat_id, content) VALUES (1, 'new question');",
+            )
+            .unwrap();
+        }
+
+        let manager = RetentionManager::new(db_path.clone());
+        manager
+            .set_category_policy(&EntityType::Document, 365 * 7, true)
+            .unwrap();
+        manager
+            .set_category_policy(&EntityType::ChatMessage, 90, true)
+            .unwrap();
+        assert!(manager
+            .set_category_policy(&EntityType::Consent, 30, true)
+            .is_err());
+
+        let task = RetentionCleanupTask::new(db_path.clone());
+        let preview = task.execute_preview().await.unwrap();
+        assert_eq!(preview.documents_to_delete, 0);
+        assert_eq!(preview.messages_to_delete, 1);
+        assert_eq!(preview.total, 1);
+        let messages = preview
+            .categories
+            .iter()
+            .find(|category| category.entity_type == "chat_message")
+            .unwrap();
+        assert_eq!((messages.to_delete, messages.retention_days), (1, Some(90)));
+
+        let (documents, _, messages, _) = task.execute().await.unwrap();
+        assert_eq!((documents, messages), (0, 1));
+        assert_eq!(task.execute_preview().await.unwrap().total, 0);
+
+        let _ = std::fs::remove_file(db_path);
+    }
+
+    #[tokio::test]
+    async fn test_preview_matches_cleanup() {
+        let db_path = get_test_db();
+
+        {
+            use rusqlite::Connection;
+            let conn = Connection::open(&db_path).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE documents (
+                    id INTEGER PRIMARY KEY,
+                    filename TEXT,
+                    upload_date DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    retention_until DATETIME
+                );
+                CREATE TABLE document_chunks (id INTEGER PRIMARY KEY, document_id INTEGER);
+                CREATE TABLE pii_detections (id INTEGER PRIMARY KEY, document_id INTEGER);
+                CREATE TABLE chat_sessions (
+                    id TEXT PRIMARY KEY,
+                    title TEXT,
+                    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    retention_until DATETIME
+                );
+                CREATE TABLE chat_messages (
+                    id INTEGER PRIMARY KEY,
+                    chat_id TEXT,
+                    content TEXT,
+                    timestamp DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    retention_until DATETIME
+                );
+                CREATE TABLE query_history (
+                    id INTEGER PRIMARY KEY,
+                    query TEXT,
+                    retention_until DATETIME
+                );
+                INSERT INTO documents (filename, upload_date) VALUES
+                    ('old_lease.pdf', datetime('now', '-60 days')),
+                    ('new_lease.pdf', datetime('now'));
+                INSERT INTO document_chunks (document_id) VALUES (1), (1), (2);
+                INSERT INTO pii_detections (document_id) VALUES (1), (2);
+                INSERT INTO chat_sessions (id, title, created_at) VALUES
+                    ('old-chat', 'Lease review', datetime('now', '-120 days')),
+                    ('new-chat', 'Notice periods', datetime('now'));
+                INSERT INTO chat_messages (chat_id, content, timestamp) VALUES
+                    ('old-chat', 'first', datetime('now', '-120 days')),
+                    ('old-chat', 'second', datetime('now', '-120 days')),
+                    ('new-chat', 'carried over', datetime('now', '-120 days')),
+                    ('new-chat', 'today', datetime('now'));
+                INSERT INTO query_history (query, retention_until) VALUES
+                    ('termination clause', '2000-01-01T00:00:00+00:00');",
+            )
+            .unwrap();
+        }
+
+        let manager = RetentionManager::new(db_path.clone());
+        manager
+            .set_category_policy(&EntityType::Document, 30, true)
+            .unwrap();
+        manager
+            .set_category_policy(&EntityType::ChatMessage, 90, true)
+            .unwrap();
+
+        let task = RetentionCleanupTask::new(db_path.clone());
+        let preview = task.execute_preview().await.unwrap();
+        let labels = |entity_type: &str| -> Vec<Option<String>> {
+            preview
+                .categories
+                .iter()
+                .find(|category| category.entity_type == entity_type)
+                .unwrap()
+                .records
+                .iter()
+                .map(|record| record.label.clone())
+                .collect()
+        };
+        assert_eq!(labels("document"), vec![Some("old_lease.pdf".to_string())]);
+        assert_eq!(
+            labels("chat_session"),
+            vec![Some("Lease review".to_string())]
+        );
+        let cascaded = |entity_type: &str| {
+            preview
+                .categories
+                .iter()
+                .find(|category| category.entity_type == entity_type)
+                .unwrap()
+                .cascaded
+                .clone()
+        };
+        assert_eq!(
+            cascaded("document"),
+            BTreeMap::from([
+                ("document_chunks".to_string(), 2),
+                ("pii_detections".to_string(), 1)
+            ])
+        );
+        assert_eq!(
+            cascaded("chat_session"),
+            BTreeMap::from([("chat_messages".to_string(), 2)])
+        );
+        // Four expired entities plus two chunks, one detection and two messages
+        assert_eq!(preview.total, 9);
+
+        // Nothing is deleted by the preview
+        assert_eq!(task.execute_preview().await.unwrap().total, preview.total);
+
+        let deleted = task.execute().await.unwrap();
+        assert_eq!(
+            deleted,
+            (
+                preview.documents_to_delete,
+                preview.sessions_to_delete,
+                preview.messages_to_delete,
+                preview.queries_to_delete
+            )
+        );
+        assert_eq!(deleted, (1, 1, 1, 1));
+
+        let _ = std::fs::remove_file(db_path);
+    }
 }
@@ -118,6 +118,7 @@ async fn test_config_update() {
     // Update configuration
     let new_config = ScheduleConfig {
         interval_hours: 48,
+        cron: None,
         enabled: false,
         next_run: None,
     };
@@ -217,6 +218,7 @@ async fn test_concurrent_commands() {
     let task3 = tokio::spawn(async move {
         let config = ScheduleConfig {
             interval_hours: 12,
+            cron: None,
             enabled: true,
             next_run: None,
         };
@@ -244,6 +246,7 @@ async fn test_concurrent_commands() {
 fn test_schedule_config_serialization() {
     let config = ScheduleConfig {
         interval_hours: 24,
+        cron: None,
         enabled: true,
         next_run: Some(Utc::now()),
     };
@@ -8,9 +8,10 @@
 // All data at rest is encrypted with AES-256 encryption.
 
 use anyhow::{Context, Result};
-use rusqlite::Connection;
+use rusqlite::{Connection, OptionalExtension};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use zeroize::Zeroize;
 
 use super::key_manager::KeyManager;
 
@@ -107,8 +108,35 @@ impl EncryptedDatabase {
     }
 
     /// Open or create an encrypted database connection
+    ///
+    /// A database left under the pending key of an interrupted rotation is
+    /// opened with that key, which then becomes the current one.
     pub fn connect(&self) -> Result<Connection> {
-        self.connect_with_context(None)
+        let conn = self.connect_with_context(None)?;
+        if Self::is_readable(&conn) {
+            return Ok(conn);
+        }
+
+        if let Some(mut pending) = self.key_manager.pending_key()? {
+            let recovered = Connection::open(&self.db_path)
+                .with_context(|| format!("Failed to open database at {:?}", self.db_path))?;
+            self.configure_encryption(&recovered, &KeyManager::format_sqlcipher_key(&pending))?;
+            pending.zeroize();
+            if Self::is_readable(&recovered) {
+                tracing::warn!("Database is under the key of an interrupted rotation; adopting it");
+                self.key_manager.promote_pending_key()?;
+                return Ok(recovered);
+            }
+        }
+
+        Ok(conn)
+    }
+
+    fn is_readable(conn: &Connection) -> bool {
+        conn.query_row("SELECT count(*) FROM sqlite_master", [], |row| {
+            row.get::<_, i64>(0)
+        })
+        .is_ok()
     }
 
     /// Open or create an encrypted database connection with a specific key context
@@ -186,6 +214,21 @@ impl EncryptedDatabase {
         Ok(())
     }
 
+    /// Fail unless SQLite is built with SQLCipher. A plain SQLite ignores
+    /// `PRAGMA key` and `PRAGMA rekey`, so the database is not encrypted and
+    /// a key change would only replace the keychain entry.
+    fn ensure_sqlcipher(&self) -> Result<()> {
+        let conn = Connection::open_in_memory()?;
+        let cipher_version: Option<String> = conn
+            .query_row("PRAGMA cipher_version", [], |row| row.get(0))
+            .optional()?;
+
+        match cipher_version {
+            Some(version) if !version.is_empty() => Ok(()),
+            _ => anyhow::bail!("SQLite is built without SQLCipher; the database is not encrypted"),
+        }
+    }
+
     /// Create a new encrypted database from scratch
     pub fn create_new(&self) -> Result<Connection> {
         // Ensure database doesn't exist
@@ -230,6 +273,36 @@ impl EncryptedDatabase {
         Ok(())
     }
 
+    /// Rotate the master key without a full re-export
+    ///
+    /// Runs `PRAGMA rekey` with a freshly generated key, which the key manager
+    /// keeps as a pending keychain entry until the rekey succeeds. If the
+    /// keychain update fails the database is re-keyed back to the old key.
+    /// The connection holds SQLite's exclusive lock throughout, so no other
+    /// connection reads or writes the database until the rotation is done.
+    pub fn rotate_key(&self) -> Result<()> {
+        self.ensure_sqlcipher()?;
+        let conn = self.connect()?;
+        conn.execute_batch("PRAGMA locking_mode = EXCLUSIVE; BEGIN EXCLUSIVE; COMMIT;")
+            .context("Database is busy; try again once pending writes finish")?;
+
+        self.key_manager.rotate_key_with(|_, new_key| {
+            conn.execute_batch(&format!(
+                "PRAGMA rekey = \"{}\";",
+                KeyManager::format_sqlcipher_key(new_key)
+            ))
+            .context("Failed to rekey database")?;
+            // Confirm the pages are readable under the key just applied
+            conn.query_row("SELECT count(*) FROM sqlite_master", [], |row| {
+                row.get::<_, i64>(0)
+            })
+            .context("Database unreadable after rekey")?;
+            Ok(())
+        })?;
+
+        Ok(())
+    }
+
     /// Export encrypted database to an unencrypted database
     ///
     /// WARNING: This removes encryption protection. Use only for authorized exports
@@ -364,6 +437,7 @@ impl DatabaseMigration {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::security::key_manager::tests::test_key_manager;
     use tempfile::TempDir;
 
     #[ignore]
@@ -465,6 +539,45 @@ mod tests {
         );
     }
 
+    #[ignore]
+    #[test]
+    fn test_rotate_key() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+
+        let encrypted_db = EncryptedDatabase {
+            key_manager: Arc::new(test_key_manager()),
+            config: EncryptionConfig::default(),
+            db_path: db_path.clone(),
+        };
+        let conn = encrypted_db.create_new().unwrap();
+        drop(conn);
+        let old_key = encrypted_db.key_manager.get_sqlcipher_key(None).unwrap();
+
+        encrypted_db.rotate_key().unwrap();
+
+        // Opens with the new key from the keychain
+        let conn = encrypted_db.connect().unwrap();
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM _encryption_meta", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(count, 1);
+        drop(conn);
+
+        // But no longer with the old one
+        let stale = Connection::open(&db_path).unwrap();
+        encrypted_db.configure_encryption(&stale, &old_key).unwrap();
+        assert!(stale
+            .query_row("SELECT COUNT(*) FROM _encryption_meta", [], |row| {
+                row.get::<_, i64>(0)
+            })
+            .is_err());
+
+        encrypted_db.key_manager.delete_key().unwrap();
+    }
+
     #[ignore]
     #[test]
     fn test_database_migration() {
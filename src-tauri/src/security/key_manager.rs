@@ -15,21 +15,34 @@ use zeroize::Zeroize;
 
 const SERVICE_NAME: &str = "bear-ai-llm";
 const KEY_NAME: &str = "database-encryption-key";
+/// Holds the new key while a rotation is re-encrypting, so a crash between the
+/// rekey and the keychain update cannot lose the key the data is under
+const PENDING_KEY_NAME: &str = "database-encryption-key-pending";
 const KEY_LENGTH: usize = 32; // 256-bit key for AES-256
 
 /// Secure key manager that stores encryption keys in OS keychain
 pub struct KeyManager {
     entry: Entry,
+    pending_entry: Entry,
     cached_key: Arc<Mutex<Option<Vec<u8>>>>,
 }
 
 impl KeyManager {
     /// Create a new key manager instance
     pub fn new() -> Result<Self> {
-        let entry = Entry::new(SERVICE_NAME, KEY_NAME).context("Failed to create keyring entry")?;
+        Self::with_service(SERVICE_NAME)
+    }
+
+    /// Key manager storing its keys under another keychain service, so tests
+    /// never touch the key of the real database
+    pub fn with_service(service: &str) -> Result<Self> {
+        let entry = Entry::new(service, KEY_NAME).context("Failed to create keyring entry")?;
+        let pending_entry =
+            Entry::new(service, PENDING_KEY_NAME).context("Failed to create keyring entry")?;
 
         Ok(Self {
             entry,
+            pending_entry,
             cached_key: Arc::new(Mutex::new(None)),
         })
     }
@@ -120,6 +133,92 @@ impl KeyManager {
         Ok(new_key)
     }
 
+    /// Rotate the encryption key, re-encrypting data with `reencrypt`
+    ///
+    /// The new key is saved as a pending keychain entry before
+    /// `reencrypt(from, to)` is called with the current and the new key, and
+    /// promoted to the main entry once it succeeds. If promoting fails,
+    /// `reencrypt(new, old)` is called to undo the change; should that fail
+    /// too, the pending entry is kept so the data can still be opened.
+    pub fn rotate_key_with<F>(&self, mut reencrypt: F) -> Result<Vec<u8>>
+    where
+        F: FnMut(&[u8], &[u8]) -> Result<()>,
+    {
+        let mut old_key = self.get_or_create_key()?;
+        let new_key = self.generate_key()?;
+
+        if let Err(e) = self.pending_entry.set_password(&hex::encode(&new_key)) {
+            old_key.zeroize();
+            return Err(anyhow::Error::new(e).context("Failed to store the pending key"));
+        }
+
+        if let Err(e) = reencrypt(&old_key, &new_key) {
+            old_key.zeroize();
+            let _ = self.pending_entry.delete_password();
+            return Err(e.context("Failed to re-encrypt with the new key"));
+        }
+
+        if let Err(e) = self.store_key(&new_key) {
+            let rollback = reencrypt(&new_key, &old_key);
+            old_key.zeroize();
+            return match rollback {
+                Ok(()) => {
+                    let _ = self.pending_entry.delete_password();
+                    Err(e.context("Key rotation rolled back"))
+                }
+                Err(rollback_err) => Err(e.context(format!(
+                    "Key rotation could not be rolled back, the new key is kept as pending: {}",
+                    rollback_err
+                ))),
+            };
+        }
+        old_key.zeroize();
+        if let Err(e) = self.pending_entry.delete_password() {
+            tracing::warn!("Failed to remove the pending key after rotation: {}", e);
+        }
+
+        let mut cached = self.cached_key.lock().unwrap();
+        if let Some(ref mut key) = *cached {
+            key.zeroize();
+        }
+        *cached = Some(new_key.clone());
+
+        Ok(new_key)
+    }
+
+    /// Key left pending by a rotation that did not finish, if any
+    pub fn pending_key(&self) -> Result<Option<Vec<u8>>> {
+        match self.pending_entry.get_password() {
+            Ok(stored_key) => {
+                let key = hex::decode(&stored_key).context("Failed to decode pending key")?;
+                if key.len() != KEY_LENGTH {
+                    anyhow::bail!("Invalid pending key length in keychain");
+                }
+                Ok(Some(key))
+            }
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => anyhow::bail!("Failed to access keychain: {}", e),
+        }
+    }
+
+    /// Make the pending key of an unfinished rotation the current key
+    pub fn promote_pending_key(&self) -> Result<()> {
+        let key = self
+            .pending_key()?
+            .ok_or_else(|| anyhow::anyhow!("No pending key to promote"))?;
+        self.store_key(&key)?;
+        self.pending_entry
+            .delete_password()
+            .context("Failed to remove the pending key")?;
+
+        let mut cached = self.cached_key.lock().unwrap();
+        if let Some(ref mut cached_key) = *cached {
+            cached_key.zeroize();
+        }
+        *cached = Some(key);
+        Ok(())
+    }
+
     /// Delete the encryption key from keychain
     ///
     /// WARNING: This will make encrypted databases inaccessible
@@ -128,6 +227,7 @@ impl KeyManager {
         self.entry
             .delete_password()
             .context("Failed to delete key from keychain")?;
+        let _ = self.pending_entry.delete_password();
 
         // Clear cache
         let mut cached = self.cached_key.lock().unwrap();
@@ -160,8 +260,13 @@ impl KeyManager {
             None => self.get_or_create_key()?,
         };
 
+        Ok(Self::format_sqlcipher_key(&key))
+    }
+
+    /// Format a raw key for SQLCipher PRAGMA key / rekey
+    pub fn format_sqlcipher_key(key: &[u8]) -> String {
         // SQLCipher expects hex-encoded key with 'x' prefix
-        Ok(format!("x'{}'", hex::encode(key)))
+        format!("x'{}'", hex::encode(key))
     }
 
     /// Clear the in-memory key cache
@@ -185,12 +290,18 @@ impl Drop for KeyManager {
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
 
+    /// Key manager on a keychain service of its own, never the real database key
+    pub(crate) fn test_key_manager() -> KeyManager {
+        KeyManager::with_service(&format!("{}-test-{}", SERVICE_NAME, uuid::Uuid::new_v4()))
+            .unwrap()
+    }
+
     #[test]
     fn test_key_generation() {
-        let manager = KeyManager::new().unwrap();
+        let manager = test_key_manager();
 
         // Clean up any existing test key
         let _ = manager.delete_key();
@@ -209,7 +320,7 @@ mod tests {
 
     #[test]
     fn test_key_derivation() {
-        let manager = KeyManager::new().unwrap();
+        let manager = test_key_manager();
 
         // Clean up any existing test key
         let _ = manager.delete_key();
@@ -231,7 +342,7 @@ mod tests {
 
     #[test]
     fn test_sqlcipher_key_format() {
-        let manager = KeyManager::new().unwrap();
+        let manager = test_key_manager();
 
         // Clean up any existing test key
         let _ = manager.delete_key();
@@ -251,7 +362,7 @@ mod tests {
 
     #[test]
     fn test_key_rotation() {
-        let manager = KeyManager::new().unwrap();
+        let manager = test_key_manager();
 
         // Clean up any existing test key
         let _ = manager.delete_key();
@@ -272,7 +383,7 @@ mod tests {
 
     #[test]
     fn test_cache_clearing() {
-        let manager = KeyManager::new().unwrap();
+        let manager = test_key_manager();
 
         // Clean up any existing test key
         let _ = manager.delete_key();
@@ -290,4 +401,33 @@ mod tests {
         // Clean up
         manager.delete_key().unwrap();
     }
+
+    #[test]
+    fn test_rotation_keeps_new_key_pending_until_promoted() {
+        let manager = test_key_manager();
+        let original_key = manager.get_or_create_key().unwrap();
+
+        let mut seen_pending = None;
+        let rotated_key = manager
+            .rotate_key_with(|_, new_key| {
+                // The new key is already in the keychain when the data moves to it
+                seen_pending = manager.pending_key().unwrap();
+                assert_eq!(seen_pending.as_deref(), Some(new_key));
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(seen_pending, Some(rotated_key.clone()));
+        assert_ne!(original_key, rotated_key);
+        assert!(manager.pending_key().unwrap().is_none());
+
+        // A failed re-encryption leaves the current key and no pending key
+        assert!(manager
+            .rotate_key_with(|_, _| Err(anyhow::anyhow!("disk full")))
+            .is_err());
+        assert!(manager.pending_key().unwrap().is_none());
+        manager.clear_cache();
+        assert_eq!(manager.get_or_create_key().unwrap(), rotated_key);
+
+        manager.delete_key().unwrap();
+    }
 }
@@ -24,6 +24,20 @@ pub struct SetupConfig {
     pub model_size: String, // "small", "medium", "large"
     pub enable_gpu: bool,
     pub data_dir: PathBuf,
+    /// After the starter model, download larger models the hardware can run
+    #[serde(default = "default_download_recommended_models")]
+    pub download_recommended_models: bool,
+    /// Larger models downloaded in the background on first run
+    #[serde(default = "default_max_background_models")]
+    pub max_background_models: usize,
+}
+
+fn default_download_recommended_models() -> bool {
+    true
+}
+
+fn default_max_background_models() -> usize {
+    1
 }
 
 impl Default for SetupConfig {
@@ -38,6 +52,8 @@ impl Default for SetupConfig {
             model_size: "medium".to_string(),
             enable_gpu: false,
             data_dir,
+            download_recommended_models: default_download_recommended_models(),
+            max_background_models: default_max_background_models(),
         }
     }
 }
@@ -462,6 +478,10 @@ impl SetupManager {
         *complete
     }
 
+    pub async fn get_config(&self) -> SetupConfig {
+        self.config.read().await.clone()
+    }
+
     pub async fn update_config(&self, config: SetupConfig) -> Result<()> {
         let mut current = self.config.write().await;
         *current = config;
@@ -0,0 +1,171 @@
+/// Coordinated shutdown of background subsystems
+///
+/// Subsystems are stopped in order so nothing is dropped mid-write: in-flight
+/// generations are cancelled, the retention scheduler is stopped once any
+/// running cleanup or snapshot finishes, the database write-ahead log is
+/// checkpointed, and the model is unloaded last. Settings are held in memory
+/// and the RAG index is saved on every change, so neither needs a final flush.
+use crate::llm_manager::LLMManager;
+use crate::scheduler::SchedulerHandle;
+use anyhow::Result;
+use async_trait::async_trait;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Loaded model as seen by shutdown
+#[async_trait]
+pub trait ShutdownModel: Send + Sync {
+    fn cancel_generations(&self);
+    /// Unload the active model, returning its name if one was loaded
+    async fn unload(&self) -> Result<Option<String>>;
+}
+
+#[async_trait]
+impl ShutdownModel for LLMManager {
+    fn cancel_generations(&self) {
+        LLMManager::cancel_generations(self);
+    }
+
+    async fn unload(&self) -> Result<Option<String>> {
+        let active = self.get_active_model().await;
+        self.unload_model().await?;
+        Ok(active)
+    }
+}
+
+/// What each subsystem reported while shutting down
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ShutdownReport {
+    pub generations_cancelled: bool,
+    pub scheduler_stopped: bool,
+    pub database_flushed: bool,
+    pub unloaded_model: Option<String>,
+    pub model_unloaded: bool,
+    pub errors: Vec<String>,
+    pub duration_ms: u64,
+}
+
+impl ShutdownReport {
+    /// Every subsystem confirmed a clean stop
+    pub fn is_clean(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// Stop all subsystems, waiting up to `timeout` for each step that can block
+pub async fn shutdown_subsystems(
+    model: &dyn ShutdownModel,
+    scheduler: Option<&SchedulerHandle>,
+    db_path: &Path,
+    timeout: Duration,
+) -> ShutdownReport {
+    let started = Instant::now();
+    let mut report = ShutdownReport::default();
+
+    model.cancel_generations();
+    report.generations_cancelled = true;
+
+    match scheduler {
+        Some(scheduler) => match scheduler.shutdown_and_wait(timeout).await {
+            Ok(()) => report.scheduler_stopped = true,
+            Err(e) => report.errors.push(format!("Scheduler: {}", e)),
+        },
+        None => report.scheduler_stopped = true,
+    }
+
+    match checkpoint_database(db_path) {
+        Ok(()) => report.database_flushed = true,
+        Err(e) => report.errors.push(format!("Database: {}", e)),
+    }
+
+    // Generations hold the model until their current token completes
+    match tokio::time::timeout(timeout, model.unload()).await {
+        Ok(Ok(unloaded)) => {
+            report.unloaded_model = unloaded;
+            report.model_unloaded = true;
+        }
+        Ok(Err(e)) => report.errors.push(format!("Model: {}", e)),
+        Err(_) => report
+            .errors
+            .push(format!("Model: unload did not finish within {:?}", timeout)),
+    }
+
+    report.duration_ms = started.elapsed().as_millis() as u64;
+    if report.is_clean() {
+        tracing::info!(
+            duration_ms = report.duration_ms,
+            "Subsystems shut down cleanly"
+        );
+    } else {
+        tracing::warn!(errors = ?report.errors, "Shutdown finished with errors");
+    }
+    report
+}
+
+/// Write committed WAL pages back into the database file
+fn checkpoint_database(db_path: &Path) -> Result<()> {
+    if !db_path.exists() {
+        return Ok(());
+    }
+    let conn = Connection::open(db_path)?;
+    conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scheduler::RetentionScheduler;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Mutex;
+
+    struct MockModel {
+        active: Mutex<Option<String>>,
+        cancelled: AtomicBool,
+    }
+
+    #[async_trait]
+    impl ShutdownModel for MockModel {
+        fn cancel_generations(&self) {
+            self.cancelled.store(true, Ordering::SeqCst);
+        }
+
+        async fn unload(&self) -> Result<Option<String>> {
+            Ok(self.active.lock().unwrap().take())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_stops_scheduler_and_unloads_model() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("bear_ai.db");
+        Connection::open(&db_path)
+            .unwrap()
+            .execute_batch("PRAGMA journal_mode = WAL; CREATE TABLE notes (body TEXT);")
+            .unwrap();
+
+        let scheduler = RetentionScheduler::new(db_path.clone());
+        let handle = scheduler.get_handle();
+        scheduler.start().await.unwrap();
+        assert!(handle.get_status().await.is_running);
+
+        let model = MockModel {
+            active: Mutex::new(Some("tinyllama-1.1b".to_string())),
+            cancelled: AtomicBool::new(false),
+        };
+
+        let report =
+            shutdown_subsystems(&model, Some(&handle), &db_path, Duration::from_secs(5)).await;
+
+        assert!(report.is_clean(), "{:?}", report.errors);
+        assert!(report.scheduler_stopped);
+        assert!(!handle.get_status().await.is_running);
+        assert!(model.cancelled.load(Ordering::SeqCst));
+        assert!(report.model_unloaded);
+        assert_eq!(report.unloaded_model.as_deref(), Some("tinyllama-1.1b"));
+        assert!(model.active.lock().unwrap().is_none());
+        assert!(report.database_flushed);
+    }
+}
@@ -0,0 +1,184 @@
+/// Extraction of structured data validated against a caller-supplied JSON schema
+///
+/// The model is asked for a single JSON value conforming to the schema. Its
+/// reply is parsed (tolerating code fences and surrounding prose) and validated;
+/// when either fails, the problems are fed back in a corrective prompt and the
+/// model tries again, up to a retry budget, before the extraction fails.
+use crate::completion::{CompletionModel, CompletionOptions};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Characters of the document included in the extraction prompt
+const EXTRACTION_PROMPT_CHARS: usize = 12_000;
+
+/// Validation errors quoted back to the model per retry
+const MAX_REPORTED_ERRORS: usize = 10;
+
+/// Extraction wants the most likely reading, not a creative one
+const EXTRACTION_MAX_TEMPERATURE: f32 = 0.2;
+
+/// Output that passed schema validation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StructuredExtraction {
+    pub value: Value,
+    /// Completions requested, including the first
+    pub attempts: usize,
+}
+
+fn extraction_prompt(content: &str, schema: &Value, instructions: &str) -> String {
+    let content: String = content.chars().take(EXTRACTION_PROMPT_CHARS).collect();
+    let schema = serde_json::to_string_pretty(schema).unwrap_or_else(|_| schema.to_string());
+    format!(
+        "Extract data from the document below. Reply with a single JSON value that \
+         conforms to this JSON schema, and nothing else.\n\nSchema:\n{}\n\n\
+         Instructions: {}\n\nDocument:\n{}\n\nJSON:",
+        schema,
+        instructions.trim(),
+        content
+    )
+}
+
+fn corrective_prompt(prompt: &str, reply: &str, problems: &[String]) -> String {
+    format!(
+        "{}\n{}\n\nThat reply is invalid:\n- {}\n\nReply again with corrected JSON only.\n\nJSON:",
+        prompt,
+        reply.trim(),
+        problems.join("\n- ")
+    )
+}
+
+/// The JSON value in a model reply, which may be wrapped in a code fence or prose
+fn parse_reply(reply: &str) -> std::result::Result<Value, String> {
+    let trimmed = reply.trim();
+    if let Ok(value) = serde_json::from_str(trimmed) {
+        return Ok(value);
+    }
+    let start = trimmed.find(['{', '[']);
+    let end = trimmed.rfind(['}', ']']);
+    match (start, end) {
+        (Some(start), Some(end)) if start < end => serde_json::from_str(&trimmed[start..=end])
+            .map_err(|e| format!("the reply is not valid JSON: {}", e)),
+        _ => Err("the reply contains no JSON object or array".to_string()),
+    }
+}
+
+/// Schema violations in `value`, one per line, each prefixed with its location
+fn schema_errors(validator: &jsonschema::Validator, value: &Value) -> Vec<String> {
+    validator
+        .iter_errors(value)
+        .take(MAX_REPORTED_ERRORS)
+        .map(|error| {
+            let path = error.instance_path.to_string();
+            let path = if path.is_empty() {
+                "/".to_string()
+            } else {
+                path
+            };
+            format!("at {}: {}", path, error)
+        })
+        .collect()
+}
+
+/// Extract a value conforming to `schema` from `content`, retrying with the
+/// validation errors up to `max_retries` times after the first attempt
+pub async fn extract_structured(
+    model: &dyn CompletionModel,
+    content: &str,
+    schema: &Value,
+    instructions: &str,
+    max_retries: usize,
+) -> Result<StructuredExtraction> {
+    let validator =
+        jsonschema::validator_for(schema).map_err(|e| anyhow!("Invalid JSON schema: {}", e))?;
+
+    let options = CompletionOptions {
+        max_temperature: Some(EXTRACTION_MAX_TEMPERATURE),
+        ..Default::default()
+    };
+    let mut prompt = extraction_prompt(content, schema, instructions);
+    let mut problems = Vec::new();
+    for attempt in 1..=max_retries + 1 {
+        let reply = model.complete(&prompt, &options).await?;
+        problems = match parse_reply(&reply) {
+            Ok(value) => {
+                let errors = schema_errors(&validator, &value);
+                if errors.is_empty() {
+                    return Ok(StructuredExtraction {
+                        value,
+                        attempts: attempt,
+                    });
+                }
+                errors
+            }
+            Err(problem) => vec![problem],
+        };
+        tracing::debug!(
+            attempt,
+            errors = problems.len(),
+            "Structured output failed validation"
+        );
+        prompt = corrective_prompt(&prompt, &reply, &problems);
+    }
+
+    Err(anyhow!(
+        "Model output did not match the schema after {} attempts: {}",
+        max_retries + 1,
+        problems.join("; ")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::completion::mock::MockCompletion;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_invalid_output_triggers_corrective_retry() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "parties": {"type": "array", "items": {"type": "string"}},
+                "effective_date": {"type": "string"}
+            },
+            "required": ["parties", "effective_date"]
+        });
+        let model = MockCompletion::replies(vec![
+            r#"{"parties": "Acme Corp"}"#,
+            "```json\n{\"parties\": [\"Acme Corp\", \"Jane Doe\"], \"effective_date\": \"2024-01-01\"}\n```",
+        ]);
+
+        let extraction = extract_structured(
+            &model,
+            "This lease between Acme Corp and Jane Doe starts 2024-01-01.",
+            &schema,
+            "List the parties and the effective date.",
+            2,
+        )
+        .await
+        .unwrap();
+        assert_eq!(extraction.attempts, 2);
+        assert_eq!(extraction.value["parties"][1], "Jane Doe");
+
+        // The retry quotes the failed reply and what was wrong with it
+        let prompts = model.prompts();
+        assert!(prompts[1].contains(r#"{"parties": "Acme Corp"}"#));
+        assert!(prompts[1].contains("\"effective_date\" is a required property"));
+        assert!(prompts[1].contains("at /parties: \"Acme Corp\" is not of type \"array\""));
+
+        // Every attempt runs cool, however warm the configured temperature
+        assert!(model
+            .calls
+            .lock()
+            .unwrap()
+            .iter()
+            .all(|(_, options)| options.max_temperature == Some(EXTRACTION_MAX_TEMPERATURE)));
+
+        let model = MockCompletion::replies(vec!["no idea", "[]"]);
+        let error = extract_structured(&model, "text", &schema, "", 1)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("after 2 attempts"));
+    }
+}
@@ -0,0 +1,444 @@
+/// Script detection, sentence segmentation and token counting for chunking
+///
+/// Whitespace word counts are a fair token proxy for English and other spaced
+/// scripts but fail for Chinese, Japanese and Thai, which do not separate words.
+/// For those, text is segmented on the script's own sentence terminators and
+/// sized with the loaded model's tokenizer, falling back to an estimate of one
+/// token per character when no tokenizer is available.
+use anyhow::{anyhow, Result};
+use std::sync::Arc;
+use tokenizers::Tokenizer;
+use tokio::sync::RwLock;
+
+/// Share of letters from unspaced scripts above which a text is treated as unspaced
+const UNSPACED_SCRIPT_RATIO: f32 = 0.3;
+
+/// How words are delimited in a text's dominant script
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptClass {
+    /// Latin, Cyrillic, Greek, Hangul, ... words separated by whitespace
+    Spaced,
+    /// Han, Kana, Thai, ... no spaces between words
+    Unspaced,
+}
+
+/// Scripts written without spaces between words
+pub fn is_unspaced_char(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30FF}'   // Hiragana, Katakana
+        | '\u{3400}'..='\u{4DBF}' // CJK Extension A
+        | '\u{4E00}'..='\u{9FFF}' // CJK Unified Ideographs
+        | '\u{F900}'..='\u{FAFF}' // CJK Compatibility Ideographs
+        | '\u{FF66}'..='\u{FF9F}' // Half-width Katakana
+        | '\u{0E00}'..='\u{0EFF}' // Thai, Lao
+        | '\u{1000}'..='\u{109F}' // Myanmar
+        | '\u{1780}'..='\u{17FF}' // Khmer
+        | '\u{20000}'..='\u{2FA1F}' // CJK Extensions B-F, supplement
+    )
+}
+
+/// Classify a text by the share of its letters written in unspaced scripts
+pub fn detect_script(text: &str) -> ScriptClass {
+    let mut letters = 0usize;
+    let mut unspaced = 0usize;
+    for c in text.chars().filter(|c| c.is_alphanumeric()) {
+        letters += 1;
+        if is_unspaced_char(c) {
+            unspaced += 1;
+        }
+    }
+    if letters > 0 && unspaced as f32 / letters as f32 > UNSPACED_SCRIPT_RATIO {
+        ScriptClass::Unspaced
+    } else {
+        ScriptClass::Spaced
+    }
+}
+
+/// Split text into sentences
+///
+/// ASCII terminators end a sentence only when followed by whitespace (so "3.5"
+/// and "e.g." mid-sentence survive); full-width CJK terminators end one directly.
+pub fn split_sentences(text: &str) -> Vec<&str> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    for (idx, c) in text.char_indices() {
+        let end = idx + c.len_utf8();
+        let boundary = match c {
+            '。' | '！' | '？' | '．' | '｡' => true,
+            '.' | '!' | '?' => end == text.len() || text[end..].starts_with(char::is_whitespace),
+            _ => false,
+        };
+        if boundary {
+            let sentence = text[start..end].trim();
+            if !sentence.is_empty() {
+                sentences.push(sentence);
+            }
+            start = end;
+        }
+    }
+    let rest = text[start..].trim();
+    if !rest.is_empty() {
+        sentences.push(rest);
+    }
+    sentences
+}
+
+/// Split text into paragraphs at blank lines
+pub fn split_paragraphs(text: &str) -> Vec<&str> {
+    let mut paragraphs = Vec::new();
+    let mut start = 0;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        if line.trim().is_empty() {
+            let paragraph = text[start..offset].trim();
+            if !paragraph.is_empty() {
+                paragraphs.push(paragraph);
+            }
+            start = offset + line.len();
+        }
+        offset += line.len();
+    }
+    let rest = text[start..].trim();
+    if !rest.is_empty() {
+        paragraphs.push(rest);
+    }
+    paragraphs
+}
+
+/// Counts tokens the way the model will see them
+pub trait TokenCounter: Send + Sync {
+    fn count_tokens(&self, text: &str) -> usize;
+}
+
+/// Tokenizer-free estimate: one token per unspaced-script character and
+/// roughly four characters per token for spaced words
+pub struct HeuristicTokenCounter;
+
+impl TokenCounter for HeuristicTokenCounter {
+    fn count_tokens(&self, text: &str) -> usize {
+        let mut tokens = 0;
+        let mut run = 0;
+        for c in text.chars() {
+            if is_unspaced_char(c) {
+                tokens += 1 + run.div_ceil(4);
+                run = 0;
+            } else if c.is_whitespace() {
+                tokens += run.div_ceil(4);
+                run = 0;
+            } else {
+                run += 1;
+            }
+        }
+        tokens + run.div_ceil(4)
+    }
+}
+
+/// Whitespace-separated words, the unit `chunk_size` counts in spaced scripts
+pub struct WordCounter;
+
+impl TokenCounter for WordCounter {
+    fn count_tokens(&self, text: &str) -> usize {
+        text.split_whitespace().count()
+    }
+}
+
+impl TokenCounter for Tokenizer {
+    fn count_tokens(&self, text: &str) -> usize {
+        match self.encode(text, false) {
+            Ok(encoding) => encoding.len(),
+            Err(_) => HeuristicTokenCounter.count_tokens(text),
+        }
+    }
+}
+
+/// Counts with whichever tokenizer the LLM manager currently has loaded
+pub struct LoadedTokenizerCounter {
+    tokenizer: Arc<RwLock<Option<Tokenizer>>>,
+}
+
+impl LoadedTokenizerCounter {
+    pub fn new(tokenizer: Arc<RwLock<Option<Tokenizer>>>) -> Self {
+        Self { tokenizer }
+    }
+}
+
+impl TokenCounter for LoadedTokenizerCounter {
+    fn count_tokens(&self, text: &str) -> usize {
+        // Never block chunking on a model (re)load; estimate instead
+        match self.tokenizer.try_read() {
+            Ok(guard) => match guard.as_ref() {
+                Some(tokenizer) => tokenizer.count_tokens(text),
+                None => HeuristicTokenCounter.count_tokens(text),
+            },
+            Err(_) => HeuristicTokenCounter.count_tokens(text),
+        }
+    }
+}
+
+/// The end of `text` holding at most `max_tokens` tokens. The front is
+/// dropped, since the most recent context matters most, and the cut falls on a
+/// token boundary so no token is split.
+pub fn truncate_to_tokens(tokenizer: &Tokenizer, text: &str, max_tokens: usize) -> Result<String> {
+    let encoding = tokenizer
+        .encode(text, false)
+        .map_err(|e| anyhow!("Tokenization failed: {}", e))?;
+    let offsets = encoding.get_offsets();
+    if offsets.len() <= max_tokens {
+        return Ok(text.to_string());
+    }
+    if max_tokens == 0 {
+        return Ok(String::new());
+    }
+    let start = offsets[offsets.len() - max_tokens].0;
+    text.get(start..)
+        .map(str::to_string)
+        .ok_or_else(|| anyhow!("Token offset {} is not a character boundary", start))
+}
+
+/// Pack sentences into chunks of at most `max_tokens`, carrying up to
+/// `overlap_tokens` of trailing sentences into the next chunk
+///
+/// Sentences longer than `max_tokens` are cut between characters, never inside one.
+pub fn chunk_by_tokens(
+    text: &str,
+    counter: &dyn TokenCounter,
+    max_tokens: usize,
+    overlap_tokens: usize,
+) -> Vec<String> {
+    let max_tokens = max_tokens.max(1);
+    let joiner = match detect_script(text) {
+        ScriptClass::Spaced => " ",
+        ScriptClass::Unspaced => "",
+    };
+
+    let mut pieces: Vec<(&str, usize)> = Vec::new();
+    for sentence in split_sentences(text) {
+        let tokens = counter.count_tokens(sentence);
+        if tokens <= max_tokens {
+            pieces.push((sentence, tokens));
+        } else {
+            pieces.extend(split_long_sentence(sentence, counter, max_tokens));
+        }
+    }
+
+    let mut chunks = Vec::new();
+    let mut current: Vec<(&str, usize)> = Vec::new();
+    let mut current_tokens = 0;
+    for (piece, tokens) in pieces {
+        if current_tokens + tokens > max_tokens && !current.is_empty() {
+            chunks.push(join(&current, joiner));
+
+            // Keep trailing sentences as overlap if they leave room for this one
+            let mut carried = Vec::new();
+            let mut carried_tokens = 0;
+            for &(prev, prev_tokens) in current.iter().rev() {
+                if carried_tokens + prev_tokens > overlap_tokens {
+                    break;
+                }
+                carried.insert(0, (prev, prev_tokens));
+                carried_tokens += prev_tokens;
+            }
+            if carried_tokens + tokens > max_tokens {
+                carried.clear();
+                carried_tokens = 0;
+            }
+            current = carried;
+            current_tokens = carried_tokens;
+        }
+        current.push((piece, tokens));
+        current_tokens += tokens;
+    }
+    if !current.is_empty() {
+        chunks.push(join(&current, joiner));
+    }
+    chunks
+}
+
+/// Pack whole units (sentences or paragraphs) into chunks of at most
+/// `max_size` as measured by `counter`, starting each chunk with the last
+/// `overlap_units` units of the previous one when they leave room
+///
+/// Units larger than `max_size` are cut between characters, as in `chunk_by_tokens`.
+pub fn chunk_by_units(
+    units: &[&str],
+    counter: &dyn TokenCounter,
+    max_size: usize,
+    overlap_units: usize,
+    joiner: &str,
+) -> Vec<String> {
+    let max_size = max_size.max(1);
+    let mut pieces: Vec<(&str, usize)> = Vec::new();
+    for unit in units {
+        let size = counter.count_tokens(unit);
+        if size <= max_size {
+            pieces.push((unit, size));
+        } else {
+            pieces.extend(
+                split_long_sentence(unit, counter, max_size)
+                    .into_iter()
+                    .map(|(piece, size)| (piece.trim(), size))
+                    .filter(|(piece, _)| !piece.is_empty()),
+            );
+        }
+    }
+
+    let mut chunks = Vec::new();
+    let mut current: Vec<(&str, usize)> = Vec::new();
+    let mut current_size = 0;
+    for (piece, size) in pieces {
+        if current_size + size > max_size && !current.is_empty() {
+            chunks.push(join(&current, joiner));
+
+            let carried_from = current.len().saturating_sub(overlap_units);
+            let carried: Vec<(&str, usize)> = current.split_off(carried_from);
+            let carried_size: usize = carried.iter().map(|(_, size)| size).sum();
+            if carried_size + size > max_size {
+                current.clear();
+                current_size = 0;
+            } else {
+                current = carried;
+                current_size = carried_size;
+            }
+        }
+        current.push((piece, size));
+        current_size += size;
+    }
+    if !current.is_empty() {
+        chunks.push(join(&current, joiner));
+    }
+    chunks
+}
+
+fn join(pieces: &[(&str, usize)], joiner: &str) -> String {
+    pieces
+        .iter()
+        .map(|(piece, _)| *piece)
+        .collect::<Vec<_>>()
+        .join(joiner)
+}
+
+/// Cut an over-long sentence at character boundaries into pieces of at most
+/// `max_tokens`. Each cut point is binary-searched, so a piece costs a
+/// logarithmic number of tokenizer calls rather than one per character.
+fn split_long_sentence<'a>(
+    sentence: &'a str,
+    counter: &dyn TokenCounter,
+    max_tokens: usize,
+) -> Vec<(&'a str, usize)> {
+    // Byte offset just past each character
+    let ends: Vec<usize> = sentence
+        .char_indices()
+        .map(|(idx, c)| idx + c.len_utf8())
+        .collect();
+
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    let mut first = 0;
+    while first < ends.len() {
+        // Last character the piece can end on and still fit
+        let (mut lo, mut hi) = (first, ends.len());
+        let mut fit = None;
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let tokens = counter.count_tokens(&sentence[start..ends[mid]]);
+            if tokens <= max_tokens {
+                fit = Some((mid, tokens));
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        // A single character over the limit still has to go somewhere
+        let (last, tokens) =
+            fit.unwrap_or_else(|| (first, counter.count_tokens(&sentence[start..ends[first]])));
+        pieces.push((&sentence[start..ends[last]], tokens));
+        start = ends[last];
+        first = last + 1;
+    }
+    pieces
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_script_and_cjk_sentences() {
+        assert_eq!(detect_script("The tenant pays rent."), ScriptClass::Spaced);
+        assert_eq!(detect_script("租户应按月支付租金。"), ScriptClass::Unspaced);
+        assert_eq!(
+            split_sentences("租户应按月支付租金。任何一方可提前解约！Version 3.5 applies."),
+            vec![
+                "租户应按月支付租金。",
+                "任何一方可提前解约！",
+                "Version 3.5 applies."
+            ]
+        );
+        assert_eq!(HeuristicTokenCounter.count_tokens("租金 rent"), 3);
+    }
+
+    /// Word-level tokenizer mapping every whitespace-separated word to one
+    /// token; shared with other modules' tests
+    pub(crate) fn word_tokenizer() -> Tokenizer {
+        r#"{
+            "version": "1.0",
+            "truncation": null,
+            "padding": null,
+            "added_tokens": [],
+            "normalizer": null,
+            "pre_tokenizer": {"type": "WhitespaceSplit"},
+            "post_processor": null,
+            "decoder": null,
+            "model": {"type": "WordLevel", "vocab": {"[UNK]": 0}, "unk_token": "[UNK]"}
+        }"#
+        .parse()
+        .unwrap()
+    }
+
+    #[test]
+    fn test_truncate_to_tokens_keeps_whole_trailing_tokens() {
+        let tokenizer = word_tokenizer();
+        assert_eq!(truncate_to_tokens(&tokenizer, "", 10).unwrap(), "");
+        assert_eq!(truncate_to_tokens(&tokenizer, "", 0).unwrap(), "");
+
+        let long = (0..5000)
+            .map(|i| format!("clause{}", i))
+            .collect::<Vec<_>>()
+            .join(" ");
+        assert_eq!(tokenizer.count_tokens(&long), 5000);
+        let truncated = truncate_to_tokens(&tokenizer, &long, 3).unwrap();
+        assert_eq!(truncated, "clause4997 clause4998 clause4999");
+        assert_eq!(truncate_to_tokens(&tokenizer, &long, 5000).unwrap(), long);
+        assert_eq!(truncate_to_tokens(&tokenizer, &long, 0).unwrap(), "");
+    }
+
+    /// Heuristic counter that records how often it is called
+    struct CallCounter(std::sync::atomic::AtomicUsize);
+
+    impl TokenCounter for CallCounter {
+        fn count_tokens(&self, text: &str) -> usize {
+            self.0.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            HeuristicTokenCounter.count_tokens(text)
+        }
+    }
+
+    #[test]
+    fn test_long_unpunctuated_run_split_with_few_tokenizer_calls() {
+        let run = "表".repeat(10_000);
+        let counter = CallCounter(std::sync::atomic::AtomicUsize::new(0));
+        let pieces = split_long_sentence(&run, &counter, 100);
+
+        assert_eq!(pieces.len(), 100);
+        assert!(pieces
+            .iter()
+            .all(|(piece, tokens)| *tokens == 100
+                && HeuristicTokenCounter.count_tokens(piece) == 100));
+        assert_eq!(
+            pieces.iter().map(|(piece, _)| *piece).collect::<String>(),
+            run
+        );
+        // About log2(10_000) calls per piece, not one per character
+        let calls = counter.0.load(std::sync::atomic::Ordering::Relaxed);
+        assert!(calls < 100 * 20, "{} tokenizer calls", calls);
+    }
+}
@@ -144,7 +144,7 @@ function App() {
         multiple: false,
         filters: [{
           name: 'Documents',
-          extensions: ['txt', 'pdf', 'docx', 'xlsx', 'csv', 'pptx', 'md', 'json']
+          extensions: ['txt', 'pdf', 'docx', 'xlsx', 'csv', 'pptx', 'md', 'json', 'odt', 'ods', 'epub']
         }]
       });
 
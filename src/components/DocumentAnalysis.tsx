@@ -15,6 +15,7 @@ interface DocumentAnalysis {
   filename: string;
   fileType: string;
   originalText: string;
+  originalTruncated?: boolean;
   cleanedText: string;
   piiDetections: PIIDetection[];
   processingTime: number;
@@ -120,7 +121,7 @@ const DocumentAnalysis: React.FC = () => {
             ref={fileInputRef}
             onChange={handleFileUpload}
             multiple
-            accept=".pdf,.docx,.doc,.txt,.md,.json,.csv,.xml,.html,.xlsx,.xls,.pptx,.ppt,.rtf"
+            accept=".pdf,.docx,.doc,.txt,.md,.json,.csv,.xml,.html,.xlsx,.xls,.pptx,.ppt,.rtf,.odt,.ods,.epub"
             className="hidden"
           />
           <button
@@ -266,7 +267,8 @@ const DocumentAnalysis: React.FC = () => {
                     <div className="p-3 bg-[var(--bg-tertiary)] rounded-lg border border-[var(--border-secondary)]">
                       <pre className="text-xs text-[var(--text-secondary)] whitespace-pre-wrap max-h-48 overflow-y-auto">
                         {showOriginal[analysis.filename]
-                          ? analysis.originalText || 'Original text not available'
+                          ? (analysis.originalText || 'Original text not available') +
+                            (analysis.originalTruncated ? '\n\n[Preview truncated]' : '')
                           : analysis.cleanedText || 'No cleaned text available'
                         }
                       </pre>
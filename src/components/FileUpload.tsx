@@ -62,7 +62,7 @@ const FileUpload: React.FC<FileUploadProps> = ({ onFileSelect, isProcessing }) =
         type="file"
         id="file-upload"
         onChange={handleChange}
-        accept=".txt,.pdf,.docx,.doc,.xlsx,.xls,.csv,.pptx,.ppt"
+        accept=".txt,.pdf,.docx,.doc,.xlsx,.xls,.csv,.pptx,.ppt,.odt,.ods,.epub"
         className="hidden"
         disabled={isProcessing}
       />
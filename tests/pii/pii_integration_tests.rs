@@ -318,7 +318,7 @@ async fn test_redaction_with_multilayer() {
     detector.initialize().await.unwrap();
 
     let text = "Contact John Smith at john@example.com, SSN: 123-45-6789";
-    let redacted = detector.redact_pii(text).await.unwrap();
+    let redacted = detector.redact_pii(text, None).await.unwrap();
 
     println!("Original: {}", text);
     println!("Redacted: {}", redacted);